@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use failure::Error;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::hash_map::DefaultHasher,
     fmt,
@@ -14,16 +15,150 @@ mod base_type_tests;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
 pub struct Round(pub usize);
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Deserialize, Serialize)]
 pub struct NodeTime(pub i64);
-pub type Duration = i64;
+/// A span of time, in milliseconds. Use the unit constructors rather than raw integers: one
+/// unit of simulated time stands for one millisecond of real time.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Duration(i64);
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
 pub struct Author(pub usize);
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug)]
+/// A placeholder signature, unless the `ed25519` feature is enabled.
+#[cfg(not(feature = "ed25519"))]
+#[derive(
+    Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Default, Deserialize, Serialize,
+)]
 pub struct Signature(pub u64);
+/// The two halves of an ed25519 signature.
+#[cfg(feature = "ed25519")]
+#[derive(
+    Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Default, Deserialize, Serialize,
+)]
+pub struct Signature(pub [u8; 32], pub [u8; 32]);
+/// A placeholder aggregate of signatures, unless the `ed25519` feature is enabled.
+#[cfg(not(feature = "ed25519"))]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+pub struct AggregateSignature(pub u64);
+/// Ed25519 signatures do not aggregate, so aggregates keep all the signatures.
+#[cfg(feature = "ed25519")]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug, Deserialize, Serialize)]
+pub struct AggregateSignature(pub Vec<Signature>);
+/// A placeholder share of a threshold signature, signed with the key share of one validator.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+pub struct SignatureShare(pub u64);
+/// A placeholder signature of the group of validators of an epoch, combined from shares.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+pub struct ThresholdSignature(pub u64);
+
+/// Signatures of several authors, each on the digest of its own record.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug, Deserialize, Serialize)]
+pub enum MultiSignature {
+    /// One signature per author. Signatures in increasing order of authors are serialized as a
+    /// bitmap of the authors followed by the signatures.
+    Individual(#[serde(with = "individual_signatures")] Vec<(Author, Signature)>),
+    /// The authors, in order, and the aggregate of their signatures.
+    Aggregate(Vec<Author>, AggregateSignature),
+    /// A threshold signature of the group, which does not reveal the authors.
+    Threshold(ThresholdSignature),
+}
+
+/// Size of an author index on the wire, in bytes.
+pub const AUTHOR_BYTES: usize = 8;
+
+/// Serde layout of individual signatures.
+mod individual_signatures {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(untagged)]
+    enum Layout {
+        Bitmap {
+            /// See `signer_bitmap`.
+            signers: Vec<u64>,
+            signatures: Vec<Signature>,
+        },
+        /// Signatures out of order, which honest nodes do not produce.
+        List(Vec<(Author, Signature)>),
+    }
+
+    pub fn serialize<S: Serializer>(
+        signatures: &[(Author, Signature)],
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let layout = match signer_bitmap(signatures.iter().map(|(author, _)| *author)) {
+            Some(signers) => Layout::Bitmap {
+                signers,
+                signatures: signatures.iter().map(|(_, signature)| *signature).collect(),
+            },
+            None => Layout::List(signatures.to_vec()),
+        };
+        layout.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Vec<(Author, Signature)>, D::Error> {
+        match Layout::deserialize(deserializer)? {
+            Layout::Bitmap {
+                signers,
+                signatures,
+            } => {
+                let authors = bitmap_authors(&signers);
+                if authors.len() != signatures.len() {
+                    return Err(serde::de::Error::custom(
+                        "The signer bitmap does not match the number of signatures.",
+                    ));
+                }
+                Ok(authors.into_iter().zip(signatures).collect())
+            }
+            Layout::List(signatures) => Ok(signatures),
+        }
+    }
+}
+
+/// The bitmap of a sequence of authors: bit `i % 64` of word `i / 64` is set if `Author(i)` is
+/// present. Returns `None` unless the authors are strictly increasing, since the bitmap does not
+/// keep their order.
+pub fn signer_bitmap<I: IntoIterator<Item = Author>>(authors: I) -> Option<Vec<u64>> {
+    let mut signers = Vec::new();
+    let mut previous: Option<Author> = None;
+    for author in authors {
+        if previous.map_or(false, |previous| previous >= author) {
+            return None;
+        }
+        previous = Some(author);
+        let index = author.0 / 64;
+        if signers.len() <= index {
+            signers.resize(index + 1, 0);
+        }
+        signers[index] |= 1u64 << (author.0 % 64);
+    }
+    Some(signers)
+}
+
+/// Size of the bitmap of `authors`, in bytes.
+fn bitmap_bytes(authors: &[Author]) -> usize {
+    let bitmap_bits = authors.iter().map(|author| author.0 + 1).max().unwrap_or(0);
+    (bitmap_bits + 7) / 8
+}
+
+/// The authors of a signer bitmap, in increasing order.
+pub fn bitmap_authors(signers: &[u64]) -> Vec<Author> {
+    let mut authors = Vec::new();
+    for (index, word) in signers.iter().enumerate() {
+        for bit in 0..64 {
+            if word & (1u64 << bit) != 0 {
+                authors.push(Author(index * 64 + bit));
+            }
+        }
+    }
+    authors
+}
 
 impl fmt::Debug for NodeTime {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -31,6 +166,28 @@ impl fmt::Debug for NodeTime {
     }
 }
 
+impl fmt::Debug for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}ms", self.0)
+    }
+}
+
+/// A number of milliseconds, as parsed by `from_str`, e.g. in CSV files.
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Durations are parsed from a number of milliseconds, e.g. on the command line.
+impl std::str::FromStr for Duration {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(Duration(s.parse()?))
+    }
+}
+
 impl std::ops::Add<usize> for Round {
     type Output = Round;
 
@@ -39,21 +196,195 @@ impl std::ops::Add<usize> for Round {
     }
 }
 
+impl Duration {
+    pub const ZERO: Duration = Duration(0);
+
+    pub const fn from_millis(millis: i64) -> Self {
+        Duration(millis)
+    }
+
+    /// Saturates on overflow.
+    pub fn from_secs(secs: i64) -> Self {
+        Duration(secs.saturating_mul(1000))
+    }
+
+    /// Rounded toward zero to whole milliseconds.
+    pub fn from_micros(micros: i64) -> Self {
+        Duration(micros / 1000)
+    }
+
+    /// Rounded toward zero to whole milliseconds, saturating on overflow. NaN is zero.
+    pub fn from_millis_f64(millis: f64) -> Self {
+        if millis.is_nan() {
+            Duration(0)
+        } else if millis >= std::i64::MAX as f64 {
+            Duration(std::i64::MAX)
+        } else if millis <= std::i64::MIN as f64 {
+            Duration(std::i64::MIN)
+        } else {
+            Duration(millis as i64)
+        }
+    }
+
+    pub const fn as_millis(self) -> i64 {
+        self.0
+    }
+
+    /// Saturates on overflow.
+    pub fn as_micros(self) -> i64 {
+        self.0.saturating_mul(1000)
+    }
+
+    pub fn as_millis_f64(self) -> f64 {
+        self.0 as f64
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn checked_add(self, rhs: Duration) -> Option<Duration> {
+        self.0.checked_add(rhs.0).map(Duration)
+    }
+
+    pub fn checked_sub(self, rhs: Duration) -> Option<Duration> {
+        self.0.checked_sub(rhs.0).map(Duration)
+    }
+
+    pub fn checked_mul(self, rhs: i64) -> Option<Duration> {
+        self.0.checked_mul(rhs).map(Duration)
+    }
+
+    /// Scale the duration, rounding toward zero and saturating on overflow.
+    pub fn mul_f64(self, factor: f64) -> Duration {
+        Duration::from_millis_f64(self.0 as f64 * factor)
+    }
+
+    /// Number of whole `period`s in the duration, rounded toward zero.
+    pub fn div_duration(self, period: Duration) -> i64 {
+        assert!(period.0 != 0, "Periods must not be empty");
+        self.0 / period.0
+    }
+}
+
+// Arithmetic on durations and times saturates instead of overflowing, so that
+// `NodeTime::never()` stays in the future.
+impl std::ops::Add for Duration {
+    type Output = Duration;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        Duration(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl std::ops::Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        Duration(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl std::ops::Neg for Duration {
+    type Output = Duration;
+
+    fn neg(self) -> Self::Output {
+        Duration(0i64.saturating_sub(self.0))
+    }
+}
+
+impl std::ops::Mul<i64> for Duration {
+    type Output = Duration;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        Duration(self.0.saturating_mul(rhs))
+    }
+}
+
+impl std::ops::AddAssign for Duration {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::SubAssign for Duration {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl std::iter::Sum for Duration {
+    fn sum<I: Iterator<Item = Duration>>(iter: I) -> Self {
+        iter.fold(Duration::ZERO, |x, y| x + y)
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Duration> for Duration {
+    fn sum<I: Iterator<Item = &'a Duration>>(iter: I) -> Self {
+        iter.fold(Duration::ZERO, |x, y| x + *y)
+    }
+}
+
 impl NodeTime {
     pub fn never() -> Self {
         NodeTime(std::i64::MAX)
     }
+
+    pub fn is_never(self) -> bool {
+        self == NodeTime::never()
+    }
+
+    /// `None` on overflow, or for `never()`.
+    pub fn checked_add(self, rhs: Duration) -> Option<NodeTime> {
+        if self.is_never() {
+            return None;
+        }
+        self.0
+            .checked_add(rhs.0)
+            .map(NodeTime)
+            .filter(|time| !time.is_never())
+    }
+
+    /// Time elapsed since `earlier`, negative if `earlier` is later.
+    pub fn duration_since(self, earlier: NodeTime) -> Duration {
+        Duration(self.0.saturating_sub(earlier.0))
+    }
 }
 
+/// Adding any duration to `never()` is still `never()`.
 impl std::ops::Add<Duration> for NodeTime {
     type Output = NodeTime;
 
     fn add(self, rhs: Duration) -> Self::Output {
-        NodeTime(self.0 + rhs)
+        if self.is_never() {
+            return self;
+        }
+        NodeTime(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl std::ops::Sub<Duration> for NodeTime {
+    type Output = NodeTime;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self + (-rhs)
+    }
+}
+
+impl std::ops::Sub for NodeTime {
+    type Output = Duration;
+
+    fn sub(self, rhs: NodeTime) -> Self::Output {
+        self.duration_since(rhs)
     }
 }
 
 impl Signature {
+    #[cfg(not(feature = "ed25519"))]
     pub fn sign(hash: u64, author: Author) -> Self {
         let mut hasher = DefaultHasher::new();
         hash.hash(&mut hasher);
@@ -61,6 +392,7 @@ impl Signature {
         Signature(hasher.finish())
     }
 
+    #[cfg(not(feature = "ed25519"))]
     pub fn check(&self, hash: u64, author: Author) -> Result<()> {
         let mut hasher = DefaultHasher::new();
         hash.hash(&mut hasher);
@@ -68,6 +400,150 @@ impl Signature {
         ensure!(hasher.finish() == self.0, "Signatures must be valid.");
         Ok(())
     }
+
+    /// An arbitrary signature derived from `value`, e.g. to forge records in tests.
+    #[cfg(not(feature = "ed25519"))]
+    pub fn forged(value: u64) -> Self {
+        Signature(value)
+    }
+
+    /// Encoding of the signature on the wire.
+    #[cfg(not(feature = "ed25519"))]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_le_bytes().to_vec()
+    }
+
+    #[cfg(not(feature = "ed25519"))]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(Signature(u64_from_bytes(bytes)?))
+    }
+
+    /// Size on the wire, in bytes. Placeholders count as ed25519 signatures.
+    pub fn num_bytes() -> usize {
+        64
+    }
+}
+
+#[cfg(not(feature = "ed25519"))]
+impl AggregateSignature {
+    pub fn aggregate(signatures: &[Signature]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        signatures.hash(&mut hasher);
+        AggregateSignature(hasher.finish())
+    }
+
+    /// Check the aggregate of the signatures of the given authors on the given digests.
+    pub fn check(&self, digests: &[(Author, u64)]) -> Result<()> {
+        let signatures: Vec<_> = digests
+            .iter()
+            .map(|(author, hash)| Signature::sign(*hash, *author))
+            .collect();
+        ensure!(
+            *self == Self::aggregate(&signatures),
+            "Aggregate signatures must be valid."
+        );
+        Ok(())
+    }
+
+    /// Size on the wire, in bytes. Placeholders count as BLS signatures.
+    pub fn num_bytes(&self) -> usize {
+        96
+    }
+
+    /// Encoding of the aggregate on the wire.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_le_bytes().to_vec()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(AggregateSignature(u64_from_bytes(bytes)?))
+    }
+}
+
+#[cfg(not(feature = "ed25519"))]
+fn u64_from_bytes(bytes: &[u8]) -> Result<u64> {
+    ensure!(
+        bytes.len() == 8,
+        "Placeholder signatures must have 8 bytes."
+    );
+    let mut value = [0; 8];
+    value.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(value))
+}
+
+impl MultiSignature {
+    /// Gather the signatures of distinct authors, aggregated or not.
+    pub fn new(signatures: Vec<(Author, Signature)>, aggregate: bool) -> Self {
+        if !aggregate {
+            return MultiSignature::Individual(signatures);
+        }
+        let (authors, signatures): (Vec<_>, Vec<_>) = signatures.into_iter().unzip();
+        MultiSignature::Aggregate(authors, AggregateSignature::aggregate(&signatures))
+    }
+
+    pub fn authors(&self) -> Vec<Author> {
+        match self {
+            MultiSignature::Individual(signatures) => {
+                signatures.iter().map(|(author, _)| *author).collect()
+            }
+            MultiSignature::Aggregate(authors, _) => authors.clone(),
+            MultiSignature::Threshold(_) => Vec::new(),
+        }
+    }
+
+    /// Number of known authors.
+    pub fn len(&self) -> usize {
+        match self {
+            MultiSignature::Individual(signatures) => signatures.len(),
+            MultiSignature::Aggregate(authors, _) => authors.len(),
+            MultiSignature::Threshold(_) => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check the signature of each author on the digest `digest(author)`. Threshold signatures
+    /// are checked with the keys of their epoch instead.
+    pub fn check<F: Fn(Author) -> u64>(&self, digest: F) -> Result<()> {
+        match self {
+            MultiSignature::Individual(signatures) => {
+                for (author, signature) in signatures {
+                    signature.check(digest(*author), *author)?;
+                }
+                Ok(())
+            }
+            MultiSignature::Aggregate(authors, signature) => {
+                let digests: Vec<_> = authors
+                    .iter()
+                    .map(|author| (*author, digest(*author)))
+                    .collect();
+                signature.check(&digests)
+            }
+            MultiSignature::Threshold(_) => {
+                bail!("Threshold signatures need the keys of their epoch.")
+            }
+        }
+    }
+
+    /// Size on the wire, in bytes. Signatures come with a bitmap of their authors, except for
+    /// individual signatures out of order, which come with the index of each author.
+    pub fn num_bytes(&self) -> usize {
+        let authors = self.authors();
+        match self {
+            MultiSignature::Individual(signatures) if signer_bitmap(authors.clone()).is_none() => {
+                signatures.len() * (Signature::num_bytes() + AUTHOR_BYTES)
+            }
+            MultiSignature::Individual(signatures) => {
+                signatures.len() * Signature::num_bytes() + bitmap_bytes(&authors)
+            }
+            MultiSignature::Aggregate(_, signature) => {
+                signature.num_bytes() + bitmap_bytes(&authors)
+            }
+            MultiSignature::Threshold(_) => ThresholdSignature::num_bytes(),
+        }
+    }
 }
 
 impl Round {
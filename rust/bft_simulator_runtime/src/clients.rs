@@ -0,0 +1,326 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Clients sending requests to the nodes and waiting for their commit.
+//!
+//! Each client runs a closed loop: it sends a request to `replicas` nodes, waits until one of
+//! them acknowledges the commit of the request, thinks, and sends its next request. A client
+//! without acknowledgment after `timeout` sends the request again to the next nodes. Requests
+//! and acknowledgments travel with the delays of the network, so that request latencies are
+//! the ones observed by the clients, retries included.
+//!
+//! With a positive `gossip_fanout`, a node that receives a request new to it forwards the request
+//! to `gossip_fanout` other nodes, chosen at random, after `gossip_delay` and the delay of the
+//! network. Requests thus reach the mempools of the leaders even if clients only contact a few
+//! nodes.
+
+use crate::{
+    base_types::{Author, Duration, Result},
+    simulator::GlobalTime,
+};
+use rand::{rngs::StdRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[cfg(test)]
+#[path = "unit_tests/clients_tests.rs"]
+mod clients_tests;
+
+/// A request of a client, numbered in the order of the requests of the client.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+pub struct ClientRequest {
+    pub client: usize,
+    pub sequence: usize,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientModel {
+    /// Number of clients.
+    pub clients: usize,
+    /// Number of nodes that each attempt of a request is sent to.
+    pub replicas: usize,
+    /// Time between an acknowledgment and the next request of a client.
+    pub think_time: Duration,
+    /// Time after which a client sends an unacknowledged request again.
+    pub timeout: Duration,
+    /// Number of nodes that a node forwards each new request to, if any.
+    pub gossip_fanout: usize,
+    /// Time before a node forwards a new request, on top of the delay of the network.
+    pub gossip_delay: Duration,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum ClientState {
+    /// Thinking until the given time, before sending the next request.
+    Thinking(GlobalTime),
+    /// Waiting for the acknowledgment of the current request.
+    Waiting {
+        /// Time of the first attempt.
+        issue_time: GlobalTime,
+        /// End of the current attempt.
+        deadline: GlobalTime,
+        /// Arrival of the earliest acknowledgment on its way, if any.
+        acknowledgment: Option<GlobalTime>,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Client {
+    state: ClientState,
+    /// Sequence number of the current request, or of the next one while thinking.
+    sequence: usize,
+    /// Nodes that the current request was sent to so far.
+    contacted: BTreeSet<Author>,
+    /// Next node to send a request to, in round-robin order.
+    next_node: usize,
+}
+
+/// State of all the clients of a simulation.
+#[derive(Clone, Debug)]
+pub struct Clients {
+    model: ClientModel,
+    num_nodes: usize,
+    clients: Vec<Client>,
+    /// Requests on their way to the nodes, with their arrival times.
+    in_transit: Vec<(GlobalTime, Author, ClientRequest)>,
+    /// Completion times and latencies of the requests not reported yet.
+    completions: Vec<(GlobalTime, Duration)>,
+    retries: usize,
+    /// Requests added to the mempool of a node that their client did not contact.
+    forwarded: usize,
+}
+
+impl Default for ClientModel {
+    fn default() -> Self {
+        ClientModel {
+            clients: 1,
+            replicas: 1,
+            think_time: Duration::from_millis(10),
+            timeout: Duration::from_millis(2000),
+            gossip_fanout: 0,
+            gossip_delay: Duration::from_millis(0),
+        }
+    }
+}
+
+impl ClientModel {
+    pub fn check(&self, num_nodes: usize) -> Result<()> {
+        ensure!(self.clients > 0, "At least one client is needed");
+        ensure!(
+            self.replicas > 0 && self.replicas <= num_nodes,
+            "Clients must send their requests to between 1 and {} nodes, not {}",
+            num_nodes,
+            self.replicas
+        );
+        ensure!(
+            !self.think_time.is_negative(),
+            "Think times cannot be negative"
+        );
+        ensure!(
+            self.timeout.is_positive(),
+            "Client timeouts must be positive"
+        );
+        ensure!(
+            self.gossip_fanout < num_nodes,
+            "Requests can be forwarded to at most {} other nodes, not {}",
+            num_nodes.saturating_sub(1),
+            self.gossip_fanout
+        );
+        ensure!(
+            !self.gossip_delay.is_negative(),
+            "Gossip delays cannot be negative"
+        );
+        Ok(())
+    }
+}
+
+impl Clients {
+    /// Clients of `num_nodes` nodes, all sending their first request at time 0. Clients start
+    /// with different nodes so that requests are spread over the nodes.
+    pub fn new(model: ClientModel, num_nodes: usize) -> Self {
+        let clients = (0..model.clients)
+            .map(|index| Client {
+                state: ClientState::Thinking(GlobalTime(0)),
+                sequence: 0,
+                contacted: BTreeSet::new(),
+                next_node: index * model.replicas % num_nodes,
+            })
+            .collect();
+        Clients {
+            model,
+            num_nodes,
+            clients,
+            in_transit: Vec::new(),
+            completions: Vec::new(),
+            retries: 0,
+            forwarded: 0,
+        }
+    }
+
+    /// Requests to send at `time`, by receiving node. Completes the acknowledged requests and
+    /// retries the timed-out ones.
+    pub fn poll(&mut self, time: GlobalTime) -> Vec<(Author, ClientRequest)> {
+        let mut sends = Vec::new();
+        for (index, client) in self.clients.iter_mut().enumerate() {
+            let state = client.state;
+            let is_retry = match state {
+                ClientState::Thinking(until) => {
+                    if until > time {
+                        continue;
+                    }
+                    client.contacted.clear();
+                    client.state = ClientState::Waiting {
+                        issue_time: time,
+                        deadline: time + self.model.timeout,
+                        acknowledgment: None,
+                    };
+                    false
+                }
+                ClientState::Waiting {
+                    issue_time,
+                    deadline,
+                    acknowledgment,
+                } => match acknowledgment {
+                    Some(arrival) if arrival <= time => {
+                        self.completions.push((arrival, arrival - issue_time));
+                        client.sequence += 1;
+                        client.state = ClientState::Thinking(arrival + self.model.think_time);
+                        continue;
+                    }
+                    _ => {
+                        if deadline > time {
+                            continue;
+                        }
+                        client.state = ClientState::Waiting {
+                            issue_time,
+                            deadline: time + self.model.timeout,
+                            acknowledgment,
+                        };
+                        true
+                    }
+                },
+            };
+            if is_retry {
+                self.retries += 1;
+            }
+            let request = ClientRequest {
+                client: index,
+                sequence: client.sequence,
+            };
+            for _ in 0..self.model.replicas {
+                let author = Author(client.next_node);
+                client.contacted.insert(author);
+                client.next_node = (client.next_node + 1) % self.num_nodes;
+                sends.push((author, request));
+            }
+        }
+        sends
+    }
+
+    /// Send a request, which reaches `author` at `arrival`.
+    pub fn send(&mut self, arrival: GlobalTime, author: Author, request: ClientRequest) {
+        self.in_transit.push((arrival, author, request));
+    }
+
+    /// Requests reaching the nodes until `time` included, in order of arrival.
+    pub fn arrivals_until(&mut self, time: GlobalTime) -> Vec<(Author, ClientRequest)> {
+        let (mut arrived, in_transit): (Vec<_>, Vec<_>) = self
+            .in_transit
+            .drain(..)
+            .partition(|(arrival, _, _)| *arrival <= time);
+        self.in_transit = in_transit;
+        arrived.sort();
+        arrived
+            .into_iter()
+            .map(|(_, author, request)| (author, request))
+            .collect()
+    }
+
+    /// Whether the client of `request` waits for it and sent it to `author`, which may then
+    /// acknowledge it.
+    pub fn is_waiting_for(&self, author: Author, request: ClientRequest) -> bool {
+        match self.clients.get(request.client) {
+            Some(client) => match client.state {
+                ClientState::Waiting { .. } => {
+                    client.sequence == request.sequence && client.contacted.contains(&author)
+                }
+                ClientState::Thinking(_) => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Account for `author` adding `request` to its mempool.
+    pub fn accept(&mut self, author: Author, request: ClientRequest) {
+        let is_contacted = match self.clients.get(request.client) {
+            Some(client) => {
+                client.sequence == request.sequence && client.contacted.contains(&author)
+            }
+            None => false,
+        };
+        if !is_contacted {
+            self.forwarded += 1;
+        }
+    }
+
+    /// Nodes that `author` forwards a request new to it to, and the time it waits before
+    /// sending it.
+    pub fn gossip_targets(&self, author: Author, rng: &mut StdRng) -> (Vec<Author>, Duration) {
+        let peers: Vec<_> = (0..self.num_nodes)
+            .map(Author)
+            .filter(|peer| *peer != author)
+            .collect();
+        let targets = peers
+            .choose_multiple(rng, self.model.gossip_fanout)
+            .cloned()
+            .collect();
+        (targets, self.model.gossip_delay)
+    }
+
+    /// Deliver an acknowledgment of `request` to its client at `arrival`.
+    pub fn acknowledge(&mut self, request: ClientRequest, arrival: GlobalTime) {
+        if let Some(client) = self.clients.get_mut(request.client) {
+            if client.sequence != request.sequence {
+                return;
+            }
+            if let ClientState::Waiting { acknowledgment, .. } = &mut client.state {
+                if acknowledgment.map_or(true, |earliest| arrival < earliest) {
+                    *acknowledgment = Some(arrival);
+                }
+            }
+        }
+    }
+
+    /// Earliest time at which the clients have something to do, if any: send a request, receive
+    /// an acknowledgment, time out, or deliver a request to a node.
+    pub fn next_time(&self) -> Option<GlobalTime> {
+        self.clients
+            .iter()
+            .map(|client| match client.state {
+                ClientState::Thinking(until) => until,
+                ClientState::Waiting {
+                    deadline,
+                    acknowledgment,
+                    ..
+                } => acknowledgment.map_or(deadline, |arrival| std::cmp::min(arrival, deadline)),
+            })
+            .chain(self.in_transit.iter().map(|(arrival, _, _)| *arrival))
+            .min()
+    }
+
+    /// Completion times and latencies of the requests completed since the last call.
+    pub fn take_completions(&mut self) -> Vec<(GlobalTime, Duration)> {
+        std::mem::replace(&mut self.completions, Vec::new())
+    }
+
+    /// Number of attempts that were sent again after a timeout.
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    /// Number of requests that reached the mempool of a node only through gossip.
+    pub fn forwarded(&self) -> usize {
+        self.forwarded
+    }
+}
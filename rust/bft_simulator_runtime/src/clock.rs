@@ -0,0 +1,149 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sources of the local time of nodes.
+//!
+//! Nodes only see `NodeTime` values, which drivers read from a `Clock`: the simulator derives
+//! them from the global simulated time with a `SimulatedClock`, whereas nodes on real sockets
+//! read a `WallClock`, counting milliseconds, or a `ScaledClock`, e.g. to run scenarios written
+//! in simulated units at a chosen speed. Clocks that follow real time also tell when timers
+//! should fire.
+
+use crate::{
+    base_types::{Duration, NodeTime, Result},
+    simulator::GlobalTime,
+};
+use std::time::{Duration as StdDuration, Instant};
+
+#[cfg(test)]
+#[path = "unit_tests/clock_tests.rs"]
+mod clock_tests;
+
+pub trait Clock {
+    /// Current local time.
+    fn now(&self) -> NodeTime;
+}
+
+/// Clocks driven by the passing of real time.
+pub trait RealTimeClock: Clock {
+    /// Instant at which the local time reaches `time`.
+    fn instant_at(&self, time: NodeTime) -> Instant;
+}
+
+/// Local clock of a simulated node: the global time since the node started, shifted by the clock
+/// jumps of the node. The simulator sets the global time before each event of the node.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct SimulatedClock {
+    startup_time: GlobalTime,
+    /// Shift of the local time, after clock jumps.
+    offset: Duration,
+    global_time: GlobalTime,
+}
+
+impl SimulatedClock {
+    pub fn new(startup_time: GlobalTime) -> Self {
+        SimulatedClock {
+            startup_time,
+            offset: Duration::ZERO,
+            global_time: startup_time,
+        }
+    }
+
+    pub fn startup_time(&self) -> GlobalTime {
+        self.startup_time
+    }
+
+    /// Start the clock at another time, e.g. for nodes joining late. Clock jumps are kept.
+    pub fn set_startup_time(&mut self, startup_time: GlobalTime) {
+        self.startup_time = startup_time;
+    }
+
+    /// Move the local time forward, or backward for negative offsets.
+    pub fn jump(&mut self, offset: Duration) {
+        self.offset += offset;
+    }
+
+    pub fn set_global_time(&mut self, global_time: GlobalTime) {
+        self.global_time = global_time;
+    }
+
+    /// Global time at which the local time was 0.
+    pub fn origin(&self) -> GlobalTime {
+        self.startup_time - self.offset
+    }
+
+    /// Global time at which the local time is `time`.
+    pub fn global_time_at(&self, time: NodeTime) -> GlobalTime {
+        GlobalTime::from_node_time(time, self.origin())
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> NodeTime {
+        self.global_time.to_node_time(self.origin())
+    }
+}
+
+/// Milliseconds since the creation of the clock.
+#[derive(Copy, Clone, Debug)]
+pub struct WallClock {
+    start: Instant,
+}
+
+impl WallClock {
+    pub fn new() -> Self {
+        WallClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        WallClock::new()
+    }
+}
+
+impl Clock for WallClock {
+    fn now(&self) -> NodeTime {
+        NodeTime(self.start.elapsed().as_millis() as i64)
+    }
+}
+
+impl RealTimeClock for WallClock {
+    fn instant_at(&self, time: NodeTime) -> Instant {
+        self.start + StdDuration::from_millis(std::cmp::max(time.0, 0) as u64)
+    }
+}
+
+/// A wall clock running `speed` times faster, i.e. counting `speed` units of local time per
+/// millisecond.
+#[derive(Copy, Clone, Debug)]
+pub struct ScaledClock {
+    start: Instant,
+    speed: f64,
+}
+
+impl ScaledClock {
+    pub fn new(speed: f64) -> Result<Self> {
+        ensure!(speed > 0.0, "Clock speeds must be positive");
+        Ok(ScaledClock {
+            start: Instant::now(),
+            speed,
+        })
+    }
+}
+
+impl Clock for ScaledClock {
+    fn now(&self) -> NodeTime {
+        let millis = self.start.elapsed().as_micros() as f64 / 1000.0;
+        NodeTime((millis * self.speed) as i64)
+    }
+}
+
+impl RealTimeClock for ScaledClock {
+    fn instant_at(&self, time: NodeTime) -> Instant {
+        let micros = std::cmp::max(time.0, 0) as f64 / self.speed * 1000.0;
+        self.start + StdDuration::from_micros(micros as u64)
+    }
+}
@@ -0,0 +1,24 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Callbacks invoked by the simulator whenever a node commits a block.
+//!
+//! Listeners are registered with `Simulator::add_commit_listener`. They are called once per node
+//! and committed block, in the order of the commits of each node, so that analyses do not need to
+//! poll the nodes after every event.
+
+use crate::{base_types::Author, simulator::GlobalTime, CommittedBlock};
+
+pub trait CommitListener {
+    /// Called when the node `author` has committed `block` at time `time`.
+    fn on_commit(&mut self, author: Author, block: &CommittedBlock, time: GlobalTime);
+}
+
+impl<F> CommitListener for F
+where
+    F: FnMut(Author, &CommittedBlock, GlobalTime),
+{
+    fn on_commit(&mut self, author: Author, block: &CommittedBlock, time: GlobalTime) {
+        self(author, block, time)
+    }
+}
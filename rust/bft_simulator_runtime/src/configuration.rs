@@ -10,13 +10,33 @@ mod configuration_tests;
 
 impl EpochConfiguration {
     pub fn new(voting_rights: BTreeMap<Author, usize>) -> Self {
-        let total_votes = voting_rights.iter().fold(0, |sum, (_, votes)| sum + *votes);
+        let mut total_votes = 0;
+        let mut cumulative_votes = Vec::new();
+        for (author, votes) in &voting_rights {
+            if *votes > 0 {
+                total_votes += *votes;
+                cumulative_votes.push((total_votes, *author));
+            }
+        }
         EpochConfiguration {
             voting_rights,
             total_votes,
+            cumulative_votes,
         }
     }
 
+    pub fn total_votes(&self) -> usize {
+        self.total_votes
+    }
+
+    /// Authors with positive voting rights, in increasing order.
+    pub fn validators(&self) -> Vec<Author> {
+        self.cumulative_votes
+            .iter()
+            .map(|(_, author)| *author)
+            .collect()
+    }
+
     pub fn weight(&self, author: &Author) -> usize {
         *self.voting_rights.get(author).unwrap_or(&0)
     }
@@ -43,14 +63,14 @@ impl EpochConfiguration {
     }
 
     pub fn pick_author(&self, seed: u64) -> Author {
-        // TODO: this is linear-time.
-        let mut target = seed as usize % self.total_votes;
-        for (author, votes) in &self.voting_rights {
-            if *votes > target {
-                return *author;
-            }
-            target -= *votes;
-        }
-        unreachable!()
+        let target = seed as usize % self.total_votes;
+        // Find the first author whose running total exceeds the target.
+        let index = match self
+            .cumulative_votes
+            .binary_search_by_key(&(target + 1), |(total, _)| *total)
+        {
+            Ok(index) | Err(index) => index,
+        };
+        self.cumulative_votes[index].1
     }
 }
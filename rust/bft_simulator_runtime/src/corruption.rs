@@ -0,0 +1,110 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Corruption of network messages in transit, to exercise how receivers decode and verify them.
+//!
+//! Messages picked for corruption are encoded in their wire format, damaged by flipping random
+//! bytes or by truncating them, then decoded again. Messages that cannot be decoded are dropped
+//! and counted as rejected by their receiver. The others are delivered as decoded, so that the
+//! receiver must detect the damage itself.
+//!
+//! The wire format is plain JSON without a checksum or a MAC, so nothing protects the integrity
+//! of a message as a whole. A flipped digit often still decodes, and only the signatures of the
+//! records reveal it. Damage to unsigned fields, such as the sender or the version of a data sync
+//! message, goes undetected.
+
+use crate::base_types::Result;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+#[path = "unit_tests/corruption_tests.rs"]
+mod corruption_tests;
+
+/// Encoding of messages on the wire.
+pub trait WireFormat: Sized {
+    fn to_wire(&self) -> Vec<u8>;
+    /// Decode a message, failing cleanly on garbage.
+    fn from_wire(bytes: &[u8]) -> Result<Self>;
+}
+
+/// How often messages are damaged in transit, and how.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorruptionModel {
+    /// Probability that bytes of a message are flipped.
+    pub flip_rate: f64,
+    /// Number of random bytes flipped in a damaged message.
+    pub flipped_bytes: usize,
+    /// Probability that a message is cut at a random length.
+    pub truncate_rate: f64,
+}
+
+impl Default for CorruptionModel {
+    fn default() -> Self {
+        CorruptionModel {
+            flip_rate: 0.0,
+            flipped_bytes: 1,
+            truncate_rate: 0.0,
+        }
+    }
+}
+
+impl CorruptionModel {
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.flip_rate >= 0.0
+                && self.flip_rate <= 1.0
+                && self.truncate_rate >= 0.0
+                && self.truncate_rate <= 1.0,
+            "Corruption rates must be between 0 and 1"
+        );
+        ensure!(
+            self.flipped_bytes > 0,
+            "Damaged messages need at least one flipped byte"
+        );
+        Ok(())
+    }
+
+    /// Whether any message may be damaged.
+    pub fn is_active(&self) -> bool {
+        self.flip_rate > 0.0 || self.truncate_rate > 0.0
+    }
+
+    /// Damage the encoding of a message at random. Returns whether it was damaged.
+    pub fn corrupt<R: Rng>(&self, bytes: &mut Vec<u8>, rng: &mut R) -> bool {
+        let mut corrupted = false;
+        if !bytes.is_empty() && self.flip_rate > 0.0 && rng.gen::<f64>() < self.flip_rate {
+            for _ in 0..self.flipped_bytes {
+                let index = rng.gen_range(0, bytes.len());
+                bytes[index] ^= rng.gen_range(1, 256) as u8;
+            }
+            corrupted = true;
+        }
+        if !bytes.is_empty() && self.truncate_rate > 0.0 && rng.gen::<f64>() < self.truncate_rate {
+            let length = rng.gen_range(0, bytes.len());
+            bytes.truncate(length);
+            corrupted = true;
+        }
+        corrupted
+    }
+
+    /// Damage a message at random. Returns `None` if the message was left intact, and the
+    /// decoding of the damaged message otherwise.
+    pub fn corrupt_message<T: WireFormat, R: Rng>(
+        &self,
+        message: &T,
+        rng: &mut R,
+    ) -> Option<Result<T>> {
+        if !self.is_active() {
+            // Skip the encoding.
+            return None;
+        }
+        let mut bytes = message.to_wire();
+        if self.corrupt(&mut bytes, rng) {
+            Some(T::from_wire(&bytes))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Terminal dashboard showing the state of each node while a simulation runs.
+//!
+//! Frames are drawn with plain ANSI escape codes whenever the simulated clock crosses the next
+//! refresh time, so the display follows simulated time regardless of how fast the host is.
+
+use crate::{
+    base_types::{Author, Duration, Round},
+    simulator::GlobalTime,
+};
+use serde::Serialize;
+use std::{fmt::Write as _, io};
+
+#[cfg(test)]
+#[path = "unit_tests/dashboard_tests.rs"]
+mod dashboard_tests;
+
+/// Clear the screen and move the cursor to the top-left corner.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// State of a node as displayed in one frame.
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+pub struct DashboardRow {
+    pub author: Author,
+    pub active_round: Round,
+    pub active_leader: Option<Author>,
+    pub highest_committed_round: Option<Round>,
+    /// Messages that were sent to the node and have not been delivered yet.
+    pub pending_messages: usize,
+}
+
+pub struct Dashboard {
+    output: Box<io::Write>,
+    /// Simulated time between two frames.
+    refresh_interval: Duration,
+    /// Wall-clock pause after each frame, so that humans can follow the run.
+    frame_delay: std::time::Duration,
+    next_refresh: GlobalTime,
+    /// Highest committed round of each node in the previous frames, and when it was first seen.
+    last_progress: Vec<(Option<Round>, GlobalTime)>,
+}
+
+impl Dashboard {
+    pub fn new(
+        output: Box<io::Write>,
+        refresh_interval: Duration,
+        frame_delay: std::time::Duration,
+    ) -> Self {
+        Dashboard {
+            output,
+            refresh_interval: std::cmp::max(refresh_interval, Duration::from_millis(1)),
+            frame_delay,
+            next_refresh: GlobalTime(0),
+            last_progress: Vec::new(),
+        }
+    }
+
+    /// Draw on the standard output.
+    pub fn stdout(refresh_interval: Duration, frame_delay: std::time::Duration) -> Self {
+        Self::new(Box::new(io::stdout()), refresh_interval, frame_delay)
+    }
+
+    /// Whether a new frame is due at the given time.
+    pub fn should_refresh(&self, clock: GlobalTime) -> bool {
+        clock >= self.next_refresh
+    }
+
+    /// Draw a frame and schedule the next one.
+    pub fn refresh(&mut self, clock: GlobalTime, rows: &[DashboardRow]) -> io::Result<()> {
+        let frame = self.render_frame(clock, rows);
+        self.output.write_all(CLEAR_SCREEN.as_bytes())?;
+        self.output.write_all(frame.as_bytes())?;
+        self.output.flush()?;
+        while self.next_refresh <= clock {
+            self.next_refresh = self.next_refresh + self.refresh_interval;
+        }
+        // Browsers cannot block the thread, and pace frames themselves.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if self.frame_delay > std::time::Duration::from_millis(0) {
+                std::thread::sleep(self.frame_delay);
+            }
+        }
+        Ok(())
+    }
+
+    /// Format a frame. Nodes whose highest committed round did not change for more than
+    /// two refresh intervals are flagged as stalled.
+    pub fn render_frame(&mut self, clock: GlobalTime, rows: &[DashboardRow]) -> String {
+        self.last_progress.resize(rows.len(), (None, GlobalTime(0)));
+        let mut frame = String::new();
+        writeln!(frame, "Simulated time: {}", clock.0).unwrap();
+        writeln!(
+            frame,
+            "{:>6} {:>8} {:>8} {:>10} {:>8}  status",
+            "node", "round", "leader", "committed", "pending"
+        )
+        .unwrap();
+        for (row, progress) in rows.iter().zip(self.last_progress.iter_mut()) {
+            if progress.0 != row.highest_committed_round {
+                *progress = (row.highest_committed_round, clock);
+            }
+            let stalled = clock - progress.1 > self.refresh_interval * 2;
+            writeln!(
+                frame,
+                "{:>6} {:>8} {:>8} {:>10} {:>8}  {}",
+                row.author.0,
+                row.active_round.0,
+                row.active_leader
+                    .map(|author| author.0.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                row.highest_committed_round
+                    .map(|round| round.0.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                row.pending_messages,
+                if stalled {
+                    format!("stalled since {}", (progress.1).0)
+                } else {
+                    String::new()
+                }
+            )
+            .unwrap();
+        }
+        frame
+    }
+}
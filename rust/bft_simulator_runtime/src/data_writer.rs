@@ -2,9 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    base_types::Author,
-    simulator::{Event, GlobalTime, Simulator},
-    ActiveRound,
+    base_types::{Author, Round},
+    simulator::{Event, GlobalTime},
 };
 use std::{fs, path::Path};
 
@@ -32,20 +31,13 @@ impl DataWriter {
         data_writer
     }
 
-    pub fn update_round_number<State, Context, Notification, Request, Response>(
-        &mut self,
-        simulator: &Simulator<State, Context, Notification, Request, Response>,
-        clock: &GlobalTime,
-    ) where
-        State: ActiveRound,
-    {
-        for node_num in 0..self.nodes_len {
-            let node = simulator.simulated_node(Author(node_num));
-            let node_round = node.active_round().0;
-            if node_round > *self.max_round_per_node.get(node_num).unwrap() {
-                self.max_round_per_node[node_num] = node_round;
-                self.nodes_round_switch[node_num].push((node_round, *clock))
-            }
+    /// Record the active round of a node after it has processed an event.
+    pub fn update_round_number(&mut self, author: Author, round: Round, clock: GlobalTime) {
+        let node_num = author.0;
+        let node_round = round.0;
+        if node_round > self.max_round_per_node[node_num] {
+            self.max_round_per_node[node_num] = node_round;
+            self.nodes_round_switch[node_num].push((node_round, clock))
         }
     }
 
@@ -54,7 +46,7 @@ impl DataWriter {
         event: &Event<Notification, Request, Response>,
     ) {
         match event {
-            Event::UpdateTimerEvent { author: _ } => {}
+            Event::UpdateTimerEvent { author: _ } | Event::RecoveryEvent { author: _ } => {}
             _ => self.message_counter += 1,
         }
     }
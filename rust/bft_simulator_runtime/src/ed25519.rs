@@ -0,0 +1,182 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Ed25519 signatures, with the `ed25519` feature.
+//!
+//! The key pair of each author is derived from a seed, so that all the nodes of a simulation, or
+//! of a deployment started from the same scenario, agree on the public keys. Keys are only as
+//! secret as the seed: the point is to exercise record layouts and verification paths with real
+//! cryptography, not to secure a deployment.
+
+use crate::base_types::{AggregateSignature, Author, Result, Signature};
+use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+};
+
+#[cfg(test)]
+#[path = "unit_tests/ed25519_tests.rs"]
+mod ed25519_tests;
+
+/// Key pairs of the authors, derived on demand from a seed.
+struct KeyRegistry {
+    seed: u64,
+    keypairs: BTreeMap<Author, Keypair>,
+}
+
+thread_local! {
+    /// Keys used by the current thread, so that concurrent simulations may use different seeds.
+    static KEYS: RefCell<KeyRegistry> = RefCell::new(KeyRegistry::new(0));
+}
+
+impl KeyRegistry {
+    fn new(seed: u64) -> Self {
+        KeyRegistry {
+            seed,
+            keypairs: BTreeMap::new(),
+        }
+    }
+
+    fn keypair(&mut self, author: Author) -> &Keypair {
+        let seed = self.seed;
+        self.keypairs
+            .entry(author)
+            .or_insert_with(|| derive_keypair(seed, author))
+    }
+}
+
+fn derive_keypair(seed: u64, author: Author) -> Keypair {
+    let mut bytes = [0; 32];
+    for (index, chunk) in bytes.chunks_mut(8).enumerate() {
+        let mut hasher = DefaultHasher::new();
+        (seed, author, index).hash(&mut hasher);
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    let secret = SecretKey::from_bytes(&bytes).expect("Secret keys have 32 bytes");
+    let public = PublicKey::from(&secret);
+    Keypair { secret, public }
+}
+
+/// Generate the key pairs of the first `num_authors` authors from `seed`, for the current
+/// thread. Keys of other authors are derived from the same seed when they are first used.
+pub fn generate_keys(num_authors: usize, seed: u64) {
+    KEYS.with(|keys| {
+        let mut keys = keys.borrow_mut();
+        *keys = KeyRegistry::new(seed);
+        for index in 0..num_authors {
+            keys.keypair(Author(index));
+        }
+    })
+}
+
+/// Seed of the keys of the current thread.
+pub fn key_seed() -> u64 {
+    KEYS.with(|keys| keys.borrow().seed)
+}
+
+/// Use the keys derived from `seed` in the current thread, e.g. to check signatures on behalf of
+/// another thread. Keys already derived from the same seed are kept.
+pub fn use_key_seed(seed: u64) {
+    KEYS.with(|keys| {
+        let mut keys = keys.borrow_mut();
+        if keys.seed != seed {
+            *keys = KeyRegistry::new(seed);
+        }
+    })
+}
+
+pub fn public_key(author: Author) -> [u8; 32] {
+    KEYS.with(|keys| keys.borrow_mut().keypair(author).public.to_bytes())
+}
+
+impl Signature {
+    pub fn sign(hash: u64, author: Author) -> Self {
+        let bytes = KEYS.with(|keys| {
+            keys.borrow_mut()
+                .keypair(author)
+                .sign(&hash.to_le_bytes())
+                .to_bytes()
+        });
+        let mut signature = Signature([0; 32], [0; 32]);
+        signature.0.copy_from_slice(&bytes[..32]);
+        signature.1.copy_from_slice(&bytes[32..]);
+        signature
+    }
+
+    pub fn check(&self, hash: u64, author: Author) -> Result<()> {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(&self.0);
+        bytes[32..].copy_from_slice(&self.1);
+        let is_valid = match ed25519_dalek::Signature::from_bytes(&bytes) {
+            Ok(signature) => KEYS.with(|keys| {
+                keys.borrow_mut()
+                    .keypair(author)
+                    .public
+                    .verify(&hash.to_le_bytes(), &signature)
+                    .is_ok()
+            }),
+            Err(_) => false,
+        };
+        ensure!(is_valid, "Signatures must be valid.");
+        Ok(())
+    }
+
+    /// An arbitrary signature derived from `value`, e.g. to forge records in tests.
+    pub fn forged(value: u64) -> Self {
+        let mut signature = Signature([0; 32], [0; 32]);
+        signature.0[..8].copy_from_slice(&value.to_le_bytes());
+        signature
+    }
+
+    /// Encoding of the signature on the wire.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.0.to_vec();
+        bytes.extend_from_slice(&self.1);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() == 64, "Signatures must have 64 bytes.");
+        let mut signature = Signature([0; 32], [0; 32]);
+        signature.0.copy_from_slice(&bytes[..32]);
+        signature.1.copy_from_slice(&bytes[32..]);
+        Ok(signature)
+    }
+}
+
+impl AggregateSignature {
+    pub fn aggregate(signatures: &[Signature]) -> Self {
+        AggregateSignature(signatures.to_vec())
+    }
+
+    /// Check the signatures of the given authors on the given digests.
+    pub fn check(&self, digests: &[(Author, u64)]) -> Result<()> {
+        ensure!(
+            self.0.len() == digests.len(),
+            "Aggregate signatures must have one signature per author."
+        );
+        for (signature, (author, hash)) in self.0.iter().zip(digests) {
+            signature.check(*hash, *author)?;
+        }
+        Ok(())
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        self.0.len() * Signature::num_bytes()
+    }
+
+    /// Encoding of the aggregate on the wire, as the concatenation of the signatures.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(Signature::to_bytes).collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let signatures = bytes
+            .chunks(64)
+            .map(Signature::from_bytes)
+            .collect::<Result<_>>()?;
+        Ok(AggregateSignature(signatures))
+    }
+}
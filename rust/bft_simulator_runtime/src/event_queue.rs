@@ -0,0 +1,118 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Pending events of the simulator.
+//!
+//! Events are ordered by their scheduled time, then by the order in which they were scheduled.
+//! Comparing keys never looks at the events themselves, which keeps heap operations cheap even
+//! when events carry large payloads, and makes the processing order independent of the content
+//! of messages.
+//!
+//! Determinism: given the same sequence of calls to `push` and `pop`, the queue returns the same
+//! events in the same order. Events scheduled for the same time are returned in FIFO order.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+#[cfg(test)]
+#[path = "unit_tests/event_queue_tests.rs"]
+mod event_queue_tests;
+
+/// Key of a scheduled event: time first, then insertion order.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+struct EventKey<Time> {
+    time: Time,
+    sequence_number: u64,
+    /// Position of the event in the slab. Unique among pending events, hence ignored by the
+    /// ordering in practice.
+    slot: usize,
+}
+
+#[derive(Debug)]
+pub struct EventQueue<Time, Event> {
+    /// Min-heap of keys.
+    keys: BinaryHeap<Reverse<EventKey<Time>>>,
+    /// Storage of the pending events, indexed by slots.
+    slab: Vec<Option<Event>>,
+    /// Slots available for reuse.
+    free_slots: Vec<usize>,
+    /// Number of events pushed so far.
+    next_sequence_number: u64,
+}
+
+impl<Time, Event> EventQueue<Time, Event>
+where
+    Time: Ord + Copy,
+{
+    pub fn new() -> Self {
+        EventQueue {
+            keys: BinaryHeap::new(),
+            slab: Vec::new(),
+            free_slots: Vec::new(),
+            next_sequence_number: 0,
+        }
+    }
+
+    pub fn push(&mut self, time: Time, event: Event) {
+        let slot = match self.free_slots.pop() {
+            Some(slot) => {
+                self.slab[slot] = Some(event);
+                slot
+            }
+            None => {
+                self.slab.push(Some(event));
+                self.slab.len() - 1
+            }
+        };
+        self.keys.push(Reverse(EventKey {
+            time,
+            sequence_number: self.next_sequence_number,
+            slot,
+        }));
+        self.next_sequence_number += 1;
+    }
+
+    /// Remove the earliest event.
+    pub fn pop(&mut self) -> Option<(Time, Event)> {
+        let Reverse(key) = self.keys.pop()?;
+        let event = self.slab[key.slot]
+            .take()
+            .expect("Pending keys should point to stored events");
+        self.free_slots.push(key.slot);
+        Some((key.time, event))
+    }
+
+    /// Remove the event at position `index` in the processing order, e.g. 0 for the earliest
+    /// event. Returns `None` if there are not enough events.
+    pub fn pop_nth(&mut self, index: usize) -> Option<(Time, Event)> {
+        if index >= self.keys.len() {
+            return None;
+        }
+        let skipped: Vec<_> = (0..index).map(|_| self.keys.pop().unwrap()).collect();
+        let result = self.pop();
+        // Skipped keys keep their sequence numbers, hence their relative order.
+        self.keys.extend(skipped);
+        result
+    }
+
+    /// Time of the earliest event, if any.
+    pub fn peek_time(&self) -> Option<Time> {
+        self.keys.peek().map(|Reverse(key)| key.time)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl<Time, Event> Default for EventQueue<Time, Event>
+where
+    Time: Ord + Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
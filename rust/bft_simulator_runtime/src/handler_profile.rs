@@ -0,0 +1,180 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Host CPU time spent in the handlers of the simulated nodes.
+//!
+//! While profiling, the simulator times the handlers that it calls for each event, and nodes may
+//! time parts of their handlers, such as the insertion of records, with `measure`. A part is also
+//! counted in the handler that runs it. Timings come from the monotonic clock of the host, so
+//! they vary from run to run and only point at hot spots.
+
+use crate::base_types::Author;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt,
+    time::{Duration as StdDuration, Instant},
+};
+
+#[cfg(test)]
+#[path = "unit_tests/handler_profile_tests.rs"]
+mod handler_profile_tests;
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+pub enum Handler {
+    /// Pacemaker update, after each event of a node.
+    Update,
+    Recovery,
+    Notification,
+    Request,
+    Response,
+    /// Insertion of the records received from the network.
+    RecordInsertion,
+}
+
+/// Calls of a handler and the time spent in them.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct HandlerTime {
+    pub calls: usize,
+    pub total: StdDuration,
+}
+
+/// Times of the handlers of each node.
+#[derive(Clone, Debug)]
+pub struct HandlerProfile {
+    nodes: Vec<BTreeMap<Handler, HandlerTime>>,
+}
+
+thread_local! {
+    /// Times measured since `start`, if profiling.
+    static MEASUREMENTS: RefCell<Option<Vec<(Handler, StdDuration)>>> = RefCell::new(None);
+}
+
+/// Run `f`, and time it as a call of `handler` if the handlers of the current thread are
+/// profiled.
+pub fn measure<T, F: FnOnce() -> T>(handler: Handler, f: F) -> T {
+    let is_profiling = MEASUREMENTS.with(|measurements| measurements.borrow().is_some());
+    if !is_profiling {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    MEASUREMENTS.with(|measurements| {
+        if let Some(measurements) = measurements.borrow_mut().as_mut() {
+            measurements.push((handler, elapsed));
+        }
+    });
+    result
+}
+
+/// Collect the times measured on the current thread until the result is dropped.
+pub fn start() -> Measurements {
+    MEASUREMENTS.with(|measurements| *measurements.borrow_mut() = Some(Vec::new()));
+    Measurements { _private: () }
+}
+
+pub struct Measurements {
+    _private: (),
+}
+
+impl Measurements {
+    /// Stop collecting times, and return the times measured since `start`.
+    pub fn finish(self) -> Vec<(Handler, StdDuration)> {
+        MEASUREMENTS.with(|measurements| measurements.borrow_mut().take().unwrap_or_default())
+    }
+}
+
+impl Drop for Measurements {
+    fn drop(&mut self) {
+        MEASUREMENTS.with(|measurements| *measurements.borrow_mut() = None);
+    }
+}
+
+impl fmt::Display for Handler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Handler::Update => "update",
+            Handler::Recovery => "recovery",
+            Handler::Notification => "notification",
+            Handler::Request => "request",
+            Handler::Response => "response",
+            Handler::RecordInsertion => "record insertion",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl HandlerTime {
+    /// Mean time of a call.
+    pub fn mean(&self) -> StdDuration {
+        if self.calls == 0 {
+            StdDuration::default()
+        } else {
+            self.total / self.calls as u32
+        }
+    }
+}
+
+impl HandlerProfile {
+    pub fn new(num_nodes: usize) -> Self {
+        HandlerProfile {
+            nodes: vec![BTreeMap::new(); num_nodes],
+        }
+    }
+
+    /// Record a call of `handler` by `author` that lasted `elapsed`.
+    pub fn record(&mut self, author: Author, handler: Handler, elapsed: StdDuration) {
+        let time = self.nodes[author.0].entry(handler).or_default();
+        time.calls += 1;
+        time.total += elapsed;
+    }
+
+    /// Times of the handlers of `author`.
+    pub fn node(&self, author: Author) -> &BTreeMap<Handler, HandlerTime> {
+        &self.nodes[author.0]
+    }
+
+    /// Times of the handlers of all the nodes.
+    pub fn aggregate(&self) -> BTreeMap<Handler, HandlerTime> {
+        let mut aggregate = BTreeMap::new();
+        for times in &self.nodes {
+            for (handler, time) in times {
+                let total: &mut HandlerTime = aggregate.entry(*handler).or_default();
+                total.calls += time.calls;
+                total.total += time.total;
+            }
+        }
+        aggregate
+    }
+
+    /// The node that spent the most time in `handler`, if any.
+    pub fn busiest_node(&self, handler: Handler) -> Option<Author> {
+        (0..self.nodes.len())
+            .filter(|index| self.nodes[*index].contains_key(&handler))
+            .max_by_key(|index| self.nodes[*index][&handler].total)
+            .map(Author)
+    }
+}
+
+impl fmt::Display for HandlerProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<18} {:>10} {:>12} {:>10}  busiest node",
+            "handler", "calls", "total (ms)", "mean (us)"
+        )?;
+        for (handler, time) in self.aggregate() {
+            writeln!(
+                f,
+                "{:<18} {:>10} {:>12.3} {:>10.3}  {}",
+                handler.to_string(),
+                time.calls,
+                time.total.as_micros() as f64 / 1000.0,
+                time.mean().as_nanos() as f64 / 1000.0,
+                self.busiest_node(handler).map_or(0, |author| author.0)
+            )?;
+        }
+        Ok(())
+    }
+}
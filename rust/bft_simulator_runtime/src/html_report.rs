@@ -0,0 +1,270 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Self-contained HTML reports of runs and sweeps.
+//!
+//! A report is a single page of headings, tables, and charts. Charts are inline SVG, and the
+//! page has no scripts or external resources, so that the file can be shared as is and opened
+//! with any browser.
+
+use crate::{base_types::Result, statistics::Distribution};
+use std::{fs, path::Path};
+
+#[cfg(test)]
+#[path = "unit_tests/html_report_tests.rs"]
+mod html_report_tests;
+
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 240.0;
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 10.0;
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 30.0;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }\n\
+                     table { border-collapse: collapse; margin: 1em 0; }\n\
+                     th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: right; }\n\
+                     th { background: #eee; }\n\
+                     figure { margin: 1em 0; }\n\
+                     svg text { font-size: 10px; fill: #444; }\n";
+
+/// An HTML page, built section by section.
+#[derive(Clone, Debug)]
+pub struct HtmlReport {
+    title: String,
+    body: Vec<String>,
+}
+
+impl HtmlReport {
+    pub fn new(title: &str) -> Self {
+        HtmlReport {
+            title: title.to_string(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn heading(&mut self, text: &str) {
+        self.body.push(format!("<h2>{}</h2>", escape(text)));
+    }
+
+    pub fn paragraph(&mut self, text: &str) {
+        self.body.push(format!("<p>{}</p>", escape(text)));
+    }
+
+    pub fn table(&mut self, headers: &[&str], rows: &[Vec<String>]) {
+        let mut lines = vec!["<table>".to_string(), "<tr>".to_string()];
+        for header in headers {
+            lines.push(format!("<th>{}</th>", escape(header)));
+        }
+        lines.push("</tr>".to_string());
+        for row in rows {
+            let cells: Vec<_> = row
+                .iter()
+                .map(|cell| format!("<td>{}</td>", escape(cell)))
+                .collect();
+            lines.push(format!("<tr>{}</tr>", cells.join("")));
+        }
+        lines.push("</table>".to_string());
+        self.body.push(lines.join("\n"));
+    }
+
+    /// One bar per value, scaled to the highest value. Labels are shown below the bars when
+    /// they fit, and in the tooltips otherwise.
+    pub fn bar_chart(&mut self, caption: &str, labels: &[String], values: &[f64]) {
+        let max = values.iter().cloned().fold(0.0, f64::max);
+        let (width, height) = plot_size();
+        let slot = width / std::cmp::max(values.len(), 1) as f64;
+        let mut shapes = axes(&format_number(max), "", "");
+        for (index, value) in values.iter().enumerate() {
+            let bar_height = if max > 0.0 { value / max * height } else { 0.0 };
+            let label = labels.get(index).map_or("", String::as_str);
+            let x = MARGIN_LEFT + index as f64 * slot;
+            shapes.push(format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#4878a8\">\
+                 <title>{}: {}</title></rect>",
+                x + slot * 0.1,
+                MARGIN_TOP + height - bar_height,
+                slot * 0.8,
+                bar_height,
+                escape(label),
+                format_number(*value)
+            ));
+            if values.len() <= 20 {
+                shapes.push(format!(
+                    "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                    x + slot / 2.0,
+                    CHART_HEIGHT - MARGIN_BOTTOM + 12.0,
+                    escape(label)
+                ));
+            }
+        }
+        self.figure(caption, shapes);
+    }
+
+    /// A line through the given points, in increasing order of `x`.
+    pub fn line_chart(&mut self, caption: &str, points: &[(f64, f64)]) {
+        let (x_min, x_max) = bounds(points.iter().map(|point| point.0));
+        let (_, y_max) = bounds(points.iter().map(|point| point.1));
+        let (width, height) = plot_size();
+        let mut shapes = axes(
+            &format_number(y_max),
+            &format_number(x_min),
+            &format_number(x_max),
+        );
+        let coordinates: Vec<_> = points
+            .iter()
+            .map(|(x, y)| {
+                let x = MARGIN_LEFT + scale(*x, x_min, x_max) * width;
+                let y = MARGIN_TOP + height - scale(*y, 0.0, y_max) * height;
+                format!("{:.1},{:.1}", x, y)
+            })
+            .collect();
+        shapes.push(format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"#4878a8\" stroke-width=\"2\"/>",
+            coordinates.join(" ")
+        ));
+        self.figure(caption, shapes);
+    }
+
+    /// Markers at the times of the given events, from 0 to `end`, with the events as
+    /// tooltips.
+    pub fn timeline(&mut self, caption: &str, end: f64, events: &[(f64, String)]) {
+        let (width, height) = plot_size();
+        let mut shapes = axes("", "0", &format_number(end));
+        for (time, event) in events {
+            let x = MARGIN_LEFT + scale(*time, 0.0, end) * width;
+            shapes.push(format!(
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#c04830\" \
+                 stroke-width=\"2\"><title>{}: {}</title></line>",
+                x,
+                MARGIN_TOP,
+                x,
+                MARGIN_TOP + height,
+                format_number(*time),
+                escape(event)
+            ));
+        }
+        self.figure(caption, shapes);
+    }
+
+    fn figure(&mut self, caption: &str, shapes: Vec<String>) {
+        self.body.push(format!(
+            "<figure>\n<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n{}\n</svg>\n\
+             <figcaption>{}</figcaption>\n</figure>",
+            CHART_WIDTH,
+            CHART_HEIGHT,
+            CHART_WIDTH,
+            CHART_HEIGHT,
+            shapes.join("\n"),
+            escape(caption)
+        ));
+    }
+
+    pub fn to_html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>\n{}</style>\n</head>\n<body>\n<h1>{}</h1>\n{}\n</body>\n</html>\n",
+            escape(&self.title),
+            STYLE,
+            escape(&self.title),
+            self.body.join("\n")
+        )
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_html())?;
+        Ok(())
+    }
+}
+
+/// A table row with the statistics of a distribution: count, min, p50, p95, p99, max, and
+/// mean. Cells are empty when nothing was measured.
+pub fn distribution_row(name: &str, distribution: &Option<Distribution>) -> Vec<String> {
+    let mut row = vec![name.to_string()];
+    match distribution {
+        Some(x) => {
+            row.push(x.count.to_string());
+            for value in &[x.min, x.p50, x.p95, x.p99, x.max, x.mean] {
+                row.push(format_number(*value));
+            }
+        }
+        None => row.extend(vec![String::new(); 7]),
+    }
+    row
+}
+
+/// Headers of the rows made by `distribution_row`.
+pub const DISTRIBUTION_HEADERS: [&str; 8] =
+    ["metric", "count", "min", "p50", "p95", "p99", "max", "mean"];
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Integers as such, and other numbers with at most 3 decimals.
+pub fn format_number(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn plot_size() -> (f64, f64) {
+    (
+        CHART_WIDTH - MARGIN_LEFT - MARGIN_RIGHT,
+        CHART_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM,
+    )
+}
+
+/// The axes of a chart, with the highest value of the y axis and both ends of the x axis.
+fn axes(y_max: &str, x_min: &str, x_max: &str) -> Vec<String> {
+    let (width, height) = plot_size();
+    let bottom = MARGIN_TOP + height;
+    vec![
+        format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#888\"/>",
+            MARGIN_LEFT, MARGIN_TOP, MARGIN_LEFT, bottom
+        ),
+        format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#888\"/>",
+            MARGIN_LEFT,
+            bottom,
+            MARGIN_LEFT + width,
+            bottom
+        ),
+        format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            MARGIN_LEFT - 4.0,
+            MARGIN_TOP + 4.0,
+            escape(y_max)
+        ),
+        format!(
+            "<text x=\"{}\" y=\"{}\">{}</text>",
+            MARGIN_LEFT,
+            CHART_HEIGHT - 4.0,
+            escape(x_min)
+        ),
+        format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            MARGIN_LEFT + width,
+            CHART_HEIGHT - 4.0,
+            escape(x_max)
+        ),
+    ]
+}
+
+fn bounds<I: Iterator<Item = f64>>(values: I) -> (f64, f64) {
+    values.fold((0.0, 0.0), |(min, max), value| {
+        (f64::min(min, value), f64::max(max, value))
+    })
+}
+
+/// Position of `value` between `min` and `max`, from 0 to 1.
+fn scale(value: f64, min: f64, max: f64) -> f64 {
+    if max > min {
+        (value - min) / (max - min)
+    } else {
+        0.0
+    }
+}
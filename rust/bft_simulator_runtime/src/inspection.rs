@@ -0,0 +1,39 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Read-only views of the nodes of a simulation.
+//!
+//! Views are snapshots taken with `Simulator::inspect_node` or `Simulator::inspect_nodes` at any
+//! point of a run, e.g. between two calls to `Simulator::run` or from an invariant, so that
+//! drivers, checkers, and user interfaces do not depend on the internals of the simulator.
+
+use crate::{
+    base_types::{Author, Round},
+    simulator::GlobalTime,
+};
+
+/// State of a node at the time of the inspection.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct NodeView {
+    pub author: Author,
+    /// Whether the node has started and has not crashed.
+    pub running: bool,
+    pub active_round: Round,
+    pub active_leader: Option<Author>,
+    /// Round of the highest QC known in the current epoch of the node.
+    pub highest_quorum_certificate_round: Round,
+    pub highest_committed_round: Option<Round>,
+    pub committed_blocks: usize,
+    pub pending: PendingActions,
+}
+
+/// Work of a node that the simulator has not processed yet.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct PendingActions {
+    /// Messages that were sent to the node and have not been delivered yet.
+    pub messages: usize,
+    /// Time of the next timer of the node, if any.
+    pub next_update: Option<GlobalTime>,
+    /// Time until which the node executes commands, if it is still busy.
+    pub busy_until: Option<GlobalTime>,
+}
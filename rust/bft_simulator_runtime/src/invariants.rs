@@ -0,0 +1,59 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Properties of the nodes checked by the simulator while a run progresses.
+//!
+//! Invariants are registered with `Simulator::add_invariant` and checked after every event, or
+//! only after events that led to new commits. Protocol-specific invariants are defined next to
+//! the protocol.
+
+use crate::simulator::SimulatedNode;
+
+/// When an invariant is checked.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum CheckPoint {
+    EveryEvent,
+    /// After the events where at least one node committed a block.
+    EveryCommit,
+}
+
+pub trait Invariant<Node, Context> {
+    /// Name of the invariant, used to report violations.
+    fn name(&self) -> &str;
+
+    fn check_point(&self) -> CheckPoint {
+        CheckPoint::EveryEvent
+    }
+
+    /// Check the property. Invariants may keep state between checks, e.g. to verify that
+    /// some value never decreases. Returns a description of the violation, if any.
+    fn check(&mut self, nodes: &[SimulatedNode<Node, Context>]) -> Result<(), String>;
+}
+
+/// An invariant defined by a closure, checked after every event.
+pub struct FnInvariant<F> {
+    name: String,
+    function: F,
+}
+
+impl<F> FnInvariant<F> {
+    pub fn new(name: &str, function: F) -> Self {
+        FnInvariant {
+            name: name.to_string(),
+            function,
+        }
+    }
+}
+
+impl<Node, Context, F> Invariant<Node, Context> for FnInvariant<F>
+where
+    F: FnMut(&[SimulatedNode<Node, Context>]) -> Result<(), String>,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, nodes: &[SimulatedNode<Node, Context>]) -> Result<(), String> {
+        (self.function)(nodes)
+    }
+}
@@ -3,13 +3,21 @@
 
 #![allow(bare_trait_objects)]
 
+#[cfg(feature = "ed25519")]
+extern crate ed25519_dalek;
+#[cfg(not(target_arch = "wasm32"))]
+extern crate env_logger;
 #[macro_use]
 extern crate failure;
-extern crate rand;
 #[macro_use]
 extern crate log;
-extern crate env_logger;
+extern crate rand;
+extern crate serde;
+extern crate serde_json;
+#[cfg(feature = "tokio")]
+extern crate tokio;
 
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 // Comments in the following form are used for code-block generation in the consensus report:
@@ -18,11 +26,48 @@ use std::collections::BTreeMap;
 // Do not modify definitions without changing the report as well :)
 
 pub mod base_types;
+pub mod clients;
+pub mod clock;
+pub mod commit_listeners;
 pub mod configuration;
+pub mod corruption;
+pub mod dashboard;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod data_writer;
+#[cfg(feature = "ed25519")]
+pub mod ed25519;
+pub mod event_queue;
+pub mod handler_profile;
+pub mod html_report;
+pub mod inspection;
+pub mod invariants;
+pub mod mempool;
+pub mod message_counters;
+pub mod metrics;
+pub mod nemesis;
+#[cfg(feature = "tokio")]
+pub mod network;
+pub mod network_trace;
+pub mod node_logs;
+pub mod observers;
+pub mod prometheus;
+pub mod quota;
+pub mod resource_costs;
+pub mod safety;
+pub mod scheduler;
 pub mod simulator;
+pub mod stall;
+pub mod statistics;
+pub mod termination;
+pub mod threshold;
+pub mod workload;
 
-use crate::base_types::{Author, NodeTime, Round};
+use crate::{
+    base_types::{Author, Duration, NodeTime, Round},
+    clients::ClientRequest,
+    mempool::Transaction,
+    message_counters::MessageKind,
+};
 
 // -- BEGIN FILE node_update_actions --
 #[derive(Debug)]
@@ -35,6 +80,10 @@ pub struct NodeUpdateActions {
     pub should_broadcast: bool,
     /// Whether we need to request data from all other nodes.
     pub should_query_all: bool,
+    /// Whether we need to request data from a subset of nodes.
+    pub should_query: Vec<Author>,
+    /// Nodes to leave out of the notifications, e.g. because they hold the records already.
+    pub should_skip: Vec<Author>,
 }
 // -- END FILE --
 
@@ -45,6 +94,8 @@ impl NodeUpdateActions {
             should_send: Vec::new(),
             should_broadcast: false,
             should_query_all: false,
+            should_query: Vec::new(),
+            should_skip: Vec::new(),
         }
     }
 }
@@ -65,26 +116,153 @@ pub trait DataSyncNode<Context> {
     fn create_notification(&self) -> Self::Notification;
     /// Query role: what to send to initiate a query exchange and obtain data from a sender.
     fn create_request(&self) -> Self::Request;
-    /// Sender role: handle a request from a receiver.
-    fn handle_request(&self, request: Self::Request) -> Self::Response;
-    /// Receiver role: accept or refuse a notification.
+    /// Sender role: handle a request from a receiver. Requests may be shared with other senders.
+    fn handle_request(&self, request: &Self::Request) -> Self::Response;
+    /// Receiver role: accept or refuse a notification from `sender`. Notifications may be shared
+    /// with other receivers, so nodes only copy the parts that they keep.
     fn handle_notification(
         &mut self,
-        notification: Self::Notification,
+        sender: Author,
+        notification: &Self::Notification,
         context: &mut Context,
     ) -> Option<Self::Request>;
-    /// Receiver role: receive data.
-    fn handle_response(&mut self, response: Self::Response, context: &mut Context, clock: NodeTime);
+    /// Receiver role: receive data from `sender`.
+    fn handle_response(
+        &mut self,
+        sender: Author,
+        response: Self::Response,
+        context: &mut Context,
+        clock: NodeTime,
+    );
 }
 // -- END FILE --
 
+/// Nodes that keep track of the peers they hear from. Senders are given by the network, i.e.
+/// by authenticated channels, so that nodes never trust the sender claimed by a message.
+pub trait PeerTracker {
+    /// Note that a message of the given kind arrived from `sender`, before it is handled.
+    fn receive_message(&mut self, sender: Author, kind: MessageKind);
+}
+
+/// Nodes that restart after a crash.
+pub trait RecoverableNode<Context> {
+    /// Drop the volatile state of the node and reload its durable state from the context.
+    fn recover(&mut self, clock: NodeTime, context: &mut Context);
+}
+
 pub trait ActiveRound {
     fn active_round(&self) -> Round;
 }
 
+/// Leader of the active round, as believed by a node.
+pub trait ActiveLeader {
+    fn active_leader(&self) -> Option<Author>;
+}
+
+/// A block committed by a node, as reported for monitoring purposes.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Deserialize, Serialize)]
+pub struct CommittedBlock {
+    /// Round of the committed block.
+    pub round: Round,
+    /// Creator of the committed block.
+    pub proposer: Author,
+    /// Time of the proposal, according to the local clock of the proposer.
+    pub proposal_time: NodeTime,
+    /// Hash of the committed block, used to detect conflicting commits.
+    pub hash: u64,
+    /// Transactions of the block, submitted to the proposer. Submission times are according to
+    /// the local clock of the proposer.
+    pub transactions: Vec<Transaction>,
+    /// Whether the block was committed by a fast path rather than by the commit rule.
+    pub fast_path: bool,
+}
+
+/// Statistics exposed by a node so that the simulator can summarize a run.
+pub trait NodeStatistics {
+    /// Blocks committed so far, in the order of commits.
+    fn committed_blocks(&self) -> &[CommittedBlock];
+    /// Number of rounds that were ended by a quorum certificate.
+    fn quorum_certificate_rounds(&self) -> usize;
+    /// Round of the highest quorum certificate known in the current epoch.
+    fn highest_quorum_certificate_round(&self) -> Round;
+    /// Number of rounds that were ended by a timeout certificate.
+    fn timeout_certificate_rounds(&self) -> usize;
+    /// Number of rounds that were ended by a timeout certificate, per leader of the round.
+    fn timeout_certificate_leaders(&self) -> BTreeMap<Author, usize>;
+    /// Local times at which the node switched between pacemaker strategies, if it has several.
+    fn pacemaker_switch_times(&self) -> Vec<NodeTime> {
+        Vec::new()
+    }
+    /// Number of records that the node evicted to bound its memory, if it does.
+    fn evicted_records(&self) -> usize {
+        0
+    }
+    /// Number of notifications that the node did not send because their receiver held the
+    /// records already, if it tracks them.
+    fn suppressed_notifications(&self) -> usize {
+        0
+    }
+    /// Number of decoded messages that the node rejected on verification, if it verifies them
+    /// before processing them.
+    fn rejected_messages(&self) -> usize {
+        0
+    }
+    /// Number of times that the node started suspecting a peer, if it runs a failure detector.
+    fn suspicions(&self) -> usize {
+        0
+    }
+    /// Number of proposals that the node refused to vote for because their payload failed
+    /// validation, if it validates them.
+    fn rejected_proposals(&self) -> usize {
+        0
+    }
+    /// Rounds above `round` that were ended by a timeout certificate in the current epoch.
+    fn timeout_certificate_rounds_after(&self, _round: Round) -> Vec<Round> {
+        Vec::new()
+    }
+}
+
+/// Simulated time spent by a node on local computations, e.g. executing commands.
+pub trait ExecutionTime {
+    /// Time spent since the last call.
+    fn take_execution_time(&mut self) -> Duration;
+}
+
+/// Cryptographic operations performed by a node so far.
+pub trait CryptoStatistics {
+    fn signatures_created(&self) -> usize;
+    fn signatures_verified(&self) -> usize;
+    /// MACs computed or checked, for authenticated channels.
+    fn macs_computed(&self) -> usize;
+}
+
+/// Requests of the simulated clients, received by a node.
+pub trait ClientRequests {
+    /// Receive a request from a client, or forwarded by another node, at local time `clock`.
+    /// Returns whether the request was new to the node.
+    fn submit_request(&mut self, request: ClientRequest, clock: NodeTime) -> bool;
+}
+
+/// Authentication of the network messages of a node, for accounting purposes.
+pub trait AuthenticatedChannels {
+    /// Authenticate a message sent to `recipients` nodes. Returns the size of the
+    /// authentication data added to each copy, in bytes.
+    fn authenticate_message(&mut self, recipients: usize) -> usize;
+    /// Check the authentication of a received message.
+    fn check_message_authentication(&mut self);
+}
+
+/// Errors met by a node on unexpected inputs or states, which it skipped instead of aborting.
+pub trait NodeErrors {
+    /// Errors met since the last call.
+    fn take_errors(&mut self) -> Vec<String>;
+}
+
 #[derive(Eq, PartialEq, Clone, Debug)]
 /// Hold voting rights for a give epoch.
 pub struct EpochConfiguration {
     voting_rights: BTreeMap<Author, usize>,
     total_votes: usize,
+    /// Authors with positive voting rights and the running total of votes up to each of them.
+    cumulative_votes: Vec<(usize, Author)>,
 }
@@ -0,0 +1,260 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Transactions submitted to a node and waiting to be committed.
+//!
+//! Leaders pull batches of the oldest pending transactions into their proposals. A batch stays
+//! in flight until it is committed. Once a later batch is committed, earlier batches still in
+//! flight can no longer be committed, so their transactions are pending again. The same goes
+//! for a batch released by its proposer, e.g. because the committed chain left out its block.
+//!
+//! Targeted transactions are submitted to all the nodes at once, e.g. by a client expecting to
+//! be censored. Each node drops them once any block carrying them is committed. A censoring
+//! node never pulls them into its batches.
+//!
+//! Requests of clients may be sent to several nodes, and again after a timeout. Nodes drop the
+//! copies of a request once any block carrying it is committed.
+
+use crate::{base_types::NodeTime, clients::ClientRequest};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(test)]
+#[path = "unit_tests/mempool_tests.rs"]
+mod mempool_tests;
+
+/// A transaction, numbered in the order of submission to a node.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+pub struct Transaction {
+    pub index: usize,
+    /// Local time of the node at submission.
+    pub submission_time: NodeTime,
+    /// Size of the payload, in bytes.
+    pub size: usize,
+    /// Identifier of a targeted transaction, shared by all the nodes it was submitted to.
+    #[serde(default)]
+    pub targeted: Option<usize>,
+    /// Request of a simulated client carried by the transaction, if any.
+    #[serde(default)]
+    pub request: Option<ClientRequest>,
+    /// Number of earlier proposals of the transaction, in batches that were abandoned.
+    #[serde(default)]
+    pub proposals: usize,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Mempool {
+    /// Transactions not included in a batch in flight, oldest first.
+    pending: BTreeSet<Transaction>,
+    /// Batches pulled into proposals and not committed yet, by identifier.
+    in_flight: BTreeMap<usize, Vec<Transaction>>,
+    next_index: usize,
+    committed_transactions: usize,
+    /// Whether batches leave out targeted transactions.
+    censoring: bool,
+    /// Targeted transactions committed so far, by any proposer.
+    committed_targeted: BTreeSet<usize>,
+    /// Client requests committed so far, by any proposer.
+    committed_requests: BTreeSet<ClientRequest>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Mempool::default()
+    }
+
+    pub fn submit(&mut self, submission_time: NodeTime, size: usize) {
+        self.pending.insert(Transaction {
+            index: self.next_index,
+            submission_time,
+            size,
+            targeted: None,
+            request: None,
+            proposals: 0,
+        });
+        self.next_index += 1;
+    }
+
+    /// Submit the targeted transaction `id`, unless the node holds it already or it was
+    /// committed.
+    pub fn submit_targeted(&mut self, submission_time: NodeTime, size: usize, id: usize) {
+        let is_known = self.committed_targeted.contains(&id)
+            || self
+                .pending
+                .iter()
+                .chain(self.in_flight.values().flatten())
+                .any(|x| x.targeted == Some(id));
+        if is_known {
+            return;
+        }
+        self.pending.insert(Transaction {
+            index: self.next_index,
+            submission_time,
+            size,
+            targeted: Some(id),
+            request: None,
+            proposals: 0,
+        });
+        self.next_index += 1;
+    }
+
+    /// Submit a request of a client, unless the node holds it already or it was committed.
+    /// Returns whether the request was added.
+    pub fn submit_request(
+        &mut self,
+        submission_time: NodeTime,
+        size: usize,
+        request: ClientRequest,
+    ) -> bool {
+        let is_known = self.committed_requests.contains(&request)
+            || self
+                .pending
+                .iter()
+                .chain(self.in_flight.values().flatten())
+                .any(|x| x.request == Some(request));
+        if is_known {
+            return false;
+        }
+        self.pending.insert(Transaction {
+            index: self.next_index,
+            submission_time,
+            size,
+            targeted: None,
+            request: Some(request),
+            proposals: 0,
+        });
+        self.next_index += 1;
+        true
+    }
+
+    /// Leave targeted transactions out of batches, or not.
+    pub fn set_censoring(&mut self, censoring: bool) {
+        self.censoring = censoring;
+    }
+
+    /// Number of transactions waiting to be pulled.
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn num_committed(&self) -> usize {
+        self.committed_transactions
+    }
+
+    /// The oldest pending transactions, at most `max_size` of them and `max_bytes` in total,
+    /// and whether a later transaction is left out because of these limits. Transactions larger
+    /// than `max_bytes` are skipped, since they would hold back all later ones. They stay pending
+    /// in case the limit is raised.
+    fn next_batch(&self, max_size: usize, max_bytes: Option<usize>) -> (Vec<Transaction>, bool) {
+        let mut batch = Vec::new();
+        let mut bytes = 0;
+        for transaction in &self.pending {
+            if self.censoring && transaction.targeted.is_some() {
+                continue;
+            }
+            if max_bytes.map_or(false, |max| transaction.size > max) {
+                continue;
+            }
+            bytes += transaction.size;
+            if batch.len() == max_size || max_bytes.map_or(false, |max| bytes > max) {
+                return (batch, true);
+            }
+            batch.push(*transaction);
+        }
+        (batch, false)
+    }
+
+    /// Whether the next batch is limited by its size rather than by the pending transactions.
+    pub fn is_batch_full(&self, max_size: usize, max_bytes: Option<usize>) -> bool {
+        let (batch, is_truncated) = self.next_batch(max_size, max_bytes);
+        is_truncated || batch.len() == max_size
+    }
+
+    /// Whether the next batch would carry no transactions.
+    pub fn is_batch_empty(&self, max_size: usize, max_bytes: Option<usize>) -> bool {
+        self.next_batch(max_size, max_bytes).0.is_empty()
+    }
+
+    /// Pull the oldest pending transactions into the batch `batch_id`, at most `max_size` of them
+    /// and `max_bytes` in total. Batch identifiers must increase with the order of proposals.
+    pub fn pull_batch(
+        &mut self,
+        batch_id: usize,
+        max_size: usize,
+        max_bytes: Option<usize>,
+    ) -> Vec<Transaction> {
+        let (batch, _) = self.next_batch(max_size, max_bytes);
+        for transaction in &batch {
+            self.pending.remove(transaction);
+        }
+        self.in_flight.insert(batch_id, batch.clone());
+        batch
+    }
+
+    /// Remove the transactions of a committed batch. Earlier batches in flight are abandoned.
+    pub fn commit_batch(&mut self, batch_id: usize) {
+        let mut abandoned = self.in_flight.split_off(&batch_id);
+        std::mem::swap(&mut abandoned, &mut self.in_flight);
+        if let Some(batch) = self.in_flight.remove(&batch_id) {
+            self.committed_transactions += batch.len();
+            self.committed_targeted
+                .extend(batch.iter().filter_map(|x| x.targeted));
+            self.committed_requests
+                .extend(batch.iter().filter_map(|x| x.request));
+        }
+        for (_, batch) in abandoned {
+            self.abandon(batch);
+        }
+    }
+
+    /// Make the transactions of the batch `batch_id` pending again, if it is still in flight,
+    /// because its block can no longer be committed.
+    pub fn release_batch(&mut self, batch_id: usize) {
+        if let Some(batch) = self.in_flight.remove(&batch_id) {
+            self.abandon(batch);
+        }
+    }
+
+    /// Make the transactions of an abandoned batch pending again, unless they were committed
+    /// in the block of another proposer.
+    fn abandon(&mut self, batch: Vec<Transaction>) {
+        for mut transaction in batch {
+            if !self.is_committed_elsewhere(&transaction) {
+                transaction.proposals += 1;
+                self.pending.insert(transaction);
+            }
+        }
+    }
+
+    /// Drop the targeted transactions and the client requests committed in the block of another
+    /// proposer.
+    pub fn commit_targeted(&mut self, transactions: &[Transaction]) {
+        let ids: Vec<_> = transactions.iter().filter_map(|x| x.targeted).collect();
+        let requests: Vec<_> = transactions.iter().filter_map(|x| x.request).collect();
+        if ids.is_empty() && requests.is_empty() {
+            return;
+        }
+        self.committed_targeted.extend(ids);
+        self.committed_requests.extend(requests);
+        let committed: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|x| self.is_committed_elsewhere(x))
+            .cloned()
+            .collect();
+        for transaction in committed {
+            self.pending.remove(&transaction);
+        }
+    }
+
+    /// Whether a targeted transaction or a client request was committed in the block of any
+    /// proposer.
+    fn is_committed_elsewhere(&self, transaction: &Transaction) -> bool {
+        transaction
+            .targeted
+            .map_or(false, |id| self.committed_targeted.contains(&id))
+            || transaction
+                .request
+                .map_or(false, |x| self.committed_requests.contains(&x))
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Counters of the network messages sent and received by each node, broken down by content.
+//!
+//! Messages are counted as sent when the simulator schedules them, including messages that are
+//! lost afterwards, and as received when the receiving node processes them.
+
+use serde::Serialize;
+use std::ops::AddAssign;
+
+#[cfg(test)]
+#[path = "unit_tests/message_counters_tests.rs"]
+mod message_counters_tests;
+
+/// Number of records of each kind carried by messages.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default, Serialize)]
+pub struct RecordCounts {
+    /// Proposed blocks.
+    pub blocks: usize,
+    pub votes: usize,
+    pub timeouts: usize,
+    pub quorum_certificates: usize,
+    /// Size of the transactions carried by the blocks, in bytes.
+    pub payload_bytes: usize,
+    /// Size of the signatures of the records, in bytes, including the votes of QCs.
+    pub signature_bytes: usize,
+}
+
+/// What a message carries, for accounting purposes.
+pub trait CountRecords {
+    fn record_counts(&self) -> RecordCounts;
+}
+
+/// Number of messages of each kind and of the records that they carry.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default, Serialize)]
+pub struct MessageCounts {
+    pub notifications: usize,
+    pub sync_requests: usize,
+    pub sync_responses: usize,
+    pub records: RecordCounts,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default, Serialize)]
+pub struct NodeMessageCounters {
+    pub sent: MessageCounts,
+    pub received: MessageCounts,
+    /// Messages to the node that were damaged in transit. See `corruption`.
+    pub corrupted: usize,
+    /// Damaged messages to the node that could not be decoded, hence were dropped.
+    pub undecodable: usize,
+    /// Messages to the node dropped for exceeding the quota of their sender. See `quota`.
+    pub over_quota: usize,
+    /// Size of the messages sent by the node once encoded, in bytes.
+    pub bytes_sent: usize,
+}
+
+/// Kind of a network message.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum MessageKind {
+    Notification,
+    SyncRequest,
+    SyncResponse,
+}
+
+impl RecordCounts {
+    pub fn total(&self) -> usize {
+        self.blocks + self.votes + self.timeouts + self.quorum_certificates
+    }
+}
+
+impl AddAssign for RecordCounts {
+    fn add_assign(&mut self, other: RecordCounts) {
+        self.blocks += other.blocks;
+        self.votes += other.votes;
+        self.timeouts += other.timeouts;
+        self.quorum_certificates += other.quorum_certificates;
+        self.payload_bytes += other.payload_bytes;
+        self.signature_bytes += other.signature_bytes;
+    }
+}
+
+impl MessageCounts {
+    pub fn add_message(&mut self, kind: MessageKind, records: RecordCounts) {
+        match kind {
+            MessageKind::Notification => self.notifications += 1,
+            MessageKind::SyncRequest => self.sync_requests += 1,
+            MessageKind::SyncResponse => self.sync_responses += 1,
+        }
+        self.records += records;
+    }
+
+    /// Total number of messages.
+    pub fn messages(&self) -> usize {
+        self.notifications + self.sync_requests + self.sync_responses
+    }
+}
+
+impl AddAssign for MessageCounts {
+    fn add_assign(&mut self, other: MessageCounts) {
+        self.notifications += other.notifications;
+        self.sync_requests += other.sync_requests;
+        self.sync_responses += other.sync_responses;
+        self.records += other.records;
+    }
+}
+
+/// Sum of the counters of all nodes.
+pub fn total_counters(counters: &[NodeMessageCounters]) -> NodeMessageCounters {
+    let mut total = NodeMessageCounters::default();
+    for node in counters {
+        total.sent += node.sent;
+        total.received += node.received;
+        total.corrupted += node.corrupted;
+        total.undecodable += node.undecodable;
+        total.over_quota += node.over_quota;
+        total.bytes_sent += node.bytes_sent;
+    }
+    total
+}
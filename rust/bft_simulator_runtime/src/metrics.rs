@@ -0,0 +1,274 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Export of the metrics of a run to CSV and JSON files.
+//!
+//! Schema (version 1):
+//! * `metrics.json`: an object with the fields `schema_version`, `aggregate`, and `nodes`, as
+//!   defined by `MetricsReport`. Distributions are `null` when nothing was measured.
+//! * `aggregate.csv`: a single row with the fields of `AggregateMetrics`. Distributions are
+//!   flattened into columns such as `commit_latency_p95`.
+//! * `nodes.csv`: one row per node with the fields of `NodeMetrics`.
+//!
+//! Every CSV row starts with the `schema_version` column. Missing values are left empty. New
+//! fields may be appended in later versions; renaming or removing a field bumps the version.
+
+use crate::{
+    base_types::Result,
+    resource_costs::ResourceCosts,
+    simulator::GlobalTime,
+    statistics::{ChainQuality, Distribution},
+};
+use serde::Serialize;
+use std::{fs, io, path::Path};
+
+#[cfg(test)]
+#[path = "unit_tests/metrics_tests.rs"]
+mod metrics_tests;
+
+pub const METRICS_SCHEMA_VERSION: u32 = 1;
+
+#[derive(PartialEq, Clone, Debug, Serialize)]
+pub struct MetricsReport {
+    pub schema_version: u32,
+    pub aggregate: AggregateMetrics,
+    pub nodes: Vec<NodeMetrics>,
+}
+
+/// Metrics of the run as a whole.
+#[derive(PartialEq, Clone, Debug, Serialize)]
+pub struct AggregateMetrics {
+    /// Simulated time at the end of the run.
+    pub duration: i64,
+    /// Average number of blocks committed by a node.
+    pub committed_blocks: f64,
+    /// Average number of blocks committed by a node per unit of simulated time.
+    pub commit_throughput: f64,
+    /// Fraction of the rounds that were ended by a timeout certificate.
+    pub timeout_rate: f64,
+    /// Durations between the proposal of a block and its commit, for all nodes.
+    pub commit_latency: Option<Distribution>,
+    /// Differences between the rounds of consecutive commits, for all nodes.
+    pub rounds_per_commit: Option<Distribution>,
+    /// Quality of the longest chain committed by an honest node.
+    pub chain_quality: ChainQuality,
+    /// Rounds strictly between consecutive commits of a node, for all nodes.
+    pub wasted_rounds: Option<Distribution>,
+    /// Average number of transactions committed by a node per unit of simulated time.
+    pub transaction_throughput: f64,
+    /// Durations between the submission of a transaction and its commit, for all nodes.
+    pub transaction_latency: Option<Distribution>,
+    /// Average number of blocks committed by a node, weighted by the stake of the nodes.
+    pub stake_weighted_committed_blocks: f64,
+    /// Fraction of the commits made by a fast path, for all nodes.
+    pub fast_path_rate: f64,
+    /// Resources spent by all the nodes. The CSV file reports them per commit.
+    pub resource_costs: ResourceCosts,
+}
+
+/// Metrics of a single node.
+#[derive(PartialEq, Clone, Debug, Serialize)]
+pub struct NodeMetrics {
+    pub author: usize,
+    pub active_round: usize,
+    pub committed_blocks: usize,
+    pub highest_committed_round: Option<usize>,
+    pub quorum_certificate_rounds: usize,
+    pub timeout_certificate_rounds: usize,
+    pub mean_commit_latency: Option<f64>,
+    /// Whether the node had crashed at the end of the run.
+    pub crashed: bool,
+    pub messages_sent: usize,
+    pub messages_received: usize,
+    /// Records carried by the messages sent by the node. See `message_counters`.
+    pub records_sent: usize,
+    pub records_received: usize,
+    /// Rounds led by the node that ended with a timeout certificate.
+    pub timeouts_as_leader: usize,
+    pub stake: usize,
+    /// Errors reported by the node on unexpected inputs, which it skipped.
+    pub errors: usize,
+    /// Switches of the node between pacemaker strategies.
+    pub pacemaker_switches: usize,
+    /// Simulated times of these switches. The CSV file separates them with semicolons.
+    pub pacemaker_switch_times: Vec<GlobalTime>,
+    /// Records evicted by the node to bound its memory.
+    pub evicted_records: usize,
+    /// Notifications not sent by the node because their receiver held the records already.
+    pub suppressed_notifications: usize,
+    /// Messages to the node that were damaged in transit.
+    pub corrupted_messages: usize,
+    /// Damaged messages that the node could not decode or rejected on verification.
+    pub rejected_messages: usize,
+    /// Messages to the node dropped for exceeding the quota of their sender.
+    pub over_quota_messages: usize,
+    /// Times that the node started suspecting a peer.
+    pub suspicions: usize,
+    /// Proposals that the node refused to vote for because of an invalid payload.
+    pub rejected_proposals: usize,
+}
+
+impl MetricsReport {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Write `metrics.json`, `aggregate.csv`, and `nodes.csv` in the given directory.
+    pub fn export<P: AsRef<Path>>(&self, directory: P) -> Result<()> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+        fs::write(directory.join("metrics.json"), self.to_json()?)?;
+        self.write_aggregate_csv(fs::File::create(directory.join("aggregate.csv"))?)?;
+        self.write_nodes_csv(fs::File::create(directory.join("nodes.csv"))?)?;
+        Ok(())
+    }
+
+    pub fn write_aggregate_csv<W: io::Write>(&self, output: W) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(output);
+        let mut headers = vec![
+            "schema_version".to_string(),
+            "duration".to_string(),
+            "committed_blocks".to_string(),
+            "commit_throughput".to_string(),
+            "timeout_rate".to_string(),
+        ];
+        for name in &["commit_latency", "rounds_per_commit"] {
+            for statistic in &["count", "min", "max", "mean", "p50", "p95", "p99"] {
+                headers.push(format!("{}_{}", name, statistic));
+            }
+        }
+        for name in &[
+            "honest_blocks",
+            "byzantine_blocks",
+            "longest_byzantine_span",
+        ] {
+            headers.push(name.to_string());
+        }
+        for statistic in &["count", "min", "max", "mean", "p50", "p95", "p99"] {
+            headers.push(format!("wasted_rounds_{}", statistic));
+        }
+        headers.push("transaction_throughput".to_string());
+        for statistic in &["count", "min", "max", "mean", "p50", "p95", "p99"] {
+            headers.push(format!("transaction_latency_{}", statistic));
+        }
+        headers.push("stake_weighted_committed_blocks".to_string());
+        headers.push("fast_path_rate".to_string());
+        for name in &["messages", "bytes", "signatures", "verifications", "macs"] {
+            headers.push(format!("{}_per_commit", name));
+        }
+        writer.write_record(&headers)?;
+        let aggregate = &self.aggregate;
+        let mut record = vec![
+            self.schema_version.to_string(),
+            aggregate.duration.to_string(),
+            aggregate.committed_blocks.to_string(),
+            aggregate.commit_throughput.to_string(),
+            aggregate.timeout_rate.to_string(),
+        ];
+        record.extend(distribution_fields(&aggregate.commit_latency));
+        record.extend(distribution_fields(&aggregate.rounds_per_commit));
+        let quality = &aggregate.chain_quality;
+        record.push(quality.honest_blocks.to_string());
+        record.push(quality.byzantine_blocks.to_string());
+        record.push(quality.longest_byzantine_span.to_string());
+        record.extend(distribution_fields(&aggregate.wasted_rounds));
+        record.push(aggregate.transaction_throughput.to_string());
+        record.extend(distribution_fields(&aggregate.transaction_latency));
+        record.push(aggregate.stake_weighted_committed_blocks.to_string());
+        record.push(aggregate.fast_path_rate.to_string());
+        let costs = &aggregate.resource_costs;
+        record.push(optional_field(&costs.messages_per_commit()));
+        record.push(optional_field(&costs.bytes_per_commit()));
+        record.push(optional_field(&costs.signatures_per_commit()));
+        record.push(optional_field(&costs.verifications_per_commit()));
+        record.push(optional_field(&costs.macs_per_commit()));
+        writer.write_record(&record)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_nodes_csv<W: io::Write>(&self, output: W) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(output);
+        writer.write_record(&[
+            "schema_version",
+            "author",
+            "active_round",
+            "committed_blocks",
+            "highest_committed_round",
+            "quorum_certificate_rounds",
+            "timeout_certificate_rounds",
+            "mean_commit_latency",
+            "crashed",
+            "messages_sent",
+            "messages_received",
+            "records_sent",
+            "records_received",
+            "timeouts_as_leader",
+            "stake",
+            "errors",
+            "pacemaker_switches",
+            "pacemaker_switch_times",
+            "evicted_records",
+            "suppressed_notifications",
+            "corrupted_messages",
+            "rejected_messages",
+            "over_quota_messages",
+            "suspicions",
+            "rejected_proposals",
+        ])?;
+        for node in &self.nodes {
+            writer.write_record(&[
+                self.schema_version.to_string(),
+                node.author.to_string(),
+                node.active_round.to_string(),
+                node.committed_blocks.to_string(),
+                optional_field(&node.highest_committed_round),
+                node.quorum_certificate_rounds.to_string(),
+                node.timeout_certificate_rounds.to_string(),
+                optional_field(&node.mean_commit_latency),
+                node.crashed.to_string(),
+                node.messages_sent.to_string(),
+                node.messages_received.to_string(),
+                node.records_sent.to_string(),
+                node.records_received.to_string(),
+                node.timeouts_as_leader.to_string(),
+                node.stake.to_string(),
+                node.errors.to_string(),
+                node.pacemaker_switches.to_string(),
+                node.pacemaker_switch_times
+                    .iter()
+                    .map(|time| time.0.to_string())
+                    .collect::<Vec<_>>()
+                    .join(";"),
+                node.evicted_records.to_string(),
+                node.suppressed_notifications.to_string(),
+                node.corrupted_messages.to_string(),
+                node.rejected_messages.to_string(),
+                node.over_quota_messages.to_string(),
+                node.suspicions.to_string(),
+                node.rejected_proposals.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn optional_field<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+fn distribution_fields(distribution: &Option<Distribution>) -> Vec<String> {
+    match distribution {
+        Some(x) => vec![
+            x.count.to_string(),
+            x.min.to_string(),
+            x.max.to_string(),
+            x.mean.to_string(),
+            x.p50.to_string(),
+            x.p95.to_string(),
+            x.p99.to_string(),
+        ],
+        None => vec![String::new(); 7],
+    }
+}
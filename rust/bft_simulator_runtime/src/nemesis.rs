@@ -0,0 +1,242 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! External fault injection, after the nemesis of Jepsen.
+//!
+//! A nemesis is registered with `Simulator::add_nemesis` and polled before every event with the
+//! time of the event. The faults that it returns are applied at that time, before the event is
+//! processed, so that fault schedules can be written outside of the simulator: as closures, or
+//! as controllers on other threads sending faults over a channel.
+
+use crate::{
+    base_types::{Author, Duration, Result, Round},
+    simulator::GlobalTime,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::mpsc::Receiver};
+
+#[cfg(test)]
+#[path = "unit_tests/nemesis_tests.rs"]
+mod nemesis_tests;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Fault {
+    /// Stop processing the events of a node, as with `Simulator::crash_node`.
+    Crash(Author),
+    /// Restart a crashed node. No effect on nodes that are running.
+    Recover(Author),
+    /// Split the network into groups of nodes that cannot exchange messages, including messages
+    /// already in flight. Nodes missing from the groups form one more group. Replaces the
+    /// previous partition, if any.
+    Partition(Vec<Vec<Author>>),
+    /// Remove the partition of the network.
+    Heal,
+    /// Move the local clock of a node forward, or backward for negative durations.
+    ClockJump(Author, Duration),
+    /// Let an adversary control all the links of a node, the victim. Replaces the previous
+    /// eclipse of the victim, if any.
+    Eclipse(Author, Eclipse),
+    /// Give the victim of an eclipse its links back. Messages in flight are not affected.
+    EndEclipse(Author),
+    /// Let a node flood the others with messages. Replaces the previous flood of the node, if
+    /// any.
+    Flood(Author, Flood),
+    /// Stop the flood of a node. Messages in flight are not affected.
+    EndFlood(Author),
+}
+
+/// How an adversary controlling all the links of a victim distorts its view of the network.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Eclipse {
+    /// Extra delay of the messages between the victim and the other nodes.
+    pub delay: Duration,
+    /// Probability that a message between the victim and another node is dropped.
+    pub drop_rate: f64,
+    /// Nodes whose messages with the victim go through untouched, e.g. nodes of the adversary.
+    pub relayed: Vec<Author>,
+}
+
+/// Messages that a flooding node sends to every other node, on top of its own traffic.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Flood {
+    /// Time between bursts of messages.
+    pub period: Duration,
+    /// Copies of the current notification of the node sent to each node per burst.
+    pub notifications: usize,
+    /// Copies of a data sync request sent to each node per burst, each asking for a response.
+    pub requests: usize,
+}
+
+/// Time taken by the victim of an eclipse to catch up once the eclipse ended.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct EclipseRecovery {
+    pub victim: Author,
+    pub end_time: GlobalTime,
+    /// Highest round of the other nodes when the eclipse ended.
+    pub target_round: Round,
+    /// Time at which the victim reached `target_round`, if it did.
+    pub recovery_time: Option<GlobalTime>,
+}
+
+impl Default for Eclipse {
+    fn default() -> Self {
+        Eclipse {
+            delay: Duration::from_millis(0),
+            drop_rate: 1.0,
+            relayed: Vec::new(),
+        }
+    }
+}
+
+impl Eclipse {
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            !self.delay.is_negative(),
+            "Eclipse delays cannot be negative"
+        );
+        ensure!(
+            self.drop_rate >= 0.0 && self.drop_rate <= 1.0,
+            "Drop rates must be between 0 and 1"
+        );
+        Ok(())
+    }
+
+    /// Whether the messages between the victim and `peer` are distorted.
+    pub fn controls(&self, peer: Author) -> bool {
+        !self.relayed.contains(&peer)
+    }
+}
+
+impl Default for Flood {
+    fn default() -> Self {
+        Flood {
+            period: Duration::from_millis(1),
+            notifications: 1,
+            requests: 0,
+        }
+    }
+}
+
+impl Flood {
+    pub fn check(&self) -> Result<()> {
+        ensure!(self.period.is_positive(), "Flood periods must be positive");
+        Ok(())
+    }
+}
+
+impl Fault {
+    /// The node that the fault targets, if it targets a single node.
+    pub fn author(&self) -> Option<Author> {
+        match self {
+            Fault::Crash(author)
+            | Fault::Recover(author)
+            | Fault::ClockJump(author, _)
+            | Fault::Eclipse(author, _)
+            | Fault::EndEclipse(author)
+            | Fault::Flood(author, _)
+            | Fault::EndFlood(author) => Some(*author),
+            Fault::Partition(_) | Fault::Heal => None,
+        }
+    }
+}
+
+/// A short description of the fault, e.g. in reports.
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::Crash(author) => write!(f, "crash of node {}", author.0),
+            Fault::Recover(author) => write!(f, "recovery of node {}", author.0),
+            Fault::Partition(groups) => {
+                let groups: Vec<_> = groups
+                    .iter()
+                    .map(|group| group.iter().map(|x| x.0).collect::<Vec<_>>())
+                    .collect();
+                write!(f, "partition {:?}", groups)
+            }
+            Fault::Heal => write!(f, "end of the partition"),
+            Fault::ClockJump(author, jump) => {
+                write!(f, "clock jump of node {} by {:?}", author.0, jump)
+            }
+            Fault::Eclipse(author, _) => write!(f, "eclipse of node {}", author.0),
+            Fault::EndEclipse(author) => write!(f, "end of the eclipse of node {}", author.0),
+            Fault::Flood(author, _) => write!(f, "flood from node {}", author.0),
+            Fault::EndFlood(author) => write!(f, "end of the flood from node {}", author.0),
+        }
+    }
+}
+
+impl EclipseRecovery {
+    /// Time between the end of the eclipse and the recovery of the victim, if it recovered.
+    pub fn duration(&self) -> Option<Duration> {
+        self.recovery_time.map(|time| time - self.end_time)
+    }
+}
+
+pub trait Nemesis {
+    /// Faults to inject at time `time`, before processing the next event.
+    fn faults(&mut self, time: GlobalTime) -> Vec<Fault>;
+}
+
+impl<F> Nemesis for F
+where
+    F: FnMut(GlobalTime) -> Vec<Fault>,
+{
+    fn faults(&mut self, time: GlobalTime) -> Vec<Fault> {
+        self(time)
+    }
+}
+
+/// Faults sent to the channel are injected as soon as the simulator receives them.
+impl Nemesis for Receiver<Fault> {
+    fn faults(&mut self, _time: GlobalTime) -> Vec<Fault> {
+        self.try_iter().collect()
+    }
+}
+
+/// Faults injected at given times, e.g. as described by a scenario.
+#[derive(Clone, Debug, Default)]
+pub struct FaultSchedule {
+    /// Faults in increasing order of time.
+    faults: Vec<(GlobalTime, Fault)>,
+    /// Index of the first fault not injected yet.
+    next: usize,
+}
+
+impl FaultSchedule {
+    /// Faults at the same time are injected in the given order.
+    pub fn new(mut faults: Vec<(GlobalTime, Fault)>) -> Self {
+        faults.sort_by_key(|(time, _)| *time);
+        FaultSchedule { faults, next: 0 }
+    }
+}
+
+/// Faults are injected at the first event at or after their time.
+impl Nemesis for FaultSchedule {
+    fn faults(&mut self, time: GlobalTime) -> Vec<Fault> {
+        let mut faults = Vec::new();
+        while self.next < self.faults.len() && self.faults[self.next].0 <= time {
+            faults.push(self.faults[self.next].1.clone());
+            self.next += 1;
+        }
+        faults
+    }
+}
+
+/// The group of each of the `num_nodes` nodes in a partition. Fails if a group contains an
+/// unknown node.
+pub fn partition_groups(num_nodes: usize, groups: &[Vec<Author>]) -> Result<Vec<usize>> {
+    let mut result = vec![groups.len(); num_nodes];
+    for (index, group) in groups.iter().enumerate() {
+        for author in group {
+            ensure!(
+                author.0 < num_nodes,
+                "Partitions cannot contain the unknown node {}",
+                author.0
+            );
+            result[author.0] = index;
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,430 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Nodes hosted on real sockets, with the `tokio` feature.
+//!
+//! Each node runs as a task of a single-threaded tokio runtime, with the same protocol logic as
+//! in simulations. Nodes exchange their data-sync messages over TCP, serialized as one JSON value
+//! per line. Local times are read from a `clock::RealTimeClock`, e.g. a `WallClock` counting
+//! milliseconds since the start of the node. Messages that cannot be delivered are lost, and
+//! connections are opened again for the next messages. Given a metrics address, a node also
+//! serves its metrics in the Prometheus text format over HTTP.
+
+use crate::{
+    base_types::{Author, NodeTime, Result},
+    clock::RealTimeClock,
+    message_counters::{CountRecords, MessageKind, NodeMessageCounters, RecordCounts},
+    prometheus::{self, NodeSample},
+    ActiveRound, ConsensusNode, DataSyncNode, NodeErrors, NodeStatistics, NodeUpdateActions,
+    PeerTracker,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    io,
+    net::SocketAddr,
+    rc::Rc,
+    time::{Duration as StdDuration, Instant},
+};
+use tokio::{
+    codec::{FramedRead, FramedWrite, LinesCodec},
+    io as async_io,
+    net::{TcpListener, TcpStream},
+    prelude::*,
+    runtime::current_thread,
+    sync::mpsc,
+    timer::Delay,
+};
+
+/// A message between two nodes, as sent on the wire.
+#[derive(Eq, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WireMessage<Notification, Request, Response> {
+    Notification {
+        sender: Author,
+        notification: Notification,
+    },
+    Request {
+        sender: Author,
+        request: Request,
+    },
+    Response {
+        sender: Author,
+        response: Response,
+    },
+}
+
+impl<Notification, Request, Response> WireMessage<Notification, Request, Response>
+where
+    Notification: CountRecords,
+    Request: CountRecords,
+    Response: CountRecords,
+{
+    fn counts(&self) -> (MessageKind, RecordCounts) {
+        match self {
+            WireMessage::Notification { notification, .. } => {
+                (MessageKind::Notification, notification.record_counts())
+            }
+            WireMessage::Request { request, .. } => {
+                (MessageKind::SyncRequest, request.record_counts())
+            }
+            WireMessage::Response { response, .. } => {
+                (MessageKind::SyncResponse, response.record_counts())
+            }
+        }
+    }
+
+    fn sender(&self) -> Author {
+        match self {
+            WireMessage::Notification { sender, .. }
+            | WireMessage::Request { sender, .. }
+            | WireMessage::Response { sender, .. } => *sender,
+        }
+    }
+}
+
+/// Inputs of the main loop of a node.
+enum Input {
+    /// A line received from another node.
+    Message(String),
+    /// A timer set for the given local time.
+    Timer(NodeTime),
+}
+
+struct Host<Node, Context> {
+    author: Author,
+    node: Node,
+    context: Context,
+    /// Address of each node, by author.
+    addresses: Vec<SocketAddr>,
+    /// Outgoing connections, opened on demand.
+    peers: Vec<Option<mpsc::UnboundedSender<String>>>,
+    inputs: mpsc::UnboundedSender<Input>,
+    clock: Box<RealTimeClock>,
+    /// Local time of the next scheduled update.
+    next_update: NodeTime,
+    /// Messages sent, including the ones that could not be delivered, and received.
+    counters: NodeMessageCounters,
+}
+
+impl<Node, Context> Host<Node, Context>
+where
+    Node: ConsensusNode<Context> + DataSyncNode<Context> + NodeErrors + PeerTracker,
+    Node::Notification: Serialize + DeserializeOwned + CountRecords,
+    Node::Request: Serialize + DeserializeOwned + CountRecords,
+    Node::Response: Serialize + DeserializeOwned + CountRecords,
+{
+    fn clock(&self) -> NodeTime {
+        self.clock.now()
+    }
+
+    fn others(&self) -> Vec<Author> {
+        (0..self.addresses.len())
+            .map(Author)
+            .filter(|author| *author != self.author)
+            .collect()
+    }
+
+    /// Serialize a message sent `copies` times.
+    fn encode(
+        &mut self,
+        message: &WireMessage<Node::Notification, Node::Request, Node::Response>,
+        copies: usize,
+    ) -> Option<String> {
+        let (kind, records) = message.counts();
+        for _ in 0..copies {
+            self.counters.sent.add_message(kind, records);
+        }
+        match serde_json::to_string(message) {
+            Ok(line) => Some(line),
+            Err(error) => {
+                error!("Failed to serialize a message: {}", error);
+                None
+            }
+        }
+    }
+
+    /// Send a line to another node, opening a connection if there is none.
+    fn send(&mut self, receiver: Author, line: String) {
+        let line = match &mut self.peers[receiver.0] {
+            Some(peer) => match peer.try_send(line) {
+                Ok(()) => return,
+                // The connection was closed.
+                Err(error) => error.into_inner(),
+            },
+            None => line,
+        };
+        let (mut peer, lines) = mpsc::unbounded_channel();
+        if peer.try_send(line).is_err() {
+            return;
+        }
+        let address = self.addresses[receiver.0];
+        current_thread::spawn(
+            TcpStream::connect(&address)
+                .and_then(move |stream| {
+                    lines
+                        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+                        .forward(FramedWrite::new(stream, LinesCodec::new()))
+                        .map(|_| ())
+                })
+                .map_err(move |error| debug!("Connection to {} lost: {}", address, error)),
+        );
+        self.peers[receiver.0] = Some(peer);
+    }
+
+    fn schedule_update(&mut self, deadline: NodeTime) {
+        if deadline == self.next_update || deadline == NodeTime::never() {
+            self.next_update = deadline;
+            return;
+        }
+        self.next_update = deadline;
+        let instant = self.clock.instant_at(deadline);
+        let mut inputs = self.inputs.clone();
+        current_thread::spawn(
+            Delay::new(instant)
+                .then(move |_| inputs.try_send(Input::Timer(deadline)).map_err(|_| ())),
+        );
+    }
+
+    fn process_node_actions(&mut self, actions: NodeUpdateActions) {
+        self.schedule_update(actions.next_scheduled_update);
+        let mut receivers: BTreeSet<_> = actions.should_send.into_iter().collect();
+        if actions.should_broadcast {
+            receivers.extend(self.others());
+        }
+        receivers.remove(&self.author);
+        for author in &actions.should_skip {
+            receivers.remove(author);
+        }
+        if !receivers.is_empty() {
+            let message = WireMessage::Notification {
+                sender: self.author,
+                notification: self.node.create_notification(),
+            };
+            if let Some(line) = self.encode(&message, receivers.len()) {
+                for receiver in receivers {
+                    self.send(receiver, line.clone());
+                }
+            }
+        }
+        let mut senders: BTreeSet<_> = actions.should_query.into_iter().collect();
+        if actions.should_query_all {
+            senders.extend(self.others());
+        }
+        senders.remove(&self.author);
+        if !senders.is_empty() {
+            let message = WireMessage::Request {
+                sender: self.author,
+                request: self.node.create_request(),
+            };
+            if let Some(line) = self.encode(&message, senders.len()) {
+                for sender in senders {
+                    self.send(sender, line.clone());
+                }
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        let clock = self.clock();
+        let actions = self.node.update_node(clock, &mut self.context);
+        for error in self.node.take_errors() {
+            warn!("Error of node {:?}: {}", self.author, error);
+        }
+        self.process_node_actions(actions);
+    }
+
+    fn handle_input(&mut self, input: Input) {
+        let line = match input {
+            Input::Timer(deadline) => {
+                // Later updates cancel the earlier ones.
+                if deadline == self.next_update {
+                    self.next_update = NodeTime::never();
+                    self.update();
+                }
+                return;
+            }
+            Input::Message(line) => line,
+        };
+        let message: WireMessage<Node::Notification, Node::Request, Node::Response> =
+            match serde_json::from_str(&line) {
+                Ok(message) => message,
+                Err(error) => {
+                    warn!("Dropping an invalid message: {}", error);
+                    return;
+                }
+            };
+        let (kind, records) = message.counts();
+        self.counters.received.add_message(kind, records);
+        // Peers are identified by the envelopes of their messages.
+        self.node.receive_message(message.sender(), kind);
+        match message {
+            WireMessage::Notification {
+                sender,
+                notification,
+            } => {
+                let request =
+                    self.node
+                        .handle_notification(sender, &notification, &mut self.context);
+                if let Some(request) = request {
+                    let message = WireMessage::Request {
+                        sender: self.author,
+                        request,
+                    };
+                    if let Some(line) = self.encode(&message, 1) {
+                        self.send(sender, line);
+                    }
+                }
+                self.update();
+            }
+            WireMessage::Request { sender, request } => {
+                let message = WireMessage::Response {
+                    sender: self.author,
+                    response: self.node.handle_request(&request),
+                };
+                if let Some(line) = self.encode(&message, 1) {
+                    self.send(sender, line);
+                }
+            }
+            WireMessage::Response { sender, response } => {
+                let clock = self.clock();
+                self.node
+                    .handle_response(sender, response, &mut self.context, clock);
+                self.update();
+            }
+        }
+    }
+}
+
+/// An HTTP response carrying metrics in the Prometheus text format.
+fn metrics_response(body: String) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// Answer every HTTP request on `listener` with the metrics of the node.
+fn serve_metrics<Node, Context>(
+    listener: TcpListener,
+    host: Rc<RefCell<Host<Node, Context>>>,
+) -> impl Future<Item = (), Error = ()>
+where
+    Node: ActiveRound + NodeStatistics + 'static,
+    Context: 'static,
+{
+    listener
+        .incoming()
+        .map_err(|error| warn!("Failed to accept a metrics connection: {}", error))
+        .for_each(move |stream| {
+            let host = host.clone();
+            current_thread::spawn(
+                // Requests are not parsed: every path serves the metrics.
+                async_io::read(stream, vec![0; 1024])
+                    .and_then(move |(stream, _, _)| {
+                        let body = {
+                            let host = host.borrow();
+                            let sample = NodeSample::of(host.author, &host.node, host.counters);
+                            prometheus::render(&[sample])
+                        };
+                        async_io::write_all(stream, metrics_response(body))
+                    })
+                    .map(|_| ())
+                    .map_err(|error| debug!("Metrics connection closed: {}", error)),
+            );
+            Ok(())
+        })
+}
+
+/// Run a node for `duration` on the address of its author in `addresses`, together with the
+/// nodes at the other addresses, and serve its metrics on `metrics_address` if any. The local
+/// time of the node is read from `clock`. Returns the node and its context at the end of the run.
+pub fn run_node<Node, Context>(
+    author: Author,
+    node: Node,
+    context: Context,
+    clock: Box<RealTimeClock>,
+    addresses: Vec<SocketAddr>,
+    metrics_address: Option<SocketAddr>,
+    duration: StdDuration,
+) -> Result<(Node, Context)>
+where
+    Node: ConsensusNode<Context>
+        + DataSyncNode<Context>
+        + ActiveRound
+        + NodeErrors
+        + NodeStatistics
+        + PeerTracker
+        + 'static,
+    Node::Notification: Serialize + DeserializeOwned + CountRecords,
+    Node::Request: Serialize + DeserializeOwned + CountRecords,
+    Node::Response: Serialize + DeserializeOwned + CountRecords,
+    Context: 'static,
+{
+    ensure!(
+        author.0 < addresses.len(),
+        "No address for node {:?}",
+        author
+    );
+    let mut runtime = current_thread::Runtime::new()?;
+    let listener = TcpListener::bind(&addresses[author.0])?;
+    let (inputs, received_inputs) = mpsc::unbounded_channel();
+    let host = Rc::new(RefCell::new(Host {
+        author,
+        node,
+        context,
+        peers: vec![None; addresses.len()],
+        addresses,
+        inputs: inputs.clone(),
+        clock,
+        next_update: NodeTime::never(),
+        counters: NodeMessageCounters::default(),
+    }));
+    if let Some(address) = metrics_address {
+        runtime.spawn(serve_metrics(TcpListener::bind(&address)?, host.clone()));
+    }
+    // Forward the lines received on every connection to the main loop.
+    runtime.spawn(
+        listener
+            .incoming()
+            .map_err(|error| warn!("Failed to accept a connection: {}", error))
+            .for_each(move |stream| {
+                let mut inputs = inputs.clone();
+                current_thread::spawn(
+                    FramedRead::new(stream, LinesCodec::new())
+                        .map_err(|error| debug!("Connection closed: {}", error))
+                        .for_each(move |line| {
+                            inputs.try_send(Input::Message(line)).map_err(|_| ())
+                        }),
+                );
+                Ok(())
+            }),
+    );
+    let main_host = host.clone();
+    let main_loop = future::lazy(move || {
+        main_host.borrow_mut().update();
+        received_inputs.map_err(|_| ()).for_each(move |input| {
+            main_host.borrow_mut().handle_input(input);
+            Ok(())
+        })
+    });
+    let end = Delay::new(Instant::now() + duration).map_err(|_| ());
+    if runtime
+        .block_on(main_loop.select(end).map(|_| ()).map_err(|_| ()))
+        .is_err()
+    {
+        bail!("The main loop of node {:?} failed", author);
+    }
+    // Dropping the runtime drops the other references to the host.
+    drop(runtime);
+    let host = match Rc::try_unwrap(host) {
+        Ok(host) => host.into_inner(),
+        Err(_) => bail!("Node {:?} is still in use", author),
+    };
+    Ok((host.node, host.context))
+}
@@ -0,0 +1,174 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Network conditions changing over time, e.g. to replay a recorded network incident.
+//!
+//! Traces are CSV files with the columns `time,mean,variance,loss_rate`. Each row changes the
+//! network delays or losses of the messages sent from its time on, and empty fields keep the
+//! previous values. Optional `ramp` and `steps` columns reach the new values gradually instead:
+//! linearly over `ramp` units of time, or in `steps` equal steps. Example:
+//! ```text
+//! time,mean,variance,loss_rate,ramp,steps
+//! 10000,50.0,,,,
+//! 30000,,,0.2,10000,
+//! 45000,10.0,4.0,0.0,5000,5
+//! ```
+
+use crate::{
+    base_types::{Duration, Result},
+    simulator::GlobalTime,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+#[cfg(test)]
+#[path = "unit_tests/network_trace_tests.rs"]
+mod network_trace_tests;
+
+/// New network conditions from time `time` on. Missing values do not change.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkChange {
+    pub time: GlobalTime,
+    /// Mean of the log-normal network delays.
+    pub mean: Option<f64>,
+    /// Variance of the log-normal network delays.
+    pub variance: Option<f64>,
+    /// Probability that a message is lost.
+    pub loss_rate: Option<f64>,
+    /// Duration over which the values change gradually from the previous ones, if any.
+    #[serde(default)]
+    pub ramp: Option<Duration>,
+    /// Number of equal steps of the ramp. The ramp is linear if absent.
+    #[serde(default)]
+    pub steps: Option<usize>,
+}
+
+/// Network delays and losses.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct NetworkConditions {
+    pub mean: f64,
+    pub variance: f64,
+    pub loss_rate: f64,
+}
+
+/// Gradual change of the network conditions, started by a change with a ramp.
+#[derive(PartialEq, Clone, Debug)]
+pub struct NetworkRamp {
+    start: GlobalTime,
+    duration: Duration,
+    steps: Option<usize>,
+    from: NetworkConditions,
+    to: NetworkConditions,
+}
+
+/// Changes of network conditions, in increasing order of time.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct NetworkTrace {
+    changes: Vec<NetworkChange>,
+}
+
+impl NetworkChange {
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.mean
+                .map_or(true, |mean| mean > 0.0 && mean.is_finite()),
+            "Network delays need a positive and finite mean"
+        );
+        ensure!(
+            self.variance
+                .map_or(true, |variance| variance >= 0.0 && variance.is_finite()),
+            "Network delays need a non-negative and finite variance"
+        );
+        ensure!(
+            self.loss_rate
+                .map_or(true, |loss_rate| loss_rate >= 0.0 && loss_rate <= 1.0),
+            "Loss rates must be between 0 and 1"
+        );
+        ensure!(
+            self.ramp.map_or(true, Duration::is_positive),
+            "Ramps must last a positive duration"
+        );
+        ensure!(
+            self.steps
+                .map_or(true, |steps| steps > 0 && self.ramp.is_some()),
+            "Steps need a ramp, and at least one step"
+        );
+        Ok(())
+    }
+
+    /// Conditions once the change is complete, starting from `conditions`.
+    pub fn apply(&self, conditions: NetworkConditions) -> NetworkConditions {
+        NetworkConditions {
+            mean: self.mean.unwrap_or(conditions.mean),
+            variance: self.variance.unwrap_or(conditions.variance),
+            loss_rate: self.loss_rate.unwrap_or(conditions.loss_rate),
+        }
+    }
+}
+
+impl NetworkRamp {
+    /// The ramp of `change` starting from `from`, if the change is gradual.
+    pub fn new(change: &NetworkChange, from: NetworkConditions) -> Option<Self> {
+        change.ramp.map(|duration| NetworkRamp {
+            start: change.time,
+            duration,
+            steps: change.steps,
+            from,
+            to: change.apply(from),
+        })
+    }
+
+    /// Time at which the conditions reach their final values.
+    pub fn end(&self) -> GlobalTime {
+        self.start + self.duration
+    }
+
+    pub fn conditions_at(&self, time: GlobalTime) -> NetworkConditions {
+        let elapsed = std::cmp::min(
+            std::cmp::max(time - self.start, Duration::from_millis(0)),
+            self.duration,
+        );
+        let mut progress = elapsed.as_millis_f64() / self.duration.as_millis_f64();
+        if let Some(steps) = self.steps {
+            progress = (progress * steps as f64).floor() / steps as f64;
+        }
+        let interpolate = |from: f64, to: f64| from + (to - from) * progress;
+        NetworkConditions {
+            mean: interpolate(self.from.mean, self.to.mean),
+            variance: interpolate(self.from.variance, self.to.variance),
+            loss_rate: interpolate(self.from.loss_rate, self.to.loss_rate),
+        }
+    }
+}
+
+impl NetworkTrace {
+    /// Check and sort the changes. Changes at the same time apply in the given order.
+    pub fn new(mut changes: Vec<NetworkChange>) -> Result<Self> {
+        for change in &changes {
+            change.check()?;
+        }
+        changes.sort_by_key(|change| change.time);
+        Ok(NetworkTrace { changes })
+    }
+
+    /// Read a trace in CSV format.
+    pub fn from_csv<R: io::Read>(reader: R) -> Result<Self> {
+        let changes = csv::Reader::from_reader(reader)
+            .deserialize()
+            .collect::<std::result::Result<_, _>>()?;
+        Self::new(changes)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_csv(fs::File::open(path)?)
+    }
+
+    pub fn changes(&self) -> &[NetworkChange] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
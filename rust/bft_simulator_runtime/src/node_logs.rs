@@ -0,0 +1,212 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Logging verbosity and capture per simulated node.
+//!
+//! The simulator marks the node whose handlers process each event. While a node is marked,
+//! records of the `log` crate, including the events of nodes instrumented with `tracing` when no
+//! tracing subscriber is installed, go to the output configured for the node, at the level
+//! configured for the node. Other records go to the default logger.
+
+use crate::base_types::{Author, Result};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    fs::File,
+    io::{LineWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+};
+
+#[cfg(test)]
+#[path = "unit_tests/node_logs_tests.rs"]
+mod node_logs_tests;
+
+thread_local! {
+    static CURRENT_NODE: Cell<Option<Author>> = Cell::new(None);
+}
+
+/// Where the records of a node go.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum NodeLogOutput {
+    /// Printed to stderr, prefixed with the node.
+    Stderr,
+    /// Kept in memory, see `NodeLogs::captured`.
+    Buffer,
+    File(PathBuf),
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct NodeLogConfig {
+    pub author: Author,
+    pub level: LevelFilter,
+    pub output: NodeLogOutput,
+}
+
+impl FromStr for NodeLogConfig {
+    type Err = String;
+
+    /// Parse `AUTHOR:LEVEL`, printed to stderr, or `AUTHOR:LEVEL:FILE`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let fields: Vec<_> = s.splitn(3, ':').collect();
+        let error = || format!("Invalid node log: {} (expected AUTHOR:LEVEL[:FILE])", s);
+        if fields.len() < 2 {
+            return Err(error());
+        }
+        Ok(NodeLogConfig {
+            author: Author(fields[0].parse().map_err(|_| error())?),
+            level: fields[1].parse().map_err(|_| error())?,
+            output: match fields.get(2) {
+                Some(path) => NodeLogOutput::File(PathBuf::from(path)),
+                None => NodeLogOutput::Stderr,
+            },
+        })
+    }
+}
+
+enum Sink {
+    Stderr,
+    Buffer(Vec<String>),
+    /// Flushed after each line, since the logger is never dropped.
+    File(LineWriter<File>),
+}
+
+struct NodeLog {
+    level: LevelFilter,
+    sink: Mutex<Sink>,
+}
+
+/// A logger dispatching records to the node marked by the simulator.
+pub struct NodeLogs {
+    default: Box<Log>,
+    default_level: LevelFilter,
+    nodes: BTreeMap<Author, NodeLog>,
+}
+
+impl NodeLogs {
+    /// Configure some nodes on top of a default logger, which filters records at `default_level`
+    /// at most. Files are created at once.
+    pub fn new(
+        default: Box<Log>,
+        default_level: LevelFilter,
+        configs: &[NodeLogConfig],
+    ) -> Result<Self> {
+        let mut nodes = BTreeMap::new();
+        for config in configs {
+            let sink = match &config.output {
+                NodeLogOutput::Stderr => Sink::Stderr,
+                NodeLogOutput::Buffer => Sink::Buffer(Vec::new()),
+                NodeLogOutput::File(path) => Sink::File(LineWriter::new(File::create(path)?)),
+            };
+            let log = NodeLog {
+                level: config.level,
+                sink: Mutex::new(sink),
+            };
+            nodes.insert(config.author, log);
+        }
+        Ok(NodeLogs {
+            default,
+            default_level,
+            nodes,
+        })
+    }
+
+    /// Most verbose level of the default logger and the nodes.
+    pub fn max_level(&self) -> LevelFilter {
+        self.nodes
+            .values()
+            .map(|log| log.level)
+            .fold(self.default_level, std::cmp::max)
+    }
+
+    /// Records kept in memory for the node `author`.
+    pub fn captured(&self, author: Author) -> Vec<String> {
+        match self.nodes.get(&author) {
+            Some(log) => match &*log.sink.lock().unwrap() {
+                Sink::Buffer(lines) => lines.clone(),
+                _ => Vec::new(),
+            },
+            None => Vec::new(),
+        }
+    }
+
+    /// Install as the global logger, for the rest of the process.
+    pub fn install(self) -> Result<&'static NodeLogs> {
+        let logs: &'static NodeLogs = Box::leak(Box::new(self));
+        log::set_logger(logs).map_err(|error| format_err!("{}", error))?;
+        log::set_max_level(logs.max_level());
+        Ok(logs)
+    }
+}
+
+impl Log for NodeLogs {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match current_node().and_then(|author| self.nodes.get(&author)) {
+            Some(log) => metadata.level() <= log.level,
+            None => self.default.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        let author = match current_node() {
+            Some(author) => author,
+            None => return self.default.log(record),
+        };
+        let log = match self.nodes.get(&author) {
+            Some(log) => log,
+            None => return self.default.log(record),
+        };
+        if record.level() > log.level {
+            return;
+        }
+        let line = format!("{} {} {}", record.level(), record.target(), record.args());
+        match &mut *log.sink.lock().unwrap() {
+            Sink::Stderr => eprintln!("[node {}] {}", author.0, line),
+            Sink::Buffer(lines) => lines.push(line),
+            Sink::File(file) => {
+                // Logging must not fail the run.
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.default.flush();
+        for log in self.nodes.values() {
+            if let Sink::File(file) = &mut *log.sink.lock().unwrap() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/// Set up the default logger from `RUST_LOG`, as `env_logger` does, and the given nodes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init(configs: &[NodeLogConfig]) -> Result<&'static NodeLogs> {
+    let default = env_logger::Builder::from_env(env_logger::Env::default()).build();
+    let default_level = default.filter();
+    NodeLogs::new(Box::new(default), default_level, configs)?.install()
+}
+
+/// Node whose handlers are running on this thread, if any.
+pub fn current_node() -> Option<Author> {
+    CURRENT_NODE.with(Cell::get)
+}
+
+/// Mark `author` as the running node until the returned guard is dropped.
+pub fn enter(author: Author) -> NodeLogScope {
+    let previous = CURRENT_NODE.with(|node| node.replace(Some(author)));
+    NodeLogScope { previous }
+}
+
+pub struct NodeLogScope {
+    previous: Option<Author>,
+}
+
+impl Drop for NodeLogScope {
+    fn drop(&mut self) {
+        CURRENT_NODE.with(|node| node.set(self.previous));
+    }
+}
@@ -0,0 +1,38 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Callbacks invoked by the simulator on the main events of a run.
+//!
+//! Observers are registered with `Simulator::add_observer` and see every delivered message,
+//! node update, round change, and commit, in the order of the simulation. Metrics, checkers,
+//! trace exports, or user interfaces can be written as observers instead of changing the main
+//! loop of the simulator. All callbacks do nothing by default.
+
+use crate::{
+    base_types::{Author, Round},
+    message_counters::MessageKind,
+    simulator::GlobalTime,
+    CommittedBlock,
+};
+
+pub trait SimulationObserver {
+    /// Called when a message from `sender` is handed to the handlers of `receiver`. Messages
+    /// lost by the network or dropped by stopped nodes and partitions are not reported.
+    fn on_message_delivered(
+        &mut self,
+        _sender: Author,
+        _receiver: Author,
+        _kind: MessageKind,
+        _time: GlobalTime,
+    ) {
+    }
+
+    /// Called after the node `author` has updated its state, e.g. on a timer or a message.
+    fn on_node_updated(&mut self, _author: Author, _time: GlobalTime) {}
+
+    /// Called when the active round of the node `author` has changed to `round`.
+    fn on_round_change(&mut self, _author: Author, _round: Round, _time: GlobalTime) {}
+
+    /// Called when the node `author` has committed `block`, in the order of its commits.
+    fn on_commit(&mut self, _author: Author, _block: &CommittedBlock, _time: GlobalTime) {}
+}
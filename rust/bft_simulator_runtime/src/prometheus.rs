@@ -0,0 +1,151 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metrics of running nodes in the Prometheus text format.
+//!
+//! Nodes hosted on sockets serve these metrics over HTTP when they are given a metrics address,
+//! so that standard monitoring dashboards can follow long deployments. Each sample is labeled
+//! with the author of the node.
+
+use crate::{
+    base_types::{Author, Round},
+    message_counters::{MessageCounts, NodeMessageCounters},
+    ActiveRound, NodeStatistics,
+};
+use std::fmt::Write as _;
+
+#[cfg(test)]
+#[path = "unit_tests/prometheus_tests.rs"]
+mod prometheus_tests;
+
+/// Values exported for a node when metrics are scraped.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct NodeSample {
+    pub author: Author,
+    pub active_round: Round,
+    /// Round of the latest committed block, or 0.
+    pub committed_round: Round,
+    pub committed_blocks: usize,
+    pub quorum_certificate_rounds: usize,
+    pub timeout_certificate_rounds: usize,
+    pub counters: NodeMessageCounters,
+}
+
+impl NodeSample {
+    pub fn of<Node>(author: Author, node: &Node, counters: NodeMessageCounters) -> Self
+    where
+        Node: ActiveRound + NodeStatistics,
+    {
+        let committed_blocks = node.committed_blocks();
+        NodeSample {
+            author,
+            active_round: node.active_round(),
+            committed_round: committed_blocks
+                .last()
+                .map_or(Round(0), |block| block.round),
+            committed_blocks: committed_blocks.len(),
+            quorum_certificate_rounds: node.quorum_certificate_rounds(),
+            timeout_certificate_rounds: node.timeout_certificate_rounds(),
+            counters,
+        }
+    }
+}
+
+fn write_header(output: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(output, "# HELP {} {}", name, help).unwrap();
+    writeln!(output, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn write_messages(output: &mut String, name: &str, author: Author, counts: &MessageCounts) {
+    for (kind, value) in &[
+        ("notification", counts.notifications),
+        ("sync_request", counts.sync_requests),
+        ("sync_response", counts.sync_responses),
+    ] {
+        writeln!(
+            output,
+            "{}{{node=\"{}\",kind=\"{}\"}} {}",
+            name, author.0, kind, value
+        )
+        .unwrap();
+    }
+}
+
+/// Render the samples, one metric family at a time as required by the format.
+pub fn render(samples: &[NodeSample]) -> String {
+    let mut output = String::new();
+    let families: &[(&str, &str, &str, fn(&NodeSample) -> usize)] = &[
+        (
+            "bft_active_round",
+            "gauge",
+            "Active round of the node.",
+            |sample| sample.active_round.0,
+        ),
+        (
+            "bft_committed_round",
+            "gauge",
+            "Round of the latest block committed by the node.",
+            |sample| sample.committed_round.0,
+        ),
+        (
+            "bft_committed_blocks_total",
+            "counter",
+            "Blocks committed by the node.",
+            |sample| sample.committed_blocks,
+        ),
+        (
+            "bft_quorum_certificate_rounds_total",
+            "counter",
+            "Rounds ended by a quorum certificate.",
+            |sample| sample.quorum_certificate_rounds,
+        ),
+        (
+            "bft_timeout_certificate_rounds_total",
+            "counter",
+            "Rounds ended by a timeout certificate.",
+            |sample| sample.timeout_certificate_rounds,
+        ),
+    ];
+    for (name, kind, help, value) in families {
+        write_header(&mut output, name, kind, help);
+        for sample in samples {
+            writeln!(
+                output,
+                "{}{{node=\"{}\"}} {}",
+                name,
+                sample.author.0,
+                value(sample)
+            )
+            .unwrap();
+        }
+    }
+    write_header(
+        &mut output,
+        "bft_messages_sent_total",
+        "counter",
+        "Messages sent by the node, by kind.",
+    );
+    for sample in samples {
+        write_messages(
+            &mut output,
+            "bft_messages_sent_total",
+            sample.author,
+            &sample.counters.sent,
+        );
+    }
+    write_header(
+        &mut output,
+        "bft_messages_received_total",
+        "counter",
+        "Messages received by the node, by kind.",
+    );
+    for sample in samples {
+        write_messages(
+            &mut output,
+            "bft_messages_received_total",
+            sample.author,
+            &sample.counters.received,
+        );
+    }
+    output
+}
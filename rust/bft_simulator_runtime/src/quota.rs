@@ -0,0 +1,97 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Quotas enforced by each node on the messages of each sender, as a simple DoS protection.
+//!
+//! Time is cut into periods of fixed length. In each period, a receiver accepts up to
+//! `max_messages` messages and `max_bytes` encoded bytes from each sender, and drops the
+//! messages beyond, before any of its handlers sees them.
+
+use crate::{
+    base_types::{Author, Duration, Result},
+    simulator::GlobalTime,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+#[path = "unit_tests/quota_tests.rs"]
+mod quota_tests;
+
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MessageQuota {
+    /// Length of the periods over which messages are counted.
+    pub period: Duration,
+    /// Messages accepted from each sender per period, if limited.
+    pub max_messages: Option<usize>,
+    /// Encoded bytes accepted from each sender per period, if limited.
+    pub max_bytes: Option<usize>,
+}
+
+/// Messages and bytes accepted by a node from each sender in the current period.
+#[derive(Clone, Debug, Default)]
+pub struct QuotaLedger {
+    usage: BTreeMap<Author, (i64, usize, usize)>,
+    /// Messages dropped so far, per sender.
+    dropped: BTreeMap<Author, usize>,
+}
+
+impl Default for MessageQuota {
+    fn default() -> Self {
+        MessageQuota {
+            period: Duration::from_millis(100),
+            max_messages: None,
+            max_bytes: None,
+        }
+    }
+}
+
+impl MessageQuota {
+    pub fn check(&self) -> Result<()> {
+        ensure!(self.period.is_positive(), "Quota periods must be positive");
+        ensure!(
+            self.max_messages != Some(0) && self.max_bytes != Some(0),
+            "Quotas must let some messages through"
+        );
+        Ok(())
+    }
+
+    /// Whether any message may be dropped.
+    pub fn is_active(&self) -> bool {
+        self.max_messages.is_some() || self.max_bytes.is_some()
+    }
+}
+
+impl QuotaLedger {
+    /// Count a message of `bytes` bytes from `sender` at `time`, unless it exceeds the quota.
+    /// Returns whether the message is accepted. Dropped messages do not count.
+    pub fn admit(
+        &mut self,
+        quota: &MessageQuota,
+        sender: Author,
+        time: GlobalTime,
+        bytes: usize,
+    ) -> bool {
+        let period = time.0 / quota.period.as_millis();
+        let usage = self.usage.entry(sender).or_insert((period, 0, 0));
+        if usage.0 != period {
+            *usage = (period, 0, 0);
+        }
+        let messages = usage.1 + 1;
+        let total_bytes = usage.2 + bytes;
+        if quota.max_messages.map_or(false, |max| messages > max)
+            || quota.max_bytes.map_or(false, |max| total_bytes > max)
+        {
+            *self.dropped.entry(sender).or_insert(0) += 1;
+            return false;
+        }
+        *usage = (period, messages, total_bytes);
+        true
+    }
+
+    /// Number of messages of `sender` dropped so far.
+    pub fn dropped(&self, sender: Author) -> usize {
+        self.dropped.get(&sender).cloned().unwrap_or(0)
+    }
+}
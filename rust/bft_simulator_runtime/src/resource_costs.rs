@@ -0,0 +1,55 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Network and cryptographic resources spent by all the nodes, amortized over the commits.
+//!
+//! Messages and bytes are counted when sent, including messages that are lost afterwards. The
+//! number of commits is the number of blocks committed by the node that committed the most.
+
+use serde::Serialize;
+
+#[cfg(test)]
+#[path = "unit_tests/resource_costs_tests.rs"]
+mod resource_costs_tests;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default, Serialize)]
+pub struct ResourceCosts {
+    pub committed_blocks: usize,
+    pub messages: usize,
+    /// Size of the messages once encoded.
+    pub bytes: usize,
+    pub signatures: usize,
+    pub verifications: usize,
+    /// MACs computed or checked, for authenticated channels.
+    pub macs: usize,
+}
+
+impl ResourceCosts {
+    pub fn messages_per_commit(&self) -> Option<f64> {
+        self.per_commit(self.messages)
+    }
+
+    pub fn bytes_per_commit(&self) -> Option<f64> {
+        self.per_commit(self.bytes)
+    }
+
+    pub fn signatures_per_commit(&self) -> Option<f64> {
+        self.per_commit(self.signatures)
+    }
+
+    pub fn verifications_per_commit(&self) -> Option<f64> {
+        self.per_commit(self.verifications)
+    }
+
+    pub fn macs_per_commit(&self) -> Option<f64> {
+        self.per_commit(self.macs)
+    }
+
+    fn per_commit(&self, total: usize) -> Option<f64> {
+        if self.committed_blocks == 0 {
+            None
+        } else {
+            Some(total as f64 / self.committed_blocks as f64)
+        }
+    }
+}
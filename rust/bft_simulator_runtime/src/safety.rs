@@ -0,0 +1,99 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cross-node safety checks on committed chains.
+//!
+//! The chains committed by honest nodes must be prefixes of one another. The checker keeps the
+//! longest chain committed so far by any node and compares every new commit to the block at
+//! the same position, so each commit is checked once.
+
+use crate::{base_types::Author, CommittedBlock};
+use std::fmt;
+
+#[cfg(test)]
+#[path = "unit_tests/safety_tests.rs"]
+mod safety_tests;
+
+#[derive(Debug)]
+pub struct SafetyChecker {
+    /// Longest chain committed so far, with the node that first committed each block.
+    chain: Vec<(Author, CommittedBlock)>,
+    /// Number of blocks already checked, per node.
+    checked_blocks: Vec<usize>,
+}
+
+/// Two nodes committed different blocks at the same position of their chains.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct SafetyViolation {
+    pub position: usize,
+    /// Node that first committed a block at this position, and that block.
+    pub first_author: Author,
+    pub first_block: CommittedBlock,
+    /// Node that committed a conflicting block, and that block.
+    pub author: Author,
+    pub block: CommittedBlock,
+    /// Blocks that both nodes committed before the conflict, most recent last.
+    pub common_prefix: Vec<CommittedBlock>,
+}
+
+/// Number of common blocks included in a violation report.
+const REPORTED_PREFIX_LENGTH: usize = 3;
+
+impl SafetyChecker {
+    pub fn new(num_nodes: usize) -> Self {
+        SafetyChecker {
+            chain: Vec::new(),
+            checked_blocks: vec![0; num_nodes],
+        }
+    }
+
+    /// Check the blocks newly committed by `author`, given all its committed blocks.
+    pub fn check_commits(
+        &mut self,
+        author: Author,
+        committed_blocks: &[CommittedBlock],
+    ) -> Result<(), SafetyViolation> {
+        for position in self.checked_blocks[author.0]..committed_blocks.len() {
+            let block = &committed_blocks[position];
+            match self.chain.get(position) {
+                None => self.chain.push((author, block.clone())),
+                Some((first_author, first_block)) => {
+                    if first_block.hash != block.hash {
+                        let start = position.saturating_sub(REPORTED_PREFIX_LENGTH);
+                        return Err(SafetyViolation {
+                            position,
+                            first_author: *first_author,
+                            first_block: first_block.clone(),
+                            author,
+                            block: block.clone(),
+                            common_prefix: committed_blocks[start..position].to_vec(),
+                        });
+                    }
+                }
+            }
+            self.checked_blocks[author.0] = position + 1;
+        }
+        Ok(())
+    }
+
+    /// Longest chain committed so far.
+    pub fn chain(&self) -> Vec<&CommittedBlock> {
+        self.chain.iter().map(|(_, block)| block).collect()
+    }
+}
+
+impl fmt::Display for SafetyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Conflicting commits at position {}: {:?} committed {:?} but {:?} committed {:?} \
+             (after {:?})",
+            self.position,
+            self.first_author,
+            self.first_block,
+            self.author,
+            self.block,
+            self.common_prefix
+        )
+    }
+}
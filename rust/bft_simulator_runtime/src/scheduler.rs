@@ -0,0 +1,220 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Control over the order in which the simulator processes events.
+//!
+//! By default, events are processed in the order of their scheduled times. A scheduler may
+//! instead pick any of the earliest pending events, which models messages that are delivered
+//! later than planned. Events processed out of order do not move the clock backward.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+
+#[cfg(test)]
+#[path = "unit_tests/scheduler_tests.rs"]
+mod scheduler_tests;
+
+pub trait Scheduler {
+    /// Maximum number of pending events to choose from, earliest first.
+    fn width(&self) -> usize;
+
+    /// Pick the index of the next event among `choices` earliest events, where
+    /// `0 < choices <= width`. `state` is a hash of the observable state of the nodes, as
+    /// computed by `Simulator::state_hash`. Returning `None` stops the run.
+    fn choose(&mut self, choices: usize, state: u64) -> Option<usize>;
+}
+
+/// A decision made by a `PrefixScheduler`.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Decision {
+    pub choice: usize,
+    pub choices: usize,
+}
+
+/// Follows a given sequence of choices, then always picks the earliest event. Used to explore
+/// schedules by depth-first search, re-running the simulation for each branch.
+#[derive(Debug)]
+pub struct PrefixScheduler {
+    width: usize,
+    prefix: Vec<usize>,
+    /// Maximum number of decisions in a run.
+    max_depth: usize,
+    decisions: Vec<Decision>,
+    /// States met after the prefix in previous runs. Runs stop when they reach one of them.
+    visited_states: Option<HashSet<u64>>,
+    /// Whether the run stopped on a visited state.
+    pruned: bool,
+}
+
+impl PrefixScheduler {
+    pub fn new(width: usize, prefix: Vec<usize>, max_depth: usize) -> Self {
+        assert!(width > 0, "Schedulers need at least one choice");
+        PrefixScheduler {
+            width,
+            prefix,
+            max_depth,
+            decisions: Vec::new(),
+            visited_states: None,
+            pruned: false,
+        }
+    }
+
+    /// Stop the run on states already visited after the prefix, and record new ones.
+    pub fn with_visited_states(mut self, visited_states: HashSet<u64>) -> Self {
+        self.visited_states = Some(visited_states);
+        self
+    }
+
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+
+    pub fn is_pruned(&self) -> bool {
+        self.pruned
+    }
+
+    pub fn into_visited_states(self) -> HashSet<u64> {
+        self.visited_states.unwrap_or_default()
+    }
+
+    /// The prefix of the next branch in depth-first order, if any.
+    pub fn next_prefix(decisions: &[Decision]) -> Option<Vec<usize>> {
+        let last = decisions
+            .iter()
+            .rposition(|decision| decision.choice + 1 < decision.choices)?;
+        let mut prefix: Vec<_> = decisions[..last].iter().map(|x| x.choice).collect();
+        prefix.push(decisions[last].choice + 1);
+        Some(prefix)
+    }
+}
+
+impl Scheduler for PrefixScheduler {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn choose(&mut self, choices: usize, state: u64) -> Option<usize> {
+        let depth = self.decisions.len();
+        if depth >= self.max_depth {
+            return None;
+        }
+        let choice = match self.prefix.get(depth) {
+            Some(choice) => std::cmp::min(*choice, choices - 1),
+            None => {
+                if let Some(visited_states) = self.visited_states.as_mut() {
+                    if !visited_states.insert(state) {
+                        self.pruned = true;
+                        return None;
+                    }
+                }
+                0
+            }
+        };
+        self.decisions.push(Decision { choice, choices });
+        Some(choice)
+    }
+}
+
+/// Random scheduler guided by the states reached in previous runs. In each state, choices that
+/// were never tried are picked first. Otherwise, choices are weighted by the inverse of the
+/// number of visits of the state they led to, which favors rarely seen interleavings.
+#[derive(Debug)]
+pub struct CoverageScheduler {
+    width: usize,
+    /// Maximum number of decisions in a run.
+    max_depth: usize,
+    rng: StdRng,
+    /// Number of visits of each state, over all runs.
+    visits: HashMap<u64, usize>,
+    /// State reached after each choice in a given state, over all runs.
+    successors: HashMap<(u64, usize), u64>,
+    /// Latest state and choice of the current run.
+    latest_decision: Option<(u64, usize)>,
+    /// Choices made in the current run.
+    choices: Vec<usize>,
+    /// Number of states first reached in the current run.
+    new_states: usize,
+}
+
+impl CoverageScheduler {
+    pub fn new(width: usize, max_depth: usize, seed: u64) -> Self {
+        assert!(width > 0, "Schedulers need at least one choice");
+        CoverageScheduler {
+            width,
+            max_depth,
+            rng: StdRng::seed_from_u64(seed),
+            visits: HashMap::new(),
+            successors: HashMap::new(),
+            latest_decision: None,
+            choices: Vec::new(),
+            new_states: 0,
+        }
+    }
+
+    /// Prepare a new run, keeping the coverage of the previous ones.
+    pub fn start_run(&mut self) {
+        self.latest_decision = None;
+        self.choices.clear();
+        self.new_states = 0;
+    }
+
+    /// Number of distinct states reached so far.
+    pub fn num_states(&self) -> usize {
+        self.visits.len()
+    }
+
+    pub fn new_states(&self) -> usize {
+        self.new_states
+    }
+
+    /// Choices made in the current run, e.g. to replay it with a `PrefixScheduler`.
+    pub fn choices(&self) -> &[usize] {
+        &self.choices
+    }
+
+    fn weight(&self, state: u64, choice: usize) -> f64 {
+        match self.successors.get(&(state, choice)) {
+            None => 1.0,
+            Some(successor) => 1.0 / (1 + self.visits[successor]) as f64,
+        }
+    }
+}
+
+impl Scheduler for CoverageScheduler {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn choose(&mut self, choices: usize, state: u64) -> Option<usize> {
+        if let Some(decision) = self.latest_decision.take() {
+            self.successors.insert(decision, state);
+        }
+        let visits = self.visits.entry(state).or_insert(0);
+        if *visits == 0 {
+            self.new_states += 1;
+        }
+        *visits += 1;
+        if self.choices.len() >= self.max_depth {
+            return None;
+        }
+        let untried: Vec<_> = (0..choices)
+            .filter(|choice| !self.successors.contains_key(&(state, *choice)))
+            .collect();
+        let choice = if !untried.is_empty() {
+            untried[self.rng.gen_range(0, untried.len())]
+        } else {
+            let weights: Vec<_> = (0..choices).map(|x| self.weight(state, x)).collect();
+            let mut target = self.rng.gen::<f64>() * weights.iter().sum::<f64>();
+            weights
+                .iter()
+                .position(|weight| {
+                    target -= weight;
+                    target < 0.0
+                })
+                .unwrap_or(choices - 1)
+        };
+        self.latest_decision = Some((state, choice));
+        self.choices.push(choice);
+        Some(choice)
+    }
+}
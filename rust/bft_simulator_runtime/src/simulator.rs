@@ -1,33 +1,91 @@
 // Copyright (c) Calibra Research
 // SPDX-License-Identifier: Apache-2.0
 
-use rand::distributions::{Distribution, LogNormal};
-use std::collections::{BinaryHeap, HashSet};
+use rand::{
+    distributions::{Distribution, LogNormal},
+    rngs::StdRng,
+    Rng, SeedableRng,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::data_writer::*;
 use crate::{
     base_types::{Author, Duration, NodeTime, Round},
-    data_writer::*,
-    ActiveRound, ConsensusNode, DataSyncNode, NodeUpdateActions,
+    clients::Clients,
+    clock::{Clock, SimulatedClock},
+    commit_listeners::CommitListener,
+    corruption::{CorruptionModel, WireFormat},
+    dashboard::{Dashboard, DashboardRow},
+    event_queue::EventQueue,
+    handler_profile::{self, Handler, HandlerProfile},
+    html_report::{distribution_row, format_number, HtmlReport, DISTRIBUTION_HEADERS},
+    inspection::{NodeView, PendingActions},
+    invariants::{CheckPoint, Invariant},
+    message_counters::{
+        total_counters, CountRecords, MessageKind, NodeMessageCounters, RecordCounts,
+    },
+    metrics::{AggregateMetrics, MetricsReport, NodeMetrics, METRICS_SCHEMA_VERSION},
+    nemesis::{partition_groups, Eclipse, EclipseRecovery, Fault, Flood, Nemesis},
+    network_trace::{NetworkConditions, NetworkRamp, NetworkTrace},
+    node_logs,
+    observers::SimulationObserver,
+    quota::{MessageQuota, QuotaLedger},
+    resource_costs::ResourceCosts,
+    safety::{SafetyChecker, SafetyViolation},
+    scheduler::Scheduler,
+    stall::{DropCause, NodeStall, StallReport, StallTracker},
+    statistics::{ChainQuality, MeasurementPhases, RunSummary, Statistics, ViewChangeStatistics},
+    termination::{StopReason, TerminationCondition},
+    ActiveLeader, ActiveRound, AuthenticatedChannels, ClientRequests, CommittedBlock,
+    ConsensusNode, CryptoStatistics, DataSyncNode, ExecutionTime, NodeErrors, NodeStatistics,
+    NodeUpdateActions, PeerTracker, RecoverableNode,
 };
 
 #[cfg(test)]
 #[path = "unit_tests/simulator_tests.rs"]
 mod simulator_tests;
 
-// Simulated global clock
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+// Simulated global clock, in milliseconds
+#[derive(
+    Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Default, Deserialize, Serialize,
+)]
+#[serde(transparent)]
 pub struct GlobalTime(pub i64);
 
 impl std::ops::Add<Duration> for GlobalTime {
     type Output = GlobalTime;
 
     fn add(self, rhs: Duration) -> Self::Output {
-        GlobalTime(self.0 + rhs)
+        GlobalTime(self.0.saturating_add(rhs.as_millis()))
+    }
+}
+
+impl std::ops::Sub<Duration> for GlobalTime {
+    type Output = GlobalTime;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self + (-rhs)
+    }
+}
+
+impl std::ops::Sub for GlobalTime {
+    type Output = Duration;
+
+    fn sub(self, rhs: GlobalTime) -> Self::Output {
+        Duration::from_millis(self.0.saturating_sub(rhs.0))
     }
 }
 
 #[derive(Copy, Clone)]
 pub struct RandomDelay {
+    mean: f64,
+    variance: f64,
     distribution: LogNormal,
 }
 
@@ -37,37 +95,53 @@ impl RandomDelay {
         let mu = f64::ln(mean / f64::sqrt(1.0 + variance / (mean * mean)));
         let sigma = f64::sqrt(f64::ln(1.0 + variance / (mean * mean)));
         RandomDelay {
+            mean,
+            variance,
             distribution: LogNormal::new(mu, sigma),
         }
     }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    /// Delays are sampled in milliseconds and rounded down.
+    fn sample<R: Rng>(self, rng: &mut R) -> Duration {
+        Duration::from_millis_f64(self.distribution.sample(rng))
+    }
 }
 
 impl GlobalTime {
-    fn add_delay(self, delay: RandomDelay) -> GlobalTime {
-        let v = delay.distribution.sample(&mut rand::thread_rng());
-        GlobalTime(self.0 + (v as i64))
+    fn add_delay<R: Rng>(self, delay: RandomDelay, rng: &mut R) -> GlobalTime {
+        self + delay.sample(rng)
     }
 
-    fn to_node_time(self, startup_time: GlobalTime) -> NodeTime {
-        NodeTime(self.0 - startup_time.0)
+    pub(crate) fn to_node_time(self, startup_time: GlobalTime) -> NodeTime {
+        NodeTime((self - startup_time).as_millis())
     }
 
-    fn from_node_time(node_time: NodeTime, startup_time: GlobalTime) -> GlobalTime {
-        GlobalTime(node_time.0 + startup_time.0)
+    pub(crate) fn from_node_time(node_time: NodeTime, startup_time: GlobalTime) -> GlobalTime {
+        startup_time + Duration::from_millis(node_time.0)
     }
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug)]
+/// Notifications and requests are broadcast as shared values, which every receiver reads in
+/// place. Only damaged messages get their own copy, decoded by the corruption model.
+#[derive(Eq, PartialEq, Debug)]
 pub enum Event<Notification, Request, Response> {
     DataSyncNotifyEvent {
         receiver: Author,
         sender: Author,
-        notification: Notification,
+        notification: Rc<Notification>,
     },
     DataSyncRequestEvent {
         receiver: Author,
         sender: Author,
-        request: Request,
+        request: Rc<Request>,
     },
     DataSyncResponseEvent {
         receiver: Author,
@@ -77,21 +151,197 @@ pub enum Event<Notification, Request, Response> {
     UpdateTimerEvent {
         author: Author,
     },
+    /// Restart of a crashed node.
+    RecoveryEvent {
+        author: Author,
+    },
+}
+
+impl<Notification, Request, Response> Event<Notification, Request, Response>
+where
+    Notification: CountRecords,
+    Request: CountRecords,
+    Response: CountRecords,
+{
+    fn record_counts(&self) -> RecordCounts {
+        match self {
+            Event::DataSyncNotifyEvent { notification, .. } => notification.record_counts(),
+            Event::DataSyncRequestEvent { request, .. } => request.record_counts(),
+            Event::DataSyncResponseEvent { response, .. } => response.record_counts(),
+            Event::UpdateTimerEvent { .. } | Event::RecoveryEvent { .. } => RecordCounts::default(),
+        }
+    }
+}
+
+impl<Notification, Request, Response> Event<Notification, Request, Response>
+where
+    Notification: WireFormat,
+    Request: WireFormat,
+    Response: WireFormat,
+{
+    /// Damage the message of a network event at random. Returns `None` if the message was left
+    /// intact, and the event with the decoding of the damaged message otherwise.
+    fn corrupt<R: Rng>(
+        &self,
+        model: &CorruptionModel,
+        rng: &mut R,
+    ) -> Option<crate::base_types::Result<Self>> {
+        match self {
+            Event::DataSyncNotifyEvent {
+                receiver,
+                sender,
+                notification,
+            } => model.corrupt_message(&**notification, rng).map(|result| {
+                result.map(|notification| Event::DataSyncNotifyEvent {
+                    receiver: *receiver,
+                    sender: *sender,
+                    notification: Rc::new(notification),
+                })
+            }),
+            Event::DataSyncRequestEvent {
+                receiver,
+                sender,
+                request,
+            } => model.corrupt_message(&**request, rng).map(|result| {
+                result.map(|request| Event::DataSyncRequestEvent {
+                    receiver: *receiver,
+                    sender: *sender,
+                    request: Rc::new(request),
+                })
+            }),
+            Event::DataSyncResponseEvent {
+                receiver,
+                sender,
+                response,
+            } => model.corrupt_message(response, rng).map(|result| {
+                result.map(|response| Event::DataSyncResponseEvent {
+                    receiver: *receiver,
+                    sender: *sender,
+                    response,
+                })
+            }),
+            Event::UpdateTimerEvent { .. } | Event::RecoveryEvent { .. } => None,
+        }
+    }
+}
+
+/// Encoded sizes of the shared messages in flight, so that a message broadcast to many receivers
+/// is encoded once. Entries hold their message, so that no later message reuses its address, until
+/// no event holds it anymore.
+struct WireSizes<T> {
+    sizes: HashMap<*const T, (Rc<T>, usize)>,
+    /// Number of entries left by the latest removal of unused messages.
+    retained: usize,
+}
+
+impl<T: WireFormat> WireSizes<T> {
+    fn new() -> Self {
+        WireSizes {
+            sizes: HashMap::new(),
+            retained: 0,
+        }
+    }
+
+    /// Size of `message` once encoded, in bytes.
+    fn get(&mut self, message: &Rc<T>) -> usize {
+        let key = &**message as *const T;
+        if let Some((_, size)) = self.sizes.get(&key) {
+            return *size;
+        }
+        if self.sizes.len() >= 2 * std::cmp::max(self.retained, 16) {
+            self.sizes
+                .retain(|_, (message, _)| Rc::strong_count(message) > 1);
+            self.retained = self.sizes.len();
+        }
+        let size = message.to_wire().len();
+        self.sizes.insert(key, (message.clone(), size));
+        size
+    }
+}
+
+impl<Notification, Request, Response> Event<Notification, Request, Response> {
+    /// Whether the event is local to a node rather than a network message.
+    fn is_local(&self) -> bool {
+        match self {
+            Event::UpdateTimerEvent { .. } | Event::RecoveryEvent { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Short description of the type of the event.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::DataSyncNotifyEvent { .. } => "notify",
+            Event::DataSyncRequestEvent { .. } => "request",
+            Event::DataSyncResponseEvent { .. } => "response",
+            Event::UpdateTimerEvent { .. } => "timer",
+            Event::RecoveryEvent { .. } => "recovery",
+        }
+    }
+
+    /// The kind of network message carried by the event, if any.
+    fn message_kind(&self) -> Option<MessageKind> {
+        match self {
+            Event::DataSyncNotifyEvent { .. } => Some(MessageKind::Notification),
+            Event::DataSyncRequestEvent { .. } => Some(MessageKind::SyncRequest),
+            Event::DataSyncResponseEvent { .. } => Some(MessageKind::SyncResponse),
+            Event::UpdateTimerEvent { .. } | Event::RecoveryEvent { .. } => None,
+        }
+    }
+
+    /// The node that sent the network message carried by the event.
+    fn sending_node(&self) -> Author {
+        match self {
+            Event::DataSyncNotifyEvent { sender, .. } => *sender,
+            Event::DataSyncRequestEvent { receiver, .. } => *receiver,
+            Event::DataSyncResponseEvent { sender, .. } => *sender,
+            Event::UpdateTimerEvent { author } | Event::RecoveryEvent { author } => *author,
+        }
+    }
+
+    /// The node whose handlers process the event.
+    fn handling_node(&self) -> Author {
+        match self {
+            Event::DataSyncNotifyEvent { receiver, .. } => *receiver,
+            Event::DataSyncRequestEvent { sender, .. } => *sender,
+            Event::DataSyncResponseEvent { receiver, .. } => *receiver,
+            Event::UpdateTimerEvent { author } | Event::RecoveryEvent { author } => *author,
+        }
+    }
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd)]
-struct ScheduledEvent<Notification, Request, Response>(
-    std::cmp::Reverse<GlobalTime>,
-    Event<Notification, Request, Response>,
-);
+/// An event processed by the simulator, as recorded in the event log.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct EventRecord {
+    pub time: GlobalTime,
+    /// The node whose handlers processed the event.
+    pub node: Author,
+    pub kind: &'static str,
+}
 
+/// Events are processed by increasing time. Events scheduled at the same time are processed in
+/// the order in which they were scheduled. Together with seeded network delays, this makes a
+/// simulation a deterministic function of its parameters, its seed, and the (deterministic)
+/// handlers of the nodes.
 type PendingEvents<Notification, Request, Response> =
-    BinaryHeap<ScheduledEvent<Notification, Request, Response>>;
+    EventQueue<GlobalTime, Event<Notification, Request, Response>>;
 
 #[derive(Debug)]
 pub struct SimulatedNode<Node, Context> {
-    startup_time: GlobalTime,
+    clock: SimulatedClock,
+    /// Time after which the node stops processing events, if any.
+    crash_time: Option<GlobalTime>,
+    /// Time at which a crashed node restarts, if any.
+    recovery_time: Option<GlobalTime>,
+    /// Whether the node joined the network after the start of the run. Events are dropped
+    /// until its startup time.
+    joins_late: bool,
     ignore_scheduled_updates_until: GlobalTime,
+    /// Time of the latest timer event that was scheduled for this node and has not fired yet.
+    /// Used to avoid queueing duplicate timer events.
+    scheduled_update: Option<GlobalTime>,
+    /// Time until which the node is executing commands. Events are delayed until then.
+    busy_until: GlobalTime,
     node: Node,
     context: Context,
 }
@@ -101,8 +351,10 @@ where
     Node: ConsensusNode<Context>,
 {
     fn update(&mut self, global_clock: GlobalTime) -> NodeUpdateActions {
-        let local_clock = global_clock.to_node_time(self.startup_time);
-        self.node.update_node(local_clock, &mut self.context)
+        let local_clock = self.local_time(global_clock);
+        let node = &mut self.node;
+        let context = &mut self.context;
+        handler_profile::measure(Handler::Update, || node.update_node(local_clock, context))
     }
 }
 
@@ -115,23 +367,127 @@ where
     }
 }
 
+impl<Node, Context> SimulatedNode<Node, Context> {
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    pub fn startup_time(&self) -> GlobalTime {
+        self.clock.startup_time()
+    }
+
+    pub fn clock(&self) -> &SimulatedClock {
+        &self.clock
+    }
+
+    /// Local time of the node at the given global time.
+    fn local_time(&mut self, global_clock: GlobalTime) -> NodeTime {
+        self.clock.set_global_time(global_clock);
+        self.clock.now()
+    }
+
+    /// Whether the node is down after a crash at the given time.
+    pub fn is_crashed(&self, clock: GlobalTime) -> bool {
+        match (self.crash_time, self.recovery_time) {
+            (Some(crash_time), Some(recovery_time)) => clock >= crash_time && clock < recovery_time,
+            (Some(crash_time), None) => clock >= crash_time,
+            (None, _) => false,
+        }
+    }
+
+    /// Whether the node has started and has not crashed at the given time.
+    pub fn is_running(&self, clock: GlobalTime) -> bool {
+        !self.is_crashed(clock) && !(self.joins_late && clock < self.clock.startup_time())
+    }
+}
+
 pub struct Simulator<Node, Context, Notification, Request, Response> {
     clock: GlobalTime,
     network_delay: RandomDelay,
+    /// Probability that a network message is lost.
+    network_loss_rate: f64,
+    /// Changes of the network delays and losses over time.
+    network_trace: NetworkTrace,
+    /// Index of the first change of the trace not applied yet.
+    next_network_change: usize,
+    /// Gradual change of the network conditions in progress, if any.
+    network_ramp: Option<NetworkRamp>,
+    /// Damage of the messages in transit.
+    corruption: CorruptionModel,
+    /// Messages that nodes accept from each sender per period.
+    message_quota: MessageQuota,
+    /// Messages accepted by each node from each sender in the current period.
+    quota_ledgers: Vec<QuotaLedger>,
+    /// Encoded sizes of the notifications and requests in flight.
+    notification_sizes: WireSizes<Notification>,
+    request_sizes: WireSizes<Request>,
+    /// Source of randomness for the network, seeded for reproducibility.
+    rng: StdRng,
     pending_events: PendingEvents<Notification, Request, Response>,
     nodes: Vec<SimulatedNode<Node, Context>>,
+    /// Number of network events in the queue, per receiving node.
+    pending_messages: Vec<usize>,
+    message_counters: Vec<NodeMessageCounters>,
+    /// Nodes configured as Byzantine, e.g. for chain quality.
+    byzantine_nodes: BTreeSet<Author>,
+    /// Stake of each node, used to weigh quorums in the liveness check and the metrics.
+    stakes: Vec<usize>,
+    safety_checker: SafetyChecker,
+    /// First conflicting commit detected by the safety checker, if any.
+    safety_violation: Option<SafetyViolation>,
+    /// Latest commit, or latest time when a quorum of honest nodes was not running.
+    liveness_reference: GlobalTime,
+    statistics: Statistics,
+    dashboard: Option<Dashboard>,
+    invariants: Vec<Box<Invariant<Node, Context>>>,
+    commit_listeners: Vec<Box<CommitListener>>,
+    nemeses: Vec<Box<Nemesis>>,
+    /// Simulated clients sending requests to the nodes, if any.
+    clients: Option<Clients>,
+    observers: Vec<Box<SimulationObserver>>,
+    /// Active round of each node reported to the observers.
+    observed_rounds: Vec<Round>,
+    /// Group of each node, while the network is partitioned.
+    partition: Option<Vec<usize>>,
+    /// Nodes whose links are controlled by an adversary, and how.
+    eclipses: BTreeMap<Author, Eclipse>,
+    /// Catching up of the victims of the eclipses that ended.
+    eclipse_recoveries: Vec<EclipseRecovery>,
+    /// Nodes flooding the others, how, and the time of their next burst.
+    floods: BTreeMap<Author, (Flood, GlobalTime)>,
+    /// Number of commits of each node reported to the commit listeners.
+    notified_commits: Vec<usize>,
+    /// Number of errors reported by each node.
+    errors: Vec<usize>,
+    /// Processed events, if they are recorded.
+    event_log: Option<Vec<EventRecord>>,
+    /// Host CPU time of the handlers, if profiled.
+    handler_profile: Option<HandlerProfile>,
+    /// Highest active round of a node at the time of the latest commit.
+    progress_round: Round,
+    /// Time without commits after which stalls are reported, if they are diagnosed.
+    stall_threshold: Option<Duration>,
+    /// Leaders and dropped messages since the start of the current stall.
+    stall_tracker: StallTracker,
+    stall_reports: Vec<StallReport>,
+    /// Whether the current stall was reported already.
+    has_reported_stall: bool,
+    /// Faults applied or scheduled so far, including crashes and recoveries.
+    fault_log: Vec<(GlobalTime, Fault)>,
 }
 
 impl<Node, Context, Notification, Request, Response>
     Simulator<Node, Context, Notification, Request, Response>
 where
-    Notification: std::cmp::Ord + std::fmt::Debug,
-    Request: std::cmp::Ord + std::fmt::Debug,
-    Response: std::cmp::Ord + std::fmt::Debug,
+    Notification: std::fmt::Debug + CountRecords + WireFormat,
+    Request: std::fmt::Debug + CountRecords + WireFormat,
+    Response: std::fmt::Debug + CountRecords + WireFormat,
 {
     pub fn new<F, G>(
         num_nodes: usize,
         network_delay: RandomDelay,
+        network_loss_rate: f64,
+        seed: u64,
         context_factory: F,
         node_factory: G,
     ) -> Simulator<Node, Context, Notification, Request, Response>
@@ -140,12 +496,14 @@ where
         G: Fn(Author, &Context, NodeTime) -> Node,
     {
         let clock = GlobalTime(0);
-        let mut pending_events = BinaryHeap::new();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut pending_events = EventQueue::new();
         let nodes = (0..num_nodes)
             .map(|index| {
                 let author = Author(index);
                 let context = context_factory(author, num_nodes);
-                let startup_time = clock.add_delay(network_delay) + 1;
+                let startup_time =
+                    clock.add_delay(network_delay, &mut rng) + Duration::from_millis(1);
                 let node_time = NodeTime(0);
                 let deadline = GlobalTime::from_node_time(node_time, startup_time);
                 let event = Event::UpdateTimerEvent { author };
@@ -154,10 +512,15 @@ where
                     event,
                     deadline
                 );
-                pending_events.push(ScheduledEvent(std::cmp::Reverse(deadline), event));
+                pending_events.push(deadline, event);
                 SimulatedNode {
-                    startup_time,
-                    ignore_scheduled_updates_until: startup_time + (-1),
+                    clock: SimulatedClock::new(startup_time),
+                    crash_time: None,
+                    recovery_time: None,
+                    joins_late: false,
+                    ignore_scheduled_updates_until: startup_time - Duration::from_millis(1),
+                    scheduled_update: Some(deadline),
+                    busy_until: clock,
                     node: node_factory(author, &context, node_time),
                     context,
                 }
@@ -166,8 +529,47 @@ where
         Simulator {
             clock,
             network_delay,
+            network_loss_rate,
+            network_trace: NetworkTrace::default(),
+            next_network_change: 0,
+            network_ramp: None,
+            corruption: CorruptionModel::default(),
+            message_quota: MessageQuota::default(),
+            quota_ledgers: vec![QuotaLedger::default(); num_nodes],
+            notification_sizes: WireSizes::new(),
+            request_sizes: WireSizes::new(),
+            rng,
             pending_events,
             nodes,
+            pending_messages: vec![0; num_nodes],
+            message_counters: vec![NodeMessageCounters::default(); num_nodes],
+            byzantine_nodes: BTreeSet::new(),
+            stakes: vec![1; num_nodes],
+            safety_checker: SafetyChecker::new(num_nodes),
+            safety_violation: None,
+            liveness_reference: GlobalTime(0),
+            statistics: Statistics::new(num_nodes),
+            dashboard: None,
+            invariants: Vec::new(),
+            commit_listeners: Vec::new(),
+            nemeses: Vec::new(),
+            clients: None,
+            observers: Vec::new(),
+            observed_rounds: vec![Round(0); num_nodes],
+            partition: None,
+            eclipses: BTreeMap::new(),
+            eclipse_recoveries: Vec::new(),
+            floods: BTreeMap::new(),
+            notified_commits: vec![0; num_nodes],
+            errors: vec![0; num_nodes],
+            event_log: None,
+            handler_profile: None,
+            progress_round: Round(0),
+            stall_threshold: None,
+            stall_tracker: StallTracker::default(),
+            stall_reports: Vec::new(),
+            has_reported_stall: false,
+            fault_log: Vec::new(),
         }
     }
 
@@ -177,14 +579,149 @@ where
         event: Event<Notification, Request, Response>,
     ) {
         trace!("Scheduling event {:?} for {:?}", event, deadline);
-        self.pending_events
-            .push(ScheduledEvent(std::cmp::Reverse(deadline), event));
+        if !event.is_local() {
+            self.pending_messages[event.handling_node().0] += 1;
+        }
+        self.pending_events.push(deadline, event);
     }
 
     fn schedule_network_event(&mut self, event: Event<Notification, Request, Response>) {
-        let deadline = self.clock.add_delay(self.network_delay);
+        let clock = self.clock;
+        self.schedule_network_event_at(clock, event);
+    }
+
+    /// Send a message that leaves the sending node at time `departure`.
+    fn schedule_network_event_at(
+        &mut self,
+        departure: GlobalTime,
+        event: Event<Notification, Request, Response>,
+    ) {
+        if let Some(kind) = event.message_kind() {
+            let bytes = self.wire_size(&event);
+            let counters = &mut self.message_counters[event.sending_node().0];
+            counters.sent.add_message(kind, event.record_counts());
+            counters.bytes_sent += bytes;
+        }
+        self.update_network_conditions();
+        if self.network_loss_rate > 0.0 && self.rng.gen::<f64>() < self.network_loss_rate {
+            debug!("Dropping network event {:?}", event);
+            self.record_drop(&event, DropCause::Loss);
+            return;
+        }
+        if self.is_partitioned(event.sending_node(), event.handling_node()) {
+            debug!("Dropping network event across the partition {:?}", event);
+            self.record_drop(&event, DropCause::Partition);
+            return;
+        }
+        let mut departure = departure;
+        if let Some(eclipse) = self.eclipse_between(event.sending_node(), event.handling_node()) {
+            if eclipse.drop_rate > 0.0 && self.rng.gen::<f64>() < eclipse.drop_rate {
+                debug!("Dropping eclipsed network event {:?}", event);
+                self.record_drop(&event, DropCause::Eclipse);
+                return;
+            }
+            departure = departure + eclipse.delay;
+        }
+        let event = match event.corrupt(&self.corruption, &mut self.rng) {
+            None => event,
+            Some(result) => {
+                let counters = &mut self.message_counters[event.handling_node().0];
+                counters.corrupted += 1;
+                match result {
+                    Ok(corrupted_event) => corrupted_event,
+                    Err(error) => {
+                        debug!("Dropping corrupted network event {:?}: {}", event, error);
+                        counters.undecodable += 1;
+                        self.record_drop(&event, DropCause::Corruption);
+                        return;
+                    }
+                }
+            }
+        };
+        let deadline = departure.add_delay(self.network_delay, &mut self.rng);
         self.schedule_event(deadline, event);
     }
+
+    /// Size of the network message carried by an event once encoded, in bytes. Notifications
+    /// and requests are encoded once for all their receivers.
+    fn wire_size(&mut self, event: &Event<Notification, Request, Response>) -> usize {
+        match event {
+            Event::DataSyncNotifyEvent { notification, .. } => {
+                self.notification_sizes.get(notification)
+            }
+            Event::DataSyncRequestEvent { request, .. } => self.request_sizes.get(request),
+            Event::DataSyncResponseEvent { response, .. } => response.to_wire().len(),
+            Event::UpdateTimerEvent { .. } | Event::RecoveryEvent { .. } => 0,
+        }
+    }
+
+    /// Count a network message against the quota of its sender at its receiver. Returns false,
+    /// and counts the message as dropped, if the quota is exceeded.
+    fn is_within_quota(&mut self, event: &Event<Notification, Request, Response>) -> bool {
+        if !self.message_quota.is_active() {
+            return true;
+        }
+        let bytes = match self.message_quota.max_bytes {
+            Some(_) => self.wire_size(event),
+            None => 0,
+        };
+        let receiver = event.handling_node();
+        let is_admitted = self.quota_ledgers[receiver.0].admit(
+            &self.message_quota,
+            event.sending_node(),
+            self.clock,
+            bytes,
+        );
+        if !is_admitted {
+            self.message_counters[receiver.0].over_quota += 1;
+            self.record_drop(event, DropCause::Quota);
+        }
+        is_admitted
+    }
+
+    /// Record a dropped network message in the current stall, if stalls are diagnosed.
+    fn record_drop(&mut self, event: &Event<Notification, Request, Response>, cause: DropCause) {
+        if self.stall_threshold.is_some() && !event.is_local() {
+            self.stall_tracker
+                .record_drop(event.sending_node(), event.handling_node(), cause);
+        }
+    }
+
+    /// Apply the changes of the network trace up to the current time, following the active
+    /// ramp, if any.
+    fn update_network_conditions(&mut self) {
+        loop {
+            let change = match self.network_trace.changes().get(self.next_network_change) {
+                Some(change) if change.time <= self.clock => change.clone(),
+                _ => break,
+            };
+            debug!(
+                "@{:?} Changing network conditions: {:?}",
+                self.clock, change
+            );
+            // A new change interrupts the active ramp.
+            let current = match &self.network_ramp {
+                Some(ramp) => ramp.conditions_at(change.time),
+                None => self.network_conditions(),
+            };
+            self.network_ramp = NetworkRamp::new(&change, current);
+            if self.network_ramp.is_none() {
+                self.set_network_conditions(change.apply(current));
+            }
+            self.next_network_change += 1;
+        }
+        if let Some(ramp) = self.network_ramp.take() {
+            self.set_network_conditions(ramp.conditions_at(self.clock));
+            if ramp.end() > self.clock {
+                self.network_ramp = Some(ramp);
+            }
+        }
+    }
+
+    fn set_network_conditions(&mut self, conditions: NetworkConditions) {
+        self.network_delay = RandomDelay::new(conditions.mean, conditions.variance);
+        self.network_loss_rate = conditions.loss_rate;
+    }
 }
 
 impl<Node, Context, Notification, Request, Response>
@@ -194,15 +731,889 @@ impl<Node, Context, Notification, Request, Response>
         self.nodes.get(author.0).unwrap()
     }
 
+    pub fn simulated_nodes(&self) -> &[SimulatedNode<Node, Context>] {
+        &self.nodes
+    }
+
     fn simulated_node_mut(&mut self, author: Author) -> &mut SimulatedNode<Node, Context> {
         self.nodes.get_mut(author.0).unwrap()
     }
+
+    /// Time of the latest processed event.
+    pub fn clock(&self) -> GlobalTime {
+        self.clock
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// First conflict found between the committed chains of two nodes, if any.
+    pub fn safety_violation(&self) -> Option<&SafetyViolation> {
+        self.safety_violation.as_ref()
+    }
+
+    /// Messages sent and received so far, per node.
+    pub fn message_counters(&self) -> &[NodeMessageCounters] {
+        &self.message_counters
+    }
+
+    /// Number of errors reported by each node so far.
+    pub fn node_errors(&self) -> &[usize] {
+        &self.errors
+    }
+
+    pub fn contexts(&self) -> Vec<&Context> {
+        self.nodes.iter().map(|node| &node.context).collect()
+    }
+
+    /// Change the network delays and losses over time, according to `trace`. Times of the trace
+    /// are global times.
+    pub fn set_network_trace(&mut self, trace: NetworkTrace) {
+        self.network_trace = trace;
+        self.next_network_change = 0;
+        self.network_ramp = None;
+    }
+
+    pub fn network_delay(&self) -> RandomDelay {
+        self.network_delay
+    }
+
+    pub fn network_loss_rate(&self) -> f64 {
+        self.network_loss_rate
+    }
+
+    /// Damage messages in transit according to `model`.
+    pub fn set_message_corruption(&mut self, model: CorruptionModel) {
+        self.corruption = model;
+    }
+
+    /// Drop the messages beyond `quota` at their receivers.
+    pub fn set_message_quota(&mut self, quota: MessageQuota) {
+        self.message_quota = quota;
+    }
+
+    /// Delays and losses of the messages sent at the time of the latest message.
+    pub fn network_conditions(&self) -> NetworkConditions {
+        NetworkConditions {
+            mean: self.network_delay.mean(),
+            variance: self.network_delay.variance(),
+            loss_rate: self.network_loss_rate,
+        }
+    }
+
+    /// Show a live dashboard while the simulation runs.
+    pub fn set_dashboard(&mut self, dashboard: Dashboard) {
+        self.dashboard = Some(dashboard);
+    }
+
+    /// Report the stalls that last longer than `threshold`, once per stall, along with the
+    /// state of the nodes and the messages dropped since the start of the stall.
+    pub fn diagnose_stalls(&mut self, threshold: Duration) {
+        self.stall_threshold = Some(threshold);
+    }
+
+    /// Reports of the stalls of the run so far.
+    pub fn stall_reports(&self) -> &[StallReport] {
+        &self.stall_reports
+    }
+
+    /// Faults applied or scheduled so far, in the order of the calls, with their times.
+    pub fn fault_log(&self) -> &[(GlobalTime, Fault)] {
+        &self.fault_log
+    }
+
+    /// Count the given node as Byzantine in the metrics.
+    pub fn set_byzantine(&mut self, author: Author) {
+        self.byzantine_nodes.insert(author);
+    }
+
+    pub fn byzantine_nodes(&self) -> &BTreeSet<Author> {
+        &self.byzantine_nodes
+    }
+
+    /// Set the stake of a node. All nodes have a stake of 1 by default.
+    pub fn set_stake(&mut self, author: Author, stake: usize) {
+        self.stakes[author.0] = stake;
+        self.statistics.set_stake(author, stake);
+    }
+
+    pub fn stakes(&self) -> &[usize] {
+        &self.stakes
+    }
+
+    /// Leave the commits before the warm-up, or after the measurement window, out of the
+    /// statistics. Runs still go on until their termination condition.
+    pub fn set_measurement_phases(&mut self, phases: MeasurementPhases) {
+        self.statistics.set_phases(phases);
+    }
+
+    /// Whether honest nodes holding a quorum of the stake are running at the given time.
+    fn has_honest_quorum(&self, clock: GlobalTime) -> bool {
+        let running_stake: usize = (0..self.nodes.len())
+            .filter(|index| {
+                !self.byzantine_nodes.contains(&Author(*index))
+                    && self.nodes[*index].is_running(clock)
+            })
+            .map(|index| self.stakes[index])
+            .sum();
+        running_stake >= self.statistics.quorum_stake()
+    }
+
+    /// Stop processing the events of a node from the given time on. Messages sent to the node
+    /// are lost, and the node does not send anything. Crashing a node again cancels its previous
+    /// recovery.
+    pub fn crash_node(&mut self, author: Author, crash_time: GlobalTime) {
+        let node = self.simulated_node_mut(author);
+        node.crash_time = Some(crash_time);
+        if let Some(recovery_time) = node.recovery_time.take() {
+            if recovery_time > crash_time {
+                let cancelled = (recovery_time, Fault::Recover(author));
+                self.fault_log.retain(|entry| *entry != cancelled);
+            }
+        }
+        self.fault_log.push((crash_time, Fault::Crash(author)));
+    }
+
+    /// Restart a crashed node at `recovery_time`. The node then loses its volatile state and
+    /// reloads its durable state, and messages sent to it in the meantime are lost.
+    pub fn recover_node_at(&mut self, author: Author, recovery_time: GlobalTime) {
+        self.simulated_node_mut(author).recovery_time = Some(recovery_time);
+        self.pending_events
+            .push(recovery_time, Event::RecoveryEvent { author });
+        self.fault_log.push((recovery_time, Fault::Recover(author)));
+    }
+
+    /// Start a node at `join_time` instead of the beginning of the run. The node drops all
+    /// events until then, and its local clock starts at `join_time`.
+    pub fn join_node_at(&mut self, author: Author, join_time: GlobalTime) {
+        let node = self.simulated_node_mut(author);
+        node.joins_late = true;
+        node.clock.set_startup_time(join_time);
+        // Cancel the initial update.
+        node.ignore_scheduled_updates_until = join_time - Duration::from_millis(1);
+        node.scheduled_update = Some(join_time);
+        self.pending_events
+            .push(join_time, Event::UpdateTimerEvent { author });
+    }
+
+    /// Register a nemesis, polled for faults before every event for the rest of the run.
+    pub fn add_nemesis(&mut self, nemesis: Box<Nemesis>) {
+        self.nemeses.push(nemesis);
+    }
+
+    /// Simulate clients that send requests to the nodes and wait for their commit. Polled
+    /// before every event, as nemeses.
+    pub fn set_clients(&mut self, clients: Clients) {
+        self.clients = Some(clients);
+    }
+
+    pub fn clients(&self) -> Option<&Clients> {
+        self.clients.as_ref()
+    }
+
+    /// Quotas of the senders of messages at `receiver`, and the messages dropped so far.
+    pub fn quota_ledger(&self, receiver: Author) -> &QuotaLedger {
+        &self.quota_ledgers[receiver.0]
+    }
+
+    /// Apply a fault at `time`, which must not be before the processed events.
+    /// Partitions with unknown nodes are ignored.
+    pub fn inject_fault(&mut self, fault: Fault, time: GlobalTime) {
+        debug!("@{:?} Injecting fault {:?}", time, fault);
+        if let Some(author) = fault.author() {
+            if author.0 >= self.nodes.len() {
+                warn!(
+                    "@{:?} Ignoring fault {:?}: unknown node {}",
+                    time, fault, author.0
+                );
+                return;
+            }
+        }
+        let partition = match &fault {
+            Fault::Partition(groups) => match partition_groups(self.nodes.len(), groups) {
+                Ok(partition) => Some(partition),
+                Err(error) => {
+                    warn!("@{:?} Ignoring fault {:?}: {}", time, fault, error);
+                    return;
+                }
+            },
+            _ => None,
+        };
+        match fault {
+            Fault::Crash(_) | Fault::Recover(_) => (),
+            _ => self.fault_log.push((time, fault.clone())),
+        }
+        match fault {
+            Fault::Crash(author) => self.crash_node(author, time),
+            Fault::Recover(author) => {
+                if self.simulated_node(author).is_crashed(time) {
+                    self.recover_node_at(author, time);
+                }
+            }
+            Fault::Partition(_) => self.partition = partition,
+            Fault::Heal => self.partition = None,
+            Fault::Eclipse(victim, eclipse) => {
+                self.eclipses.insert(victim, eclipse);
+            }
+            Fault::EndEclipse(victim) => {
+                if self.eclipses.remove(&victim).is_some() {
+                    let target_round = (0..self.nodes.len())
+                        .filter(|index| *index != victim.0)
+                        .map(|index| self.observed_rounds[index])
+                        .max()
+                        .unwrap_or(Round(0));
+                    self.eclipse_recoveries.push(EclipseRecovery {
+                        victim,
+                        end_time: time,
+                        target_round,
+                        recovery_time: None,
+                    });
+                }
+            }
+            Fault::Flood(author, flood) => {
+                self.floods.insert(author, (flood, time));
+            }
+            Fault::EndFlood(author) => {
+                self.floods.remove(&author);
+            }
+            Fault::ClockJump(author, offset) => {
+                let node = self.simulated_node_mut(author);
+                node.clock.jump(offset);
+                // Let the node react to its new clock right away.
+                node.ignore_scheduled_updates_until = time - Duration::from_millis(1);
+                node.scheduled_update = Some(time);
+                self.pending_events
+                    .push(time, Event::UpdateTimerEvent { author });
+            }
+        }
+    }
+
+    /// The eclipse that distorts the messages between two nodes, if any.
+    fn eclipse_between(&self, sender: Author, receiver: Author) -> Option<Eclipse> {
+        let sender_eclipse = self.eclipses.get(&sender).filter(|x| x.controls(receiver));
+        let receiver_eclipse = self.eclipses.get(&receiver).filter(|x| x.controls(sender));
+        sender_eclipse.or(receiver_eclipse).cloned()
+    }
+
+    /// Time taken by the victims of the eclipses that ended to reach the highest round of the
+    /// other nodes at the end of the eclipse.
+    pub fn eclipse_recoveries(&self) -> &[EclipseRecovery] {
+        &self.eclipse_recoveries
+    }
+
+    /// Whether the partition of the network, if any, separates the two nodes.
+    pub fn is_partitioned(&self, sender: Author, receiver: Author) -> bool {
+        match &self.partition {
+            Some(groups) => groups[sender.0] != groups[receiver.0],
+            None => false,
+        }
+    }
+
+    fn poll_nemeses(&mut self, time: GlobalTime) {
+        let mut faults = Vec::new();
+        for nemesis in &mut self.nemeses {
+            faults.extend(nemesis.faults(time));
+        }
+        for fault in faults {
+            self.inject_fault(fault, time);
+        }
+    }
+
+    /// Record the events processed from now on.
+    pub fn record_events(&mut self) {
+        self.event_log = Some(Vec::new());
+    }
+
+    /// Events recorded so far.
+    pub fn event_log(&self) -> &[EventRecord] {
+        match &self.event_log {
+            Some(log) => log,
+            None => &[],
+        }
+    }
+
+    /// Time the handlers of the nodes from now on.
+    pub fn profile_handlers(&mut self) {
+        self.handler_profile = Some(HandlerProfile::new(self.nodes.len()));
+    }
+
+    /// Times of the handlers so far, if profiled.
+    pub fn handler_profile(&self) -> Option<&HandlerProfile> {
+        self.handler_profile.as_ref()
+    }
+
+    /// Register an invariant, checked at its check point for the rest of the run.
+    pub fn add_invariant(&mut self, invariant: Box<Invariant<Node, Context>>) {
+        self.invariants.push(invariant);
+    }
+
+    /// Register a listener, called with every block committed from now on.
+    pub fn add_commit_listener(&mut self, listener: Box<CommitListener>) {
+        self.commit_listeners.push(listener);
+    }
+
+    /// Register an observer, called on the events of the simulation from now on.
+    pub fn add_observer(&mut self, observer: Box<SimulationObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Check the registered invariants. Returns the name of the first violated invariant and
+    /// the description of the violation.
+    fn check_invariants(&mut self, has_new_commits: bool) -> Result<(), (String, String)> {
+        let nodes = &self.nodes;
+        for invariant in &mut self.invariants {
+            if invariant.check_point() == CheckPoint::EveryCommit && !has_new_commits {
+                continue;
+            }
+            if let Err(message) = invariant.check(nodes) {
+                return Err((invariant.name().to_string(), message));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<Node, Context, Notification, Request, Response>
     Simulator<Node, Context, Notification, Request, Response>
 where
-    Context: std::fmt::Debug,
+    Node: ActiveRound + ActiveLeader + NodeStatistics,
+{
+    /// Current state of each node, as shown by the dashboard.
+    pub fn dashboard_rows(&self) -> Vec<DashboardRow> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| DashboardRow {
+                author: Author(index),
+                active_round: node.node.active_round(),
+                active_leader: node.node.active_leader(),
+                highest_committed_round: node
+                    .node
+                    .committed_blocks()
+                    .iter()
+                    .map(|block| block.round)
+                    .max(),
+                pending_messages: self.pending_messages[index],
+            })
+            .collect()
+    }
+
+    /// Current state of a node.
+    pub fn inspect_node(&self, author: Author) -> NodeView {
+        let node = self.simulated_node(author);
+        let committed_blocks = node.node.committed_blocks();
+        NodeView {
+            author,
+            running: node.is_running(self.clock),
+            active_round: node.node.active_round(),
+            active_leader: node.node.active_leader(),
+            highest_quorum_certificate_round: node.node.highest_quorum_certificate_round(),
+            highest_committed_round: committed_blocks.iter().map(|block| block.round).max(),
+            committed_blocks: committed_blocks.len(),
+            pending: PendingActions {
+                messages: self.pending_messages[author.0],
+                next_update: node.scheduled_update,
+                busy_until: if node.busy_until > self.clock {
+                    Some(node.busy_until)
+                } else {
+                    None
+                },
+            },
+        }
+    }
+
+    /// Current state of every node, in the order of authors.
+    pub fn inspect_nodes(&self) -> Vec<NodeView> {
+        (0..self.nodes.len())
+            .map(|index| self.inspect_node(Author(index)))
+            .collect()
+    }
+
+    /// Diagnostics of the current stall, i.e. since the latest commit.
+    pub fn stall_report(&self) -> StallReport {
+        let nodes = self
+            .inspect_nodes()
+            .into_iter()
+            .map(|view| NodeStall {
+                timeout_certificate_rounds: self
+                    .simulated_node(view.author)
+                    .node
+                    .timeout_certificate_rounds_after(view.highest_quorum_certificate_round),
+                view,
+            })
+            .collect();
+        self.stall_tracker
+            .report(self.clock, self.liveness_reference, nodes)
+    }
+
+    fn refresh_dashboard(&mut self, dashboard: &mut Dashboard, force: bool) {
+        if force || dashboard.should_refresh(self.clock) {
+            if let Err(error) = dashboard.refresh(self.clock, &self.dashboard_rows()) {
+                warn!("Failed to draw the dashboard: {}", error);
+            }
+        }
+    }
+}
+
+impl<Node, Context, Notification, Request, Response>
+    Simulator<Node, Context, Notification, Request, Response>
+where
+    Node: NodeStatistics,
+{
+    fn record_commits(&mut self, author: Author) {
+        let nodes = &self.nodes;
+        let committed_blocks = nodes[author.0].node.committed_blocks();
+        for block in &committed_blocks[self.notified_commits[author.0]..] {
+            for listener in &mut self.commit_listeners {
+                listener.on_commit(author, block, self.clock);
+            }
+            for observer in &mut self.observers {
+                observer.on_commit(author, block, self.clock);
+            }
+            if let Some(clients) = self.clients.as_mut() {
+                // Nodes acknowledge the committed requests that their clients sent them.
+                for request in block.transactions.iter().filter_map(|x| x.request) {
+                    if clients.is_waiting_for(author, request) {
+                        let arrival = self.clock.add_delay(self.network_delay, &mut self.rng);
+                        clients.acknowledge(request, arrival);
+                    }
+                }
+            }
+        }
+        self.notified_commits[author.0] = committed_blocks.len();
+        if self.safety_violation.is_none() {
+            if let Err(violation) = self
+                .safety_checker
+                .check_commits(author, nodes[author.0].node.committed_blocks())
+            {
+                error!("@{:?} Safety violation: {}", self.clock, violation);
+                self.safety_violation = Some(violation);
+            }
+        }
+        self.statistics.record_commits(
+            author,
+            nodes[author.0].node.committed_blocks(),
+            self.clock,
+            |block| {
+                nodes[block.proposer.0]
+                    .clock
+                    .global_time_at(block.proposal_time)
+            },
+        );
+    }
+
+    /// Summarize the run so far.
+    pub fn summary(&self) -> RunSummary {
+        let num_nodes = self.nodes.len() as f64;
+        let mut committed_blocks = 0;
+        let mut stake_weighted_committed_blocks = 0;
+        let mut quorum_certificate_rounds = 0;
+        let mut timeout_certificate_rounds = 0;
+        for (index, (node, stake)) in self.nodes.iter().zip(&self.stakes).enumerate() {
+            let commits = self.statistics.measured_commits(Author(index));
+            committed_blocks += commits;
+            stake_weighted_committed_blocks += commits * stake;
+            quorum_certificate_rounds += node.node.quorum_certificate_rounds();
+            timeout_certificate_rounds += node.node.timeout_certificate_rounds();
+        }
+        let committed_blocks = committed_blocks as f64 / num_nodes;
+        let total_stake = self.stakes.iter().sum::<usize>() as f64;
+        let transaction_latencies = self.statistics.transaction_latencies();
+        let committed_transactions = transaction_latencies.len() as f64 / num_nodes;
+        let ended_rounds = quorum_certificate_rounds + timeout_certificate_rounds;
+        let measured_duration = self
+            .statistics
+            .phases()
+            .measured_duration(self.clock)
+            .as_millis_f64();
+        RunSummary {
+            duration: self.clock - GlobalTime(0),
+            committed_blocks,
+            stake_weighted_committed_blocks: if total_stake > 0.0 {
+                stake_weighted_committed_blocks as f64 / total_stake
+            } else {
+                0.0
+            },
+            commit_throughput: if measured_duration > 0.0 {
+                committed_blocks / measured_duration
+            } else {
+                0.0
+            },
+            mean_commit_latency: self.statistics.mean_commit_latency(),
+            median_commit_latency: self.statistics.median_commit_latency(),
+            timeout_rate: if ended_rounds > 0 {
+                timeout_certificate_rounds as f64 / ended_rounds as f64
+            } else {
+                0.0
+            },
+            fast_path_rate: self.statistics.fast_path_rate(),
+            transaction_throughput: if measured_duration > 0.0 {
+                committed_transactions / measured_duration
+            } else {
+                0.0
+            },
+            mean_transaction_latency: if transaction_latencies.is_empty() {
+                None
+            } else {
+                Some(
+                    transaction_latencies
+                        .iter()
+                        .sum::<Duration>()
+                        .as_millis_f64()
+                        / transaction_latencies.len() as f64,
+                )
+            },
+            mean_targeted_latency: self
+                .statistics
+                .targeted_latency_distribution()
+                .map(|distribution| distribution.mean),
+        }
+    }
+}
+
+impl<Node, Context, Notification, Request, Response>
+    Simulator<Node, Context, Notification, Request, Response>
+where
+    Context: CryptoStatistics,
+    Node: ActiveRound + NodeStatistics,
+{
+    /// Quality of the longest chain committed by an honest node, or by any node if all
+    /// nodes are Byzantine.
+    pub fn chain_quality(&self) -> ChainQuality {
+        ChainQuality::new(self.longest_honest_chain(), &self.byzantine_nodes)
+    }
+
+    /// The longest chain committed by an honest node, or by any node if all nodes are
+    /// Byzantine.
+    fn longest_honest_chain(&self) -> &[CommittedBlock] {
+        self.nodes
+            .iter()
+            .enumerate()
+            .max_by_key(|(index, node)| {
+                let is_honest = !self.byzantine_nodes.contains(&Author(*index));
+                (is_honest, node.node.committed_blocks().len())
+            })
+            .map(|(_, node)| node.node.committed_blocks())
+            .unwrap_or(&[])
+    }
+
+    /// How the rounds of the run ended so far, over all the nodes.
+    pub fn view_change_statistics(&self) -> ViewChangeStatistics {
+        let num_nodes = self.nodes.len() as f64;
+        let mut timeouts_by_leader = BTreeMap::new();
+        for node in &self.nodes {
+            for (leader, count) in node.node.timeout_certificate_leaders() {
+                let entry = timeouts_by_leader.entry(leader).or_insert(0);
+                *entry = std::cmp::max(*entry, count);
+            }
+        }
+        ViewChangeStatistics {
+            quorum_certificate_rounds: self
+                .nodes
+                .iter()
+                .map(|node| node.node.quorum_certificate_rounds())
+                .sum::<usize>() as f64
+                / num_nodes,
+            timeout_certificate_rounds: self
+                .nodes
+                .iter()
+                .map(|node| node.node.timeout_certificate_rounds())
+                .sum::<usize>() as f64
+                / num_nodes,
+            wasted_rounds: self.statistics.wasted_rounds_distribution(),
+            timeouts_by_leader,
+        }
+    }
+
+    /// Messages, bytes, and cryptographic operations of all the nodes so far.
+    pub fn resource_costs(&self) -> ResourceCosts {
+        let counters = total_counters(&self.message_counters);
+        ResourceCosts {
+            committed_blocks: self
+                .nodes
+                .iter()
+                .map(|node| node.node.committed_blocks().len())
+                .max()
+                .unwrap_or(0),
+            messages: counters.sent.messages(),
+            bytes: counters.bytes_sent,
+            signatures: self
+                .nodes
+                .iter()
+                .map(|node| node.context.signatures_created())
+                .sum(),
+            verifications: self
+                .nodes
+                .iter()
+                .map(|node| node.context.signatures_verified())
+                .sum(),
+            macs: self
+                .nodes
+                .iter()
+                .map(|node| node.context.macs_computed())
+                .sum(),
+        }
+    }
+
+    /// Metrics of the run so far, per node and for the whole run.
+    pub fn metrics_report(&self) -> MetricsReport {
+        let view_changes = self.view_change_statistics();
+        let summary = self.summary();
+        let nodes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let counters = &self.message_counters[index];
+                let switch_times: Vec<_> = node
+                    .node
+                    .pacemaker_switch_times()
+                    .into_iter()
+                    .map(|time| node.clock.global_time_at(time))
+                    .collect();
+                NodeMetrics {
+                    author: index,
+                    active_round: node.node.active_round().0,
+                    committed_blocks: node.node.committed_blocks().len(),
+                    highest_committed_round: node
+                        .node
+                        .committed_blocks()
+                        .iter()
+                        .map(|block| block.round.0)
+                        .max(),
+                    quorum_certificate_rounds: node.node.quorum_certificate_rounds(),
+                    timeout_certificate_rounds: node.node.timeout_certificate_rounds(),
+                    mean_commit_latency: self.statistics.node_mean_commit_latency(Author(index)),
+                    crashed: node.is_crashed(self.clock),
+                    messages_sent: counters.sent.messages(),
+                    messages_received: counters.received.messages(),
+                    records_sent: counters.sent.records.total(),
+                    records_received: counters.received.records.total(),
+                    timeouts_as_leader: view_changes
+                        .timeouts_by_leader
+                        .get(&Author(index))
+                        .cloned()
+                        .unwrap_or(0),
+                    stake: self.stakes[index],
+                    errors: self.errors[index],
+                    pacemaker_switches: switch_times.len(),
+                    pacemaker_switch_times: switch_times,
+                    evicted_records: node.node.evicted_records(),
+                    suppressed_notifications: node.node.suppressed_notifications(),
+                    corrupted_messages: counters.corrupted,
+                    rejected_messages: counters.undecodable + node.node.rejected_messages(),
+                    over_quota_messages: counters.over_quota,
+                    suspicions: node.node.suspicions(),
+                    rejected_proposals: node.node.rejected_proposals(),
+                }
+            })
+            .collect();
+        MetricsReport {
+            schema_version: METRICS_SCHEMA_VERSION,
+            aggregate: AggregateMetrics {
+                duration: summary.duration.as_millis(),
+                committed_blocks: summary.committed_blocks,
+                commit_throughput: summary.commit_throughput,
+                timeout_rate: summary.timeout_rate,
+                commit_latency: self.statistics.commit_latency_distribution(),
+                rounds_per_commit: self.statistics.rounds_per_commit_distribution(),
+                chain_quality: self.chain_quality(),
+                wasted_rounds: view_changes.wasted_rounds,
+                transaction_throughput: summary.transaction_throughput,
+                transaction_latency: self.statistics.transaction_latency_distribution(),
+                stake_weighted_committed_blocks: summary.stake_weighted_committed_blocks,
+                fast_path_rate: summary.fast_path_rate,
+                resource_costs: self.resource_costs(),
+            },
+            nodes,
+        }
+    }
+
+    /// A self-contained HTML report of the run so far: throughput, latency percentiles,
+    /// timeout rates, leader distribution, and fault timeline.
+    pub fn html_report(&self, title: &str) -> HtmlReport {
+        let summary = self.summary();
+        let view_changes = self.view_change_statistics();
+        let mut report = HtmlReport::new(title);
+
+        report.heading("Summary");
+        let summary_rows = vec![
+            ("nodes", self.nodes.len().to_string()),
+            ("duration (ms)", summary.duration.to_string()),
+            ("committed blocks", format_number(summary.committed_blocks)),
+            (
+                "commit throughput (per node per s)",
+                format_number(summary.commit_throughput * 1000.0),
+            ),
+            ("timeout rate", format_number(summary.timeout_rate)),
+            (
+                "transaction throughput (per node per s)",
+                format_number(summary.transaction_throughput * 1000.0),
+            ),
+            (
+                "honest fraction of the chain",
+                self.chain_quality()
+                    .honest_fraction()
+                    .map_or(String::new(), format_number),
+            ),
+        ];
+        let summary_rows: Vec<_> = summary_rows
+            .into_iter()
+            .map(|(name, value)| vec![name.to_string(), value])
+            .collect();
+        report.table(&["metric", "value"], &summary_rows);
+
+        report.heading("Throughput");
+        let window = Duration::from_millis(std::cmp::max(self.clock.0 / 50, 1));
+        let points: Vec<_> = self
+            .statistics
+            .commit_throughput_series(window, self.clock)
+            .iter()
+            .enumerate()
+            .map(|(index, value)| ((index as i64 * window.as_millis()) as f64, value * 1000.0))
+            .collect();
+        report.line_chart(
+            &format!(
+                "Commits per node and per second, over windows of {}ms",
+                window
+            ),
+            &points,
+        );
+
+        report.heading("Latency");
+        let commit_latency = self.statistics.commit_latency_distribution();
+        report.table(
+            &DISTRIBUTION_HEADERS,
+            &[
+                distribution_row("commit latency (ms)", &commit_latency),
+                distribution_row(
+                    "transaction latency (ms)",
+                    &self.statistics.transaction_latency_distribution(),
+                ),
+                distribution_row(
+                    "rounds per commit",
+                    &self.statistics.rounds_per_commit_distribution(),
+                ),
+                distribution_row("wasted rounds", &view_changes.wasted_rounds),
+            ],
+        );
+        if let Some(latency) = commit_latency {
+            let bucket_width = Duration::from_millis(std::cmp::max(latency.max as i64 / 20, 1));
+            let counts = self.statistics.commit_latency_histogram(bucket_width);
+            let labels: Vec<_> = (0..counts.len())
+                .map(|index| (index as i64 * bucket_width.as_millis()).to_string())
+                .collect();
+            let values: Vec<_> = counts.iter().map(|count| *count as f64).collect();
+            report.bar_chart(
+                &format!("Commit latencies, in buckets of {}ms", bucket_width),
+                &labels,
+                &values,
+            );
+        }
+
+        report.heading("Timeouts");
+        let labels: Vec<_> = (0..self.nodes.len())
+            .map(|index| index.to_string())
+            .collect();
+        let mut timeout_rates = Vec::new();
+        let mut timeout_rows = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let quorum_certificate_rounds = node.node.quorum_certificate_rounds();
+            let timeout_certificate_rounds = node.node.timeout_certificate_rounds();
+            let rounds = quorum_certificate_rounds + timeout_certificate_rounds;
+            let rate = if rounds == 0 {
+                0.0
+            } else {
+                timeout_certificate_rounds as f64 / rounds as f64
+            };
+            timeout_rates.push(rate);
+            timeout_rows.push(vec![
+                index.to_string(),
+                quorum_certificate_rounds.to_string(),
+                timeout_certificate_rounds.to_string(),
+                format_number(rate),
+            ]);
+        }
+        report.table(
+            &["node", "QC rounds", "TC rounds", "timeout rate"],
+            &timeout_rows,
+        );
+        report.bar_chart("Timeout rate per node", &labels, &timeout_rates);
+
+        report.heading("Leaders");
+        let mut proposed = vec![0usize; self.nodes.len()];
+        let chain = self.longest_honest_chain();
+        for block in chain {
+            proposed[block.proposer.0] += 1;
+        }
+        let leader_rows: Vec<_> = proposed
+            .iter()
+            .enumerate()
+            .map(|(index, count)| {
+                let share = if chain.is_empty() {
+                    0.0
+                } else {
+                    *count as f64 / chain.len() as f64
+                };
+                vec![
+                    index.to_string(),
+                    if self.byzantine_nodes.contains(&Author(index)) {
+                        "Byzantine".to_string()
+                    } else {
+                        "honest".to_string()
+                    },
+                    count.to_string(),
+                    format_number(share),
+                    view_changes
+                        .timeouts_by_leader
+                        .get(&Author(index))
+                        .cloned()
+                        .unwrap_or(0)
+                        .to_string(),
+                ]
+            })
+            .collect();
+        report.table(
+            &["node", "kind", "committed blocks", "share", "TCs as leader"],
+            &leader_rows,
+        );
+        let values: Vec<_> = proposed.iter().map(|count| *count as f64).collect();
+        report.bar_chart(
+            "Blocks of the longest honest chain, per proposer",
+            &labels,
+            &values,
+        );
+
+        report.heading("Faults");
+        let mut faults = self.fault_log.clone();
+        faults.sort_by_key(|(time, _)| *time);
+        if faults.is_empty() {
+            report.paragraph("No fault was injected.");
+        } else {
+            let events: Vec<_> = faults
+                .iter()
+                .map(|(time, fault)| (time.0 as f64, fault.to_string()))
+                .collect();
+            report.timeline(
+                "Faults over simulated time (ms)",
+                self.clock.0 as f64,
+                &events,
+            );
+            let fault_rows: Vec<_> = faults
+                .iter()
+                .map(|(time, fault)| vec![time.0.to_string(), fault.to_string()])
+                .collect();
+            report.table(&["time (ms)", "fault"], &fault_rows);
+        }
+        report
+    }
+}
+
+impl<Node, Context, Notification, Request, Response>
+    Simulator<Node, Context, Notification, Request, Response>
+where
+    Context: AuthenticatedChannels + ClientRequests + ExecutionTime + std::fmt::Debug,
     Node: ConsensusNode<Context>
         + DataSyncNode<
             Context,
@@ -210,11 +1621,138 @@ where
             Request = Request,
             Response = Response,
         > + ActiveRound
+        + ActiveLeader
+        + NodeErrors
+        + NodeStatistics
+        + PeerTracker
+        + RecoverableNode<Context>
         + std::fmt::Debug,
-    Notification: std::cmp::Ord + std::fmt::Debug + std::clone::Clone,
-    Request: std::cmp::Ord + std::fmt::Debug + std::clone::Clone,
-    Response: std::cmp::Ord + std::fmt::Debug,
+    Notification: std::fmt::Debug + std::clone::Clone + CountRecords + WireFormat,
+    Request: std::fmt::Debug + std::clone::Clone + CountRecords + WireFormat,
+    Response: std::fmt::Debug + CountRecords + WireFormat,
 {
+    /// Let the clients act at each of their own times up to `time`, so that they do not wait for
+    /// the events of the nodes.
+    fn poll_clients_until(&mut self, time: GlobalTime) {
+        while let Some(next_time) = self.clients.as_ref().and_then(Clients::next_time) {
+            if next_time > time {
+                break;
+            }
+            self.poll_clients(std::cmp::max(next_time, self.clock));
+        }
+    }
+
+    /// Let the clients act at `time`: send their requests, and deliver the requests that
+    /// reached running nodes.
+    fn poll_clients(&mut self, time: GlobalTime) {
+        let clients = match self.clients.as_mut() {
+            Some(clients) => clients,
+            None => return,
+        };
+        for (author, request) in clients.poll(time) {
+            let arrival = time.add_delay(self.network_delay, &mut self.rng);
+            clients.send(arrival, author, request);
+        }
+        for (author, request) in clients.arrivals_until(time) {
+            let node = &mut self.nodes[author.0];
+            if !node.is_running(time) {
+                continue;
+            }
+            let clock = node.local_time(time);
+            if !node.context.submit_request(request, clock) {
+                continue;
+            }
+            clients.accept(author, request);
+            // Forward the new request to other nodes on the same side of a partition.
+            let (peers, delay) = clients.gossip_targets(author, &mut self.rng);
+            for peer in peers {
+                let is_partitioned = match &self.partition {
+                    Some(groups) => groups[author.0] != groups[peer.0],
+                    None => false,
+                };
+                if !is_partitioned {
+                    let arrival = (time + delay).add_delay(self.network_delay, &mut self.rng);
+                    clients.send(arrival, peer, request);
+                }
+            }
+        }
+        for (completion_time, latency) in clients.take_completions() {
+            self.statistics
+                .record_request_latency(completion_time, latency);
+        }
+    }
+
+    /// Let the flooding nodes send their bursts of messages up to `time`. Stopped nodes skip
+    /// their bursts.
+    fn poll_floods(&mut self, time: GlobalTime) {
+        let attackers: Vec<_> = self.floods.keys().cloned().collect();
+        for attacker in attackers {
+            while let Some((flood, burst_time)) = self.floods.get(&attacker).cloned() {
+                if burst_time > time {
+                    break;
+                }
+                self.floods
+                    .insert(attacker, (flood, burst_time + flood.period));
+                if self.simulated_node(attacker).is_running(burst_time) {
+                    self.send_flood(attacker, flood, burst_time);
+                }
+            }
+        }
+    }
+
+    fn send_flood(&mut self, attacker: Author, flood: Flood, departure: GlobalTime) {
+        let notification = Rc::new(self.simulated_node(attacker).node.create_notification());
+        let request = Rc::new(self.simulated_node(attacker).node.create_request());
+        for index in 0..self.nodes.len() {
+            let victim = Author(index);
+            if victim == attacker {
+                continue;
+            }
+            for _ in 0..flood.notifications {
+                let event = Event::DataSyncNotifyEvent {
+                    sender: attacker,
+                    receiver: victim,
+                    notification: notification.clone(),
+                };
+                self.schedule_network_event_at(departure, event);
+            }
+            for _ in 0..flood.requests {
+                let event = Event::DataSyncRequestEvent {
+                    receiver: attacker,
+                    sender: victim,
+                    request: request.clone(),
+                };
+                self.schedule_network_event_at(departure, event);
+            }
+        }
+    }
+
+    /// Report the update of a node to the observers, then record its new commits.
+    fn record_update(&mut self, author: Author) {
+        let round = self.simulated_node(author).active_round();
+        let has_new_round = round != self.observed_rounds[author.0];
+        self.observed_rounds[author.0] = round;
+        for recovery in &mut self.eclipse_recoveries {
+            if recovery.victim == author
+                && recovery.recovery_time.is_none()
+                && round >= recovery.target_round
+            {
+                recovery.recovery_time = Some(self.clock);
+            }
+        }
+        for observer in &mut self.observers {
+            observer.on_node_updated(author, self.clock);
+            if has_new_round {
+                observer.on_round_change(author, round, self.clock);
+            }
+        }
+        if has_new_round && self.stall_threshold.is_some() {
+            let leader = self.simulated_node(author).node.active_leader();
+            self.stall_tracker.record_round(round, leader);
+        }
+        self.record_commits(author);
+    }
+
     fn process_node_actions(
         &mut self,
         clock: GlobalTime,
@@ -225,24 +1763,8 @@ where
             "@{:?} Processing node actions for {:?}: {:?}",
             clock, author, actions
         );
-        // Timers
-        let new_deadline = {
-            let mut node = self.nodes.get_mut(author.0).unwrap();
-            let new_deadline = std::cmp::max(
-                GlobalTime::from_node_time(actions.next_scheduled_update, node.startup_time),
-                // Make sure we schedule the update strictly in the future so it does not get
-                // ignored by `ignore_scheduled_updates_until` below.
-                clock + 1,
-            );
-            // We don't remove the previously scheduled updates but this will cancel them.
-            node.ignore_scheduled_updates_until = new_deadline + (-1);
-            new_deadline
-            // scoping the mutable 'node' for the borrow checker
-        };
-        let event = Event::UpdateTimerEvent { author };
-        self.schedule_event(new_deadline, event);
-        // Notifications
-        let mut receivers = HashSet::new();
+        // Ordered sets make the sequence of random delays, hence the run, a function of the seed.
+        let mut receivers = BTreeSet::new();
         for node in actions.should_send {
             receivers.insert(node);
         }
@@ -253,16 +1775,13 @@ where
                 }
             }
         }
-        let notification = self.simulated_node(author).node.create_notification();
-        for receiver in receivers {
-            self.schedule_network_event(Event::DataSyncNotifyEvent {
-                sender: author,
-                receiver,
-                notification: notification.clone(),
-            });
+        for node in actions.should_skip {
+            receivers.remove(&node);
+        }
+        let mut senders = BTreeSet::new();
+        for node in actions.should_query {
+            senders.insert(node);
         }
-        // Queries
-        let mut senders = HashSet::new();
         if actions.should_query_all {
             for index in 0..self.nodes.len() {
                 if index != author.0 {
@@ -270,43 +1789,271 @@ where
                 }
             }
         }
-        let request = self.simulated_node(author).node.create_request();
-        for sender in senders {
-            self.schedule_network_event(Event::DataSyncRequestEvent {
-                receiver: author,
-                sender,
-                request: request.clone(),
-            });
+        if !receivers.is_empty() {
+            self.authenticate_message(author, receivers.len());
+        }
+        if !senders.is_empty() {
+            self.authenticate_message(author, senders.len());
+        }
+        // Messages leave once the node is done executing commands.
+        let departure = {
+            let node = self.nodes.get_mut(author.0).unwrap();
+            node.busy_until = clock + node.context.take_execution_time();
+            node.busy_until
+        };
+        // Timers
+        let new_deadline = {
+            let mut node = self.nodes.get_mut(author.0).unwrap();
+            let new_deadline = std::cmp::max(
+                node.clock.global_time_at(actions.next_scheduled_update),
+                // Make sure we schedule the update strictly in the future so it does not get
+                // ignored by `ignore_scheduled_updates_until` below.
+                clock + Duration::from_millis(1),
+            );
+            // We don't remove the previously scheduled updates but this will cancel them.
+            node.ignore_scheduled_updates_until = new_deadline - Duration::from_millis(1);
+            if node.scheduled_update == Some(new_deadline) {
+                // The same update is already in the queue.
+                None
+            } else {
+                node.scheduled_update = Some(new_deadline);
+                Some(new_deadline)
+            }
+            // scoping the mutable 'node' for the borrow checker
+        };
+        if let Some(new_deadline) = new_deadline {
+            let event = Event::UpdateTimerEvent { author };
+            self.schedule_event(new_deadline, event);
+        }
+        // Notifications
+        if !receivers.is_empty() {
+            let notification = Rc::new(self.simulated_node(author).node.create_notification());
+            for receiver in receivers {
+                let event = Event::DataSyncNotifyEvent {
+                    sender: author,
+                    receiver,
+                    notification: notification.clone(),
+                };
+                self.schedule_network_event_at(departure, event);
+            }
+        }
+        // Queries
+        if !senders.is_empty() {
+            let request = Rc::new(self.simulated_node(author).node.create_request());
+            for sender in senders {
+                let event = Event::DataSyncRequestEvent {
+                    receiver: author,
+                    sender,
+                    request: request.clone(),
+                };
+                self.schedule_network_event_at(departure, event);
+            }
         }
     }
 
+    /// Charge `author` for authenticating a message sent to `recipients` nodes.
+    fn authenticate_message(&mut self, author: Author, recipients: usize) {
+        let bytes = self.nodes[author.0]
+            .context
+            .authenticate_message(recipients);
+        self.message_counters[author.0].bytes_sent += bytes * recipients;
+    }
+
     pub fn loop_until(&mut self, max_clock: GlobalTime, csv_path: Option<String>) -> Vec<&Context> {
+        self.run(&TerminationCondition::at_time(max_clock), csv_path);
+        self.contexts()
+    }
+
+    /// Describe the state of the nodes, to report violations.
+    fn describe_nodes(&self) -> String {
+        let nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                format!(
+                    "node {}: round {}, {} commits, {} pending messages{}",
+                    index,
+                    node.node.active_round().0,
+                    node.node.committed_blocks().len(),
+                    self.pending_messages[index],
+                    if node.is_crashed(self.clock) {
+                        ", crashed"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect();
+        nodes.join("; ")
+    }
+
+    fn report_stall(&mut self) {
+        let report = self.stall_report();
+        warn!("Stall detected: {}", report);
+        self.stall_reports.push(report);
+        self.has_reported_stall = true;
+    }
+
+    /// Hash of the rounds, commits, and certificates of the nodes, and of the number of
+    /// pending events. Ignores the clock, so that equivalent schedules lead to equal hashes.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for node in &self.nodes {
+            node.node.active_round().hash(&mut hasher);
+            let committed_blocks = node.node.committed_blocks();
+            committed_blocks.len().hash(&mut hasher);
+            committed_blocks.last().map(|x| x.hash).hash(&mut hasher);
+            node.node.quorum_certificate_rounds().hash(&mut hasher);
+            node.node.timeout_certificate_rounds().hash(&mut hasher);
+        }
+        self.pending_events.len().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Process events until one of the termination conditions holds.
+    pub fn run(
+        &mut self,
+        termination: &TerminationCondition,
+        csv_path: Option<String>,
+    ) -> StopReason {
+        self.run_internal(termination, csv_path, None)
+    }
+
+    /// Run the simulation, letting `scheduler` pick the next event at each step.
+    pub fn run_with_scheduler(
+        &mut self,
+        termination: &TerminationCondition,
+        scheduler: &mut Scheduler,
+    ) -> StopReason {
+        self.run_internal(termination, None, Some(scheduler))
+    }
+
+    fn run_internal(
+        &mut self,
+        termination: &TerminationCondition,
+        csv_path: Option<String>,
+        mut scheduler: Option<&mut Scheduler>,
+    ) -> StopReason {
+        // Browsers have no file system, so wasm builds do not write CSV files.
+        #[cfg(not(target_arch = "wasm32"))]
         let mut data_writer = {
             match csv_path {
                 Some(path) => Some(DataWriter::new(self.nodes.len(), path)),
                 None => None,
             }
         };
+        #[cfg(target_arch = "wasm32")]
+        let _ = csv_path;
 
-        while let Some(ScheduledEvent(std::cmp::Reverse(clock), event)) = self.pending_events.pop()
-        {
-            if clock > max_clock {
-                break;
+        let mut dashboard = self.dashboard.take();
+        let mut has_reported_violation = false;
+
+        let stop_reason = loop {
+            let time = match self.pending_events.peek_time() {
+                Some(time) => time,
+                None => break StopReason::NoMoreEvents,
+            };
+            if termination.is_past_max_clock(time) {
+                break StopReason::MaxClock;
+            }
+            if !self.nemeses.is_empty() {
+                self.poll_nemeses(std::cmp::max(time, self.clock));
+            }
+            if self.clients.is_some() {
+                self.poll_clients_until(std::cmp::max(time, self.clock));
+            }
+            if !self.floods.is_empty() {
+                self.poll_floods(std::cmp::max(time, self.clock));
+            }
+            let choice = match scheduler.as_mut() {
+                None => 0,
+                Some(scheduler) => {
+                    let choices = std::cmp::min(scheduler.width(), self.pending_events.len());
+                    match scheduler.choose(choices, self.state_hash()) {
+                        Some(choice) => choice,
+                        None => break StopReason::SchedulerStopped,
+                    }
+                }
+            };
+            let (time, event) = self.pending_events.pop_nth(choice).unwrap();
+            if termination.is_past_max_clock(time) {
+                // The scheduler picked an event after the end of the run.
+                self.pending_events.push(time, event);
+                break StopReason::MaxClock;
+            }
+            let busy_until = self.simulated_node(event.handling_node()).busy_until;
+            if time < busy_until {
+                // The handling node is still executing commands.
+                self.pending_events.push(busy_until, event);
+                continue;
+            }
+            if !event.is_local() {
+                self.pending_messages[event.handling_node().0] -= 1;
             }
 
-            if let Some(data_writer_val) = data_writer.as_mut() {
-                data_writer_val.update_round_number(&self, &clock);
-                data_writer_val.add_message_counter(&event);
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                if let Some(data_writer_val) = data_writer.as_mut() {
+                    data_writer_val.add_message_counter(&event);
+                }
             }
 
             // Events scheduled in the past are fine but they do not move the clock.
-            let clock = std::cmp::max(clock, self.clock);
+            let clock = std::cmp::max(time, self.clock);
             self.clock = clock;
+            let handling_node = event.handling_node();
+            if !self.simulated_node(handling_node).is_running(clock) {
+                debug!("@{:?} Dropping event of stopped node {:?}", clock, event);
+                self.record_drop(&event, DropCause::StoppedNode);
+                continue;
+            }
+            if self.is_partitioned(event.sending_node(), handling_node) {
+                debug!(
+                    "@{:?} Dropping event across the partition {:?}",
+                    clock, event
+                );
+                self.record_drop(&event, DropCause::Partition);
+                continue;
+            }
+            if !event.is_local() && !self.is_within_quota(&event) {
+                debug!("@{:?} Dropping event over quota {:?}", clock, event);
+                continue;
+            }
             debug!("@{:?} Processing event {:?}", clock, event);
+            if let Some(kind) = event.message_kind() {
+                self.message_counters[handling_node.0]
+                    .received
+                    .add_message(kind, event.record_counts());
+                let sender = event.sending_node();
+                let node = self.simulated_node_mut(handling_node);
+                node.context.check_message_authentication();
+                node.node.receive_message(sender, kind);
+                for observer in &mut self.observers {
+                    observer.on_message_delivered(event.sending_node(), handling_node, kind, clock);
+                }
+            }
+            if let Some(log) = self.event_log.as_mut() {
+                log.push(EventRecord {
+                    time: clock,
+                    node: handling_node,
+                    kind: event.kind(),
+                });
+            }
+            let num_commits = self.statistics.commit_latencies().len();
+            // Records logged by the handlers go to the logs of the handling node.
+            let node_log = node_logs::enter(handling_node);
+            let measurements = self
+                .handler_profile
+                .as_ref()
+                .map(|_| handler_profile::start());
             match event {
                 Event::UpdateTimerEvent { author } => {
                     let actions = {
                         let node = self.simulated_node_mut(author);
+                        if node.scheduled_update == Some(time) {
+                            node.scheduled_update = None;
+                        }
                         if clock <= node.ignore_scheduled_updates_until {
                             // This scheduled update was invalidated in the meantime.
                             debug!("@{:?} Timer was cancelled: {:?}", clock, event);
@@ -315,6 +2062,25 @@ where
                         node.update(clock)
                     };
                     trace!("Node state: {:?}", self.simulated_node(author));
+                    self.record_update(author);
+                    self.process_node_actions(clock, author, actions);
+                }
+                Event::RecoveryEvent { author } => {
+                    let actions = {
+                        let node = self.simulated_node_mut(author);
+                        let local_clock = node.local_time(clock);
+                        let context = &mut node.context;
+                        let inner = &mut node.node;
+                        handler_profile::measure(Handler::Recovery, || {
+                            inner.recover(local_clock, context)
+                        });
+                        // Timers set before the crash were lost.
+                        node.ignore_scheduled_updates_until = clock - Duration::from_millis(1);
+                        node.scheduled_update = None;
+                        node.update(clock)
+                    };
+                    trace!("Node state: {:?}", self.simulated_node(author));
+                    self.record_update(author);
                     self.process_node_actions(clock, author, actions);
                 }
                 Event::DataSyncNotifyEvent {
@@ -323,15 +2089,18 @@ where
                     notification,
                 } => {
                     let node = self.simulated_node_mut(receiver);
-                    let result = node
-                        .node
-                        .handle_notification(notification, &mut node.context);
+                    let context = &mut node.context;
+                    let inner = &mut node.node;
+                    let result = handler_profile::measure(Handler::Notification, || {
+                        inner.handle_notification(sender, &notification, context)
+                    });
                     let actions = node.update(clock);
                     if let Some(request) = result {
+                        self.authenticate_message(receiver, 1);
                         self.schedule_network_event(Event::DataSyncRequestEvent {
                             sender,
                             receiver,
-                            request,
+                            request: Rc::new(request),
                         });
                     }
                     trace!(
@@ -339,6 +2108,7 @@ where
                         self.simulated_node(receiver),
                         receiver
                     );
+                    self.record_update(receiver);
                     self.process_node_actions(clock, receiver, actions);
                 }
                 Event::DataSyncRequestEvent {
@@ -346,7 +2116,11 @@ where
                     sender,
                     request,
                 } => {
-                    let response = self.simulated_node_mut(sender).node.handle_request(request);
+                    let node = &self.simulated_node(sender).node;
+                    let response = handler_profile::measure(Handler::Request, || {
+                        node.handle_request(&request)
+                    });
+                    self.authenticate_message(sender, 1);
                     self.schedule_network_event(Event::DataSyncResponseEvent {
                         sender,
                         receiver,
@@ -354,23 +2128,119 @@ where
                     });
                 }
                 Event::DataSyncResponseEvent {
-                    receiver, response, ..
+                    receiver,
+                    sender,
+                    response,
                 } => {
                     let node = self.simulated_node_mut(receiver);
-                    let local_clock = clock.to_node_time(node.startup_time);
-                    node.node
-                        .handle_response(response, &mut node.context, local_clock);
+                    let local_clock = node.local_time(clock);
+                    let context = &mut node.context;
+                    let inner = &mut node.node;
+                    handler_profile::measure(Handler::Response, || {
+                        inner.handle_response(sender, response, context, local_clock)
+                    });
                     let actions = node.update(clock);
                     trace!("Node state: {:?}", node);
+                    self.record_update(receiver);
                     self.process_node_actions(clock, receiver, actions);
                 }
             }
-        }
+            // Nodes skip unexpected inputs instead of aborting the run.
+            for error in self.simulated_node_mut(handling_node).node.take_errors() {
+                warn!("@{:?} Error of node {:?}: {}", clock, handling_node, error);
+                self.errors[handling_node.0] += 1;
+            }
+            drop(node_log);
+            if let (Some(profile), Some(measurements)) =
+                (self.handler_profile.as_mut(), measurements)
+            {
+                for (handler, elapsed) in measurements.finish() {
+                    profile.record(handling_node, handler, elapsed);
+                }
+            }
 
-        if let Some(data_writer_val) = data_writer {
-            data_writer_val.write_to_file();
-        }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                if let Some(data_writer_val) = data_writer.as_mut() {
+                    data_writer_val.update_round_number(
+                        handling_node,
+                        self.simulated_node(handling_node).active_round(),
+                        clock,
+                    );
+                }
+            }
+            if let Some(dashboard) = dashboard.as_mut() {
+                self.refresh_dashboard(dashboard, false);
+            }
 
-        self.nodes.iter().map(|node| &node.context).collect()
+            let (round, committed_blocks) = {
+                let node = &self.simulated_node(handling_node).node;
+                (node.active_round(), node.committed_blocks().len())
+            };
+            let has_new_commits = self.statistics.commit_latencies().len() > num_commits;
+            if has_new_commits || !self.has_honest_quorum(clock) {
+                if has_new_commits {
+                    self.progress_round.max_update(round);
+                }
+                self.liveness_reference = clock;
+                self.stall_tracker.reset();
+                self.has_reported_stall = false;
+            }
+            if let Some(threshold) = self.stall_threshold {
+                if !self.has_reported_stall && clock - self.liveness_reference > threshold {
+                    self.report_stall();
+                }
+            }
+            if let Some(violation) = &self.safety_violation {
+                break StopReason::SafetyViolation(violation.to_string());
+            }
+            if termination.has_enough_commits(committed_blocks) {
+                break StopReason::CommittedBlocks;
+            }
+            if termination.has_stalled(round, self.progress_round) {
+                break StopReason::NoProgress;
+            }
+            if termination.has_missed_progress_bound(clock, self.liveness_reference) {
+                if self.stall_threshold.is_some() && !self.has_reported_stall {
+                    self.report_stall();
+                }
+                break StopReason::LivenessViolation(format!(
+                    "No commit between {:?} and {:?} ({})",
+                    self.liveness_reference,
+                    clock,
+                    self.describe_nodes()
+                ));
+            }
+            if let Err((name, message)) = self.check_invariants(has_new_commits) {
+                let message = format!(
+                    "{} at {:?}: {} ({})",
+                    name,
+                    clock,
+                    message,
+                    self.describe_nodes()
+                );
+                if termination.stop_on_invariant_violation {
+                    break StopReason::InvariantViolation(message);
+                }
+                if !has_reported_violation {
+                    warn!("Invariant violated: {}", message);
+                    has_reported_violation = true;
+                }
+            }
+        };
+        debug!("@{:?} Stopping simulation: {:?}", self.clock, stop_reason);
+
+        if let Some(mut dashboard) = dashboard {
+            // Show the final state.
+            self.refresh_dashboard(&mut dashboard, true);
+            self.dashboard = Some(dashboard);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(data_writer_val) = data_writer {
+                data_writer_val.write_to_file();
+            }
+        }
+        stop_reason
     }
 }
@@ -0,0 +1,229 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Diagnostics of stalled runs, in which no block is committed for a long time.
+//!
+//! From the start of a stall, i.e. the latest commit or the latest time a quorum of honest
+//! nodes was not running, the simulator records the leaders of the rounds entered by the nodes
+//! and the network messages that it drops, by link and by cause. Once the stall lasts longer
+//! than the threshold given to `Simulator::diagnose_stalls`, or when a run fails its progress
+//! bound, it reports these records along with the state of each node, so that stalled runs
+//! explain themselves.
+
+use crate::{
+    base_types::{Author, Round},
+    inspection::NodeView,
+    simulator::GlobalTime,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+#[cfg(test)]
+#[path = "unit_tests/stall_tests.rs"]
+mod stall_tests;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum StallKind {
+    /// Nodes keep entering new rounds without committing, e.g. because every round times out.
+    Livelock,
+    /// No node entered a new round since the start of the stall.
+    Deadlock,
+}
+
+/// Why the simulator dropped a network message.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+pub enum DropCause {
+    Loss,
+    Partition,
+    Eclipse,
+    /// The message was damaged beyond decoding.
+    Corruption,
+    /// The message exceeded the quota of its sender at its receiver.
+    Quota,
+    /// The receiver was stopped when the message arrived.
+    StoppedNode,
+}
+
+/// Leaders and dropped messages since the start of the current stall.
+#[derive(Clone, Debug, Default)]
+pub struct StallTracker {
+    leaders: BTreeMap<Round, BTreeSet<Author>>,
+    dropped: BTreeMap<(Author, Author), BTreeMap<DropCause, usize>>,
+}
+
+/// A node at the time of a stall report.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct NodeStall {
+    pub view: NodeView,
+    /// Rounds after the highest QC of the node that ended with a TC.
+    pub timeout_certificate_rounds: Vec<Round>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct StallReport {
+    pub time: GlobalTime,
+    /// Start of the stall.
+    pub since: GlobalTime,
+    pub kind: StallKind,
+    pub nodes: Vec<NodeStall>,
+    /// Leaders of the rounds entered since the start of the stall, as seen by the nodes.
+    pub leaders: BTreeMap<Round, BTreeSet<Author>>,
+    /// Messages dropped since the start of the stall, per sender and receiver.
+    pub dropped: BTreeMap<(Author, Author), BTreeMap<DropCause, usize>>,
+}
+
+impl StallTracker {
+    /// Start a new stall.
+    pub fn reset(&mut self) {
+        self.leaders.clear();
+        self.dropped.clear();
+    }
+
+    /// Record that a node entered `round`, led by `leader`.
+    pub fn record_round(&mut self, round: Round, leader: Option<Author>) {
+        let leaders = self.leaders.entry(round).or_default();
+        leaders.extend(leader);
+    }
+
+    pub fn record_drop(&mut self, sender: Author, receiver: Author, cause: DropCause) {
+        *self
+            .dropped
+            .entry((sender, receiver))
+            .or_default()
+            .entry(cause)
+            .or_insert(0) += 1;
+    }
+
+    pub fn report(
+        &self,
+        time: GlobalTime,
+        since: GlobalTime,
+        nodes: Vec<NodeStall>,
+    ) -> StallReport {
+        StallReport {
+            time,
+            since,
+            kind: if self.leaders.is_empty() {
+                StallKind::Deadlock
+            } else {
+                StallKind::Livelock
+            },
+            nodes,
+            leaders: self.leaders.clone(),
+            dropped: self.dropped.clone(),
+        }
+    }
+}
+
+impl NodeStall {
+    /// Rounds after the highest QC of the node, up to its active round, that ended with neither
+    /// a QC nor a TC.
+    pub fn missing_certificate_rounds(&self) -> Vec<Round> {
+        let first = self.view.highest_quorum_certificate_round.0 + 1;
+        (first..=self.view.active_round.0)
+            .map(Round)
+            .filter(|round| !self.timeout_certificate_rounds.contains(round))
+            .collect()
+    }
+}
+
+impl StallReport {
+    /// Highest QC round known to any node.
+    pub fn highest_quorum_certificate_round(&self) -> Round {
+        self.nodes
+            .iter()
+            .map(|node| node.view.highest_quorum_certificate_round)
+            .max()
+            .unwrap_or(Round(0))
+    }
+
+    /// Messages dropped since the start of the stall, per cause.
+    pub fn dropped_by_cause(&self) -> BTreeMap<DropCause, usize> {
+        let mut total = BTreeMap::new();
+        for causes in self.dropped.values() {
+            for (cause, count) in causes {
+                *total.entry(*cause).or_insert(0) += count;
+            }
+        }
+        total
+    }
+}
+
+impl fmt::Display for StallKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StallKind::Livelock => write!(f, "livelock"),
+            StallKind::Deadlock => write!(f, "deadlock"),
+        }
+    }
+}
+
+impl fmt::Display for DropCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DropCause::Loss => "lost",
+            DropCause::Partition => "partitioned",
+            DropCause::Eclipse => "eclipsed",
+            DropCause::Corruption => "undecodable",
+            DropCause::Quota => "over quota",
+            DropCause::StoppedNode => "to a stopped node",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for StallReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "No commit between {} and {} ({}), highest QC at round {}",
+            self.since.0,
+            self.time.0,
+            self.kind,
+            self.highest_quorum_certificate_round().0
+        )?;
+        for node in &self.nodes {
+            let view = &node.view;
+            writeln!(
+                f,
+                "node {}: {}, round {}, leader {}, highest QC at round {}, TCs at rounds {:?}, \
+                 no QC or TC at rounds {:?}, {} pending messages",
+                view.author.0,
+                if view.running { "running" } else { "stopped" },
+                view.active_round.0,
+                view.active_leader
+                    .map_or("unknown".to_string(), |x| x.0.to_string()),
+                view.highest_quorum_certificate_round.0,
+                node.timeout_certificate_rounds
+                    .iter()
+                    .map(|x| x.0)
+                    .collect::<Vec<_>>(),
+                node.missing_certificate_rounds()
+                    .iter()
+                    .map(|x| x.0)
+                    .collect::<Vec<_>>(),
+                view.pending.messages
+            )?;
+        }
+        for (round, leaders) in &self.leaders {
+            let leaders: Vec<_> = leaders.iter().map(|x| x.0).collect();
+            writeln!(f, "round {}: leader {:?}", round.0, leaders)?;
+        }
+        for ((sender, receiver), causes) in &self.dropped {
+            let causes: Vec<_> = causes
+                .iter()
+                .map(|(cause, count)| format!("{} {}", count, cause))
+                .collect();
+            writeln!(
+                f,
+                "dropped from {} to {}: {}",
+                sender.0,
+                receiver.0,
+                causes.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,767 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    base_types::{Author, Duration, Result, Round},
+    simulator::GlobalTime,
+    CommittedBlock,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[cfg(test)]
+#[path = "unit_tests/statistics_tests.rs"]
+mod statistics_tests;
+
+/// Data collected by the simulator while a run progresses.
+#[derive(Debug)]
+pub struct Statistics {
+    /// Part of the run whose commits are measured.
+    phases: MeasurementPhases,
+    /// Number of committed blocks already accounted for, per node.
+    observed_commits: Vec<usize>,
+    /// Number of blocks committed during the measurement window, per node.
+    measured_commits: Vec<usize>,
+    /// Durations between the proposal of a block and its commit, for all nodes.
+    commit_latencies: Vec<Duration>,
+    /// Node that made each commit of `commit_latencies`.
+    commit_nodes: Vec<Author>,
+    /// Times of the commits, for all nodes.
+    commit_times: Vec<GlobalTime>,
+    /// Differences between the rounds of consecutive blocks committed by a node, for all nodes.
+    rounds_per_commit: Vec<usize>,
+    /// Round of the latest block committed by each node.
+    last_committed_rounds: Vec<Round>,
+    /// Durations between the submission of a transaction and its commit, for all nodes.
+    /// Targeted transactions are left out.
+    transaction_latencies: Vec<Duration>,
+    /// Number of proposals that each transaction of `transaction_latencies` needed, the committed
+    /// one included.
+    proposals_per_transaction: Vec<usize>,
+    /// Durations between the submission of a targeted transaction and its first commit by a
+    /// node, for all nodes.
+    targeted_latencies: Vec<Duration>,
+    /// Targeted transactions committed by each node so far.
+    committed_targeted: BTreeSet<(Author, usize)>,
+    /// Number of commits made by a fast path, for all nodes.
+    fast_path_commits: usize,
+    /// Stake of each node. Commits by nodes holding more than two thirds of the total stake
+    /// make a transaction final for clients.
+    stakes: Vec<usize>,
+    /// Stake of the nodes that committed each block, by hash.
+    committed_stakes: HashMap<u64, usize>,
+    /// Durations between the submission of a transaction and its commit by a quorum of nodes,
+    /// by proposer and index of the transaction.
+    client_latencies: BTreeMap<(Author, usize), Duration>,
+    /// Durations between the first attempt of a request of a simulated client and the arrival
+    /// of its acknowledgment, in order of completion.
+    request_latencies: Vec<Duration>,
+}
+
+/// Phases of a run. Only the commits made during the measurement window count in the
+/// statistics, so that transients at the start of a run do not skew them.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeasurementPhases {
+    /// Commits before this time are left out.
+    pub warm_up: Duration,
+    /// Length of the measurement window, which starts after the warm-up, if bounded.
+    pub window: Option<Duration>,
+}
+
+/// Summary of the distribution of a measured value.
+#[derive(PartialEq, Clone, Debug, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Percentiles, using the nearest-rank method.
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+/// Share of a committed chain proposed by honest nodes.
+#[derive(Eq, PartialEq, Clone, Debug, Default, Serialize)]
+pub struct ChainQuality {
+    pub honest_blocks: usize,
+    pub byzantine_blocks: usize,
+    /// Longest sequence of consecutive committed blocks proposed by Byzantine nodes.
+    pub longest_byzantine_span: usize,
+}
+
+/// How the rounds of a run ended.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ViewChangeStatistics {
+    /// Rounds ended by a quorum certificate, averaged over nodes.
+    pub quorum_certificate_rounds: f64,
+    /// Rounds ended by a timeout certificate, averaged over nodes.
+    pub timeout_certificate_rounds: f64,
+    /// Rounds strictly between consecutive commits of a node, for all nodes.
+    pub wasted_rounds: Option<Distribution>,
+    /// Number of rounds ended by a timeout certificate, per leader of the round. Takes the
+    /// highest count over nodes, since nodes usually observe the same certificates.
+    pub timeouts_by_leader: BTreeMap<Author, usize>,
+}
+
+/// Summary of a simulation run. Commits, latencies, and throughputs only cover the measurement
+/// window.
+#[derive(PartialEq, Clone, Debug, Serialize)]
+pub struct RunSummary {
+    /// Simulated time at the end of the run.
+    pub duration: Duration,
+    /// Average number of blocks committed by a node.
+    pub committed_blocks: f64,
+    /// Average number of blocks committed by a node, weighted by the stake of the nodes.
+    pub stake_weighted_committed_blocks: f64,
+    /// Average number of blocks committed by a node per unit of simulated time.
+    pub commit_throughput: f64,
+    /// Mean duration between the proposal of a block and its commit by a node, if any.
+    pub mean_commit_latency: Option<f64>,
+    /// Median duration between the proposal of a block and its commit by a node, if any.
+    pub median_commit_latency: Option<f64>,
+    /// Fraction of the rounds that were ended by a timeout certificate.
+    pub timeout_rate: f64,
+    /// Fraction of the commits made by a fast path, for all nodes.
+    pub fast_path_rate: f64,
+    /// Average number of transactions committed by a node per unit of simulated time.
+    pub transaction_throughput: f64,
+    /// Mean duration between the submission of a transaction and its commit by a node, if any.
+    pub mean_transaction_latency: Option<f64>,
+    /// Mean duration between the submission of a targeted transaction and its commit by a
+    /// node, if any.
+    pub mean_targeted_latency: Option<f64>,
+}
+
+impl MeasurementPhases {
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.warm_up >= Duration::ZERO,
+            "The warm-up cannot be negative"
+        );
+        ensure!(
+            self.window.map_or(true, |window| window > Duration::ZERO),
+            "The measurement window must be positive"
+        );
+        Ok(())
+    }
+
+    /// End of the measurement window, if bounded.
+    pub fn end(&self) -> Option<GlobalTime> {
+        self.window
+            .map(|window| GlobalTime(0) + self.warm_up + window)
+    }
+
+    pub fn is_measured(&self, time: GlobalTime) -> bool {
+        time >= GlobalTime(0) + self.warm_up && self.end().map_or(true, |end| time <= end)
+    }
+
+    /// Length of the part of the window covered by a run that stopped at `clock`.
+    pub fn measured_duration(&self, clock: GlobalTime) -> Duration {
+        let end = match self.end() {
+            Some(end) if end < clock => end,
+            _ => clock,
+        };
+        std::cmp::max(end - (GlobalTime(0) + self.warm_up), Duration::ZERO)
+    }
+}
+
+impl Statistics {
+    pub fn new(num_nodes: usize) -> Self {
+        Statistics {
+            phases: MeasurementPhases::default(),
+            observed_commits: vec![0; num_nodes],
+            measured_commits: vec![0; num_nodes],
+            commit_latencies: Vec::new(),
+            commit_nodes: Vec::new(),
+            commit_times: Vec::new(),
+            rounds_per_commit: Vec::new(),
+            last_committed_rounds: vec![Round(0); num_nodes],
+            transaction_latencies: Vec::new(),
+            proposals_per_transaction: Vec::new(),
+            targeted_latencies: Vec::new(),
+            committed_targeted: BTreeSet::new(),
+            fast_path_commits: 0,
+            stakes: vec![1; num_nodes],
+            committed_stakes: HashMap::new(),
+            client_latencies: BTreeMap::new(),
+            request_latencies: Vec::new(),
+        }
+    }
+
+    pub fn set_stake(&mut self, author: Author, stake: usize) {
+        self.stakes[author.0] = stake;
+    }
+
+    pub fn set_phases(&mut self, phases: MeasurementPhases) {
+        self.phases = phases;
+    }
+
+    pub fn phases(&self) -> MeasurementPhases {
+        self.phases
+    }
+
+    /// Number of blocks committed by `author` during the measurement window so far.
+    pub fn measured_commits(&self, author: Author) -> usize {
+        self.measured_commits[author.0]
+    }
+
+    /// Smallest stake of more than two thirds of the total stake.
+    pub fn quorum_stake(&self) -> usize {
+        2 * self.stakes.iter().sum::<usize>() / 3 + 1
+    }
+
+    /// Stake of the nodes that committed the block `block_hash` so far.
+    pub fn committed_stake(&self, block_hash: u64) -> usize {
+        *self.committed_stakes.get(&block_hash).unwrap_or(&0)
+    }
+
+    /// Account for the blocks newly committed by `author` at time `clock`. The function
+    /// `proposal_time` converts the time of a proposal into global time. Commits outside the
+    /// measurement window only update the state of the node.
+    pub fn record_commits<F>(
+        &mut self,
+        author: Author,
+        committed_blocks: &[CommittedBlock],
+        clock: GlobalTime,
+        proposal_time: F,
+    ) where
+        F: Fn(&CommittedBlock) -> GlobalTime,
+    {
+        let quorum_stake = self.quorum_stake();
+        let stake = self.stakes[author.0];
+        let observed = &mut self.observed_commits[author.0];
+        let last_committed_round = &mut self.last_committed_rounds[author.0];
+        let is_measured = self.phases.is_measured(clock);
+        for block in &committed_blocks[*observed..] {
+            let latency = clock - proposal_time(block);
+            if is_measured {
+                self.commit_latencies.push(latency);
+                self.measured_commits[author.0] += 1;
+                if block.fast_path {
+                    self.fast_path_commits += 1;
+                }
+            }
+            // Submission times use the same clock as the proposal time.
+            let mut submission_delays = Vec::new();
+            for transaction in &block.transactions {
+                let delay = block.proposal_time - transaction.submission_time;
+                match transaction.targeted {
+                    // Several blocks may carry the same targeted transaction.
+                    Some(id) => {
+                        if self.committed_targeted.insert((author, id)) && is_measured {
+                            self.targeted_latencies.push(latency + delay);
+                        }
+                    }
+                    None => {
+                        submission_delays.push((transaction.index, delay));
+                        if is_measured {
+                            self.proposals_per_transaction
+                                .push(transaction.proposals + 1);
+                        }
+                    }
+                }
+            }
+            let committed_stake = self.committed_stakes.entry(block.hash).or_insert(0);
+            let is_new_quorum = *committed_stake < quorum_stake;
+            *committed_stake += stake;
+            // Rounds restart at each epoch.
+            let rounds = if block.round > *last_committed_round {
+                Some(block.round.0 - last_committed_round.0)
+            } else {
+                None
+            };
+            *last_committed_round = block.round;
+            if !is_measured {
+                continue;
+            }
+            for (_, delay) in &submission_delays {
+                self.transaction_latencies.push(latency + *delay);
+            }
+            if is_new_quorum && *committed_stake >= quorum_stake {
+                for (index, delay) in submission_delays {
+                    self.client_latencies
+                        .insert((block.proposer, index), latency + delay);
+                }
+            }
+            self.commit_nodes.push(author);
+            self.commit_times.push(clock);
+            if let Some(rounds) = rounds {
+                self.rounds_per_commit.push(rounds);
+            }
+        }
+        *observed = committed_blocks.len();
+    }
+
+    /// Fraction of the commits made by a fast path, for all nodes.
+    pub fn fast_path_rate(&self) -> f64 {
+        if self.commit_latencies.is_empty() {
+            0.0
+        } else {
+            self.fast_path_commits as f64 / self.commit_latencies.len() as f64
+        }
+    }
+
+    pub fn commit_latencies(&self) -> &[Duration] {
+        &self.commit_latencies
+    }
+
+    pub fn transaction_latencies(&self) -> &[Duration] {
+        &self.transaction_latencies
+    }
+
+    pub fn transaction_latency_distribution(&self) -> Option<Distribution> {
+        let latencies: Vec<_> = self
+            .transaction_latencies
+            .iter()
+            .map(|x| x.as_millis_f64())
+            .collect();
+        Distribution::new(&latencies)
+    }
+
+    pub fn proposals_per_transaction(&self) -> &[usize] {
+        &self.proposals_per_transaction
+    }
+
+    pub fn proposals_per_transaction_distribution(&self) -> Option<Distribution> {
+        let proposals: Vec<_> = self
+            .proposals_per_transaction
+            .iter()
+            .map(|x| *x as f64)
+            .collect();
+        Distribution::new(&proposals)
+    }
+
+    pub fn targeted_latencies(&self) -> &[Duration] {
+        &self.targeted_latencies
+    }
+
+    pub fn targeted_latency_distribution(&self) -> Option<Distribution> {
+        let latencies: Vec<_> = self
+            .targeted_latencies
+            .iter()
+            .map(|x| x.as_millis_f64())
+            .collect();
+        Distribution::new(&latencies)
+    }
+
+    /// Durations between the submission of a transaction and its commit by a quorum of nodes,
+    /// by proposer and index of the transaction.
+    pub fn client_latencies(&self) -> &BTreeMap<(Author, usize), Duration> {
+        &self.client_latencies
+    }
+
+    pub fn client_latency_distribution(&self) -> Option<Distribution> {
+        let latencies: Vec<_> = self
+            .client_latencies
+            .values()
+            .map(|x| x.as_millis_f64())
+            .collect();
+        Distribution::new(&latencies)
+    }
+
+    /// Account for a request of a simulated client completed at `time`, if in the measurement
+    /// window.
+    pub fn record_request_latency(&mut self, time: GlobalTime, latency: Duration) {
+        if self.phases.is_measured(time) {
+            self.request_latencies.push(latency);
+        }
+    }
+
+    pub fn request_latencies(&self) -> &[Duration] {
+        &self.request_latencies
+    }
+
+    pub fn request_latency_distribution(&self) -> Option<Distribution> {
+        let latencies: Vec<_> = self
+            .request_latencies
+            .iter()
+            .map(|x| x.as_millis_f64())
+            .collect();
+        Distribution::new(&latencies)
+    }
+
+    pub fn rounds_per_commit(&self) -> &[usize] {
+        &self.rounds_per_commit
+    }
+
+    pub fn commit_latency_distribution(&self) -> Option<Distribution> {
+        let latencies: Vec<_> = self
+            .commit_latencies
+            .iter()
+            .map(|x| x.as_millis_f64())
+            .collect();
+        Distribution::new(&latencies)
+    }
+
+    pub fn rounds_per_commit_distribution(&self) -> Option<Distribution> {
+        let rounds: Vec<_> = self.rounds_per_commit.iter().map(|x| *x as f64).collect();
+        Distribution::new(&rounds)
+    }
+
+    /// Rounds strictly between consecutive commits of a node, for all nodes.
+    pub fn wasted_rounds_distribution(&self) -> Option<Distribution> {
+        let rounds: Vec<_> = self
+            .rounds_per_commit
+            .iter()
+            .map(|x| (*x - 1) as f64)
+            .collect();
+        Distribution::new(&rounds)
+    }
+
+    /// Number of commit latencies in each bucket `[k * bucket_width, (k + 1) * bucket_width)`,
+    /// for `k` from 0 to the bucket of the highest latency.
+    pub fn commit_latency_histogram(&self, bucket_width: Duration) -> Vec<usize> {
+        assert!(
+            bucket_width.is_positive(),
+            "Buckets must have a positive width"
+        );
+        let mut counts = Vec::new();
+        for latency in &self.commit_latencies {
+            let bucket =
+                std::cmp::max(*latency, Duration::ZERO).div_duration(bucket_width) as usize;
+            if bucket >= counts.len() {
+                counts.resize(bucket + 1, 0);
+            }
+            counts[bucket] += 1;
+        }
+        counts
+    }
+
+    /// Average number of commits per node and per unit of simulated time, in each window
+    /// `[k * window, (k + 1) * window)` until the given time.
+    pub fn commit_throughput_series(&self, window: Duration, until: GlobalTime) -> Vec<f64> {
+        assert!(window.is_positive(), "Windows must have a positive length");
+        let window = window.as_millis();
+        let num_windows = (std::cmp::max(until.0, 0) / window + 1) as usize;
+        let mut counts = vec![0usize; num_windows];
+        for time in &self.commit_times {
+            let index = (std::cmp::max(time.0, 0) / window) as usize;
+            if index < num_windows {
+                counts[index] += 1;
+            }
+        }
+        let num_nodes = self.observed_commits.len() as f64;
+        counts
+            .iter()
+            .map(|count| *count as f64 / num_nodes / window as f64)
+            .collect()
+    }
+
+    pub fn mean_commit_latency(&self) -> Option<f64> {
+        if self.commit_latencies.is_empty() {
+            return None;
+        }
+        let sum: Duration = self.commit_latencies.iter().sum();
+        Some(sum.as_millis_f64() / self.commit_latencies.len() as f64)
+    }
+
+    /// Mean commit latency of the commits made by the given node.
+    pub fn node_mean_commit_latency(&self, author: Author) -> Option<f64> {
+        let latencies: Vec<_> = self
+            .commit_latencies
+            .iter()
+            .zip(self.commit_nodes.iter())
+            .filter(|(_, node)| **node == author)
+            .map(|(latency, _)| *latency)
+            .collect();
+        if latencies.is_empty() {
+            return None;
+        }
+        Some(latencies.iter().sum::<Duration>().as_millis_f64() / latencies.len() as f64)
+    }
+
+    pub fn median_commit_latency(&self) -> Option<f64> {
+        let latencies: Vec<_> = self
+            .commit_latencies
+            .iter()
+            .map(|x| x.as_millis_f64())
+            .collect();
+        median(&latencies)
+    }
+}
+
+impl Distribution {
+    pub fn new(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[std::cmp::max(rank, 1) - 1]
+        };
+        Some(Distribution {
+            count: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+        })
+    }
+}
+
+impl ChainQuality {
+    pub fn new(blocks: &[CommittedBlock], byzantine_nodes: &BTreeSet<Author>) -> Self {
+        let mut quality = ChainQuality::default();
+        let mut span = 0;
+        for block in blocks {
+            if byzantine_nodes.contains(&block.proposer) {
+                quality.byzantine_blocks += 1;
+                span += 1;
+                quality.longest_byzantine_span =
+                    std::cmp::max(quality.longest_byzantine_span, span);
+            } else {
+                quality.honest_blocks += 1;
+                span = 0;
+            }
+        }
+        quality
+    }
+
+    /// Fraction of the committed blocks proposed by honest nodes, if any block was committed.
+    pub fn honest_fraction(&self) -> Option<f64> {
+        let total = self.honest_blocks + self.byzantine_blocks;
+        if total == 0 {
+            return None;
+        }
+        Some(self.honest_blocks as f64 / total as f64)
+    }
+}
+
+/// Coefficient of variation above which a metric is flagged as sensitive to the seed.
+pub const SEED_SENSITIVITY_THRESHOLD: f64 = 0.25;
+
+/// Statistics of a value measured over independent runs.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SampleStatistics {
+    /// Number of runs.
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    /// Sample standard deviation, or 0 when there is a single run.
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Bounds of the 95% confidence interval of the mean, using Student's t-distribution.
+    /// Collapses to the mean when there is a single run.
+    pub confidence_interval: (f64, f64),
+}
+
+/// Aggregated results of running the same configuration with different seeds.
+#[derive(PartialEq, Clone, Debug)]
+pub struct MonteCarloSummary {
+    /// Number of runs.
+    pub runs: usize,
+    /// Number of runs where at least one block was committed.
+    pub live_runs: usize,
+    /// Per-run mean commit latencies, over the live runs.
+    pub mean_commit_latency: Option<SampleStatistics>,
+    /// Per-run median commit latencies, over the live runs.
+    pub median_commit_latency: Option<SampleStatistics>,
+    pub commit_throughput: SampleStatistics,
+    pub timeout_rate: SampleStatistics,
+}
+
+impl MonteCarloSummary {
+    pub fn new(summaries: &[RunSummary]) -> Self {
+        let live: Vec<_> = summaries
+            .iter()
+            .filter(|summary| summary.committed_blocks > 0.0)
+            .collect();
+        MonteCarloSummary {
+            runs: summaries.len(),
+            live_runs: live.len(),
+            mean_commit_latency: SampleStatistics::new(
+                &live
+                    .iter()
+                    .filter_map(|summary| summary.mean_commit_latency)
+                    .collect::<Vec<_>>(),
+            ),
+            median_commit_latency: SampleStatistics::new(
+                &live
+                    .iter()
+                    .filter_map(|summary| summary.median_commit_latency)
+                    .collect::<Vec<_>>(),
+            ),
+            commit_throughput: SampleStatistics::new(
+                &summaries
+                    .iter()
+                    .map(|summary| summary.commit_throughput)
+                    .collect::<Vec<_>>(),
+            )
+            .expect("Monte Carlo summaries need at least one run"),
+            timeout_rate: SampleStatistics::new(
+                &summaries
+                    .iter()
+                    .map(|summary| summary.timeout_rate)
+                    .collect::<Vec<_>>(),
+            )
+            .expect("Monte Carlo summaries need at least one run"),
+        }
+    }
+
+    /// Names of the metrics, in the order of `metrics`.
+    pub const METRICS: [&'static str; 4] = [
+        "mean_commit_latency",
+        "median_commit_latency",
+        "commit_throughput",
+        "timeout_rate",
+    ];
+
+    /// Statistics of each metric, if measured in some run.
+    pub fn metrics(&self) -> Vec<(&'static str, Option<&SampleStatistics>)> {
+        let values = [
+            self.mean_commit_latency.as_ref(),
+            self.median_commit_latency.as_ref(),
+            Some(&self.commit_throughput),
+            Some(&self.timeout_rate),
+        ];
+        Self::METRICS
+            .iter()
+            .cloned()
+            .zip(values.iter().cloned())
+            .collect()
+    }
+
+    /// Names of the metrics whose coefficient of variation exceeds
+    /// `SEED_SENSITIVITY_THRESHOLD`.
+    pub fn seed_sensitive_metrics(&self) -> Vec<&'static str> {
+        self.metrics()
+            .into_iter()
+            .filter(|(_, statistics)| statistics.map_or(false, SampleStatistics::is_seed_sensitive))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Whether some seeds made progress while others did not.
+    pub fn is_liveness_seed_dependent(&self) -> bool {
+        self.live_runs > 0 && self.live_runs < self.runs
+    }
+}
+
+impl SampleStatistics {
+    pub fn new(values: &[f64]) -> Option<Self> {
+        let count = values.len();
+        if count == 0 {
+            return None;
+        }
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let (std_dev, half_width) = if count > 1 {
+            let variance =
+                values.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (count - 1) as f64;
+            (
+                variance.sqrt(),
+                student_t_975(count - 1) * (variance / count as f64).sqrt(),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        Some(SampleStatistics {
+            count,
+            mean,
+            median: median(values).unwrap(),
+            std_dev,
+            min: values.iter().cloned().fold(std::f64::INFINITY, f64::min),
+            max: values
+                .iter()
+                .cloned()
+                .fold(std::f64::NEG_INFINITY, f64::max),
+            confidence_interval: (mean - half_width, mean + half_width),
+        })
+    }
+
+    /// Standard deviation relative to the mean, if not zero.
+    pub fn coefficient_of_variation(&self) -> Option<f64> {
+        if self.mean == 0.0 {
+            None
+        } else {
+            Some(self.std_dev / self.mean.abs())
+        }
+    }
+
+    pub fn is_seed_sensitive(&self) -> bool {
+        self.coefficient_of_variation()
+            .map_or(false, |x| x > SEED_SENSITIVITY_THRESHOLD)
+    }
+}
+
+/// Paired comparison of a value measured on two configurations, run by pairs with the same seed.
+#[derive(PartialEq, Clone, Debug)]
+pub struct PairedComparison {
+    /// Number of pairs of runs.
+    pub count: usize,
+    /// Mean of the first configuration.
+    pub mean_a: f64,
+    /// Mean of the second configuration.
+    pub mean_b: f64,
+    /// Mean of the differences `b - a` between the runs of each pair.
+    pub mean_difference: f64,
+    /// Bounds of the 95% confidence interval of the mean difference, using Student's
+    /// t-distribution. Collapses to the mean difference when there is a single pair.
+    pub confidence_interval: (f64, f64),
+}
+
+impl PairedComparison {
+    /// Compare the values `(a, b)` of each pair of runs.
+    pub fn new(pairs: &[(f64, f64)]) -> Option<Self> {
+        let differences: Vec<_> = pairs.iter().map(|(a, b)| b - a).collect();
+        let difference = SampleStatistics::new(&differences)?;
+        let count = pairs.len() as f64;
+        Some(PairedComparison {
+            count: pairs.len(),
+            mean_a: pairs.iter().map(|(a, _)| a).sum::<f64>() / count,
+            mean_b: pairs.iter().map(|(_, b)| b).sum::<f64>() / count,
+            mean_difference: difference.mean,
+            confidence_interval: difference.confidence_interval,
+        })
+    }
+
+    /// Whether a paired t-test rejects the hypothesis of equal means at the 5% level, i.e. the
+    /// confidence interval of the difference excludes 0. Needs at least two pairs.
+    pub fn is_significant(&self) -> bool {
+        self.count > 1 && (self.confidence_interval.0 > 0.0 || self.confidence_interval.1 < 0.0)
+    }
+
+    /// Mean difference relative to the mean of the first configuration, if not zero.
+    pub fn relative_difference(&self) -> Option<f64> {
+        if self.mean_a == 0.0 {
+            None
+        } else {
+            Some(self.mean_difference / self.mean_a)
+        }
+    }
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    let n = sorted.len();
+    if n % 2 == 1 {
+        Some(sorted[n / 2])
+    } else {
+        Some((sorted[n / 2 - 1] + sorted[n / 2]) / 2.0)
+    }
+}
+
+/// 97.5% quantile of Student's t-distribution with the given (positive) degrees of freedom.
+fn student_t_975(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match degrees_of_freedom {
+        d if d <= TABLE.len() => TABLE[d - 1],
+        d if d <= 60 => 2.000,
+        d if d <= 120 => 1.980,
+        _ => 1.960,
+    }
+}
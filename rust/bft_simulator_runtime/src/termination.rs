@@ -0,0 +1,95 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conditions deciding when a simulation run ends.
+//!
+//! A run stops as soon as one of the configured conditions holds, or when no event is left to
+//! process. Conditions are evaluated after each event.
+
+use crate::{
+    base_types::{Duration, Round},
+    simulator::GlobalTime,
+};
+
+#[cfg(test)]
+#[path = "unit_tests/termination_tests.rs"]
+mod termination_tests;
+
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct TerminationCondition {
+    /// Stop before processing events scheduled after this time.
+    pub max_clock: Option<GlobalTime>,
+    /// Stop once a node has committed this many blocks.
+    pub max_committed_blocks: Option<usize>,
+    /// Stop once a node is this many rounds ahead of the highest round known to any node at
+    /// the time of the latest commit.
+    pub max_rounds_without_progress: Option<usize>,
+    /// Stop when an invariant registered with the simulator is violated.
+    pub stop_on_invariant_violation: bool,
+    /// Fail the run if no block is committed for longer than this, while a quorum of honest
+    /// nodes is running. Nodes are considered connected unless they have crashed.
+    pub max_time_without_commit: Option<Duration>,
+}
+
+/// Why a run ended.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum StopReason {
+    /// The next event is scheduled after `max_clock`.
+    MaxClock,
+    /// A node reached `max_committed_blocks`.
+    CommittedBlocks,
+    /// A node reached `max_rounds_without_progress`.
+    NoProgress,
+    /// An invariant failed. Contains its name, the violation, and the state of the nodes.
+    InvariantViolation(String),
+    /// Two nodes committed conflicting blocks. Contains the offending blocks.
+    SafetyViolation(String),
+    /// No block was committed within `max_time_without_commit`. Contains diagnostics.
+    LivenessViolation(String),
+    /// No event is left to process.
+    NoMoreEvents,
+    /// The scheduler of the run declined to pick an event.
+    SchedulerStopped,
+}
+
+impl TerminationCondition {
+    /// Stop at the given simulated time only.
+    pub fn at_time(max_clock: GlobalTime) -> Self {
+        TerminationCondition {
+            max_clock: Some(max_clock),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn is_past_max_clock(&self, time: GlobalTime) -> bool {
+        match self.max_clock {
+            Some(max_clock) => time > max_clock,
+            None => false,
+        }
+    }
+
+    pub(crate) fn has_enough_commits(&self, committed_blocks: usize) -> bool {
+        match self.max_committed_blocks {
+            Some(max_committed_blocks) => committed_blocks >= max_committed_blocks,
+            None => false,
+        }
+    }
+
+    /// Whether the time elapsed since `since`, the latest commit or the latest time a quorum
+    /// of honest nodes was not running, exceeds the progress bound.
+    pub(crate) fn has_missed_progress_bound(&self, clock: GlobalTime, since: GlobalTime) -> bool {
+        match self.max_time_without_commit {
+            Some(max_duration) => clock - since > max_duration,
+            None => false,
+        }
+    }
+
+    /// Whether `round` is too far ahead of `progress_round`, the highest round known at the
+    /// time of the latest commit.
+    pub(crate) fn has_stalled(&self, round: Round, progress_round: Round) -> bool {
+        match self.max_rounds_without_progress {
+            Some(max_rounds) => round.0 >= progress_round.0 + max_rounds,
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Simulated threshold signatures.
+//!
+//! At the start of each epoch, a trusted dealer hands out a key share to every validator. Shares
+//! of validators holding a quorum of the votes combine into a single signature of the group,
+//! which anyone can verify with the group key, without learning who signed. Keys and signatures
+//! are placeholders derived from hashes, with the sizes of BLS signatures.
+
+use crate::{
+    base_types::{Author, Result, SignatureShare, ThresholdSignature},
+    EpochConfiguration,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet},
+    hash::{Hash, Hasher},
+};
+
+#[cfg(test)]
+#[path = "unit_tests/threshold_tests.rs"]
+mod threshold_tests;
+
+/// Keys of the validators of an epoch, as handed out by the dealer.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ThresholdKeys {
+    /// Public key of the group.
+    group_key: u64,
+    /// Key share and voting rights of each validator.
+    shares: BTreeMap<Author, (u64, usize)>,
+    /// Voting rights needed to combine a signature.
+    threshold: usize,
+}
+
+fn digest<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ThresholdKeys {
+    /// Deal the keys of the validators of an epoch. Every validator deals the same keys from
+    /// the same `seed`, typically the epoch identifier.
+    pub fn deal(seed: u64, configuration: &EpochConfiguration) -> Self {
+        let group_key = digest((seed, &configuration.voting_rights));
+        let shares = configuration
+            .voting_rights
+            .iter()
+            .filter(|(_, votes)| **votes > 0)
+            .map(|(author, votes)| (*author, (digest((group_key, *author)), *votes)))
+            .collect();
+        ThresholdKeys {
+            group_key,
+            shares,
+            threshold: configuration.quorum_threshold(),
+        }
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The share of `author` on a digest, if `author` is a validator.
+    pub fn sign_share(&self, hash: u64, author: Author) -> Option<SignatureShare> {
+        self.shares
+            .get(&author)
+            .map(|(key, _)| SignatureShare(digest((*key, hash))))
+    }
+
+    pub fn check_share(&self, hash: u64, author: Author, share: SignatureShare) -> Result<()> {
+        ensure!(
+            self.sign_share(hash, author) == Some(share),
+            "Signature shares must be valid."
+        );
+        Ok(())
+    }
+
+    /// Combine the shares of distinct validators holding at least `threshold` votes.
+    pub fn combine(
+        &self,
+        hash: u64,
+        shares: &[(Author, SignatureShare)],
+    ) -> Result<ThresholdSignature> {
+        let mut authors = BTreeSet::new();
+        let mut votes = 0;
+        for (author, share) in shares {
+            self.check_share(hash, *author, *share)?;
+            if authors.insert(*author) {
+                votes += self.shares[author].1;
+            }
+        }
+        ensure!(
+            votes >= self.threshold,
+            "Signature shares must form a quorum to be combined."
+        );
+        Ok(ThresholdSignature(digest((self.group_key, hash))))
+    }
+
+    pub fn check(&self, hash: u64, signature: ThresholdSignature) -> Result<()> {
+        ensure!(
+            signature == ThresholdSignature(digest((self.group_key, hash))),
+            "Threshold signatures must be valid."
+        );
+        Ok(())
+    }
+}
+
+impl SignatureShare {
+    /// Size on the wire, in bytes.
+    pub fn num_bytes() -> usize {
+        96
+    }
+}
+
+impl ThresholdSignature {
+    /// Size on the wire, in bytes.
+    pub fn num_bytes() -> usize {
+        96
+    }
+}
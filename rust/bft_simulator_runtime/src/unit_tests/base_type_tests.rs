@@ -8,6 +8,39 @@ fn test_round_plus_usize() {
     assert_eq!(Round(3) + 4, Round(7));
 }
 
+#[test]
+fn test_durations() {
+    assert_eq!(Duration::from_secs(2), Duration::from_millis(2000));
+    assert_eq!(Duration::from_micros(1500), Duration::from_millis(1));
+    assert_eq!(Duration::from_millis(3).as_micros(), 3000);
+    assert_eq!(
+        Duration::from_millis(20).mul_f64(2.5),
+        Duration::from_millis(50)
+    );
+    assert_eq!("25".parse::<Duration>(), Ok(Duration::from_millis(25)));
+    assert_eq!(Duration::from_millis(25).to_string(), "25");
+    assert_eq!(format!("{:?}", Duration::from_millis(25)), "25ms");
+    assert_eq!(NodeTime(10) - NodeTime(4), Duration::from_millis(6));
+    assert_eq!(NodeTime(10) - Duration::from_millis(4), NodeTime(6));
+    // Times saturate instead of overflowing, and `never()` stays in the future.
+    let long = Duration::from_millis(std::i64::MAX);
+    assert_eq!(
+        NodeTime::never() + Duration::from_millis(5),
+        NodeTime::never()
+    );
+    assert_eq!(
+        NodeTime::never() - Duration::from_millis(5),
+        NodeTime::never()
+    );
+    assert_eq!(NodeTime(3) + long, NodeTime::never());
+    assert_eq!(NodeTime(3).checked_add(long), None);
+    assert_eq!(
+        NodeTime(3).checked_add(Duration::from_millis(4)),
+        Some(NodeTime(7))
+    );
+    assert_eq!(long.checked_add(long), None);
+}
+
 #[test]
 fn test_signature() {
     let sig = Signature::sign(35, Author(2));
@@ -15,3 +48,22 @@ fn test_signature() {
     assert!(sig.check(32, Author(2)).is_err());
     assert!(sig.check(35, Author(1)).is_err());
 }
+
+#[test]
+fn test_multi_signature() {
+    let digest = |author: Author| 35 + author.0 as u64;
+    let signatures: Vec<_> = (0..4)
+        .map(|i| (Author(i), Signature::sign(digest(Author(i)), Author(i))))
+        .collect();
+    let individual = MultiSignature::new(signatures.clone(), false);
+    let aggregate = MultiSignature::new(signatures, true);
+    for multi_signature in &[&individual, &aggregate] {
+        assert_eq!(
+            multi_signature.authors(),
+            (0..4).map(Author).collect::<Vec<_>>()
+        );
+        assert!(multi_signature.check(digest).is_ok());
+        assert!(multi_signature.check(|_| 35).is_err());
+    }
+    assert!(aggregate.num_bytes() < individual.num_bytes());
+}
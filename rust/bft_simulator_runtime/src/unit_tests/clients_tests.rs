@@ -0,0 +1,108 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use rand::SeedableRng;
+
+fn request(client: usize, sequence: usize) -> ClientRequest {
+    ClientRequest { client, sequence }
+}
+
+#[test]
+fn test_clients() {
+    let model = ClientModel {
+        clients: 2,
+        replicas: 2,
+        think_time: Duration::from_millis(10),
+        timeout: Duration::from_millis(100),
+        ..ClientModel::default()
+    };
+    model.check(4).unwrap();
+    assert!(ClientModel {
+        replicas: 5,
+        ..model.clone()
+    }
+    .check(4)
+    .is_err());
+    let mut clients = Clients::new(model, 4);
+    assert_eq!(clients.next_time(), Some(GlobalTime(0)));
+
+    // Clients start with different nodes.
+    assert_eq!(
+        clients.poll(GlobalTime(0)),
+        vec![
+            (Author(0), request(0, 0)),
+            (Author(1), request(0, 0)),
+            (Author(2), request(1, 0)),
+            (Author(3), request(1, 0)),
+        ]
+    );
+    assert!(clients.is_waiting_for(Author(1), request(0, 0)));
+    assert!(!clients.is_waiting_for(Author(2), request(0, 0)));
+    assert_eq!(clients.next_time(), Some(GlobalTime(100)));
+
+    // Requests reach the nodes in order of arrival.
+    clients.send(GlobalTime(15), Author(0), request(0, 0));
+    clients.send(GlobalTime(5), Author(1), request(0, 0));
+    assert_eq!(clients.next_time(), Some(GlobalTime(5)));
+    assert_eq!(
+        clients.arrivals_until(GlobalTime(10)),
+        vec![(Author(1), request(0, 0))]
+    );
+    assert_eq!(
+        clients.arrivals_until(GlobalTime(20)),
+        vec![(Author(0), request(0, 0))]
+    );
+
+    // The earliest acknowledgment completes the request once it arrives.
+    clients.acknowledge(request(0, 0), GlobalTime(40));
+    clients.acknowledge(request(0, 0), GlobalTime(30));
+    assert_eq!(clients.poll(GlobalTime(20)), vec![]);
+    assert_eq!(clients.poll(GlobalTime(30)), vec![]);
+    assert_eq!(
+        clients.take_completions(),
+        vec![(GlobalTime(30), Duration::from_millis(30))]
+    );
+    assert!(!clients.is_waiting_for(Author(1), request(0, 0)));
+
+    // After thinking, client 0 sends its next request, while client 1 times out and sends its
+    // request again to the next nodes.
+    assert_eq!(
+        clients.poll(GlobalTime(100)),
+        vec![
+            (Author(2), request(0, 1)),
+            (Author(3), request(0, 1)),
+            (Author(0), request(1, 0)),
+            (Author(1), request(1, 0)),
+        ]
+    );
+    assert_eq!(clients.retries(), 1);
+    assert!(clients.take_completions().is_empty());
+}
+
+#[test]
+fn test_gossip_targets() {
+    let model = ClientModel {
+        gossip_fanout: 2,
+        gossip_delay: Duration::from_millis(5),
+        ..ClientModel::default()
+    };
+    model.check(3).unwrap();
+    assert!(model.check(2).is_err());
+    let mut clients = Clients::new(model, 3);
+    let mut rng = StdRng::seed_from_u64(0);
+    let (mut targets, delay) = clients.gossip_targets(Author(1), &mut rng);
+    targets.sort();
+    assert_eq!(targets, vec![Author(0), Author(2)]);
+    assert_eq!(delay, Duration::from_millis(5));
+
+    // Only the nodes that the client did not contact receive its request through gossip.
+    assert_eq!(
+        clients.poll(GlobalTime(0)),
+        vec![(Author(0), request(0, 0))]
+    );
+    clients.accept(Author(0), request(0, 0));
+    assert_eq!(clients.forwarded(), 0);
+    clients.accept(Author(1), request(0, 0));
+    assert_eq!(clients.forwarded(), 1);
+}
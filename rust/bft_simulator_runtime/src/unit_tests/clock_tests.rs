@@ -0,0 +1,32 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_simulated_clock() {
+    let mut clock = SimulatedClock::new(GlobalTime(10));
+    clock.set_global_time(GlobalTime(25));
+    assert_eq!(clock.now(), NodeTime(15));
+    clock.jump(Duration::from_millis(5));
+    assert_eq!(clock.now(), NodeTime(20));
+    assert_eq!(clock.global_time_at(NodeTime(20)), GlobalTime(25));
+    // Clock jumps survive a later start.
+    clock.set_startup_time(GlobalTime(20));
+    assert_eq!(clock.now(), NodeTime(10));
+}
+
+#[test]
+fn test_scaled_clock() {
+    assert!(ScaledClock::new(0.0).is_err());
+    let clock = ScaledClock::new(4.0).unwrap();
+    assert_eq!(
+        clock.instant_at(NodeTime(8)) - clock.instant_at(NodeTime(0)),
+        StdDuration::from_millis(2)
+    );
+    let wall_clock = WallClock::new();
+    assert_eq!(
+        wall_clock.instant_at(NodeTime(8)) - wall_clock.instant_at(NodeTime(0)),
+        StdDuration::from_millis(8)
+    );
+}
@@ -13,6 +13,7 @@ fn test_count() {
     rights.insert(Author(2), 3);
     let config = EpochConfiguration::new(rights);
     assert_eq!(config.total_votes, 6);
+    assert_eq!(config.total_votes(), 6);
 
     assert_eq!(config.count_votes(vec![&Author(1)]), 2);
     assert_eq!(config.count_votes(vec![&Author(4)]), 0);
@@ -53,3 +54,18 @@ fn test_quorum() {
     assert_eq!(equal_configuration(5).quorum_threshold(), 4);
     assert_eq!(equal_configuration(6).quorum_threshold(), 5);
 }
+
+#[test]
+fn test_pick_author_skips_zero_rights() {
+    let mut rights = BTreeMap::new();
+    rights.insert(Author(0), 0);
+    rights.insert(Author(1), 1);
+    rights.insert(Author(2), 0);
+    rights.insert(Author(3), 2);
+    let config = EpochConfiguration::new(rights);
+
+    assert_eq!(config.pick_author(0), Author(1));
+    assert_eq!(config.pick_author(1), Author(3));
+    assert_eq!(config.pick_author(2), Author(3));
+    assert_eq!(config.pick_author(3), Author(1));
+}
@@ -0,0 +1,53 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+#[derive(PartialEq, Debug)]
+struct Message(Vec<u8>);
+
+impl WireFormat for Message {
+    fn to_wire(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    fn from_wire(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() == 4, "Messages have 4 bytes");
+        Ok(Message(bytes.to_vec()))
+    }
+}
+
+#[test]
+fn test_corruption() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let message = Message(vec![1, 2, 3, 4]);
+    assert!(!CorruptionModel::default().is_active());
+    assert!(CorruptionModel::default()
+        .corrupt_message(&message, &mut rng)
+        .is_none());
+
+    let flips = CorruptionModel {
+        flip_rate: 1.0,
+        flipped_bytes: 1,
+        ..CorruptionModel::default()
+    };
+    let damaged = flips.corrupt_message(&message, &mut rng).unwrap().unwrap();
+    let differences = (0..4).filter(|i| damaged.0[*i] != message.0[*i]).count();
+    assert_eq!(differences, 1);
+
+    let truncations = CorruptionModel {
+        truncate_rate: 1.0,
+        ..CorruptionModel::default()
+    };
+    assert!(truncations
+        .corrupt_message(&message, &mut rng)
+        .unwrap()
+        .is_err());
+    assert!(CorruptionModel {
+        flip_rate: 1.5,
+        ..CorruptionModel::default()
+    }
+    .check()
+    .is_err());
+}
@@ -0,0 +1,58 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+fn row(author: usize, committed: Option<usize>, pending_messages: usize) -> DashboardRow {
+    DashboardRow {
+        author: Author(author),
+        active_round: Round(3),
+        active_leader: Some(Author(1)),
+        highest_committed_round: committed.map(Round),
+        pending_messages,
+    }
+}
+
+#[test]
+fn test_render_frame() {
+    let mut dashboard = Dashboard::new(
+        Box::new(io::sink()),
+        Duration::from_millis(10),
+        std::time::Duration::from_millis(0),
+    );
+    let frame = dashboard.render_frame(GlobalTime(5), &[row(0, Some(1), 4), row(1, None, 0)]);
+    let lines: Vec<_> = frame.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "Simulated time: 5");
+    let fields: Vec<_> = lines[2].split_whitespace().collect();
+    assert_eq!(fields, vec!["0", "3", "1", "1", "4"]);
+    let fields: Vec<_> = lines[3].split_whitespace().collect();
+    assert_eq!(fields, vec!["1", "3", "1", "-", "0"]);
+}
+
+#[test]
+fn test_stalled_nodes() {
+    let mut dashboard = Dashboard::new(
+        Box::new(io::sink()),
+        Duration::from_millis(10),
+        std::time::Duration::from_millis(0),
+    );
+    dashboard.render_frame(GlobalTime(0), &[row(0, Some(1), 0), row(1, Some(1), 0)]);
+    let frame = dashboard.render_frame(GlobalTime(30), &[row(0, Some(2), 0), row(1, Some(1), 0)]);
+    let lines: Vec<_> = frame.lines().collect();
+    assert!(!lines[2].contains("stalled"));
+    assert!(lines[3].ends_with("stalled since 0"));
+}
+
+#[test]
+fn test_refresh_schedule() {
+    let mut dashboard = Dashboard::new(
+        Box::new(io::sink()),
+        Duration::from_millis(10),
+        std::time::Duration::from_millis(0),
+    );
+    assert!(dashboard.should_refresh(GlobalTime(0)));
+    dashboard.refresh(GlobalTime(25), &[]).unwrap();
+    assert!(!dashboard.should_refresh(GlobalTime(29)));
+    assert!(dashboard.should_refresh(GlobalTime(30)));
+}
@@ -0,0 +1,34 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_keys_per_seed() {
+    generate_keys(3, 7);
+    let key = public_key(Author(1));
+    assert_ne!(key, public_key(Author(2)));
+    let signature = Signature::sign(35, Author(1));
+    assert!(signature.check(35, Author(1)).is_ok());
+    assert!(Signature::forged(35).check(35, Author(1)).is_err());
+
+    // Other seeds give other keys, which reject the signature.
+    generate_keys(3, 8);
+    assert_ne!(public_key(Author(1)), key);
+    assert!(signature.check(35, Author(1)).is_err());
+    generate_keys(3, 7);
+    assert_eq!(public_key(Author(1)), key);
+}
+
+#[test]
+fn test_keys_in_other_threads() {
+    generate_keys(3, 11);
+    let signature = Signature::sign(35, Author(1));
+    let seed = key_seed();
+    let checked = std::thread::spawn(move || {
+        let before = signature.check(35, Author(1)).is_ok();
+        use_key_seed(seed);
+        (before, signature.check(35, Author(1)).is_ok())
+    });
+    assert_eq!(checked.join().unwrap(), (false, true));
+}
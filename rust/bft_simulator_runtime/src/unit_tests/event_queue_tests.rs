@@ -0,0 +1,58 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_time_order() {
+    let mut queue = EventQueue::new();
+    queue.push(5, "c");
+    queue.push(1, "a");
+    queue.push(3, "b");
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue.peek_time(), Some(1));
+    assert_eq!(queue.pop(), Some((1, "a")));
+    assert_eq!(queue.pop(), Some((3, "b")));
+    assert_eq!(queue.pop(), Some((5, "c")));
+    assert_eq!(queue.pop(), None);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_fifo_ties() {
+    let mut queue = EventQueue::new();
+    // Events with equal times come out in insertion order, regardless of their content.
+    for event in &[9, 2, 7, 4] {
+        queue.push(10, *event);
+    }
+    queue.push(0, 100);
+    let events: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+    assert_eq!(events, vec![(0, 100), (10, 9), (10, 2), (10, 7), (10, 4)]);
+}
+
+#[test]
+fn test_pop_nth() {
+    let mut queue = EventQueue::new();
+    for (time, event) in &[(1, 'a'), (2, 'b'), (2, 'c'), (3, 'd')] {
+        queue.push(*time, *event);
+    }
+    assert_eq!(queue.pop_nth(4), None);
+    assert_eq!(queue.pop_nth(2), Some((2, 'c')));
+    assert_eq!(queue.pop_nth(0), Some((1, 'a')));
+    let events: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+    assert_eq!(events, vec![(2, 'b'), (3, 'd')]);
+}
+
+#[test]
+fn test_slot_reuse() {
+    let mut queue = EventQueue::new();
+    queue.push(1, 'a');
+    queue.push(2, 'b');
+    assert_eq!(queue.pop(), Some((1, 'a')));
+    queue.push(2, 'c');
+    queue.push(0, 'd');
+    assert_eq!(queue.slab.len(), 3);
+    assert_eq!(queue.pop(), Some((0, 'd')));
+    assert_eq!(queue.pop(), Some((2, 'b')));
+    assert_eq!(queue.pop(), Some((2, 'c')));
+}
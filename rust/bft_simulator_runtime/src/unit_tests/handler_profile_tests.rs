@@ -0,0 +1,38 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_measure() {
+    // Without measurements, `measure` only runs the closure.
+    assert_eq!(measure(Handler::Update, || 3), 3);
+    let measurements = start();
+    measure(Handler::Notification, || {
+        measure(Handler::RecordInsertion, || ())
+    });
+    let times = measurements.finish();
+    let handlers: Vec<_> = times.iter().map(|(handler, _)| *handler).collect();
+    assert_eq!(
+        handlers,
+        vec![Handler::RecordInsertion, Handler::Notification]
+    );
+    assert!(times[0].1 <= times[1].1);
+    measure(Handler::Update, || ());
+    assert!(start().finish().is_empty());
+
+    let mut profile = HandlerProfile::new(2);
+    profile.record(Author(0), Handler::Update, StdDuration::from_micros(10));
+    profile.record(Author(1), Handler::Update, StdDuration::from_micros(30));
+    profile.record(Author(1), Handler::Request, StdDuration::from_micros(5));
+    let aggregate = profile.aggregate();
+    assert_eq!(aggregate[&Handler::Update].calls, 2);
+    assert_eq!(
+        aggregate[&Handler::Update].mean(),
+        StdDuration::from_micros(20)
+    );
+    assert_eq!(profile.node(Author(0)).len(), 1);
+    assert_eq!(profile.busiest_node(Handler::Update), Some(Author(1)));
+    assert_eq!(profile.busiest_node(Handler::Response), None);
+    assert_eq!(profile.to_string().lines().count(), 3);
+}
@@ -0,0 +1,33 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_html_report() {
+    let mut report = HtmlReport::new("Run <1>");
+    report.heading("Latency");
+    report.table(
+        &DISTRIBUTION_HEADERS,
+        &[distribution_row("commit", &Distribution::new(&[2.0, 4.0]))],
+    );
+    report.table(&["metric"], &[distribution_row("empty", &None)]);
+    report.bar_chart("Timeouts", &["0".to_string(), "1".to_string()], &[1.0, 3.0]);
+    report.line_chart("Throughput", &[(0.0, 0.5), (10.0, 1.5)]);
+    report.timeline("Faults", 100.0, &[(50.0, "crash of node 1".to_string())]);
+    let html = report.to_html();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Run &lt;1&gt;</title>"));
+    assert!(html.contains("<td>commit</td><td>2</td><td>2</td>"));
+    assert!(html.contains("<td>empty</td><td></td>"));
+    // The highest bar fills the plot, and the fault sits in the middle of the timeline.
+    assert!(html.contains("height=\"190.0\""));
+    assert!(html.contains("<line x1=\"345.0\""));
+    assert_eq!(html.matches("<svg").count(), 3);
+    // Nothing is loaded from elsewhere.
+    assert!(!html.contains("<script") && !html.contains("http"));
+
+    assert_eq!(format_number(2.0), "2");
+    assert_eq!(format_number(0.12345), "0.123");
+    assert_eq!(format_number(-1.5), "-1.5");
+}
@@ -0,0 +1,180 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_pull_and_commit() {
+    let mut mempool = Mempool::new();
+    for time in 0..5 {
+        mempool.submit(NodeTime(time), 10);
+    }
+    let batch = mempool.pull_batch(0, 2, None);
+    assert_eq!(
+        batch.iter().map(|x| x.index).collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    assert_eq!(batch[1].submission_time, NodeTime(1));
+    assert_eq!(mempool.num_pending(), 3);
+    // Transactions in flight are not pulled twice.
+    let batch = mempool.pull_batch(1, 2, None);
+    assert_eq!(
+        batch.iter().map(|x| x.index).collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+    mempool.commit_batch(1);
+    assert_eq!(mempool.num_committed(), 2);
+    // The first batch was abandoned, so it goes back before the remaining transaction.
+    assert_eq!(mempool.num_pending(), 3);
+    let batch = mempool.pull_batch(2, 10, None);
+    assert_eq!(
+        batch.iter().map(|x| x.index).collect::<Vec<_>>(),
+        vec![0, 1, 4]
+    );
+    mempool.commit_batch(2);
+    assert_eq!(mempool.num_committed(), 5);
+    assert_eq!(mempool.num_pending(), 0);
+}
+
+#[test]
+fn test_empty_batches() {
+    let mut mempool = Mempool::new();
+    assert!(mempool.pull_batch(0, 10, None).is_empty());
+    mempool.commit_batch(0);
+    assert_eq!(mempool.num_committed(), 0);
+}
+
+#[test]
+fn test_batch_limits() {
+    let mut mempool = Mempool::new();
+    for size in &[10, 20, 30, 5] {
+        mempool.submit(NodeTime(0), *size);
+    }
+    assert!(!mempool.is_batch_full(5, None));
+    assert!(mempool.is_batch_full(4, None));
+    assert!(mempool.is_batch_full(5, Some(60)));
+    // Batches keep the order of submission, even if a later transaction would fit.
+    let batch = mempool.pull_batch(0, 5, Some(40));
+    assert_eq!(
+        batch.iter().map(|x| x.size).collect::<Vec<_>>(),
+        vec![10, 20]
+    );
+    assert!(!mempool.is_batch_full(5, Some(35)));
+    assert_eq!(mempool.pull_batch(1, 5, Some(35)).len(), 2);
+}
+
+#[test]
+fn test_oversized_transactions() {
+    let mut mempool = Mempool::new();
+    for size in &[50, 10, 20] {
+        mempool.submit(NodeTime(0), *size);
+    }
+    // A transaction that can never fit does not hold back the later ones.
+    assert!(!mempool.is_batch_empty(5, Some(40)));
+    let batch = mempool.pull_batch(0, 5, Some(40));
+    assert_eq!(
+        batch.iter().map(|x| x.size).collect::<Vec<_>>(),
+        vec![10, 20]
+    );
+    assert!(mempool.is_batch_empty(5, Some(40)));
+    assert_eq!(mempool.num_pending(), 1);
+    // It is proposed once the limit allows it.
+    assert_eq!(mempool.pull_batch(1, 5, Some(50)).len(), 1);
+}
+
+#[test]
+fn test_targeted_transactions() {
+    let mut mempool = Mempool::new();
+    mempool.submit(NodeTime(0), 10);
+    mempool.submit_targeted(NodeTime(1), 10, 7);
+    mempool.submit_targeted(NodeTime(2), 10, 8);
+    // Targeted transactions are submitted once.
+    mempool.submit_targeted(NodeTime(2), 10, 8);
+    assert_eq!(mempool.num_pending(), 3);
+    mempool.set_censoring(true);
+    assert!(!mempool.is_batch_full(2, None));
+    let batch = mempool.pull_batch(0, 10, None);
+    assert_eq!(batch.len(), 1);
+    assert_eq!(batch[0].targeted, None);
+
+    // The targeted transaction 7 is committed by another proposer.
+    let other = Transaction {
+        index: 3,
+        submission_time: NodeTime(1),
+        size: 10,
+        targeted: Some(7),
+        request: None,
+        proposals: 0,
+    };
+    mempool.commit_targeted(&[other]);
+    assert_eq!(mempool.num_pending(), 1);
+    mempool.submit_targeted(NodeTime(3), 10, 7);
+    assert_eq!(mempool.num_pending(), 1);
+    mempool.set_censoring(false);
+    let batch = mempool.pull_batch(1, 10, None);
+    assert_eq!(batch.len(), 1);
+    assert_eq!(batch[0].targeted, Some(8));
+    mempool.submit_targeted(NodeTime(4), 10, 8);
+    assert_eq!(mempool.num_pending(), 0);
+}
+
+#[test]
+fn test_client_requests() {
+    let mut mempool = Mempool::new();
+    let first = ClientRequest {
+        client: 0,
+        sequence: 0,
+    };
+    let second = ClientRequest {
+        client: 1,
+        sequence: 0,
+    };
+    // Retries of a request held by the node are dropped.
+    mempool.submit_request(NodeTime(0), 10, first);
+    mempool.submit_request(NodeTime(1), 10, first);
+    mempool.submit_request(NodeTime(2), 10, second);
+    assert_eq!(mempool.num_pending(), 2);
+    let batch = mempool.pull_batch(0, 1, None);
+    assert_eq!(batch[0].request, Some(first));
+    mempool.submit_request(NodeTime(3), 10, first);
+    assert_eq!(mempool.num_pending(), 1);
+    mempool.commit_batch(0);
+    mempool.submit_request(NodeTime(4), 10, first);
+    assert_eq!(mempool.num_pending(), 1);
+
+    // The other request is committed by another proposer.
+    let other = Transaction {
+        index: 5,
+        submission_time: NodeTime(2),
+        size: 10,
+        targeted: None,
+        request: Some(second),
+        proposals: 0,
+    };
+    mempool.commit_targeted(&[other]);
+    assert_eq!(mempool.num_pending(), 0);
+}
+
+#[test]
+fn test_release_batch() {
+    let mut mempool = Mempool::new();
+    for time in 0..3 {
+        mempool.submit(NodeTime(time), 10);
+    }
+    assert_eq!(mempool.pull_batch(0, 2, None).len(), 2);
+    assert_eq!(mempool.pull_batch(1, 2, None).len(), 1);
+    // The block of the first batch was left out of the committed chain.
+    mempool.release_batch(0);
+    assert_eq!(mempool.num_pending(), 2);
+    let batch = mempool.pull_batch(2, 10, None);
+    assert_eq!(
+        batch.iter().map(|x| x.proposals).collect::<Vec<_>>(),
+        vec![1, 1]
+    );
+    // Releasing again or releasing a committed batch has no effect.
+    mempool.release_batch(0);
+    mempool.commit_batch(2);
+    mempool.release_batch(2);
+    assert_eq!(mempool.num_pending(), 0);
+    assert_eq!(mempool.num_committed(), 2);
+}
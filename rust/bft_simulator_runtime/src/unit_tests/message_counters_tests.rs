@@ -0,0 +1,46 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+fn records(blocks: usize, votes: usize) -> RecordCounts {
+    RecordCounts {
+        blocks,
+        votes,
+        ..RecordCounts::default()
+    }
+}
+
+#[test]
+fn test_counts() {
+    let mut counts = MessageCounts::default();
+    counts.add_message(MessageKind::Notification, records(1, 1));
+    counts.add_message(MessageKind::SyncRequest, RecordCounts::default());
+    counts.add_message(MessageKind::Notification, records(0, 1));
+    assert_eq!(counts.notifications, 2);
+    assert_eq!(counts.sync_requests, 1);
+    assert_eq!(counts.messages(), 3);
+    assert_eq!(counts.records, records(1, 2));
+    assert_eq!(counts.records.total(), 3);
+}
+
+#[test]
+fn test_total_counters() {
+    let mut node = NodeMessageCounters::default();
+    node.sent
+        .add_message(MessageKind::SyncResponse, records(2, 0));
+    node.received
+        .add_message(MessageKind::Notification, records(0, 1));
+    node.sent.add_message(
+        MessageKind::Notification,
+        RecordCounts {
+            payload_bytes: 100,
+            ..records(1, 0)
+        },
+    );
+    let total = total_counters(&[node, node]);
+    assert_eq!(total.sent.sync_responses, 2);
+    assert_eq!(total.sent.records.blocks, 6);
+    assert_eq!(total.sent.records.payload_bytes, 200);
+    assert_eq!(total.received.messages(), 2);
+}
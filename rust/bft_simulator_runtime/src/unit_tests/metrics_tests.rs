@@ -0,0 +1,94 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+fn report() -> MetricsReport {
+    MetricsReport {
+        schema_version: METRICS_SCHEMA_VERSION,
+        aggregate: AggregateMetrics {
+            duration: 100,
+            committed_blocks: 2.0,
+            commit_throughput: 0.02,
+            timeout_rate: 0.5,
+            commit_latency: Distribution::new(&[10.0, 20.0]),
+            rounds_per_commit: None,
+            chain_quality: ChainQuality {
+                honest_blocks: 2,
+                byzantine_blocks: 1,
+                longest_byzantine_span: 1,
+            },
+            wasted_rounds: None,
+            transaction_throughput: 0.0,
+            transaction_latency: None,
+            stake_weighted_committed_blocks: 2.0,
+            fast_path_rate: 0.5,
+            resource_costs: ResourceCosts {
+                committed_blocks: 2,
+                messages: 20,
+                bytes: 3000,
+                signatures: 8,
+                verifications: 24,
+                macs: 0,
+            },
+        },
+        nodes: vec![NodeMetrics {
+            author: 0,
+            active_round: 4,
+            committed_blocks: 2,
+            highest_committed_round: Some(3),
+            quorum_certificate_rounds: 3,
+            timeout_certificate_rounds: 1,
+            mean_commit_latency: None,
+            crashed: false,
+            messages_sent: 10,
+            messages_received: 8,
+            records_sent: 25,
+            records_received: 20,
+            timeouts_as_leader: 1,
+            stake: 1,
+            errors: 0,
+            pacemaker_switches: 2,
+            pacemaker_switch_times: vec![GlobalTime(100), GlobalTime(250)],
+            evicted_records: 3,
+            suppressed_notifications: 4,
+            corrupted_messages: 3,
+            rejected_messages: 2,
+            over_quota_messages: 5,
+            suspicions: 1,
+            rejected_proposals: 2,
+        }],
+    }
+}
+
+#[test]
+fn test_json() {
+    let json: serde_json::Value = serde_json::from_str(&report().to_json().unwrap()).unwrap();
+    assert_eq!(json["schema_version"], METRICS_SCHEMA_VERSION);
+    assert_eq!(json["aggregate"]["commit_latency"]["p99"], 20.0);
+    assert!(json["aggregate"]["rounds_per_commit"].is_null());
+    assert_eq!(json["nodes"][0]["highest_committed_round"], 3);
+}
+
+#[test]
+fn test_csv() {
+    let mut output = Vec::new();
+    report().write_aggregate_csv(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("schema_version,duration,"));
+    assert!(lines[0].contains(",commit_latency_p95,"));
+    assert!(lines[1].starts_with("1,100,2,0.02,0.5,2,10,20,15,10,20,20,"));
+    assert!(lines[1].ends_with(",,,,,,,2,1,1,,,,,,,,0,,,,,,,,2,0.5,10,1500,4,12,0"));
+
+    let mut output = Vec::new();
+    report().write_nodes_csv(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[1],
+        "1,0,4,2,3,3,1,,false,10,8,25,20,1,1,0,2,100;250,3,4,3,2,5,1,2"
+    );
+}
@@ -0,0 +1,72 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use std::sync::mpsc::channel;
+
+#[test]
+fn test_partition_groups() {
+    let groups = vec![vec![Author(0), Author(2)], vec![Author(1)]];
+    assert_eq!(partition_groups(5, &groups).unwrap(), vec![0, 1, 0, 2, 2]);
+    assert_eq!(partition_groups(2, &[]).unwrap(), vec![0, 0]);
+    assert!(partition_groups(2, &groups).is_err());
+}
+
+#[test]
+fn test_channel_nemesis() {
+    let (sender, mut receiver) = channel();
+    assert_eq!(receiver.faults(GlobalTime(0)), vec![]);
+    sender.send(Fault::Crash(Author(1))).unwrap();
+    sender
+        .send(Fault::ClockJump(Author(2), Duration::from_millis(30)))
+        .unwrap();
+    assert_eq!(
+        receiver.faults(GlobalTime(10)),
+        vec![
+            Fault::Crash(Author(1)),
+            Fault::ClockJump(Author(2), Duration::from_millis(30))
+        ]
+    );
+    assert_eq!(receiver.faults(GlobalTime(20)), vec![]);
+}
+
+#[test]
+fn test_fault_schedule() {
+    let mut schedule = FaultSchedule::new(vec![
+        (GlobalTime(20), Fault::Heal),
+        (GlobalTime(10), Fault::Crash(Author(1))),
+        (GlobalTime(10), Fault::Crash(Author(2))),
+    ]);
+    assert_eq!(schedule.faults(GlobalTime(5)), vec![]);
+    assert_eq!(
+        schedule.faults(GlobalTime(12)),
+        vec![Fault::Crash(Author(1)), Fault::Crash(Author(2))]
+    );
+    assert_eq!(schedule.faults(GlobalTime(30)), vec![Fault::Heal]);
+    assert_eq!(schedule.faults(GlobalTime(40)), vec![]);
+}
+
+#[test]
+fn test_fault_author() {
+    assert_eq!(Fault::Crash(Author(1)).author(), Some(Author(1)));
+    assert_eq!(
+        Fault::Flood(Author(2), Flood::default()).author(),
+        Some(Author(2))
+    );
+    assert_eq!(Fault::Partition(vec![vec![Author(0)]]).author(), None);
+    assert_eq!(Fault::Heal.author(), None);
+}
+
+#[test]
+fn test_fault_display() {
+    let partition = Fault::Partition(vec![vec![Author(0), Author(2)], vec![Author(1)]]);
+    assert_eq!(partition.to_string(), "partition [[0, 2], [1]]");
+    assert_eq!(
+        Fault::ClockJump(Author(1), Duration::from_millis(-10)).to_string(),
+        "clock jump of node 1 by -10ms"
+    );
+    assert_eq!(
+        Fault::EndEclipse(Author(3)).to_string(),
+        "end of the eclipse of node 3"
+    );
+}
@@ -0,0 +1,81 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_network_trace() {
+    let contents = "time,mean,variance,loss_rate\n30000,,,0.2\n10000,50.0,,\n";
+    let trace = NetworkTrace::from_csv(contents.as_bytes()).unwrap();
+    assert_eq!(
+        trace.changes(),
+        &[
+            NetworkChange {
+                time: GlobalTime(10000),
+                mean: Some(50.0),
+                variance: None,
+                loss_rate: None,
+                ramp: None,
+                steps: None,
+            },
+            NetworkChange {
+                time: GlobalTime(30000),
+                mean: None,
+                variance: None,
+                loss_rate: Some(0.2),
+                ramp: None,
+                steps: None,
+            },
+        ][..]
+    );
+    assert!(NetworkTrace::from_csv("time,mean,variance,loss_rate\n0,,,2.0\n".as_bytes()).is_err());
+    assert!(NetworkTrace::from_csv("time,delay\n0,10\n".as_bytes()).is_err());
+    assert!(NetworkTrace::from_csv("time,mean,variance,loss_rate\n0,inf,,\n".as_bytes()).is_err());
+    assert!(NetworkTrace::from_csv("time,mean,variance,loss_rate\n0,,NaN,\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_network_ramp() {
+    let contents = "time,mean,variance,loss_rate,ramp,steps\n100,30.0,,0.4,100,\n300,,,0.0,100,4\n";
+    let trace = NetworkTrace::from_csv(contents.as_bytes()).unwrap();
+    let from = NetworkConditions {
+        mean: 10.0,
+        variance: 4.0,
+        loss_rate: 0.0,
+    };
+    let linear = NetworkRamp::new(&trace.changes()[0], from).unwrap();
+    assert_eq!(linear.end(), GlobalTime(200));
+    assert_eq!(linear.conditions_at(GlobalTime(50)), from);
+    let halfway = linear.conditions_at(GlobalTime(150));
+    assert!((halfway.mean - 20.0).abs() < 1e-9);
+    assert!((halfway.variance - 4.0).abs() < 1e-9);
+    assert!((halfway.loss_rate - 0.2).abs() < 1e-9);
+    assert_eq!(
+        linear.conditions_at(GlobalTime(250)),
+        trace.changes()[0].apply(from)
+    );
+
+    let stepwise =
+        NetworkRamp::new(&trace.changes()[1], linear.conditions_at(GlobalTime(300))).unwrap();
+    assert!((stepwise.conditions_at(GlobalTime(320)).loss_rate - 0.4).abs() < 1e-9);
+    assert!((stepwise.conditions_at(GlobalTime(330)).loss_rate - 0.3).abs() < 1e-9);
+    assert!((stepwise.conditions_at(GlobalTime(399)).loss_rate - 0.1).abs() < 1e-9);
+    assert!(stepwise.conditions_at(GlobalTime(400)).loss_rate.abs() < 1e-9);
+
+    assert!(NetworkRamp::new(
+        &NetworkChange {
+            ramp: None,
+            ..trace.changes()[0].clone()
+        },
+        from
+    )
+    .is_none());
+    assert!(NetworkTrace::from_csv(
+        "time,mean,variance,loss_rate,ramp,steps\n0,,,,0,\n".as_bytes()
+    )
+    .is_err());
+    assert!(NetworkTrace::from_csv(
+        "time,mean,variance,loss_rate,ramp,steps\n0,,,,,2\n".as_bytes()
+    )
+    .is_err());
+}
@@ -0,0 +1,67 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use log::Level;
+
+struct Silent;
+
+impl Log for Silent {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        false
+    }
+
+    fn log(&self, _record: &Record) {}
+
+    fn flush(&self) {}
+}
+
+fn log_message(logs: &NodeLogs, level: Level, message: &str) {
+    logs.log(
+        &Record::builder()
+            .level(level)
+            .target("test")
+            .args(format_args!("{}", message))
+            .build(),
+    );
+}
+
+#[test]
+fn test_node_logs() {
+    assert_eq!(
+        "3:debug:node_3.log".parse::<NodeLogConfig>(),
+        Ok(NodeLogConfig {
+            author: Author(3),
+            level: LevelFilter::Debug,
+            output: NodeLogOutput::File(PathBuf::from("node_3.log")),
+        })
+    );
+    assert!("3".parse::<NodeLogConfig>().is_err());
+    assert!("3:loud".parse::<NodeLogConfig>().is_err());
+
+    let config = NodeLogConfig {
+        author: Author(1),
+        level: LevelFilter::Debug,
+        output: NodeLogOutput::Buffer,
+    };
+    let logs = NodeLogs::new(Box::new(Silent), LevelFilter::Warn, &[config]).unwrap();
+    assert_eq!(logs.max_level(), LevelFilter::Debug);
+    log_message(&logs, Level::Debug, "outside of any node");
+    {
+        let _scope = enter(Author(1));
+        assert_eq!(current_node(), Some(Author(1)));
+        log_message(&logs, Level::Debug, "kept");
+        log_message(&logs, Level::Trace, "too verbose");
+        {
+            let _scope = enter(Author(0));
+            log_message(&logs, Level::Debug, "another node");
+        }
+        assert_eq!(current_node(), Some(Author(1)));
+    }
+    assert_eq!(current_node(), None);
+    assert_eq!(
+        logs.captured(Author(1)),
+        vec!["DEBUG test kept".to_string()]
+    );
+    assert!(logs.captured(Author(0)).is_empty());
+}
@@ -0,0 +1,37 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_render() {
+    let mut counters = NodeMessageCounters::default();
+    counters.sent.notifications = 5;
+    counters.received.sync_responses = 2;
+    let sample = NodeSample {
+        author: Author(1),
+        active_round: Round(7),
+        committed_round: Round(4),
+        committed_blocks: 3,
+        quorum_certificate_rounds: 6,
+        timeout_certificate_rounds: 1,
+        counters,
+    };
+    let output = render(&[sample]);
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(
+        &lines[..3],
+        &[
+            "# HELP bft_active_round Active round of the node.",
+            "# TYPE bft_active_round gauge",
+            "bft_active_round{node=\"1\"} 7",
+        ]
+    );
+    assert!(lines.contains(&"bft_committed_round{node=\"1\"} 4"));
+    assert!(lines.contains(&"# TYPE bft_timeout_certificate_rounds_total counter"));
+    assert!(lines.contains(&"bft_messages_sent_total{node=\"1\",kind=\"notification\"} 5"));
+    assert!(lines.contains(&"bft_messages_received_total{node=\"1\",kind=\"sync_response\"} 2"));
+    // Every sample follows the header of its family.
+    let samples = lines.iter().filter(|line| !line.starts_with('#')).count();
+    assert_eq!(samples, 5 + 3 + 3);
+}
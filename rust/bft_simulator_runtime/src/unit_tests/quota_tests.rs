@@ -0,0 +1,40 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_quota() {
+    assert!(!MessageQuota::default().is_active());
+    let quota = MessageQuota {
+        period: Duration::from_millis(10),
+        max_messages: Some(2),
+        max_bytes: Some(100),
+    };
+    assert!(quota.check().is_ok());
+    let mut ledger = QuotaLedger::default();
+    assert!(ledger.admit(&quota, Author(1), GlobalTime(0), 10));
+    assert!(ledger.admit(&quota, Author(1), GlobalTime(5), 10));
+    // Too many messages in the period, but other senders have their own quota.
+    assert!(!ledger.admit(&quota, Author(1), GlobalTime(9), 10));
+    assert!(ledger.admit(&quota, Author(2), GlobalTime(9), 10));
+    // Quotas start over with each period, and also limit bytes.
+    assert!(ledger.admit(&quota, Author(1), GlobalTime(10), 60));
+    assert!(!ledger.admit(&quota, Author(1), GlobalTime(11), 60));
+    assert!(ledger.admit(&quota, Author(1), GlobalTime(12), 40));
+    assert_eq!(ledger.dropped(Author(1)), 2);
+    assert_eq!(ledger.dropped(Author(2)), 0);
+
+    assert!(MessageQuota {
+        period: Duration::from_millis(0),
+        ..MessageQuota::default()
+    }
+    .check()
+    .is_err());
+    assert!(MessageQuota {
+        max_messages: Some(0),
+        ..MessageQuota::default()
+    }
+    .check()
+    .is_err());
+}
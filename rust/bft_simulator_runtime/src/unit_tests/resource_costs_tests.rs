@@ -0,0 +1,24 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_per_commit() {
+    let mut costs = ResourceCosts {
+        committed_blocks: 4,
+        messages: 30,
+        bytes: 1000,
+        signatures: 10,
+        verifications: 0,
+        macs: 6,
+    };
+    assert_eq!(costs.messages_per_commit(), Some(7.5));
+    assert_eq!(costs.bytes_per_commit(), Some(250.0));
+    assert_eq!(costs.signatures_per_commit(), Some(2.5));
+    assert_eq!(costs.verifications_per_commit(), Some(0.0));
+    assert_eq!(costs.macs_per_commit(), Some(1.5));
+
+    costs.committed_blocks = 0;
+    assert_eq!(costs.messages_per_commit(), None);
+}
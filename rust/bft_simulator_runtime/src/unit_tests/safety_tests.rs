@@ -0,0 +1,41 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::base_types::{NodeTime, Round};
+
+fn block(round: usize, hash: u64) -> CommittedBlock {
+    CommittedBlock {
+        round: Round(round),
+        proposer: Author(0),
+        proposal_time: NodeTime(0),
+        hash,
+        transactions: Vec::new(),
+        fast_path: false,
+    }
+}
+
+#[test]
+fn test_prefixes() {
+    let mut checker = SafetyChecker::new(2);
+    let chain = vec![block(1, 10), block(2, 20), block(4, 40)];
+    checker.check_commits(Author(0), &chain[..1]).unwrap();
+    checker.check_commits(Author(1), &chain).unwrap();
+    checker.check_commits(Author(0), &chain[..2]).unwrap();
+    assert_eq!(checker.chain().len(), 3);
+}
+
+#[test]
+fn test_conflicting_commits() {
+    let mut checker = SafetyChecker::new(2);
+    let chain = vec![block(1, 10), block(2, 20), block(4, 40)];
+    checker.check_commits(Author(0), &chain).unwrap();
+    let fork = vec![block(1, 10), block(3, 30)];
+    let violation = checker.check_commits(Author(1), &fork).unwrap_err();
+    assert_eq!(violation.position, 1);
+    assert_eq!(violation.first_author, Author(0));
+    assert_eq!(violation.first_block, block(2, 20));
+    assert_eq!(violation.block, block(3, 30));
+    assert_eq!(violation.common_prefix, vec![block(1, 10)]);
+    assert!(violation.to_string().contains("position 1"));
+}
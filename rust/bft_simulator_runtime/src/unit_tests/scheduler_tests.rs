@@ -0,0 +1,76 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_prefix_scheduler() {
+    let mut scheduler = PrefixScheduler::new(2, vec![1, 0], 4);
+    assert_eq!(scheduler.choose(2, 10), Some(1));
+    assert_eq!(scheduler.choose(1, 11), Some(0));
+    assert_eq!(scheduler.choose(2, 12), Some(0));
+    assert_eq!(scheduler.choose(2, 13), Some(0));
+    // Maximal depth reached.
+    assert_eq!(scheduler.choose(2, 14), None);
+    assert!(!scheduler.is_pruned());
+    let prefix = PrefixScheduler::next_prefix(scheduler.decisions());
+    assert_eq!(prefix, Some(vec![1, 0, 0, 1]));
+    let done = [Decision {
+        choice: 1,
+        choices: 2,
+    }];
+    assert_eq!(PrefixScheduler::next_prefix(&done), None);
+}
+
+#[test]
+fn test_visited_states() {
+    let visited: HashSet<_> = vec![12].into_iter().collect();
+    let mut scheduler = PrefixScheduler::new(2, vec![0], 10).with_visited_states(visited);
+    // States in the prefix are not checked.
+    assert_eq!(scheduler.choose(2, 12), Some(0));
+    assert_eq!(scheduler.choose(2, 13), Some(0));
+    assert_eq!(scheduler.choose(2, 12), None);
+    assert!(scheduler.is_pruned());
+    assert_eq!(scheduler.into_visited_states().len(), 2);
+}
+
+#[test]
+fn test_coverage_scheduler_tries_new_choices() {
+    let mut scheduler = CoverageScheduler::new(2, 10, 0);
+    let first = scheduler.choose(2, 1).unwrap();
+    assert!(scheduler.choose(2, 2).is_some());
+    assert_eq!(scheduler.num_states(), 2);
+    assert_eq!(scheduler.new_states(), 2);
+    scheduler.start_run();
+    // The other choice was never tried in state 1.
+    assert_eq!(scheduler.choose(2, 1), Some(1 - first));
+    assert_eq!(scheduler.new_states(), 0);
+}
+
+#[test]
+fn test_coverage_scheduler_prefers_rare_states() {
+    let mut scheduler = CoverageScheduler::new(2, 10, 0);
+    // In state 1, choice 0 leads to the frequent state 2 and choice 1 to state 3.
+    scheduler.successors.insert((1, 0), 2);
+    scheduler.successors.insert((1, 1), 3);
+    scheduler.visits.insert(2, 1000);
+    scheduler.visits.insert(3, 1);
+    let rare_choices = (0..100)
+        .filter(|_| {
+            scheduler.start_run();
+            scheduler.choose(2, 1) == Some(1)
+        })
+        .count();
+    assert!(rare_choices > 90);
+}
+
+#[test]
+fn test_coverage_scheduler_max_depth() {
+    let mut scheduler = CoverageScheduler::new(3, 2, 0);
+    assert!(scheduler.choose(3, 1).is_some());
+    assert!(scheduler.choose(1, 2).is_some());
+    assert_eq!(scheduler.choose(3, 3), None);
+    assert_eq!(scheduler.choices().len(), 2);
+    // The last state still counts as covered.
+    assert_eq!(scheduler.num_states(), 3);
+}
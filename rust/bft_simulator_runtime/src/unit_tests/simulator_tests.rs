@@ -10,3 +10,51 @@ fn test_time_conversion() {
     assert_eq!(x.to_node_time(start), NodeTime(12));
     assert_eq!(GlobalTime::from_node_time(NodeTime(12), start), x);
 }
+
+/// A message that counts its encodings.
+struct Message {
+    size: usize,
+    encodings: std::cell::Cell<usize>,
+}
+
+impl Message {
+    fn new(size: usize) -> Rc<Self> {
+        Rc::new(Message {
+            size,
+            encodings: std::cell::Cell::new(0),
+        })
+    }
+}
+
+impl WireFormat for Message {
+    fn to_wire(&self) -> Vec<u8> {
+        self.encodings.set(self.encodings.get() + 1);
+        vec![0; self.size]
+    }
+
+    fn from_wire(bytes: &[u8]) -> crate::base_types::Result<Self> {
+        Ok(Message {
+            size: bytes.len(),
+            encodings: std::cell::Cell::new(0),
+        })
+    }
+}
+
+#[test]
+fn test_wire_sizes() {
+    let mut sizes = WireSizes::new();
+    let message = Message::new(5);
+    let receivers: Vec<_> = (0..3).map(|_| message.clone()).collect();
+    for receiver in &receivers {
+        assert_eq!(sizes.get(receiver), 5);
+    }
+    // A shared message is encoded once for all its receivers.
+    assert_eq!(message.encodings.get(), 1);
+    // Messages that no event holds anymore are eventually released.
+    drop(receivers);
+    drop(message);
+    for size in 0..40 {
+        assert_eq!(sizes.get(&Message::new(size)), size);
+    }
+    assert!(sizes.sizes.len() < 40);
+}
@@ -0,0 +1,61 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::inspection::PendingActions;
+
+fn node_stall(author: usize, active_round: usize, highest_qc_round: usize) -> NodeStall {
+    NodeStall {
+        view: NodeView {
+            author: Author(author),
+            running: true,
+            active_round: Round(active_round),
+            active_leader: Some(Author(active_round % 2)),
+            highest_quorum_certificate_round: Round(highest_qc_round),
+            highest_committed_round: None,
+            committed_blocks: 0,
+            pending: PendingActions {
+                messages: 0,
+                next_update: None,
+                busy_until: None,
+            },
+        },
+        timeout_certificate_rounds: vec![Round(4)],
+    }
+}
+
+#[test]
+fn test_stall_report() {
+    let mut tracker = StallTracker::default();
+    let report = tracker.report(GlobalTime(10), GlobalTime(0), vec![node_stall(0, 3, 2)]);
+    assert_eq!(report.kind, StallKind::Deadlock);
+    tracker.record_round(Round(4), Some(Author(0)));
+    tracker.record_round(Round(4), Some(Author(0)));
+    tracker.record_round(Round(5), Some(Author(1)));
+    tracker.record_drop(Author(0), Author(1), DropCause::Partition);
+    tracker.record_drop(Author(0), Author(1), DropCause::Partition);
+    tracker.record_drop(Author(1), Author(0), DropCause::Loss);
+    let nodes = vec![node_stall(0, 5, 2), node_stall(1, 5, 3)];
+    let report = tracker.report(GlobalTime(10), GlobalTime(0), nodes);
+    assert_eq!(report.kind, StallKind::Livelock);
+    assert_eq!(report.leaders.len(), 2);
+    assert_eq!(report.highest_quorum_certificate_round(), Round(3));
+    // Round 4 ended with a TC, the active round 5 with nothing yet.
+    assert_eq!(
+        report.nodes[0].missing_certificate_rounds(),
+        vec![Round(3), Round(5)]
+    );
+    assert_eq!(report.nodes[1].missing_certificate_rounds(), vec![Round(5)]);
+    let dropped = report.dropped_by_cause();
+    assert_eq!(dropped[&DropCause::Partition], 2);
+    assert_eq!(dropped[&DropCause::Loss], 1);
+    let text = report.to_string();
+    assert!(text.contains("(livelock)"));
+    assert!(text.contains("dropped from 0 to 1: 2 partitioned"));
+    assert!(text.contains("no QC or TC at rounds [3, 5]"));
+
+    tracker.reset();
+    let report = tracker.report(GlobalTime(20), GlobalTime(15), Vec::new());
+    assert_eq!(report.kind, StallKind::Deadlock);
+    assert!(report.dropped.is_empty());
+}
@@ -0,0 +1,334 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::{
+    base_types::{NodeTime, Round},
+    mempool::Transaction,
+};
+
+fn millis(latencies: &[Duration]) -> Vec<i64> {
+    latencies.iter().map(|x| x.as_millis()).collect()
+}
+
+fn block(round: usize, proposal_time: i64) -> CommittedBlock {
+    CommittedBlock {
+        round: Round(round),
+        proposer: Author(0),
+        proposal_time: NodeTime(proposal_time),
+        hash: round as u64,
+        transactions: Vec::new(),
+        fast_path: false,
+    }
+}
+
+fn transaction(index: usize, submission_time: i64) -> Transaction {
+    Transaction {
+        index,
+        submission_time: NodeTime(submission_time),
+        size: 0,
+        targeted: None,
+        request: None,
+        proposals: 0,
+    }
+}
+
+#[test]
+fn test_record_commits() {
+    let mut statistics = Statistics::new(2);
+    assert_eq!(statistics.mean_commit_latency(), None);
+    let offset = |block: &CommittedBlock| GlobalTime(block.proposal_time.0 + 5);
+    let mut blocks = vec![block(1, 10), block(2, 20)];
+    blocks[1].fast_path = true;
+    statistics.record_commits(Author(1), &blocks[..1], GlobalTime(25), offset);
+    statistics.record_commits(Author(1), &blocks, GlobalTime(35), offset);
+    // Blocks already accounted for are skipped.
+    statistics.record_commits(Author(1), &blocks, GlobalTime(50), offset);
+    assert_eq!(millis(statistics.commit_latencies()), vec![10, 10]);
+    statistics.record_commits(Author(0), &blocks, GlobalTime(45), offset);
+    assert_eq!(millis(statistics.commit_latencies()), vec![10, 10, 30, 20]);
+    assert_eq!(statistics.mean_commit_latency(), Some(17.5));
+    assert_eq!(statistics.fast_path_rate(), 0.5);
+}
+
+#[test]
+fn test_measurement_phases() {
+    let phases = MeasurementPhases {
+        warm_up: Duration::from_millis(30),
+        window: Some(Duration::from_millis(20)),
+    };
+    assert!(!phases.is_measured(GlobalTime(29)));
+    assert!(phases.is_measured(GlobalTime(50)));
+    assert!(!phases.is_measured(GlobalTime(51)));
+    assert_eq!(phases.measured_duration(GlobalTime(10)), Duration::ZERO);
+    assert_eq!(
+        phases.measured_duration(GlobalTime(40)),
+        Duration::from_millis(10)
+    );
+    assert_eq!(
+        phases.measured_duration(GlobalTime(90)),
+        Duration::from_millis(20)
+    );
+
+    let mut statistics = Statistics::new(1);
+    statistics.set_phases(phases);
+    let offset = |block: &CommittedBlock| GlobalTime(block.proposal_time.0);
+    let blocks = vec![block(1, 10), block(3, 30), block(4, 50)];
+    statistics.record_commits(Author(0), &blocks[..1], GlobalTime(20), offset);
+    statistics.record_commits(Author(0), &blocks[..2], GlobalTime(40), offset);
+    statistics.record_commits(Author(0), &blocks, GlobalTime(60), offset);
+    // Only the commit during the window counts, with the rounds since the previous commit.
+    assert_eq!(millis(statistics.commit_latencies()), vec![10]);
+    assert_eq!(statistics.rounds_per_commit(), &[2]);
+    assert_eq!(statistics.measured_commits(Author(0)), 1);
+}
+
+#[test]
+fn test_transaction_latencies() {
+    let mut statistics = Statistics::new(1);
+    let blocks = vec![CommittedBlock {
+        transactions: vec![transaction(0, 4), transaction(1, 9)],
+        ..block(1, 10)
+    }];
+    let offset = |block: &CommittedBlock| GlobalTime(block.proposal_time.0 + 5);
+    statistics.record_commits(Author(0), &blocks, GlobalTime(25), offset);
+    assert_eq!(millis(statistics.commit_latencies()), vec![10]);
+    assert_eq!(millis(statistics.transaction_latencies()), vec![16, 11]);
+    let distribution = statistics.transaction_latency_distribution().unwrap();
+    assert_eq!(distribution.max, 16.0);
+    assert_eq!(statistics.proposals_per_transaction(), &[1, 1]);
+
+    // Targeted transactions are measured apart, once per node.
+    let targeted = Transaction {
+        targeted: Some(3),
+        ..transaction(2, 8)
+    };
+    let blocks = vec![
+        blocks[0].clone(),
+        CommittedBlock {
+            transactions: vec![targeted],
+            ..block(2, 20)
+        },
+        CommittedBlock {
+            transactions: vec![targeted],
+            ..block(3, 30)
+        },
+    ];
+    statistics.record_commits(Author(0), &blocks, GlobalTime(45), offset);
+    assert_eq!(millis(statistics.transaction_latencies()), vec![16, 11]);
+    assert_eq!(millis(statistics.targeted_latencies()), vec![32]);
+
+    // Transactions of abandoned blocks count their earlier proposals.
+    let mut blocks = blocks;
+    blocks.push(CommittedBlock {
+        transactions: vec![Transaction {
+            proposals: 2,
+            ..transaction(3, 30)
+        }],
+        ..block(4, 40)
+    });
+    statistics.record_commits(Author(0), &blocks, GlobalTime(55), offset);
+    assert_eq!(statistics.proposals_per_transaction(), &[1, 1, 3]);
+    let distribution = statistics.proposals_per_transaction_distribution().unwrap();
+    assert_eq!(distribution.max, 3.0);
+}
+
+#[test]
+fn test_client_latencies() {
+    // Three commits out of four nodes make a quorum.
+    let mut statistics = Statistics::new(4);
+    let blocks = vec![CommittedBlock {
+        transactions: vec![transaction(0, 4), transaction(1, 9)],
+        ..block(1, 10)
+    }];
+    let offset = |block: &CommittedBlock| GlobalTime(block.proposal_time.0);
+    statistics.record_commits(Author(0), &blocks, GlobalTime(20), offset);
+    statistics.record_commits(Author(1), &blocks, GlobalTime(25), offset);
+    assert!(statistics.client_latencies().is_empty());
+    assert_eq!(statistics.client_latency_distribution(), None);
+    statistics.record_commits(Author(2), &blocks, GlobalTime(30), offset);
+    statistics.record_commits(Author(3), &blocks, GlobalTime(40), offset);
+    let latencies: Vec<_> = statistics.client_latencies().iter().collect();
+    assert_eq!(
+        latencies,
+        vec![
+            (&(Author(0), 0), &Duration::from_millis(26)),
+            (&(Author(0), 1), &Duration::from_millis(21))
+        ]
+    );
+    assert_eq!(statistics.client_latency_distribution().unwrap().mean, 23.5);
+}
+
+#[test]
+fn test_stake_weighted_client_latencies() {
+    // Node 0 holds 4 of the 7 units of stake, so that a quorum needs 5 of them.
+    let mut statistics = Statistics::new(4);
+    statistics.set_stake(Author(0), 4);
+    assert_eq!(statistics.quorum_stake(), 5);
+    let blocks = vec![CommittedBlock {
+        transactions: vec![transaction(0, 4)],
+        ..block(1, 10)
+    }];
+    let offset = |block: &CommittedBlock| GlobalTime(block.proposal_time.0);
+    statistics.record_commits(Author(1), &blocks, GlobalTime(20), offset);
+    statistics.record_commits(Author(2), &blocks, GlobalTime(25), offset);
+    // Three of the four nodes hold less than a quorum of the stake.
+    statistics.record_commits(Author(3), &blocks, GlobalTime(30), offset);
+    assert_eq!(statistics.committed_stake(blocks[0].hash), 3);
+    assert!(statistics.client_latencies().is_empty());
+    statistics.record_commits(Author(0), &blocks, GlobalTime(40), offset);
+    assert_eq!(statistics.committed_stake(blocks[0].hash), 7);
+    let latencies: Vec<_> = statistics.client_latencies().values().collect();
+    assert_eq!(latencies, vec![&Duration::from_millis(36)]);
+}
+
+#[test]
+fn test_wasted_rounds() {
+    let mut statistics = Statistics::new(1);
+    assert_eq!(statistics.wasted_rounds_distribution(), None);
+    let offset = |block: &CommittedBlock| GlobalTime(block.proposal_time.0);
+    let blocks = vec![block(1, 0), block(2, 0), block(5, 0)];
+    statistics.record_commits(Author(0), &blocks, GlobalTime(10), offset);
+    let wasted = statistics.wasted_rounds_distribution().unwrap();
+    assert_eq!(wasted.count, 3);
+    assert_eq!(wasted.max, 2.0);
+    assert_eq!(wasted.p50, 0.0);
+}
+
+#[test]
+fn test_chain_quality() {
+    let proposers = [0, 2, 2, 1, 2, 2, 2, 0];
+    let blocks: Vec<_> = proposers
+        .iter()
+        .enumerate()
+        .map(|(round, proposer)| CommittedBlock {
+            proposer: Author(*proposer),
+            ..block(round, 0)
+        })
+        .collect();
+    let byzantine_nodes = vec![Author(2)].into_iter().collect();
+    let quality = ChainQuality::new(&blocks, &byzantine_nodes);
+    assert_eq!(quality.honest_blocks, 3);
+    assert_eq!(quality.byzantine_blocks, 5);
+    assert_eq!(quality.longest_byzantine_span, 3);
+    assert_eq!(quality.honest_fraction(), Some(0.375));
+    let empty = ChainQuality::new(&[], &byzantine_nodes);
+    assert_eq!(empty.honest_fraction(), None);
+}
+
+#[test]
+fn test_sample_statistics() {
+    assert_eq!(SampleStatistics::new(&[]), None);
+    let single = SampleStatistics::new(&[3.0]).unwrap();
+    assert_eq!(single.confidence_interval, (3.0, 3.0));
+    let sample = SampleStatistics::new(&[1.0, 2.0, 4.0, 5.0]).unwrap();
+    assert_eq!(sample.count, 4);
+    assert!((sample.mean - 3.0).abs() < 1e-9);
+    assert!((sample.median - 3.0).abs() < 1e-9);
+    assert!((sample.std_dev - (10.0f64 / 3.0).sqrt()).abs() < 1e-9);
+    assert_eq!((sample.min, sample.max), (1.0, 5.0));
+    assert!(sample.is_seed_sensitive());
+    assert!(!single.is_seed_sensitive());
+    // Standard deviation is sqrt(10/3), hence a half-width of 3.182 * sqrt(10/3) / 2.
+    let (low, high) = sample.confidence_interval;
+    assert!((high - 3.0 - 2.9047).abs() < 1e-3);
+    assert!((3.0 - low - 2.9047).abs() < 1e-3);
+}
+
+#[test]
+fn test_paired_comparison() {
+    assert_eq!(PairedComparison::new(&[]), None);
+    let single = PairedComparison::new(&[(1.0, 2.0)]).unwrap();
+    assert!(!single.is_significant());
+    // Pairs cancel the variance of the seeds: b is always a bit larger than a.
+    let pairs = [(1.0, 1.5), (10.0, 10.4), (5.0, 5.6), (20.0, 20.5)];
+    let comparison = PairedComparison::new(&pairs).unwrap();
+    assert_eq!(comparison.count, 4);
+    assert!((comparison.mean_a - 9.0).abs() < 1e-9);
+    assert!((comparison.mean_difference - 0.5).abs() < 1e-9);
+    assert!(comparison.is_significant());
+    assert!((comparison.relative_difference().unwrap() - 0.5 / 9.0).abs() < 1e-9);
+    let mixed = PairedComparison::new(&[(1.0, 2.0), (2.0, 1.0), (3.0, 3.5)]).unwrap();
+    assert!(!mixed.is_significant());
+}
+
+fn summary(committed_blocks: f64, mean_commit_latency: Option<f64>) -> RunSummary {
+    RunSummary {
+        duration: Duration::from_millis(100),
+        committed_blocks,
+        stake_weighted_committed_blocks: committed_blocks,
+        commit_throughput: committed_blocks / 100.0,
+        mean_commit_latency,
+        median_commit_latency: mean_commit_latency,
+        timeout_rate: 0.5,
+        fast_path_rate: 0.0,
+        transaction_throughput: 0.0,
+        mean_transaction_latency: None,
+        mean_targeted_latency: None,
+    }
+}
+
+#[test]
+fn test_monte_carlo_summary() {
+    let live = MonteCarloSummary::new(&[summary(2.0, Some(10.0)), summary(4.0, Some(20.0))]);
+    assert_eq!(live.runs, 2);
+    assert_eq!(live.live_runs, 2);
+    assert!(!live.is_liveness_seed_dependent());
+    assert!((live.mean_commit_latency.unwrap().mean - 15.0).abs() < 1e-9);
+    assert!((live.commit_throughput.mean - 0.03).abs() < 1e-9);
+    // Latencies of 10 and 20 and throughputs of 0.02 and 0.04 vary by about 47%.
+    assert_eq!(
+        live.seed_sensitive_metrics(),
+        vec![
+            "mean_commit_latency",
+            "median_commit_latency",
+            "commit_throughput"
+        ]
+    );
+
+    let mixed = MonteCarloSummary::new(&[summary(0.0, None), summary(4.0, Some(20.0))]);
+    assert_eq!(mixed.live_runs, 1);
+    assert!(mixed.is_liveness_seed_dependent());
+    assert_eq!(mixed.median_commit_latency.unwrap().count, 1);
+
+    let dead = MonteCarloSummary::new(&[summary(0.0, None)]);
+    assert!(!dead.is_liveness_seed_dependent());
+    assert_eq!(dead.mean_commit_latency, None);
+}
+
+#[test]
+fn test_distribution() {
+    assert_eq!(Distribution::new(&[]), None);
+    let values: Vec<_> = (1..=100).rev().map(|x| x as f64).collect();
+    let distribution = Distribution::new(&values).unwrap();
+    assert_eq!(distribution.count, 100);
+    assert_eq!(distribution.min, 1.0);
+    assert_eq!(distribution.max, 100.0);
+    assert!((distribution.mean - 50.5).abs() < 1e-9);
+    assert_eq!(distribution.p50, 50.0);
+    assert_eq!(distribution.p95, 95.0);
+    assert_eq!(distribution.p99, 99.0);
+    let single = Distribution::new(&[7.0]).unwrap();
+    assert_eq!((single.p50, single.p99), (7.0, 7.0));
+}
+
+#[test]
+fn test_commit_metrics() {
+    let mut statistics = Statistics::new(2);
+    let offset = |block: &CommittedBlock| GlobalTime(block.proposal_time.0);
+    let blocks = vec![block(1, 0), block(3, 10), block(4, 20)];
+    statistics.record_commits(Author(0), &blocks, GlobalTime(25), offset);
+    statistics.record_commits(Author(1), &blocks[..1], GlobalTime(5), offset);
+    assert_eq!(statistics.rounds_per_commit(), &[1, 2, 1, 1]);
+    assert_eq!(
+        statistics.commit_latency_histogram(Duration::from_millis(10)),
+        vec![2, 1, 1]
+    );
+    let rounds = statistics.rounds_per_commit_distribution().unwrap();
+    assert_eq!((rounds.p50, rounds.p99), (1.0, 2.0));
+    // One commit in [0, 10) and three in [20, 30), with two nodes.
+    assert_eq!(
+        statistics.commit_throughput_series(Duration::from_millis(10), GlobalTime(25)),
+        vec![0.05, 0.0, 0.15]
+    );
+}
@@ -0,0 +1,38 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_default_never_stops() {
+    let termination = TerminationCondition::default();
+    assert!(!termination.is_past_max_clock(GlobalTime(std::i64::MAX)));
+    assert!(!termination.has_enough_commits(1000));
+    assert!(!termination.has_stalled(Round(1000), Round(0)));
+    assert!(!termination.has_missed_progress_bound(GlobalTime(1000), GlobalTime(0)));
+}
+
+#[test]
+fn test_conditions() {
+    let termination = TerminationCondition {
+        max_committed_blocks: Some(5),
+        max_rounds_without_progress: Some(3),
+        ..TerminationCondition::at_time(GlobalTime(100))
+    };
+    assert!(!termination.is_past_max_clock(GlobalTime(100)));
+    assert!(termination.is_past_max_clock(GlobalTime(101)));
+    assert!(!termination.has_enough_commits(4));
+    assert!(termination.has_enough_commits(5));
+    assert!(!termination.has_stalled(Round(6), Round(4)));
+    assert!(termination.has_stalled(Round(7), Round(4)));
+}
+
+#[test]
+fn test_progress_bound() {
+    let termination = TerminationCondition {
+        max_time_without_commit: Some(Duration::from_millis(50)),
+        ..TerminationCondition::default()
+    };
+    assert!(!termination.has_missed_progress_bound(GlobalTime(60), GlobalTime(10)));
+    assert!(termination.has_missed_progress_bound(GlobalTime(61), GlobalTime(10)));
+}
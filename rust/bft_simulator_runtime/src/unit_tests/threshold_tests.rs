@@ -0,0 +1,31 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_threshold_signature() {
+    let configuration = EpochConfiguration::new((0..4).map(|i| (Author(i), 1)).collect());
+    let keys = ThresholdKeys::deal(1, &configuration);
+    assert_eq!(keys.threshold(), 3);
+    let shares: Vec<_> = (0..4)
+        .map(|i| (Author(i), keys.sign_share(35, Author(i)).unwrap()))
+        .collect();
+    assert!(keys.check_share(35, Author(0), shares[0].1).is_ok());
+    assert!(keys.check_share(35, Author(1), shares[0].1).is_err());
+    assert!(keys.sign_share(35, Author(4)).is_none());
+    // Any quorum of shares gives the same signature.
+    let signature = keys.combine(35, &shares[..3]).unwrap();
+    assert_eq!(keys.combine(35, &shares[1..]).unwrap(), signature);
+    assert!(keys.check(35, signature).is_ok());
+    assert!(keys.check(32, signature).is_err());
+    // Shares of too few validators, or repeated shares, do not combine.
+    assert!(keys.combine(35, &shares[..2]).is_err());
+    assert!(keys
+        .combine(35, &[shares[0], shares[0], shares[1]])
+        .is_err());
+    // Keys of other epochs differ.
+    assert!(ThresholdKeys::deal(2, &configuration)
+        .check(35, signature)
+        .is_err());
+}
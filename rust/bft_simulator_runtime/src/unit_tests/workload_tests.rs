@@ -0,0 +1,83 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_constant_rate() {
+    let mut generator = WorkloadGenerator::new(ArrivalProcess::ConstantRate { rate: 0.5 }, 0);
+    assert_eq!(
+        generator.arrivals_until(NodeTime(7)),
+        vec![NodeTime(2), NodeTime(4), NodeTime(6)]
+    );
+    // Arrivals are returned once.
+    assert_eq!(generator.arrivals_until(NodeTime(8)), vec![NodeTime(8)]);
+    assert!(generator.arrivals_until(NodeTime(8)).is_empty());
+}
+
+#[test]
+fn test_poisson() {
+    let process = ArrivalProcess::Poisson { rate: 0.1 };
+    let arrivals = WorkloadGenerator::new(process.clone(), 3).arrivals_until(NodeTime(100_000));
+    // About 10000 arrivals are expected.
+    assert!(arrivals.len() > 9000 && arrivals.len() < 11000);
+    assert!(arrivals.windows(2).all(|pair| pair[0] <= pair[1]));
+    let same_seed = WorkloadGenerator::new(process, 3).arrivals_until(NodeTime(100_000));
+    assert_eq!(arrivals, same_seed);
+}
+
+#[test]
+fn test_bursty() {
+    let process = ArrivalProcess::Bursty {
+        size: 3,
+        interval: Duration::from_millis(10),
+    };
+    let arrivals = WorkloadGenerator::new(process, 0).arrivals_until(NodeTime(25));
+    assert_eq!(arrivals.len(), 6);
+    assert_eq!(arrivals[2], NodeTime(10));
+    assert_eq!(arrivals[3], NodeTime(20));
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        "poisson:0.5".parse::<ArrivalProcess>(),
+        Ok(ArrivalProcess::Poisson { rate: 0.5 })
+    );
+    assert_eq!(
+        "bursty:20:100".parse::<ArrivalProcess>(),
+        Ok(ArrivalProcess::Bursty {
+            size: 20,
+            interval: Duration::from_millis(100)
+        })
+    );
+    assert!("constant:0".parse::<ArrivalProcess>().is_err());
+    assert!("poisson:inf".parse::<ArrivalProcess>().is_err());
+    assert!("constant:NaN".parse::<ArrivalProcess>().is_err());
+    assert!("bursty:0:100".parse::<ArrivalProcess>().is_err());
+    assert!("constant".parse::<ArrivalProcess>().is_err());
+    assert!("uniform:1".parse::<ArrivalProcess>().is_err());
+}
+
+#[test]
+fn test_with_rate() {
+    let poisson = ArrivalProcess::Poisson { rate: 0.5 }.with_rate(2.0);
+    assert_eq!(poisson, ArrivalProcess::Poisson { rate: 2.0 });
+    let bursty = ArrivalProcess::Bursty {
+        size: 3,
+        interval: Duration::from_millis(10),
+    };
+    assert!((bursty.rate() - 0.3).abs() < 1e-9);
+    // Bursts keep their interval.
+    assert_eq!(
+        bursty.with_rate(1.0),
+        ArrivalProcess::Bursty {
+            size: 10,
+            interval: Duration::from_millis(10)
+        }
+    );
+    match bursty.with_rate(0.01) {
+        ArrivalProcess::Bursty { size, .. } => assert_eq!(size, 1),
+        process => panic!("Unexpected arrivals: {:?}", process),
+    }
+}
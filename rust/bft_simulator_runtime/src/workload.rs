@@ -0,0 +1,163 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Arrival times of the transactions submitted to a node by its clients.
+
+use crate::base_types::{Duration, NodeTime};
+use rand::{
+    distributions::{Distribution, Exp},
+    rngs::StdRng,
+    SeedableRng,
+};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[cfg(test)]
+#[path = "unit_tests/workload_tests.rs"]
+mod workload_tests;
+
+/// Arrivals of transactions at a node. Rates are in transactions per unit of time.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum ArrivalProcess {
+    ConstantRate {
+        rate: f64,
+    },
+    /// Exponential times between arrivals.
+    Poisson {
+        rate: f64,
+    },
+    /// `size` transactions at once, every `interval`.
+    Bursty {
+        size: usize,
+        interval: Duration,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct WorkloadGenerator {
+    process: ArrivalProcess,
+    rng: StdRng,
+    /// Time of the next arrival, not returned yet.
+    next_arrival: f64,
+}
+
+impl ArrivalProcess {
+    /// Check that rates are positive and finite, and that bursts are not empty and have positive
+    /// intervals.
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            ArrivalProcess::ConstantRate { rate } | ArrivalProcess::Poisson { rate } => {
+                if !(*rate > 0.0 && rate.is_finite()) {
+                    return Err(format!(
+                        "Arrival rates must be positive and finite, not {}",
+                        rate
+                    ));
+                }
+            }
+            ArrivalProcess::Bursty { size, interval } => {
+                if *size == 0 {
+                    return Err("Bursts must carry at least one transaction".to_string());
+                }
+                if !interval.is_positive() {
+                    return Err(format!(
+                        "Burst intervals must be positive, not {}",
+                        interval
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Mean number of arrivals per unit of time.
+    pub fn rate(&self) -> f64 {
+        match self {
+            ArrivalProcess::ConstantRate { rate } | ArrivalProcess::Poisson { rate } => *rate,
+            ArrivalProcess::Bursty { size, interval } => *size as f64 / interval.as_millis_f64(),
+        }
+    }
+
+    /// Arrivals of the same kind at the mean rate `rate`. Bursts keep their interval and change
+    /// size, with at least one transaction per burst.
+    pub fn with_rate(&self, rate: f64) -> ArrivalProcess {
+        match self {
+            ArrivalProcess::ConstantRate { .. } => ArrivalProcess::ConstantRate { rate },
+            ArrivalProcess::Poisson { .. } => ArrivalProcess::Poisson { rate },
+            ArrivalProcess::Bursty { interval, .. } => ArrivalProcess::Bursty {
+                size: (rate * interval.as_millis_f64()).round().max(1.0) as usize,
+                interval: *interval,
+            },
+        }
+    }
+}
+
+/// Parse `constant:RATE`, `poisson:RATE`, or `bursty:SIZE:INTERVAL`.
+impl FromStr for ArrivalProcess {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split(':').collect();
+        let number = |i: usize| -> Result<f64, String> {
+            fields
+                .get(i)
+                .and_then(|x| x.parse::<f64>().ok())
+                .ok_or_else(|| format!("Invalid arrival process: {}", s))
+        };
+        let process = match (fields[0], fields.len()) {
+            ("constant", 2) => ArrivalProcess::ConstantRate { rate: number(1)? },
+            ("poisson", 2) => ArrivalProcess::Poisson { rate: number(1)? },
+            ("bursty", 3) => ArrivalProcess::Bursty {
+                size: number(1)? as usize,
+                interval: Duration::from_millis_f64(number(2)?),
+            },
+            _ => return Err(format!("Invalid arrival process: {}", s)),
+        };
+        process.check()?;
+        Ok(process)
+    }
+}
+
+impl WorkloadGenerator {
+    pub fn new(process: ArrivalProcess, seed: u64) -> Self {
+        if let Err(message) = process.check() {
+            panic!("{}", message);
+        }
+        let mut generator = WorkloadGenerator {
+            process,
+            rng: StdRng::seed_from_u64(seed),
+            next_arrival: 0.0,
+        };
+        generator.next_arrival = generator.time_to_next_arrival();
+        generator
+    }
+
+    fn time_to_next_arrival(&mut self) -> f64 {
+        match &self.process {
+            ArrivalProcess::ConstantRate { rate } => 1.0 / rate,
+            ArrivalProcess::Poisson { rate } => Exp::new(*rate).sample(&mut self.rng),
+            ArrivalProcess::Bursty { interval, .. } => interval.as_millis_f64(),
+        }
+    }
+
+    /// Arrivals until `time` included that were not returned yet, in increasing order.
+    pub fn arrivals_until(&mut self, time: NodeTime) -> Vec<NodeTime> {
+        let mut arrivals = Vec::new();
+        while self.next_arrival <= time.0 as f64 {
+            let arrival = NodeTime(self.next_arrival.ceil() as i64);
+            match &self.process {
+                ArrivalProcess::Bursty { size, .. } => {
+                    arrivals.extend(std::iter::repeat(arrival).take(*size))
+                }
+                _ => arrivals.push(arrival),
+            }
+            self.next_arrival += self.time_to_next_arrival();
+        }
+        arrivals
+    }
+
+    /// Time of the next arrival that was not returned yet.
+    pub fn next_arrival(&self) -> NodeTime {
+        NodeTime(self.next_arrival.ceil() as i64)
+    }
+}
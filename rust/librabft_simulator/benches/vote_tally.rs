@@ -0,0 +1,79 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cost of collecting the votes of a round at 1000 validators. Run with `cargo bench`.
+
+#![feature(test)]
+
+extern crate bft_simulator_runtime;
+extern crate librabft_simulator;
+extern crate test;
+
+use bft_simulator_runtime::base_types::*;
+use librabft_simulator::*;
+use std::collections::BTreeSet;
+use test::Bencher;
+
+const NODES: usize = 1000;
+
+/// A store holding the proposal of the first round, the votes of a quorum for it, and the
+/// contexts of all the validators.
+fn make_round() -> (RecordStoreState, Vec<Record>, Author, Vec<SimulatedContext>) {
+    let mut contexts: Vec<_> = (0..NODES)
+        .map(|i| SimulatedContext::new(Author(i), NODES, 20))
+        .collect();
+    let state = contexts[0].last_committed_state();
+    let mut store = RecordStoreState::new(
+        QuorumCertificateHash(0),
+        state.clone(),
+        EpochId(0),
+        contexts[0].configuration(&state),
+        CommitRule::ThreeChain,
+    );
+    let leader = PacemakerState::leader(&store, Round(1));
+    store.propose_block(
+        leader,
+        QuorumCertificateHash(0),
+        NodeTime(1),
+        &mut contexts[leader.0],
+    );
+    let block_hash = match store.unknown_record_refs(BTreeSet::new()).pop() {
+        Some(RecordRef::Block(hash, _)) => hash,
+        _ => panic!("The leader should have proposed a block"),
+    };
+    let threshold = contexts[0].configuration(&state).quorum_threshold();
+    let mut voting_store = store.clone();
+    let votes = (0..threshold)
+        .map(|i| {
+            assert!(voting_store.create_vote(Author(i), block_hash, &mut contexts[i]));
+            Record::Vote(voting_store.current_vote(Author(i)).unwrap())
+        })
+        .collect();
+    (store, votes, leader, contexts)
+}
+
+/// The leader receives the votes one by one and checks for a QC after each of them.
+#[bench]
+fn bench_vote_insertion(b: &mut Bencher) {
+    let (store, votes, leader, mut contexts) = make_round();
+    b.iter(|| {
+        let mut store = store.clone();
+        let context = &mut contexts[leader.0];
+        let mut formed = false;
+        for vote in &votes {
+            store.insert_network_record(vote.clone(), context).unwrap();
+            formed = store.check_for_new_quorum_certificate(leader, context);
+        }
+        assert!(formed);
+    });
+}
+
+/// The leader checks for a QC while it misses a single vote.
+#[bench]
+fn bench_quorum_check(b: &mut Bencher) {
+    let (mut store, mut votes, leader, mut contexts) = make_round();
+    votes.pop();
+    let context = &mut contexts[leader.0];
+    store.insert_network_records(votes, context).unwrap();
+    b.iter(|| assert!(!store.check_for_new_quorum_certificate(leader, context)));
+}
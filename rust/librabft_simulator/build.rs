@@ -0,0 +1,14 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(feature = "grpc")]
+extern crate tower_grpc_build;
+
+fn main() {
+    // Generate the messages and the service of the control API.
+    #[cfg(feature = "grpc")]
+    tower_grpc_build::Config::new()
+        .enable_server(true)
+        .build(&["proto/control.proto"], &["proto"])
+        .unwrap_or_else(|error| panic!("Failed to compile the control API: {}", error));
+}
@@ -1,27 +1,380 @@
 // Copyright (c) Calibra Research
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::Author;
+use crate::{Author, Duration, Round, Signature};
+use bft_simulator_runtime::mempool::Transaction;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 
 #[cfg(test)]
 #[path = "unit_tests/base_type_tests.rs"]
 mod base_type_tests;
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
 pub struct EpochId(pub usize);
 
 // The following types are simplified for simulation purposes.
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug)]
+/// Digest of a block, as computed by the current `hashing::RecordHasher`.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
 pub struct BlockHash(pub u64);
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug)]
+/// Digest of a QC, as computed by the current `hashing::RecordHasher`.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
 pub struct QuorumCertificateHash(pub u64);
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug, Deserialize, Serialize)]
 pub struct State(pub u64);
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug, Deserialize, Serialize)]
 pub struct Command {
     pub proposer: Author,
     pub index: usize,
+    /// Batch of transactions pulled from the mempool of the proposer.
+    pub transactions: Vec<Transaction>,
+    /// Governance change of the protocol parameters, if any, with its index in the schedule of
+    /// changes, so that identical changes are told apart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameter_change: Option<(usize, ParameterChange)>,
+}
+
+/// Change of protocol parameters committed on chain. It takes effect at the start of the next
+/// epoch. Absent values are left unchanged.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParameterChange {
+    pub delta: Option<Duration>,
+    pub gamma: Option<f64>,
+    pub lambda: Option<f64>,
+    /// Maximal size of the transactions of a block, in bytes.
+    pub max_block_payload: Option<usize>,
+}
+
+/// Voting constraints of a node, persisted so that they survive restarts.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct SafetyData {
+    pub epoch_id: EpochId,
+    /// Highest round voted so far.
+    pub latest_voted_round: Round,
+    /// Current locked round.
+    pub locked_round: Round,
+}
+
+/// Number of QCs at consecutive rounds needed to commit the first certified block.
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitRule {
+    /// The LibraBFT rule.
+    ThreeChain,
+    /// As in HotStuff-2 and Jolteon: nodes lock on the QC of the block that they vote for.
+    TwoChain,
+}
+
+impl Default for CommitRule {
+    fn default() -> Self {
+        CommitRule::ThreeChain
+    }
+}
+
+impl CommitRule {
+    pub fn chain_length(self) -> usize {
+        match self {
+            CommitRule::ThreeChain => 3,
+            CommitRule::TwoChain => 2,
+        }
+    }
+
+    /// Rounds between a committed block and the last QC of its commit rule.
+    pub fn commit_certificate_distance(self) -> usize {
+        self.chain_length() - 1
+    }
+}
+
+impl fmt::Display for CommitRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommitRule::ThreeChain => write!(f, "three-chain"),
+            CommitRule::TwoChain => write!(f, "two-chain"),
+        }
+    }
+}
+
+impl FromStr for CommitRule {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "three-chain" => Ok(CommitRule::ThreeChain),
+            "two-chain" => Ok(CommitRule::TwoChain),
+            _ => bail!("Unknown commit rule: {}", s),
+        }
+    }
+}
+
+/// Recipients of the votes, and thus authors of the QCs.
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VoteDelivery {
+    /// Votes are sent to the proposer of the block, who assembles and broadcasts the QC.
+    Proposer,
+    /// Votes are sent to the leader of the next round, who assembles the QC and broadcasts it
+    /// with its proposal, as in chained HotStuff.
+    NextLeader,
+    /// Votes are sent to every node. The proposer still assembles the QC.
+    Broadcast,
+}
+
+impl Default for VoteDelivery {
+    fn default() -> Self {
+        VoteDelivery::Proposer
+    }
+}
+
+impl fmt::Display for VoteDelivery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VoteDelivery::Proposer => write!(f, "proposer"),
+            VoteDelivery::NextLeader => write!(f, "next-leader"),
+            VoteDelivery::Broadcast => write!(f, "broadcast"),
+        }
+    }
+}
+
+impl FromStr for VoteDelivery {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "proposer" => Ok(VoteDelivery::Proposer),
+            "next-leader" => Ok(VoteDelivery::NextLeader),
+            "broadcast" => Ok(VoteDelivery::Broadcast),
+            _ => bail!("Unknown vote delivery: {}", s),
+        }
+    }
+}
+
+/// What a leader does when it has no transactions to propose.
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum EmptyBlockPolicy {
+    /// Propose an empty block right away.
+    Propose,
+    /// Wait up to `max_delay` for a transaction, then propose an empty block.
+    Delay { max_delay: Duration },
+    /// Only propose empty blocks that extend certified blocks carrying uncommitted
+    /// transactions, so that these get committed. Otherwise, wait for a transaction until the
+    /// round ends by a timeout.
+    NoOp,
+}
+
+impl Default for EmptyBlockPolicy {
+    fn default() -> Self {
+        EmptyBlockPolicy::Propose
+    }
+}
+
+impl FromStr for EmptyBlockPolicy {
+    type Err = failure::Error;
+
+    /// Parse `propose`, `delay:MAX_DELAY`, or `no-op`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split(':').collect();
+        match fields.as_slice() {
+            ["propose"] => Ok(EmptyBlockPolicy::Propose),
+            ["delay", max_delay] => Ok(EmptyBlockPolicy::Delay {
+                max_delay: max_delay
+                    .parse::<Duration>()
+                    .map_err(|_| format_err!("Invalid delay: {}", max_delay))?,
+            }),
+            ["no-op"] => Ok(EmptyBlockPolicy::NoOp),
+            _ => bail!("Unknown empty-block policy: {}", s),
+        }
+    }
+}
+
+/// Cryptographic operations whose time is charged to the nodes that perform them.
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
+pub enum CryptoOperation {
+    Sign,
+    Verify,
+    /// Combining one signature into an aggregate, or one public key to verify an aggregate.
+    Aggregate,
+    /// Computing or checking the MAC of a message on a point-to-point channel.
+    Mac,
+}
+
+/// Simulated time spent per cryptographic operation.
+#[derive(Eq, PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CryptoCosts {
+    pub sign: Duration,
+    pub verify: Duration,
+    pub aggregate: Duration,
+    pub mac: Duration,
+}
+
+/// Authentication of the network messages, on top of the signatures of their records.
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageAuthentication {
+    /// Messages are not authenticated, as if channels were secure for free.
+    None,
+    /// Point-to-point channels authenticate each copy of a message with a MAC.
+    Mac,
+    /// Senders sign each message once, for all its recipients.
+    Signature,
+}
+
+impl CryptoCosts {
+    pub fn check(&self) -> Result<(), failure::Error> {
+        ensure!(
+            !(self.sign.is_negative()
+                || self.verify.is_negative()
+                || self.aggregate.is_negative()
+                || self.mac.is_negative()),
+            "Cryptographic costs cannot be negative"
+        );
+        Ok(())
+    }
+
+    pub fn cost(&self, operation: CryptoOperation) -> Duration {
+        match operation {
+            CryptoOperation::Sign => self.sign,
+            CryptoOperation::Verify => self.verify,
+            CryptoOperation::Aggregate => self.aggregate,
+            CryptoOperation::Mac => self.mac,
+        }
+    }
+}
+
+/// Parse `SIGN:VERIFY:AGGREGATE` or `SIGN:VERIFY:AGGREGATE:MAC`.
+impl FromStr for CryptoCosts {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split(':').collect();
+        ensure!(
+            fields.len() == 3 || fields.len() == 4,
+            "Invalid cryptographic costs: {}",
+            s
+        );
+        let costs = CryptoCosts {
+            sign: fields[0].parse()?,
+            verify: fields[1].parse()?,
+            aggregate: fields[2].parse()?,
+            mac: match fields.get(3) {
+                Some(mac) => mac.parse()?,
+                None => Duration::ZERO,
+            },
+        };
+        costs.check()?;
+        Ok(costs)
+    }
+}
+
+impl Default for MessageAuthentication {
+    fn default() -> Self {
+        MessageAuthentication::None
+    }
+}
+
+impl MessageAuthentication {
+    /// Size of the authentication data of each copy of a message, in bytes. MACs count as
+    /// HMAC-SHA256 tags.
+    pub fn tag_bytes(self) -> usize {
+        match self {
+            MessageAuthentication::None => 0,
+            MessageAuthentication::Mac => 32,
+            MessageAuthentication::Signature => Signature::num_bytes(),
+        }
+    }
+}
+
+impl fmt::Display for MessageAuthentication {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MessageAuthentication::None => write!(f, "none"),
+            MessageAuthentication::Mac => write!(f, "mac"),
+            MessageAuthentication::Signature => write!(f, "signature"),
+        }
+    }
+}
+
+impl FromStr for MessageAuthentication {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(MessageAuthentication::None),
+            "mac" => Ok(MessageAuthentication::Mac),
+            "signature" => Ok(MessageAuthentication::Signature),
+            _ => bail!("Unknown message authentication: {}", s),
+        }
+    }
+}
+
+impl Command {
+    /// Size of the transactions, in bytes.
+    pub fn payload_bytes(&self) -> usize {
+        self.transactions.iter().map(|x| x.size).sum()
+    }
+}
+
+impl ParameterChange {
+    /// The parameters changed by `self`, then by `later`.
+    pub fn then(self, later: &ParameterChange) -> ParameterChange {
+        ParameterChange {
+            delta: later.delta.or(self.delta),
+            gamma: later.gamma.or(self.gamma),
+            lambda: later.lambda.or(self.lambda),
+            max_block_payload: later.max_block_payload.or(self.max_block_payload),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.delta.is_none()
+            && self.gamma.is_none()
+            && self.lambda.is_none()
+            && self.max_block_payload.is_none()
+    }
+
+    /// Comparable representation, with floats as bits so that commands can be hashed.
+    fn key(&self) -> (Option<Duration>, Option<u64>, Option<u64>, Option<usize>) {
+        (
+            self.delta,
+            self.gamma.map(f64::to_bits),
+            self.lambda.map(f64::to_bits),
+            self.max_block_payload,
+        )
+    }
+}
+
+impl PartialEq for ParameterChange {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for ParameterChange {}
+
+impl PartialOrd for ParameterChange {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ParameterChange {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for ParameterChange {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
 }
 
 impl EpochId {
@@ -33,7 +386,7 @@ impl EpochId {
         if self.0 == 0 {
             None
         } else {
-            Some(EpochId(self.0))
+            Some(EpochId(self.0 - 1))
         }
     }
 }
@@ -0,0 +1,715 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Command-line entry point to run scenario files, sweep parameters, and replay traces.
+
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate log;
+extern crate bft_simulator_runtime;
+extern crate clap;
+extern crate librabft_simulator;
+
+#[cfg(feature = "tokio")]
+use bft_simulator_runtime::base_types::Author;
+use bft_simulator_runtime::{base_types::Result, termination::StopReason};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+#[cfg(feature = "grpc")]
+use librabft_simulator::control;
+#[cfg(feature = "tokio")]
+use librabft_simulator::deployment;
+#[cfg(feature = "websocket")]
+use librabft_simulator::streaming;
+use librabft_simulator::{
+    bisection::{self, BisectionSettings},
+    block_tree, censorship, comparison, decisions, diem, instrumentation,
+    load_curve::{self, LoadLevels},
+    make_simulator,
+    model_checker::{self, ModelCheckingOptions},
+    monte_carlo,
+    scenario::Scenario,
+    shrink,
+    sweep::{self, SweepGrid, SweepResult},
+    timeline,
+    tla::{self, TlaTraceRecorder},
+    trace::{Trace, TraceEvent},
+    SimulationParameters,
+};
+
+fn main() -> Result<()> {
+    instrumentation::init()?;
+    let app = App::new("librabft-sim")
+        .about("Simulations of the LibraBFT consensus protocol")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Run a scenario and print a summary of the run")
+                .arg(scenario_arg())
+                .arg(output_arg())
+                .arg(
+                    Arg::with_name("trace")
+                        .long("trace")
+                        .help("Record the processed events in the given trace file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("metrics")
+                        .long("metrics")
+                        .help("Export CSV and JSON metrics to the given directory")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("decisions")
+                        .long("decisions")
+                        .help("Write the decision log of each node to the given directory")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("timeline")
+                        .long("timeline")
+                        .help("Write the rounds of each node to the given CSV or JSON file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("block_trees")
+                        .long("block_trees")
+                        .help("Write the block tree of each node to the given directory, as DOT")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("diem_traces")
+                        .long("diem_traces")
+                        .help("Write the records of each node to the given directory, as Diem JSON")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("tla_trace")
+                        .long("tla_trace")
+                        .help("Write the states of the run to the given .tla or .ndjson file")
+                        .takes_value(true),
+                )
+                .arg(html_arg())
+                .arg(
+                    Arg::with_name("seeds")
+                        .long("seeds")
+                        .help("Run with this many consecutive seeds and aggregate the metrics")
+                        .default_value("1")
+                        .conflicts_with_all(&[
+                            "trace",
+                            "metrics",
+                            "decisions",
+                            "timeline",
+                            "block_trees",
+                            "diem_traces",
+                            "tla_trace",
+                            "html",
+                        ]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sweep")
+                .about("Run a scenario once per combination of the given parameter values")
+                .arg(scenario_arg())
+                .arg(output_arg())
+                .args(&sweep_args())
+                .arg(html_arg())
+                .arg(
+                    Arg::with_name("seeds")
+                        .long("seeds")
+                        .help("Run each configuration with this many consecutive seeds")
+                        .default_value("1")
+                        .conflicts_with("html"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("load")
+                .about("Run a scenario with increasing transaction rates until it saturates")
+                .arg(scenario_arg())
+                .arg(output_arg())
+                .args(&sweep_args())
+                .arg(
+                    Arg::with_name("start_rate")
+                        .long("start_rate")
+                        .help("Transactions per unit of time and per node at the first level")
+                        .default_value("0.01"),
+                )
+                .arg(
+                    Arg::with_name("step")
+                        .long("step")
+                        .help("Increase of the rate from one level to the next")
+                        .default_value("0.01"),
+                )
+                .arg(
+                    Arg::with_name("max_levels")
+                        .long("max_levels")
+                        .help("Maximal number of levels per configuration")
+                        .default_value("20"),
+                )
+                .arg(
+                    Arg::with_name("saturation_ratio")
+                        .long("saturation_ratio")
+                        .help("Stop below this fraction of the submitted rate")
+                        .default_value("0.9"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("censorship")
+                .about("Measure the delay of targeted transactions under censoring Byzantine nodes")
+                .arg(scenario_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("bisect")
+                .about("Bisect a parameter of a scenario to find where liveness is lost")
+                .arg(scenario_arg())
+                .arg(output_arg())
+                .arg(
+                    Arg::with_name("parameter")
+                        .long("parameter")
+                        .help("One of delta, gamma, lambda, loss_rate, mean")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("low")
+                        .long("low")
+                        .help("Lower end of the search")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("high")
+                        .long("high")
+                        .help("Upper end of the search")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seeds")
+                        .long("seeds")
+                        .help("Run each probe with this many consecutive seeds")
+                        .default_value("5"),
+                )
+                .arg(
+                    Arg::with_name("tolerance")
+                        .long("tolerance")
+                        .help("Stop once the threshold is known within this width")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("max_probes")
+                        .long("max_probes")
+                        .help("Maximal number of probes, including both ends")
+                        .default_value("20"),
+                )
+                .arg(
+                    Arg::with_name("max_time_without_commit")
+                        .long("max_time_without_commit")
+                        .help("Liveness bound, in place of the one of the scenario")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Run two scenarios with the same seeds and compare their metrics by pairs")
+                .arg(
+                    Arg::with_name("A")
+                        .help("TOML scenario file of the baseline")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("B")
+                        .help("TOML scenario file compared to the baseline")
+                        .required(true),
+                )
+                .arg(output_arg())
+                .arg(
+                    Arg::with_name("seeds")
+                        .long("seeds")
+                        .help("Run both scenarios with this many consecutive seeds")
+                        .default_value("10"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Replay a trace and check that the run is reproduced")
+                .arg(
+                    Arg::with_name("TRACE")
+                        .help("Trace file recorded by `run --trace`")
+                        .required(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Explore the delivery orders of a tiny scenario and check safety")
+                .arg(scenario_arg())
+                .arg(
+                    Arg::with_name("width")
+                        .long("width")
+                        .help("Number of earliest pending events to choose from at each step")
+                        .default_value("2"),
+                )
+                .arg(
+                    Arg::with_name("max_depth")
+                        .long("max_depth")
+                        .help("Maximal number of steps per run")
+                        .default_value("200"),
+                )
+                .arg(
+                    Arg::with_name("max_runs")
+                        .long("max_runs")
+                        .help("Stop the exploration after this many runs")
+                        .default_value("1000"),
+                )
+                .arg(
+                    Arg::with_name("no_state_hashing")
+                        .long("no_state_hashing")
+                        .help("Do not stop runs on states that were already visited"),
+                )
+                .arg(
+                    Arg::with_name("coverage")
+                        .long("coverage")
+                        .help("Run random schedules guided by coverage instead of a full search"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("shrink")
+                .about("Minimize a scenario that ends with a violation")
+                .arg(scenario_arg())
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .help("Write the minimized scenario to the given file instead of stdout")
+                        .takes_value(true),
+                ),
+        );
+    #[cfg(feature = "tokio")]
+    let app = app.subcommand(
+        SubCommand::with_name("serve")
+            .about(
+                "Run one node of a scenario over TCP, with the other nodes at the given addresses",
+            )
+            .arg(scenario_arg())
+            .arg(
+                Arg::with_name("author")
+                    .long("author")
+                    .help("Index of the node to run")
+                    .required(true)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("addresses")
+                    .long("addresses")
+                    .help("Comma-separated addresses of all the nodes, in order")
+                    .required(true)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("duration")
+                    .long("duration")
+                    .help("Stop the node after this many seconds")
+                    .default_value("10"),
+            )
+            .arg(
+                Arg::with_name("metrics_address")
+                    .long("metrics_address")
+                    .help("Serve Prometheus metrics of the node on the given address")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("time_scale")
+                    .long("time_scale")
+                    .help("Units of local time per millisecond, e.g. to use simulated durations")
+                    .takes_value(true),
+            ),
+    );
+    #[cfg(feature = "grpc")]
+    let app = app.subcommand(
+        SubCommand::with_name("control")
+            .about("Serve a gRPC API to start, monitor, and inject faults into runs")
+            .arg(
+                Arg::with_name("address")
+                    .long("address")
+                    .help("Address to listen on")
+                    .default_value("127.0.0.1:50051"),
+            ),
+    );
+    #[cfg(feature = "websocket")]
+    let app = app.subcommand(
+        SubCommand::with_name("stream")
+            .about("Stream the events of a run to a WebSocket client, e.g. a web dashboard")
+            .arg(scenario_arg())
+            .arg(
+                Arg::with_name("address")
+                    .long("address")
+                    .help("Address to wait for the client on")
+                    .default_value("127.0.0.1:9090"),
+            )
+            .arg(
+                Arg::with_name("speed")
+                    .long("speed")
+                    .help("Units of simulated time per second, or 0 to stream without pauses")
+                    .default_value("100"),
+            ),
+    );
+    let matches = app.get_matches();
+
+    match matches.subcommand() {
+        ("run", Some(matches)) => run(matches),
+        ("sweep", Some(matches)) => run_sweep(matches),
+        ("compare", Some(matches)) => run_compare(matches),
+        ("load", Some(matches)) => run_load(matches),
+        ("censorship", Some(matches)) => run_censorship(matches),
+        ("bisect", Some(matches)) => run_bisect(matches),
+        ("replay", Some(matches)) => replay(matches),
+        ("check", Some(matches)) => run_check(matches),
+        ("shrink", Some(matches)) => run_shrink(matches),
+        #[cfg(feature = "tokio")]
+        ("serve", Some(matches)) => serve(matches),
+        #[cfg(feature = "grpc")]
+        ("control", Some(matches)) => serve_control(matches),
+        #[cfg(feature = "websocket")]
+        ("stream", Some(matches)) => stream(matches),
+        _ => unreachable!(),
+    }
+}
+
+fn scenario_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("SCENARIO")
+        .help("TOML scenario file")
+        .required(true)
+}
+
+fn output_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output")
+        .long("output")
+        .help("Write the CSV summary to the given file instead of stdout")
+        .takes_value(true)
+}
+
+fn html_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("html")
+        .long("html")
+        .help("Write a self-contained HTML report with charts and tables to the given file")
+        .takes_value(true)
+}
+
+fn sweep_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    ["delta", "gamma", "lambda", "nodes", "loss_rate"]
+        .iter()
+        .map(|name| {
+            Arg::with_name(*name)
+                .long(*name)
+                .help("Comma-separated values (the value of the scenario by default)")
+                .takes_value(true)
+        })
+        .chain(std::iter::once(
+            Arg::with_name("commit_rule")
+                .long("commit_rule")
+                .help("Comma-separated commit rules, e.g. three-chain,two-chain")
+                .takes_value(true),
+        ))
+        .collect()
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let mut parameters = scenario.parameters();
+    parameters.record_decisions = matches.is_present("decisions") || matches.is_present("timeline");
+    warn!("Using seed {}", parameters.seed);
+    let seeds = matches.value_of("seeds").unwrap().parse::<u64>()?;
+    if seeds > 1 {
+        let grid = SweepGrid::single(&parameters);
+        let results = monte_carlo::run_monte_carlo(&parameters, &grid, seeds);
+        return monte_carlo::write_results(&results, matches.value_of("output"));
+    }
+    let mut sim = make_simulator(&parameters);
+    if matches.is_present("trace") {
+        sim.record_events();
+    }
+    let tla_states = if matches.is_present("tla_trace") {
+        Some(TlaTraceRecorder::attach(&mut sim))
+    } else {
+        None
+    };
+    let stop_reason = sim.run(&parameters.termination(), None);
+    warn!("Simulation stopped: {:?}", stop_reason);
+    // Stalls explain themselves whatever the logging level.
+    for report in sim.stall_reports() {
+        eprintln!("Stall detected: {}", report);
+    }
+    if let Some(path) = matches.value_of("trace") {
+        let events = sim.event_log().iter().map(TraceEvent::from).collect();
+        Trace::new(&scenario, parameters.seed, events).save(path)?;
+    }
+    if let Some(directory) = matches.value_of("metrics") {
+        sim.metrics_report().export(directory)?;
+    }
+    if let Some(directory) = matches.value_of("decisions") {
+        decisions::write_decision_logs(&sim, directory)?;
+    }
+    if let Some(path) = matches.value_of("timeline") {
+        timeline::write_round_timeline(&sim, path)?;
+    }
+    if let Some(directory) = matches.value_of("block_trees") {
+        block_tree::write_block_trees(&sim, directory)?;
+    }
+    if let Some(directory) = matches.value_of("diem_traces") {
+        diem::write_diem_traces(&sim, directory)?;
+    }
+    if let (Some(path), Some(states)) = (matches.value_of("tla_trace"), tla_states) {
+        tla::write_trace(path, &states.borrow())?;
+    }
+    if let Some(path) = matches.value_of("html") {
+        let title = format!("Run of {}", matches.value_of("SCENARIO").unwrap());
+        sim.html_report(&title).write(path)?;
+    }
+    let summary = sim.summary();
+    sweep::write_results(
+        &[SweepResult {
+            parameters,
+            summary,
+        }],
+        matches.value_of("output"),
+    )?;
+    if let StopReason::LivenessViolation(message) = stop_reason {
+        bail!("Liveness violation: {}", message);
+    }
+    Ok(())
+}
+
+/// The grid of the swept values given on the command line, the scenario values otherwise.
+fn sweep_grid(matches: &ArgMatches, base: &SimulationParameters) -> SweepGrid {
+    let mut grid = SweepGrid::single(base);
+    if let Some(values) = matches.value_of("delta") {
+        grid.delta = sweep::parse_list(values);
+    }
+    if let Some(values) = matches.value_of("gamma") {
+        grid.gamma = sweep::parse_list(values);
+    }
+    if let Some(values) = matches.value_of("lambda") {
+        grid.lambda = sweep::parse_list(values);
+    }
+    if let Some(values) = matches.value_of("nodes") {
+        grid.nodes = sweep::parse_list(values);
+    }
+    if let Some(values) = matches.value_of("loss_rate") {
+        grid.loss_rate = sweep::parse_list(values);
+    }
+    if let Some(values) = matches.value_of("commit_rule") {
+        grid.commit_rule = sweep::parse_list(values);
+    }
+    grid
+}
+
+fn run_sweep(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let base = scenario.parameters();
+    warn!("Using seed {}", base.seed);
+    let grid = sweep_grid(matches, &base);
+    let seeds = matches.value_of("seeds").unwrap().parse::<u64>()?;
+    if seeds > 1 {
+        let results = monte_carlo::run_monte_carlo(&base, &grid, seeds);
+        monte_carlo::write_results(&results, matches.value_of("output"))
+    } else {
+        let results = sweep::run_sweep(&base, &grid);
+        if let Some(path) = matches.value_of("html") {
+            sweep::write_html_report(&results, path)?;
+        }
+        sweep::write_results(&results, matches.value_of("output"))
+    }
+}
+
+fn run_load(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let base = scenario.parameters();
+    warn!("Using seed {}", base.seed);
+    let grid = sweep_grid(matches, &base);
+    let levels = LoadLevels {
+        start_rate: matches.value_of("start_rate").unwrap().parse()?,
+        step: matches.value_of("step").unwrap().parse()?,
+        max_levels: matches.value_of("max_levels").unwrap().parse()?,
+        saturation_ratio: matches.value_of("saturation_ratio").unwrap().parse()?,
+    };
+    levels.check()?;
+    let curves = load_curve::run_load_curves(&base, &grid, &levels);
+    for curve in &curves {
+        match curve.saturation_rate() {
+            Some(rate) => warn!("Saturated at rate {} with {:?}", rate, curve.parameters),
+            None => warn!("Not saturated with {:?}", curve.parameters),
+        }
+    }
+    load_curve::write_results(&curves, matches.value_of("output"))
+}
+
+fn run_censorship(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let parameters = scenario.parameters();
+    warn!("Using seed {}", parameters.seed);
+    let results = censorship::run_censorship(&parameters)?;
+    for result in &results {
+        warn!(
+            "Slowdown of targeted transactions with {} Byzantine nodes: {:?}",
+            result.parameters.byzantine.len(),
+            result.slowdown()
+        );
+    }
+    censorship::write_results(&results, matches.value_of("output"))
+}
+
+fn run_bisect(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let mut parameters = scenario.parameters();
+    if let Some(bound) = matches.value_of("max_time_without_commit") {
+        parameters.max_time_without_commit = Some(bound.parse()?);
+    }
+    let settings = BisectionSettings {
+        parameter: matches.value_of("parameter").unwrap().parse()?,
+        low: matches.value_of("low").unwrap().parse()?,
+        high: matches.value_of("high").unwrap().parse()?,
+        seeds: matches.value_of("seeds").unwrap().parse()?,
+        tolerance: matches.value_of("tolerance").unwrap().parse()?,
+        max_probes: matches.value_of("max_probes").unwrap().parse()?,
+    };
+    let bisection = bisection::run_bisection(&parameters, &settings)?;
+    warn!(
+        "Liveness is lost with {} between {} and {}",
+        bisection.parameter, bisection.live_value, bisection.lost_value
+    );
+    bisection::write_results(&bisection, matches.value_of("output"))
+}
+
+fn run_compare(matches: &ArgMatches) -> Result<()> {
+    let a = Scenario::load(matches.value_of("A").unwrap())?.parameters();
+    let b = Scenario::load(matches.value_of("B").unwrap())?.parameters();
+    let seeds = matches.value_of("seeds").unwrap().parse::<u64>()?;
+    warn!("Using {} seeds from {}", seeds, a.seed);
+    let report = comparison::compare(&a, &b, seeds);
+    for metric in &report.metrics {
+        if let Some(comparison) = &metric.comparison {
+            warn!(
+                "{}: {} vs {}, significant: {}",
+                metric.metric,
+                comparison.mean_a,
+                comparison.mean_b,
+                comparison.is_significant()
+            );
+        }
+    }
+    comparison::write_report(&report, matches.value_of("output"))
+}
+
+fn replay(matches: &ArgMatches) -> Result<()> {
+    let trace = Trace::load(matches.value_of("TRACE").unwrap())?;
+    let summary = trace.replay()?;
+    warn!("Replayed {} events", trace.events.len());
+    sweep::write_results(
+        &[SweepResult {
+            parameters: trace.scenario.parameters(),
+            summary,
+        }],
+        matches.value_of("output"),
+    )
+}
+
+fn run_shrink(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let result = match shrink::shrink(&scenario) {
+        Some(result) => result,
+        None => bail!("The scenario does not fail"),
+    };
+    warn!(
+        "Minimized the scenario in {} runs: {:?}",
+        result.runs, result.stop_reason
+    );
+    let contents = result.scenario.to_toml()?;
+    match matches.value_of("output") {
+        Some(path) => std::fs::write(path, contents)?,
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+fn run_check(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let parameters = scenario.parameters();
+    let options = ModelCheckingOptions {
+        width: matches.value_of("width").unwrap().parse()?,
+        max_depth: matches.value_of("max_depth").unwrap().parse()?,
+        max_runs: matches.value_of("max_runs").unwrap().parse()?,
+        state_hashing: !matches.is_present("no_state_hashing"),
+    };
+    let report = if matches.is_present("coverage") {
+        model_checker::explore(&parameters, &options, parameters.seed)?
+    } else {
+        model_checker::check(&parameters, &options)?
+    };
+    warn!(
+        "Explored {} runs ({} pruned, {} states), complete: {}",
+        report.runs, report.pruned_runs, report.states, report.complete
+    );
+    if let Some((schedule, stop_reason)) = report.violation {
+        bail!(
+            "Violation with seed {} and choices {:?}: {:?}",
+            parameters.seed,
+            schedule,
+            stop_reason
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+fn serve(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let parameters = scenario.parameters();
+    let author = Author(matches.value_of("author").unwrap().parse()?);
+    let addresses = matches
+        .value_of("addresses")
+        .unwrap()
+        .split(',')
+        .map(|address| address.trim().parse())
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let duration = std::time::Duration::from_secs(matches.value_of("duration").unwrap().parse()?);
+    let metrics_address = match matches.value_of("metrics_address") {
+        Some(address) => Some(address.parse()?),
+        None => None,
+    };
+    let time_scale = match matches.value_of("time_scale") {
+        Some(scale) => Some(scale.parse()?),
+        None => None,
+    };
+    let blocks = deployment::run_deployed_node(
+        &parameters,
+        author,
+        addresses,
+        metrics_address,
+        time_scale,
+        duration,
+    )?;
+    warn!("Node {:?} committed {} blocks", author, blocks.len());
+    for block in blocks {
+        println!("{:?},{:?},{}", block.round, block.proposer, block.hash);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+fn serve_control(matches: &ArgMatches) -> Result<()> {
+    let address = matches.value_of("address").unwrap().parse()?;
+    warn!("Serving the control API on {}", address);
+    control::serve(&address)
+}
+
+#[cfg(feature = "websocket")]
+fn stream(matches: &ArgMatches) -> Result<()> {
+    let scenario = Scenario::load(matches.value_of("SCENARIO").unwrap())?;
+    let parameters = scenario.parameters();
+    let address = matches.value_of("address").unwrap().parse()?;
+    let speed = matches.value_of("speed").unwrap().parse()?;
+    warn!("Waiting for a WebSocket client on {}", address);
+    streaming::serve(parameters, &address, speed)
+}
@@ -0,0 +1,227 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bisection of the value of a parameter at which liveness is lost.
+//!
+//! Each probe runs the scenario with one value of the parameter and several seeds. The value is
+//! live if no run goes longer than `max_time_without_commit` without a commit and every run
+//! commits blocks. Starting from two values, exactly one of which is live, the interval between
+//! the highest-known live value and the lowest-known failing one is halved until it is narrower
+//! than the tolerance. Liveness is assumed to change only once between the two values.
+
+use super::*;
+use bft_simulator_runtime::termination::StopReason;
+use std::{fmt, io, str::FromStr};
+
+#[cfg(test)]
+#[path = "unit_tests/bisection_tests.rs"]
+mod bisection_tests;
+
+/// Parameters that can be bisected, with durations in milliseconds.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum BisectedParameter {
+    Delta,
+    Gamma,
+    Lambda,
+    LossRate,
+    /// Mean network delay.
+    Mean,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct BisectionSettings {
+    pub parameter: BisectedParameter,
+    /// Ends of the interval to search. Liveness must differ between them.
+    pub low: f64,
+    pub high: f64,
+    /// Runs per probe, with the seeds `seed .. seed + seeds` of the scenario.
+    pub seeds: u64,
+    /// Stop once the threshold is known within this width.
+    pub tolerance: f64,
+    /// Maximal number of probes, including both ends.
+    pub max_probes: usize,
+}
+
+/// Outcome of the runs with one value of the parameter.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Probe {
+    pub value: f64,
+    pub runs: u64,
+    pub live_runs: u64,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Bisection {
+    pub parameter: BisectedParameter,
+    /// Probes in the order they ran.
+    pub probes: Vec<Probe>,
+    /// Closest values on both sides of the threshold.
+    pub live_value: f64,
+    pub lost_value: f64,
+}
+
+impl BisectedParameter {
+    pub fn name(self) -> &'static str {
+        match self {
+            BisectedParameter::Delta => "delta",
+            BisectedParameter::Gamma => "gamma",
+            BisectedParameter::Lambda => "lambda",
+            BisectedParameter::LossRate => "loss_rate",
+            BisectedParameter::Mean => "mean",
+        }
+    }
+
+    /// A copy of `parameters` with the given value of the parameter.
+    pub fn apply(self, parameters: &SimulationParameters, value: f64) -> SimulationParameters {
+        let mut parameters = parameters.clone();
+        match self {
+            BisectedParameter::Delta => parameters.delta = Duration::from_millis_f64(value),
+            BisectedParameter::Gamma => parameters.gamma = value,
+            BisectedParameter::Lambda => parameters.lambda = value,
+            BisectedParameter::LossRate => parameters.loss_rate = value,
+            BisectedParameter::Mean => parameters.mean = value,
+        }
+        parameters
+    }
+}
+
+impl fmt::Display for BisectedParameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for BisectedParameter {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "delta" => Ok(BisectedParameter::Delta),
+            "gamma" => Ok(BisectedParameter::Gamma),
+            "lambda" => Ok(BisectedParameter::Lambda),
+            "loss_rate" => Ok(BisectedParameter::LossRate),
+            "mean" => Ok(BisectedParameter::Mean),
+            _ => bail!("Unknown bisected parameter: {}", s),
+        }
+    }
+}
+
+impl BisectionSettings {
+    pub fn check(&self) -> Result<()> {
+        ensure!(self.low < self.high, "Bisection needs low < high");
+        ensure!(
+            self.seeds > 0,
+            "Bisection needs at least one seed per probe"
+        );
+        ensure!(self.tolerance > 0.0, "Bisection needs a positive tolerance");
+        ensure!(self.max_probes >= 2, "Bisection needs at least two probes");
+        Ok(())
+    }
+}
+
+impl Probe {
+    pub fn is_live(&self) -> bool {
+        self.live_runs == self.runs
+    }
+}
+
+impl Bisection {
+    /// Middle of the last interval known to contain the threshold.
+    pub fn threshold(&self) -> f64 {
+        (self.live_value + self.lost_value) / 2.0
+    }
+}
+
+/// Whether a run commits blocks without missing the progress bound.
+fn is_live_run(parameters: &SimulationParameters) -> bool {
+    let mut sim = make_simulator(parameters);
+    match sim.run(&parameters.termination(), None) {
+        StopReason::LivenessViolation(_) | StopReason::NoProgress => false,
+        _ => sim.summary().committed_blocks > 0.0,
+    }
+}
+
+fn run_probe(base: &SimulationParameters, settings: &BisectionSettings, value: f64) -> Probe {
+    let parameters = settings.parameter.apply(base, value);
+    info!("Probing {} = {}", settings.parameter, value);
+    let live_runs = (0..settings.seeds)
+        .filter(|i| {
+            is_live_run(&SimulationParameters {
+                seed: base.seed.wrapping_add(*i),
+                ..parameters.clone()
+            })
+        })
+        .count() as u64;
+    Probe {
+        value,
+        runs: settings.seeds,
+        live_runs,
+    }
+}
+
+/// Bisect the parameter of `base` between the ends of the settings.
+pub fn run_bisection(
+    base: &SimulationParameters,
+    settings: &BisectionSettings,
+) -> Result<Bisection> {
+    settings.check()?;
+    ensure!(
+        base.max_time_without_commit.is_some(),
+        "Bisecting liveness needs a bound on the time without commits"
+    );
+    let low = run_probe(base, settings, settings.low);
+    let high = run_probe(base, settings, settings.high);
+    ensure!(
+        low.is_live() != high.is_live(),
+        "Liveness is the same with {} = {} and {}",
+        settings.parameter,
+        settings.low,
+        settings.high
+    );
+    let (mut live_value, mut lost_value) = if low.is_live() {
+        (settings.low, settings.high)
+    } else {
+        (settings.high, settings.low)
+    };
+    let mut probes = vec![low, high];
+    while (lost_value - live_value).abs() > settings.tolerance && probes.len() < settings.max_probes
+    {
+        let value = (live_value + lost_value) / 2.0;
+        let probe = run_probe(base, settings, value);
+        if probe.is_live() {
+            live_value = value;
+        } else {
+            lost_value = value;
+        }
+        probes.push(probe);
+    }
+    Ok(Bisection {
+        parameter: settings.parameter,
+        probes,
+        live_value,
+        lost_value,
+    })
+}
+
+/// Write one CSV row per probe, to the given file or to stdout.
+pub fn write_results(bisection: &Bisection, path: Option<&str>) -> Result<()> {
+    match path {
+        Some(path) => write_csv(bisection, csv::Writer::from_path(path)?),
+        None => write_csv(bisection, csv::Writer::from_writer(io::stdout())),
+    }
+}
+
+fn write_csv<W: io::Write>(bisection: &Bisection, mut writer: csv::Writer<W>) -> Result<()> {
+    writer.write_record(&["parameter", "value", "runs", "live_runs", "live"])?;
+    for probe in &bisection.probes {
+        writer.write_record(&[
+            bisection.parameter.to_string(),
+            probe.value.to_string(),
+            probe.runs.to_string(),
+            probe.live_runs.to_string(),
+            probe.is_live().to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,44 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Graphviz rendering of the block trees of nodes, to inspect forks after a run.
+//!
+//! Each epoch is drawn as a cluster, from the initial QC on the left to the latest records on
+//! the right. Render with e.g. `dot -Tsvg node_0.dot -o node_0.svg`.
+
+use super::*;
+use std::{fs, path::Path};
+
+#[cfg(test)]
+#[path = "unit_tests/block_tree_tests.rs"]
+mod block_tree_tests;
+
+/// The blocks and QCs known to a node, in the DOT language.
+pub fn block_tree_dot(node: &NodeState) -> String {
+    let mut lines = vec![
+        "digraph block_tree {".to_string(),
+        "  rankdir = RL;".to_string(),
+    ];
+    for (epoch_id, store) in node.record_stores() {
+        lines.push(format!("  subgraph cluster_{} {{", epoch_id.0));
+        lines.push(format!("    label = \"epoch {}\";", epoch_id.0));
+        let prefix = format!("{}:", epoch_id.0);
+        for statement in store.dot_statements(&prefix) {
+            lines.push(format!("    {}", statement));
+        }
+        lines.push("  }".to_string());
+    }
+    lines.push("}".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Write the block tree of each node to `DIRECTORY/node_AUTHOR.dot`.
+pub fn write_block_trees<P: AsRef<Path>>(sim: &Simulator, directory: P) -> Result<()> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    for (index, node) in sim.simulated_nodes().iter().enumerate() {
+        let path = directory.join(format!("node_{}.dot", index));
+        fs::write(path, block_tree_dot(node.node()))?;
+    }
+    Ok(())
+}
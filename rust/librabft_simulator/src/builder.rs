@@ -0,0 +1,483 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Programmatic construction of simulations.
+//!
+//! `SimulationBuilder` starts from the defaults of scenario files and checks the parameters in
+//! the same way when building them, so that misconfigured runs fail early with an error instead
+//! of diverging or looping:
+//! ```ignore
+//! let parameters = SimulationBuilder::new()
+//!     .seed(42)
+//!     .nodes(4)
+//!     .pacemaker(Duration::from_millis(20), 2.0, 0.5)
+//!     .node(NodeConfigBuilder::new(Author(3)).delta(Duration::from_millis(40)).stake(2))
+//!     .crash(Author(2), GlobalTime(300), None)
+//!     .max_clock(GlobalTime(2000))
+//!     .build()?;
+//! let mut sim = make_simulator(&parameters);
+//! ```
+
+use super::*;
+use bft_simulator_runtime::{
+    clients::ClientModel,
+    corruption::CorruptionModel,
+    nemesis::{Eclipse, Flood},
+    network_trace::NetworkTrace,
+    quota::MessageQuota,
+    simulator::GlobalTime,
+    workload::ArrivalProcess,
+};
+use scenario::{
+    DomainFault, DomainFaultKind, EclipseAttack, FailureDomain, Fault, FloodAttack, NodeOverrides,
+    Scenario, ScheduledParameterChange,
+};
+
+#[cfg(test)]
+#[path = "unit_tests/builder_tests.rs"]
+mod builder_tests;
+
+/// Configuration of a single node, replacing the pacemaker parameters of the simulation.
+#[derive(Clone, Debug)]
+pub struct NodeConfigBuilder {
+    overrides: NodeOverrides,
+}
+
+impl NodeConfigBuilder {
+    pub fn new(author: Author) -> Self {
+        NodeConfigBuilder {
+            overrides: NodeOverrides {
+                author: author.0,
+                ..NodeOverrides::default()
+            },
+        }
+    }
+
+    pub fn delta(mut self, delta: Duration) -> Self {
+        self.overrides.delta = Some(delta);
+        self
+    }
+
+    pub fn gamma(mut self, gamma: f64) -> Self {
+        self.overrides.gamma = Some(gamma);
+        self
+    }
+
+    pub fn lambda(mut self, lambda: f64) -> Self {
+        self.overrides.lambda = Some(lambda);
+        self
+    }
+
+    /// Voting rights of the node. 1 by default.
+    pub fn stake(mut self, stake: usize) -> Self {
+        self.overrides.stake = Some(stake);
+        self
+    }
+
+    /// Latest version of the data sync protocol supported by the node.
+    pub fn data_sync_version(mut self, version: u32) -> Self {
+        self.overrides.data_sync_version = Some(version);
+        self
+    }
+
+    pub fn strict_verification(mut self, strict_verification: bool) -> Self {
+        self.overrides.strict_verification = Some(strict_verification);
+        self
+    }
+
+    pub fn state_sync(mut self, state_sync: bool) -> Self {
+        self.overrides.state_sync = Some(state_sync);
+        self
+    }
+
+    /// Whether the voting constraints and the records of the node survive its restarts. Both
+    /// survive by default.
+    pub fn durability(mut self, safety_data: bool, records: bool) -> Self {
+        self.overrides.durable_safety_data = Some(safety_data);
+        self.overrides.durable_records = Some(records);
+        self
+    }
+
+    /// Propose payloads that fail validation. The node must also be set as Byzantine.
+    pub fn invalid_payloads(mut self, invalid_payloads: bool) -> Self {
+        self.overrides.invalid_payloads = invalid_payloads;
+        self
+    }
+
+    /// Speed of the processor of the node, relative to the others. 1 by default.
+    pub fn cpu_speed(mut self, speed: f64) -> Self {
+        self.overrides.cpu_speed = Some(speed);
+        self
+    }
+}
+
+/// Parameters of a simulation, with the defaults of scenario files.
+#[derive(Clone, Debug, Default)]
+pub struct SimulationBuilder {
+    scenario: Scenario,
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        SimulationBuilder::default()
+    }
+
+    /// Seed of the random network delays and losses. Chosen randomly by default.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.scenario.seed = Some(seed);
+        self
+    }
+
+    pub fn nodes(mut self, nodes: usize) -> Self {
+        self.scenario.nodes = nodes;
+        self
+    }
+
+    /// Duration of the first round after a commit, growth exponent of the durations of the
+    /// following rounds, and coefficient of the period of query-all actions.
+    pub fn pacemaker(mut self, delta: Duration, gamma: f64, lambda: f64) -> Self {
+        self.scenario.pacemaker.delta = delta;
+        self.scenario.pacemaker.gamma = gamma;
+        self.scenario.pacemaker.lambda = lambda;
+        self
+    }
+
+    pub fn delta(mut self, delta: Duration) -> Self {
+        self.scenario.pacemaker.delta = delta;
+        self
+    }
+
+    pub fn gamma(mut self, gamma: f64) -> Self {
+        self.scenario.pacemaker.gamma = gamma;
+        self
+    }
+
+    pub fn lambda(mut self, lambda: f64) -> Self {
+        self.scenario.pacemaker.lambda = lambda;
+        self
+    }
+
+    /// Lengthen the rounds of each node by up to `jitter` times their duration, at random.
+    pub fn round_jitter(mut self, jitter: f64) -> Self {
+        self.scenario.pacemaker.jitter = jitter;
+        self
+    }
+
+    /// Broadcast proposals and timeouts again after `period`, then after periods growing by
+    /// `backoff`, while their round lasts.
+    pub fn retransmission(mut self, period: Duration, backoff: f64) -> Self {
+        self.scenario.pacemaker.retransmission_period = Some(period);
+        self.scenario.pacemaker.retransmission_backoff = backoff;
+        self
+    }
+
+    /// Switch to longer rounds while many rounds time out, according to `policy`.
+    pub fn hybrid_pacemaker(mut self, policy: HybridPolicy) -> Self {
+        self.scenario.pacemaker.hybrid = Some(policy);
+        self
+    }
+
+    /// Send records to the nodes chosen by `peer_selection` when a round starts.
+    pub fn peer_selection(mut self, peer_selection: PeerSelection) -> Self {
+        self.scenario.pacemaker.peer_selection = peer_selection;
+        self
+    }
+
+    /// Skip the peers suspected according to `policy` when synchronizing.
+    pub fn failure_detector(mut self, policy: FailureDetectorPolicy) -> Self {
+        self.scenario.pacemaker.failure_detector = Some(policy);
+        self
+    }
+
+    /// Push records to random peers and pull from them according to `policy`, instead of
+    /// broadcasting.
+    pub fn push_pull(mut self, policy: PushPullPolicy) -> Self {
+        self.scenario.push_pull = Some(policy);
+        self
+    }
+
+    /// Latest version of the data sync protocol supported by the nodes, unless configured
+    /// otherwise.
+    pub fn data_sync_version(mut self, version: u32) -> Self {
+        self.scenario.data_sync_version = version;
+        self
+    }
+
+    /// Log-normal network delays with the given mean and variance.
+    pub fn network_delay(mut self, mean: f64, variance: f64) -> Self {
+        self.scenario.network.mean = mean;
+        self.scenario.network.variance = variance;
+        self
+    }
+
+    pub fn loss_rate(mut self, loss_rate: f64) -> Self {
+        self.scenario.network.loss_rate = loss_rate;
+        self
+    }
+
+    /// Change the network delays and losses over time.
+    pub fn network_trace(mut self, trace: &NetworkTrace) -> Self {
+        self.scenario.network.change = trace.changes().to_vec();
+        self
+    }
+
+    pub fn commit_rule(mut self, commit_rule: CommitRule) -> Self {
+        self.scenario.commit_rule = commit_rule;
+        self
+    }
+
+    pub fn vote_delivery(mut self, vote_delivery: VoteDelivery) -> Self {
+        self.scenario.vote_delivery = vote_delivery;
+        self
+    }
+
+    /// Let nodes skip the records that the receivers of their messages are known to hold.
+    pub fn suppress_redundant_records(mut self, suppress_redundant_records: bool) -> Self {
+        self.scenario.suppress_redundant_records = suppress_redundant_records;
+        self
+    }
+
+    /// Let nodes check all the records of a message before processing any of them.
+    pub fn strict_verification(mut self, strict_verification: bool) -> Self {
+        self.scenario.strict_verification = strict_verification;
+        self
+    }
+
+    /// Let lagging nodes resume from checkpoints of the executed state.
+    pub fn state_sync(mut self, state_sync: bool) -> Self {
+        self.scenario.state_sync = state_sync;
+        self
+    }
+
+    /// Damage messages in transit.
+    pub fn message_corruption(mut self, corruption: CorruptionModel) -> Self {
+        self.scenario.corruption = corruption;
+        self
+    }
+
+    /// Drop the messages beyond `quota` at their receivers.
+    pub fn message_quota(mut self, quota: MessageQuota) -> Self {
+        self.scenario.quota = quota;
+        self
+    }
+
+    pub fn aggregate_signatures(mut self, aggregate_signatures: bool) -> Self {
+        self.scenario.aggregate_signatures = aggregate_signatures;
+        self
+    }
+
+    pub fn threshold_signatures(mut self, threshold_signatures: bool) -> Self {
+        self.scenario.threshold_signatures = threshold_signatures;
+        self
+    }
+
+    /// Let nodes keep at most `max_uncertified_blocks` blocks without a QC per author.
+    pub fn max_uncertified_blocks(mut self, max_uncertified_blocks: usize) -> Self {
+        self.scenario.max_uncertified_blocks = Some(max_uncertified_blocks);
+        self
+    }
+
+    pub fn crypto_costs(mut self, crypto_costs: CryptoCosts) -> Self {
+        self.scenario.crypto = crypto_costs;
+        self
+    }
+
+    pub fn message_authentication(mut self, authentication: MessageAuthentication) -> Self {
+        self.scenario.message_authentication = authentication;
+        self
+    }
+
+    pub fn commands_per_epoch(mut self, commands_per_epoch: usize) -> Self {
+        self.scenario.workload.commands_per_epoch = commands_per_epoch;
+        self
+    }
+
+    /// Let leaders propose a chain of `blocks_per_round` blocks in each round.
+    pub fn blocks_per_round(mut self, blocks_per_round: usize) -> Self {
+        self.scenario.workload.blocks_per_round = blocks_per_round;
+        self
+    }
+
+    /// Execute the committed commands with `backend`.
+    pub fn backend(mut self, backend: ExecutionBackend) -> Self {
+        self.scenario.workload.backend = Some(backend);
+        self
+    }
+
+    /// Decide what leaders propose when they have no transactions.
+    pub fn empty_blocks(mut self, policy: EmptyBlockPolicy) -> Self {
+        self.scenario.workload.empty_blocks = policy;
+        self
+    }
+
+    /// Configure a node. Later configurations of the same node replace earlier ones.
+    pub fn node(mut self, node: NodeConfigBuilder) -> Self {
+        let author = node.overrides.author;
+        self.scenario.node.retain(|x| x.author != author);
+        self.scenario.node.push(node.overrides);
+        self
+    }
+
+    /// Submit transactions to each node.
+    pub fn arrivals(mut self, process: ArrivalProcess) -> Self {
+        self.scenario.workload.arrivals = Some(process);
+        self
+    }
+
+    /// Submit targeted transactions to all the nodes at once.
+    pub fn targeted_arrivals(mut self, process: ArrivalProcess) -> Self {
+        self.scenario.workload.targeted_arrivals = Some(process);
+        self
+    }
+
+    /// Simulate clients that send requests to the nodes and wait for their commit.
+    pub fn clients(mut self, model: ClientModel) -> Self {
+        self.scenario.clients = Some(model);
+        self
+    }
+
+    /// Count a node as Byzantine in the chain quality, and let it censor targeted transactions.
+    pub fn byzantine(mut self, author: Author) -> Self {
+        self.scenario.byzantine.push(author.0);
+        self
+    }
+
+    /// Let a node follow the committed chain without voting, proposing, or being a validator.
+    pub fn observer(mut self, author: Author) -> Self {
+        self.scenario.observers.push(author.0);
+        self
+    }
+
+    /// Crash a node at `crash_time`, and restart it at `recovery_time` if given.
+    pub fn crash(
+        mut self,
+        author: Author,
+        crash_time: GlobalTime,
+        recovery_time: Option<GlobalTime>,
+    ) -> Self {
+        self.scenario.fault.push(Fault {
+            author: author.0,
+            crash_time,
+            recovery_time,
+        });
+        self
+    }
+
+    /// Declare a group of nodes that fail together, e.g. a rack or a region.
+    pub fn failure_domain(mut self, name: &str, nodes: &[Author]) -> Self {
+        self.scenario.domain.push(FailureDomain {
+            name: name.to_string(),
+            nodes: nodes.iter().map(|x| x.0).collect(),
+        });
+        self
+    }
+
+    /// Crash or partition all the nodes of a failure domain at `time`, until `end_time` if given.
+    pub fn domain_fault(
+        mut self,
+        domain: &str,
+        kind: DomainFaultKind,
+        time: GlobalTime,
+        end_time: Option<GlobalTime>,
+    ) -> Self {
+        self.scenario.domain_fault.push(DomainFault {
+            domain: domain.to_string(),
+            kind,
+            time,
+            end_time,
+        });
+        self
+    }
+
+    /// Let an adversary control all the links of `victim` at `time`, until `end_time` if given.
+    pub fn eclipse(
+        mut self,
+        victim: Author,
+        time: GlobalTime,
+        end_time: Option<GlobalTime>,
+        eclipse: &Eclipse,
+    ) -> Self {
+        self.scenario.eclipse.push(EclipseAttack {
+            victim: victim.0,
+            time,
+            end_time,
+            delay: eclipse.delay,
+            drop_rate: eclipse.drop_rate,
+            relayed: eclipse.relayed.iter().map(|x| x.0).collect(),
+        });
+        self
+    }
+
+    /// Let `attacker` flood the other nodes at `time`, until `end_time` if given.
+    pub fn flood(
+        mut self,
+        attacker: Author,
+        time: GlobalTime,
+        end_time: Option<GlobalTime>,
+        flood: &Flood,
+    ) -> Self {
+        self.scenario.flood.push(FloodAttack {
+            attacker: attacker.0,
+            time,
+            end_time,
+            period: flood.period,
+            notifications: flood.notifications,
+            requests: flood.requests,
+        });
+        self
+    }
+
+    /// Propose `change` on chain from `time` on. It takes effect at the start of the epoch after
+    /// its commit.
+    pub fn parameter_change(mut self, time: GlobalTime, change: ParameterChange) -> Self {
+        self.scenario
+            .parameter_change
+            .push(ScheduledParameterChange {
+                time,
+                delta: change.delta,
+                gamma: change.gamma,
+                lambda: change.lambda,
+                max_block_payload: change.max_block_payload,
+            });
+        self
+    }
+
+    pub fn max_clock(mut self, max_clock: GlobalTime) -> Self {
+        self.scenario.termination.max_clock = max_clock;
+        self
+    }
+
+    pub fn max_commits(mut self, max_commits: usize) -> Self {
+        self.scenario.termination.max_commits = Some(max_commits);
+        self
+    }
+
+    /// Report the stalls that last longer than `threshold`.
+    pub fn stall_threshold(mut self, threshold: Duration) -> Self {
+        self.scenario.termination.stall_threshold = Some(threshold);
+        self
+    }
+
+    /// Leave the commits made before `warm_up` out of the statistics.
+    pub fn warm_up(mut self, warm_up: Duration) -> Self {
+        self.scenario.measurement.warm_up = warm_up;
+        self
+    }
+
+    /// Measure for `window` after the warm-up, then stop the run.
+    pub fn measurement_window(mut self, window: Duration) -> Self {
+        self.scenario.measurement.window = Some(window);
+        self
+    }
+
+    /// Check the configuration and return it as a scenario, e.g. to save it.
+    pub fn build_scenario(self) -> Result<Scenario> {
+        self.scenario.check()?;
+        Ok(self.scenario)
+    }
+
+    /// Check the configuration and return the parameters of the run.
+    pub fn build(self) -> Result<SimulationParameters> {
+        Ok(self.build_scenario()?.parameters())
+    }
+}
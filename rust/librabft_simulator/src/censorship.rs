@@ -0,0 +1,98 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Resistance to the censorship of targeted transactions by Byzantine leaders.
+//!
+//! Targeted transactions are submitted to all the nodes, and Byzantine nodes leave them out of
+//! their proposals. They still commit once an honest node proposes them, so the slowdown of
+//! targeted transactions over the other ones measures how long Byzantine leaders can delay
+//! them. The scenario runs once per number of Byzantine nodes, from none to the largest
+//! minority that the protocol tolerates.
+
+use super::*;
+use bft_simulator_runtime::statistics::RunSummary;
+use std::io;
+
+#[cfg(test)]
+#[path = "unit_tests/censorship_tests.rs"]
+mod censorship_tests;
+
+/// Outcome of one number of Byzantine nodes.
+#[derive(Clone, Debug)]
+pub struct CensorshipResult {
+    pub parameters: SimulationParameters,
+    pub summary: RunSummary,
+}
+
+impl CensorshipResult {
+    pub fn byzantine_fraction(&self) -> f64 {
+        self.parameters.byzantine.len() as f64 / self.parameters.nodes as f64
+    }
+
+    /// Mean latency of the targeted transactions over the mean latency of the other ones, if
+    /// both were committed.
+    pub fn slowdown(&self) -> Option<f64> {
+        let targeted = self.summary.mean_targeted_latency?;
+        let other = self.summary.mean_transaction_latency?;
+        if other > 0.0 {
+            Some(targeted / other)
+        } else {
+            None
+        }
+    }
+}
+
+/// Run `base` with the last `0 ..= (nodes - 1) / 3` nodes being Byzantine, in place of the
+/// Byzantine nodes of `base`.
+pub fn run_censorship(base: &SimulationParameters) -> Result<Vec<CensorshipResult>> {
+    ensure!(
+        base.targeted_arrivals.is_some(),
+        "Measuring censorship needs targeted transactions"
+    );
+    let max_byzantine = base.nodes.saturating_sub(1) / 3;
+    Ok((0..=max_byzantine)
+        .map(|count| {
+            let parameters = SimulationParameters {
+                byzantine: (base.nodes - count..base.nodes).map(Author).collect(),
+                ..base.clone()
+            };
+            info!("Running with {} Byzantine nodes", count);
+            CensorshipResult {
+                summary: sweep::run_summary(&parameters),
+                parameters,
+            }
+        })
+        .collect())
+}
+
+/// Write one CSV row per number of Byzantine nodes, to the given file or to stdout.
+pub fn write_results(results: &[CensorshipResult], path: Option<&str>) -> Result<()> {
+    match path {
+        Some(path) => write_csv(results, csv::Writer::from_path(path)?),
+        None => write_csv(results, csv::Writer::from_writer(io::stdout())),
+    }
+}
+
+fn write_csv<W: io::Write>(results: &[CensorshipResult], mut writer: csv::Writer<W>) -> Result<()> {
+    writer.write_record(&[
+        "nodes",
+        "byzantine",
+        "byzantine_fraction",
+        "mean_transaction_latency",
+        "mean_targeted_latency",
+        "slowdown",
+    ])?;
+    for result in results {
+        let optional = |x: Option<f64>| x.map_or(String::new(), |x| x.to_string());
+        writer.write_record(&[
+            result.parameters.nodes.to_string(),
+            result.parameters.byzantine.len().to_string(),
+            result.byzantine_fraction().to_string(),
+            optional(result.summary.mean_transaction_latency),
+            optional(result.summary.mean_targeted_latency),
+            optional(result.slowdown()),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
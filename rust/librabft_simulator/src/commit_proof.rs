@@ -0,0 +1,110 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Self-contained proofs that a block was committed.
+//!
+//! A proof is the chain of blocks and QCs from the committed block to the QCs that complete the
+//! commit rule, e.g. three QCs at consecutive rounds. Each QC certifies the block before it, and
+//! each block extends the previous QC, so that observers who only know the validators of the
+//! epoch can check the commit without a record store.
+
+use super::*;
+use bft_simulator_runtime::threshold::ThresholdKeys;
+use record::*;
+use record_store::RecordStoreState;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct CommitProof {
+    pub epoch_id: EpochId,
+    /// Blocks and their QCs, starting with the committed block, in increasing order of rounds.
+    pub links: Vec<(Block, QuorumCertificate)>,
+}
+
+impl CommitProof {
+    /// The committed block, if the proof is not empty.
+    pub fn committed_block(&self) -> Option<&Block> {
+        self.links.first().map(|(block, _)| block)
+    }
+
+    /// Execution state after the committed block, if the proof is not empty.
+    pub fn committed_state(&self) -> Option<&State> {
+        self.links.first().map(|(_, qc)| &qc.state)
+    }
+
+    /// Check the proof against the validators of its epoch, with the commit rule of the epoch
+    /// and, if `fast_path` is set, commits by QCs with the votes of all validators.
+    pub fn verify(
+        &self,
+        configuration: &EpochConfiguration,
+        commit_rule: CommitRule,
+        fast_path: bool,
+    ) -> Result<()> {
+        ensure!(!self.links.is_empty(), "Commit proofs cannot be empty");
+        let mut previous_qc_hash = None;
+        for (block, qc) in &self.links {
+            ensure!(
+                qc.epoch_id == self.epoch_id,
+                "QCs of a commit proof must belong to its epoch"
+            );
+            let block_hash = BlockHash(Record::Block(Arc::new(block.clone())).digest());
+            ensure!(
+                qc.certified_block_hash == block_hash && qc.round == block.round,
+                "Each QC of a commit proof must certify the block before it"
+            );
+            if let Some(previous_qc_hash) = previous_qc_hash {
+                ensure!(
+                    block.previous_quorum_certificate_hash == previous_qc_hash,
+                    "Each block of a commit proof must extend the previous QC"
+                );
+            }
+            match &*qc.votes {
+                MultiSignature::Threshold(signature) => {
+                    ThresholdKeys::deal(self.epoch_id.0 as u64, configuration)
+                        .check(qc.share_digest(), *signature)?
+                }
+                votes => ensure!(
+                    configuration.count_votes(&votes.authors()) >= configuration.quorum_threshold(),
+                    "Votes in QCs must form a quorum"
+                ),
+            }
+            let record = Record::QuorumCertificate(Arc::new(qc.clone()));
+            let hash = record.digest();
+            RecordStoreState::check_signatures(&record, hash)?;
+            previous_qc_hash = Some(QuorumCertificateHash(hash));
+        }
+        let qcs: Vec<_> = self.links.iter().map(|(_, qc)| qc).collect();
+        ensure!(
+            completes_commit_rule(&qcs, commit_rule, fast_path, configuration),
+            "The QCs of a commit proof must complete the commit rule"
+        );
+        Ok(())
+    }
+}
+
+/// Whether the last QCs of a chain commit its first block. `qcs` are in increasing order of
+/// rounds, each one extending the previous one.
+pub(crate) fn completes_commit_rule(
+    qcs: &[&QuorumCertificate],
+    commit_rule: CommitRule,
+    fast_path: bool,
+    configuration: &EpochConfiguration,
+) -> bool {
+    let length = commit_rule.chain_length();
+    match qcs.last() {
+        Some(qc)
+            if fast_path
+                && configuration.count_votes(&qc.votes.authors())
+                    == configuration.total_votes() =>
+        {
+            true
+        }
+        _ => {
+            qcs.len() >= length
+                && qcs[qcs.len() - length..]
+                    .windows(2)
+                    .all(|pair| pair[1].round == pair[0].round + 1)
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! A/B comparisons of two configurations.
+//!
+//! Both configurations run once with each of the seeds `seed .. seed + seeds` of the first
+//! configuration, and each metric is compared by pairs of runs with the same seed. Pairing
+//! removes the variance between seeds from the comparison, which then detects smaller
+//! differences than independent runs would.
+
+use super::*;
+use bft_simulator_runtime::statistics::{PairedComparison, RunSummary};
+use std::io;
+
+#[cfg(test)]
+#[path = "unit_tests/comparison_tests.rs"]
+mod comparison_tests;
+
+#[derive(Clone, Debug)]
+pub struct MetricComparison {
+    /// Name of the metric, as in the summary of a run.
+    pub metric: &'static str,
+    /// Comparison over the pairs of runs where both configurations measured the metric, if any.
+    pub comparison: Option<PairedComparison>,
+}
+
+/// Outcome of an A/B comparison.
+#[derive(Clone, Debug)]
+pub struct ComparisonReport {
+    /// Seeds of the pairs of runs.
+    pub seeds: Vec<u64>,
+    pub metrics: Vec<MetricComparison>,
+}
+
+impl ComparisonReport {
+    pub fn metric(&self, name: &str) -> Option<&MetricComparison> {
+        self.metrics.iter().find(|metric| metric.metric == name)
+    }
+}
+
+/// Run `a` and `b` with `seeds` consecutive seeds, starting from the seed of `a`, and compare
+/// their throughput, latencies, and timeout rates.
+pub fn compare(a: &SimulationParameters, b: &SimulationParameters, seeds: u64) -> ComparisonReport {
+    let seeds: Vec<_> = (0..seeds).map(|i| a.seed.wrapping_add(i)).collect();
+    let runs: Vec<_> = seeds
+        .iter()
+        .map(|seed| {
+            info!("Comparing configurations with seed {}", seed);
+            let run = |parameters: &SimulationParameters| {
+                sweep::run_summary(&SimulationParameters {
+                    seed: *seed,
+                    ..parameters.clone()
+                })
+            };
+            (run(a), run(b))
+        })
+        .collect();
+    let metric = |name, value: &Fn(&RunSummary) -> Option<f64>| {
+        let pairs: Vec<_> = runs
+            .iter()
+            .filter_map(|(x, y)| Some((value(x)?, value(y)?)))
+            .collect();
+        MetricComparison {
+            metric: name,
+            comparison: PairedComparison::new(&pairs),
+        }
+    };
+    let metrics = vec![
+        metric("commit_throughput", &|summary| {
+            Some(summary.commit_throughput)
+        }),
+        metric("mean_commit_latency", &|summary| {
+            summary.mean_commit_latency
+        }),
+        metric("median_commit_latency", &|summary| {
+            summary.median_commit_latency
+        }),
+        metric("timeout_rate", &|summary| Some(summary.timeout_rate)),
+        metric("transaction_throughput", &|summary| {
+            Some(summary.transaction_throughput)
+        }),
+        metric("mean_transaction_latency", &|summary| {
+            summary.mean_transaction_latency
+        }),
+        metric("mean_targeted_latency", &|summary| {
+            summary.mean_targeted_latency
+        }),
+    ];
+    ComparisonReport { seeds, metrics }
+}
+
+/// Write one CSV row per metric, to the given file or to stdout.
+pub fn write_report(report: &ComparisonReport, path: Option<&str>) -> Result<()> {
+    match path {
+        Some(path) => write_csv(report, csv::Writer::from_path(path)?),
+        None => write_csv(report, csv::Writer::from_writer(io::stdout())),
+    }
+}
+
+fn write_csv<W: io::Write>(report: &ComparisonReport, mut writer: csv::Writer<W>) -> Result<()> {
+    writer.write_record(&[
+        "metric",
+        "pairs",
+        "mean_a",
+        "mean_b",
+        "difference",
+        "ci_low",
+        "ci_high",
+        "relative_difference",
+        "significant",
+    ])?;
+    for metric in &report.metrics {
+        let mut record = vec![metric.metric.to_string()];
+        match &metric.comparison {
+            Some(x) => record.extend(vec![
+                x.count.to_string(),
+                x.mean_a.to_string(),
+                x.mean_b.to_string(),
+                x.mean_difference.to_string(),
+                x.confidence_interval.0.to_string(),
+                x.confidence_interval.1.to_string(),
+                x.relative_difference()
+                    .map_or(String::new(), |x| x.to_string()),
+                x.is_significant().to_string(),
+            ]),
+            None => {
+                record.push("0".to_string());
+                record.extend(vec![String::new(); 7]);
+            }
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
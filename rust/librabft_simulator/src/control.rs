@@ -0,0 +1,294 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Remote control of simulation runs over gRPC, with the `grpc` feature.
+//!
+//! The service of `proto/control.proto` lets CI farms and notebooks start runs of scenarios,
+//! poll them, fetch their metrics, and inject faults, without linking against this crate. Each
+//! run proceeds on its own thread, in steps of `STEP` units of simulated time. Faults are applied
+//! between two steps.
+
+use super::*;
+use bft_simulator_runtime::{
+    simulator::GlobalTime, statistics::RunSummary, termination::StopReason,
+};
+use futures::future::{self, FutureResult};
+use hyper::{
+    rt::{Future, Stream},
+    server::conn::AddrIncoming,
+};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    thread,
+};
+use tower_grpc::{Code, Request, Response, Status};
+use tower_hyper::server::{Http, Server};
+
+#[cfg(test)]
+#[path = "unit_tests/control_tests.rs"]
+mod control_tests;
+
+/// Messages and service of `proto/control.proto`, as generated by `tower-grpc-build`.
+pub mod messages {
+    include!(concat!(env!("OUT_DIR"), "/librabft.control.rs"));
+}
+
+use self::messages::{
+    server, FaultKind, InjectFaultRequest, InjectFaultResponse, MetricsRequest, MetricsResponse,
+    StartRunRequest, StartRunResponse, StatusRequest, StatusResponse,
+};
+
+/// Simulated time processed by a run between two checks for faults.
+const STEP: Duration = Duration::from_millis(100);
+
+/// A fault injected into a running simulation.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Fault {
+    Crash(Author),
+    /// Restart a crashed node. No effect on nodes that are running.
+    Recover(Author),
+}
+
+/// Progress of a run.
+#[derive(Clone, Debug, Default)]
+pub struct RunStatus {
+    /// Events were processed up to this simulated time.
+    pub clock: GlobalTime,
+    /// Why the run ended, if it did.
+    pub stop_reason: Option<String>,
+    /// Summary of the run so far, once the first step is done.
+    pub summary: Option<RunSummary>,
+}
+
+/// A run, as shared between its thread and the service.
+#[derive(Default)]
+struct SharedRun {
+    nodes: usize,
+    status: RunStatus,
+    /// Faults to apply after the current step, with their times.
+    faults: Vec<(Fault, GlobalTime)>,
+}
+
+/// A simulation processed step by step.
+struct ControlledRun {
+    parameters: SimulationParameters,
+    simulator: Simulator,
+    clock: GlobalTime,
+    stop_reason: Option<StopReason>,
+}
+
+impl ControlledRun {
+    fn new(parameters: SimulationParameters) -> Self {
+        let simulator = make_simulator(&parameters);
+        ControlledRun {
+            parameters,
+            simulator,
+            clock: GlobalTime(0),
+            stop_reason: None,
+        }
+    }
+
+    /// Process the events of the next `STEP` units of time, unless the run is over.
+    fn step(&mut self) {
+        if self.stop_reason.is_some() {
+            return;
+        }
+        let until = std::cmp::min(self.clock + STEP, self.parameters.end_clock());
+        let mut termination = self.parameters.termination();
+        termination.max_clock = Some(until);
+        let stop_reason = self.simulator.run(&termination, None);
+        self.clock = until;
+        match stop_reason {
+            StopReason::MaxClock if until < self.parameters.end_clock() => (),
+            stop_reason => self.stop_reason = Some(stop_reason),
+        }
+    }
+
+    /// Apply a fault at `time`, which must be after the processed events.
+    fn inject(&mut self, fault: Fault, time: GlobalTime) {
+        match fault {
+            Fault::Crash(author) => self.simulator.crash_node(author, time),
+            Fault::Recover(author) => {
+                if self.simulator.simulated_node(author).is_crashed(time) {
+                    self.simulator.recover_node_at(author, time);
+                } else {
+                    warn!("Ignoring the recovery of running node {:?}", author);
+                }
+            }
+        }
+    }
+
+    fn status(&self) -> RunStatus {
+        RunStatus {
+            clock: self.clock,
+            stop_reason: self
+                .stop_reason
+                .as_ref()
+                .map(|stop_reason| format!("{:?}", stop_reason)),
+            summary: Some(self.simulator.summary()),
+        }
+    }
+}
+
+/// Publish the status of a run after each step, and apply the faults received in the meantime.
+fn run_controlled(parameters: SimulationParameters, shared: Arc<Mutex<SharedRun>>) {
+    let mut run = ControlledRun::new(parameters);
+    loop {
+        run.step();
+        let faults = {
+            let mut shared = shared.lock().unwrap();
+            shared.status = run.status();
+            if run.stop_reason.is_some() {
+                return;
+            }
+            std::mem::replace(&mut shared.faults, Vec::new())
+        };
+        for (fault, time) in faults {
+            run.inject(fault, time);
+        }
+    }
+}
+
+/// The runs started by a server, identified by their index.
+#[derive(Clone, Default)]
+pub struct Experiments {
+    runs: Arc<Mutex<Vec<Arc<Mutex<SharedRun>>>>>,
+}
+
+impl Experiments {
+    /// Start a run of a TOML scenario in the background. Returns the identifier of the run and
+    /// its seed.
+    pub fn start_run(&self, scenario: &str) -> Result<(u64, u64)> {
+        let parameters = scenario::Scenario::parse(scenario)?.parameters();
+        let seed = parameters.seed;
+        let shared = Arc::new(Mutex::new(SharedRun {
+            nodes: parameters.nodes,
+            ..SharedRun::default()
+        }));
+        let mut runs = self.runs.lock().unwrap();
+        let run_id = runs.len() as u64;
+        runs.push(shared.clone());
+        thread::Builder::new()
+            .name(format!("run-{}", run_id))
+            .spawn(move || run_controlled(parameters, shared))?;
+        Ok((run_id, seed))
+    }
+
+    fn run(&self, run_id: u64) -> Result<Arc<Mutex<SharedRun>>> {
+        match self.runs.lock().unwrap().get(run_id as usize) {
+            Some(shared) => Ok(shared.clone()),
+            None => bail!("Unknown run {}", run_id),
+        }
+    }
+
+    pub fn status(&self, run_id: u64) -> Result<RunStatus> {
+        Ok(self.run(run_id)?.lock().unwrap().status.clone())
+    }
+
+    /// Schedule a fault at `time`, or right after the processed events if `time` is in the past
+    /// of the run. Returns the time of the fault.
+    pub fn inject_fault(&self, run_id: u64, fault: Fault, time: GlobalTime) -> Result<GlobalTime> {
+        let run = self.run(run_id)?;
+        let mut shared = run.lock().unwrap();
+        ensure!(
+            shared.status.stop_reason.is_none(),
+            "Run {} is over",
+            run_id
+        );
+        let author = match fault {
+            Fault::Crash(author) | Fault::Recover(author) => author,
+        };
+        ensure!(author.0 < shared.nodes, "Unknown node {}", author.0);
+        let time = std::cmp::max(time, shared.status.clock + Duration::from_millis(1));
+        shared.faults.push((fault, time));
+        Ok(time)
+    }
+}
+
+fn invalid_argument(error: failure::Error) -> Status {
+    Status::new(Code::InvalidArgument, error.to_string())
+}
+
+/// The gRPC service of the control API.
+#[derive(Clone, Default)]
+struct ControlService {
+    experiments: Experiments,
+}
+
+impl server::SimulationControl for ControlService {
+    type StartRunFuture = FutureResult<Response<StartRunResponse>, Status>;
+    type GetStatusFuture = FutureResult<Response<StatusResponse>, Status>;
+    type FetchMetricsFuture = FutureResult<Response<MetricsResponse>, Status>;
+    type InjectFaultFuture = FutureResult<Response<InjectFaultResponse>, Status>;
+
+    fn start_run(&mut self, request: Request<StartRunRequest>) -> Self::StartRunFuture {
+        let result = self
+            .experiments
+            .start_run(&request.get_ref().scenario)
+            .map(|(run_id, seed)| Response::new(StartRunResponse { run_id, seed }));
+        future::result(result.map_err(invalid_argument))
+    }
+
+    fn get_status(&mut self, request: Request<StatusRequest>) -> Self::GetStatusFuture {
+        let result = self
+            .experiments
+            .status(request.get_ref().run_id)
+            .map(|status| {
+                Response::new(StatusResponse {
+                    finished: status.stop_reason.is_some(),
+                    clock: status.clock.0,
+                    stop_reason: status.stop_reason.unwrap_or_default(),
+                })
+            });
+        future::result(result.map_err(invalid_argument))
+    }
+
+    fn fetch_metrics(&mut self, request: Request<MetricsRequest>) -> Self::FetchMetricsFuture {
+        let result = self
+            .experiments
+            .status(request.get_ref().run_id)
+            .and_then(|status| {
+                Ok(Response::new(MetricsResponse {
+                    clock: status.clock.0,
+                    summary: serde_json::to_string(&status.summary)?,
+                }))
+            });
+        future::result(result.map_err(invalid_argument))
+    }
+
+    fn inject_fault(&mut self, request: Request<InjectFaultRequest>) -> Self::InjectFaultFuture {
+        let request = request.get_ref();
+        let author = Author(request.author as usize);
+        let result = match FaultKind::from_i32(request.kind) {
+            Some(FaultKind::Crash) => Ok(Fault::Crash(author)),
+            Some(FaultKind::Recover) => Ok(Fault::Recover(author)),
+            None => Err(format_err!("Unknown fault kind {}", request.kind)),
+        }
+        .and_then(|fault| {
+            self.experiments
+                .inject_fault(request.run_id, fault, GlobalTime(request.time))
+        })
+        .map(|time| Response::new(InjectFaultResponse { time: time.0 }));
+        future::result(result.map_err(invalid_argument))
+    }
+}
+
+/// Serve the control API on `address` until the process is stopped.
+pub fn serve(address: &SocketAddr) -> Result<()> {
+    let service = server::SimulationControlServer::new(ControlService::default());
+    let mut server = Server::new(service);
+    let http = Http::new().http2_only(true).clone();
+    let incoming = AddrIncoming::bind(address)?;
+    let serve = incoming
+        .for_each(move |stream| {
+            let connection = server
+                .serve_with(stream, http.clone())
+                .map_err(|error| warn!("gRPC connection failed: {:?}", error));
+            hyper::rt::spawn(connection);
+            Ok(())
+        })
+        .map_err(|error| warn!("Failed to accept a connection: {}", error));
+    hyper::rt::run(serve);
+    Ok(())
+}
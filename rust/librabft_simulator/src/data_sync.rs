@@ -3,56 +3,465 @@
 
 use super::*;
 use base_types::*;
+use bft_simulator_runtime::{
+    corruption::WireFormat,
+    message_counters::{CountRecords, RecordCounts},
+};
+use error::NodeError;
 use node::*;
 use record::*;
+use record_store::{RecordRef, RecordStoreState};
+use serde::{Deserialize, Serialize};
 use smr_context::SMRContext;
-use std::collections::BTreeSet;
+use std::{
+    cmp::{max, min},
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
 
 #[cfg(test)]
 #[path = "unit_tests/data_sync_tests.rs"]
 mod data_sync_tests;
 
+/// Latest version of the data sync protocol.
+pub const DATA_SYNC_VERSION: u32 = 2;
+/// First version of the protocol. Unlike version 2, requests do not carry the rounds of known
+/// QCs, so that responses contain all the records of each epoch, and notifications do not carry
+/// the blocks chained before a proposal.
+pub const LEGACY_DATA_SYNC_VERSION: u32 = 1;
+
+fn legacy_data_sync_version() -> u32 {
+    LEGACY_DATA_SYNC_VERSION
+}
+
 // -- BEGIN FILE data_sync --
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Deserialize, Serialize)]
 pub struct DataSyncNotification {
+    /// Protocol version of the sender. Messages without a version are from version 1.
+    #[serde(default = "legacy_data_sync_version")]
+    pub(crate) version: u32,
     /// Current epoch identifier.
-    current_epoch: EpochId,
+    pub(crate) current_epoch: EpochId,
     /// Tail QC of the highest commit rule.
-    highest_commit_certificate: Option<QuorumCertificate>,
+    pub(crate) highest_commit_certificate: Option<Arc<QuorumCertificate>>,
     /// Highest QC.
-    highest_quorum_certificate: Option<QuorumCertificate>,
+    pub(crate) highest_quorum_certificate: Option<Arc<QuorumCertificate>>,
     /// Timeouts in the highest TC, then at the current round, if any.
-    timeouts: Vec<Timeout>,
+    pub(crate) timeouts: Vec<Timeout>,
     /// Sender's vote at the current round, if any (meant for the proposer).
-    current_vote: Option<Vote>,
+    pub(crate) current_vote: Option<Vote>,
     /// Known proposed block at the current round, if any.
-    proposed_block: Option<Block>,
+    pub(crate) proposed_block: Option<Arc<Block>>,
+    /// Blocks chained before the proposed block in the current round, starting with the first.
+    #[serde(default)]
+    pub(crate) previous_proposed_blocks: Vec<Arc<Block>>,
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Deserialize, Serialize)]
 pub struct DataSyncRequest {
+    /// Protocol version of the requester.
+    #[serde(default = "legacy_data_sync_version")]
+    pub(crate) version: u32,
     /// Current epoch identifier.
-    current_epoch: EpochId,
+    pub(crate) current_epoch: EpochId,
     /// Selection of rounds for which the receiver already knows a QC.
-    known_quorum_certificates: BTreeSet<Round>,
+    pub(crate) known_quorum_certificates: BTreeSet<Round>,
+    /// Number of blocks committed by the requester, if it accepts state checkpoints.
+    #[serde(default)]
+    pub(crate) committed_blocks: Option<usize>,
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Deserialize, Serialize)]
 pub struct DataSyncResponse {
+    /// Version negotiated for the exchange, i.e. the older version of the two nodes.
+    #[serde(default = "legacy_data_sync_version")]
+    pub(crate) version: u32,
     /// Current epoch identifier.
-    current_epoch: EpochId,
+    pub(crate) current_epoch: EpochId,
     /// Records for the receiver to insert, for each epoch, in the given order.
     /// Epochs older than the receiver's current epoch will be skipped, as well as chains
     /// of records ending with QC known to the receiver.
-    records: Vec<(EpochId, Vec<Record>)>,
+    pub(crate) records: Vec<(EpochId, Vec<Record>)>,
+    /// Executed state at the start of a later epoch than the receiver's, if requested. The
+    /// records then start at this epoch. Receivers only install a checkpoint that enough peers
+    /// sent them.
+    #[serde(default)]
+    pub(crate) checkpoint: Option<StateCheckpoint>,
+    /// Blocks committed before the checkpoint and unknown to the receiver, in order.
+    #[serde(default)]
+    pub(crate) checkpoint_blocks: Vec<CommittedBlock>,
 }
 // -- END FILE --
 
+/// Executed state at the start of an epoch, for lagging nodes to resume execution from it.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Deserialize, Serialize)]
+pub struct StateCheckpoint {
+    pub(crate) epoch_id: EpochId,
+    /// Committed state that starts the epoch.
+    pub(crate) state: State,
+    /// Snapshot of the state by the SMR context.
+    pub(crate) snapshot: Vec<u8>,
+    /// Number of blocks committed up to the state.
+    pub(crate) committed_blocks: usize,
+}
+
+/// Push/pull dissemination: nodes push new records to a few random peers instead of all of
+/// them, and send a request to a random peer periodically to fill the gaps. The fanout shrinks
+/// while most of the records that nodes receive are duplicates, and grows while few are.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PushPullPolicy {
+    /// Number of random peers to push to at first.
+    pub initial_fanout: usize,
+    pub min_fanout: usize,
+    pub max_fanout: usize,
+    /// Time between two pulls.
+    pub pull_period: Duration,
+    /// Rate of duplicates among the records received since the last pull under which the
+    /// fanout grows.
+    pub min_duplicate_rate: f64,
+    /// Rate of duplicates over which the fanout shrinks.
+    pub max_duplicate_rate: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct PushPullState {
+    policy: PushPullPolicy,
+    /// Current number of random peers to push to.
+    fanout: usize,
+    /// Number of random draws so far, so that each push and pull picks new peers.
+    draws: u64,
+    /// Time of the next pull.
+    next_pull_time: NodeTime,
+    /// Records received in notifications since the last pull, and how many were known.
+    received_records: usize,
+    duplicate_records: usize,
+}
+
+impl Default for PushPullPolicy {
+    fn default() -> Self {
+        PushPullPolicy {
+            initial_fanout: 2,
+            min_fanout: 1,
+            max_fanout: 8,
+            pull_period: Duration::from_millis(50),
+            min_duplicate_rate: 0.2,
+            max_duplicate_rate: 0.6,
+        }
+    }
+}
+
+impl PushPullPolicy {
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            0 < self.min_fanout
+                && self.min_fanout <= self.initial_fanout
+                && self.initial_fanout <= self.max_fanout,
+            "Fanouts must satisfy 0 < min_fanout <= initial_fanout <= max_fanout"
+        );
+        ensure!(
+            self.pull_period.is_positive(),
+            "Pull periods must be positive"
+        );
+        ensure!(
+            0.0 <= self.min_duplicate_rate
+                && self.min_duplicate_rate <= self.max_duplicate_rate
+                && self.max_duplicate_rate <= 1.0,
+            "Duplicate rates must satisfy 0 <= min_duplicate_rate <= max_duplicate_rate <= 1"
+        );
+        Ok(())
+    }
+}
+
+impl PushPullState {
+    /// Start with the initial fanout and a pull at the first update.
+    pub fn new(policy: PushPullPolicy) -> Self {
+        PushPullState {
+            policy,
+            fanout: policy.initial_fanout,
+            draws: 0,
+            next_pull_time: NodeTime(0),
+            received_records: 0,
+            duplicate_records: 0,
+        }
+    }
+
+    /// Restart from the initial fanout after a crash, and pull at the next update.
+    pub fn restart(&self) -> PushPullState {
+        PushPullState {
+            draws: self.draws,
+            ..PushPullState::new(self.policy)
+        }
+    }
+
+    pub fn fanout(&self) -> usize {
+        self.fanout
+    }
+
+    pub fn next_pull_time(&self) -> NodeTime {
+        self.next_pull_time
+    }
+
+    /// Count `records` received in a notification, `duplicates` of which were known.
+    pub fn observe(&mut self, records: usize, duplicates: usize) {
+        self.received_records += records;
+        self.duplicate_records += duplicates;
+    }
+
+    /// Random peers to push new records to.
+    pub fn push_targets(&mut self, local_author: Author, validators: Vec<Author>) -> Vec<Author> {
+        let fanout = self.fanout;
+        self.random_peers(local_author, validators, fanout)
+    }
+
+    /// Random peer to pull from, if a pull is due at `clock`. The fanout adapts to the
+    /// duplicates received since the last pull first.
+    pub fn pull_target(
+        &mut self,
+        local_author: Author,
+        validators: Vec<Author>,
+        clock: NodeTime,
+    ) -> Option<Author> {
+        if clock < self.next_pull_time {
+            return None;
+        }
+        self.next_pull_time = clock + self.policy.pull_period;
+        self.adapt_fanout();
+        self.random_peers(local_author, validators, 1).pop()
+    }
+
+    fn adapt_fanout(&mut self) {
+        if self.received_records > 0 {
+            let rate = self.duplicate_records as f64 / self.received_records as f64;
+            let fanout = if rate > self.policy.max_duplicate_rate {
+                max(self.fanout - 1, self.policy.min_fanout)
+            } else if rate < self.policy.min_duplicate_rate {
+                min(self.fanout + 1, self.policy.max_fanout)
+            } else {
+                self.fanout
+            };
+            if fanout != self.fanout {
+                tracing::debug!(duplicate_rate = rate, fanout, "Adapting push fanout");
+                self.fanout = fanout;
+            }
+        }
+        self.received_records = 0;
+        self.duplicate_records = 0;
+    }
+
+    fn random_peers(
+        &mut self,
+        local_author: Author,
+        mut validators: Vec<Author>,
+        count: usize,
+    ) -> Vec<Author> {
+        // Shuffle the validators in a reproducible way.
+        let draw = self.draws;
+        self.draws += 1;
+        validators.retain(|author| *author != local_author);
+        validators.sort_by_key(|author| {
+            let mut hasher = DefaultHasher::new();
+            (author, local_author, draw).hash(&mut hasher);
+            hasher.finish()
+        });
+        validators.truncate(count);
+        validators
+    }
+}
+
+/// Records that other nodes are known to hold in the current epoch, so that we do not send them
+/// again. Nodes hold the records of their notifications and the blocks that they voted for.
+#[derive(Clone, Debug)]
+pub struct PeerKnowledge {
+    /// Epoch of the records.
+    epoch_id: EpochId,
+    /// Digests of the records held by each peer.
+    records: HashMap<Author, HashSet<u64>>,
+    /// Number of notifications not sent because the receiver held their records.
+    suppressed_notifications: usize,
+}
+
+impl PeerKnowledge {
+    pub fn new(epoch_id: EpochId) -> Self {
+        PeerKnowledge {
+            epoch_id,
+            records: HashMap::new(),
+            suppressed_notifications: 0,
+        }
+    }
+
+    /// Forget what peers hold after a crash. Suppressed notifications are still counted.
+    pub fn restart(&self) -> PeerKnowledge {
+        PeerKnowledge {
+            suppressed_notifications: self.suppressed_notifications,
+            ..PeerKnowledge::new(self.epoch_id)
+        }
+    }
+
+    pub fn suppressed_notifications(&self) -> usize {
+        self.suppressed_notifications
+    }
+
+    /// Learn that `peer` holds `records` of the epoch `epoch_id`.
+    pub fn learn(&mut self, epoch_id: EpochId, peer: Author, records: &[Record]) {
+        if epoch_id > self.epoch_id {
+            self.epoch_id = epoch_id;
+            self.records.clear();
+        }
+        if epoch_id < self.epoch_id {
+            return;
+        }
+        let held = self.records.entry(peer).or_insert_with(HashSet::new);
+        for record in records {
+            held.insert(record.digest());
+            if let Record::Vote(vote) = record {
+                held.insert(vote.certified_block_hash.0);
+            }
+        }
+    }
+
+    /// Whether `peer` holds the record with the given digest in the epoch `epoch_id`.
+    pub fn holds(&self, epoch_id: EpochId, peer: Author, digest: u64) -> bool {
+        epoch_id == self.epoch_id
+            && self
+                .records
+                .get(&peer)
+                .map_or(false, |held| held.contains(&digest))
+    }
+
+    /// Whether `peer` holds all the records with the given digests, if any, in the epoch
+    /// `epoch_id`.
+    pub fn holds_all(&self, epoch_id: EpochId, peer: Author, digests: &[u64]) -> bool {
+        !digests.is_empty()
+            && digests
+                .iter()
+                .all(|digest| self.holds(epoch_id, peer, *digest))
+    }
+
+    /// Forget the records held by `peer`, e.g. because it lost some of them in a crash.
+    pub fn forget(&mut self, peer: Author) {
+        self.records.remove(&peer);
+    }
+
+    /// Peers whose records we know, in increasing order.
+    pub fn peers(&self) -> Vec<Author> {
+        let mut peers: Vec<_> = self.records.keys().cloned().collect();
+        peers.sort();
+        peers
+    }
+
+    pub(crate) fn count_suppressed_notifications(&mut self, count: usize) {
+        self.suppressed_notifications += count;
+    }
+}
+
+impl DataSyncNotification {
+    /// Records of the notification, in the order of insertion.
+    pub(crate) fn records(&self) -> Vec<Record> {
+        let quorum_certificates = self
+            .highest_commit_certificate
+            .iter()
+            .chain(self.highest_quorum_certificate.iter())
+            .cloned()
+            .map(Record::QuorumCertificate);
+        let blocks = self
+            .previous_proposed_blocks
+            .iter()
+            .chain(self.proposed_block.iter())
+            .cloned()
+            .map(Record::Block);
+        let timeouts = self.timeouts.iter().cloned().map(Record::Timeout);
+        let votes = self.current_vote.iter().cloned().map(Record::Vote);
+        quorum_certificates
+            .chain(blocks)
+            .chain(timeouts)
+            .chain(votes)
+            .collect()
+    }
+
+    /// Strict verification: check the signatures of all the records before processing any.
+    pub(crate) fn verify(&self) -> Result<()> {
+        verify_records(&self.records())
+    }
+}
+
+impl DataSyncResponse {
+    /// Strict verification: check the signatures of all the records before inserting any.
+    pub(crate) fn verify(&self) -> Result<()> {
+        for (_, records) in &self.records {
+            verify_records(records)?;
+        }
+        Ok(())
+    }
+}
+
+fn verify_records(records: &[Record]) -> Result<()> {
+    for record in records {
+        RecordStoreState::check_signatures(record, record.digest())?;
+    }
+    Ok(())
+}
+
 impl NodeState {
     fn create_request_internal(&self) -> DataSyncRequest {
+        // Legacy nodes do not summarize their QCs.
+        let known_quorum_certificates = if self.data_sync_version() > LEGACY_DATA_SYNC_VERSION {
+            self.record_store().known_quorum_certificate_rounds()
+        } else {
+            BTreeSet::new()
+        };
+        let committed_blocks = if self.state_sync() {
+            Some(self.committed_blocks().len())
+        } else {
+            None
+        };
         DataSyncRequest {
+            version: self.data_sync_version(),
+            current_epoch: self.epoch_id(),
+            known_quorum_certificates,
+            committed_blocks,
+        }
+    }
+
+    /// Records are shared with the record store, so that the receivers of a notification share
+    /// the same copies of its blocks and QCs.
+    pub(crate) fn create_notification_internal(&self) -> DataSyncNotification {
+        let store = self.record_store_state();
+        // Pass the latest (non-empty) commit certificate across epochs.
+        let highest_commit_certificate = match store.shared_highest_commit_certificate() {
+            Some(hqc) => Some(hqc.clone()),
+            None => self
+                .epoch_id()
+                .previous()
+                .and_then(|previous_epoch| self.record_store_state_at(previous_epoch))
+                .and_then(|store| store.shared_highest_commit_certificate())
+                .cloned(),
+        };
+        let proposal = store.proposed_block(self.pacemaker());
+        let mut proposed_blocks: Vec<_> = match proposal {
+            // Do not reshare other leaders' proposals.
+            Some((hash, _, author)) if author == self.local_author() => store
+                .shared_round_chain(hash)
+                .into_iter()
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        };
+        let proposed_block = proposed_blocks.pop();
+        // Legacy nodes only share the last block of their proposals.
+        if self.data_sync_version() == LEGACY_DATA_SYNC_VERSION {
+            proposed_blocks.clear();
+        }
+        DataSyncNotification {
+            version: self.data_sync_version(),
             current_epoch: self.epoch_id(),
-            known_quorum_certificates: self.record_store().known_quorum_certificate_rounds(),
+            highest_commit_certificate,
+            highest_quorum_certificate: store.shared_highest_quorum_certificate().cloned(),
+            timeouts: store.timeouts(),
+            current_vote: store.current_vote(self.local_author()),
+            proposed_block,
+            previous_proposed_blocks: proposed_blocks,
         }
     }
 }
@@ -66,52 +475,38 @@ where
     type Response = DataSyncResponse;
 
     fn create_notification(&self) -> DataSyncNotification {
-        // Pass the latest (non-empty) commit certificate across epochs.
-        let highest_commit_certificate = match self.record_store().highest_commit_certificate() {
-            Some(hqc) => Some(hqc.clone()),
-            None => match self.epoch_id().previous() {
-                Some(previous_epoch) => self
-                    .record_store_at(previous_epoch)
-                    .expect("The record store of the previous epoch should exist.")
-                    .highest_commit_certificate()
-                    .cloned(),
-                None => None,
-            },
-        };
-        DataSyncNotification {
-            current_epoch: self.epoch_id(),
-            highest_commit_certificate,
-            highest_quorum_certificate: self.record_store().highest_quorum_certificate().cloned(),
-            timeouts: self.record_store().timeouts(),
-            current_vote: self
-                .record_store()
-                .current_vote(self.local_author())
-                .cloned(),
-            proposed_block: match self.record_store().proposed_block(self.pacemaker()) {
-                Some((hash, _, author)) => {
-                    // Do not reshare other leaders' proposals.
-                    if author == self.local_author() {
-                        Some(self.record_store().block(hash).unwrap().clone())
-                    } else {
-                        None
-                    }
-                }
-                None => None,
-            },
-        }
+        self.create_notification_internal()
     }
 
     fn handle_notification(
         &mut self,
-        notification: DataSyncNotification,
+        sender: Author,
+        notification: &DataSyncNotification,
         smr_context: &mut Context,
     ) -> Option<DataSyncRequest> {
+        let span = self.span();
+        let _entered = span.enter();
+        if self.strict_verification() {
+            if let Err(error) = notification.verify() {
+                self.reject_message(error);
+                return None;
+            }
+        }
         // Whether we should request more data because of a new epoch or missings records.
         let mut should_sync = false;
         // Note that malicious nodes can always lie to make us send a request, but they may as
         // well send us a lengthy and slow `DataSyncResponse` directly. (DoS prevention is out of
         // scope for this simulator.)
         should_sync |= notification.current_epoch > self.epoch_id();
+        if self.push_pull().is_some() || self.peer_knowledge().is_some() {
+            let records = notification.records();
+            // Measure duplicates to adapt the push fanout, if any.
+            if notification.current_epoch == self.epoch_id() {
+                self.observe_pushed_records(&records);
+            }
+            // Remember that the sender holds its records.
+            self.learn_peer_records(notification.current_epoch, sender, &records);
+        }
 
         if let Some(highest_commit_certificate) = &notification.highest_commit_certificate {
             // Try to insert the QC just in case.
@@ -120,10 +515,11 @@ where
                 Record::QuorumCertificate(highest_commit_certificate.clone()),
                 smr_context,
             );
+            let highest_commit_certificate_round =
+                self.record_store().highest_commit_certificate_round();
             should_sync |= (highest_commit_certificate.epoch_id > self.epoch_id())
                 || (highest_commit_certificate.epoch_id == self.epoch_id()
-                    && highest_commit_certificate.round
-                        > self.record_store().highest_committed_round() + 2);
+                    && highest_commit_certificate.round > highest_commit_certificate_round);
         }
         if let Some(highest_quorum_certificate) = &notification.highest_quorum_certificate {
             // Try to insert the QC.
@@ -138,26 +534,43 @@ where
                     && highest_quorum_certificate.round
                         > self.record_store().highest_quorum_certificate_round());
         }
-        // Try to insert the proposed block right away.
-        if let Some(block) = notification.proposed_block {
+        // Try to insert the proposed blocks right away. Legacy nodes ignore the chains of
+        // blocks, which they do not know of. Blocks that we already hold are skipped without
+        // hashing them.
+        let previous_proposed_blocks: &[Arc<Block>] =
+            if self.data_sync_version() > LEGACY_DATA_SYNC_VERSION {
+                &notification.previous_proposed_blocks
+            } else {
+                &[]
+            };
+        for block in previous_proposed_blocks
+            .iter()
+            .chain(notification.proposed_block.iter())
+        {
+            if !self.holds_block(notification.current_epoch, block) {
+                self.insert_network_record(
+                    notification.current_epoch,
+                    Record::Block(block.clone()),
+                    smr_context,
+                );
+            }
+        }
+        // Try to insert timeouts right away.
+        for timeout in &notification.timeouts {
             self.insert_network_record(
                 notification.current_epoch,
-                Record::Block(block),
+                Record::Timeout(timeout.clone()),
                 smr_context,
             );
         }
-        // Try to insert timeouts right away.
-        for timeout in notification.timeouts {
+        // Try to insert votes right away.
+        if let Some(vote) = &notification.current_vote {
             self.insert_network_record(
                 notification.current_epoch,
-                Record::Timeout(timeout),
+                Record::Vote(vote.clone()),
                 smr_context,
             );
         }
-        // Try to insert votes right away.
-        if let Some(vote) = notification.current_vote {
-            self.insert_network_record(notification.current_epoch, Record::Vote(vote), smr_context);
-        }
         // Create a follow-up request if needed.
         if should_sync {
             Some(self.create_request_internal())
@@ -170,33 +583,118 @@ where
         self.create_request_internal()
     }
 
-    fn handle_request(&self, request: DataSyncRequest) -> DataSyncResponse {
+    fn handle_request(&self, request: &DataSyncRequest) -> DataSyncResponse {
+        let span = self.span();
+        let _entered = span.enter();
+        // Use the features of the older version of the two nodes.
+        let version = min(request.version, self.data_sync_version());
+        let known_quorum_certificates = if version > LEGACY_DATA_SYNC_VERSION {
+            request.known_quorum_certificates.clone()
+        } else {
+            BTreeSet::new()
+        };
+        // Send our checkpoint if it lets the requester skip epochs.
+        let checkpoint = match (request.committed_blocks, self.state_checkpoint()) {
+            (Some(known_blocks), Some(checkpoint))
+                if checkpoint.epoch_id > request.current_epoch
+                    && known_blocks <= checkpoint.committed_blocks =>
+            {
+                Some(checkpoint.clone())
+            }
+            _ => None,
+        };
         let mut records = Vec::new();
-        if let Some(store) = self.record_store_at(request.current_epoch) {
-            records.push((
-                request.current_epoch,
-                store.unknown_records(request.known_quorum_certificates),
-            ));
-        }
-        for i in (request.current_epoch.0 + 1)..(self.epoch_id().0 + 1) {
+        let (first_epoch, checkpoint_blocks) = match &checkpoint {
+            Some(checkpoint) => (
+                checkpoint.epoch_id,
+                self.committed_blocks()
+                    [request.committed_blocks.unwrap_or(0)..checkpoint.committed_blocks]
+                    .to_vec(),
+            ),
+            None => {
+                if let Some(store) = self.record_store_at(request.current_epoch) {
+                    let unknown_records = store.unknown_record_refs(known_quorum_certificates);
+                    records.push((
+                        request.current_epoch,
+                        unknown_records.iter().map(RecordRef::to_record).collect(),
+                    ));
+                }
+                (EpochId(request.current_epoch.0 + 1), Vec::new())
+            }
+        };
+        for i in first_epoch.0..(self.epoch_id().0 + 1) {
             let epoch_id = EpochId(i);
-            let store = self
-                .record_store_at(epoch_id)
-                .expect("All record stores up to the current epoch should exist.");
-            records.push((epoch_id, store.unknown_records(BTreeSet::new())));
+            match self.record_store_at(epoch_id) {
+                Some(store) => records.push((epoch_id, store.unknown_records(BTreeSet::new()))),
+                None => {
+                    // Records of later epochs cannot be inserted without this one.
+                    tracing::warn!(epoch = epoch_id.0, "Missing record store");
+                    break;
+                }
+            }
         }
         DataSyncResponse {
+            version,
             current_epoch: self.epoch_id(),
             records,
+            checkpoint,
+            checkpoint_blocks,
         }
     }
 
     fn handle_response(
         &mut self,
+        sender: Author,
         response: DataSyncResponse,
         smr_context: &mut Context,
         clock: NodeTime,
     ) {
+        let span = self.span();
+        let _entered = span.enter();
+        let supported_version = self.data_sync_version();
+        if response.version < LEGACY_DATA_SYNC_VERSION || response.version > supported_version {
+            // The sender did not negotiate a version that we support.
+            self.report_error(NodeError::UnsupportedVersion {
+                version: response.version,
+                supported_version,
+            });
+            return;
+        }
+        if self.strict_verification() {
+            if let Err(error) = response.verify() {
+                self.reject_message(error);
+                return;
+            }
+        }
+        if let Some(checkpoint) = response.checkpoint {
+            // Skip the epochs up to the checkpoint unless we reached it in the meantime.
+            if checkpoint.epoch_id > self.epoch_id() {
+                let epoch_id = checkpoint.epoch_id;
+                if smr_context.read_epoch_id(&checkpoint.state) != epoch_id {
+                    self.report_error(NodeError::InvalidCheckpoint {
+                        epoch_id,
+                        reason: "The epoch does not match the state".to_string(),
+                    });
+                    return;
+                }
+                let blocks = response.checkpoint_blocks;
+                let (checkpoint, blocks) = match self.endorse_checkpoint(sender, checkpoint, blocks)
+                {
+                    Some(endorsed) => endorsed,
+                    // The records start at the checkpoint, so they must wait as well.
+                    None => return,
+                };
+                if let Err(error) = self.install_checkpoint(checkpoint, blocks, clock, smr_context)
+                {
+                    self.report_error(NodeError::InvalidCheckpoint {
+                        epoch_id,
+                        reason: error.to_string(),
+                    });
+                    return;
+                }
+                self.update_tracker(clock);
+            }
+        }
         let num_records = response.records.len();
         // Insert all the records in order.
         // Process the commits so that new epochs are created along the way.
@@ -207,19 +705,101 @@ where
                 continue;
             }
             if epoch_id > self.epoch_id() {
-                // This should not happen. Abort.
+                // This should not happen. Skip the rest of the response.
+                let current_epoch = self.epoch_id();
+                self.report_error(NodeError::UnexpectedEpoch {
+                    epoch_id,
+                    current_epoch,
+                });
                 break;
             }
-            for record in records {
-                self.insert_network_record(epoch_id, record, smr_context);
-            }
+            self.insert_network_records(epoch_id, records, smr_context);
             if i == num_records - 1 {
                 // Leave the latest epoch for the main handler to process.
                 break;
             }
             // Deliver commits and start the next epochs.
-            self.process_commits(smr_context);
+            self.process_commits(clock, smr_context);
             self.update_tracker(clock);
         }
     }
 }
+
+impl CountRecords for DataSyncNotification {
+    fn record_counts(&self) -> RecordCounts {
+        let quorum_certificates: Vec<_> = self
+            .highest_commit_certificate
+            .iter()
+            .chain(self.highest_quorum_certificate.iter())
+            .collect();
+        let proposed_blocks: Vec<_> = self
+            .previous_proposed_blocks
+            .iter()
+            .chain(self.proposed_block.iter())
+            .collect();
+        let blocks = proposed_blocks.len();
+        let votes = self.current_vote.iter().count();
+        RecordCounts {
+            blocks,
+            votes,
+            timeouts: self.timeouts.len(),
+            quorum_certificates: quorum_certificates.len(),
+            payload_bytes: proposed_blocks
+                .iter()
+                .map(|x| x.command.payload_bytes())
+                .sum(),
+            signature_bytes: (blocks + votes + self.timeouts.len()) * Signature::num_bytes()
+                + quorum_certificates
+                    .iter()
+                    .map(|qc| qc.signature_bytes())
+                    .sum::<usize>(),
+        }
+    }
+}
+
+impl CountRecords for DataSyncRequest {
+    fn record_counts(&self) -> RecordCounts {
+        RecordCounts::default()
+    }
+}
+
+impl CountRecords for DataSyncResponse {
+    fn record_counts(&self) -> RecordCounts {
+        let mut counts = RecordCounts::default();
+        if let Some(checkpoint) = &self.checkpoint {
+            counts.payload_bytes += checkpoint.snapshot.len();
+        }
+        for record in self.records.iter().flat_map(|(_, records)| records) {
+            counts.signature_bytes += record.signature_bytes();
+            match record {
+                Record::Block(block) => {
+                    counts.blocks += 1;
+                    counts.payload_bytes += block.command.payload_bytes();
+                }
+                Record::Vote(_) => counts.votes += 1,
+                Record::QuorumCertificate(_) => counts.quorum_certificates += 1,
+                Record::Timeout(_) => counts.timeouts += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// Messages travel as JSON, e.g. for the corruption of messages in transit.
+macro_rules! json_wire_format {
+    ($message:ty) => {
+        impl WireFormat for $message {
+            fn to_wire(&self) -> Vec<u8> {
+                serde_json::to_vec(self).expect("Messages are serializable")
+            }
+
+            fn from_wire(bytes: &[u8]) -> Result<Self> {
+                Ok(serde_json::from_slice(bytes)?)
+            }
+        }
+    };
+}
+
+json_wire_format!(DataSyncNotification);
+json_wire_format!(DataSyncRequest);
+json_wire_format!(DataSyncResponse);
@@ -0,0 +1,163 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Decisions made by a node, with their reasons, for post-mortem analysis.
+//!
+//! Unlike the event log of the simulator, which records what happened on the network, the
+//! decision log of a node records why the node voted or not, timed out, or proposed a block.
+
+use super::*;
+use serde::Serialize;
+use std::io::Write;
+#[cfg(feature = "simulation")]
+use std::{fs, path::Path};
+
+#[cfg(test)]
+#[path = "unit_tests/decisions_tests.rs"]
+mod decisions_tests;
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Decision {
+    /// Started a new round, led by `leader` if known.
+    EnterRound {
+        round: Round,
+        leader: Option<Author>,
+    },
+    /// Proposed a block extending the QC of `previous_round`.
+    Propose { round: Round, previous_round: Round },
+    /// Waited for more transactions before proposing.
+    WaitForBatch { round: Round, until: NodeTime },
+    /// Voted for the block of `proposer`, which extends the QC of `previous_round`.
+    Vote {
+        round: Round,
+        proposer: Author,
+        previous_round: Round,
+    },
+    /// Did not vote for the block of `proposer`.
+    RefuseVote {
+        round: Round,
+        proposer: Author,
+        reason: VoteRefusal,
+    },
+    /// Waited for the execution of the block before voting.
+    WaitForExecution { round: Round, until: NodeTime },
+    /// Timed out after the deadline of the round passed without a QC at this round.
+    Timeout {
+        round: Round,
+        deadline: NodeTime,
+        highest_quorum_certificate_round: Round,
+    },
+    /// Waited for the votes of all validators before creating a QC.
+    WaitForVotes { round: Round, until: NodeTime },
+    /// Created a QC from the votes on our proposal.
+    CreateQuorumCertificate { round: Round },
+    /// Delivered the commit of a block.
+    Commit { round: Round, fast_path: bool },
+    /// Switched to a new epoch after a commit.
+    NewEpoch { epoch_id: EpochId },
+    /// Restarted after a crash with the given voting constraints.
+    Recover {
+        latest_voted_round: Round,
+        locked_round: Round,
+    },
+}
+
+impl Decision {
+    /// Kind of the decision, as in the `kind` field of decision logs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Decision::EnterRound { .. } => "enter-round",
+            Decision::Propose { .. } => "propose",
+            Decision::WaitForBatch { .. } => "wait-for-batch",
+            Decision::Vote { .. } => "vote",
+            Decision::RefuseVote { .. } => "refuse-vote",
+            Decision::WaitForExecution { .. } => "wait-for-execution",
+            Decision::Timeout { .. } => "timeout",
+            Decision::WaitForVotes { .. } => "wait-for-votes",
+            Decision::CreateQuorumCertificate { .. } => "create-quorum-certificate",
+            Decision::Commit { .. } => "commit",
+            Decision::NewEpoch { .. } => "new-epoch",
+            Decision::Recover { .. } => "recover",
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum VoteRefusal {
+    /// We already created a timeout at this round.
+    TimedOut,
+    /// We already voted at this round or a later one.
+    AlreadyVoted { latest_voted_round: Round },
+    /// The block does not extend a QC at or above our locked round.
+    Locked {
+        previous_round: Round,
+        locked_round: Round,
+    },
+    /// The state after the block could not be computed.
+    UnknownState,
+    /// The payload of the block failed the validation of the SMR context.
+    InvalidPayload,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+pub struct DecisionRecord {
+    /// Local time of the decision.
+    pub time: NodeTime,
+    pub epoch_id: EpochId,
+    #[serde(flatten)]
+    pub decision: Decision,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct DecisionLog {
+    records: Vec<DecisionRecord>,
+}
+
+impl DecisionLog {
+    pub fn new() -> Self {
+        DecisionLog::default()
+    }
+
+    /// Record a decision, unless it repeats the previous one.
+    pub fn record(&mut self, time: NodeTime, epoch_id: EpochId, decision: Decision) {
+        if let Some(last) = self.records.last() {
+            if last.epoch_id == epoch_id && last.decision == decision {
+                return;
+            }
+        }
+        self.records.push(DecisionRecord {
+            time,
+            epoch_id,
+            decision,
+        });
+    }
+
+    pub fn records(&self) -> &[DecisionRecord] {
+        &self.records
+    }
+
+    /// Write the records as JSON, one record per line.
+    pub fn write<W: Write>(&self, mut output: W) -> Result<()> {
+        for record in &self.records {
+            writeln!(output, "{}", serde_json::to_string(record)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the decision log of each node to `DIRECTORY/node_AUTHOR.jsonl`. Nodes that did not
+/// record their decisions are skipped.
+#[cfg(feature = "simulation")]
+pub fn write_decision_logs<P: AsRef<Path>>(sim: &Simulator, directory: P) -> Result<()> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    for (index, node) in sim.simulated_nodes().iter().enumerate() {
+        if let Some(log) = node.node().decision_log() {
+            let path = directory.join(format!("node_{}.jsonl", index));
+            log.write(fs::File::create(path)?)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Small deployments of LibraBFT nodes over TCP, with the `tokio` feature.
+//!
+//! Nodes are configured by the same parameters as simulations. Network settings such as delays
+//! and losses are ignored, and durations are in milliseconds unless a time scale is given.
+
+use super::*;
+use bft_simulator_runtime::{
+    clock::{RealTimeClock, ScaledClock, WallClock},
+    network,
+};
+use std::{net::SocketAddr, thread, time::Duration as StdDuration};
+
+#[cfg(test)]
+#[path = "unit_tests/deployment_tests.rs"]
+mod deployment_tests;
+
+/// Run the node `author` for `duration` and return the blocks that it committed. Metrics of the
+/// node are served on `metrics_address`, if any. With a `time_scale`, the local clock of the node
+/// counts this many units of time per millisecond.
+pub fn run_deployed_node(
+    parameters: &SimulationParameters,
+    author: Author,
+    addresses: Vec<SocketAddr>,
+    metrics_address: Option<SocketAddr>,
+    time_scale: Option<f64>,
+    duration: StdDuration,
+) -> Result<Vec<CommittedBlock>> {
+    ensure!(
+        addresses.len() == parameters.nodes,
+        "Expected {} addresses, not {}",
+        parameters.nodes,
+        addresses.len()
+    );
+    #[cfg(feature = "ed25519")]
+    bft_simulator_runtime::ed25519::generate_keys(parameters.nodes, parameters.seed);
+    let context = make_context(parameters, author);
+    let node = make_node(parameters, author, &context, NodeTime(0));
+    let clock: Box<RealTimeClock> = match time_scale {
+        Some(speed) => Box::new(ScaledClock::new(speed)?),
+        None => Box::new(WallClock::new()),
+    };
+    let (node, _) = network::run_node(
+        author,
+        node,
+        context,
+        clock,
+        addresses,
+        metrics_address,
+        duration,
+    )?;
+    Ok(node.committed_blocks().to_vec())
+}
+
+/// Run all the nodes on the given addresses, one thread per node, and return the blocks
+/// committed by each node.
+pub fn run_local_deployment(
+    parameters: &SimulationParameters,
+    addresses: Vec<SocketAddr>,
+    duration: StdDuration,
+) -> Result<Vec<Vec<CommittedBlock>>> {
+    let handles: Vec<_> = (0..addresses.len())
+        .map(|index| {
+            let parameters = parameters.clone();
+            let addresses = addresses.clone();
+            thread::spawn(move || {
+                run_deployed_node(&parameters, Author(index), addresses, None, None, duration)
+            })
+        })
+        .collect();
+    let mut blocks = Vec::new();
+    for handle in handles {
+        match handle.join() {
+            Ok(result) => blocks.push(result?),
+            Err(_) => bail!("A node thread panicked"),
+        }
+    }
+    Ok(blocks)
+}
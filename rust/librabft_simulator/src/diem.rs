@@ -0,0 +1,512 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conversions between records and the consensus types of Diem and Aptos.
+//!
+//! The types of this module follow the serde layout of `Block`, `Vote`, `QuorumCert`, and
+//! `TimeoutCertificate` in the `consensus-types` crate of Diem, so that the block trees of the
+//! simulated nodes can be fed to their verification tooling as JSON, and their traces read back
+//! as records. Hashes, states, and authors are 64-bit values here: they become the last 8 bytes
+//! of 32-byte hex values, and longer values are truncated when converted back. Signatures are
+//! copied as they are, so they sign the digests of this crate rather than those of Diem.
+//! Only QCs with individual signatures have a Diem counterpart. Blocks chained in the same
+//! round carry the hash of the previous block of the round in `previous_block_hash`, which Diem
+//! does not have.
+
+use super::*;
+use base_types::*;
+use node::NodeState;
+use record::*;
+use record_store::{RecordStore, RecordStoreState};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+#[cfg(test)]
+#[path = "unit_tests/diem_tests.rs"]
+mod diem_tests;
+
+/// A 32-byte `HashValue`, in hex.
+fn hash_value(value: u64) -> String {
+    format!("{:064x}", value)
+}
+
+/// Diem timestamps count microseconds, whereas node times count milliseconds.
+fn timestamp_usecs(time: NodeTime) -> u64 {
+    Duration::from_millis(std::cmp::max(time.0, 0)).as_micros() as u64
+}
+
+fn node_time(timestamp_usecs: u64) -> NodeTime {
+    NodeTime(Duration::from_micros(timestamp_usecs as i64).as_millis())
+}
+
+/// An `AccountAddress`, in hex. Uses the 32-byte addresses of Aptos.
+fn account_address(author: Author) -> String {
+    format!("{:064x}", author.0)
+}
+
+fn from_hex(value: &str) -> Result<u64> {
+    ensure!(
+        !value.is_empty() && value.chars().all(|c| c.is_ascii_hexdigit()),
+        "Invalid hex value: {:?}",
+        value
+    );
+    let start = value.len().saturating_sub(16);
+    Ok(u64::from_str_radix(&value[start..], 16)?)
+}
+
+fn signature_hex(signature: &Signature) -> String {
+    signature
+        .to_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn signature_from_hex(value: &str) -> Result<Signature> {
+    ensure!(
+        value.len() % 2 == 0 && value.is_ascii(),
+        "Invalid signature: {:?}",
+        value
+    );
+    let bytes = (0..value.len())
+        .step_by(2)
+        .map(|index| Ok(u8::from_str_radix(&value[index..index + 2], 16)?))
+        .collect::<Result<Vec<_>>>()?;
+    Signature::from_bytes(&bytes)
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct BlockInfo {
+    pub epoch: u64,
+    pub round: u64,
+    pub id: String,
+    pub executed_state_id: String,
+    pub version: u64,
+    /// Proposal time, in microseconds.
+    pub timestamp_usecs: u64,
+    #[serde(default)]
+    pub next_epoch_state: Option<serde_json::Value>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct VoteData {
+    pub proposed: BlockInfo,
+    pub parent: BlockInfo,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct LedgerInfo {
+    /// The committed block, if any, or an empty `BlockInfo`.
+    pub commit_info: BlockInfo,
+    pub consensus_data_hash: String,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct LedgerInfoWithV0 {
+    pub ledger_info: LedgerInfo,
+    pub signatures: BTreeMap<String, String>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub enum LedgerInfoWithSignatures {
+    V0(LedgerInfoWithV0),
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct QuorumCert {
+    pub vote_data: VoteData,
+    pub signed_ledger_info: LedgerInfoWithSignatures,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct Vote {
+    pub vote_data: VoteData,
+    pub author: String,
+    pub ledger_info: LedgerInfo,
+    pub signature: String,
+    #[serde(default)]
+    pub timeout_signature: Option<String>,
+}
+
+/// Blocks of the simulator are always proposals, whose payload is a command.
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub enum BlockType {
+    Proposal { payload: Command, author: String },
+    NilBlock,
+    Genesis,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct BlockData {
+    pub epoch: u64,
+    pub round: u64,
+    pub timestamp_usecs: u64,
+    pub quorum_cert: QuorumCert,
+    pub block_type: BlockType,
+    /// The previous block of the round, if the block is chained on it.
+    #[serde(default)]
+    pub previous_block_hash: Option<String>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct Block {
+    pub id: String,
+    pub block_data: BlockData,
+    pub signature: Option<String>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct Timeout {
+    pub epoch: u64,
+    pub round: u64,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct TimeoutCertificate {
+    pub timeout: Timeout,
+    pub signatures: BTreeMap<String, String>,
+}
+
+/// The block tree of a node, in all the epochs that it went through.
+#[derive(Eq, PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DiemTrace {
+    pub blocks: Vec<Block>,
+    pub quorum_certs: Vec<QuorumCert>,
+    pub timeout_certificates: Vec<TimeoutCertificate>,
+}
+
+impl BlockInfo {
+    /// The `BlockInfo` of ledger infos that commit nothing.
+    fn empty() -> Self {
+        BlockInfo {
+            epoch: 0,
+            round: 0,
+            id: hash_value(0),
+            executed_state_id: hash_value(0),
+            version: 0,
+            timestamp_usecs: 0,
+            next_epoch_state: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == BlockInfo::empty()
+    }
+}
+
+/// Converts the records of a record store, whose blocks and QCs provide the context missing
+/// from individual records, such as the parent of a block.
+pub struct DiemAdapter<'a> {
+    store: &'a RecordStoreState,
+}
+
+impl<'a> DiemAdapter<'a> {
+    /// An adapter for the records of the current epoch of a node.
+    pub fn new(node: &'a NodeState) -> Self {
+        let (_, store) = node.record_stores().pop().unwrap();
+        DiemAdapter { store }
+    }
+
+    /// The `BlockInfo` of the block certified by a QC, or of the genesis block of the epoch for
+    /// the initial QC.
+    fn certified_info(&self, qc_hash: QuorumCertificateHash) -> BlockInfo {
+        match self.store.quorum_certificate(qc_hash) {
+            Some(qc) => self.block_info(qc.certified_block_hash, &qc.state),
+            None => {
+                let (initial_hash, initial_state) = self.store.initial_quorum_certificate();
+                BlockInfo {
+                    epoch: self.store.epoch_id().0 as u64,
+                    round: 0,
+                    id: hash_value(initial_hash.0),
+                    executed_state_id: hash_value(initial_state.0),
+                    version: 0,
+                    timestamp_usecs: 0,
+                    next_epoch_state: None,
+                }
+            }
+        }
+    }
+
+    fn block_info(&self, block_hash: BlockHash, state: &State) -> BlockInfo {
+        let block = self.store.block(block_hash);
+        BlockInfo {
+            epoch: self.store.epoch_id().0 as u64,
+            round: block.map_or(0, |block| block.round.0 as u64),
+            id: hash_value(block_hash.0),
+            executed_state_id: hash_value(state.0),
+            version: 0,
+            timestamp_usecs: block.map_or(0, |block| timestamp_usecs(block.time)),
+            next_epoch_state: None,
+        }
+    }
+
+    /// The proposed and the parent block of a vote or a QC.
+    fn vote_data(&self, block_hash: BlockHash, state: &State) -> VoteData {
+        let parent = match self.store.block(block_hash) {
+            Some(block) => self.certified_info(block.previous_quorum_certificate_hash),
+            None => BlockInfo::empty(),
+        };
+        VoteData {
+            proposed: self.block_info(block_hash, state),
+            parent,
+        }
+    }
+
+    /// The ledger info committing the ancestor of `block_hash` with state `committed_state`.
+    fn ledger_info(&self, block_hash: BlockHash, committed_state: &Option<State>) -> LedgerInfo {
+        let mut commit_info = BlockInfo::empty();
+        if let Some(committed_state) = committed_state {
+            let mut current = self.store.block(block_hash);
+            while let Some(block) = current {
+                let qc = match self
+                    .store
+                    .quorum_certificate(block.previous_quorum_certificate_hash)
+                {
+                    Some(qc) => qc,
+                    None => break,
+                };
+                if qc.state == *committed_state {
+                    commit_info = self.block_info(qc.certified_block_hash, &qc.state);
+                    break;
+                }
+                current = self.store.block(qc.certified_block_hash);
+            }
+        }
+        LedgerInfo {
+            consensus_data_hash: hash_value(hashing::digest(&(block_hash, committed_state))),
+            commit_info,
+        }
+    }
+
+    pub fn quorum_cert(&self, qc: &QuorumCertificate) -> Result<QuorumCert> {
+        let signatures = match &*qc.votes {
+            MultiSignature::Individual(signatures) => signatures
+                .iter()
+                .map(|(author, signature)| (account_address(*author), signature_hex(signature)))
+                .collect(),
+            _ => bail!("Only QCs with individual signatures are supported by Diem"),
+        };
+        Ok(QuorumCert {
+            vote_data: self.vote_data(qc.certified_block_hash, &qc.state),
+            signed_ledger_info: LedgerInfoWithSignatures::V0(LedgerInfoWithV0 {
+                ledger_info: self.ledger_info(qc.certified_block_hash, &qc.committed_state),
+                signatures,
+            }),
+        })
+    }
+
+    /// The QC that a block extends. The initial QC becomes a QC of the genesis block.
+    fn previous_quorum_cert(&self, qc_hash: QuorumCertificateHash) -> Result<QuorumCert> {
+        match self.store.quorum_certificate(qc_hash) {
+            Some(qc) => self.quorum_cert(qc),
+            None => {
+                let genesis = self.certified_info(qc_hash);
+                Ok(QuorumCert {
+                    vote_data: VoteData {
+                        proposed: genesis.clone(),
+                        parent: genesis.clone(),
+                    },
+                    signed_ledger_info: LedgerInfoWithSignatures::V0(LedgerInfoWithV0 {
+                        ledger_info: LedgerInfo {
+                            commit_info: genesis,
+                            consensus_data_hash: hash_value(0),
+                        },
+                        signatures: BTreeMap::new(),
+                    }),
+                })
+            }
+        }
+    }
+
+    pub fn block(&self, block_hash: BlockHash, block: &record::Block) -> Result<Block> {
+        Ok(Block {
+            id: hash_value(block_hash.0),
+            block_data: BlockData {
+                epoch: self.store.epoch_id().0 as u64,
+                round: block.round.0 as u64,
+                timestamp_usecs: timestamp_usecs(block.time),
+                quorum_cert: self.previous_quorum_cert(block.previous_quorum_certificate_hash)?,
+                block_type: BlockType::Proposal {
+                    payload: block.command.clone(),
+                    author: account_address(block.author),
+                },
+                previous_block_hash: block.previous_block_hash.map(|hash| hash_value(hash.0)),
+            },
+            signature: Some(signature_hex(&block.signature)),
+        })
+    }
+
+    pub fn vote(&self, vote: &record::Vote) -> Vote {
+        Vote {
+            vote_data: self.vote_data(vote.certified_block_hash, &vote.state),
+            author: account_address(vote.author),
+            ledger_info: self.ledger_info(vote.certified_block_hash, &vote.committed_state),
+            signature: signature_hex(&vote.signature),
+            timeout_signature: None,
+        }
+    }
+
+    /// The TC formed by timeouts at the same round.
+    pub fn timeout_certificate(&self, timeouts: &[record::Timeout]) -> TimeoutCertificate {
+        TimeoutCertificate {
+            timeout: Timeout {
+                epoch: self.store.epoch_id().0 as u64,
+                round: timeouts.first().map_or(0, |timeout| timeout.round.0 as u64),
+            },
+            signatures: timeouts
+                .iter()
+                .map(|timeout| {
+                    (
+                        account_address(timeout.author),
+                        signature_hex(&timeout.signature),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The verified blocks and QCs of a node, and its highest TC in each epoch.
+pub fn export_node(node: &NodeState) -> Result<DiemTrace> {
+    let mut trace = DiemTrace::default();
+    for (_, store) in node.record_stores() {
+        let adapter = DiemAdapter { store };
+        for (hash, block) in store.verified_blocks() {
+            trace.blocks.push(adapter.block(hash, block)?);
+        }
+        for (_, qc) in store.verified_quorum_certificates() {
+            trace.quorum_certs.push(adapter.quorum_cert(qc)?);
+        }
+        if let Some(timeouts) = store.highest_timeout_certificate() {
+            trace
+                .timeout_certificates
+                .push(adapter.timeout_certificate(timeouts));
+        }
+    }
+    Ok(trace)
+}
+
+/// Write the trace of each node to `directory`, as `node_I.json`.
+pub fn write_diem_traces<P: AsRef<Path>>(sim: &Simulator, directory: P) -> Result<()> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    for (index, node) in sim.simulated_nodes().iter().enumerate() {
+        let path = directory.join(format!("node_{}.json", index));
+        fs::write(
+            path,
+            serde_json::to_string_pretty(&export_node(node.node())?)?,
+        )?;
+    }
+    Ok(())
+}
+
+fn author_from_address(address: &str) -> Result<Author> {
+    Ok(Author(from_hex(address)? as usize))
+}
+
+fn committed_state(ledger_info: &LedgerInfo) -> Result<Option<State>> {
+    if ledger_info.commit_info.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(State(from_hex(
+        &ledger_info.commit_info.executed_state_id,
+    )?)))
+}
+
+fn ledger_info_with_signatures(qc: &QuorumCert) -> &LedgerInfoWithV0 {
+    match &qc.signed_ledger_info {
+        LedgerInfoWithSignatures::V0(ledger_info) => ledger_info,
+    }
+}
+
+/// Read a QC. Diem QCs have no author: the author of the certified block is used, as when votes
+/// are sent to proposers.
+fn quorum_certificate(qc: &QuorumCert, authors: &HashMap<u64, Author>) -> Result<Record> {
+    let proposed = &qc.vote_data.proposed;
+    let certified_block_hash = from_hex(&proposed.id)?;
+    let author = match authors.get(&certified_block_hash) {
+        Some(author) => *author,
+        None => bail!("Unknown certified block {}", proposed.id),
+    };
+    let ledger_info = ledger_info_with_signatures(qc);
+    let votes = ledger_info
+        .signatures
+        .iter()
+        .map(|(address, signature)| {
+            Ok((
+                author_from_address(address)?,
+                signature_from_hex(signature)?,
+            ))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Record::make_quorum_certificate(
+        EpochId(proposed.epoch as usize),
+        Round(proposed.round as usize),
+        BlockHash(certified_block_hash),
+        State(from_hex(&proposed.executed_state_id)?),
+        MultiSignature::Individual(votes),
+        committed_state(&ledger_info.ledger_info)?,
+        author,
+    ))
+}
+
+/// Read the blocks and QCs of a trace, e.g. produced by Diem, as records. QCs of genesis blocks
+/// become the initial QC of their epoch, and TCs are ignored since Diem timeouts do not carry the
+/// highest certified round. Blocks and QCs are signed again by their authors.
+pub fn import_records(trace: &DiemTrace) -> Result<Vec<Record>> {
+    let mut authors = HashMap::new();
+    for block in &trace.blocks {
+        match &block.block_data.block_type {
+            BlockType::Proposal { author, .. } => {
+                authors.insert(from_hex(&block.id)?, author_from_address(author)?);
+            }
+            _ => bail!("Only proposals are supported, not {:?}", block.block_data),
+        }
+    }
+    let mut records = Vec::new();
+    for block in &trace.blocks {
+        let quorum_cert = &block.block_data.quorum_cert;
+        let previous_quorum_certificate_hash = if quorum_cert.vote_data.proposed.round == 0 {
+            QuorumCertificateHash(from_hex(&quorum_cert.vote_data.proposed.id)?)
+        } else {
+            QuorumCertificateHash(quorum_certificate(quorum_cert, &authors)?.digest())
+        };
+        let (command, author) = match &block.block_data.block_type {
+            BlockType::Proposal { payload, author } => (payload.clone(), author),
+            _ => unreachable!(),
+        };
+        let previous_block_hash = match &block.block_data.previous_block_hash {
+            Some(hash) => Some(BlockHash(from_hex(hash)?)),
+            None => None,
+        };
+        records.push(Record::make_chained_block(
+            command,
+            node_time(block.block_data.timestamp_usecs),
+            previous_quorum_certificate_hash,
+            previous_block_hash,
+            Round(block.block_data.round as usize),
+            author_from_address(author)?,
+        ));
+    }
+    for qc in &trace.quorum_certs {
+        records.push(quorum_certificate(qc, &authors)?);
+    }
+    Ok(records)
+}
+
+/// Read a vote, e.g. produced by Diem.
+pub fn import_vote(vote: &Vote) -> Result<Record> {
+    let proposed = &vote.vote_data.proposed;
+    Ok(Record::make_vote(
+        EpochId(proposed.epoch as usize),
+        Round(proposed.round as usize),
+        BlockHash(from_hex(&proposed.id)?),
+        State(from_hex(&proposed.executed_state_id)?),
+        author_from_address(&vote.author)?,
+        committed_state(&vote.ledger_info)?,
+    ))
+}
@@ -0,0 +1,82 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Errors of nodes on unexpected inputs or states.
+//!
+//! Nodes skip the faulty input and keep running, so that a single malformed record does not
+//! abort a long experiment. Errors are kept by the node until the simulator takes them with
+//! `NodeErrors::take_errors`, then logged and counted in the metrics of the node.
+
+use super::*;
+use data_sync::LEGACY_DATA_SYNC_VERSION;
+use std::fmt;
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum NodeError {
+    /// A verified record could not be inserted, e.g. because executing its block failed.
+    RecordInsertion { epoch_id: EpochId, reason: String },
+    /// A data-sync response contained records of an epoch that we have not reached.
+    UnexpectedEpoch {
+        epoch_id: EpochId,
+        current_epoch: EpochId,
+    },
+    /// A data-sync response used a version of the protocol that we do not support.
+    UnsupportedVersion {
+        version: u32,
+        supported_version: u32,
+    },
+    /// A message failed strict verification, e.g. because it was damaged in transit.
+    InvalidMessage { reason: String },
+    /// The active round was not above the round of the highest commit certificate.
+    InvalidRound {
+        round: Round,
+        highest_commit_certificate_round: Round,
+    },
+    /// A state checkpoint from a data-sync response could not be installed.
+    InvalidCheckpoint { epoch_id: EpochId, reason: String },
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeError::RecordInsertion { epoch_id, reason } => {
+                write!(f, "Failed to insert a record at {:?}: {}", epoch_id, reason)
+            }
+            NodeError::UnexpectedEpoch {
+                epoch_id,
+                current_epoch,
+            } => write!(
+                f,
+                "Received records of {:?} while the current epoch is {:?}",
+                epoch_id, current_epoch
+            ),
+            NodeError::UnsupportedVersion {
+                version,
+                supported_version,
+            } => write!(
+                f,
+                "Received a data-sync response of version {} while we support versions {} to {}",
+                version, LEGACY_DATA_SYNC_VERSION, supported_version
+            ),
+            NodeError::InvalidMessage { reason } => {
+                write!(f, "Rejected a message on verification: {}", reason)
+            }
+            NodeError::InvalidRound {
+                round,
+                highest_commit_certificate_round,
+            } => write!(
+                f,
+                "Active round {:?} is not higher than the round of the highest commit \
+                 certificate {:?}",
+                round, highest_commit_certificate_round
+            ),
+            NodeError::InvalidCheckpoint { epoch_id, reason } => write!(
+                f,
+                "Failed to install a state checkpoint at {:?}: {}",
+                epoch_id, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NodeError {}
@@ -0,0 +1,223 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Suspicion levels of the other validators, from the times at which a node hears from them.
+//!
+//! A node hears from a peer when it receives any message from it, with the sender given by the
+//! network. Suspicions are purely local: they decide which peers the pacemaker synchronizes
+//! with, besides the leader, and are counted in the metrics. Leaders are elected by a fixed
+//! rotation that all the nodes must agree on, so suspicions never change them.
+
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    str::FromStr,
+};
+
+#[cfg(test)]
+#[path = "unit_tests/failure_detector_tests.rs"]
+mod failure_detector_tests;
+
+/// How suspicion levels are computed, and from which level peers are suspected.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum FailureDetectorPolicy {
+    /// The time since we last heard from a peer, as a fraction of `timeout`. Peers are
+    /// suspected from 1.
+    Heartbeat { timeout: Duration },
+    /// The phi of the accrual failure detector, assuming exponentially distributed intervals
+    /// between messages, with the mean of the `window` latest intervals, and at least
+    /// `min_interval`. Peers are suspected from `threshold`.
+    PhiAccrual {
+        threshold: f64,
+        window: usize,
+        min_interval: Duration,
+    },
+}
+
+/// Times at which we heard from the other validators, and the peers suspected so far.
+#[derive(Debug)]
+pub struct FailureDetector {
+    policy: FailureDetectorPolicy,
+    /// Time of the first update, which counts as the latest message of silent peers.
+    start: Option<NodeTime>,
+    /// Peers heard from since the latest update.
+    pending: BTreeSet<Author>,
+    /// Time of the latest message of each peer, and the latest intervals between messages.
+    peers: BTreeMap<Author, (NodeTime, VecDeque<Duration>)>,
+    /// Peers suspected at the latest update.
+    suspected: BTreeSet<Author>,
+    /// Number of times that a peer became suspected, for monitoring purposes.
+    suspicions: usize,
+}
+
+impl FailureDetectorPolicy {
+    pub fn check(&self) -> Result<()> {
+        match self {
+            FailureDetectorPolicy::Heartbeat { timeout } => {
+                ensure!(timeout.is_positive(), "Heartbeat timeouts must be positive")
+            }
+            FailureDetectorPolicy::PhiAccrual {
+                threshold,
+                window,
+                min_interval,
+            } => {
+                ensure!(*threshold > 0.0, "Phi thresholds must be positive");
+                ensure!(*window > 0, "Phi windows must hold at least one interval");
+                ensure!(
+                    min_interval.is_positive(),
+                    "Minimal intervals between messages must be positive"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FailureDetectorPolicy {
+    type Err = failure::Error;
+
+    /// Parse `heartbeat:TIMEOUT` or `phi-accrual:THRESHOLD,WINDOW,MIN_INTERVAL`, with
+    /// durations in milliseconds.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let fields: Vec<_> = s.splitn(2, ':').collect();
+        let values: Vec<_> = fields.get(1).map_or(Vec::new(), |x| x.split(',').collect());
+        let number = |index: usize| -> Result<f64> {
+            let value = values
+                .get(index)
+                .ok_or_else(|| format_err!("Missing values in failure detector: {}", s))?;
+            value
+                .parse::<f64>()
+                .map_err(|_| format_err!("Invalid number: {}", value))
+        };
+        let policy = match fields[0] {
+            "heartbeat" if values.len() == 1 => FailureDetectorPolicy::Heartbeat {
+                timeout: Duration::from_millis_f64(number(0)?),
+            },
+            "phi-accrual" if values.len() == 3 => FailureDetectorPolicy::PhiAccrual {
+                threshold: number(0)?,
+                window: values[1]
+                    .parse::<usize>()
+                    .map_err(|_| format_err!("Invalid window: {}", values[1]))?,
+                min_interval: Duration::from_millis_f64(number(2)?),
+            },
+            _ => bail!("Unknown failure detector: {}", s),
+        };
+        Ok(policy)
+    }
+}
+
+impl FailureDetector {
+    pub fn new(policy: FailureDetectorPolicy) -> Self {
+        FailureDetector {
+            policy,
+            start: None,
+            pending: BTreeSet::new(),
+            peers: BTreeMap::new(),
+            suspected: BTreeSet::new(),
+            suspicions: 0,
+        }
+    }
+
+    pub fn policy(&self) -> FailureDetectorPolicy {
+        self.policy
+    }
+
+    /// Record that we heard from `peer`, at the time of the next update.
+    pub fn hear_from(&mut self, peer: Author) {
+        self.pending.insert(peer);
+    }
+
+    /// Date the messages heard since the latest update at `clock`, and suspect the `peers`
+    /// whose suspicion level reached the threshold of the policy.
+    pub fn update(&mut self, peers: &[Author], clock: NodeTime) {
+        self.start.get_or_insert(clock);
+        let window = match self.policy {
+            FailureDetectorPolicy::Heartbeat { .. } => 1,
+            FailureDetectorPolicy::PhiAccrual { window, .. } => window,
+        };
+        for peer in std::mem::replace(&mut self.pending, BTreeSet::new()) {
+            let (latest, intervals) = self
+                .peers
+                .entry(peer)
+                .or_insert_with(|| (clock, VecDeque::new()));
+            if clock > *latest {
+                intervals.push_back(clock - *latest);
+                if intervals.len() > window {
+                    intervals.pop_front();
+                }
+                *latest = clock;
+            }
+        }
+        let suspected: BTreeSet<_> = peers
+            .iter()
+            .cloned()
+            .filter(|peer| self.suspicion(*peer, clock) >= self.threshold())
+            .collect();
+        self.suspicions += suspected.difference(&self.suspected).count();
+        self.suspected = suspected;
+    }
+
+    /// Suspicion level of `peer` at `clock`.
+    pub fn suspicion(&self, peer: Author, clock: NodeTime) -> f64 {
+        let (latest, intervals) = match self.peers.get(&peer) {
+            Some((latest, intervals)) => (*latest, Some(intervals)),
+            None => match self.start {
+                Some(start) => (start, None),
+                None => return 0.0,
+            },
+        };
+        let elapsed = (clock - latest).as_millis_f64().max(0.0);
+        match self.policy {
+            FailureDetectorPolicy::Heartbeat { timeout } => elapsed / timeout.as_millis_f64(),
+            FailureDetectorPolicy::PhiAccrual { min_interval, .. } => {
+                let mean = match intervals.filter(|x| !x.is_empty()) {
+                    Some(intervals) => {
+                        intervals.iter().sum::<Duration>().as_millis_f64() / intervals.len() as f64
+                    }
+                    None => min_interval.as_millis_f64(),
+                };
+                elapsed / mean.max(min_interval.as_millis_f64()) * std::f64::consts::LOG10_E
+            }
+        }
+    }
+
+    /// Level from which peers are suspected.
+    pub fn threshold(&self) -> f64 {
+        match self.policy {
+            FailureDetectorPolicy::Heartbeat { .. } => 1.0,
+            FailureDetectorPolicy::PhiAccrual { threshold, .. } => threshold,
+        }
+    }
+
+    /// Suspicion levels of `peers` at `clock`.
+    pub fn suspicion_levels(&self, peers: &[Author], clock: NodeTime) -> BTreeMap<Author, f64> {
+        peers
+            .iter()
+            .map(|peer| (*peer, self.suspicion(*peer, clock)))
+            .collect()
+    }
+
+    /// Peers suspected at the latest update.
+    pub fn suspected(&self) -> &BTreeSet<Author> {
+        &self.suspected
+    }
+
+    pub fn is_suspected(&self, peer: Author) -> bool {
+        self.suspected.contains(&peer)
+    }
+
+    /// Number of times that a peer became suspected so far.
+    pub fn suspicions(&self) -> usize {
+        self.suspicions
+    }
+
+    /// Forget the latest messages after a restart of the node, but keep counting suspicions.
+    pub fn restart(&self) -> FailureDetector {
+        FailureDetector {
+            suspicions: self.suspicions,
+            ..FailureDetector::new(self.policy)
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fuzz targets for record decoding and insertion, e.g. for cargo-fuzz.
+//!
+//! Enabled with the `fuzzing` feature. Records have no wire format, so fuzz targets decode
+//! arbitrary bytes into the steps of an honest history followed by arbitrary records. Hashes and
+//! states of arbitrary records may refer to those of the history, so that fuzzers can reach
+//! records that pass the first verification checks. Targets panic when a check of the
+//! `property_testing` harness fails.
+
+use super::*;
+use property_testing::*;
+use std::collections::BTreeSet;
+
+#[cfg(test)]
+#[path = "unit_tests/fuzzing_tests.rs"]
+mod fuzzing_tests;
+
+/// Largest number of nodes in decoded histories. Sets of nodes are decoded from single bytes.
+pub const MAX_NODES: usize = 7;
+
+/// A decoded fuzzing input.
+#[derive(Clone, Debug)]
+pub struct FuzzInput {
+    pub history: NetworkHistory,
+    /// Arbitrary records, each with the number of honest records to deliver before it.
+    pub records: Vec<(usize, Record)>,
+}
+
+/// Reads values from the input bytes, then zeros once the input is exhausted.
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+/// Hashes and states of an honest history that arbitrary records may refer to.
+#[derive(Default)]
+struct KnownValues {
+    block_hashes: Vec<u64>,
+    quorum_certificate_hashes: Vec<u64>,
+    states: Vec<u64>,
+}
+
+impl<'a> Decoder<'a> {
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn byte(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((byte, rest)) => {
+                self.data = rest;
+                *byte
+            }
+            None => 0,
+        }
+    }
+
+    fn u64(&mut self) -> u64 {
+        (0..8).fold(0, |value, _| value << 8 | u64::from(self.byte()))
+    }
+
+    /// An arbitrary value, or most of the time one of the `known` values.
+    fn value(&mut self, known: &[u64]) -> u64 {
+        let byte = self.byte();
+        if byte < 0xc0 && !known.is_empty() {
+            known[byte as usize % known.len()]
+        } else {
+            self.u64()
+        }
+    }
+
+    fn round(&mut self) -> Round {
+        Round(self.byte() as usize)
+    }
+
+    fn author(&mut self, authors: &[Author]) -> Author {
+        authors[self.byte() as usize % authors.len()]
+    }
+
+    fn nodes(&mut self, num_nodes: usize) -> BTreeSet<usize> {
+        let mask = self.byte();
+        (0..num_nodes).filter(|i| mask & (1 << i) != 0).collect()
+    }
+
+    fn step(&mut self, num_nodes: usize) -> RoundStep {
+        if self.byte() % 4 == 0 {
+            RoundStep::Timeout {
+                authors: self.nodes(num_nodes),
+            }
+        } else {
+            RoundStep::Propose {
+                voters: self.nodes(num_nodes),
+            }
+        }
+    }
+
+    fn committed_state(&mut self, known: &KnownValues) -> Option<State> {
+        if self.byte() % 2 == 0 {
+            None
+        } else {
+            Some(State(self.value(&known.states)))
+        }
+    }
+
+    /// Decode a record signed by one of `authors`, with a forged signature if the tag says so.
+    fn record(&mut self, authors: &[Author], known: &KnownValues) -> Record {
+        let epoch_id = EpochId(0);
+        let tag = self.byte();
+        let record = match tag % 4 {
+            0 => {
+                let author = self.author(authors);
+                let round = self.round();
+                let previous_hash =
+                    QuorumCertificateHash(self.value(&known.quorum_certificate_hashes));
+                let time = NodeTime(i64::from(self.byte()));
+                let command = Command {
+                    proposer: author,
+                    index: self.byte() as usize,
+                    transactions: Vec::new(),
+                    parameter_change: None,
+                };
+                Record::make_block(command, time, previous_hash, round, author)
+            }
+            1 => {
+                let author = self.author(authors);
+                let round = self.round();
+                let block_hash = BlockHash(self.value(&known.block_hashes));
+                let state = State(self.value(&known.states));
+                let committed_state = self.committed_state(known);
+                Record::make_vote(epoch_id, round, block_hash, state, author, committed_state)
+            }
+            2 => {
+                let author = self.author(authors);
+                let round = self.round();
+                let certified_round = self.round();
+                Record::make_timeout(epoch_id, round, certified_round, author)
+            }
+            _ => {
+                let author = self.author(authors);
+                let round = self.round();
+                let block_hash = BlockHash(self.value(&known.block_hashes));
+                let state = State(self.value(&known.states));
+                let committed_state = self.committed_state(known);
+                // Votes are correctly signed by some of the authors.
+                let votes = self
+                    .nodes(authors.len())
+                    .into_iter()
+                    .map(|index| {
+                        let vote = Record::make_vote(
+                            epoch_id,
+                            round,
+                            block_hash,
+                            state.clone(),
+                            authors[index],
+                            committed_state.clone(),
+                        );
+                        match vote {
+                            Record::Vote(vote) => (vote.author, vote.signature),
+                            _ => unreachable!(),
+                        }
+                    })
+                    .collect();
+                Record::make_quorum_certificate(
+                    epoch_id,
+                    round,
+                    block_hash,
+                    state,
+                    MultiSignature::Individual(votes),
+                    committed_state,
+                    author,
+                )
+            }
+        };
+        if tag & 4 == 0 {
+            record
+        } else {
+            forge(record, Signature::forged(self.u64()))
+        }
+    }
+}
+
+impl KnownValues {
+    fn new(history: &NetworkHistory) -> Self {
+        let mut known = KnownValues::default();
+        known
+            .quorum_certificate_hashes
+            .push(EpochId(0).initial_hash().0);
+        for record in &history.records {
+            match record {
+                Record::Block(_) => known.block_hashes.push(record.digest()),
+                Record::Vote(vote) => known.states.push(vote.state.0),
+                Record::QuorumCertificate(qc) => {
+                    known.quorum_certificate_hashes.push(record.digest());
+                    known.states.push(qc.state.0);
+                }
+                Record::Timeout(_) => (),
+            }
+        }
+        known
+    }
+}
+
+/// Decode the steps of an honest history, then arbitrary records signed by `authors` (or by any
+/// node if `None`).
+pub fn decode_input(data: &[u8], num_nodes: usize, authors: Option<&[Author]>) -> FuzzInput {
+    let mut decoder = Decoder { data };
+    let num_steps = decoder.byte() as usize;
+    let steps: Vec<_> = (0..num_steps).map(|_| decoder.step(num_nodes)).collect();
+    let history = NetworkHistory::new(num_nodes, &steps);
+    let known = KnownValues::new(&history);
+    let all_authors: Vec<_> = (0..num_nodes).map(Author).collect();
+    let authors = authors.unwrap_or(&all_authors);
+    let mut records = Vec::new();
+    while !decoder.is_empty() {
+        let position = decoder.byte() as usize % (history.records.len() + 1);
+        records.push((position, decoder.record(authors, &known)));
+    }
+    FuzzInput { history, records }
+}
+
+/// Deliver the honest history of the input, interleaved with its arbitrary records, to a
+/// fresh record store. Panics if the record store becomes inconsistent.
+pub fn fuzz_record_insertion(data: &[u8]) {
+    let (num_nodes, data) = match data.split_first() {
+        Some((byte, rest)) => (1 + *byte as usize % MAX_NODES, rest),
+        None => return,
+    };
+    let mut input = decode_input(data, num_nodes, None);
+    input.records.sort_by_key(|(position, _)| *position);
+    let mut arbitrary_records = input.records.into_iter().peekable();
+    let mut records = Vec::new();
+    for (index, record) in input.history.records.into_iter().enumerate() {
+        while arbitrary_records
+            .peek()
+            .map_or(false, |(position, _)| *position <= index)
+        {
+            records.push(arbitrary_records.next().unwrap().1);
+        }
+        records.push(record);
+    }
+    records.extend(arbitrary_records.map(|(_, record)| record));
+    let (mut store, mut context) = initial_record_store(num_nodes);
+    if let Err(error) = check_insertions(&mut store, &mut context, records) {
+        panic!("{}", error);
+    }
+}
+
+/// Deliver the honest history of the input, with arbitrary records from the last node acting
+/// as a Byzantine node. Panics if the record store becomes inconsistent or commits a block
+/// outside the honest history.
+pub fn fuzz_byzantine_records(data: &[u8]) {
+    let (num_nodes, data) = match data.split_first() {
+        Some((byte, rest)) => (4 + *byte as usize % (MAX_NODES - 3), rest),
+        None => return,
+    };
+    let byzantine_author = Author(num_nodes - 1);
+    let input = decode_input(data, num_nodes, Some(&[byzantine_author][..]));
+    let byzantine = vec![byzantine_author].into_iter().collect();
+    let records = input
+        .records
+        .into_iter()
+        .map(|(_, record)| record)
+        .collect();
+    if let Err(error) = check_byzantine_records(&input.history, &byzantine, records) {
+        panic!("{}", error);
+    }
+}
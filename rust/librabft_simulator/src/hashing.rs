@@ -0,0 +1,97 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hash functions of records.
+//!
+//! Records are fed to a `std::hash::Hasher` through their `Hash` implementations, and their
+//! digests are the 64-bit values returned by `finish`. The hash function is chosen per thread,
+//! like the keys of signatures, so that concurrent simulations may use different functions.
+//! Hash functions can be shared with worker threads, e.g. to hash batches of records in
+//! parallel.
+//!
+//! Digests are only meant to tell records apart, not to resist an adversary looking for
+//! collisions, so 64 bits are enough: the probability of a collision among `n` records is
+//! about `n² / 2^65`, i.e. below 3% for a billion records. Wider digests would widen the block
+//! and QC hashes of all records, hence the size of the messages and the cost of the maps that
+//! index records by hash.
+
+use super::*;
+use std::{
+    cell::RefCell,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+#[cfg(test)]
+#[path = "unit_tests/hashing_tests.rs"]
+mod hashing_tests;
+
+/// A hash function for records.
+pub trait RecordHasher: Debug + Send + Sync {
+    /// A fresh hasher, used for a single record.
+    fn hasher(&self) -> Box<Hasher>;
+}
+
+/// The default hash function of the standard library.
+#[derive(Debug, Default)]
+pub struct SipRecordHasher;
+
+impl RecordHasher for SipRecordHasher {
+    fn hasher(&self) -> Box<Hasher> {
+        Box::new(DefaultHasher::new())
+    }
+}
+
+/// Counts the records hashed with another hash function.
+#[derive(Debug)]
+pub struct CountingRecordHasher<H> {
+    inner: H,
+    count: Arc<AtomicUsize>,
+}
+
+impl<H: RecordHasher> CountingRecordHasher<H> {
+    pub fn new(inner: H) -> Self {
+        CountingRecordHasher {
+            inner,
+            count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Number of records hashed so far, shared with the copies of the counter.
+    pub fn counter(&self) -> Arc<AtomicUsize> {
+        self.count.clone()
+    }
+}
+
+impl<H: RecordHasher> RecordHasher for CountingRecordHasher<H> {
+    fn hasher(&self) -> Box<Hasher> {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.inner.hasher()
+    }
+}
+
+thread_local! {
+    static RECORD_HASHER: RefCell<Arc<RecordHasher>> = RefCell::new(Arc::new(SipRecordHasher));
+}
+
+/// Hash the records of the current thread with `hasher` from now on. Returns the previous
+/// hash function.
+pub fn set_record_hasher(hasher: Arc<RecordHasher>) -> Arc<RecordHasher> {
+    RECORD_HASHER.with(|current| std::mem::replace(&mut *current.borrow_mut(), hasher))
+}
+
+/// The hash function of the current thread, e.g. to use it on worker threads.
+pub fn record_hasher() -> Arc<RecordHasher> {
+    RECORD_HASHER.with(|current| current.borrow().clone())
+}
+
+/// Digest of a value with the hash function of the current thread.
+pub fn digest<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = record_hasher().hasher();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,261 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! A pacemaker switching between short and long rounds depending on the health of the network.
+//!
+//! `HybridPacemaker` wraps a `PacemakerState`. When a new round starts, it computes the fraction
+//! of the latest rounds of the epoch that ended with a timeout certificate. Past
+//! `max_timeout_rate`, it switches to the conservative round duration; once back under
+//! `min_timeout_rate`, it returns to the aggressive one, i.e. the `delta` of the node. The times
+//! of the switches are reported in the metrics of the node.
+
+use super::*;
+use pacemaker::*;
+use record_store::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+
+#[cfg(test)]
+#[path = "unit_tests/hybrid_pacemaker_tests.rs"]
+mod hybrid_pacemaker_tests;
+
+/// When to switch between the aggressive and the conservative round durations.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HybridPolicy {
+    /// Duration of the first round after a QC in the conservative strategy.
+    pub conservative_delta: Duration,
+    /// Number of latest rounds used to measure the timeout rate.
+    pub window: usize,
+    /// Timeout rate from which to switch to the conservative strategy.
+    pub max_timeout_rate: f64,
+    /// Timeout rate under which to switch back to the aggressive strategy.
+    pub min_timeout_rate: f64,
+}
+
+#[derive(Debug)]
+pub struct HybridPacemaker {
+    /// Pacemaker of the active strategy.
+    inner: PacemakerState,
+    /// How to switch strategies, if at all.
+    policy: Option<HybridPolicy>,
+    /// Duration of the first round after a QC in the aggressive strategy.
+    aggressive_delta: Duration,
+    /// Whether the conservative strategy is active.
+    conservative: bool,
+    /// Local times of the switches so far, for monitoring purposes.
+    switch_times: Vec<NodeTime>,
+}
+
+impl Default for HybridPolicy {
+    fn default() -> Self {
+        HybridPolicy {
+            conservative_delta: Duration::from_millis(100),
+            window: 10,
+            max_timeout_rate: 0.5,
+            min_timeout_rate: 0.1,
+        }
+    }
+}
+
+impl HybridPolicy {
+    /// Check the policy against the aggressive round duration `delta`.
+    pub fn check(&self, delta: Duration) -> Result<()> {
+        ensure!(
+            self.conservative_delta >= delta,
+            "Conservative rounds cannot be shorter (conservative_delta >= delta)"
+        );
+        ensure!(
+            self.window > 0,
+            "Timeout rates need a window of at least one round"
+        );
+        ensure!(
+            0.0 <= self.min_timeout_rate
+                && self.min_timeout_rate <= self.max_timeout_rate
+                && self.max_timeout_rate <= 1.0,
+            "Timeout rates must satisfy 0 <= min_timeout_rate <= max_timeout_rate <= 1"
+        );
+        Ok(())
+    }
+}
+
+impl HybridPacemaker {
+    pub fn new(
+        epoch_id: EpochId,
+        node_time: NodeTime,
+        delta: Duration,
+        gamma: f64,
+        lambda: f64,
+    ) -> Self {
+        HybridPacemaker {
+            inner: PacemakerState::new(epoch_id, node_time, delta, gamma, lambda),
+            policy: None,
+            aggressive_delta: delta,
+            conservative: false,
+            switch_times: Vec::new(),
+        }
+    }
+
+    /// Switch strategies according to `policy`, or keep the aggressive one if absent.
+    pub fn with_policy(mut self, policy: Option<HybridPolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64, seed: u64) -> Self {
+        self.inner = self.inner.with_jitter(jitter, seed);
+        self
+    }
+
+    pub fn with_retransmission(mut self, retransmission: Option<RetransmissionPolicy>) -> Self {
+        self.inner = self.inner.with_retransmission(retransmission);
+        self
+    }
+
+    pub fn with_peer_selection(mut self, peer_selection: PeerSelection) -> Self {
+        self.inner = self.inner.with_peer_selection(peer_selection);
+        self
+    }
+
+    pub fn set_suspected_peers(&mut self, peers: BTreeSet<Author>) {
+        self.inner.set_suspected_peers(peers);
+    }
+
+    pub fn active_round_deadline(&self) -> NodeTime {
+        self.inner.active_round_deadline()
+    }
+
+    pub fn defer_proposal(&mut self, until: NodeTime) {
+        self.inner.defer_proposal(until);
+    }
+
+    /// Whether the conservative strategy is active.
+    pub fn is_conservative(&self) -> bool {
+        self.conservative
+    }
+
+    /// Local times of the switches between strategies so far.
+    pub fn switch_times(&self) -> &[NodeTime] {
+        &self.switch_times
+    }
+
+    /// Apply governance changes of the parameters. A new `delta` replaces the aggressive round
+    /// duration, which only applies once the conservative strategy is left, if active.
+    pub fn apply_parameter_changes(&mut self, changes: &ParameterChange) {
+        self.aggressive_delta = changes.delta.unwrap_or(self.aggressive_delta);
+        let delta = if self.conservative {
+            None
+        } else {
+            changes.delta
+        };
+        self.inner
+            .apply_parameter_changes(&ParameterChange { delta, ..*changes });
+    }
+
+    /// Restart from the aggressive strategy after a crash. Past switches are kept.
+    pub fn restart(&self, node_time: NodeTime) -> HybridPacemaker {
+        let mut inner = self.inner.restart(node_time);
+        inner.set_delta(self.aggressive_delta);
+        HybridPacemaker {
+            inner,
+            policy: self.policy,
+            aggressive_delta: self.aggressive_delta,
+            conservative: false,
+            switch_times: self.switch_times.clone(),
+        }
+    }
+
+    /// Fraction of the rounds of the epoch ended before `round`, up to the `window` latest
+    /// ones, that were ended by a TC.
+    fn timeout_rate(record_store: &RecordStore, round: Round, window: usize) -> f64 {
+        let ended = min(window, round.0.saturating_sub(1));
+        if ended == 0 {
+            return 0.0;
+        }
+        let start = round.0 - ended;
+        let timeouts = record_store
+            .timeout_certificate_rounds()
+            .iter()
+            .rev()
+            .take_while(|tc_round| tc_round.0 >= start)
+            .filter(|tc_round| tc_round.0 < round.0)
+            .count();
+        timeouts as f64 / ended as f64
+    }
+
+    /// Switch strategies, if needed, before `round` starts at `clock`.
+    fn switch_strategies(
+        &mut self,
+        policy: HybridPolicy,
+        record_store: &RecordStore,
+        round: Round,
+        clock: NodeTime,
+    ) {
+        let rate = Self::timeout_rate(record_store, round, policy.window);
+        let conservative = if self.conservative {
+            rate > policy.min_timeout_rate
+        } else {
+            rate >= policy.max_timeout_rate
+        };
+        if conservative == self.conservative {
+            return;
+        }
+        tracing::info!(
+            round = round.0,
+            timeout_rate = rate,
+            conservative,
+            "Switching pacemaker strategy"
+        );
+        self.conservative = conservative;
+        self.switch_times.push(clock);
+        self.inner.set_delta(if conservative {
+            policy.conservative_delta
+        } else {
+            self.aggressive_delta
+        });
+    }
+}
+
+impl Pacemaker for HybridPacemaker {
+    fn update_pacemaker(
+        &mut self,
+        local_author: Author,
+        epoch_id: EpochId,
+        record_store: &RecordStore,
+        latest_query_all_time: NodeTime,
+        clock: NodeTime,
+    ) -> PacemakerUpdateActions {
+        if let Some(policy) = self.policy {
+            // Pick the strategy of the new round, if any, before the inner pacemaker starts it.
+            let active_round = max(
+                record_store.highest_quorum_certificate_round(),
+                record_store.highest_timeout_certificate_round(),
+            ) + 1;
+            if epoch_id > self.inner.active_epoch()
+                || (epoch_id == self.inner.active_epoch()
+                    && active_round > self.inner.active_round())
+            {
+                self.switch_strategies(policy, record_store, active_round, clock);
+            }
+        }
+        self.inner.update_pacemaker(
+            local_author,
+            epoch_id,
+            record_store,
+            latest_query_all_time,
+            clock,
+        )
+    }
+
+    fn active_epoch(&self) -> EpochId {
+        self.inner.active_epoch()
+    }
+
+    fn active_round(&self) -> Round {
+        self.inner.active_round()
+    }
+
+    fn active_leader(&self) -> Option<Author> {
+        self.inner.active_leader()
+    }
+}
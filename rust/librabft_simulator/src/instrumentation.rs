@@ -0,0 +1,38 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Structured tracing of nodes with the `tracing` crate.
+//!
+//! Handlers of a node run inside a `node` span with the fields `author`, `epoch`, and `round`,
+//! as of the start of the handler. Decisions of the node, such as proposals, votes, timeouts, and
+//! commits, are events with a `kind` field, as in decision logs. Without a tracing subscriber,
+//! spans are ignored and events are forwarded to the `log` crate.
+
+use super::*;
+use bft_simulator_runtime::node_logs::{self, NodeLogConfig};
+
+/// Print events to stderr, filtered by `RUST_LOG` directives. On top of filters by target, such
+/// as `librabft_simulator=debug`, directives may select the spans of some nodes or rounds, e.g.
+/// `[node{author=3}]=debug` or `[node{round=12}]=trace`. Records of the `log` crate, e.g. from
+/// the simulator, are printed as well.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init() -> Result<()> {
+    tracing_log::LogTracer::init()?;
+    let subscriber = tracing_subscriber::FmtSubscriber::builder()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .finish();
+    tracing::subscriber::set_global_default(subscriber)?;
+    Ok(())
+}
+
+/// Print records to stderr, filtered by `RUST_LOG` as in `env_logger`, except for the records of
+/// the given nodes, which go to their own output at their own level. Events of nodes go through
+/// the `log` crate, so span directives do not apply.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init_with_node_logs(configs: &[NodeLogConfig]) -> Result<()> {
+    if configs.is_empty() {
+        return init();
+    }
+    node_logs::init(configs)?;
+    Ok(())
+}
@@ -0,0 +1,259 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Built-in invariants of LibraBFT nodes, registered by `make_simulator`.
+//!
+//! Lemmas of the LibraBFT paper are checked over the record stores of all the nodes, after a
+//! run by `audit`, and online by `PaperLemmas` if the simulation parameters ask for it.
+
+use super::*;
+use bft_simulator_runtime::{
+    invariants::{CheckPoint, Invariant},
+    simulator::SimulatedNode,
+};
+use record_store::RecordStoreState;
+
+#[cfg(test)]
+#[path = "unit_tests/invariants_tests.rs"]
+mod invariants_tests;
+
+/// The highest committed round of a node never decreases within an epoch, unless the node lost
+/// its records in a restart.
+#[derive(Default, Debug)]
+pub struct MonotonicCommittedRound {
+    /// Latest epoch, number of record losses, and highest committed round observed, per node.
+    observed: BTreeMap<Author, (EpochId, usize, Round)>,
+}
+
+/// A node never knows two QCs for the same round of an epoch.
+#[derive(Default, Debug)]
+pub struct UniqueQuorumCertificatePerRound;
+
+/// The highest commit certificate of a node ends the chain of its commit rule.
+#[derive(Default, Debug)]
+pub struct CommitCertificateFollowsRule;
+
+/// The epoch of a node never decreases.
+#[derive(Default, Debug)]
+pub struct MonotonicEpoch {
+    observed: BTreeMap<Author, EpochId>,
+}
+
+/// The lemmas of `check_lemmas`, over the record stores of the current epochs of the nodes.
+/// Each check scans all the QCs of the nodes, so that checking every commit is quadratic in
+/// the length of the run.
+#[derive(Default, Debug)]
+pub struct PaperLemmas;
+
+pub fn builtin_invariants<Context>() -> Vec<Box<Invariant<NodeState, Context>>> {
+    vec![
+        Box::new(MonotonicCommittedRound::default()),
+        Box::new(UniqueQuorumCertificatePerRound),
+        Box::new(CommitCertificateFollowsRule),
+        Box::new(MonotonicEpoch::default()),
+    ]
+}
+
+/// Check lemmas of the LibraBFT paper over a set of record stores, e.g. those of all the nodes:
+/// a single block is certified at each round of an epoch, even across stores, and every block
+/// certified after the commit certificate of a store extends the block committed by the store.
+pub fn check_lemmas(stores: &[&RecordStoreState]) -> Result<(), String> {
+    // Certified block and round of the previous QC, by epoch and round.
+    let mut certified = BTreeMap::new();
+    for store in stores {
+        for qc in store.quorum_certificates() {
+            let hash = qc.certified_block_hash;
+            let link = (hash, store.previous_round(hash));
+            let (known_hash, _) = *certified.entry((qc.epoch_id, qc.round)).or_insert(link);
+            if known_hash != hash {
+                return Err(format!(
+                    "{:?} and {:?} are both certified at {:?} of {:?}",
+                    known_hash, hash, qc.round, qc.epoch_id
+                ));
+            }
+        }
+    }
+    let commits: BTreeSet<_> = stores
+        .iter()
+        .filter_map(|store| {
+            let committed_round = store.highest_committed_round();
+            let qc = store.highest_commit_certificate()?;
+            // Commits by the fast path follow from a single QC.
+            if store.is_fast_path_commit(committed_round) {
+                return None;
+            }
+            Some((store.epoch_id(), committed_round, qc.round))
+        })
+        .collect();
+    for (epoch_id, committed_round, certificate_round) in commits {
+        // Rounds of the QCs known to extend the committed block.
+        let mut extending = BTreeSet::new();
+        extending.insert(committed_round);
+        let later_rounds = certified
+            .range((epoch_id, certificate_round + 1)..)
+            .map(|(key, _)| *key)
+            .take_while(|(epoch, _)| *epoch == epoch_id);
+        for (_, round) in later_rounds {
+            let mut path = Vec::new();
+            let mut current = round;
+            while !extending.contains(&current) {
+                if current < committed_round {
+                    return Err(format!(
+                        "The block certified at {:?} of {:?} does not extend the block \
+                         committed at {:?}",
+                        round, epoch_id, committed_round
+                    ));
+                }
+                path.push(current);
+                current = certified
+                    .get(&(epoch_id, current))
+                    .map_or(Round(0), |(_, previous)| *previous);
+            }
+            extending.extend(path);
+        }
+    }
+    Ok(())
+}
+
+/// Check the lemmas of `check_lemmas` over the record stores of all the epochs of the nodes,
+/// e.g. at the end of a run.
+pub fn audit<Context>(nodes: &[SimulatedNode<NodeState, Context>]) -> Result<(), String> {
+    let mut stores = Vec::new();
+    for node in nodes {
+        stores.push(node.node().record_store_state());
+        stores.extend(node.node().past_record_stores());
+    }
+    check_lemmas(&stores)
+}
+
+impl<Context> Invariant<NodeState, Context> for MonotonicCommittedRound {
+    fn name(&self) -> &str {
+        "monotonic committed round"
+    }
+
+    fn check(&mut self, nodes: &[SimulatedNode<NodeState, Context>]) -> Result<(), String> {
+        for node in nodes {
+            let node = node.node();
+            let current = (
+                node.epoch_id(),
+                node.record_losses(),
+                node.record_store().highest_committed_round(),
+            );
+            if let Some(previous) = self.observed.insert(node.local_author(), current) {
+                if current < previous {
+                    return Err(format!(
+                        "{:?} went from {:?} back to {:?}",
+                        node.local_author(),
+                        previous,
+                        current
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Context> Invariant<NodeState, Context> for UniqueQuorumCertificatePerRound {
+    fn name(&self) -> &str {
+        "unique QC per round"
+    }
+
+    fn check_point(&self) -> CheckPoint {
+        CheckPoint::EveryCommit
+    }
+
+    fn check(&mut self, nodes: &[SimulatedNode<NodeState, Context>]) -> Result<(), String> {
+        for node in nodes {
+            let node = node.node();
+            let mut rounds = node.record_store().quorum_certificate_rounds();
+            rounds.sort();
+            if let Some(pair) = rounds.windows(2).find(|pair| pair[0] == pair[1]) {
+                return Err(format!(
+                    "{:?} knows several QCs at {:?} of {:?}",
+                    node.local_author(),
+                    pair[0],
+                    node.epoch_id()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Context> Invariant<NodeState, Context> for CommitCertificateFollowsRule {
+    fn name(&self) -> &str {
+        "commit rule"
+    }
+
+    fn check_point(&self) -> CheckPoint {
+        CheckPoint::EveryCommit
+    }
+
+    fn check(&mut self, nodes: &[SimulatedNode<NodeState, Context>]) -> Result<(), String> {
+        for node in nodes {
+            let store = node.node().record_store();
+            let committed_round = store.highest_committed_round();
+            // Commits by the fast path follow from a single QC.
+            if store.is_fast_path_commit(committed_round) {
+                continue;
+            }
+            let expected_round =
+                committed_round + store.commit_rule().commit_certificate_distance();
+            if let Some(qc) = store.highest_commit_certificate() {
+                if qc.round != expected_round {
+                    return Err(format!(
+                        "{:?} committed {:?} with a {:?} certificate at {:?}",
+                        node.node().local_author(),
+                        committed_round,
+                        store.commit_rule(),
+                        qc.round
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Context> Invariant<NodeState, Context> for MonotonicEpoch {
+    fn name(&self) -> &str {
+        "monotonic epoch"
+    }
+
+    fn check(&mut self, nodes: &[SimulatedNode<NodeState, Context>]) -> Result<(), String> {
+        for node in nodes {
+            let node = node.node();
+            let current = node.epoch_id();
+            if let Some(previous) = self.observed.insert(node.local_author(), current) {
+                if current < previous {
+                    return Err(format!(
+                        "{:?} went from {:?} back to {:?}",
+                        node.local_author(),
+                        previous,
+                        current
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Context> Invariant<NodeState, Context> for PaperLemmas {
+    fn name(&self) -> &str {
+        "LibraBFT lemmas"
+    }
+
+    fn check_point(&self) -> CheckPoint {
+        CheckPoint::EveryCommit
+    }
+
+    fn check(&mut self, nodes: &[SimulatedNode<NodeState, Context>]) -> Result<(), String> {
+        let stores: Vec<_> = nodes
+            .iter()
+            .map(|node| node.node().record_store_state())
+            .collect();
+        check_lemmas(&stores)
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! A key-value store updated by the transactions of committed blocks.
+//!
+//! Each transaction overwrites one key with a value derived from the previous value, so the
+//! contents of the store depend on the order of execution. Executing a block takes simulated
+//! time according to an `ExecutionModel`.
+
+use super::*;
+use base_types::Command;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+#[cfg(test)]
+#[path = "unit_tests/key_value_store_tests.rs"]
+mod key_value_store_tests;
+
+/// How transactions are executed, and how long it takes.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutionModel {
+    /// Number of keys that transactions write to.
+    pub num_keys: u64,
+    /// Time to execute a block, not counting its transactions.
+    pub block_cost: Duration,
+    /// Time to execute a transaction.
+    pub transaction_cost: Duration,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct KeyValueStore {
+    entries: BTreeMap<u64, u64>,
+}
+
+fn hash<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Default for ExecutionModel {
+    fn default() -> Self {
+        ExecutionModel {
+            num_keys: 1000,
+            block_cost: Duration::from_millis(1),
+            transaction_cost: Duration::from_millis(1),
+        }
+    }
+}
+
+impl ExecutionModel {
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.num_keys > 0,
+            "The key-value store needs at least one key"
+        );
+        ensure!(
+            !(self.block_cost.is_negative() || self.transaction_cost.is_negative()),
+            "Execution costs cannot be negative"
+        );
+        Ok(())
+    }
+
+    /// Simulated time spent executing a command.
+    pub fn execution_time(&self, command: &Command) -> Duration {
+        self.block_cost + self.transaction_cost * command.transactions.len() as i64
+    }
+}
+
+/// Parse `KEYS:BLOCK_COST:TRANSACTION_COST`.
+impl FromStr for ExecutionModel {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<_> = s.split(':').collect();
+        ensure!(fields.len() == 3, "Invalid execution model: {}", s);
+        let model = ExecutionModel {
+            num_keys: fields[0].parse()?,
+            block_cost: fields[1].parse()?,
+            transaction_cost: fields[2].parse()?,
+        };
+        model.check()?;
+        Ok(model)
+    }
+}
+
+impl KeyValueStore {
+    pub fn new() -> Self {
+        KeyValueStore::default()
+    }
+
+    pub fn get(&self, key: u64) -> Option<u64> {
+        self.entries.get(&key).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Execute the transactions of `command` in order. Each transaction overwrites one of the
+    /// first `num_keys` keys.
+    pub fn execute(&mut self, command: &Command, num_keys: u64) {
+        for transaction in &command.transactions {
+            let key = hash((command.proposer, transaction.index)) % num_keys;
+            let value = self.entries.entry(key).or_insert(0);
+            *value = hash((*value, command.proposer, transaction.index));
+        }
+    }
+
+    /// Hash of the contents of the store.
+    pub fn state_hash(&self) -> u64 {
+        hash(&self.entries)
+    }
+}
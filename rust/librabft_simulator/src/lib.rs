@@ -0,0 +1,175 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! The LibraBFT consensus protocol, and simulations of networks of LibraBFT nodes.
+//!
+//! The protocol itself, i.e. nodes, records, the pacemaker, data synchronization, and the
+//! contexts nodes run in, only depends on the traits and base types of `bft_simulator_runtime`.
+//! Its public API is re-exported at the root of the crate. Simulations and experiment drivers,
+//! such as scenarios, sweeps, and exports, are enabled by the default `simulation` feature, so
+//! that other crates can depend on the protocol alone with `default-features = false`.
+
+#![allow(bare_trait_objects)]
+
+#[macro_use]
+extern crate failure;
+extern crate rand;
+#[macro_use]
+extern crate log;
+extern crate bft_simulator_runtime;
+#[cfg(feature = "protobuf")]
+extern crate bytes;
+#[cfg(any(feature = "grpc", feature = "stream"))]
+extern crate futures;
+#[cfg(feature = "grpc")]
+extern crate hyper;
+#[cfg(feature = "protobuf")]
+extern crate prost;
+#[cfg(feature = "protobuf")]
+#[macro_use]
+extern crate prost_derive;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+extern crate serde;
+extern crate serde_json;
+#[cfg(feature = "simulation")]
+extern crate toml;
+#[cfg(feature = "grpc")]
+extern crate tower_grpc;
+#[cfg(feature = "grpc")]
+extern crate tower_hyper;
+extern crate tracing;
+#[cfg(all(feature = "simulation", not(target_arch = "wasm32")))]
+extern crate tracing_log;
+#[cfg(all(feature = "simulation", not(target_arch = "wasm32")))]
+extern crate tracing_subscriber;
+#[cfg(feature = "websocket")]
+extern crate tungstenite;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(any(test, feature = "proptest"))]
+#[macro_use]
+extern crate proptest;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+};
+
+// Comments in the following form are used for code-block generation in the consensus report:
+//    "// -- BEGIN FILE name --"
+//    "// -- END FILE --"
+// Do not modify definitions without changing the report as well :)
+
+// Consensus protocol.
+mod base_types;
+mod commit_proof;
+pub mod data_sync;
+pub mod decisions;
+pub mod error;
+pub mod failure_detector;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzzing;
+pub mod hashing;
+mod hybrid_pacemaker;
+pub mod key_value_store;
+pub mod node;
+mod pacemaker;
+#[cfg(any(test, feature = "proptest"))]
+pub mod property_testing;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+mod record;
+mod record_store;
+pub mod record_store_diff;
+mod safety_rules;
+pub mod shared_map;
+pub mod simulated_context;
+mod smr_context;
+
+// Simulations and experiment drivers.
+#[cfg(feature = "simulation")]
+pub mod bisection;
+#[cfg(feature = "simulation")]
+pub mod block_tree;
+#[cfg(feature = "simulation")]
+pub mod builder;
+#[cfg(feature = "simulation")]
+pub mod censorship;
+#[cfg(feature = "simulation")]
+pub mod comparison;
+#[cfg(feature = "grpc")]
+pub mod control;
+#[cfg(feature = "tokio")]
+pub mod deployment;
+#[cfg(feature = "simulation")]
+pub mod diem;
+#[cfg(feature = "simulation")]
+pub mod instrumentation;
+#[cfg(feature = "simulation")]
+pub mod invariants;
+#[cfg(feature = "simulation")]
+pub mod load_curve;
+#[cfg(feature = "simulation")]
+pub mod model_checker;
+#[cfg(feature = "simulation")]
+pub mod monte_carlo;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "simulation")]
+pub mod scenario;
+#[cfg(feature = "simulation")]
+pub mod shrink;
+#[cfg(feature = "simulation")]
+mod simulation;
+#[cfg(any(feature = "websocket", feature = "stream"))]
+pub mod streaming;
+#[cfg(feature = "simulation")]
+pub mod sweep;
+#[cfg(feature = "simulation")]
+pub mod timeline;
+#[cfg(feature = "simulation")]
+pub mod tla;
+#[cfg(feature = "simulation")]
+pub mod trace;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "simulation")]
+use bft_simulator_runtime::simulator;
+use bft_simulator_runtime::{
+    base_types::*, clients::ClientRequest, ActiveLeader, ActiveRound, AuthenticatedChannels,
+    ClientRequests, CommittedBlock, ConsensusNode, CryptoStatistics, DataSyncNode,
+    EpochConfiguration, ExecutionTime, NodeErrors, NodeStatistics, NodeUpdateActions, PeerTracker,
+    RecoverableNode,
+};
+
+pub use base_types::{
+    BlockHash, Command, CommitRule, CryptoCosts, CryptoOperation, EmptyBlockPolicy, EpochId,
+    MessageAuthentication, ParameterChange, QuorumCertificateHash, SafetyData, State, VoteDelivery,
+};
+pub use commit_proof::CommitProof;
+pub use data_sync::{PushPullPolicy, PushPullState, DATA_SYNC_VERSION, LEGACY_DATA_SYNC_VERSION};
+pub use failure_detector::{FailureDetector, FailureDetectorPolicy};
+pub use hybrid_pacemaker::{HybridPacemaker, HybridPolicy};
+pub use node::NodeState;
+pub use pacemaker::{
+    Pacemaker, PacemakerState, PacemakerUpdateActions, PeerSelection, RetransmissionPolicy,
+};
+pub use record::{Block, QuorumCertificate, Record, Timeout, Vote};
+pub use record_store::{RecordRef, RecordStore, RecordStoreSnapshot, RecordStoreState};
+pub use safety_rules::SafetyRules;
+pub use simulated_context::{
+    ExecutionBackend, KeyValueContext, LedgerState, NullContext, SimulatedContext,
+    SimulationContext,
+};
+#[cfg(feature = "simulation")]
+pub use simulation::{make_context, make_node, make_simulator, SimulationParameters, Simulator};
+pub use smr_context::{
+    CommandFetcher, CryptoCost, EpochReader, SMRContext, SafetyStorage, StateComputer,
+    StateFinalizer, StateSync,
+};
+
+use base_types::*;
@@ -0,0 +1,186 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Throughput-latency curves under increasing load.
+//!
+//! Each configuration runs once per load level, with the same seed and the arrival process of
+//! the configuration (Poisson arrivals by default) at the rate of the level. Rates increase by a
+//! constant step until the system saturates, i.e. until the committed transactions stop keeping
+//! up with the submitted ones.
+
+use super::*;
+use bft_simulator_runtime::{statistics::RunSummary, workload::ArrivalProcess};
+use std::io;
+use sweep::SweepGrid;
+
+#[cfg(test)]
+#[path = "unit_tests/load_curve_tests.rs"]
+mod load_curve_tests;
+
+/// Levels of the load. Rates are in transactions per unit of time and per node.
+#[derive(PartialEq, Clone, Debug)]
+pub struct LoadLevels {
+    /// Rate of the first level.
+    pub start_rate: f64,
+    /// Increase of the rate from one level to the next.
+    pub step: f64,
+    /// Maximal number of levels.
+    pub max_levels: usize,
+    /// Stop after the first level where the transaction throughput is below this fraction of
+    /// the submitted rate.
+    pub saturation_ratio: f64,
+}
+
+/// Outcome of one load level.
+#[derive(Clone, Debug)]
+pub struct LoadPoint {
+    /// Submission rate per node.
+    pub rate: f64,
+    pub summary: RunSummary,
+    /// Whether the throughput fell behind the submitted rate.
+    pub saturated: bool,
+}
+
+/// Load curve of one configuration.
+#[derive(Clone, Debug)]
+pub struct LoadCurve {
+    pub parameters: SimulationParameters,
+    /// Points in increasing order of rates, up to the first saturated one.
+    pub points: Vec<LoadPoint>,
+}
+
+impl Default for LoadLevels {
+    fn default() -> Self {
+        LoadLevels {
+            start_rate: 0.01,
+            step: 0.01,
+            max_levels: 20,
+            saturation_ratio: 0.9,
+        }
+    }
+}
+
+impl LoadLevels {
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.start_rate > 0.0 && self.step > 0.0,
+            "Load levels need positive rates and steps"
+        );
+        ensure!(self.max_levels > 0, "Load curves need at least one level");
+        ensure!(
+            0.0 < self.saturation_ratio && self.saturation_ratio <= 1.0,
+            "The saturation ratio must be in (0, 1]"
+        );
+        Ok(())
+    }
+
+    /// Submission rate per node at the given level.
+    pub fn rate(&self, level: usize) -> f64 {
+        self.start_rate + self.step * level as f64
+    }
+}
+
+impl LoadCurve {
+    /// Submission rate per node of the first saturated level, if any.
+    pub fn saturation_rate(&self) -> Option<f64> {
+        self.points
+            .iter()
+            .find(|point| point.saturated)
+            .map(|point| point.rate)
+    }
+}
+
+/// Run the load levels of one configuration.
+pub fn run_load_curve(parameters: &SimulationParameters, levels: &LoadLevels) -> LoadCurve {
+    let process = parameters
+        .arrivals
+        .clone()
+        .unwrap_or(ArrivalProcess::Poisson {
+            rate: levels.start_rate,
+        });
+    let mut points = Vec::new();
+    for level in 0..levels.max_levels {
+        let rate = levels.rate(level);
+        let parameters = SimulationParameters {
+            arrivals: Some(process.with_rate(rate)),
+            ..parameters.clone()
+        };
+        info!("Running configuration {:?} at rate {}", parameters, rate);
+        let summary = sweep::run_summary(&parameters);
+        // Every node commits the transactions submitted to all the nodes.
+        let submitted = rate * parameters.nodes as f64;
+        let saturated = summary.transaction_throughput < levels.saturation_ratio * submitted;
+        points.push(LoadPoint {
+            rate,
+            summary,
+            saturated,
+        });
+        if saturated {
+            break;
+        }
+    }
+    LoadCurve {
+        parameters: parameters.clone(),
+        points,
+    }
+}
+
+/// Run the load levels of each configuration of the grid.
+pub fn run_load_curves(
+    base: &SimulationParameters,
+    grid: &SweepGrid,
+    levels: &LoadLevels,
+) -> Vec<LoadCurve> {
+    grid.configurations(base)
+        .iter()
+        .map(|parameters| run_load_curve(parameters, levels))
+        .collect()
+}
+
+/// Write one CSV row per configuration and load level, to the given file or to stdout.
+pub fn write_results(curves: &[LoadCurve], path: Option<&str>) -> Result<()> {
+    match path {
+        Some(path) => write_csv(curves, csv::Writer::from_path(path)?),
+        None => write_csv(curves, csv::Writer::from_writer(io::stdout())),
+    }
+}
+
+fn write_csv<W: io::Write>(curves: &[LoadCurve], mut writer: csv::Writer<W>) -> Result<()> {
+    writer.write_record(&[
+        "nodes",
+        "delta",
+        "gamma",
+        "lambda",
+        "loss_rate",
+        "commit_rule",
+        "rate",
+        "transaction_throughput",
+        "mean_transaction_latency",
+        "mean_commit_latency",
+        "timeout_rate",
+        "saturated",
+    ])?;
+    for curve in curves {
+        let parameters = &curve.parameters;
+        for point in &curve.points {
+            let summary = &point.summary;
+            let optional = |x: Option<f64>| x.map_or(String::new(), |x| x.to_string());
+            writer.write_record(&[
+                parameters.nodes.to_string(),
+                parameters.delta.to_string(),
+                parameters.gamma.to_string(),
+                parameters.lambda.to_string(),
+                parameters.loss_rate.to_string(),
+                parameters.commit_rule.to_string(),
+                point.rate.to_string(),
+                summary.transaction_throughput.to_string(),
+                optional(summary.mean_transaction_latency),
+                optional(summary.mean_commit_latency),
+                summary.timeout_rate.to_string(),
+                point.saturated.to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
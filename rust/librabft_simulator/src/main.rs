@@ -1,83 +1,154 @@
 // Copyright (c) Calibra Research
 // SPDX-License-Identifier: Apache-2.0
 
-#![allow(bare_trait_objects)]
-
-#[macro_use]
-extern crate failure;
-extern crate rand;
 #[macro_use]
 extern crate log;
 extern crate bft_simulator_runtime;
 extern crate clap;
-extern crate env_logger;
-
-use clap::{App, Arg};
-use std::{collections::BTreeMap, fmt::Debug};
-
-// Comments in the following form are used for code-block generation in the consensus report:
-//    "// -- BEGIN FILE name --"
-//    "// -- END FILE --"
-// Do not modify definitions without changing the report as well :)
-
-mod base_types;
-mod data_sync;
-mod node;
-mod pacemaker;
-mod record;
-mod record_store;
-mod simulated_context;
-mod smr_context;
+extern crate librabft_simulator;
+extern crate rand;
 
 use bft_simulator_runtime::{
-    base_types::*, simulator, ActiveRound, ConsensusNode, DataSyncNode, EpochConfiguration,
-    NodeUpdateActions,
+    base_types::Duration, clients::ClientModel, corruption::CorruptionModel, dashboard::Dashboard,
+    message_counters, network_trace::NetworkTrace, node_logs::NodeLogConfig, quota::MessageQuota,
+    simulator::GlobalTime, statistics::MeasurementPhases, workload::ArrivalProcess,
 };
-
-use base_types::*;
-use data_sync::*;
-use node::NodeState;
-use simulated_context::SimulatedContext;
+use clap::{App, Arg};
+use librabft_simulator::{
+    decisions, instrumentation, invariants, key_value_store::ExecutionModel, make_simulator,
+    monte_carlo, scenario, sweep, timeline, CommitRule, CryptoCosts, EmptyBlockPolicy,
+    ExecutionBackend, FailureDetectorPolicy, HybridPolicy, MessageAuthentication, PeerSelection,
+    PushPullPolicy, RetransmissionPolicy, SimulationParameters, VoteDelivery,
+};
+use std::collections::{BTreeMap, BTreeSet};
 
 fn main() {
     let args = get_arguments();
 
-    env_logger::init();
-    let context_factory =
-        |author, num_nodes| SimulatedContext::new(author, num_nodes, args.commands_per_epoch);
-    let node_factory = |author: Author, context: &SimulatedContext, clock: NodeTime| {
-        NodeState::new(
-            author,
-            context.last_committed_state(),
-            clock,
-            args.target_commit_interval,
-            args.delta,
-            args.gamma,
-            args.lambda,
-            context,
-        )
-    };
-    let delay_distribution = simulator::RandomDelay::new(args.mean, args.variance);
-    let mut sim = simulator::Simulator::<
-        NodeState,
-        SimulatedContext,
-        DataSyncNotification,
-        DataSyncRequest,
-        DataSyncResponse,
-    >::new(
-        args.nodes,
-        delay_distribution,
-        context_factory,
-        node_factory,
+    instrumentation::init_with_node_logs(&args.node_logs)
+        .expect("setting up tracing did not succeed");
+    warn!("Using seed {}", args.parameters.seed);
+    if args.seeds > 1 {
+        let grid = match args.sweep {
+            Some(grid) => grid,
+            None => sweep::SweepGrid::single(&args.parameters),
+        };
+        let results = monte_carlo::run_monte_carlo(&args.parameters, &grid, args.seeds);
+        monte_carlo::write_results(&results, args.sweep_output.as_ref().map(String::as_str))
+            .expect("writing Monte Carlo results did not succeed");
+        return;
+    }
+    if let Some(grid) = &args.sweep {
+        let results = sweep::run_sweep(&args.parameters, grid);
+        sweep::write_results(&results, args.sweep_output.as_ref().map(String::as_str))
+            .expect("writing sweep results did not succeed");
+        if let Some(path) = &args.html_report {
+            sweep::write_html_report(&results, path)
+                .expect("writing the HTML report did not succeed");
+        }
+        return;
+    }
+    let mut sim = make_simulator(&args.parameters);
+    if let Some(refresh_interval) = args.tui_refresh {
+        sim.set_dashboard(Dashboard::stdout(
+            refresh_interval,
+            std::time::Duration::from_millis(args.tui_frame_delay),
+        ));
+    }
+    if args.profile_handlers {
+        sim.profile_handlers();
+    }
+    let stop_reason = sim.run(&args.parameters.termination(), args.output_data_files);
+    warn!("Simulation stopped: {:?}", stop_reason);
+    // Stalls explain themselves whatever the logging level.
+    for report in sim.stall_reports() {
+        eprintln!("Stall detected: {}", report);
+    }
+    if let Err(message) = invariants::audit(sim.simulated_nodes()) {
+        warn!("Audit of the LibraBFT lemmas failed: {}", message);
+    }
+    let statistics = sim.statistics();
+    warn!(
+        "Commit latency: {:?}",
+        statistics.commit_latency_distribution()
+    );
+    warn!(
+        "Rounds per commit: {:?}",
+        statistics.rounds_per_commit_distribution()
+    );
+    warn!(
+        "Transaction latency: {:?}",
+        statistics.transaction_latency_distribution()
     );
-    let contexts = sim.loop_until(
-        simulator::GlobalTime(args.max_clock),
-        args.output_data_files,
+    warn!(
+        "Proposals per transaction: {:?}",
+        statistics.proposals_per_transaction_distribution()
     );
+    warn!(
+        "Client latency: {:?}",
+        statistics.client_latency_distribution()
+    );
+    if let Some(clients) = sim.clients() {
+        warn!(
+            "Request latency: {:?}, with {} retries and {} requests received through gossip",
+            statistics.request_latency_distribution(),
+            clients.retries(),
+            clients.forwarded()
+        );
+    }
+    warn!(
+        "Committed blocks per node, weighted by stake: {}",
+        sim.summary().stake_weighted_committed_blocks
+    );
+    warn!(
+        "Targeted transaction latency: {:?}",
+        statistics.targeted_latency_distribution()
+    );
+    warn!("Fast path rate: {}", sim.summary().fast_path_rate);
+    warn!("Chain quality: {:?}", sim.chain_quality());
+    warn!("View changes: {:?}", sim.view_change_statistics());
+    warn!(
+        "Messages sent and received: {:?}",
+        message_counters::total_counters(sim.message_counters())
+    );
+    let costs = sim.resource_costs();
+    warn!("Resource costs: {:?}", costs);
+    warn!(
+        "Messages, bytes, signatures, and verifications per commit: {:?}",
+        (
+            costs.messages_per_commit(),
+            costs.bytes_per_commit(),
+            costs.signatures_per_commit(),
+            costs.verifications_per_commit(),
+        )
+    );
+    warn!("Errors per node: {:?}", sim.node_errors());
+    if let Some(profile) = sim.handler_profile() {
+        warn!("Host time per handler:\n{}", profile);
+    }
+    if let Some(directory) = &args.metrics_output {
+        sim.metrics_report()
+            .export(directory)
+            .expect("exporting metrics did not succeed");
+    }
+    if let Some(directory) = &args.decision_log {
+        decisions::write_decision_logs(&sim, directory)
+            .expect("writing decision logs did not succeed");
+    }
+    if let Some(path) = &args.round_timeline {
+        timeline::write_round_timeline(&sim, path)
+            .expect("writing the round timeline did not succeed");
+    }
+    if let Some(path) = &args.html_report {
+        sim.html_report("Simulation report")
+            .write(path)
+            .expect("writing the HTML report did not succeed");
+    }
+    let contexts = sim.contexts();
     warn!("Commands executed per node: {:#?}", {
         let x: Vec<_> = contexts
             .iter()
-            .map(|context| context.committed_history().len())
+            .map(|context| context.num_committed_commands())
             .collect();
         x
     });
@@ -85,16 +156,25 @@ fn main() {
 }
 
 struct CliArguments {
-    max_clock: i64,
-    mean: f64,
-    variance: f64,
-    nodes: usize,
-    commands_per_epoch: usize,
-    target_commit_interval: Duration,
-    delta: Duration,
-    gamma: f64,
-    lambda: f64,
+    parameters: SimulationParameters,
     output_data_files: Option<String>,
+    /// Directory where to export the metrics of the run, if any.
+    metrics_output: Option<String>,
+    /// Directory where to write the decision log of each node, if any.
+    decision_log: Option<String>,
+    /// File where to write the rounds of each node, as CSV or JSON, if any.
+    round_timeline: Option<String>,
+    /// File where to write an HTML report of the run or sweep, if any.
+    html_report: Option<String>,
+    profile_handlers: bool,
+    sweep: Option<sweep::SweepGrid>,
+    sweep_output: Option<String>,
+    seeds: u64,
+    /// Simulated time between two frames of the dashboard, if enabled.
+    tui_refresh: Option<Duration>,
+    tui_frame_delay: u64,
+    /// Logging level and output of specific nodes.
+    node_logs: Vec<NodeLogConfig>,
 }
 
 fn get_arguments() -> CliArguments {
@@ -106,6 +186,42 @@ fn get_arguments() -> CliArguments {
                 .help("Time at which to stop the simulation")
                 .default_value("1000"),
         )
+        .arg(
+            Arg::with_name("max_commits")
+                .long("max_commits")
+                .help("Stop the simulation once a node has committed this many blocks")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_rounds_without_progress")
+                .long("max_rounds_without_progress")
+                .help("Stop once a node is this many rounds past the latest commit")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_time_without_commit")
+                .long("max_time_without_commit")
+                .help("Fail if no block is committed for this long while a quorum is running")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("stall_threshold")
+                .long("stall_threshold")
+                .help("Report the state of the nodes once no block is committed for this long")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("warm_up")
+                .long("warm_up")
+                .help("Leave the commits made before this time out of the statistics")
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("measurement_window")
+                .long("measurement_window")
+                .help("Measure for this long after the warm-up, then stop instead of at max_clock")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("mean")
                 .long("mean")
@@ -118,6 +234,57 @@ fn get_arguments() -> CliArguments {
                 .help("The variance of the normal distribution of the network delay")
                 .default_value("4.0"),
         )
+        .arg(
+            Arg::with_name("loss_rate")
+                .long("loss_rate")
+                .help("The probability that a network message is lost")
+                .default_value("0.0"),
+        )
+        .arg(
+            Arg::with_name("network_trace")
+                .long("network_trace")
+                .help(
+                    "CSV file of network changes over time (columns time,mean,variance,loss_rate), \
+                     where empty fields keep their values",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("flip_rate")
+                .long("flip_rate")
+                .help("The probability that a random byte of a network message is flipped")
+                .default_value("0.0"),
+        )
+        .arg(
+            Arg::with_name("truncate_rate")
+                .long("truncate_rate")
+                .help("The probability that a network message is cut at a random length")
+                .default_value("0.0"),
+        )
+        .arg(
+            Arg::with_name("max_messages_per_sender")
+                .long("max_messages_per_sender")
+                .help(
+                    "Messages that nodes accept from each sender per quota period (no limit by \
+                     default)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_bytes_per_sender")
+                .long("max_bytes_per_sender")
+                .help(
+                    "Bytes that nodes accept from each sender per quota period (no limit by \
+                     default)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("quota_period")
+                .long("quota_period")
+                .help("Length of the periods over which nodes count the messages of each sender")
+                .default_value("100"),
+        )
         .arg(
             Arg::with_name("nodes")
                 .long("nodes")
@@ -130,6 +297,121 @@ fn get_arguments() -> CliArguments {
                 .help("The maximum number of commands per epoch")
                 .default_value("30000"),
         )
+        .arg(
+            Arg::with_name("arrivals")
+                .long("arrivals")
+                .help(
+                    "Transactions submitted to each node: constant:RATE, poisson:RATE, or \
+                     bursty:SIZE:INTERVAL (empty blocks by default)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("targeted_arrivals")
+                .long("targeted_arrivals")
+                .help(
+                    "Targeted transactions submitted to all the nodes, which Byzantine nodes \
+                     censor, in the format of --arrivals",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("clients")
+                .long("clients")
+                .help(
+                    "Number of simulated clients sending requests to the nodes and waiting for \
+                     their commit (none by default)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("client_think_time")
+                .long("client_think_time")
+                .help(
+                    "Time between the acknowledgment of a request and the next request of a \
+                     client",
+                )
+                .default_value("10"),
+        )
+        .arg(
+            Arg::with_name("gossip_fanout")
+                .long("gossip_fanout")
+                .help("Number of other nodes that a node forwards each new client request to")
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("gossip_delay")
+                .long("gossip_delay")
+                .help("Time before a node forwards a new client request to other nodes")
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("max_batch_size")
+                .long("max_batch_size")
+                .help("The maximum number of transactions per block")
+                .default_value("100"),
+        )
+        .arg(
+            Arg::with_name("transaction_size")
+                .long("transaction_size")
+                .help("The size of each transaction, in bytes")
+                .default_value("100"),
+        )
+        .arg(
+            Arg::with_name("max_block_payload")
+                .long("max_block_payload")
+                .help("The maximum size of the transactions of a block, in bytes")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("empty_blocks")
+                .long("empty_blocks")
+                .help(
+                    "What leaders propose without transactions: propose, delay:MAX_DELAY, or \
+                     no-op",
+                )
+                .default_value("propose"),
+        )
+        .arg(
+            Arg::with_name("max_batch_wait")
+                .long("max_batch_wait")
+                .help("How long leaders may wait for a full block (propose immediately by default)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("blocks_per_round")
+                .long("blocks_per_round")
+                .help("Number of chained blocks that leaders propose in each round")
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("execution")
+                .long("execution")
+                .help(
+                    "Execute transactions in a key-value store: KEYS:BLOCK_COST:TRANSACTION_COST \
+                     (free by default)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("backend")
+                .long("backend")
+                .help(
+                    "How nodes execute the committed commands: ledger, key-value, or null \
+                     (key-value with --execution, ledger otherwise)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("execution_latency")
+                .long("execution_latency")
+                .help("Time for a node to execute a block before voting on it")
+                .default_value("0"),
+        )
+        .arg(Arg::with_name("pipelined_execution").long("pipelined_execution").help(
+            "Vote on blocks before executing them, and wait for execution before delivering \
+             commits instead"
+        ))
         .arg(
             Arg::with_name("target_commit_interval")
                 .long("target_commit_interval")
@@ -154,41 +436,582 @@ fn get_arguments() -> CliArguments {
                 .help("Coefficient to control the frequency of query-all actions")
                 .default_value("0.5"),
         )
+        .arg(
+            Arg::with_name("round_jitter")
+                .long("round_jitter")
+                .help("Largest fraction of a round duration added at random to each round")
+                .default_value("0.0"),
+        )
+        .arg(
+            Arg::with_name("retransmission_period")
+                .long("retransmission_period")
+                .help(
+                    "Time before nodes broadcast their proposal or timeout of a round again \
+                     (no retransmissions by default)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("retransmission_backoff")
+                .long("retransmission_backoff")
+                .help("Factor applied to the time between retransmissions after each of them")
+                .default_value("1.0"),
+        )
+        .arg(
+            Arg::with_name("conservative_delta")
+                .long("conservative_delta")
+                .help(
+                    "Switch to rounds starting at this duration while many rounds time out \
+                     (fixed round durations by default)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("hybrid_window")
+                .long("hybrid_window")
+                .help("Number of latest rounds used to measure the timeout rate")
+                .default_value("10"),
+        )
+        .arg(
+            Arg::with_name("max_timeout_rate")
+                .long("max_timeout_rate")
+                .help("Timeout rate from which to switch to conservative rounds")
+                .default_value("0.5"),
+        )
+        .arg(
+            Arg::with_name("min_timeout_rate")
+                .long("min_timeout_rate")
+                .help("Timeout rate under which to switch back to aggressive rounds")
+                .default_value("0.1"),
+        )
+        .arg(
+            Arg::with_name("peer_selection")
+                .long("peer_selection")
+                .help(
+                    "Nodes that nodes send their records to when a round starts: leader, \
+                     random:COUNT, leader-region:REGION,..., or relays:AUTHOR,...",
+                )
+                .default_value("leader"),
+        )
+        .arg(
+            Arg::with_name("failure_detector")
+                .long("failure_detector")
+                .help(
+                    "Skip the peers suspected by a failure detector when synchronizing: \
+                     heartbeat:TIMEOUT or phi-accrual:THRESHOLD,WINDOW,MIN_INTERVAL",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("pull_period")
+                .long("pull_period")
+                .help(
+                    "Push records to random peers and pull from a random peer with this period, \
+                     instead of broadcasting (broadcast by default)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("push_fanout")
+                .long("push_fanout")
+                .help("Number of random peers that nodes push to at first")
+                .default_value("2"),
+        )
+        .arg(
+            Arg::with_name("data_sync_version")
+                .long("data_sync_version")
+                .help("Latest version of the data sync protocol supported by the nodes")
+                .default_value("2"),
+        )
+        .arg(
+            Arg::with_name("suppress_redundant_records")
+                .long("suppress_redundant_records")
+                .help("Do not send records to the nodes known to hold them"),
+        )
+        .arg(
+            Arg::with_name("strict_verification")
+                .long("strict_verification")
+                .help("Check all the records of a message before processing any of them"),
+        )
+        .arg(
+            Arg::with_name("state_sync")
+                .long("state_sync")
+                .help("Let lagging nodes resume from checkpoints of the executed state"),
+        )
+        .arg(
+            Arg::with_name("commit_rule")
+                .long("commit_rule")
+                .help("Number of certified rounds needed to commit a block")
+                .possible_values(&["three-chain", "two-chain"])
+                .default_value("three-chain"),
+        )
+        .arg(
+            Arg::with_name("vote_delivery")
+                .long("vote_delivery")
+                .help("Nodes that receive the votes and assemble the QCs")
+                .possible_values(&["proposer", "next-leader", "broadcast"])
+                .default_value("proposer"),
+        )
+        .arg(
+            Arg::with_name("fast_path_wait")
+                .long("fast_path_wait")
+                .help("How long QC assemblers wait for the votes of all validators, which commit \
+                       a block immediately (no fast path by default)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("aggregate_signatures")
+                .long("aggregate_signatures")
+                .help("Aggregate the votes of each QC into a single signature"),
+        )
+        .arg(
+            Arg::with_name("threshold_signatures")
+                .long("threshold_signatures")
+                .help("Combine the signature shares of the votes of each QC into a threshold \
+                       signature")
+                .conflicts_with("aggregate_signatures"),
+        )
+        .arg(
+            Arg::with_name("max_uncertified_blocks")
+                .long("max_uncertified_blocks")
+                .help("Maximal number of blocks without a QC that nodes keep per author, evicting \
+                       the oldest ones (unbounded by default)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("crypto_costs")
+                .long("crypto_costs")
+                .help("Time charged to nodes per signature, verification, aggregated \
+                       signature, and optionally MAC, as SIGN:VERIFY:AGGREGATE[:MAC]")
+                .takes_value(true)
+                .default_value("0:0:0"),
+        )
+        .arg(
+            Arg::with_name("message_authentication")
+                .long("message_authentication")
+                .help("Authentication of the network messages: none, mac, or signature")
+                .takes_value(true)
+                .default_value("none"),
+        )
+        .arg(
+            Arg::with_name("scenario")
+                .long("scenario")
+                .help("Read the parameters of the simulation from a TOML scenario file")
+                .takes_value(true),
+        )
         .arg(Arg::with_name("create_csv").long("create_csv").help(
             "If given this argument, csv files will be generated with data on the simulation"
         ).takes_value(true))
+        .arg(
+            Arg::with_name("metrics_output")
+                .long("metrics_output")
+                .help("Export CSV and JSON metrics of the run to the given directory")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("check_lemmas")
+                .long("check_lemmas")
+                .help("Check the lemmas of the LibraBFT paper after every commit, not only at the \
+                       end of the run"),
+        )
+        .arg(
+            Arg::with_name("decision_log")
+                .long("decision_log")
+                .help("Write the decisions of each node and their reasons to the given directory")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("round_timeline")
+                .long("round_timeline")
+                .help("Write the rounds of each node to the given CSV file, or JSON if it ends with .json")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("html_report")
+                .long("html_report")
+                .help("Write a self-contained HTML report of the run or sweep to the given file")
+                .takes_value(true),
+        )
+        .arg(Arg::with_name("profile_handlers").long("profile_handlers").help(
+            "Time the handlers of the nodes on the host and print the time spent in each kind of \
+             handler at the end of the run"
+        ))
+        .arg(Arg::with_name("sweep").long("sweep").help(
+            "Run one simulation for each combination of the comma-separated values given to \
+             --delta, --gamma, --lambda, --nodes, and --loss_rate, then print a summary per run"
+        ))
+        .arg(
+            Arg::with_name("sweep_output")
+                .long("sweep_output")
+                .help("Write the sweep summaries to the given CSV file instead of stdout")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .help("Seed of the random network delays (chosen randomly by default)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("seeds")
+                .long("seeds")
+                .help(
+                    "Run each configuration with this many consecutive seeds and print a \
+                     statistical summary per configuration",
+                )
+                .default_value("1"),
+        )
+        .arg(Arg::with_name("tui").long("tui").help(
+            "Show a live dashboard with the round, leader, highest committed round, and pending \
+             messages of each node"
+        ))
+        .arg(
+            Arg::with_name("tui_refresh")
+                .long("tui_refresh")
+                .help("Simulated time between two refreshes of the dashboard")
+                .default_value("50"),
+        )
+        .arg(
+            Arg::with_name("tui_frame_delay")
+                .long("tui_frame_delay")
+                .help("Pause in milliseconds after each refresh of the dashboard")
+                .default_value("100"),
+        )
+        .arg(
+            Arg::with_name("node_log")
+                .long("node_log")
+                .help(
+                    "Log the records of a node at the given level, to stderr or to the given \
+                     file: AUTHOR:LEVEL[:FILE] (repeatable)",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         .get_matches();
 
+    let sweep = if matches.is_present("sweep") {
+        Some(sweep::SweepGrid {
+            delta: sweep::parse_list(matches.value_of("delta").unwrap()),
+            gamma: sweep::parse_list(matches.value_of("gamma").unwrap()),
+            lambda: sweep::parse_list(matches.value_of("lambda").unwrap()),
+            nodes: sweep::parse_list(matches.value_of("nodes").unwrap()),
+            loss_rate: sweep::parse_list(matches.value_of("loss_rate").unwrap()),
+            commit_rule: vec![matches.value_of("commit_rule").unwrap().parse().unwrap()],
+        })
+    } else {
+        None
+    };
+    // During a sweep, each swept argument holds a list. The base parameters use its first value.
+    let single_value = |name| {
+        let value = matches.value_of(name).unwrap();
+        if sweep.is_some() {
+            value.split(',').next().unwrap()
+        } else {
+            value
+        }
+    };
+
+    let mut parameters = match matches.value_of("scenario") {
+        Some(path) => scenario::Scenario::load(path)
+            .unwrap_or_else(|error| panic!("Invalid scenario file {}: {}", path, error))
+            .parameters(),
+        None => SimulationParameters {
+            max_clock: GlobalTime(
+                matches
+                    .value_of("max_clock")
+                    .unwrap()
+                    .parse::<i64>()
+                    .unwrap(),
+            ),
+            max_commits: matches
+                .value_of("max_commits")
+                .map(|x| x.parse::<usize>().unwrap()),
+            max_rounds_without_progress: matches
+                .value_of("max_rounds_without_progress")
+                .map(|x| x.parse::<usize>().unwrap()),
+            max_time_without_commit: matches
+                .value_of("max_time_without_commit")
+                .map(|x| x.parse::<Duration>().unwrap()),
+            stall_threshold: matches
+                .value_of("stall_threshold")
+                .map(|x| x.parse::<Duration>().unwrap()),
+            measurement: MeasurementPhases {
+                warm_up: matches
+                    .value_of("warm_up")
+                    .unwrap()
+                    .parse::<Duration>()
+                    .unwrap(),
+                window: matches
+                    .value_of("measurement_window")
+                    .map(|x| x.parse::<Duration>().unwrap()),
+            },
+            mean: matches.value_of("mean").unwrap().parse::<f64>().unwrap(),
+            variance: matches
+                .value_of("variance")
+                .unwrap()
+                .parse::<f64>()
+                .unwrap(),
+            loss_rate: single_value("loss_rate").parse::<f64>().unwrap(),
+            network_trace: NetworkTrace::default(),
+            nodes: single_value("nodes").parse::<usize>().unwrap(),
+            commands_per_epoch: matches
+                .value_of("commands_per_epoch")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            target_commit_interval: matches
+                .value_of("target_commit_interval")
+                .unwrap()
+                .parse::<Duration>()
+                .unwrap(),
+            arrivals: matches
+                .value_of("arrivals")
+                .map(|x| x.parse::<ArrivalProcess>().unwrap()),
+            targeted_arrivals: matches
+                .value_of("targeted_arrivals")
+                .map(|x| x.parse::<ArrivalProcess>().unwrap()),
+            clients: matches.value_of("clients").map(|x| ClientModel {
+                clients: x.parse::<usize>().unwrap(),
+                think_time: matches
+                    .value_of("client_think_time")
+                    .unwrap()
+                    .parse::<Duration>()
+                    .unwrap(),
+                gossip_fanout: matches
+                    .value_of("gossip_fanout")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap(),
+                gossip_delay: matches
+                    .value_of("gossip_delay")
+                    .unwrap()
+                    .parse::<Duration>()
+                    .unwrap(),
+                ..ClientModel::default()
+            }),
+            max_batch_size: matches
+                .value_of("max_batch_size")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            transaction_size: matches
+                .value_of("transaction_size")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            max_block_payload: matches
+                .value_of("max_block_payload")
+                .map(|x| x.parse::<usize>().unwrap()),
+            max_batch_wait: matches
+                .value_of("max_batch_wait")
+                .map(|x| x.parse::<Duration>().unwrap()),
+            empty_block_policy: matches
+                .value_of("empty_blocks")
+                .unwrap()
+                .parse::<EmptyBlockPolicy>()
+                .unwrap(),
+            blocks_per_round: single_value("blocks_per_round").parse::<usize>().unwrap(),
+            max_uncertified_blocks: matches
+                .value_of("max_uncertified_blocks")
+                .map(|x| x.parse::<usize>().unwrap()),
+            backend: match matches.value_of("backend") {
+                Some(backend) => backend.parse::<ExecutionBackend>().unwrap(),
+                None if matches.is_present("execution") => ExecutionBackend::KeyValue,
+                None => ExecutionBackend::Ledger,
+            },
+            execution: matches
+                .value_of("execution")
+                .map(|x| x.parse::<ExecutionModel>().unwrap()),
+            execution_latency: matches
+                .value_of("execution_latency")
+                .unwrap()
+                .parse::<Duration>()
+                .unwrap(),
+            pipelined_execution: matches.is_present("pipelined_execution"),
+            delta: single_value("delta").parse::<Duration>().unwrap(),
+            gamma: single_value("gamma").parse::<f64>().unwrap(),
+            lambda: single_value("lambda").parse::<f64>().unwrap(),
+            round_jitter: matches
+                .value_of("round_jitter")
+                .unwrap()
+                .parse::<f64>()
+                .unwrap(),
+            retransmission: matches.value_of("retransmission_period").map(|x| {
+                RetransmissionPolicy {
+                    period: x.parse::<Duration>().unwrap(),
+                    backoff: matches
+                        .value_of("retransmission_backoff")
+                        .unwrap()
+                        .parse::<f64>()
+                        .unwrap(),
+                }
+            }),
+            hybrid_pacemaker: matches
+                .value_of("conservative_delta")
+                .map(|x| HybridPolicy {
+                    conservative_delta: x.parse::<Duration>().unwrap(),
+                    window: single_value("hybrid_window").parse::<usize>().unwrap(),
+                    max_timeout_rate: single_value("max_timeout_rate").parse::<f64>().unwrap(),
+                    min_timeout_rate: single_value("min_timeout_rate").parse::<f64>().unwrap(),
+                }),
+            peer_selection: matches
+                .value_of("peer_selection")
+                .unwrap()
+                .parse::<PeerSelection>()
+                .unwrap(),
+            failure_detector: matches
+                .value_of("failure_detector")
+                .map(|x| x.parse::<FailureDetectorPolicy>().unwrap()),
+            push_pull: matches.value_of("pull_period").map(|x| PushPullPolicy {
+                pull_period: x.parse::<Duration>().unwrap(),
+                initial_fanout: single_value("push_fanout").parse::<usize>().unwrap(),
+                ..PushPullPolicy::default()
+            }),
+            data_sync_version: single_value("data_sync_version").parse::<u32>().unwrap(),
+            suppress_redundant_records: matches.is_present("suppress_redundant_records"),
+            strict_verification: matches.is_present("strict_verification"),
+            state_sync: matches.is_present("state_sync"),
+            message_corruption: CorruptionModel {
+                flip_rate: matches
+                    .value_of("flip_rate")
+                    .unwrap()
+                    .parse::<f64>()
+                    .unwrap(),
+                truncate_rate: matches
+                    .value_of("truncate_rate")
+                    .unwrap()
+                    .parse::<f64>()
+                    .unwrap(),
+                ..CorruptionModel::default()
+            },
+            message_quota: MessageQuota {
+                period: single_value("quota_period").parse::<Duration>().unwrap(),
+                max_messages: matches
+                    .value_of("max_messages_per_sender")
+                    .map(|x| x.parse::<usize>().unwrap()),
+                max_bytes: matches
+                    .value_of("max_bytes_per_sender")
+                    .map(|x| x.parse::<usize>().unwrap()),
+            },
+            commit_rule: matches
+                .value_of("commit_rule")
+                .unwrap()
+                .parse::<CommitRule>()
+                .unwrap(),
+            vote_delivery: matches
+                .value_of("vote_delivery")
+                .unwrap()
+                .parse::<VoteDelivery>()
+                .unwrap(),
+            fast_path_wait: matches
+                .value_of("fast_path_wait")
+                .map(|x| x.parse::<Duration>().unwrap()),
+            aggregate_signatures: matches.is_present("aggregate_signatures"),
+            threshold_signatures: matches.is_present("threshold_signatures"),
+            crypto_costs: matches
+                .value_of("crypto_costs")
+                .unwrap()
+                .parse::<CryptoCosts>()
+                .unwrap(),
+            message_authentication: matches
+                .value_of("message_authentication")
+                .unwrap()
+                .parse::<MessageAuthentication>()
+                .unwrap(),
+            seed: match matches.value_of("seed") {
+                Some(seed) => seed.parse::<u64>().unwrap(),
+                None => scenario::random_seed(),
+            },
+            node_overrides: BTreeMap::new(),
+            crashes: BTreeMap::new(),
+            recoveries: BTreeMap::new(),
+            scheduled_faults: Vec::new(),
+            byzantine: BTreeSet::new(),
+            observers: BTreeSet::new(),
+            stakes: BTreeMap::new(),
+            validator_sets: Vec::new(),
+            reconfigurations: Vec::new(),
+            parameter_changes: Vec::new(),
+            join_times: BTreeMap::new(),
+            record_decisions: false,
+            check_lemmas: false,
+        },
+    };
+    parameters.record_decisions =
+        matches.is_present("decision_log") || matches.is_present("round_timeline");
+    if matches.is_present("check_lemmas") {
+        parameters.check_lemmas = true;
+    }
+    if let Some(path) = matches.value_of("network_trace") {
+        parameters.network_trace = NetworkTrace::load(path)
+            .unwrap_or_else(|error| panic!("Invalid network trace {}: {}", path, error));
+    }
+    parameters
+        .check_execution()
+        .unwrap_or_else(|error| panic!("Invalid parameters: {}", error));
+    parameters
+        .check_fast_path()
+        .unwrap_or_else(|error| panic!("Invalid parameters: {}", error));
+    parameters
+        .peer_selection
+        .check(parameters.nodes)
+        .unwrap_or_else(|error| panic!("Invalid peer selection: {}", error));
+    scenario::check_data_sync_version(parameters.data_sync_version)
+        .unwrap_or_else(|error| panic!("Invalid data sync version: {}", error));
+    parameters
+        .measurement
+        .check()
+        .unwrap_or_else(|error| panic!("Invalid measurement phases: {}", error));
+    if let Some(push_pull) = &parameters.push_pull {
+        push_pull
+            .check()
+            .unwrap_or_else(|error| panic!("Invalid push-pull policy: {}", error));
+    }
+    parameters
+        .message_quota
+        .check()
+        .unwrap_or_else(|error| panic!("Invalid message quota: {}", error));
+    if let Some(clients) = &parameters.clients {
+        clients
+            .check(parameters.nodes)
+            .unwrap_or_else(|error| panic!("Invalid client model: {}", error));
+    }
+
     CliArguments {
-        max_clock: matches
-            .value_of("max_clock")
-            .unwrap()
-            .parse::<i64>()
-            .unwrap(),
-        mean: matches.value_of("mean").unwrap().parse::<f64>().unwrap(),
-        variance: matches
-            .value_of("variance")
-            .unwrap()
-            .parse::<f64>()
-            .unwrap(),
-        nodes: matches.value_of("nodes").unwrap().parse::<usize>().unwrap(),
-        commands_per_epoch: matches
-            .value_of("commands_per_epoch")
-            .unwrap()
-            .parse::<usize>()
-            .unwrap(),
-        target_commit_interval: matches
-            .value_of("target_commit_interval")
-            .unwrap()
-            .parse::<Duration>()
-            .unwrap(),
-        delta: matches
-            .value_of("delta")
+        parameters,
+        output_data_files: matches.value_of("create_csv").map(|x| x.to_string()),
+        metrics_output: matches.value_of("metrics_output").map(|x| x.to_string()),
+        decision_log: matches.value_of("decision_log").map(|x| x.to_string()),
+        round_timeline: matches.value_of("round_timeline").map(|x| x.to_string()),
+        html_report: matches.value_of("html_report").map(|x| x.to_string()),
+        profile_handlers: matches.is_present("profile_handlers"),
+        sweep,
+        sweep_output: matches.value_of("sweep_output").map(|x| x.to_string()),
+        seeds: matches.value_of("seeds").unwrap().parse::<u64>().unwrap(),
+        tui_refresh: if matches.is_present("tui") {
+            Some(
+                matches
+                    .value_of("tui_refresh")
+                    .unwrap()
+                    .parse::<Duration>()
+                    .unwrap(),
+            )
+        } else {
+            None
+        },
+        tui_frame_delay: matches
+            .value_of("tui_frame_delay")
             .unwrap()
-            .parse::<Duration>()
+            .parse::<u64>()
             .unwrap(),
-        gamma: matches.value_of("gamma").unwrap().parse::<f64>().unwrap(),
-        lambda: matches.value_of("lambda").unwrap().parse::<f64>().unwrap(),
-        output_data_files: matches.value_of("create_csv").map(|x| x.to_string()),
+        node_logs: matches
+            .values_of("node_log")
+            .map(|values| {
+                values
+                    .map(|x| x.parse::<NodeLogConfig>().unwrap())
+                    .collect()
+            })
+            .unwrap_or_default(),
     }
 }
@@ -0,0 +1,146 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bounded exploration of the delivery orders of tiny configurations.
+//!
+//! Instead of a single schedule, the model checker explores the runs obtained by processing,
+//! at each step, any of the `width` earliest pending events, up to `max_depth` steps per run.
+//! Branches are explored depth-first by re-running the simulation with a prefix of choices.
+//! Safety and the built-in invariants are checked in every run.
+//!
+//! With state hashing, a run stops when it reaches a state seen in another run. States only
+//! summarize rounds, commits, and certificates, so this explores a subset of the schedules.
+//!
+//! For larger configurations, `explore` runs random schedules guided by coverage instead.
+
+use super::*;
+use bft_simulator_runtime::{
+    scheduler::{CoverageScheduler, PrefixScheduler, Scheduler},
+    termination::StopReason,
+};
+use std::collections::HashSet;
+
+#[cfg(test)]
+#[path = "unit_tests/model_checker_tests.rs"]
+mod model_checker_tests;
+
+/// Largest number of nodes accepted by the model checker.
+pub const MAX_NODES: usize = 7;
+
+#[derive(Clone, Debug)]
+pub struct ModelCheckingOptions {
+    /// Number of earliest pending events to choose from at each step.
+    pub width: usize,
+    /// Maximal number of steps per run.
+    pub max_depth: usize,
+    /// Stop the exploration after this many runs.
+    pub max_runs: usize,
+    pub state_hashing: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModelCheckingReport {
+    pub runs: usize,
+    /// Runs stopped on an already visited state.
+    pub pruned_runs: usize,
+    /// Number of distinct states reached, if recorded.
+    pub states: usize,
+    /// Whether all the branches were explored within `max_runs`.
+    pub complete: bool,
+    /// First violation found, with the choices leading to it.
+    pub violation: Option<(Vec<usize>, StopReason)>,
+}
+
+impl Default for ModelCheckingOptions {
+    fn default() -> Self {
+        ModelCheckingOptions {
+            width: 2,
+            max_depth: 200,
+            max_runs: 1000,
+            state_hashing: true,
+        }
+    }
+}
+
+/// Run the simulation with the choices of `scheduler`.
+pub fn run_schedule(parameters: &SimulationParameters, scheduler: &mut Scheduler) -> StopReason {
+    let mut sim = make_simulator(parameters);
+    sim.run_with_scheduler(&parameters.termination(), scheduler)
+}
+
+pub fn check(
+    parameters: &SimulationParameters,
+    options: &ModelCheckingOptions,
+) -> Result<ModelCheckingReport> {
+    ensure!(
+        parameters.nodes <= MAX_NODES,
+        "Model checking supports at most {} nodes",
+        MAX_NODES
+    );
+    ensure!(options.width > 0, "The exploration width must be positive");
+    let mut report = ModelCheckingReport {
+        runs: 0,
+        pruned_runs: 0,
+        states: 0,
+        complete: false,
+        violation: None,
+    };
+    let mut visited_states = HashSet::new();
+    let mut prefix = Some(Vec::new());
+    while let Some(choices) = prefix {
+        if report.runs >= options.max_runs {
+            report.states = visited_states.len();
+            return Ok(report);
+        }
+        let mut scheduler = PrefixScheduler::new(options.width, choices, options.max_depth);
+        if options.state_hashing {
+            scheduler = scheduler.with_visited_states(visited_states);
+        }
+        let stop_reason = run_schedule(parameters, &mut scheduler);
+        report.runs += 1;
+        if scheduler.is_pruned() {
+            report.pruned_runs += 1;
+        }
+        if shrink::is_failure(&stop_reason) {
+            let schedule = scheduler.decisions().iter().map(|x| x.choice).collect();
+            report.violation = Some((schedule, stop_reason));
+            report.states = scheduler.into_visited_states().len();
+            return Ok(report);
+        }
+        prefix = PrefixScheduler::next_prefix(scheduler.decisions());
+        visited_states = scheduler.into_visited_states();
+    }
+    report.states = visited_states.len();
+    report.complete = true;
+    Ok(report)
+}
+
+/// Random exploration of `max_runs` schedules, preferring choices that lead to new states.
+/// Coverage is shared by all runs. `seed` drives the choices of the scheduler, not the
+/// network delays.
+pub fn explore(
+    parameters: &SimulationParameters,
+    options: &ModelCheckingOptions,
+    seed: u64,
+) -> Result<ModelCheckingReport> {
+    ensure!(options.width > 0, "The exploration width must be positive");
+    let mut scheduler = CoverageScheduler::new(options.width, options.max_depth, seed);
+    let mut report = ModelCheckingReport {
+        runs: 0,
+        pruned_runs: 0,
+        states: 0,
+        complete: false,
+        violation: None,
+    };
+    while report.runs < options.max_runs {
+        scheduler.start_run();
+        let stop_reason = run_schedule(parameters, &mut scheduler);
+        report.runs += 1;
+        report.states = scheduler.num_states();
+        if shrink::is_failure(&stop_reason) {
+            report.violation = Some((scheduler.choices().to_vec(), stop_reason));
+            break;
+        }
+    }
+    Ok(report)
+}
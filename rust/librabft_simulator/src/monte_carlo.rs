@@ -0,0 +1,139 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bft_simulator_runtime::statistics::{MonteCarloSummary, SampleStatistics};
+use std::io;
+use sweep::SweepGrid;
+
+#[cfg(test)]
+#[path = "unit_tests/monte_carlo_tests.rs"]
+mod monte_carlo_tests;
+
+/// Outcome of running one configuration with many seeds.
+#[derive(Clone, Debug)]
+pub struct MonteCarloResult {
+    /// Parameters of the configuration, including the first seed.
+    pub parameters: SimulationParameters,
+    pub summary: MonteCarloSummary,
+}
+
+/// Run each configuration of the grid with the seeds `base.seed .. base.seed + seeds`.
+pub fn run_monte_carlo(
+    base: &SimulationParameters,
+    grid: &SweepGrid,
+    seeds: u64,
+) -> Vec<MonteCarloResult> {
+    grid.configurations(base)
+        .into_iter()
+        .map(|parameters| {
+            let summaries: Vec<_> = (0..seeds)
+                .map(|i| {
+                    let parameters = SimulationParameters {
+                        seed: parameters.seed.wrapping_add(i),
+                        ..parameters.clone()
+                    };
+                    info!("Running configuration {:?}", parameters);
+                    sweep::run_summary(&parameters)
+                })
+                .collect();
+            let summary = MonteCarloSummary::new(&summaries);
+            if summary.is_liveness_seed_dependent() {
+                warn!(
+                    "Liveness depends on the seed: only {} out of {} runs committed blocks with {:?}",
+                    summary.live_runs, summary.runs, parameters
+                );
+            }
+            report_metrics(&summary);
+            MonteCarloResult {
+                parameters,
+                summary,
+            }
+        })
+        .collect()
+}
+
+/// Log the spread of each metric over the seeds, flagging the seed-sensitive ones.
+fn report_metrics(summary: &MonteCarloSummary) {
+    for (name, statistics) in summary.metrics() {
+        if let Some(x) = statistics {
+            let flag = if x.is_seed_sensitive() {
+                " (seed-sensitive)"
+            } else {
+                ""
+            };
+            warn!(
+                "{}: mean {:.3}, std dev {:.3}, min {:.3}, max {:.3}{}",
+                name, x.mean, x.std_dev, x.min, x.max, flag
+            );
+        }
+    }
+}
+
+/// Write one CSV row per configuration, to the given file or to stdout.
+pub fn write_results(results: &[MonteCarloResult], path: Option<&str>) -> Result<()> {
+    match path {
+        Some(path) => write_csv(results, csv::Writer::from_path(path)?),
+        None => write_csv(results, csv::Writer::from_writer(io::stdout())),
+    }
+}
+
+fn statistics_fields(statistics: Option<&SampleStatistics>) -> Vec<String> {
+    match statistics {
+        Some(x) => vec![
+            x.mean.to_string(),
+            x.median.to_string(),
+            x.confidence_interval.0.to_string(),
+            x.confidence_interval.1.to_string(),
+            x.std_dev.to_string(),
+            x.min.to_string(),
+            x.max.to_string(),
+        ],
+        None => vec![String::new(); 7],
+    }
+}
+
+fn write_csv<W: io::Write>(results: &[MonteCarloResult], mut writer: csv::Writer<W>) -> Result<()> {
+    let mut headers = vec![
+        "nodes".to_string(),
+        "delta".to_string(),
+        "gamma".to_string(),
+        "lambda".to_string(),
+        "loss_rate".to_string(),
+        "commit_rule".to_string(),
+        "runs".to_string(),
+        "live_runs".to_string(),
+        "seed_dependent_liveness".to_string(),
+        "seed_sensitive_metrics".to_string(),
+    ];
+    for name in &MonteCarloSummary::METRICS {
+        for statistic in &[
+            "mean", "median", "ci_low", "ci_high", "std_dev", "min", "max",
+        ] {
+            headers.push(format!("{}_{}", name, statistic));
+        }
+    }
+    writer.write_record(&headers)?;
+    for result in results {
+        let parameters = &result.parameters;
+        let summary = &result.summary;
+        let mut record = vec![
+            parameters.nodes.to_string(),
+            parameters.delta.to_string(),
+            parameters.gamma.to_string(),
+            parameters.lambda.to_string(),
+            parameters.loss_rate.to_string(),
+            parameters.commit_rule.to_string(),
+            summary.runs.to_string(),
+            summary.live_runs.to_string(),
+            summary.is_liveness_seed_dependent().to_string(),
+            summary.seed_sensitive_metrics().join(";"),
+        ];
+        for (_, statistics) in summary.metrics() {
+            record.extend(statistics_fields(statistics));
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
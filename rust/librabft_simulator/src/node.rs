@@ -3,14 +3,24 @@
 
 use super::*;
 use base_types::*;
+use bft_simulator_runtime::{
+    handler_profile::{self, Handler},
+    message_counters::MessageKind,
+};
+use data_sync::*;
+use decisions::*;
+use error::NodeError;
+use failure_detector::*;
+use hybrid_pacemaker::*;
 use pacemaker::*;
 use record::*;
 use record_store::*;
+use safety_rules::SafetyRules;
 use smr_context::SMRContext;
 
 use std::{
     cmp::{max, min},
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
 };
 
 #[cfg(test)]
@@ -23,21 +33,80 @@ pub struct NodeState {
     /// Module dedicated to storing records for the current epoch.
     record_store: RecordStoreState,
     /// Module dedicated to leader election.
-    pacemaker: PacemakerState,
+    pacemaker: HybridPacemaker,
     /// Current epoch.
     epoch_id: EpochId,
     /// Identity of this node.
     local_author: Author,
-    /// Highest round voted so far.
-    latest_voted_round: Round,
-    /// Current locked round.
-    locked_round: Round,
+    /// Voting constraints, consulted before signing votes and timeouts.
+    safety_rules: SafetyRules,
     /// Time of the latest query-all operation.
     latest_query_all_time: NodeTime,
     /// Track data to which the main handler has already reacted.
     tracker: CommitTracker,
     /// Record stores from previous epochs.
     past_record_stores: HashMap<EpochId, RecordStoreState>,
+    /// Blocks committed so far, for monitoring purposes.
+    committed_blocks: Vec<CommittedBlock>,
+    /// Epochs, rounds, and commands of the blocks that we proposed and that were neither
+    /// committed nor abandoned yet.
+    uncommitted_proposals: Vec<(EpochId, Round, Command)>,
+    /// Block being executed before we vote on it, and time at which execution ends.
+    pending_execution: Option<(BlockHash, NodeTime)>,
+    /// Whether we vote before executing blocks, and only wait for execution before
+    /// delivering commits.
+    pipelined_execution: bool,
+    /// Blocks executed in the background, with the times at which their execution ends.
+    background_executions: HashMap<BlockHash, NodeTime>,
+    /// Time at which background executions started so far are done. Blocks are executed one
+    /// at a time.
+    executor_free_time: NodeTime,
+    /// How long a leader may wait for a full block before proposing, if at all.
+    max_batch_wait: Option<Duration>,
+    /// Epoch and round, and time at which we started waiting to propose, for a full block or
+    /// for a first transaction.
+    batch_wait_start: Option<((EpochId, Round), NodeTime)>,
+    /// What we propose as a leader without transactions.
+    empty_block_policy: EmptyBlockPolicy,
+    /// How long we may wait for the votes of all validators before creating a QC, if at all.
+    fast_path_wait: Option<Duration>,
+    /// Epoch and round, and time at which we started waiting for the missing votes.
+    fast_path_wait_start: Option<((EpochId, Round), NodeTime)>,
+    /// Decisions made so far and their reasons, if we record them.
+    decision_log: Option<DecisionLog>,
+    /// Push/pull dissemination state, if nodes push to random peers instead of broadcasting.
+    push_pull: Option<PushPullState>,
+    /// Latest version of the data sync protocol that we support.
+    data_sync_version: u32,
+    /// Records held by other nodes, if we avoid sending them again.
+    peer_knowledge: Option<PeerKnowledge>,
+    /// Whether we check all the records of a message before processing any of them.
+    strict_verification: bool,
+    /// Messages rejected by strict verification so far.
+    rejected_messages: usize,
+    /// Proposals with an invalid payload so far, and the latest one.
+    rejected_proposals: usize,
+    latest_rejected_proposal: Option<BlockHash>,
+    /// Suspicions of the other validators, if we skip suspected peers when synchronizing.
+    failure_detector: Option<FailureDetector>,
+    /// Whether we exchange checkpoints of executed states with lagging nodes.
+    state_sync: bool,
+    /// Checkpoint at the start of the current epoch, if we exchange them.
+    state_checkpoint: Option<StateCheckpoint>,
+    /// Checkpoints of later epochs sent by other nodes and not installed yet, with the blocks
+    /// committed before them according to each sender.
+    checkpoint_endorsements: BTreeMap<StateCheckpoint, BTreeMap<Author, Vec<CommittedBlock>>>,
+    /// Checkpoints of other nodes installed so far.
+    installed_checkpoints: usize,
+    /// Whether we only follow the committed chain, without creating votes, timeouts, or blocks.
+    observer: bool,
+    /// Whether the voting constraints and the records of the current epoch survive restarts.
+    durable_safety_data: bool,
+    durable_records: bool,
+    /// Restarts that lost the records of the current epoch so far.
+    record_losses: usize,
+    /// Errors met since the simulator last took them.
+    errors: Vec<NodeError>,
 }
 // -- END FILE --
 
@@ -75,6 +144,7 @@ impl NodeState {
         delta: Duration,
         gamma: f64,
         lambda: f64,
+        commit_rule: CommitRule,
         smr_context: &SMRContext,
     ) -> NodeState {
         let epoch_id = EpochId(0);
@@ -84,20 +154,210 @@ impl NodeState {
             initial_state.clone(),
             epoch_id,
             smr_context.configuration(&initial_state),
+            commit_rule,
         );
         NodeState {
             record_store,
-            pacemaker: PacemakerState::new(epoch_id, node_time, delta, gamma, lambda),
+            pacemaker: HybridPacemaker::new(epoch_id, node_time, delta, gamma, lambda),
             epoch_id,
             local_author,
-            latest_voted_round: Round(0),
-            locked_round: Round(0),
+            safety_rules: SafetyRules::new(epoch_id),
             latest_query_all_time: node_time,
             tracker,
             past_record_stores: HashMap::new(),
+            committed_blocks: Vec::new(),
+            uncommitted_proposals: Vec::new(),
+            pending_execution: None,
+            pipelined_execution: false,
+            background_executions: HashMap::new(),
+            executor_free_time: node_time,
+            max_batch_wait: None,
+            batch_wait_start: None,
+            empty_block_policy: EmptyBlockPolicy::Propose,
+            fast_path_wait: None,
+            fast_path_wait_start: None,
+            decision_log: None,
+            push_pull: None,
+            data_sync_version: DATA_SYNC_VERSION,
+            peer_knowledge: None,
+            strict_verification: false,
+            rejected_messages: 0,
+            rejected_proposals: 0,
+            latest_rejected_proposal: None,
+            failure_detector: None,
+            state_sync: false,
+            state_checkpoint: None,
+            checkpoint_endorsements: BTreeMap::new(),
+            installed_checkpoints: 0,
+            observer: false,
+            durable_safety_data: true,
+            durable_records: true,
+            record_losses: 0,
+            errors: Vec::new(),
         }
     }
 
+    /// Record the decisions of the node and their reasons.
+    pub fn with_decision_log(mut self, record_decisions: bool) -> Self {
+        self.decision_log = if record_decisions {
+            Some(DecisionLog::new())
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Send votes to the given nodes, in this epoch and the next ones.
+    pub fn with_vote_delivery(mut self, vote_delivery: VoteDelivery) -> Self {
+        self.record_store = self.record_store.with_vote_delivery(vote_delivery);
+        self
+    }
+
+    /// Wait up to `fast_path_wait` for the votes of all validators before creating a QC, and
+    /// commit blocks with such QCs right away, in this epoch and the next ones.
+    pub fn with_fast_path(mut self, fast_path_wait: Option<Duration>) -> Self {
+        self.fast_path_wait = fast_path_wait;
+        self.record_store = self.record_store.with_fast_path(fast_path_wait.is_some());
+        self
+    }
+
+    /// Aggregate the votes of the QCs that we create, in this epoch and the next ones.
+    pub fn with_aggregate_signatures(mut self, aggregate_signatures: bool) -> Self {
+        self.record_store = self
+            .record_store
+            .with_aggregate_signatures(aggregate_signatures);
+        self
+    }
+
+    /// Create QCs with threshold signatures, dealing new keys at the start of each epoch.
+    pub fn with_threshold_signatures(mut self, threshold_signatures: bool) -> Self {
+        self.record_store = self
+            .record_store
+            .with_threshold_signatures(threshold_signatures);
+        self
+    }
+
+    /// Switch to longer rounds when many of the latest rounds time out, according to `policy`.
+    pub fn with_hybrid_pacemaker(mut self, policy: Option<HybridPolicy>) -> Self {
+        self.pacemaker = self.pacemaker.with_policy(policy);
+        self
+    }
+
+    /// Propose a chain of `blocks_per_round` blocks as a leader, and vote on such chains, in
+    /// this epoch and the next ones.
+    pub fn with_blocks_per_round(mut self, blocks_per_round: usize) -> Self {
+        self.record_store = self.record_store.with_blocks_per_round(blocks_per_round);
+        self
+    }
+
+    /// Keep at most `max_uncertified_blocks` blocks without a QC per author, if given, in this
+    /// epoch and the next ones.
+    pub fn with_max_uncertified_blocks(mut self, max_uncertified_blocks: Option<usize>) -> Self {
+        self.record_store = self
+            .record_store
+            .with_max_uncertified_blocks(max_uncertified_blocks);
+        self
+    }
+
+    /// Send our records to the nodes chosen by `peer_selection` when a round starts.
+    pub fn with_peer_selection(mut self, peer_selection: PeerSelection) -> Self {
+        self.pacemaker = self.pacemaker.with_peer_selection(peer_selection);
+        self
+    }
+
+    /// Push new records to random peers and pull from a random peer periodically, according to
+    /// `policy`, instead of broadcasting.
+    pub fn with_push_pull(mut self, policy: Option<PushPullPolicy>) -> Self {
+        self.push_pull = policy.map(PushPullState::new);
+        self
+    }
+
+    /// Support the versions of the data sync protocol up to `version`, e.g. to simulate nodes
+    /// that are not upgraded yet.
+    pub fn with_data_sync_version(mut self, version: u32) -> Self {
+        self.data_sync_version = version;
+        self
+    }
+
+    /// Track the records held by other nodes, as learned from their notifications, and do not
+    /// send them these records again.
+    pub fn with_redundancy_suppression(mut self, suppress_redundant_records: bool) -> Self {
+        self.peer_knowledge = if suppress_redundant_records {
+            Some(PeerKnowledge::new(self.epoch_id))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Check the signatures of all the records of a message before processing any of them, and
+    /// skip the whole message if one is invalid.
+    pub fn with_strict_verification(mut self, strict_verification: bool) -> Self {
+        self.strict_verification = strict_verification;
+        self
+    }
+
+    /// Suspect the validators that we have not heard from lately, according to `policy`, and
+    /// skip them when synchronizing.
+    pub fn with_failure_detector(mut self, policy: Option<FailureDetectorPolicy>) -> Self {
+        self.failure_detector = policy.map(FailureDetector::new);
+        self
+    }
+
+    /// Let lagging nodes resume from a checkpoint of the executed state instead of executing
+    /// the blocks of each missed epoch.
+    pub fn with_state_sync(mut self, state_sync: bool) -> Self {
+        self.state_sync = state_sync;
+        self
+    }
+
+    /// Follow the committed chain without voting, proposing, or creating timeouts, e.g. as a
+    /// full node that is not a validator.
+    pub fn with_observer(mut self, observer: bool) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Keep the voting constraints and the records of the current epoch across restarts, or
+    /// lose them. Both survive by default. Without its records, a node fetches them again from
+    /// its peers.
+    pub fn with_durability(mut self, safety_data: bool, records: bool) -> Self {
+        self.durable_safety_data = safety_data;
+        self.durable_records = records;
+        self
+    }
+
+    /// Lengthen each round by up to `jitter` times its duration, at random depending on `seed`.
+    pub fn with_round_jitter(mut self, jitter: f64, seed: u64) -> Self {
+        self.pacemaker = self.pacemaker.with_jitter(jitter, seed);
+        self
+    }
+
+    /// Broadcast our proposals and timeouts again on the given schedule while their round lasts.
+    pub fn with_retransmission(mut self, retransmission: Option<RetransmissionPolicy>) -> Self {
+        self.pacemaker = self.pacemaker.with_retransmission(retransmission);
+        self
+    }
+
+    /// Let the leader wait up to `max_batch_wait` for a full block before proposing.
+    pub fn with_max_batch_wait(mut self, max_batch_wait: Option<Duration>) -> Self {
+        self.max_batch_wait = max_batch_wait;
+        self
+    }
+
+    /// Decide what the leader proposes when it has no transactions.
+    pub fn with_empty_block_policy(mut self, policy: EmptyBlockPolicy) -> Self {
+        self.empty_block_policy = policy;
+        self
+    }
+
+    /// Vote on blocks right away and execute them in the background. States are still
+    /// computed when voting; only the delivery of commits waits for execution.
+    pub fn with_pipelined_execution(mut self, pipelined_execution: bool) -> Self {
+        self.pipelined_execution = pipelined_execution;
+        self
+    }
+
     pub fn epoch_id(&self) -> EpochId {
         self.epoch_id
     }
@@ -110,20 +370,400 @@ impl NodeState {
         &self.record_store
     }
 
+    /// Record store of the current epoch.
+    pub(crate) fn record_store_state(&self) -> &RecordStoreState {
+        &self.record_store
+    }
+
+    /// Record stores of the previous epochs.
+    pub(crate) fn past_record_stores(&self) -> Vec<&RecordStoreState> {
+        self.past_record_stores.values().collect()
+    }
+
+    /// Take a read-only view of the record store of the current epoch.
+    pub fn record_store_snapshot(&self) -> RecordStoreSnapshot {
+        self.record_store.snapshot()
+    }
+
     pub fn record_store_at(&self, epoch_id: EpochId) -> Option<&RecordStore> {
+        self.record_store_state_at(epoch_id)
+            .map(|store| store as &RecordStore)
+    }
+
+    pub(crate) fn record_store_state_at(&self, epoch_id: EpochId) -> Option<&RecordStoreState> {
         if epoch_id == self.epoch_id {
             return Some(&self.record_store);
         }
-        match self.past_record_stores.get(&epoch_id) {
-            None => None,
-            Some(store) => Some(&*store),
-        }
+        self.past_record_stores.get(&epoch_id)
+    }
+
+    /// Proof that the block `block_hash` of any known epoch was committed.
+    pub fn commit_proof(&self, block_hash: BlockHash) -> Option<CommitProof> {
+        self.record_stores()
+            .into_iter()
+            .filter_map(|(_, store)| store.commit_proof(block_hash))
+            .next()
+    }
+
+    /// Record stores of the current and previous epochs, in increasing order of epochs.
+    pub(crate) fn record_stores(&self) -> Vec<(EpochId, &RecordStoreState)> {
+        let mut stores: Vec<_> = self
+            .past_record_stores
+            .iter()
+            .map(|(epoch_id, store)| (*epoch_id, store))
+            .collect();
+        stores.push((self.epoch_id, &self.record_store));
+        stores.sort_by_key(|(epoch_id, _)| *epoch_id);
+        stores
     }
 
     pub fn pacemaker(&self) -> &Pacemaker {
         &self.pacemaker
     }
 
+    pub fn decision_log(&self) -> Option<&DecisionLog> {
+        self.decision_log.as_ref()
+    }
+
+    pub fn data_sync_version(&self) -> u32 {
+        self.data_sync_version
+    }
+
+    /// Suspicion levels of our peers, if we run a failure detector.
+    pub fn failure_detector(&self) -> Option<&FailureDetector> {
+        self.failure_detector.as_ref()
+    }
+
+    pub fn peer_knowledge(&self) -> Option<&PeerKnowledge> {
+        self.peer_knowledge.as_ref()
+    }
+
+    pub fn strict_verification(&self) -> bool {
+        self.strict_verification
+    }
+
+    pub fn state_sync(&self) -> bool {
+        self.state_sync
+    }
+
+    pub fn state_checkpoint(&self) -> Option<&StateCheckpoint> {
+        self.state_checkpoint.as_ref()
+    }
+
+    pub fn installed_checkpoints(&self) -> usize {
+        self.installed_checkpoints
+    }
+
+    pub fn is_observer(&self) -> bool {
+        self.observer
+    }
+
+    pub fn durable_safety_data(&self) -> bool {
+        self.durable_safety_data
+    }
+
+    pub fn durable_records(&self) -> bool {
+        self.durable_records
+    }
+
+    pub fn record_losses(&self) -> usize {
+        self.record_losses
+    }
+
+    pub(crate) fn learn_peer_records(
+        &mut self,
+        epoch_id: EpochId,
+        peer: Author,
+        records: &[Record],
+    ) {
+        if let Some(knowledge) = &mut self.peer_knowledge {
+            knowledge.learn(epoch_id, peer, records);
+        }
+    }
+
+    /// Leave out of the notifications the nodes that hold all their records already.
+    fn suppress_redundant_notifications(&mut self, actions: &mut NodeUpdateActions) {
+        if self.peer_knowledge.is_none()
+            || (!actions.should_broadcast && actions.should_send.is_empty())
+        {
+            return;
+        }
+        // Notifications are the same for all receivers.
+        let digests: Vec<_> = self
+            .create_notification_internal()
+            .records()
+            .iter()
+            .map(Record::digest)
+            .collect();
+        let epoch_id = self.epoch_id;
+        let knowledge = match &mut self.peer_knowledge {
+            Some(knowledge) => knowledge,
+            None => return,
+        };
+        let mut receivers = if actions.should_broadcast {
+            knowledge.peers()
+        } else {
+            actions.should_send.clone()
+        };
+        receivers.sort();
+        receivers.dedup();
+        let peers: Vec<_> = receivers
+            .into_iter()
+            .filter(|peer| knowledge.holds_all(epoch_id, *peer, &digests))
+            .collect();
+        knowledge.count_suppressed_notifications(peers.len());
+        actions.should_send.retain(|peer| !peers.contains(peer));
+        actions.should_skip.extend(peers);
+    }
+
+    pub fn push_pull(&self) -> Option<&PushPullState> {
+        self.push_pull.as_ref()
+    }
+
+    /// Record that we heard from `peer`, for our failure detector, if any.
+    pub(crate) fn hear_from(&mut self, peer: Author) {
+        if let Some(detector) = &mut self.failure_detector {
+            detector.hear_from(peer);
+        }
+    }
+
+    /// Count the records pushed to us that we already knew, to adapt the push fanout.
+    pub(crate) fn observe_pushed_records(&mut self, records: &[Record]) {
+        let store = &self.record_store;
+        if let Some(state) = &mut self.push_pull {
+            let duplicates = records
+                .iter()
+                .filter(|record| store.is_known_record(record))
+                .count();
+            state.observe(records.len(), duplicates);
+        }
+    }
+
+    /// In push/pull mode, push to random peers instead of broadcasting, and pull from a random
+    /// peer when a pull is due.
+    fn push_pull_actions(&mut self, actions: &mut NodeUpdateActions, clock: NodeTime) {
+        let state = match &mut self.push_pull {
+            Some(state) => state,
+            None => return,
+        };
+        let validators = self.record_store.validators();
+        if actions.should_broadcast {
+            actions.should_broadcast = false;
+            let peers = state.push_targets(self.local_author, validators.clone());
+            actions.should_send.extend(peers);
+        }
+        if let Some(peer) = state.pull_target(self.local_author, validators, clock) {
+            actions.should_query.push(peer);
+        }
+        actions.next_scheduled_update = min(actions.next_scheduled_update, state.next_pull_time());
+    }
+
+    /// Span of the node at its current epoch and round. Handlers run inside this span.
+    pub fn span(&self) -> tracing::Span {
+        tracing::debug_span!(
+            "node",
+            author = self.local_author.0,
+            epoch = self.epoch_id.0,
+            round = self.pacemaker.active_round().0
+        )
+    }
+
+    fn log_decision(&mut self, clock: NodeTime, decision: Decision) {
+        tracing::debug!(time = clock.0, kind = decision.kind(), "{:?}", decision);
+        if let Some(log) = &mut self.decision_log {
+            log.record(clock, self.epoch_id, decision);
+        }
+    }
+
+    /// Time at which we started waiting to propose in the active round.
+    fn proposal_wait_start(&mut self, clock: NodeTime) -> NodeTime {
+        let round = (self.epoch_id, self.pacemaker.active_round());
+        match self.batch_wait_start {
+            Some((wait_round, start)) if wait_round == round => start,
+            _ => {
+                self.batch_wait_start = Some((round, clock));
+                clock
+            }
+        }
+    }
+
+    /// End of the wait for more transactions, if the leader should not propose yet.
+    fn batch_wait_end(
+        &mut self,
+        clock: NodeTime,
+        smr_context: &mut SMRContext,
+    ) -> Option<NodeTime> {
+        let max_batch_wait = self.max_batch_wait?;
+        let end = self.proposal_wait_start(clock) + max_batch_wait;
+        if clock >= end || smr_context.is_batch_full(clock) {
+            None
+        } else {
+            Some(end)
+        }
+    }
+
+    /// End of the wait for a first transaction, if the leader should not propose an empty
+    /// block yet. Never ends if the leader should only propose once transactions arrive.
+    fn empty_block_wait_end(
+        &mut self,
+        clock: NodeTime,
+        smr_context: &mut SMRContext,
+    ) -> Option<NodeTime> {
+        if self.empty_block_policy == EmptyBlockPolicy::Propose
+            || !smr_context.is_batch_empty(clock)
+        {
+            return None;
+        }
+        match self.empty_block_policy {
+            EmptyBlockPolicy::Propose => None,
+            EmptyBlockPolicy::Delay { max_delay } => {
+                let end = self.proposal_wait_start(clock) + max_delay;
+                if clock >= end {
+                    None
+                } else {
+                    Some(end)
+                }
+            }
+            // A no-op block lets the transactions of the certified blocks commit.
+            EmptyBlockPolicy::NoOp if self.record_store.has_uncommitted_transactions() => None,
+            EmptyBlockPolicy::NoOp => Some(NodeTime::never()),
+        }
+    }
+
+    /// End of the wait for the votes of all validators, if we should not create a QC yet.
+    fn fast_path_wait_end(&mut self, clock: NodeTime) -> Option<NodeTime> {
+        let fast_path_wait = self.fast_path_wait?;
+        if !self.record_store.is_missing_votes(self.local_author) {
+            return None;
+        }
+        let round = (self.epoch_id, self.record_store.current_round());
+        let start = match self.fast_path_wait_start {
+            Some((wait_round, start)) if wait_round == round => start,
+            _ => {
+                self.fast_path_wait_start = Some((round, clock));
+                clock
+            }
+        };
+        let end = start + fast_path_wait;
+        if clock >= end {
+            None
+        } else {
+            Some(end)
+        }
+    }
+
+    /// Why the voting constraints forbid a vote for the proposed block, unless we already
+    /// voted for it.
+    fn vote_refusal(&self, block_hash: BlockHash, block_round: Round) -> Option<VoteRefusal> {
+        if block_round <= self.safety_rules.latest_voted_round() {
+            if self
+                .record_store
+                .has_timeout(self.local_author, block_round)
+            {
+                return Some(VoteRefusal::TimedOut);
+            }
+            let voted_hash = self
+                .record_store
+                .current_vote(self.local_author)
+                .map(|vote| vote.certified_block_hash);
+            if voted_hash == Some(block_hash) {
+                return None;
+            }
+            return Some(VoteRefusal::AlreadyVoted {
+                latest_voted_round: self.safety_rules.latest_voted_round(),
+            });
+        }
+        Some(VoteRefusal::Locked {
+            previous_round: self.record_store.previous_round(block_hash),
+            locked_round: self.safety_rules.locked_round(),
+        })
+    }
+
+    /// Whether the commands of the given block, and of the blocks chained before it in its
+    /// round, are valid.
+    fn has_valid_payload(&self, block_hash: BlockHash, smr_context: &SMRContext) -> bool {
+        self.record_store
+            .round_chain(block_hash)
+            .into_iter()
+            .all(|block| smr_context.is_valid(&block.command, block.time))
+    }
+
+    /// Time to execute the given block, together with the blocks chained before it in its round.
+    fn execution_duration(&self, block_hash: BlockHash, smr_context: &SMRContext) -> Duration {
+        self.record_store
+            .round_chain(block_hash)
+            .into_iter()
+            .map(|block| smr_context.execution_duration(&block.command))
+            .sum()
+    }
+
+    /// Time at which the execution of the given block ends, starting the first time we see it.
+    fn execution_end(
+        &mut self,
+        block_hash: BlockHash,
+        clock: NodeTime,
+        smr_context: &SMRContext,
+    ) -> NodeTime {
+        match self.pending_execution {
+            Some((hash, end)) if hash == block_hash => end,
+            _ => {
+                let end = clock + self.execution_duration(block_hash, smr_context);
+                self.pending_execution = Some((block_hash, end));
+                end
+            }
+        }
+    }
+
+    /// Time at which the background execution of the given block ends. Execution starts once
+    /// previous blocks are executed.
+    fn background_execution_end(
+        &mut self,
+        block_hash: BlockHash,
+        clock: NodeTime,
+        smr_context: &SMRContext,
+    ) -> NodeTime {
+        if let Some(end) = self.background_executions.get(&block_hash) {
+            return *end;
+        }
+        let end =
+            max(clock, self.executor_free_time) + self.execution_duration(block_hash, smr_context);
+        self.executor_free_time = end;
+        self.background_executions.insert(block_hash, end);
+        end
+    }
+
+    /// End of the background execution of the blocks committed since the latest delivered
+    /// commit, if they are not all executed yet. Blocks that we did not vote on are executed
+    /// now.
+    fn commit_wait_end(&mut self, clock: NodeTime, smr_context: &SMRContext) -> Option<NodeTime> {
+        let block_hashes: Vec<_> = self
+            .record_store
+            .committed_states_after(self.tracker.highest_committed_round)
+            .into_iter()
+            .map(|(_, _, _, _, block_hash)| block_hash)
+            .collect();
+        let mut end = clock;
+        for block_hash in &block_hashes {
+            let block_end = self.background_execution_end(*block_hash, clock, smr_context);
+            end = max(end, block_end);
+        }
+        if clock < end {
+            return Some(end);
+        }
+        if !block_hashes.is_empty() {
+            // Forget the committed blocks, as well as the blocks at or below the committed
+            // round that were left out of the chain, since they will never be committed.
+            let committed_round = self.record_store.highest_committed_round();
+            let record_store = &self.record_store;
+            self.background_executions.retain(|block_hash, _| {
+                record_store
+                    .block(*block_hash)
+                    .map_or(false, |block| block.round > committed_round)
+            });
+        }
+        None
+    }
+
     pub fn update_tracker(&mut self, clock: NodeTime) {
         // Ignore actions
         self.tracker.update_tracker(
@@ -134,6 +774,19 @@ impl NodeState {
         );
     }
 
+    pub(crate) fn report_error(&mut self, error: NodeError) {
+        tracing::debug!("{}", error);
+        self.errors.push(error);
+    }
+
+    /// Skip a message that failed strict verification.
+    pub(crate) fn reject_message(&mut self, error: failure::Error) {
+        self.rejected_messages += 1;
+        self.report_error(NodeError::InvalidMessage {
+            reason: error.to_string(),
+        });
+    }
+
     pub fn insert_network_record(
         &mut self,
         epoch_id: EpochId,
@@ -141,22 +794,174 @@ impl NodeState {
         smr_context: &mut SMRContext,
     ) {
         if epoch_id == self.epoch_id {
-            self.record_store.insert_network_record(record, smr_context);
+            let record_store = &mut self.record_store;
+            let result = handler_profile::measure(Handler::RecordInsertion, || {
+                record_store.insert_network_record(record, smr_context)
+            });
+            if let Err(error) = result {
+                self.report_error(NodeError::RecordInsertion {
+                    epoch_id,
+                    reason: error.to_string(),
+                });
+            }
         } else {
-            debug!(
-                "{:?} Skipped records outside the current epoch ({:?} instead of {:?})",
-                self.local_author, epoch_id, self.epoch_id
+            tracing::debug!(
+                epoch = epoch_id.0,
+                "Skipped records outside the current epoch"
+            );
+        }
+    }
+
+    /// Whether the record store of the epoch `epoch_id` already holds `block`, so that a copy
+    /// of it need not be inserted.
+    pub(crate) fn holds_block(&self, epoch_id: EpochId, block: &Block) -> bool {
+        epoch_id == self.epoch_id && self.record_store.is_known_block(block)
+    }
+
+    /// Insert a batch of records in order, checking their signatures in parallel first.
+    pub fn insert_network_records(
+        &mut self,
+        epoch_id: EpochId,
+        records: Vec<Record>,
+        smr_context: &mut SMRContext,
+    ) {
+        if epoch_id == self.epoch_id {
+            let record_store = &mut self.record_store;
+            let result = handler_profile::measure(Handler::RecordInsertion, || {
+                record_store.insert_network_records(records, smr_context)
+            });
+            if let Err(error) = result {
+                self.report_error(NodeError::RecordInsertion {
+                    epoch_id,
+                    reason: error.to_string(),
+                });
+            }
+        } else {
+            tracing::debug!(
+                epoch = epoch_id.0,
+                "Skipped records outside the current epoch"
             );
         }
     }
 }
 
+impl NodeErrors for NodeState {
+    fn take_errors(&mut self) -> Vec<String> {
+        self.errors
+            .drain(..)
+            .map(|error| error.to_string())
+            .collect()
+    }
+}
+
 impl ActiveRound for NodeState {
     fn active_round(&self) -> Round {
         self.pacemaker.active_round()
     }
 }
 
+impl ActiveLeader for NodeState {
+    fn active_leader(&self) -> Option<Author> {
+        self.pacemaker.active_leader()
+    }
+}
+
+impl PeerTracker for NodeState {
+    fn receive_message(&mut self, sender: Author, kind: MessageKind) {
+        self.hear_from(sender);
+        // Peers request the records that they miss, possibly because they restarted and lost
+        // the ones that we believe they hold.
+        if let MessageKind::SyncRequest = kind {
+            if let Some(knowledge) = &mut self.peer_knowledge {
+                knowledge.forget(sender);
+            }
+        }
+    }
+}
+
+impl NodeStatistics for NodeState {
+    fn committed_blocks(&self) -> &[CommittedBlock] {
+        &self.committed_blocks
+    }
+
+    fn quorum_certificate_rounds(&self) -> usize {
+        self.past_record_stores
+            .values()
+            .map(RecordStore::num_quorum_certificates)
+            .sum::<usize>()
+            + self.record_store.num_quorum_certificates()
+    }
+
+    fn highest_quorum_certificate_round(&self) -> Round {
+        self.record_store.highest_quorum_certificate_round()
+    }
+
+    fn timeout_certificate_rounds(&self) -> usize {
+        self.past_record_stores
+            .values()
+            .map(RecordStore::num_timeout_certificates)
+            .sum::<usize>()
+            + self.record_store.num_timeout_certificates()
+    }
+
+    fn timeout_certificate_leaders(&self) -> BTreeMap<Author, usize> {
+        let mut leaders = BTreeMap::new();
+        for record_store in self
+            .past_record_stores
+            .values()
+            .chain(std::iter::once(&self.record_store))
+        {
+            for round in record_store.timeout_certificate_rounds() {
+                *leaders
+                    .entry(Pacemaker::leader(record_store, *round))
+                    .or_insert(0) += 1;
+            }
+        }
+        leaders
+    }
+
+    fn timeout_certificate_rounds_after(&self, round: Round) -> Vec<Round> {
+        self.record_store
+            .timeout_certificate_rounds()
+            .iter()
+            .cloned()
+            .filter(|x| *x > round)
+            .collect()
+    }
+
+    fn pacemaker_switch_times(&self) -> Vec<NodeTime> {
+        self.pacemaker.switch_times().to_vec()
+    }
+
+    fn evicted_records(&self) -> usize {
+        self.past_record_stores
+            .values()
+            .chain(std::iter::once(&self.record_store))
+            .map(|record_store| record_store.evicted_blocks())
+            .sum()
+    }
+
+    fn suppressed_notifications(&self) -> usize {
+        self.peer_knowledge
+            .as_ref()
+            .map_or(0, PeerKnowledge::suppressed_notifications)
+    }
+
+    fn rejected_messages(&self) -> usize {
+        self.rejected_messages
+    }
+
+    fn rejected_proposals(&self) -> usize {
+        self.rejected_proposals
+    }
+
+    fn suspicions(&self) -> usize {
+        self.failure_detector
+            .as_ref()
+            .map_or(0, FailureDetector::suspicions)
+    }
+}
+
 // -- BEGIN FILE process_pacemaker_actions --
 impl NodeState {
     fn process_pacemaker_actions(
@@ -170,19 +975,74 @@ impl NodeState {
         actions.should_broadcast = pacemaker_actions.should_broadcast;
         actions.should_query_all = pacemaker_actions.should_query_all;
         actions.should_send = pacemaker_actions.should_send;
+        if let Some(error) = pacemaker_actions.error {
+            self.report_error(error);
+        }
+        // Observers only follow the rounds of the validators.
+        if self.observer {
+            return actions;
+        }
         if let Some(round) = pacemaker_actions.should_create_timeout {
+            let decision = Decision::Timeout {
+                round,
+                deadline: self.pacemaker.active_round_deadline(),
+                highest_quorum_certificate_round: self
+                    .record_store
+                    .highest_quorum_certificate_round(),
+            };
+            self.log_decision(clock, decision);
+            // Prevent voting at a round for which we have created a timeout already. Persist
+            // this before signing, so that it survives a crash right after the signature.
+            self.safety_rules.record_timeout(round, smr_context);
             self.record_store
                 .create_timeout(self.local_author, round, smr_context);
-            // Prevent voting at a round for which we have created a timeout already.
-            self.latest_voted_round.max_update(round);
         }
         if let Some(previous_qc_hash) = pacemaker_actions.should_propose_block {
-            self.record_store.propose_block(
-                self.local_author,
-                previous_qc_hash,
-                clock,
-                smr_context,
-            );
+            let round = self.pacemaker.active_round();
+            if let Some(end) = self.batch_wait_end(clock, smr_context) {
+                // Wait for more transactions if the batching policy says so.
+                self.log_decision(clock, Decision::WaitForBatch { round, until: end });
+                actions.next_scheduled_update = min(actions.next_scheduled_update, end);
+            } else if let Some(end) = self.empty_block_wait_end(clock, smr_context) {
+                // Wait for a first transaction if the empty-block policy says so. The pacemaker
+                // stops broadcasting and forcing updates until the end of the wait.
+                self.log_decision(clock, Decision::WaitForBatch { round, until: end });
+                self.pacemaker.defer_proposal(end);
+                actions.next_scheduled_update = min(actions.next_scheduled_update, end);
+                // Check again as soon as new transactions arrive.
+                if let Some(arrival) = smr_context.next_arrival(clock) {
+                    actions.next_scheduled_update = min(actions.next_scheduled_update, arrival);
+                }
+            } else {
+                let decision = Decision::Propose {
+                    round,
+                    previous_round: self.record_store.highest_quorum_certificate_round(),
+                };
+                self.log_decision(clock, decision);
+                self.record_store.propose_block(
+                    self.local_author,
+                    previous_qc_hash,
+                    clock,
+                    smr_context,
+                );
+                // Keep track of our blocks until they are committed or abandoned.
+                for block in self.record_store.own_proposed_blocks(self.local_author) {
+                    let is_known = self
+                        .uncommitted_proposals
+                        .iter()
+                        .any(|(_, _, command)| *command == block.command);
+                    if !is_known {
+                        self.uncommitted_proposals.push((
+                            self.epoch_id,
+                            block.round,
+                            block.command.clone(),
+                        ));
+                    }
+                }
+                // The pacemaker may have held off while we waited for transactions.
+                actions.should_broadcast = true;
+                actions.next_scheduled_update = clock;
+            }
         }
         actions
     }
@@ -192,8 +1052,20 @@ impl NodeState {
 // -- BEGIN FILE consensus_node_impl --
 impl<Context: SMRContext> ConsensusNode<Context> for NodeState {
     fn update_node(&mut self, clock: NodeTime, smr_context: &mut Context) -> NodeUpdateActions {
+        let span = self.span();
+        let _entered = span.enter();
+        // Skip the peers that we suspect when synchronizing.
+        let local_author = self.local_author;
+        if let Some(detector) = &mut self.failure_detector {
+            let mut peers = self.record_store.validators();
+            peers.retain(|author| *author != local_author);
+            detector.update(&peers, clock);
+            self.pacemaker
+                .set_suspected_peers(detector.suspected().clone());
+        }
         // Update pacemaker state and process pacemaker actions (e.g., creating a timeout, proposing
         // a block).
+        let previous_round = (self.pacemaker.active_epoch(), self.pacemaker.active_round());
         let pacemaker_actions = self.pacemaker.update_pacemaker(
             self.local_author,
             self.epoch_id,
@@ -201,44 +1073,136 @@ impl<Context: SMRContext> ConsensusNode<Context> for NodeState {
             self.latest_query_all_time,
             clock,
         );
+        if (self.pacemaker.active_epoch(), self.pacemaker.active_round()) != previous_round {
+            let decision = Decision::EnterRound {
+                round: self.pacemaker.active_round(),
+                leader: self.pacemaker.active_leader(),
+            };
+            self.log_decision(clock, decision);
+        }
         let mut actions = self.process_pacemaker_actions(pacemaker_actions, clock, smr_context);
         // Vote on a valid proposal block designated by the pacemaker, if any.
-        if let Some((block_hash, block_round, proposer)) =
-            self.record_store.proposed_block(&self.pacemaker)
+        if let Some((block_hash, block_round, proposer)) = self
+            .record_store
+            .proposed_block(&self.pacemaker)
+            .filter(|_| !self.observer)
         {
             // Enforce voting constraints.
-            if block_round > self.latest_voted_round
-                && self.record_store.previous_round(block_hash) >= self.locked_round
-            {
-                // Update the latest voted round.
-                self.latest_voted_round = block_round;
-                // Update the locked round.
-                self.locked_round = max(
-                    self.locked_round,
-                    self.record_store.second_previous_round(block_hash),
-                );
-                // Try to execute the command contained the a block and create a vote.
-                if self
-                    .record_store
-                    .create_vote(self.local_author, block_hash, smr_context)
-                {
-                    // Ask to notify and send our vote to the author of the block.
-                    actions.should_send = vec![proposer];
+            let previous_round = self.record_store.previous_round(block_hash);
+            if !self.has_valid_payload(block_hash, smr_context) {
+                if self.latest_rejected_proposal != Some(block_hash) {
+                    self.latest_rejected_proposal = Some(block_hash);
+                    self.rejected_proposals += 1;
+                }
+                let decision = Decision::RefuseVote {
+                    round: block_round,
+                    proposer,
+                    reason: VoteRefusal::InvalidPayload,
+                };
+                self.log_decision(clock, decision);
+            } else if self.safety_rules.can_vote(block_round, previous_round) {
+                // Wait until the command of the block is executed, unless it is executed in
+                // the background.
+                let execution_end = if self.pipelined_execution {
+                    self.background_execution_end(block_hash, clock, smr_context);
+                    clock
+                } else {
+                    self.execution_end(block_hash, clock, smr_context)
+                };
+                if clock < execution_end {
+                    let decision = Decision::WaitForExecution {
+                        round: block_round,
+                        until: execution_end,
+                    };
+                    self.log_decision(clock, decision);
+                    actions.next_scheduled_update =
+                        min(actions.next_scheduled_update, execution_end);
+                } else {
+                    // Update the latest voted round and the locked round.
+                    let lock = match self.record_store.commit_rule() {
+                        CommitRule::ThreeChain => {
+                            self.record_store.second_previous_round(block_hash)
+                        }
+                        CommitRule::TwoChain => self.record_store.previous_round(block_hash),
+                    };
+                    let is_allowed = self.safety_rules.record_vote(
+                        block_round,
+                        previous_round,
+                        lock,
+                        smr_context,
+                    );
+                    // Try to execute the command contained the a block and create a vote.
+                    if is_allowed
+                        && self
+                            .record_store
+                            .create_vote(self.local_author, block_hash, smr_context)
+                    {
+                        let decision = Decision::Vote {
+                            round: block_round,
+                            proposer,
+                            previous_round,
+                        };
+                        self.log_decision(clock, decision);
+                        // Ask to notify and send our vote to the author of the block, or to the
+                        // other recipients of votes.
+                        match self.record_store.vote_delivery() {
+                            VoteDelivery::Proposer => actions.should_send = vec![proposer],
+                            VoteDelivery::NextLeader => {
+                                let leader =
+                                    PacemakerState::leader(&self.record_store, block_round + 1);
+                                actions.should_send = vec![leader];
+                            }
+                            VoteDelivery::Broadcast => actions.should_broadcast = true,
+                        }
+                    } else {
+                        let decision = Decision::RefuseVote {
+                            round: block_round,
+                            proposer,
+                            reason: VoteRefusal::UnknownState,
+                        };
+                        self.log_decision(clock, decision);
+                    }
                 }
+            } else if let Some(reason) = self.vote_refusal(block_hash, block_round) {
+                let decision = Decision::RefuseVote {
+                    round: block_round,
+                    proposer,
+                    reason,
+                };
+                self.log_decision(clock, decision);
             }
         }
-        // Check if our last proposal has reached a quorum of votes and create a QC.
-        if self
-            .record_store
-            .check_for_new_quorum_certificate(self.local_author, smr_context)
+        // Check if our last proposal has reached a quorum of votes and create a QC, unless we
+        // are still waiting for the votes of all validators.
+        let fast_path_wait_end = self.fast_path_wait_end(clock);
+        if let Some(end) = fast_path_wait_end {
+            let round = self.record_store.current_round();
+            self.log_decision(clock, Decision::WaitForVotes { round, until: end });
+            actions.next_scheduled_update = min(actions.next_scheduled_update, end);
+        }
+        if fast_path_wait_end.is_none()
+            && self
+                .record_store
+                .check_for_new_quorum_certificate(self.local_author, smr_context)
         {
+            let round = self.record_store.highest_quorum_certificate_round();
+            self.log_decision(clock, Decision::CreateQuorumCertificate { round });
             // Broadcast the QC to finish our work as a leader.
             actions.should_broadcast = true;
             // Schedule a new run now to process the new QC.
             actions.next_scheduled_update = clock;
         }
+        // With pipelined execution, deliver commits only once the committed blocks are executed.
+        if self.pipelined_execution {
+            if let Some(end) = self.commit_wait_end(clock, smr_context) {
+                actions.next_scheduled_update = min(actions.next_scheduled_update, end);
+                self.push_pull_actions(&mut actions, clock);
+                self.suppress_redundant_notifications(&mut actions);
+                return actions;
+            }
+        }
         // Check for new commits and verify if we should start a new epoch.
-        self.process_commits(smr_context);
+        self.process_commits(clock, smr_context);
         // Update the commit tracker and ask that we query all nodes if needed.
         let tracker_actions = self.tracker.update_tracker(
             self.latest_query_all_time,
@@ -255,48 +1219,257 @@ impl<Context: SMRContext> ConsensusNode<Context> for NodeState {
         if actions.should_query_all {
             self.latest_query_all_time = clock;
         }
+        self.push_pull_actions(&mut actions, clock);
+        self.suppress_redundant_notifications(&mut actions);
         // Return desired actions to main handler.
         actions
     }
 }
 // -- END FILE --
 
+impl<Context: SMRContext> RecoverableNode<Context> for NodeState {
+    fn recover(&mut self, clock: NodeTime, smr_context: &mut Context) {
+        let span = self.span();
+        let _entered = span.enter();
+        // Blocks, certificates, and commits are durable, unlike the votes, timeouts, and timers
+        // of the current round and the executions in progress, unless the records are lost.
+        if self.durable_records {
+            self.record_store.restart();
+        } else {
+            self.record_store.erase();
+            self.record_losses += 1;
+        }
+        self.pacemaker = self.pacemaker.restart(clock);
+        self.pending_execution = None;
+        self.background_executions = HashMap::new();
+        self.executor_free_time = clock;
+        self.batch_wait_start = None;
+        self.fast_path_wait_start = None;
+        self.push_pull = self.push_pull.as_ref().map(PushPullState::restart);
+        self.peer_knowledge = self.peer_knowledge.as_ref().map(PeerKnowledge::restart);
+        self.failure_detector = self.failure_detector.as_ref().map(FailureDetector::restart);
+        // Reload the voting constraints of the current epoch, or start over if they were lost.
+        self.safety_rules = if self.durable_safety_data {
+            SafetyRules::load(self.epoch_id, &*smr_context)
+        } else {
+            let safety_rules = SafetyRules::new(self.epoch_id);
+            safety_rules.save(smr_context);
+            safety_rules
+        };
+        let decision = Decision::Recover {
+            latest_voted_round: self.safety_rules.latest_voted_round(),
+            locked_round: self.safety_rules.locked_round(),
+        };
+        self.log_decision(clock, decision);
+    }
+}
+
 // -- BEGIN FILE process_commits --
 impl NodeState {
-    pub fn process_commits(&mut self, smr_context: &mut SMRContext) {
+    pub fn process_commits(&mut self, clock: NodeTime, smr_context: &mut SMRContext) {
+        let mut committed_commands = Vec::new();
         // For all commits that have not been processed yet, according to the commit tracker..
-        for (round, state) in self
+        for (round, state, proposer, proposal_time, block_hash) in self
             .record_store
             .committed_states_after(self.tracker.highest_committed_round)
         {
             // .. deliver the committed state to the SMR layer, together with a commit certificate,
-            // if any.
-            if round == self.record_store.highest_committed_round() {
+            // if any. Commits by the fast path have none.
+            let is_fast_path_commit = self.record_store.is_fast_path_commit(round);
+            if round == self.record_store.highest_committed_round() && !is_fast_path_commit {
                 smr_context.commit(&state, self.record_store.highest_commit_certificate())
             } else {
                 smr_context.commit(&state, None);
             };
+            // Blocks chained in the same round are committed together.
+            let blocks = self.record_store.round_chain(block_hash);
+            let transactions = blocks
+                .iter()
+                .flat_map(|block| block.command.transactions.iter().cloned())
+                .collect();
+            committed_commands.extend(
+                blocks
+                    .iter()
+                    .filter(|block| block.author == self.local_author)
+                    .map(|block| block.command.clone()),
+            );
+            self.committed_blocks.push(CommittedBlock {
+                round,
+                proposer,
+                proposal_time,
+                hash: block_hash.0,
+                transactions,
+                fast_path: is_fast_path_commit,
+            });
+            let decision = Decision::Commit {
+                round,
+                fast_path: is_fast_path_commit,
+            };
+            self.log_decision(clock, decision);
             // .. check if the current epoch just ended. If it did..
             let new_epoch_id = smr_context.read_epoch_id(&state);
             if new_epoch_id > self.epoch_id {
-                // .. create a new record store and switch to the new epoch.
-                let new_record_store = RecordStoreState::new(
-                    new_epoch_id.initial_hash(),
-                    state.clone(),
-                    new_epoch_id,
-                    smr_context.configuration(&state),
-                );
-                let old_record_store = std::mem::replace(&mut self.record_store, new_record_store);
-                self.past_record_stores
-                    .insert(self.epoch_id, old_record_store);
-                self.epoch_id = new_epoch_id;
-                // .. initialize voting constraints.
-                self.latest_voted_round = Round(0);
-                self.locked_round = Round(0);
-                // .. stop delivering commits after an epoch change.
+                // .. switch to the new epoch and stop delivering commits.
+                self.enter_epoch(new_epoch_id, state, clock, smr_context);
                 break;
             }
         }
+        self.release_abandoned_proposals(&committed_commands, smr_context);
+    }
+
+    /// Give the transactions of our blocks that can no longer be committed back to the SMR
+    /// layer: the committed chain either left them out of its rounds, or ended their epoch.
+    fn release_abandoned_proposals(
+        &mut self,
+        committed_commands: &[Command],
+        smr_context: &mut SMRContext,
+    ) {
+        let committed_round = self.record_store.highest_committed_round();
+        let proposals = std::mem::replace(&mut self.uncommitted_proposals, Vec::new());
+        for (epoch_id, round, command) in proposals {
+            if committed_commands.contains(&command) {
+                continue;
+            }
+            if epoch_id < self.epoch_id || round <= committed_round {
+                smr_context.release(&command);
+            } else {
+                self.uncommitted_proposals.push((epoch_id, round, command));
+            }
+        }
+    }
+
+    /// Switch to the epoch `new_epoch_id`, starting at the committed state `state`.
+    fn enter_epoch(
+        &mut self,
+        new_epoch_id: EpochId,
+        state: State,
+        clock: NodeTime,
+        smr_context: &mut SMRContext,
+    ) {
+        // Create a new record store.
+        let new_record_store = RecordStoreState::new(
+            new_epoch_id.initial_hash(),
+            state.clone(),
+            new_epoch_id,
+            smr_context.configuration(&state),
+            self.record_store.commit_rule(),
+        )
+        .with_vote_delivery(self.record_store.vote_delivery())
+        .with_fast_path(self.fast_path_wait.is_some())
+        .with_aggregate_signatures(self.record_store.aggregate_signatures())
+        .with_threshold_signatures(self.record_store.threshold_signatures())
+        .with_blocks_per_round(self.record_store.blocks_per_round())
+        .with_max_uncertified_blocks(self.record_store.max_uncertified_blocks());
+        let old_record_store = std::mem::replace(&mut self.record_store, new_record_store);
+        self.past_record_stores
+            .insert(self.epoch_id, old_record_store);
+        self.epoch_id = new_epoch_id;
+        // Blocks of the previous epoch that are not committed by now never will be.
+        self.background_executions.clear();
+        let decision = Decision::NewEpoch {
+            epoch_id: new_epoch_id,
+        };
+        self.log_decision(clock, decision);
+        // Apply the changes of protocol parameters committed so far.
+        self.pacemaker
+            .apply_parameter_changes(&smr_context.parameter_changes(&state));
+        // Initialize voting constraints.
+        self.safety_rules = SafetyRules::new(new_epoch_id);
+        self.safety_rules.save(smr_context);
+        // Keep a checkpoint of the state for lagging nodes.
+        if self.state_sync {
+            let committed_blocks = self.committed_blocks.len();
+            self.state_checkpoint = smr_context
+                .snapshot(&state)
+                .map(|snapshot| StateCheckpoint {
+                    epoch_id: new_epoch_id,
+                    state,
+                    snapshot,
+                    committed_blocks,
+                });
+        }
+    }
+
+    /// Record that `sender` vouches for `checkpoint`. Returns the checkpoint and the blocks
+    /// committed before it once senders with more than `f` votes agree on both, so that at least
+    /// one of them is honest. Votes are those of the current epoch, the latest that we know.
+    /// Senders leave out the blocks that we had committed at the time of our request, so they
+    /// only need to agree on the blocks that they have in common, i.e. at the end. Each sender
+    /// endorses at most one checkpoint at a time, the latest that it sent.
+    pub(crate) fn endorse_checkpoint(
+        &mut self,
+        sender: Author,
+        checkpoint: StateCheckpoint,
+        blocks: Vec<CommittedBlock>,
+    ) -> Option<(StateCheckpoint, Vec<CommittedBlock>)> {
+        let epoch_id = self.epoch_id;
+        for endorsements in self.checkpoint_endorsements.values_mut() {
+            endorsements.remove(&sender);
+        }
+        self.checkpoint_endorsements
+            .retain(|checkpoint, endorsements| {
+                checkpoint.epoch_id > epoch_id && !endorsements.is_empty()
+            });
+        let endorsements = self
+            .checkpoint_endorsements
+            .entry(checkpoint.clone())
+            .or_insert_with(BTreeMap::new);
+        let agreeing_senders: Vec<_> = endorsements
+            .iter()
+            .filter(|(author, other_blocks)| {
+                **author != sender
+                    && other_blocks
+                        .iter()
+                        .rev()
+                        .zip(blocks.iter().rev())
+                        .all(|(other_block, block)| other_block.hash == block.hash)
+            })
+            .map(|(author, _)| *author)
+            .chain(std::iter::once(sender))
+            .collect();
+        endorsements.insert(sender, blocks.clone());
+        let configuration = self.record_store.configuration();
+        if configuration.count_votes(&agreeing_senders) < configuration.validity_threshold() {
+            return None;
+        }
+        self.checkpoint_endorsements.remove(&checkpoint);
+        Some((checkpoint, blocks))
+    }
+
+    /// Resume execution from the checkpoint of another node, after the blocks committed up to
+    /// the checkpoint, some of which we may know already.
+    pub(crate) fn install_checkpoint(
+        &mut self,
+        checkpoint: StateCheckpoint,
+        blocks: Vec<CommittedBlock>,
+        clock: NodeTime,
+        smr_context: &mut SMRContext,
+    ) -> Result<()> {
+        let known_blocks = self.committed_blocks.len();
+        let first_block = match checkpoint.committed_blocks.checked_sub(blocks.len()) {
+            Some(first_block) if first_block <= known_blocks => first_block,
+            _ => bail!("The committed blocks leave a gap after ours"),
+        };
+        ensure!(
+            known_blocks < checkpoint.committed_blocks,
+            "The checkpoint is not ahead of our commits"
+        );
+        ensure!(
+            blocks
+                .iter()
+                .zip(&self.committed_blocks[first_block..])
+                .all(|(block, known_block)| block.hash == known_block.hash),
+            "The committed blocks conflict with ours"
+        );
+        ensure!(
+            smr_context.install_snapshot(&checkpoint.state, &checkpoint.snapshot),
+            "The snapshot does not match the state"
+        );
+        self.committed_blocks
+            .extend(blocks.into_iter().skip(known_blocks - first_block));
+        self.installed_checkpoints += 1;
+        self.enter_epoch(checkpoint.epoch_id, checkpoint.state, clock, smr_context);
+        Ok(())
     }
 }
 // -- END FILE --
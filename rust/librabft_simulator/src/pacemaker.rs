@@ -5,10 +5,14 @@ use std::{
     cmp::{max, min},
     collections::hash_map::DefaultHasher,
     hash::{Hash, Hasher},
+    str::FromStr,
 };
 
 use super::*;
+use error::NodeError;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use record_store::*;
+use serde::{Deserialize, Serialize};
 
 #[cfg(test)]
 #[path = "unit_tests/pacemaker_tests.rs"]
@@ -29,6 +33,8 @@ pub struct PacemakerUpdateActions {
     pub should_query_all: bool,
     /// Time at which to call `update_pacemaker` again, at the latest.
     pub next_scheduled_update: NodeTime,
+    /// Error met during the update, if any.
+    pub error: Option<NodeError>,
 }
 // -- END FILE --
 
@@ -75,9 +81,119 @@ pub struct PacemakerState {
     gamma: f64,
     /// Coefficient to control the frequency of query-all actions.
     lambda: f64,
+    /// Largest fraction of a round duration added at random, so that nodes time out at
+    /// different times.
+    jitter: f64,
+    /// Seed of the simulation, from which jitter samples are derived.
+    jitter_seed: u64,
+    /// When to broadcast our proposal or timeout of the active round again, if at all.
+    retransmission: Option<RetransmissionPolicy>,
+    /// Nodes to synchronize with when a round starts.
+    peer_selection: PeerSelection,
+    /// Peers that our failure detector suspects, skipped when synchronizing.
+    suspected_peers: BTreeSet<Author>,
+    /// Time of our latest broadcast in the active round.
+    latest_broadcast_time: NodeTime,
+    /// Number of retransmissions since that of the proposal or timeout.
+    retransmissions: usize,
+    /// Time until which we defer our proposal of the active round, if the node chose to wait
+    /// for transactions.
+    deferred_proposal: Option<NodeTime>,
 }
 // -- END FILE --
 
+/// Schedule of the retransmissions of a proposal or timeout while its round lasts, in case
+/// the first broadcast was lost.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct RetransmissionPolicy {
+    /// Time between the first broadcast and the first retransmission.
+    pub period: Duration,
+    /// Factor applied to the time between retransmissions after each of them.
+    pub backoff: f64,
+}
+
+/// Nodes that a node sends its records to when a round starts, so that they catch up.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum PeerSelection {
+    /// The leader of the round.
+    Leader,
+    /// `count` validators picked at random for each round.
+    Random { count: usize },
+    /// The validators in the region of the leader, where `regions[i]` is the region of the
+    /// node `i`. Nodes without a region are alone in theirs.
+    LeaderRegion { regions: Vec<usize> },
+    /// A fixed set of nodes.
+    Relays { relays: Vec<Author> },
+}
+
+impl Default for PeerSelection {
+    fn default() -> Self {
+        PeerSelection::Leader
+    }
+}
+
+impl PeerSelection {
+    /// Check the policy in a network of `nodes` nodes.
+    pub fn check(&self, nodes: usize) -> Result<()> {
+        match self {
+            PeerSelection::Leader => (),
+            PeerSelection::Random { count } => {
+                ensure!(*count > 0, "Nodes must send to at least one random peer")
+            }
+            PeerSelection::LeaderRegion { regions } => ensure!(
+                regions.len() <= nodes,
+                "Regions are given for {} nodes, but there are only {}",
+                regions.len(),
+                nodes
+            ),
+            PeerSelection::Relays { relays } => {
+                ensure!(!relays.is_empty(), "Relay sets cannot be empty");
+                for relay in relays {
+                    ensure!(relay.0 < nodes, "Unknown relay {}", relay.0);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PeerSelection {
+    type Err = failure::Error;
+
+    /// Parse `leader`, `random:COUNT`, `leader-region:REGION,...`, or `relays:AUTHOR,...`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let fields: Vec<_> = s.splitn(2, ':').collect();
+        let numbers = || -> Result<Vec<usize>> {
+            match fields.get(1) {
+                Some(values) => values
+                    .split(',')
+                    .map(|x| {
+                        x.parse::<usize>()
+                            .map_err(|_| format_err!("Invalid number: {}", x))
+                    })
+                    .collect(),
+                None => bail!("Missing values in peer selection: {}", s),
+            }
+        };
+        let selection = match fields[0] {
+            "leader" if fields.len() == 1 => PeerSelection::Leader,
+            "random" => match numbers()?.as_slice() {
+                [count] => PeerSelection::Random { count: *count },
+                _ => bail!("Expected a single count of peers: {}", s),
+            },
+            "leader-region" => PeerSelection::LeaderRegion {
+                regions: numbers()?,
+            },
+            "relays" => PeerSelection::Relays {
+                relays: numbers()?.into_iter().map(Author).collect(),
+            },
+            _ => bail!("Unknown peer selection: {}", s),
+        };
+        Ok(selection)
+    }
+}
+
 impl PacemakerState {
     pub fn new(
         epoch_id: EpochId,
@@ -91,13 +207,86 @@ impl PacemakerState {
             active_round: Round(0),
             active_leader: None,
             active_round_start_time: node_time,
-            active_round_duration: 0,
+            active_round_duration: Duration::ZERO,
             delta,
             gamma,
             lambda,
+            jitter: 0.0,
+            jitter_seed: 0,
+            retransmission: None,
+            peer_selection: PeerSelection::Leader,
+            suspected_peers: BTreeSet::new(),
+            latest_broadcast_time: node_time,
+            retransmissions: 0,
+            deferred_proposal: None,
         }
     }
 
+    /// Lengthen each round by up to `jitter` times its duration, at random depending on `seed`,
+    /// the node, and the round.
+    pub fn with_jitter(mut self, jitter: f64, seed: u64) -> Self {
+        self.jitter = jitter;
+        self.jitter_seed = seed;
+        self
+    }
+
+    /// Broadcast our proposal or timeout of the active round again on the given schedule.
+    pub fn with_retransmission(mut self, retransmission: Option<RetransmissionPolicy>) -> Self {
+        self.retransmission = retransmission;
+        self
+    }
+
+    /// Synchronize with the nodes chosen by `peer_selection` when a round starts.
+    pub fn with_peer_selection(mut self, peer_selection: PeerSelection) -> Self {
+        self.peer_selection = peer_selection;
+        self
+    }
+
+    /// Skip the `peers` suspected by a failure detector when synchronizing. Leaders do not
+    /// change, since all the nodes must agree on them.
+    pub fn set_suspected_peers(&mut self, peers: BTreeSet<Author>) {
+        self.suspected_peers = peers;
+    }
+
+    /// Defer our proposal of the active round until `until`, or until the end of the round for
+    /// `NodeTime::never()`. Until then, updates still ask for a proposal, but neither broadcast
+    /// nor force another update, so that the node checks for transactions at its own pace.
+    pub fn defer_proposal(&mut self, until: NodeTime) {
+        self.deferred_proposal = Some(until);
+    }
+
+    /// Change the duration of the first round after a QC, from the next round on.
+    pub(crate) fn set_delta(&mut self, delta: Duration) {
+        self.delta = delta;
+    }
+
+    /// Apply governance changes of the parameters, from the next round on.
+    pub(crate) fn apply_parameter_changes(&mut self, changes: &ParameterChange) {
+        self.delta = changes.delta.unwrap_or(self.delta);
+        self.gamma = changes.gamma.unwrap_or(self.gamma);
+        self.lambda = changes.lambda.unwrap_or(self.lambda);
+    }
+
+    /// Time at which the active round times out, unless it ends earlier.
+    pub fn active_round_deadline(&self) -> NodeTime {
+        self.active_round_start_time + self.active_round_duration
+    }
+
+    /// A pacemaker with the same parameters that starts over at `node_time`, e.g. after a
+    /// restart of the node.
+    pub fn restart(&self, node_time: NodeTime) -> PacemakerState {
+        PacemakerState::new(
+            self.active_epoch,
+            node_time,
+            self.delta,
+            self.gamma,
+            self.lambda,
+        )
+        .with_jitter(self.jitter, self.jitter_seed)
+        .with_retransmission(self.retransmission)
+        .with_peer_selection(self.peer_selection.clone())
+    }
+
     pub fn leader(record_store: &RecordStore, round: Round) -> Author {
         let mut hasher = DefaultHasher::new();
         round.hash(&mut hasher);
@@ -105,19 +294,88 @@ impl PacemakerState {
         author
     }
 
-    fn duration(&self, record_store: &RecordStore, round: Round) -> Duration {
-        let highest_commit_certificate_round = if record_store.highest_committed_round() > Round(0)
-        {
-            record_store.highest_committed_round() + 2
-        } else {
-            Round(0)
-        };
-        assert!(
-            round > highest_commit_certificate_round,
-            "Active round is higher than any QC round."
-        );
+    fn duration(
+        &self,
+        record_store: &RecordStore,
+        round: Round,
+    ) -> std::result::Result<Duration, NodeError> {
+        let highest_commit_certificate_round = record_store.highest_commit_certificate_round();
+        if round <= highest_commit_certificate_round {
+            return Err(NodeError::InvalidRound {
+                round,
+                highest_commit_certificate_round,
+            });
+        }
         let n = round.0 - highest_commit_certificate_round.0;
-        ((self.delta as f64) * (n as f64).powf(self.gamma)) as Duration
+        Ok(self.delta.mul_f64((n as f64).powf(self.gamma)))
+    }
+
+    /// The duration of the active round for the node `author`, with jitter. Samples are drawn
+    /// from an RNG seeded by the simulation seed, the node, and the round, so that runs with the
+    /// same seed stay reproducible.
+    fn jittered(&self, author: Author, duration: Duration) -> Duration {
+        if self.jitter <= 0.0 {
+            return duration;
+        }
+        let mut hasher = DefaultHasher::new();
+        (
+            self.jitter_seed,
+            author,
+            self.active_epoch,
+            self.active_round,
+        )
+            .hash(&mut hasher);
+        let sample: f64 = StdRng::seed_from_u64(hasher.finish()).gen();
+        duration.mul_f64(1.0 + self.jitter * sample)
+    }
+
+    /// Nodes other than `local_author` to synchronize with at the start of the active round.
+    /// Suspected peers are skipped when we pick peers at random or among relays, but never the
+    /// active leader, since it needs our records to propose.
+    fn peers(&self, local_author: Author, record_store: &RecordStore) -> Vec<Author> {
+        let peers = match &self.peer_selection {
+            PeerSelection::Leader => self.active_leader.into_iter().collect(),
+            PeerSelection::Random { count } => {
+                // Shuffle the validators in a reproducible way.
+                let mut validators = record_store.validators();
+                validators.retain(|author| {
+                    *author != local_author && !self.suspected_peers.contains(author)
+                });
+                validators.sort_by_key(|author| {
+                    let mut hasher = DefaultHasher::new();
+                    (author, local_author, self.active_epoch, self.active_round).hash(&mut hasher);
+                    hasher.finish()
+                });
+                validators.truncate(*count);
+                validators
+            }
+            PeerSelection::LeaderRegion { regions } => match self.active_leader {
+                Some(leader) => {
+                    let region = regions.get(leader.0);
+                    let mut validators = record_store.validators();
+                    validators.retain(|author| {
+                        *author == leader || (region.is_some() && regions.get(author.0) == region)
+                    });
+                    validators
+                }
+                None => Vec::new(),
+            },
+            PeerSelection::Relays { relays } => relays
+                .iter()
+                .filter(|author| !self.suspected_peers.contains(author))
+                .cloned()
+                .collect(),
+        };
+        peers
+            .into_iter()
+            .filter(|author| *author != local_author)
+            .collect()
+    }
+
+    /// Time of the next retransmission in the active round.
+    fn retransmission_deadline(&self, policy: RetransmissionPolicy) -> NodeTime {
+        let factor = policy.backoff.powi(self.retransmissions as i32);
+        self.latest_broadcast_time + policy.period.mul_f64(factor)
     }
 }
 
@@ -130,6 +388,7 @@ impl PacemakerUpdateActions {
             should_broadcast: false,
             should_query_all: false,
             should_propose_block: None,
+            error: None,
         }
     }
 }
@@ -160,22 +419,38 @@ impl Pacemaker for PacemakerState {
             self.active_round = active_round;
             // .. start a timer
             self.active_round_start_time = clock;
+            self.deferred_proposal = None;
             // .. compute the leader
             self.active_leader = Some(Self::leader(record_store, active_round));
-            // .. compute the duration
-            self.active_round_duration = self.duration(record_store, active_round);
-            // .. synchronize with the leader.
+            // .. compute the duration, or fall back to the duration of the first round.
+            let duration = match self.duration(record_store, active_round) {
+                Ok(duration) => duration,
+                Err(error) => {
+                    actions.error = Some(error);
+                    self.delta
+                }
+            };
+            self.active_round_duration = self.jittered(local_author, duration);
+            // .. synchronize with the leader, or the peers of our policy.
             if self.active_leader != Some(local_author) {
-                actions.should_send = self.active_leader.into_iter().collect();
+                actions.should_send = self.peers(local_author, record_store);
             }
         }
         // If we are the leader and have not proposed yet..
         if self.active_leader == Some(local_author) && record_store.proposed_block(&*self) == None {
             // .. propose a block on top of the highest QC that we know.
             actions.should_propose_block = Some(record_store.highest_quorum_certificate_hash());
-            actions.should_broadcast = true;
-            // .. force an immediate update to vote on our own proposal.
-            actions.next_scheduled_update = clock;
+            match self.deferred_proposal {
+                // .. unless we are waiting for transactions, until the end of the wait.
+                Some(until) if clock < until => {
+                    actions.next_scheduled_update = min(actions.next_scheduled_update, until);
+                }
+                _ => {
+                    actions.should_broadcast = true;
+                    // .. force an immediate update to vote on our own proposal.
+                    actions.next_scheduled_update = clock;
+                }
+            }
         }
         if !record_store.has_timeout(local_author, active_round) {
             let timeout_deadline = self.active_round_start_time + self.active_round_duration;
@@ -191,7 +466,7 @@ impl Pacemaker for PacemakerState {
             }
         } else {
             // Otherwise, enforce frequent query-all actions if we stay too long on the same round.
-            let period = (self.lambda * self.active_round_duration as f64) as i64;
+            let period = self.active_round_duration.mul_f64(self.lambda);
             let mut query_all_deadline = latest_query_all_time + period;
             if clock >= query_all_deadline {
                 actions.should_query_all = true;
@@ -199,6 +474,26 @@ impl Pacemaker for PacemakerState {
             }
             actions.next_scheduled_update = min(actions.next_scheduled_update, query_all_deadline);
         }
+        if let Some(policy) = self.retransmission {
+            let has_proposed = self.active_leader == Some(local_author)
+                && record_store.proposed_block(&*self) != None;
+            if actions.should_broadcast {
+                // Retransmissions start over from our new proposal or timeout.
+                self.latest_broadcast_time = clock;
+                self.retransmissions = 0;
+            } else if has_proposed || record_store.has_timeout(local_author, active_round) {
+                // Broadcast our records again if the round lasts beyond the next deadline.
+                if clock >= self.retransmission_deadline(policy) {
+                    actions.should_broadcast = true;
+                    self.latest_broadcast_time = clock;
+                    self.retransmissions += 1;
+                }
+                actions.next_scheduled_update = min(
+                    actions.next_scheduled_update,
+                    self.retransmission_deadline(policy),
+                );
+            }
+        }
         // Return all computed actions.
         actions
     }
@@ -0,0 +1,528 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Proptest strategies and harness functions for the protocol handlers.
+//!
+//! Enabled with the `proptest` feature. Honest network histories are generated by running the
+//! protocol on a single record store with arbitrary choices of voters and timeouts. Harness
+//! functions then deliver records to fresh record stores, or call a pacemaker, and check
+//! properties that any correct implementation must preserve. A differential harness delivers
+//! the same records to two record stores and checks that they behave the same.
+
+use super::*;
+use proptest::{collection::vec, prelude::*, sample::select};
+use std::{collections::BTreeSet, sync::Arc};
+
+pub use base_types::{BlockHash, Command, EpochId, QuorumCertificateHash, State};
+pub use pacemaker::{Pacemaker, PacemakerState, PacemakerUpdateActions};
+pub use record::{Block, QuorumCertificate, Record, Timeout, Vote};
+pub use record_store::{RecordStore, RecordStoreState};
+
+use smr_context::EpochReader;
+
+#[cfg(test)]
+#[path = "unit_tests/property_testing_tests.rs"]
+mod property_testing_tests;
+
+/// Rounds and hashes of arbitrary records are drawn from small ranges so that some of them
+/// collide with the records of honest histories.
+const MAX_ARBITRARY_ROUND: usize = 20;
+const MAX_ARBITRARY_HASH: u64 = 20;
+/// Time between the steps of a history.
+const STEP_DURATION: Duration = Duration::from_millis(10);
+
+/// What happens during one step of an honest history.
+#[derive(Clone, Debug)]
+pub enum RoundStep {
+    /// The leader of the current round proposes a block (unless it already did) and the given
+    /// nodes vote for it. The leader creates a QC as soon as the votes form a quorum.
+    Propose { voters: BTreeSet<usize> },
+    /// The given nodes time out in the current round.
+    Timeout { authors: BTreeSet<usize> },
+}
+
+/// Records created by honest nodes, in creation order.
+#[derive(Clone, Debug)]
+pub struct NetworkHistory {
+    pub num_nodes: usize,
+    pub records: Vec<Record>,
+}
+
+impl NetworkHistory {
+    pub fn new(num_nodes: usize, steps: &[RoundStep]) -> Self {
+        let (mut store, mut context) = initial_record_store(num_nodes);
+        let mut records = Vec::new();
+        let mut command_indices = vec![0; num_nodes];
+        let mut proposal = None;
+        let mut votes = BTreeSet::new();
+        let mut timeouts = BTreeSet::new();
+        for (index, step) in steps.iter().enumerate() {
+            let round = store.current_round();
+            let clock = NodeTime(0) + STEP_DURATION * index as i64;
+            let leader = PacemakerState::leader(&store, round);
+            match step {
+                RoundStep::Propose { voters } => {
+                    let block_hash = match proposal {
+                        Some((proposal_round, block_hash)) if proposal_round == round => block_hash,
+                        _ => {
+                            let command = Command {
+                                proposer: leader,
+                                index: command_indices[leader.0],
+                                transactions: Vec::new(),
+                                parameter_change: None,
+                            };
+                            command_indices[leader.0] += 1;
+                            let block = Record::make_block(
+                                command,
+                                clock,
+                                store.highest_quorum_certificate_hash(),
+                                round,
+                                leader,
+                            );
+                            let block_hash = BlockHash(block.digest());
+                            store
+                                .insert_network_record(block.clone(), &mut context)
+                                .unwrap();
+                            records.push(block);
+                            proposal = Some((round, block_hash));
+                            block_hash
+                        }
+                    };
+                    for voter in voters {
+                        let author = Author(*voter);
+                        if !votes.insert((round, author))
+                            || !store.create_vote(author, block_hash, &mut context)
+                        {
+                            continue;
+                        }
+                        if let Some(vote) = store.current_vote(author) {
+                            records.push(Record::Vote(vote));
+                        }
+                    }
+                    if store.check_for_new_quorum_certificate(leader, &mut context) {
+                        let qc = store.shared_highest_quorum_certificate().unwrap();
+                        records.push(Record::QuorumCertificate(qc.clone()));
+                    }
+                }
+                RoundStep::Timeout { authors } => {
+                    for author in authors {
+                        let author = Author(*author);
+                        if !timeouts.insert((round, author)) {
+                            continue;
+                        }
+                        let timeout = Record::make_timeout(
+                            EpochId(0),
+                            round,
+                            store.highest_quorum_certificate_round(),
+                            author,
+                        );
+                        store
+                            .insert_network_record(timeout.clone(), &mut context)
+                            .unwrap();
+                        records.push(timeout);
+                    }
+                }
+            }
+        }
+        NetworkHistory { num_nodes, records }
+    }
+
+    /// Hashes of the blocks proposed in the history.
+    pub fn block_hashes(&self) -> BTreeSet<BlockHash> {
+        self.records
+            .iter()
+            .filter_map(|record| match record {
+                Record::Block(_) => Some(BlockHash(record.digest())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A record store of a node at the beginning of the initial epoch, with the context used to
+/// execute blocks.
+pub fn initial_record_store(num_nodes: usize) -> (RecordStoreState, SimulatedContext) {
+    // Commands never fill an epoch.
+    let context = SimulatedContext::new(Author(0), num_nodes, std::usize::MAX);
+    let initial_state = context.last_committed_state();
+    let epoch_id = EpochId(0);
+    let store = RecordStoreState::new(
+        epoch_id.initial_hash(),
+        initial_state.clone(),
+        epoch_id,
+        context.configuration(&initial_state),
+        CommitRule::default(),
+    );
+    (store, context)
+}
+
+pub fn arb_round_step(num_nodes: usize) -> impl Strategy<Value = RoundStep> {
+    let authors = || prop::collection::btree_set(0..num_nodes, 0..num_nodes + 1);
+    prop_oneof![
+        3 => authors().prop_map(|voters| RoundStep::Propose { voters }),
+        1 => authors().prop_map(|authors| RoundStep::Timeout { authors }),
+    ]
+}
+
+pub fn arb_network_history(
+    num_nodes: usize,
+    max_steps: usize,
+) -> impl Strategy<Value = NetworkHistory> {
+    vec(arb_round_step(num_nodes), 0..max_steps)
+        .prop_map(move |steps| NetworkHistory::new(num_nodes, &steps))
+}
+
+/// Record store of a node that received an honest history in order.
+pub fn arb_record_store(
+    num_nodes: usize,
+    max_steps: usize,
+) -> impl Strategy<Value = (RecordStoreState, SimulatedContext)> {
+    arb_network_history(num_nodes, max_steps).prop_map(move |history| {
+        let (mut store, mut context) = initial_record_store(num_nodes);
+        for record in history.records {
+            store.insert_network_record(record, &mut context).unwrap();
+        }
+        (store, context)
+    })
+}
+
+/// Arbitrary records with valid signatures, e.g. sent by Byzantine nodes among `authors`.
+pub fn arb_signed_record(authors: Vec<Author>) -> impl Strategy<Value = Record> {
+    let author = move || select(authors.clone());
+    let round = || (0..MAX_ARBITRARY_ROUND).prop_map(Round);
+    let hash = || 0..MAX_ARBITRARY_HASH;
+    let state = || any::<u64>().prop_map(State);
+    prop_oneof![
+        (any::<usize>(), 0..1000i64, hash(), round(), author()).prop_map(
+            |(index, time, qc_hash, round, author)| {
+                let command = Command {
+                    proposer: author,
+                    index,
+                    transactions: Vec::new(),
+                    parameter_change: None,
+                };
+                let qc_hash = QuorumCertificateHash(qc_hash);
+                Record::make_block(command, NodeTime(time), qc_hash, round, author)
+            }
+        ),
+        (round(), hash(), state(), author(), any::<Option<u64>>()).prop_map(
+            |(round, block_hash, state, author, committed_state)| {
+                let block_hash = BlockHash(block_hash);
+                let committed_state = committed_state.map(State);
+                Record::make_vote(
+                    EpochId(0),
+                    round,
+                    block_hash,
+                    state,
+                    author,
+                    committed_state,
+                )
+            }
+        ),
+        (round(), round(), author()).prop_map(|(round, certified_round, author)| {
+            Record::make_timeout(EpochId(0), round, certified_round, author)
+        }),
+        (round(), hash(), state(), vec(author(), 0..4), author()).prop_map(
+            |(round, block_hash, state, voters, author)| {
+                let block_hash = BlockHash(block_hash);
+                let signatures = voters
+                    .into_iter()
+                    .map(|voter| (voter, Signature::default()))
+                    .collect();
+                Record::make_quorum_certificate(
+                    EpochId(0),
+                    round,
+                    block_hash,
+                    state,
+                    MultiSignature::Individual(signatures),
+                    None,
+                    author,
+                )
+            }
+        ),
+    ]
+}
+
+/// Arbitrary records whose signatures are most likely invalid.
+pub fn arb_forged_record(num_nodes: usize) -> impl Strategy<Value = Record> {
+    let authors = (0..num_nodes).map(Author).collect();
+    (arb_signed_record(authors), any::<u64>())
+        .prop_map(|(record, signature)| forge(record, Signature::forged(signature)))
+}
+
+/// Replace the signature of a record.
+pub fn forge(mut record: Record, signature: Signature) -> Record {
+    match &mut record {
+        Record::Block(block) => Arc::make_mut(block).signature = signature,
+        Record::Vote(vote) => vote.signature = signature,
+        Record::QuorumCertificate(qc) => Arc::make_mut(qc).signature = signature,
+        Record::Timeout(timeout) => timeout.signature = signature,
+    }
+    record
+}
+
+/// The records of an honest history, delivered in an arbitrary order, some of them several
+/// times, mixed with forged records.
+pub fn arb_deliveries(num_nodes: usize, max_steps: usize) -> impl Strategy<Value = Vec<Record>> {
+    (
+        arb_network_history(num_nodes, max_steps),
+        vec(arb_forged_record(num_nodes), 0..max_steps),
+        any::<prop::sample::Index>(),
+    )
+        .prop_flat_map(|(history, forged, index)| {
+            let mut records = history.records;
+            if !records.is_empty() {
+                let duplicate = index.get(&records).clone();
+                records.push(duplicate);
+            }
+            records.extend(forged);
+            Just(records).prop_shuffle()
+        })
+}
+
+/// Properties of the round values of a record store.
+pub fn check_record_store(store: &RecordStore) -> std::result::Result<(), String> {
+    let current_round = store.current_round();
+    let qc_round = store.highest_quorum_certificate_round();
+    let tc_round = store.highest_timeout_certificate_round();
+    let committed_round = store.highest_committed_round();
+    if current_round <= qc_round || current_round <= tc_round {
+        return Err(format!(
+            "Current {:?} is not past the highest QC ({:?}) and TC ({:?})",
+            current_round, qc_round, tc_round
+        ));
+    }
+    let distance = if store.is_fast_path_commit(committed_round) {
+        0
+    } else {
+        store.commit_rule().commit_certificate_distance()
+    };
+    if committed_round > Round(0) && committed_round + distance > qc_round {
+        return Err(format!(
+            "Committed {:?} is not certified by a {}-chain (highest QC at {:?})",
+            committed_round,
+            distance + 1,
+            qc_round
+        ));
+    }
+    if let Some(qc) = store.highest_quorum_certificate() {
+        if qc.round != qc_round {
+            return Err(format!(
+                "Highest QC is at {:?} instead of {:?}",
+                qc.round, qc_round
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Insert records one by one, checking that the store stays consistent and that its rounds
+/// never decrease.
+pub fn check_insertions(
+    store: &mut RecordStore,
+    context: &mut SimulatedContext,
+    records: Vec<Record>,
+) -> std::result::Result<(), String> {
+    let rounds = |store: &RecordStore| {
+        [
+            store.current_round(),
+            store.highest_quorum_certificate_round(),
+            store.highest_timeout_certificate_round(),
+            store.highest_committed_round(),
+        ]
+    };
+    for record in records {
+        let previous_rounds = rounds(&*store);
+        let description = format!("{:?}", record);
+        store
+            .insert_network_record(record, context)
+            .map_err(|e| format!("{} while inserting {}", e, description))?;
+        check_record_store(&*store)
+            .map_err(|e| format!("{} after inserting {}", e, description))?;
+        let new_rounds = rounds(&*store);
+        if new_rounds.iter().zip(&previous_rounds).any(|(x, y)| x < y) {
+            return Err(format!(
+                "Rounds went back from {:?} to {:?} after inserting {}",
+                previous_rounds, new_rounds, description
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Deliver an honest history to a fresh record store, interleaved with the records of
+/// Byzantine nodes. Byzantine records must be signed by `byzantine` authors, which must hold
+/// less than a third of the votes. Besides the checks of `check_insertions`, every block
+/// committed by the store must come from the honest history.
+pub fn check_byzantine_records(
+    history: &NetworkHistory,
+    byzantine: &BTreeSet<Author>,
+    byzantine_records: Vec<Record>,
+) -> std::result::Result<(), String> {
+    if byzantine.len() * 3 >= history.num_nodes {
+        return Err(format!(
+            "Too many Byzantine nodes: {} out of {}",
+            byzantine.len(),
+            history.num_nodes
+        ));
+    }
+    if let Some(record) = byzantine_records
+        .iter()
+        .find(|record| !byzantine.contains(&record.author()))
+    {
+        return Err(format!("Record from an honest author: {:?}", record));
+    }
+    // Alternate honest and Byzantine records, then append the rest.
+    let mut records = Vec::new();
+    let mut honest = history.records.iter().cloned();
+    let mut byzantine_records = byzantine_records.into_iter();
+    loop {
+        match (honest.next(), byzantine_records.next()) {
+            (None, None) => break,
+            (x, y) => records.extend(x.into_iter().chain(y)),
+        }
+    }
+    let (mut store, mut context) = initial_record_store(history.num_nodes);
+    check_insertions(&mut store, &mut context, records)?;
+    let honest_blocks = history.block_hashes();
+    for (round, _, _, _, block_hash) in store.committed_states_after(Round(0)) {
+        if !honest_blocks.contains(&block_hash) {
+            return Err(format!(
+                "Committed block {:?} at {:?} was not proposed by an honest node",
+                block_hash, round
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// What a differential check compares between two record stores.
+#[derive(Eq, PartialEq, Debug)]
+struct Observation {
+    current_round: Round,
+    highest_quorum_certificate_round: Round,
+    highest_quorum_certificate_hash: QuorumCertificateHash,
+    highest_timeout_certificate_round: Round,
+    committed: Vec<(Round, State, Author, NodeTime, BlockHash)>,
+    active_round: Round,
+    proposed_block: Option<(BlockHash, Round, Author)>,
+}
+
+impl Observation {
+    /// Observe a store after updating a pacemaker of `Author(0)` with it at `clock`.
+    fn new(store: &RecordStore, pacemaker: &mut PacemakerState, clock: NodeTime) -> Self {
+        pacemaker.update_pacemaker(Author(0), EpochId(0), store, NodeTime(0), clock);
+        Observation {
+            current_round: store.current_round(),
+            highest_quorum_certificate_round: store.highest_quorum_certificate_round(),
+            highest_quorum_certificate_hash: store.highest_quorum_certificate_hash(),
+            highest_timeout_certificate_round: store.highest_timeout_certificate_round(),
+            committed: store.committed_states_after(Round(0)),
+            active_round: pacemaker.active_round(),
+            proposed_block: store.proposed_block(&*pacemaker),
+        }
+    }
+}
+
+/// Deliver the same records of the initial epoch to two record stores, e.g. two
+/// implementations or configurations of `RecordStore`, and check that they behave the same:
+/// insertions succeed or fail together, and the rounds, the highest QC, the committed chain,
+/// and the block proposed to a pacemaker agree after each record.
+pub fn check_differential(
+    left: &mut RecordStore,
+    left_context: &mut SimulatedContext,
+    right: &mut RecordStore,
+    right_context: &mut SimulatedContext,
+    records: Vec<Record>,
+) -> std::result::Result<(), String> {
+    let new_pacemaker =
+        || PacemakerState::new(EpochId(0), NodeTime(0), Duration::from_millis(10), 2.0, 0.5);
+    let (mut left_pacemaker, mut right_pacemaker) = (new_pacemaker(), new_pacemaker());
+    for (index, record) in records.into_iter().enumerate() {
+        let description = format!("{:?}", record);
+        let left_result = left.insert_network_record(record.clone(), left_context);
+        let right_result = right.insert_network_record(record, right_context);
+        if left_result.is_ok() != right_result.is_ok() {
+            return Err(format!(
+                "Insertions of {} gave {:?} and {:?}",
+                description, left_result, right_result
+            ));
+        }
+        let clock = NodeTime(0) + STEP_DURATION * index as i64;
+        let left_observation = Observation::new(&*left, &mut left_pacemaker, clock);
+        let right_observation = Observation::new(&*right, &mut right_pacemaker, clock);
+        if left_observation != right_observation {
+            return Err(format!(
+                "Stores diverged after inserting {}: {:?} and {:?}",
+                description, left_observation, right_observation
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Call `update_pacemaker` and check the returned actions. A pacemaker must not go back to
+/// previous rounds, must stay past the highest QC, may only time out in its active round, and
+/// may only propose when leading the active round, on top of the highest QC.
+pub fn check_pacemaker_update(
+    pacemaker: &mut Pacemaker,
+    local_author: Author,
+    epoch_id: EpochId,
+    record_store: &RecordStore,
+    latest_query_all: NodeTime,
+    clock: NodeTime,
+) -> std::result::Result<PacemakerUpdateActions, String> {
+    let previous_round = pacemaker.active_round();
+    let actions = pacemaker.update_pacemaker(
+        local_author,
+        epoch_id,
+        record_store,
+        latest_query_all,
+        clock,
+    );
+    let active_round = pacemaker.active_round();
+    if active_round < previous_round {
+        return Err(format!(
+            "Active round went back from {:?} to {:?}",
+            previous_round, active_round
+        ));
+    }
+    if active_round <= record_store.highest_quorum_certificate_round() {
+        return Err(format!(
+            "Active {:?} is not past the highest QC at {:?}",
+            active_round,
+            record_store.highest_quorum_certificate_round()
+        ));
+    }
+    if let Some(round) = actions.should_create_timeout {
+        if round != active_round {
+            return Err(format!(
+                "Timeout at {:?} while the active round is {:?}",
+                round, active_round
+            ));
+        }
+    }
+    if let Some(qc_hash) = actions.should_propose_block {
+        if pacemaker.active_leader() != Some(local_author) {
+            return Err(format!(
+                "{:?} proposes while the leader is {:?}",
+                local_author,
+                pacemaker.active_leader()
+            ));
+        }
+        if qc_hash != record_store.highest_quorum_certificate_hash() {
+            return Err(format!(
+                "Proposal on top of {:?} instead of the highest QC {:?}",
+                qc_hash,
+                record_store.highest_quorum_certificate_hash()
+            ));
+        }
+    }
+    if actions.next_scheduled_update < clock {
+        return Err(format!(
+            "Next update scheduled in the past: {:?} < {:?}",
+            actions.next_scheduled_update, clock
+        ));
+    }
+    Ok(actions)
+}
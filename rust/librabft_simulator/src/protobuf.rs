@@ -0,0 +1,850 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Protobuf wire format of records and data-sync messages, with the `protobuf` feature.
+//!
+//! The schema is `proto/librabft.proto`, so that other tools and implementations can read and
+//! produce the traffic of the simulator. The messages of the schema are in `messages`, and the
+//! `Protobuf` trait converts between them and the internal types.
+
+use super::*;
+use base_types::*;
+use bft_simulator_runtime::{clients::ClientRequest, mempool::Transaction, CommittedBlock};
+use data_sync::*;
+use prost::Message;
+use record::*;
+use std::sync::Arc;
+
+#[cfg(test)]
+#[path = "unit_tests/protobuf_tests.rs"]
+mod protobuf_tests;
+
+/// Messages of `proto/librabft.proto`.
+pub mod messages {
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Transaction {
+        #[prost(uint64, tag = "1")]
+        pub index: u64,
+        #[prost(int64, tag = "2")]
+        pub submission_time: i64,
+        #[prost(uint64, tag = "3")]
+        pub size: u64,
+        #[prost(uint64, tag = "4")]
+        pub targeted: u64,
+        #[prost(message, optional, tag = "5")]
+        pub request: Option<ClientRequest>,
+        #[prost(uint64, tag = "6")]
+        pub proposals: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ClientRequest {
+        #[prost(uint64, tag = "1")]
+        pub client: u64,
+        #[prost(uint64, tag = "2")]
+        pub sequence: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Command {
+        #[prost(uint64, tag = "1")]
+        pub proposer: u64,
+        #[prost(uint64, tag = "2")]
+        pub index: u64,
+        #[prost(message, repeated, tag = "3")]
+        pub transactions: Vec<Transaction>,
+        #[prost(message, optional, tag = "4")]
+        pub parameter_change: Option<ParameterChange>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ParameterChange {
+        #[prost(int64, tag = "1")]
+        pub delta: i64,
+        #[prost(double, tag = "2")]
+        pub gamma: f64,
+        #[prost(double, tag = "3")]
+        pub lambda: f64,
+        #[prost(uint64, tag = "4")]
+        pub max_block_payload: u64,
+        /// Index of the change in the schedule of changes.
+        #[prost(uint64, tag = "5")]
+        pub index: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct State {
+        #[prost(uint64, tag = "1")]
+        pub value: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct BlockHash {
+        #[prost(uint64, tag = "1")]
+        pub value: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Block {
+        #[prost(message, optional, tag = "1")]
+        pub command: Option<Command>,
+        #[prost(int64, tag = "2")]
+        pub time: i64,
+        #[prost(uint64, tag = "3")]
+        pub previous_quorum_certificate_hash: u64,
+        #[prost(uint64, tag = "4")]
+        pub round: u64,
+        #[prost(uint64, tag = "5")]
+        pub author: u64,
+        #[prost(bytes, tag = "6")]
+        pub signature: Vec<u8>,
+        #[prost(message, optional, tag = "7")]
+        pub previous_block_hash: Option<BlockHash>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct SignatureShare {
+        #[prost(uint64, tag = "1")]
+        pub value: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Vote {
+        #[prost(uint64, tag = "1")]
+        pub epoch_id: u64,
+        #[prost(uint64, tag = "2")]
+        pub round: u64,
+        #[prost(uint64, tag = "3")]
+        pub certified_block_hash: u64,
+        #[prost(message, optional, tag = "4")]
+        pub state: Option<State>,
+        #[prost(message, optional, tag = "5")]
+        pub committed_state: Option<State>,
+        #[prost(uint64, tag = "6")]
+        pub author: u64,
+        #[prost(bytes, tag = "7")]
+        pub signature: Vec<u8>,
+        #[prost(message, optional, tag = "8")]
+        pub share: Option<SignatureShare>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct AuthorSignature {
+        #[prost(uint64, tag = "1")]
+        pub author: u64,
+        #[prost(bytes, tag = "2")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct IndividualSignatures {
+        /// Signatures out of order, with their authors.
+        #[prost(message, repeated, tag = "1")]
+        pub signatures: Vec<AuthorSignature>,
+        /// Bitmap of the authors of the signatures in increasing order, see `signer_bitmap`.
+        #[prost(uint64, repeated, tag = "2")]
+        pub signers: Vec<u64>,
+        #[prost(bytes, repeated, tag = "3")]
+        pub signer_signatures: Vec<Vec<u8>>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct AggregateSignature {
+        #[prost(uint64, repeated, tag = "1")]
+        pub authors: Vec<u64>,
+        #[prost(bytes, tag = "2")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, Oneof)]
+    pub enum MultiSignatureKind {
+        #[prost(message, tag = "1")]
+        Individual(IndividualSignatures),
+        #[prost(message, tag = "2")]
+        Aggregate(AggregateSignature),
+        #[prost(uint64, tag = "3")]
+        Threshold(u64),
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct MultiSignature {
+        #[prost(oneof = "MultiSignatureKind", tags = "1, 2, 3")]
+        pub kind: Option<MultiSignatureKind>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct QuorumCertificate {
+        #[prost(uint64, tag = "1")]
+        pub epoch_id: u64,
+        #[prost(uint64, tag = "2")]
+        pub round: u64,
+        #[prost(uint64, tag = "3")]
+        pub certified_block_hash: u64,
+        #[prost(message, optional, tag = "4")]
+        pub state: Option<State>,
+        #[prost(message, optional, tag = "5")]
+        pub committed_state: Option<State>,
+        #[prost(message, optional, tag = "6")]
+        pub votes: Option<MultiSignature>,
+        #[prost(uint64, tag = "7")]
+        pub author: u64,
+        #[prost(bytes, tag = "8")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Timeout {
+        #[prost(uint64, tag = "1")]
+        pub epoch_id: u64,
+        #[prost(uint64, tag = "2")]
+        pub round: u64,
+        #[prost(uint64, tag = "3")]
+        pub highest_certified_block_round: u64,
+        #[prost(uint64, tag = "4")]
+        pub author: u64,
+        #[prost(bytes, tag = "5")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, Oneof)]
+    pub enum RecordKind {
+        #[prost(message, tag = "1")]
+        Block(Block),
+        #[prost(message, tag = "2")]
+        Vote(Vote),
+        #[prost(message, tag = "3")]
+        QuorumCertificate(QuorumCertificate),
+        #[prost(message, tag = "4")]
+        Timeout(Timeout),
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Record {
+        #[prost(oneof = "RecordKind", tags = "1, 2, 3, 4")]
+        pub kind: Option<RecordKind>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct DataSyncNotification {
+        #[prost(uint64, tag = "1")]
+        pub current_epoch: u64,
+        #[prost(message, optional, tag = "2")]
+        pub highest_commit_certificate: Option<QuorumCertificate>,
+        #[prost(message, optional, tag = "3")]
+        pub highest_quorum_certificate: Option<QuorumCertificate>,
+        #[prost(message, repeated, tag = "4")]
+        pub timeouts: Vec<Timeout>,
+        #[prost(message, optional, tag = "5")]
+        pub current_vote: Option<Vote>,
+        #[prost(message, optional, tag = "6")]
+        pub proposed_block: Option<Block>,
+        #[prost(message, repeated, tag = "7")]
+        pub previous_proposed_blocks: Vec<Block>,
+        #[prost(uint32, tag = "8")]
+        pub version: u32,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct DataSyncRequest {
+        #[prost(uint64, tag = "1")]
+        pub current_epoch: u64,
+        #[prost(uint64, repeated, tag = "2")]
+        pub known_quorum_certificates: Vec<u64>,
+        #[prost(uint32, tag = "3")]
+        pub version: u32,
+        #[prost(uint64, tag = "5")]
+        pub committed_blocks: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct EpochRecords {
+        #[prost(uint64, tag = "1")]
+        pub epoch_id: u64,
+        #[prost(message, repeated, tag = "2")]
+        pub records: Vec<Record>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct DataSyncResponse {
+        #[prost(uint64, tag = "1")]
+        pub current_epoch: u64,
+        #[prost(message, repeated, tag = "2")]
+        pub records: Vec<EpochRecords>,
+        #[prost(uint32, tag = "3")]
+        pub version: u32,
+        #[prost(message, optional, tag = "4")]
+        pub checkpoint: Option<StateCheckpoint>,
+        #[prost(message, repeated, tag = "5")]
+        pub checkpoint_blocks: Vec<CommittedBlock>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct StateCheckpoint {
+        #[prost(uint64, tag = "1")]
+        pub epoch_id: u64,
+        #[prost(message, optional, tag = "2")]
+        pub state: Option<State>,
+        #[prost(bytes, tag = "3")]
+        pub snapshot: Vec<u8>,
+        #[prost(uint64, tag = "4")]
+        pub committed_blocks: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct CommittedBlock {
+        #[prost(uint64, tag = "1")]
+        pub round: u64,
+        #[prost(uint64, tag = "2")]
+        pub proposer: u64,
+        #[prost(int64, tag = "3")]
+        pub proposal_time: i64,
+        #[prost(uint64, tag = "4")]
+        pub hash: u64,
+        #[prost(message, repeated, tag = "5")]
+        pub transactions: Vec<Transaction>,
+        #[prost(bool, tag = "6")]
+        pub fast_path: bool,
+    }
+}
+
+/// Conversion of an internal type to and from its protobuf message.
+pub trait Protobuf: Sized {
+    type Message: Message + Default;
+
+    fn to_message(&self) -> Self::Message;
+
+    fn from_message(message: Self::Message) -> Result<Self>;
+
+    /// Encoding of the value on the wire.
+    fn to_bytes(&self) -> Vec<u8> {
+        let message = self.to_message();
+        let mut bytes = Vec::with_capacity(message.encoded_len());
+        message.encode(&mut bytes).expect("Vectors grow as needed");
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_message(<Self::Message as Message>::decode(bytes)?)
+    }
+}
+
+fn required<T>(value: Option<T>, field: &str) -> Result<T> {
+    match value {
+        Some(value) => Ok(value),
+        None => bail!("Missing field in protobuf message: {}", field),
+    }
+}
+
+/// Versions of the data sync protocol, where 0 stands for messages without a version.
+fn data_sync_version(version: u32) -> u32 {
+    std::cmp::max(version, LEGACY_DATA_SYNC_VERSION)
+}
+
+fn state_message(state: &State) -> messages::State {
+    messages::State { value: state.0 }
+}
+
+fn state_from_message(message: messages::State) -> State {
+    State(message.value)
+}
+
+fn transaction_message(transaction: &Transaction) -> messages::Transaction {
+    messages::Transaction {
+        index: transaction.index as u64,
+        submission_time: transaction.submission_time.0,
+        size: transaction.size as u64,
+        targeted: transaction.targeted.map_or(0, |id| id as u64 + 1),
+        request: transaction.request.map(|request| messages::ClientRequest {
+            client: request.client as u64,
+            sequence: request.sequence as u64,
+        }),
+        proposals: transaction.proposals as u64,
+    }
+}
+
+fn transaction_from_message(message: messages::Transaction) -> Transaction {
+    Transaction {
+        index: message.index as usize,
+        submission_time: NodeTime(message.submission_time),
+        size: message.size as usize,
+        targeted: match message.targeted {
+            0 => None,
+            id => Some(id as usize - 1),
+        },
+        request: message.request.map(|request| ClientRequest {
+            client: request.client as usize,
+            sequence: request.sequence as usize,
+        }),
+        proposals: message.proposals as usize,
+    }
+}
+
+impl Protobuf for Command {
+    type Message = messages::Command;
+
+    fn to_message(&self) -> messages::Command {
+        messages::Command {
+            proposer: self.proposer.0 as u64,
+            index: self.index as u64,
+            transactions: self.transactions.iter().map(transaction_message).collect(),
+            parameter_change: self.parameter_change.map(|(index, change)| {
+                messages::ParameterChange {
+                    delta: change.delta.map_or(0, |x| x.as_millis()),
+                    gamma: change.gamma.unwrap_or(0.0),
+                    lambda: change.lambda.unwrap_or(0.0),
+                    max_block_payload: change.max_block_payload.map_or(0, |x| x as u64),
+                    index: index as u64,
+                }
+            }),
+        }
+    }
+
+    fn from_message(message: messages::Command) -> Result<Self> {
+        Ok(Command {
+            proposer: Author(message.proposer as usize),
+            index: message.index as usize,
+            transactions: message
+                .transactions
+                .into_iter()
+                .map(transaction_from_message)
+                .collect(),
+            parameter_change: message.parameter_change.map(|change| {
+                let parameters = ParameterChange {
+                    delta: Some(Duration::from_millis(change.delta)).filter(|x| x.is_positive()),
+                    gamma: Some(change.gamma).filter(|x| *x != 0.0),
+                    lambda: Some(change.lambda).filter(|x| *x != 0.0),
+                    max_block_payload: Some(change.max_block_payload as usize).filter(|x| *x > 0),
+                };
+                (change.index as usize, parameters)
+            }),
+        })
+    }
+}
+
+impl Protobuf for MultiSignature {
+    type Message = messages::MultiSignature;
+
+    fn to_message(&self) -> messages::MultiSignature {
+        let kind = match self {
+            MultiSignature::Individual(signatures) => {
+                let authors = signatures.iter().map(|(author, _)| *author);
+                let individual = match signer_bitmap(authors) {
+                    Some(signers) => messages::IndividualSignatures {
+                        signatures: Vec::new(),
+                        signers,
+                        signer_signatures: signatures
+                            .iter()
+                            .map(|(_, signature)| signature.to_bytes())
+                            .collect(),
+                    },
+                    None => messages::IndividualSignatures {
+                        signatures: signatures
+                            .iter()
+                            .map(|(author, signature)| messages::AuthorSignature {
+                                author: author.0 as u64,
+                                signature: signature.to_bytes(),
+                            })
+                            .collect(),
+                        signers: Vec::new(),
+                        signer_signatures: Vec::new(),
+                    },
+                };
+                messages::MultiSignatureKind::Individual(individual)
+            }
+            MultiSignature::Aggregate(authors, signature) => {
+                messages::MultiSignatureKind::Aggregate(messages::AggregateSignature {
+                    authors: authors.iter().map(|author| author.0 as u64).collect(),
+                    signature: signature.to_bytes(),
+                })
+            }
+            MultiSignature::Threshold(signature) => {
+                messages::MultiSignatureKind::Threshold(signature.0)
+            }
+        };
+        messages::MultiSignature { kind: Some(kind) }
+    }
+
+    fn from_message(message: messages::MultiSignature) -> Result<Self> {
+        let multi_signature = match required(message.kind, "MultiSignature.kind")? {
+            messages::MultiSignatureKind::Individual(individual) => {
+                let authors = bitmap_authors(&individual.signers);
+                ensure!(
+                    authors.len() == individual.signer_signatures.len(),
+                    "The signer bitmap does not match the number of signatures."
+                );
+                let mut signatures = individual
+                    .signatures
+                    .into_iter()
+                    .map(|x| {
+                        Ok((
+                            Author(x.author as usize),
+                            Signature::from_bytes(&x.signature)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                for (author, signature) in authors.into_iter().zip(individual.signer_signatures) {
+                    signatures.push((author, Signature::from_bytes(&signature)?));
+                }
+                MultiSignature::Individual(signatures)
+            }
+            messages::MultiSignatureKind::Aggregate(aggregate) => MultiSignature::Aggregate(
+                aggregate
+                    .authors
+                    .into_iter()
+                    .map(|author| Author(author as usize))
+                    .collect(),
+                AggregateSignature::from_bytes(&aggregate.signature)?,
+            ),
+            messages::MultiSignatureKind::Threshold(signature) => {
+                MultiSignature::Threshold(ThresholdSignature(signature))
+            }
+        };
+        Ok(multi_signature)
+    }
+}
+
+impl Protobuf for Block {
+    type Message = messages::Block;
+
+    fn to_message(&self) -> messages::Block {
+        messages::Block {
+            command: Some(self.command.to_message()),
+            time: self.time.0,
+            previous_quorum_certificate_hash: self.previous_quorum_certificate_hash.0,
+            round: self.round.0 as u64,
+            author: self.author.0 as u64,
+            signature: self.signature.to_bytes(),
+            previous_block_hash: self
+                .previous_block_hash
+                .map(|hash| messages::BlockHash { value: hash.0 }),
+        }
+    }
+
+    fn from_message(message: messages::Block) -> Result<Self> {
+        Ok(Block {
+            command: Command::from_message(required(message.command, "Block.command")?)?,
+            time: NodeTime(message.time),
+            previous_quorum_certificate_hash: QuorumCertificateHash(
+                message.previous_quorum_certificate_hash,
+            ),
+            previous_block_hash: message
+                .previous_block_hash
+                .map(|hash| BlockHash(hash.value)),
+            round: Round(message.round as usize),
+            author: Author(message.author as usize),
+            signature: Signature::from_bytes(&message.signature)?,
+        })
+    }
+}
+
+impl Protobuf for Vote {
+    type Message = messages::Vote;
+
+    fn to_message(&self) -> messages::Vote {
+        messages::Vote {
+            epoch_id: self.epoch_id.0 as u64,
+            round: self.round.0 as u64,
+            certified_block_hash: self.certified_block_hash.0,
+            state: Some(state_message(&self.state)),
+            committed_state: self.committed_state.as_ref().map(state_message),
+            author: self.author.0 as u64,
+            signature: self.signature.to_bytes(),
+            share: self
+                .share
+                .map(|share| messages::SignatureShare { value: share.0 }),
+        }
+    }
+
+    fn from_message(message: messages::Vote) -> Result<Self> {
+        Ok(Vote {
+            epoch_id: EpochId(message.epoch_id as usize),
+            round: Round(message.round as usize),
+            certified_block_hash: BlockHash(message.certified_block_hash),
+            state: state_from_message(required(message.state, "Vote.state")?),
+            committed_state: message.committed_state.map(state_from_message),
+            author: Author(message.author as usize),
+            signature: Signature::from_bytes(&message.signature)?,
+            share: message.share.map(|share| SignatureShare(share.value)),
+        })
+    }
+}
+
+impl Protobuf for QuorumCertificate {
+    type Message = messages::QuorumCertificate;
+
+    fn to_message(&self) -> messages::QuorumCertificate {
+        messages::QuorumCertificate {
+            epoch_id: self.epoch_id.0 as u64,
+            round: self.round.0 as u64,
+            certified_block_hash: self.certified_block_hash.0,
+            state: Some(state_message(&self.state)),
+            committed_state: self.committed_state.as_ref().map(state_message),
+            votes: Some(self.votes.to_message()),
+            author: self.author.0 as u64,
+            signature: self.signature.to_bytes(),
+        }
+    }
+
+    fn from_message(message: messages::QuorumCertificate) -> Result<Self> {
+        let votes = required(message.votes, "QuorumCertificate.votes")?;
+        Ok(QuorumCertificate {
+            epoch_id: EpochId(message.epoch_id as usize),
+            round: Round(message.round as usize),
+            certified_block_hash: BlockHash(message.certified_block_hash),
+            state: state_from_message(required(message.state, "QuorumCertificate.state")?),
+            committed_state: message.committed_state.map(state_from_message),
+            votes: Arc::new(MultiSignature::from_message(votes)?),
+            author: Author(message.author as usize),
+            signature: Signature::from_bytes(&message.signature)?,
+        })
+    }
+}
+
+impl Protobuf for Timeout {
+    type Message = messages::Timeout;
+
+    fn to_message(&self) -> messages::Timeout {
+        messages::Timeout {
+            epoch_id: self.epoch_id.0 as u64,
+            round: self.round.0 as u64,
+            highest_certified_block_round: self.highest_certified_block_round.0 as u64,
+            author: self.author.0 as u64,
+            signature: self.signature.to_bytes(),
+        }
+    }
+
+    fn from_message(message: messages::Timeout) -> Result<Self> {
+        Ok(Timeout {
+            epoch_id: EpochId(message.epoch_id as usize),
+            round: Round(message.round as usize),
+            highest_certified_block_round: Round(message.highest_certified_block_round as usize),
+            author: Author(message.author as usize),
+            signature: Signature::from_bytes(&message.signature)?,
+        })
+    }
+}
+
+impl Protobuf for Record {
+    type Message = messages::Record;
+
+    fn to_message(&self) -> messages::Record {
+        let kind = match self {
+            Record::Block(x) => messages::RecordKind::Block(x.to_message()),
+            Record::Vote(x) => messages::RecordKind::Vote(x.to_message()),
+            Record::QuorumCertificate(x) => messages::RecordKind::QuorumCertificate(x.to_message()),
+            Record::Timeout(x) => messages::RecordKind::Timeout(x.to_message()),
+        };
+        messages::Record { kind: Some(kind) }
+    }
+
+    fn from_message(message: messages::Record) -> Result<Self> {
+        let record = match required(message.kind, "Record.kind")? {
+            messages::RecordKind::Block(x) => Record::Block(Arc::new(Block::from_message(x)?)),
+            messages::RecordKind::Vote(x) => Record::Vote(Vote::from_message(x)?),
+            messages::RecordKind::QuorumCertificate(x) => {
+                Record::QuorumCertificate(Arc::new(QuorumCertificate::from_message(x)?))
+            }
+            messages::RecordKind::Timeout(x) => Record::Timeout(Timeout::from_message(x)?),
+        };
+        Ok(record)
+    }
+}
+
+impl Protobuf for DataSyncNotification {
+    type Message = messages::DataSyncNotification;
+
+    fn to_message(&self) -> messages::DataSyncNotification {
+        messages::DataSyncNotification {
+            current_epoch: self.current_epoch.0 as u64,
+            highest_commit_certificate: self
+                .highest_commit_certificate
+                .as_ref()
+                .map(|qc| qc.to_message()),
+            highest_quorum_certificate: self
+                .highest_quorum_certificate
+                .as_ref()
+                .map(|qc| qc.to_message()),
+            timeouts: self.timeouts.iter().map(Protobuf::to_message).collect(),
+            current_vote: self.current_vote.as_ref().map(Protobuf::to_message),
+            proposed_block: self.proposed_block.as_ref().map(|block| block.to_message()),
+            previous_proposed_blocks: self
+                .previous_proposed_blocks
+                .iter()
+                .map(|block| block.to_message())
+                .collect(),
+            version: self.version,
+        }
+    }
+
+    fn from_message(message: messages::DataSyncNotification) -> Result<Self> {
+        Ok(DataSyncNotification {
+            version: data_sync_version(message.version),
+            current_epoch: EpochId(message.current_epoch as usize),
+            highest_commit_certificate: message
+                .highest_commit_certificate
+                .map(QuorumCertificate::from_message)
+                .transpose()?
+                .map(Arc::new),
+            highest_quorum_certificate: message
+                .highest_quorum_certificate
+                .map(QuorumCertificate::from_message)
+                .transpose()?
+                .map(Arc::new),
+            timeouts: message
+                .timeouts
+                .into_iter()
+                .map(Timeout::from_message)
+                .collect::<Result<_>>()?,
+            current_vote: message.current_vote.map(Vote::from_message).transpose()?,
+            proposed_block: message
+                .proposed_block
+                .map(Block::from_message)
+                .transpose()?
+                .map(Arc::new),
+            previous_proposed_blocks: message
+                .previous_proposed_blocks
+                .into_iter()
+                .map(|block| Block::from_message(block).map(Arc::new))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl Protobuf for DataSyncRequest {
+    type Message = messages::DataSyncRequest;
+
+    fn to_message(&self) -> messages::DataSyncRequest {
+        messages::DataSyncRequest {
+            current_epoch: self.current_epoch.0 as u64,
+            known_quorum_certificates: self
+                .known_quorum_certificates
+                .iter()
+                .map(|round| round.0 as u64)
+                .collect(),
+            version: self.version,
+            committed_blocks: self.committed_blocks.map_or(0, |count| count as u64 + 1),
+        }
+    }
+
+    fn from_message(message: messages::DataSyncRequest) -> Result<Self> {
+        Ok(DataSyncRequest {
+            version: data_sync_version(message.version),
+            current_epoch: EpochId(message.current_epoch as usize),
+            known_quorum_certificates: message
+                .known_quorum_certificates
+                .into_iter()
+                .map(|round| Round(round as usize))
+                .collect(),
+            committed_blocks: match message.committed_blocks {
+                0 => None,
+                count => Some(count as usize - 1),
+            },
+        })
+    }
+}
+
+impl Protobuf for DataSyncResponse {
+    type Message = messages::DataSyncResponse;
+
+    fn to_message(&self) -> messages::DataSyncResponse {
+        messages::DataSyncResponse {
+            current_epoch: self.current_epoch.0 as u64,
+            records: self
+                .records
+                .iter()
+                .map(|(epoch_id, records)| messages::EpochRecords {
+                    epoch_id: epoch_id.0 as u64,
+                    records: records.iter().map(Protobuf::to_message).collect(),
+                })
+                .collect(),
+            version: self.version,
+            checkpoint: self.checkpoint.as_ref().map(Protobuf::to_message),
+            checkpoint_blocks: self
+                .checkpoint_blocks
+                .iter()
+                .map(Protobuf::to_message)
+                .collect(),
+        }
+    }
+
+    fn from_message(message: messages::DataSyncResponse) -> Result<Self> {
+        let records = message
+            .records
+            .into_iter()
+            .map(|x| {
+                let records = x
+                    .records
+                    .into_iter()
+                    .map(Record::from_message)
+                    .collect::<Result<_>>()?;
+                Ok((EpochId(x.epoch_id as usize), records))
+            })
+            .collect::<Result<_>>()?;
+        Ok(DataSyncResponse {
+            version: data_sync_version(message.version),
+            current_epoch: EpochId(message.current_epoch as usize),
+            records,
+            checkpoint: message
+                .checkpoint
+                .map(StateCheckpoint::from_message)
+                .transpose()?,
+            checkpoint_blocks: message
+                .checkpoint_blocks
+                .into_iter()
+                .map(CommittedBlock::from_message)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl Protobuf for StateCheckpoint {
+    type Message = messages::StateCheckpoint;
+
+    fn to_message(&self) -> messages::StateCheckpoint {
+        messages::StateCheckpoint {
+            epoch_id: self.epoch_id.0 as u64,
+            state: Some(state_message(&self.state)),
+            snapshot: self.snapshot.clone(),
+            committed_blocks: self.committed_blocks as u64,
+        }
+    }
+
+    fn from_message(message: messages::StateCheckpoint) -> Result<Self> {
+        Ok(StateCheckpoint {
+            epoch_id: EpochId(message.epoch_id as usize),
+            state: state_from_message(required(message.state, "state")?),
+            snapshot: message.snapshot,
+            committed_blocks: message.committed_blocks as usize,
+        })
+    }
+}
+
+impl Protobuf for CommittedBlock {
+    type Message = messages::CommittedBlock;
+
+    fn to_message(&self) -> messages::CommittedBlock {
+        messages::CommittedBlock {
+            round: self.round.0 as u64,
+            proposer: self.proposer.0 as u64,
+            proposal_time: self.proposal_time.0,
+            hash: self.hash,
+            transactions: self.transactions.iter().map(transaction_message).collect(),
+            fast_path: self.fast_path,
+        }
+    }
+
+    fn from_message(message: messages::CommittedBlock) -> Result<Self> {
+        Ok(CommittedBlock {
+            round: Round(message.round as usize),
+            proposer: Author(message.proposer as usize),
+            proposal_time: NodeTime(message.proposal_time),
+            hash: message.hash,
+            transactions: message
+                .transactions
+                .into_iter()
+                .map(transaction_from_message)
+                .collect(),
+            fast_path: message.fast_path,
+        })
+    }
+}
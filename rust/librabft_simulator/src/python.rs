@@ -0,0 +1,240 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Python bindings of scenarios and runs, with the `python` feature.
+//!
+//! Built as an extension module (e.g. `pyo3-pack develop --cargo-extra-args="--features
+//! python-extension"`), this exports `Scenario` and `Metrics` to Python, so that sweeps can be
+//! scripted and analyzed with pandas. Nested values, such as the sections of a scenario or the
+//! per-node metrics, are converted to Python dictionaries through JSON.
+
+use super::*;
+use bft_simulator_runtime::{metrics::MetricsReport, statistics::RunSummary};
+use pyo3::{
+    exceptions::ValueError,
+    prelude::*,
+    types::{PyAny, PyModule},
+};
+
+#[cfg(test)]
+#[path = "unit_tests/python_tests.rs"]
+mod python_tests;
+
+fn value_error(error: failure::Error) -> PyErr {
+    ValueError::py_err(error.to_string())
+}
+
+/// Convert a serializable value into Python values.
+fn to_python<T: serde::Serialize>(py: Python, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|error| value_error(error.into()))?;
+    let value: &PyAny = py.import("json")?.call1("loads", (json,))?;
+    Ok(value.to_object(py))
+}
+
+/// Convert Python values into a deserializable value.
+fn from_python<T: serde::de::DeserializeOwned>(py: Python, value: &PyAny) -> PyResult<T> {
+    let json: String = py.import("json")?.call1("dumps", (value,))?.extract()?;
+    serde_json::from_str(&json).map_err(|error| value_error(error.into()))
+}
+
+/// A scenario, as in TOML files. Changes are checked when the scenario is run.
+#[pyclass(name = Scenario)]
+#[derive(Clone, Debug)]
+pub struct PyScenario {
+    scenario: scenario::Scenario,
+}
+
+#[pymethods]
+impl PyScenario {
+    /// The scenario described by a TOML string, or the default scenario.
+    #[new]
+    fn new(obj: &PyRawObject, toml: Option<&str>) -> PyResult<()> {
+        let scenario = match toml {
+            Some(contents) => scenario::Scenario::parse(contents).map_err(value_error)?,
+            None => scenario::Scenario::default(),
+        };
+        obj.init(PyScenario { scenario });
+        Ok(())
+    }
+
+    #[staticmethod]
+    fn load(py: Python, path: &str) -> PyResult<Py<PyScenario>> {
+        let scenario = scenario::Scenario::load(path).map_err(value_error)?;
+        Py::new(py, PyScenario { scenario })
+    }
+
+    /// The scenario described by a dictionary with the sections of a TOML file.
+    #[staticmethod]
+    fn from_dict(py: Python, value: &PyAny) -> PyResult<Py<PyScenario>> {
+        let scenario: scenario::Scenario = from_python(py, value)?;
+        scenario.check().map_err(value_error)?;
+        Py::new(py, PyScenario { scenario })
+    }
+
+    fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        to_python(py, &self.scenario)
+    }
+
+    fn to_toml(&self) -> PyResult<String> {
+        self.scenario.to_toml().map_err(value_error)
+    }
+
+    #[getter]
+    fn seed(&self) -> PyResult<Option<u64>> {
+        Ok(self.scenario.seed)
+    }
+
+    #[setter]
+    fn set_seed(&mut self, seed: Option<u64>) -> PyResult<()> {
+        self.scenario.seed = seed;
+        Ok(())
+    }
+
+    #[getter]
+    fn nodes(&self) -> PyResult<usize> {
+        Ok(self.scenario.nodes)
+    }
+
+    #[setter]
+    fn set_nodes(&mut self, nodes: usize) -> PyResult<()> {
+        self.scenario.nodes = nodes;
+        Ok(())
+    }
+
+    /// In milliseconds.
+    #[getter]
+    fn delta(&self) -> PyResult<i64> {
+        Ok(self.scenario.pacemaker.delta.as_millis())
+    }
+
+    #[setter]
+    fn set_delta(&mut self, delta: i64) -> PyResult<()> {
+        self.scenario.pacemaker.delta = Duration::from_millis(delta);
+        Ok(())
+    }
+
+    #[getter]
+    fn loss_rate(&self) -> PyResult<f64> {
+        Ok(self.scenario.network.loss_rate)
+    }
+
+    #[setter]
+    fn set_loss_rate(&mut self, loss_rate: f64) -> PyResult<()> {
+        self.scenario.network.loss_rate = loss_rate;
+        Ok(())
+    }
+
+    #[getter]
+    fn max_clock(&self) -> PyResult<i64> {
+        Ok(self.scenario.termination.max_clock.0)
+    }
+
+    #[setter]
+    fn set_max_clock(&mut self, max_clock: i64) -> PyResult<()> {
+        self.scenario.termination.max_clock = simulator::GlobalTime(max_clock);
+        Ok(())
+    }
+
+    /// Crash node `author` at `crash_time`, and restart it at `recovery_time` if given.
+    fn add_fault(
+        &mut self,
+        author: usize,
+        crash_time: i64,
+        recovery_time: Option<i64>,
+    ) -> PyResult<()> {
+        self.scenario.fault.push(scenario::Fault {
+            author,
+            crash_time: simulator::GlobalTime(crash_time),
+            recovery_time: recovery_time.map(simulator::GlobalTime),
+        });
+        Ok(())
+    }
+
+    /// Run the scenario, without holding the GIL, and return its metrics.
+    fn run(&self, py: Python) -> PyResult<Py<PyMetrics>> {
+        self.scenario.check().map_err(value_error)?;
+        let parameters = self.scenario.parameters();
+        let metrics = py.allow_threads(move || {
+            let mut sim = make_simulator(&parameters);
+            let stop_reason = sim.run(&parameters.termination(), None);
+            PyMetrics {
+                seed: parameters.seed,
+                stop_reason: format!("{:?}", stop_reason),
+                summary: sim.summary(),
+                report: sim.metrics_report(),
+            }
+        });
+        Py::new(py, metrics)
+    }
+}
+
+/// Metrics of a run.
+#[pyclass(name = Metrics)]
+#[derive(Clone, Debug)]
+pub struct PyMetrics {
+    seed: u64,
+    stop_reason: String,
+    summary: RunSummary,
+    report: MetricsReport,
+}
+
+#[pymethods]
+impl PyMetrics {
+    #[getter]
+    fn seed(&self) -> PyResult<u64> {
+        Ok(self.seed)
+    }
+
+    #[getter]
+    fn stop_reason(&self) -> PyResult<String> {
+        Ok(self.stop_reason.clone())
+    }
+
+    #[getter]
+    fn duration(&self) -> PyResult<i64> {
+        Ok(self.summary.duration.as_millis())
+    }
+
+    #[getter]
+    fn committed_blocks(&self) -> PyResult<f64> {
+        Ok(self.summary.committed_blocks)
+    }
+
+    #[getter]
+    fn commit_throughput(&self) -> PyResult<f64> {
+        Ok(self.summary.commit_throughput)
+    }
+
+    #[getter]
+    fn mean_commit_latency(&self) -> PyResult<Option<f64>> {
+        Ok(self.summary.mean_commit_latency)
+    }
+
+    #[getter]
+    fn timeout_rate(&self) -> PyResult<f64> {
+        Ok(self.summary.timeout_rate)
+    }
+
+    #[getter]
+    fn transaction_throughput(&self) -> PyResult<f64> {
+        Ok(self.summary.transaction_throughput)
+    }
+
+    /// The run summary as a flat dictionary, e.g. a row of a pandas `DataFrame`.
+    fn summary(&self, py: Python) -> PyResult<PyObject> {
+        to_python(py, &self.summary)
+    }
+
+    /// The full metrics report, as exported by `librabft-sim run --metrics`.
+    fn report(&self, py: Python) -> PyResult<PyObject> {
+        to_python(py, &self.report)
+    }
+}
+
+/// The `librabft_simulator` Python module.
+#[pymodule]
+fn librabft_simulator(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyScenario>()?;
+    module.add_class::<PyMetrics>()?;
+    Ok(())
+}
@@ -3,9 +3,10 @@
 
 use super::*;
 use base_types::*;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::hash_map::DefaultHasher,
     hash::{Hash, Hasher},
+    sync::Arc,
 };
 
 #[cfg(test)]
@@ -18,20 +19,21 @@ mod record_tests;
 // DO NOT MODIFY definitions without changing the report as well :)
 
 // -- BEGIN FILE records --
-/// A record read from the network.
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Hash)]
+/// A record read from the network. Blocks and QCs are shared with the record store of the
+/// sender and with the other receivers of the message.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Hash, Deserialize, Serialize)]
 pub enum Record {
     /// Proposed block, containing a command, e.g. a set of Libra transactions.
-    Block(Block),
+    Block(Arc<Block>),
     /// A single vote on a proposed block and its execution state.
     Vote(Vote),
     /// A quorum of votes related to a given block and execution state.
-    QuorumCertificate(QuorumCertificate),
+    QuorumCertificate(Arc<QuorumCertificate>),
     /// A signal that a particular round of an epoch has reached a timeout.
     Timeout(Timeout),
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Deserialize, Serialize)]
 pub struct Block {
     /// User-defined command to execute in the state machine.
     pub command: Command,
@@ -39,6 +41,9 @@ pub struct Block {
     pub time: NodeTime,
     /// Hash of the quorum certificate of the previous block.
     pub previous_quorum_certificate_hash: QuorumCertificateHash,
+    /// Previous block of the same round, when the leader proposes a chain of blocks.
+    #[serde(default)]
+    pub previous_block_hash: Option<BlockHash>,
     /// Number used to identify repeated attempts to propose a block.
     pub round: Round,
     /// Creator of the block.
@@ -47,7 +52,7 @@ pub struct Block {
     pub signature: Signature,
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Deserialize, Serialize)]
 pub struct Vote {
     /// The current epoch.
     pub epoch_id: EpochId,
@@ -64,9 +69,13 @@ pub struct Vote {
     pub author: Author,
     /// Signs the hash of the vote, that is, all the fields above.
     pub signature: Signature,
+    /// Share of the threshold signature of the QC, if QCs use threshold signatures. The share
+    /// would replace the signature of the vote in practice, so only the share is counted in the
+    /// size and the cost of the vote.
+    pub share: Option<SignatureShare>,
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Deserialize, Serialize)]
 pub struct QuorumCertificate {
     /// The current epoch.
     pub epoch_id: EpochId,
@@ -79,15 +88,16 @@ pub struct QuorumCertificate {
     /// Execution state of the ancestor block (if any) that matches
     /// the commit rule thanks to this QC.
     pub committed_state: Option<State>,
-    /// A collections of votes sharing the fields above.
-    pub votes: Vec<(Author, Signature)>,
+    /// A collections of votes sharing the fields above, possibly aggregated. Shared by all the
+    /// copies of the QC.
+    pub votes: Arc<MultiSignature>,
     /// The leader who proposed the certified block should also sign the QC.
     pub author: Author,
     /// Signs the hash of the QC, that is, all the fields above.
     pub signature: Signature,
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Deserialize, Serialize)]
 pub struct Timeout {
     /// The current epoch.
     pub epoch_id: EpochId,
@@ -107,6 +117,10 @@ impl Hash for Block {
         self.command.hash(state);
         self.time.hash(state);
         self.previous_quorum_certificate_hash.hash(state);
+        // Leave the hashes of single proposals unchanged.
+        if let Some(hash) = self.previous_block_hash {
+            hash.hash(state);
+        }
         self.round.hash(state);
         self.author.hash(state);
     }
@@ -143,11 +157,56 @@ impl Hash for Timeout {
     }
 }
 
+/// Digest of the fields shared by the votes of a QC, signed by threshold signatures.
+pub fn share_digest(
+    epoch_id: EpochId,
+    round: Round,
+    certified_block_hash: BlockHash,
+    state: &State,
+    committed_state: &Option<State>,
+) -> u64 {
+    hashing::digest(&(
+        epoch_id,
+        round,
+        certified_block_hash,
+        state,
+        committed_state,
+    ))
+}
+
+impl Vote {
+    pub fn share_digest(&self) -> u64 {
+        share_digest(
+            self.epoch_id,
+            self.round,
+            self.certified_block_hash,
+            &self.state,
+            &self.committed_state,
+        )
+    }
+}
+
+impl QuorumCertificate {
+    /// Size of the signatures of the QC on the wire, in bytes, including its votes.
+    pub fn signature_bytes(&self) -> usize {
+        Signature::num_bytes() + self.votes.num_bytes()
+    }
+
+    pub fn share_digest(&self) -> u64 {
+        share_digest(
+            self.epoch_id,
+            self.round,
+            self.certified_block_hash,
+            &self.state,
+            &self.committed_state,
+        )
+    }
+}
+
 impl Record {
+    /// Digest of the record with the hash function of the current thread.
     pub fn digest(&self) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        self.hash(&mut hasher);
-        hasher.finish()
+        hashing::digest(self)
     }
 
     pub fn make_block(
@@ -157,17 +216,39 @@ impl Record {
         round: Round,
         author: Author,
     ) -> Record {
-        let mut value = Record::Block(Block {
+        Self::make_chained_block(
             command,
             time,
             previous_quorum_certificate_hash,
+            None,
             round,
             author,
-            signature: Signature(0),
-        });
+        )
+    }
+
+    /// Make a block following `previous_block_hash` in the same round, if any.
+    pub fn make_chained_block(
+        command: Command,
+        time: NodeTime,
+        previous_quorum_certificate_hash: QuorumCertificateHash,
+        previous_block_hash: Option<BlockHash>,
+        round: Round,
+        author: Author,
+    ) -> Record {
+        let mut value = Record::Block(Arc::new(Block {
+            command,
+            time,
+            previous_quorum_certificate_hash,
+            previous_block_hash,
+            round,
+            author,
+            signature: Signature::default(),
+        }));
         let hash = value.digest();
         match &mut value {
-            Record::Block(block) => block.signature = Signature::sign(hash, block.author),
+            Record::Block(block) => {
+                Arc::make_mut(block).signature = Signature::sign(hash, block.author)
+            }
             _ => unreachable!(),
         }
         value
@@ -187,8 +268,9 @@ impl Record {
             certified_block_hash,
             state,
             author,
-            signature: Signature(0),
+            signature: Signature::default(),
             committed_state,
+            share: None,
         });
         let hash = value.digest();
         match &mut value {
@@ -209,7 +291,7 @@ impl Record {
             round,
             highest_certified_block_round,
             author,
-            signature: Signature(0),
+            signature: Signature::default(),
         });
         let hash = value.digest();
         match &mut value {
@@ -224,29 +306,40 @@ impl Record {
         round: Round,
         certified_block_hash: BlockHash,
         state: State,
-        votes: Vec<(Author, Signature)>,
+        votes: MultiSignature,
         committed_state: Option<State>,
         author: Author,
     ) -> Record {
-        let mut value = Record::QuorumCertificate(QuorumCertificate {
+        let mut value = Record::QuorumCertificate(Arc::new(QuorumCertificate {
             epoch_id,
             round,
             certified_block_hash,
             state,
-            votes,
+            votes: Arc::new(votes),
             committed_state,
             author,
-            signature: Signature(0),
-        });
+            signature: Signature::default(),
+        }));
         let hash = value.digest();
         match &mut value {
-            Record::QuorumCertificate(qc) => qc.signature = Signature::sign(hash, qc.author),
+            Record::QuorumCertificate(qc) => {
+                Arc::make_mut(qc).signature = Signature::sign(hash, qc.author)
+            }
             _ => unreachable!(),
         }
         value
     }
 
-    #[cfg(test)]
+    /// Size of the signatures of the record on the wire, in bytes.
+    pub fn signature_bytes(&self) -> usize {
+        match self {
+            Record::QuorumCertificate(x) => x.signature_bytes(),
+            Record::Vote(x) if x.share.is_some() => SignatureShare::num_bytes(),
+            _ => Signature::num_bytes(),
+        }
+    }
+
+    #[cfg(any(test, feature = "proptest"))]
     pub fn author(&self) -> Author {
         match self {
             Record::Block(x) => x.author,
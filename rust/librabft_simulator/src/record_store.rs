@@ -3,12 +3,19 @@
 
 use super::*;
 use base_types::*;
+use bft_simulator_runtime::threshold::ThresholdKeys;
+use commit_proof::{completes_commit_rule, CommitProof};
 use pacemaker::{Pacemaker, PacemakerState};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use record::*;
+use shared_map::SharedMap;
 use smr_context::SMRContext;
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     fmt::Debug,
+    ops::Deref,
+    sync::Arc,
 };
 
 #[cfg(test)]
@@ -27,23 +34,57 @@ pub trait RecordStore: Debug {
     fn highest_timeout_certificate_round(&self) -> Round;
     /// Query the round of the highest commit.
     fn highest_committed_round(&self) -> Round;
+    /// Query the round of the QC that proves the highest commit, or 0.
+    fn highest_commit_certificate_round(&self) -> Round;
     /// Query the last QC of the highest commit rule.
     fn highest_commit_certificate(&self) -> Option<&QuorumCertificate>;
+    /// Proof that the block `block_hash` was committed, with as few QCs as possible.
+    fn commit_proof(&self, block_hash: BlockHash) -> Option<CommitProof>;
     /// Current round as seen by the record store.
     fn current_round(&self) -> Round;
+    /// Rule used to detect commits.
+    fn commit_rule(&self) -> CommitRule;
+    /// Recipients of the votes.
+    fn vote_delivery(&self) -> VoteDelivery;
+    /// Whether a QC with the votes of all validators commits its block right away.
+    fn fast_path(&self) -> bool;
+    /// Whether the block at the given round was committed by the fast path.
+    fn is_fast_path_commit(&self, round: Round) -> bool;
+    /// Whether the votes of the QCs that we create are aggregated.
+    fn aggregate_signatures(&self) -> bool;
+    /// Whether votes carry signature shares, combined into the threshold signatures of QCs.
+    fn threshold_signatures(&self) -> bool;
+    /// Number of chained blocks that leaders propose in each round.
+    fn blocks_per_round(&self) -> usize;
+    /// Maximal number of blocks without a QC kept per author, if bounded.
+    fn max_uncertified_blocks(&self) -> Option<usize>;
+    /// Number of blocks evicted so far to respect `max_uncertified_blocks`.
+    fn evicted_blocks(&self) -> usize;
 
     /// Iterate on the committed blocks starting after the round `after_round` and ending with the
-    /// highest commit known so far.
-    fn committed_states_after(&self, after_round: Round) -> Vec<(Round, State)>;
+    /// highest commit known so far. Return the round, the state, the author, the time, and the
+    /// hash of each block.
+    fn committed_states_after(
+        &self,
+        after_round: Round,
+    ) -> Vec<(Round, State, Author, NodeTime, BlockHash)>;
+    /// Number of QCs and TCs formed or received so far.
+    fn num_quorum_certificates(&self) -> usize;
+    fn num_timeout_certificates(&self) -> usize;
+    /// Rounds ended by a TC so far, in increasing order.
+    fn timeout_certificate_rounds(&self) -> &[Round];
+    /// Rounds of the verified QCs, once per QC.
+    fn quorum_certificate_rounds(&self) -> Vec<Round>;
 
-    /// Access the block proposed by the leader chosen by the Pacemaker (if any).
+    /// Access the block proposed by the leader chosen by the Pacemaker (if any). When leaders
+    /// propose several blocks per round, this is the tip of the chain, once it is complete.
     fn proposed_block(&self, pacemaker: &Pacemaker) -> Option<(BlockHash, Round, Author)>;
     /// Check if a timeout already exists.
     fn has_timeout(&self, author: Author, round: Round) -> bool;
 
     /// Create a timeout.
     fn create_timeout(&mut self, author: Author, round: Round, smr_context: &mut SMRContext);
-    /// Fetch a command from mempool and propose a block.
+    /// Fetch commands from mempool and propose blocks, up to the number of blocks per round.
     fn propose_block(
         &mut self,
         local_author: Author,
@@ -65,34 +106,66 @@ pub trait RecordStore: Debug {
         local_author: Author,
         smr_context: &mut SMRContext,
     ) -> bool;
+    /// Whether the fast path is on and the QC that we would create misses some votes.
+    fn is_missing_votes(&self, local_author: Author) -> bool;
 
     /// Compute the previous round and the second previous round of a block.
     fn previous_round(&self, block_hash: BlockHash) -> Round;
     fn second_previous_round(&self, block_hash: BlockHash) -> Round;
     /// Pick an author based on a seed, with chances proportional to voting rights.
     fn pick_author(&self, seed: u64) -> Author;
+    /// Authors with voting rights in the epoch, in increasing order.
+    fn validators(&self) -> Vec<Author>;
 
     /// APIs supporting data synchronization.
     fn timeouts(&self) -> Vec<Timeout>;
-    fn current_vote(&self, local_author: Author) -> Option<&Vote>;
+    fn current_vote(&self, local_author: Author) -> Option<Vote>;
     fn block(&self, block_hash: BlockHash) -> Option<&Block>;
+    /// Blocks of the same round chained up to `block_hash`, starting with the first one.
+    fn round_chain(&self, block_hash: BlockHash) -> Vec<&Block>;
     fn known_quorum_certificate_rounds(&self) -> BTreeSet<Round>;
     fn unknown_records(&self, known_qc_rounds: BTreeSet<Round>) -> Vec<Record>;
-    fn insert_network_record(&mut self, record: Record, smr_context: &mut SMRContext);
+    /// Same as `unknown_records`, without copying the records.
+    fn unknown_record_refs(&self, known_qc_rounds: BTreeSet<Round>) -> Vec<RecordRef>;
+    fn insert_network_record(&mut self, record: Record, smr_context: &mut SMRContext)
+        -> Result<()>;
+    /// Insert records in order, after checking their signatures in parallel.
+    fn insert_network_records(
+        &mut self,
+        records: Vec<Record>,
+        smr_context: &mut SMRContext,
+    ) -> Result<()>;
 }
 // -- END FILE --
 
 // -- BEGIN FILE record_store_state --
-#[derive(Debug)]
+/// Collections that grow during an epoch are shared with snapshots: records are kept in
+/// `SharedMap`s, which copy only the shard that a write changes, and round sets are behind `Arc`
+/// pointers, copied on their next write.
+#[derive(Clone, Debug)]
 pub struct RecordStoreState {
     /// Epoch initialization.
     epoch_id: EpochId,
     configuration: EpochConfiguration,
     initial_hash: QuorumCertificateHash,
     initial_state: State,
-    /// Storage of verified blocks and QCs.
-    blocks: HashMap<BlockHash, Block>,
-    quorum_certificates: HashMap<QuorumCertificateHash, QuorumCertificate>,
+    commit_rule: CommitRule,
+    vote_delivery: VoteDelivery,
+    fast_path: bool,
+    aggregate_signatures: bool,
+    blocks_per_round: usize,
+    max_uncertified_blocks: Option<usize>,
+    /// Keys dealt to the validators at the start of the epoch, if QCs use threshold signatures.
+    threshold_keys: Option<ThresholdKeys>,
+    /// Storage of verified blocks and QCs, shared with the other stores of the thread that hold
+    /// them.
+    blocks: SharedMap<BlockHash, Arc<Block>>,
+    quorum_certificates: SharedMap<QuorumCertificateHash, Arc<QuorumCertificate>>,
+    /// Index of the verified blocks and QCs by signature, to detect known records without
+    /// hashing them.
+    block_signatures: SharedMap<Signature, BlockHash>,
+    quorum_certificate_signatures: SharedMap<Signature, QuorumCertificateHash>,
+    /// Tip of the chain of blocks proposed by the leader of the current round.
     current_proposed_block: Option<BlockHash>,
     /// Computed round values.
     highest_quorum_certificate_round: Round,
@@ -101,30 +174,212 @@ pub struct RecordStoreState {
     current_round: Round,
     highest_committed_round: Round,
     highest_commit_certificate_hash: Option<QuorumCertificateHash>,
+    /// QC of the highest committed block, if it was committed by the fast path.
+    highest_fast_path_commit_hash: Option<QuorumCertificateHash>,
+    /// Rounds committed by the fast path so far.
+    fast_path_rounds: Arc<BTreeSet<Round>>,
     /// Storage of verified timeouts at the highest TC round.
     highest_timeout_certificate: Option<Vec<Timeout>>,
     /// Storage of verified votes and timeouts at the current round.
     current_timeouts: HashMap<Author, Timeout>,
-    current_votes: HashMap<Author, Vote>,
+    current_votes: Vec<VoteSet>,
     /// Computed weight values.
     current_timeouts_weight: usize,
     current_election: ElectionState,
+    /// Rounds of the TCs formed so far.
+    timeout_certificate_rounds: Arc<Vec<Round>>,
+    /// Blocks without a known QC, per author in order of insertion, if their number is bounded.
+    uncertified_blocks: HashMap<Author, VecDeque<BlockHash>>,
+    evicted_blocks: usize,
+}
+
+/// A read-only view of a record store. Taking a snapshot does not copy the stored records, so
+/// observers can keep consistent views cheaply as long as they drop them before the store grows
+/// much further.
+#[derive(Clone, Debug)]
+pub struct RecordStoreSnapshot(RecordStoreState);
+
+/// A record of a store, borrowed until it is known to be needed. Blocks and QCs come with the
+/// digests under which they are stored, so that filtering them does not hash them again.
+#[derive(Copy, Clone, Debug)]
+pub enum RecordRef<'a> {
+    Block(BlockHash, &'a Arc<Block>),
+    QuorumCertificate(QuorumCertificateHash, &'a Arc<QuorumCertificate>),
+    Timeout(&'a Timeout),
+}
+
+/// A signature carried by a record, with the digests that it signs.
+enum SignatureClaim {
+    Single(Signature, u64, Author),
+    Aggregate(AggregateSignature, Vec<(Author, u64)>),
 }
 
-/// Counting votes for a proposed block and its execution state.
-#[derive(Debug)]
+/// Votes of the current round on the same block and states. Their common fields are stored once,
+/// and their authors as a bitmap.
+#[derive(Clone, Debug)]
+struct VoteSet {
+    epoch_id: EpochId,
+    round: Round,
+    certified_block_hash: BlockHash,
+    state: State,
+    committed_state: Option<State>,
+    /// Bit `i % 64` of word `i / 64` is set if `Author(i)` voted.
+    signers: Vec<u64>,
+    /// Signatures and shares of the votes, in increasing order of authors.
+    signatures: Vec<(Signature, Option<SignatureShare>)>,
+    /// Voting rights of the authors, updated at each insertion.
+    weight: usize,
+}
+
+/// Counting votes for a proposed block and its execution state. The votes are tallied by their
+/// `VoteSet`.
+#[derive(Clone, Debug)]
 enum ElectionState {
-    Ongoing {
-        ballot: HashMap<(BlockHash, State), usize>,
-    },
-    Won {
-        block_hash: BlockHash,
-        state: State,
-    },
+    Ongoing,
+    Won { block_hash: BlockHash, state: State },
     Closed,
 }
 // -- END FILE --
 
+impl Deref for RecordStoreSnapshot {
+    type Target = RecordStoreState;
+
+    fn deref(&self) -> &RecordStoreState {
+        &self.0
+    }
+}
+
+impl<'a> RecordRef<'a> {
+    pub fn digest(&self) -> u64 {
+        match self {
+            RecordRef::Block(hash, _) => hash.0,
+            RecordRef::QuorumCertificate(hash, _) => hash.0,
+            // Timeouts are small, so we copy them to hash them.
+            RecordRef::Timeout(timeout) => Record::Timeout((*timeout).clone()).digest(),
+        }
+    }
+
+    /// A copy of the record, sharing the blocks and QCs of the store.
+    pub fn to_record(&self) -> Record {
+        match self {
+            RecordRef::Block(_, block) => Record::Block((*block).clone()),
+            RecordRef::QuorumCertificate(_, qc) => Record::QuorumCertificate((*qc).clone()),
+            RecordRef::Timeout(timeout) => Record::Timeout((*timeout).clone()),
+        }
+    }
+}
+
+impl SignatureClaim {
+    fn check(&self) -> Result<()> {
+        match self {
+            SignatureClaim::Single(signature, hash, author) => signature.check(*hash, *author),
+            SignatureClaim::Aggregate(signature, digests) => signature.check(digests),
+        }
+    }
+}
+
+impl VoteSet {
+    fn new(vote: &Vote, weight: usize) -> Self {
+        let mut votes = VoteSet {
+            epoch_id: vote.epoch_id,
+            round: vote.round,
+            certified_block_hash: vote.certified_block_hash,
+            state: vote.state.clone(),
+            committed_state: vote.committed_state.clone(),
+            signers: Vec::new(),
+            signatures: Vec::new(),
+            weight: 0,
+        };
+        votes.insert(vote, weight);
+        votes
+    }
+
+    /// Whether `vote` has the same fields as the votes of the set, except for its author and
+    /// signatures.
+    fn matches(&self, vote: &Vote) -> bool {
+        self.epoch_id == vote.epoch_id
+            && self.round == vote.round
+            && self.certified_block_hash == vote.certified_block_hash
+            && self.state == vote.state
+            && self.committed_state == vote.committed_state
+    }
+
+    fn contains(&self, author: Author) -> bool {
+        self.signers
+            .get(author.0 / 64)
+            .map_or(false, |word| word & (1u64 << (author.0 % 64)) != 0)
+    }
+
+    /// Number of authors of the set before `author`.
+    fn rank(&self, author: Author) -> usize {
+        let index = author.0 / 64;
+        let mask = (1u64 << (author.0 % 64)) - 1;
+        let before: u32 = self
+            .signers
+            .iter()
+            .take(index)
+            .map(|word| word.count_ones())
+            .sum();
+        let within = self
+            .signers
+            .get(index)
+            .map_or(0, |word| (word & mask).count_ones());
+        (before + within) as usize
+    }
+
+    /// Add a vote that matches the set, from an author with voting rights `weight` who has not
+    /// voted yet.
+    fn insert(&mut self, vote: &Vote, weight: usize) {
+        let index = vote.author.0 / 64;
+        if self.signers.len() <= index {
+            self.signers.resize(index + 1, 0);
+        }
+        let rank = self.rank(vote.author);
+        self.signers[index] |= 1u64 << (vote.author.0 % 64);
+        self.signatures.insert(rank, (vote.signature, vote.share));
+        self.weight += weight;
+    }
+
+    /// Authors of the votes, in increasing order.
+    fn authors(&self) -> Vec<Author> {
+        bitmap_authors(&self.signers)
+    }
+
+    fn signatures(&self) -> Vec<(Author, Signature)> {
+        self.authors()
+            .into_iter()
+            .zip(&self.signatures)
+            .map(|(author, (signature, _))| (author, *signature))
+            .collect()
+    }
+
+    fn shares(&self) -> Vec<(Author, SignatureShare)> {
+        self.authors()
+            .into_iter()
+            .zip(&self.signatures)
+            .filter_map(|(author, (_, share))| share.map(|share| (author, share)))
+            .collect()
+    }
+
+    /// The vote of `author`, if any.
+    fn vote(&self, author: Author) -> Option<Vote> {
+        if !self.contains(author) {
+            return None;
+        }
+        let (signature, share) = self.signatures[self.rank(author)];
+        Some(Vote {
+            epoch_id: self.epoch_id,
+            round: self.round,
+            certified_block_hash: self.certified_block_hash,
+            state: self.state.clone(),
+            committed_state: self.committed_state.clone(),
+            author,
+            signature,
+            share,
+        })
+    }
+}
+
 struct BackwardQuorumCertificateIterator<'a> {
     store: &'a RecordStoreState,
     current_hash: QuorumCertificateHash,
@@ -156,21 +411,45 @@ impl<'a> Iterator for BackwardQuorumCertificateIterator<'a> {
     }
 }
 
+impl<'a> BackwardQuorumCertificateIterator<'a> {
+    /// Same as `next`, with the hash of the QC.
+    fn next_with_hash(&mut self) -> Option<(QuorumCertificateHash, &'a QuorumCertificate)> {
+        let qc_hash = self.current_hash;
+        self.next().map(|qc| (qc_hash, qc))
+    }
+}
+
 impl RecordStoreState {
     pub fn new(
         initial_hash: QuorumCertificateHash,
         initial_state: State,
         epoch_id: EpochId,
         configuration: EpochConfiguration,
+        commit_rule: CommitRule,
     ) -> RecordStoreState {
-        warn!("Creating new record store for epoch: {:?}, initial_hash: {:?}, initial_state: {:?}, configuration: {:?}", epoch_id, initial_hash, initial_state, configuration);
+        tracing::warn!(
+            epoch = epoch_id.0,
+            "Creating new record store, initial_hash: {:?}, initial_state: {:?}, configuration: {:?}",
+            initial_hash,
+            initial_state,
+            configuration
+        );
         RecordStoreState {
             configuration,
             initial_hash,
             initial_state,
+            commit_rule,
+            vote_delivery: VoteDelivery::Proposer,
+            fast_path: false,
+            aggregate_signatures: false,
+            blocks_per_round: 1,
+            max_uncertified_blocks: None,
+            threshold_keys: None,
             epoch_id,
-            blocks: HashMap::new(),
-            quorum_certificates: HashMap::new(),
+            blocks: SharedMap::new(),
+            quorum_certificates: SharedMap::new(),
+            block_signatures: SharedMap::new(),
+            quorum_certificate_signatures: SharedMap::new(),
             current_proposed_block: None,
             highest_quorum_certificate_round: Round(0),
             highest_quorum_certificate_hash: initial_hash,
@@ -178,13 +457,106 @@ impl RecordStoreState {
             current_round: Round(1),
             highest_committed_round: Round(0),
             highest_commit_certificate_hash: None,
+            highest_fast_path_commit_hash: None,
+            fast_path_rounds: Arc::new(BTreeSet::new()),
             highest_timeout_certificate: None,
             current_timeouts: HashMap::new(),
-            current_votes: HashMap::new(),
+            current_votes: Vec::new(),
             current_timeouts_weight: 0,
-            current_election: ElectionState::Ongoing {
-                ballot: HashMap::new(),
-            },
+            current_election: ElectionState::Ongoing,
+            timeout_certificate_rounds: Arc::new(Vec::new()),
+            uncertified_blocks: HashMap::new(),
+            evicted_blocks: 0,
+        }
+    }
+
+    /// Send votes to the given nodes, who then assemble the QCs. Votes are sent to the proposer
+    /// by default.
+    pub fn with_vote_delivery(mut self, vote_delivery: VoteDelivery) -> Self {
+        self.vote_delivery = vote_delivery;
+        self
+    }
+
+    /// Commit blocks as soon as they have a QC with the votes of all validators, in addition to
+    /// the commit rule. This is only safe if the QC reaches other nodes before they time out.
+    pub fn with_fast_path(mut self, fast_path: bool) -> Self {
+        self.fast_path = fast_path;
+        self
+    }
+
+    /// Aggregate the votes of the QCs that we create into a single signature.
+    pub fn with_aggregate_signatures(mut self, aggregate_signatures: bool) -> Self {
+        self.aggregate_signatures = aggregate_signatures;
+        self
+    }
+
+    /// Let leaders propose a chain of `blocks_per_round` blocks in each round, voted on as a
+    /// whole through its last block.
+    pub fn with_blocks_per_round(mut self, blocks_per_round: usize) -> Self {
+        self.blocks_per_round = blocks_per_round;
+        self
+    }
+
+    /// Keep at most `max_uncertified_blocks` blocks without a QC per author, if given, so that
+    /// flooding nodes cannot exhaust memory. The oldest blocks are evicted first.
+    pub fn with_max_uncertified_blocks(mut self, max_uncertified_blocks: Option<usize>) -> Self {
+        self.max_uncertified_blocks = max_uncertified_blocks;
+        self
+    }
+
+    /// Deal threshold keys to the validators of the epoch, and create QCs with threshold
+    /// signatures instead of the votes.
+    pub fn with_threshold_signatures(mut self, threshold_signatures: bool) -> Self {
+        self.threshold_keys = if threshold_signatures {
+            Some(ThresholdKeys::deal(
+                self.epoch_id.0 as u64,
+                &self.configuration,
+            ))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Take a read-only view of the current state of the store, without copying its records.
+    pub fn snapshot(&self) -> RecordStoreSnapshot {
+        RecordStoreSnapshot(self.clone())
+    }
+
+    /// Drop the votes and timeouts of the current round, as after a restart. Blocks and
+    /// certificates are durable.
+    pub fn restart(&mut self) {
+        self.current_timeouts = HashMap::new();
+        self.current_votes = Vec::new();
+        self.current_timeouts_weight = 0;
+        self.current_election = ElectionState::Ongoing;
+    }
+
+    /// Drop all the records of the epoch, as after a restart that lost them. The initial state
+    /// and the settings of the epoch are kept.
+    pub fn erase(&mut self) {
+        let mut store = RecordStoreState::new(
+            self.initial_hash,
+            self.initial_state.clone(),
+            self.epoch_id,
+            self.configuration.clone(),
+            self.commit_rule,
+        )
+        .with_vote_delivery(self.vote_delivery)
+        .with_fast_path(self.fast_path)
+        .with_aggregate_signatures(self.aggregate_signatures)
+        .with_blocks_per_round(self.blocks_per_round)
+        .with_max_uncertified_blocks(self.max_uncertified_blocks);
+        store.threshold_keys = self.threshold_keys.take();
+        *self = store;
+    }
+
+    /// The node expected to assemble the QC of a block.
+    pub fn quorum_certificate_author(&self, block_hash: BlockHash) -> Author {
+        let block = self.block(block_hash).unwrap();
+        match self.vote_delivery {
+            VoteDelivery::NextLeader => PacemakerState::leader(self, block.round + 1),
+            VoteDelivery::Proposer | VoteDelivery::Broadcast => block.author,
         }
     }
 
@@ -202,57 +574,234 @@ impl RecordStoreState {
         self.current_round = round;
         self.current_proposed_block = None;
         self.current_timeouts = HashMap::new();
-        self.current_votes = HashMap::new();
+        self.current_votes = Vec::new();
         self.current_timeouts_weight = 0;
-        self.current_election = ElectionState::Ongoing {
-            ballot: HashMap::new(),
-        };
+        self.current_election = ElectionState::Ongoing;
     }
 
-    fn update_commit_3chain_round(&mut self, qc_hash: QuorumCertificateHash) {
-        let rounds = {
-            let mut iter = self.ancestor_rounds(qc_hash);
-            let r3 = iter.next();
-            let r2 = iter.next();
-            let r1 = iter.next();
-            (r1, r2, r3)
-        };
-        if let (Some(r1), Some(r2), Some(r3)) = rounds {
-            if r3 == r2 + 1 && r2 == r1 + 1 {
-                if r1 > self.highest_committed_round {
-                    self.highest_committed_round = r1;
-                    self.highest_commit_certificate_hash = Some(qc_hash);
-                }
+    fn update_commit_round(&mut self, qc_hash: QuorumCertificateHash) {
+        let rounds: Vec<_> = self
+            .ancestor_rounds(qc_hash)
+            .take(self.commit_rule.chain_length())
+            .collect();
+        if rounds.len() == self.commit_rule.chain_length()
+            && rounds.windows(2).all(|pair| pair[0] == pair[1] + 1)
+        {
+            let committed_round = *rounds.last().unwrap();
+            if committed_round > self.highest_committed_round {
+                self.highest_committed_round = committed_round;
+                self.highest_commit_certificate_hash = Some(qc_hash);
+                self.highest_fast_path_commit_hash = None;
+            }
+        }
+        if self.fast_path {
+            let (round, weight) = {
+                let qc = self.quorum_certificate(qc_hash).unwrap();
+                let authors = qc.votes.authors();
+                (qc.round, self.configuration.count_votes(authors.iter()))
+            };
+            if weight == self.configuration.total_votes() && round > self.highest_committed_round {
+                self.highest_committed_round = round;
+                self.highest_fast_path_commit_hash = Some(qc_hash);
+                Arc::make_mut(&mut self.fast_path_rounds).insert(round);
             }
         }
     }
 
     fn vote_committed_state(&self, block_hash: BlockHash) -> Option<State> {
+        // A QC for the block would complete the commit rule of the last of these QCs.
         let block = self.block(block_hash).unwrap();
-        let r3 = block.round;
-        let qc2_hash = block.previous_quorum_certificate_hash;
-        let mut iter = BackwardQuorumCertificateIterator::new(&self, qc2_hash);
-        let opt_qc2 = iter.next();
-        let opt_qc1 = iter.next();
-        if let (Some(qc1), Some(qc2)) = (opt_qc1, opt_qc2) {
-            let r2 = qc2.round;
-            let r1 = qc1.round;
-            if r3 == r2 + 1 && r2 == r1 + 1 {
-                return Some(qc1.state.clone());
+        let distance = self.commit_rule.commit_certificate_distance();
+        let qcs: Vec<_> =
+            BackwardQuorumCertificateIterator::new(&self, block.previous_quorum_certificate_hash)
+                .take(distance)
+                .collect();
+        let mut round = block.round;
+        for qc in &qcs {
+            if round != qc.round + 1 {
+                return None;
             }
+            round = qc.round;
+        }
+        if qcs.len() == distance {
+            Some(qcs[distance - 1].state.clone())
+        } else {
+            None
         }
-        None
     }
 
-    fn verify_network_record(&self, record: &Record) -> Result<u64> {
-        let hash = record.digest();
+    /// Charge the cryptographic operations needed to verify a record received from the network,
+    /// whether or not it passes the verification.
+    fn charge_verification(record: &Record, smr_context: &mut SMRContext) {
+        smr_context.charge_crypto(CryptoOperation::Verify, 1);
+        if let Record::QuorumCertificate(qc) = record {
+            match &*qc.votes {
+                MultiSignature::Individual(signatures) => {
+                    smr_context.charge_crypto(CryptoOperation::Verify, signatures.len());
+                }
+                MultiSignature::Aggregate(authors, _) => {
+                    smr_context.charge_crypto(CryptoOperation::Aggregate, authors.len());
+                    smr_context.charge_crypto(CryptoOperation::Verify, 1);
+                }
+                MultiSignature::Threshold(_) => {
+                    smr_context.charge_crypto(CryptoOperation::Verify, 1);
+                }
+            }
+        }
+    }
+
+    /// Signatures of a record with digest `hash`. Threshold signatures are checked with the
+    /// keys of the epoch instead.
+    fn signature_claims(record: &Record, hash: u64) -> Vec<SignatureClaim> {
+        let mut claims = Vec::new();
+        let (signature, author) = match record {
+            Record::Block(block) => (block.signature, block.author),
+            Record::Vote(vote) => (vote.signature, vote.author),
+            Record::QuorumCertificate(qc) => {
+                let digest = |author| {
+                    Record::digest(&Record::Vote(Vote {
+                        epoch_id: qc.epoch_id,
+                        round: qc.round,
+                        certified_block_hash: qc.certified_block_hash,
+                        state: qc.state.clone(),
+                        committed_state: qc.committed_state.clone(),
+                        author,
+                        signature: Signature::default(), // ignored
+                        share: None,                     // ignored
+                    }))
+                };
+                match &*qc.votes {
+                    MultiSignature::Individual(signatures) => {
+                        for (author, signature) in signatures {
+                            claims.push(SignatureClaim::Single(
+                                *signature,
+                                digest(*author),
+                                *author,
+                            ));
+                        }
+                    }
+                    MultiSignature::Aggregate(authors, signature) => {
+                        let digests = authors.iter().map(|author| (*author, digest(*author)));
+                        claims.push(SignatureClaim::Aggregate(
+                            signature.clone(),
+                            digests.collect(),
+                        ));
+                    }
+                    MultiSignature::Threshold(_) => (),
+                }
+                (qc.signature, qc.author)
+            }
+            Record::Timeout(timeout) => (timeout.signature, timeout.author),
+        };
+        claims.push(SignatureClaim::Single(signature, hash, author));
+        claims
+    }
+
+    pub(crate) fn check_signatures(record: &Record, hash: u64) -> Result<()> {
+        Self::signature_claims(record, hash)
+            .iter()
+            .try_for_each(SignatureClaim::check)
+    }
+
+    /// Hash the selected records of a batch and check their signatures, on a thread pool with
+    /// the `parallel` feature. Returns the digest of each selected record, with the result of
+    /// the check.
+    fn hash_and_check_records(
+        records: &[Record],
+        selected: &[bool],
+    ) -> Vec<Option<(u64, Result<()>)>> {
+        #[cfg(feature = "ed25519")]
+        let seed = bft_simulator_runtime::ed25519::key_seed();
+        let record_hasher = hashing::record_hasher();
+        let check = move |(record, selected): (&Record, &bool)| {
+            if !*selected {
+                return None;
+            }
+            // Worker threads must use the keys and the hash function of the current thread.
+            #[cfg(feature = "ed25519")]
+            bft_simulator_runtime::ed25519::use_key_seed(seed);
+            hashing::set_record_hasher(record_hasher.clone());
+            let hash = record.digest();
+            Some((hash, Self::check_signatures(record, hash)))
+        };
+        #[cfg(feature = "parallel")]
+        let records = records.par_iter().zip(selected.par_iter());
+        #[cfg(not(feature = "parallel"))]
+        let records = records.iter().zip(selected.iter());
+        records.map(check).collect()
+    }
+
+    /// Checks of a record that only depend on its own fields and on the configuration of the
+    /// epoch, hence cannot be changed by the records inserted before it. These are much
+    /// cheaper than hashing the record and checking its signatures.
+    fn check_record_fields(&self, record: &Record) -> Result<()> {
         match record {
+            Record::Block(block) => ensure!(block.round > Round(0), "Rounds must start at 1"),
+            Record::Vote(vote) => {
+                ensure!(
+                    vote.epoch_id == self.epoch_id,
+                    "Epoch identifier of vote ({:?}) must match the current epoch ({:?}).",
+                    vote.epoch_id,
+                    self.epoch_id
+                );
+                // Rounds only increase, so the vote cannot become current later.
+                ensure!(
+                    vote.round >= self.current_round,
+                    "Votes for past rounds are outdated."
+                );
+            }
+            Record::QuorumCertificate(qc) => {
+                ensure!(
+                    qc.epoch_id == self.epoch_id,
+                    "Epoch identifier of QC ({:?}) must match the current epoch ({:?}).",
+                    qc.epoch_id,
+                    self.epoch_id
+                );
+                match (&*qc.votes, &self.threshold_keys) {
+                    (MultiSignature::Threshold(_), keys) => ensure!(
+                        keys.is_some(),
+                        "Threshold signatures are not used in this epoch."
+                    ),
+                    (votes, _) => ensure!(
+                        self.configuration.count_votes(&votes.authors())
+                            >= self.configuration.quorum_threshold(),
+                        "Votes in QCs must form a quorum"
+                    ),
+                }
+            }
+            Record::Timeout(timeout) => {
+                ensure!(
+                    timeout.epoch_id == self.epoch_id,
+                    "Epoch identifier of timeout ({:?}) must match the current epoch ({:?}).",
+                    timeout.epoch_id,
+                    self.epoch_id
+                );
+                ensure!(
+                    timeout.round >= self.current_round,
+                    "Timeouts for past rounds are outdated."
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify a record. `checked_hash` is the digest of the record if its signatures were
+    /// checked already.
+    fn verify_network_record(&self, record: &Record, checked_hash: Option<u64>) -> Result<u64> {
+        let digest = || checked_hash.unwrap_or_else(|| record.digest());
+        let check_signatures = |hash| match checked_hash {
+            Some(_) => Ok(()),
+            None => Self::check_signatures(record, hash),
+        };
+        // Hashing records is comparatively expensive in large simulations, so we do it after the
+        // cheap checks whenever possible.
+        let hash = match record {
             Record::Block(block) => {
+                let hash = digest();
                 ensure!(
                     !self.blocks.contains_key(&BlockHash(hash)),
                     "Block was already inserted."
                 );
-                block.signature.check(hash, block.author)?;
                 ensure!(
                     block.previous_quorum_certificate_hash == self.initial_hash
                         || self
@@ -260,6 +809,24 @@ impl RecordStoreState {
                             .contains_key(&block.previous_quorum_certificate_hash),
                     "The previous QC (if any) must be verified first."
                 );
+                if let Some(previous_hash) = block.previous_block_hash {
+                    let previous_block = match self.block(previous_hash) {
+                        Some(previous_block) => previous_block,
+                        None => bail!("The previous block of the round must be verified first."),
+                    };
+                    ensure!(
+                        previous_block.round == block.round
+                            && previous_block.author == block.author
+                            && previous_block.previous_quorum_certificate_hash
+                                == block.previous_quorum_certificate_hash,
+                        "Chained blocks must share their round, author, and previous QC"
+                    );
+                    ensure!(
+                        self.round_chain(previous_hash).len() < self.blocks_per_round,
+                        "Chains of blocks cannot exceed {} blocks per round",
+                        self.blocks_per_round
+                    );
+                }
                 if self.initial_hash == block.previous_quorum_certificate_hash {
                     ensure!(block.round > Round(0), "Rounds must start at 1");
                 } else {
@@ -272,6 +839,8 @@ impl RecordStoreState {
                         "Rounds must be increasing"
                     );
                 }
+                check_signatures(hash)?;
+                hash
             }
             Record::Vote(vote) => {
                 ensure!(
@@ -299,10 +868,21 @@ impl RecordStoreState {
                     vote.round
                 );
                 ensure!(
-                    !self.current_votes.contains_key(&vote.author),
+                    !self
+                        .current_votes
+                        .iter()
+                        .any(|votes| votes.contains(vote.author)),
                     "We insert votes only for authors who haven't voted yet."
                 );
-                vote.signature.check(hash, vote.author)?
+                let hash = digest();
+                check_signatures(hash)?;
+                if let Some(keys) = &self.threshold_keys {
+                    match vote.share {
+                        Some(share) => keys.check_share(vote.share_digest(), vote.author, share)?,
+                        None => bail!("Votes must carry a signature share."),
+                    }
+                }
+                hash
             }
             Record::QuorumCertificate(qc) => {
                 ensure!(
@@ -311,6 +891,11 @@ impl RecordStoreState {
                     qc.epoch_id,
                     self.epoch_id
                 );
+                ensure!(
+                    !self.is_known_quorum_certificate(qc),
+                    "QuorumCertificate was already inserted."
+                );
+                let hash = digest();
                 ensure!(
                     !self
                         .quorum_certificates
@@ -326,34 +911,33 @@ impl RecordStoreState {
                     "The round of the QC must match the certified block."
                 );
                 ensure!(
-                    qc.author == self.block(qc.certified_block_hash).unwrap().author,
-                    "QCs must be created by the author of the certified block"
+                    qc.author == self.quorum_certificate_author(qc.certified_block_hash),
+                    "QCs must be created by the author of the certified block, or by the next \
+                     leader if votes are sent to the next leader"
                 );
                 ensure!(
                     self.vote_committed_state(qc.certified_block_hash) == qc.committed_state,
                     "The committed_state value of a QC must follow the commit rule."
                 );
-                let mut weight = 0;
-                for (author, signature) in &qc.votes {
-                    let original_vote_digest = Record::digest(&Record::Vote(Vote {
-                        epoch_id: self.epoch_id,
-                        round: qc.round,
-                        certified_block_hash: qc.certified_block_hash,
-                        state: qc.state.clone(),
-                        committed_state: qc.committed_state.clone(),
-                        author: author.clone(),
-                        signature: Signature(0), // ignored
-                    }));
-                    signature.check(original_vote_digest, *author)?;
-                    weight += self.configuration.weight(author);
+                match (&*qc.votes, &self.threshold_keys) {
+                    // Threshold signatures can only be combined by a quorum.
+                    (MultiSignature::Threshold(signature), Some(keys)) => {
+                        keys.check(qc.share_digest(), *signature)?
+                    }
+                    (MultiSignature::Threshold(_), None) => {
+                        bail!("Threshold signatures are not used in this epoch.")
+                    }
+                    (votes, _) => {
+                        let weight = self.configuration.count_votes(&votes.authors());
+                        ensure!(
+                            weight >= self.configuration.quorum_threshold(),
+                            "Votes in QCs must form a quorum"
+                        );
+                    }
                 }
-                ensure!(
-                    weight >= self.configuration.quorum_threshold(),
-                    "Votes in QCs must form a quorum"
-                );
-                // TODO: do not recompute hash
-                qc.signature
-                    .check(Record::QuorumCertificate(qc.clone()).digest(), qc.author)?;
+                // Individual and aggregate votes are checked with the signature of the QC.
+                check_signatures(hash)?;
+                hash
             }
             Record::Timeout(timeout) => {
                 ensure!(
@@ -377,80 +961,303 @@ impl RecordStoreState {
                     !self.current_timeouts.contains_key(&timeout.author),
                     "A timeout is already known for the same round and the same author"
                 );
-                timeout.signature.check(hash, timeout.author)?;
+                let hash = digest();
+                check_signatures(hash)?;
+                hash
             }
-        }
+        };
         Ok(hash)
     }
 
-    fn quorum_certificate(&self, qc_hash: QuorumCertificateHash) -> Option<&QuorumCertificate> {
+    /// Whether a record is already known or outdated. Nodes receive most records many times, so
+    /// this is checked before the (slower) verification of the record.
+    fn is_redundant_record(&self, record: &Record) -> bool {
+        match record {
+            Record::Block(block) => self.is_known_block(block),
+            Record::Vote(vote) => {
+                vote.epoch_id == self.epoch_id
+                    && (vote.round < self.current_round
+                        || self.current_vote(vote.author).as_ref() == Some(vote))
+            }
+            Record::QuorumCertificate(qc) => self.is_known_quorum_certificate(qc),
+            Record::Timeout(timeout) => {
+                timeout.epoch_id == self.epoch_id
+                    && (timeout.round < self.current_round
+                        || self.current_timeouts.get(&timeout.author) == Some(timeout))
+            }
+        }
+    }
+
+    /// Whether a record is already known or outdated, hashing blocks if needed.
+    pub(crate) fn is_known_record(&self, record: &Record) -> bool {
+        match record {
+            Record::Block(_) => self.blocks.contains_key(&BlockHash(record.digest())),
+            _ => self.is_redundant_record(record),
+        }
+    }
+
+    /// Whether the same block was already inserted, without hashing it.
+    pub(crate) fn is_known_block(&self, block: &Block) -> bool {
+        match self.block_signatures.get(&block.signature) {
+            Some(hash) => *self.blocks[hash] == *block,
+            None => false,
+        }
+    }
+
+    /// Whether the same QC was already inserted. Copies of a QC share their votes, so this is
+    /// much faster than hashing the QC.
+    fn is_known_quorum_certificate(&self, qc: &QuorumCertificate) -> bool {
+        match self.quorum_certificate_signatures.get(&qc.signature) {
+            Some(hash) => *self.quorum_certificates[hash] == *qc,
+            None => false,
+        }
+    }
+
+    pub(crate) fn quorum_certificate(
+        &self,
+        qc_hash: QuorumCertificateHash,
+    ) -> Option<&QuorumCertificate> {
+        self.quorum_certificates.get(&qc_hash).map(|qc| &**qc)
+    }
+
+    /// The copy of a QC shared with the messages that carry it.
+    pub(crate) fn shared_quorum_certificate(
+        &self,
+        qc_hash: QuorumCertificateHash,
+    ) -> Option<&Arc<QuorumCertificate>> {
         self.quorum_certificates.get(&qc_hash)
     }
 
+    pub(crate) fn shared_highest_commit_certificate(&self) -> Option<&Arc<QuorumCertificate>> {
+        self.shared_quorum_certificate(self.highest_commit_certificate_hash?)
+    }
+
+    pub(crate) fn shared_highest_quorum_certificate(&self) -> Option<&Arc<QuorumCertificate>> {
+        self.shared_quorum_certificate(self.highest_quorum_certificate_hash)
+    }
+
+    /// The copy of a block shared with the messages that carry it.
+    pub(crate) fn shared_block(&self, block_hash: BlockHash) -> Option<&Arc<Block>> {
+        self.blocks.get(&block_hash)
+    }
+
+    /// Same as `round_chain`, with the shared copies of the blocks.
+    pub(crate) fn shared_round_chain(&self, block_hash: BlockHash) -> Vec<&Arc<Block>> {
+        let mut chain = Vec::new();
+        let mut current = Some(block_hash);
+        while let Some(hash) = current {
+            let block = self.shared_block(hash).unwrap();
+            chain.push(block);
+            current = block.previous_block_hash;
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// All the QCs of the store, in no particular order.
+    pub(crate) fn quorum_certificates(&self) -> Vec<&QuorumCertificate> {
+        self.quorum_certificates.values().map(|qc| &**qc).collect()
+    }
+
+    /// The QC `qc_hash` and the QCs that its block extends, down to the start of the epoch.
+    pub(crate) fn quorum_certificate_chain(
+        &self,
+        qc_hash: QuorumCertificateHash,
+    ) -> Vec<&QuorumCertificate> {
+        BackwardQuorumCertificateIterator::new(self, qc_hash).collect()
+    }
+
+    /// Insert a record that we just created. Our own records pass verification, so errors can
+    /// only come from inconsistent executions. Verifying them costs no simulated time.
+    fn insert_own_record(&mut self, record: Record, smr_context: &mut SMRContext) {
+        tracing::debug!("Inserting our own {:?}", record);
+        if self.is_redundant_record(&record) {
+            tracing::debug!("=> Skipped: redundant record");
+            return;
+        }
+        if let Err(err) = self.insert_unchecked_record(record, None, smr_context) {
+            tracing::warn!("Failed to insert our own record: {}", err);
+        }
+    }
+
     fn compute_state(&self, block_hash: BlockHash, smr_context: &mut SMRContext) -> Option<State> {
         let block = self.block(block_hash).unwrap();
-        let (previous_state, previous_voters, previous_author) = {
+        let (qc_state, previous_author, previous_voters) = {
             if block.previous_quorum_certificate_hash == self.initial_hash {
-                (&self.initial_state, None, Vec::new())
+                (self.initial_state.clone(), None, Vec::new())
             } else {
                 let previous_qc = self
                     .quorum_certificate(block.previous_quorum_certificate_hash)
                     .unwrap();
-                let voters = previous_qc.votes.iter().map(|x| x.0).collect();
-                (&previous_qc.state, Some(previous_qc.author), voters)
+                let voters = previous_qc.votes.authors();
+                (previous_qc.state.clone(), Some(previous_qc.author), voters)
             }
         };
+        // Chained blocks execute on top of the previous block of the round. They share the
+        // previous QC of the first block, hence its author and voters.
+        let previous_state = match block.previous_block_hash {
+            Some(previous_hash) => self.compute_state(previous_hash, smr_context)?,
+            None => qc_state,
+        };
         smr_context.compute(
-            previous_state,
+            &previous_state,
             block.command.clone(),
             block.time,
-            previous_voters,
             previous_author,
+            previous_voters,
         )
     }
 
-    fn try_insert_network_record(
+    /// Verify and insert a record that is not redundant. `checked_hash` is the digest of the
+    /// record if its signatures were checked already.
+    fn insert_unchecked_record(
         &mut self,
         record: Record,
+        checked_hash: Option<u64>,
+        smr_context: &mut SMRContext,
+    ) -> Result<()> {
+        // Check that the record is "relevant" and that invariants of "verified records", such
+        // as chaining, are respected. Other nodes routinely send records that fail these checks.
+        let hash = match self.verify_network_record(&record, checked_hash) {
+            Ok(hash) => hash,
+            Err(err) => {
+                tracing::debug!("=> Skipped: {}", err);
+                return Ok(());
+            }
+        };
+        // TODO: discard unneeded records from self.blocks and self.quorum_certificates
+        self.insert_verified_record(record, hash, smr_context)
+    }
+
+    /// Evict the oldest blocks of `author` without a QC beyond `max_uncertified_blocks`. Blocks
+    /// that the current round may still certify, and those chained to others, are kept.
+    fn evict_uncertified_blocks(&mut self, author: Author) {
+        let limit = match self.max_uncertified_blocks {
+            Some(limit) => limit,
+            None => return,
+        };
+        let mut needed: HashSet<_> = self
+            .current_votes
+            .iter()
+            .map(|votes| votes.certified_block_hash)
+            .collect();
+        if let Some(block_hash) = self.current_proposed_block {
+            needed.extend(self.chain_hashes(block_hash));
+        }
+        let blocks = &self.blocks;
+        let queue = self.uncertified_blocks.get_mut(&author).unwrap();
+        needed.extend(
+            queue
+                .iter()
+                .filter_map(|hash| blocks[hash].previous_block_hash),
+        );
+        while queue.len() > limit {
+            let position = match queue.iter().position(|hash| !needed.contains(hash)) {
+                Some(position) => position,
+                None => break,
+            };
+            let block_hash = queue.remove(position).unwrap();
+            if let Some(block) = self.blocks.remove(&block_hash) {
+                self.block_signatures.remove(&block.signature);
+            }
+            self.evicted_blocks += 1;
+        }
+    }
+
+    /// Stop tracking the blocks certified by a new QC, up to the first block of their round.
+    fn forget_uncertified_blocks(&mut self, block_hash: BlockHash) {
+        if self.max_uncertified_blocks.is_none() {
+            return;
+        }
+        let author = self.blocks[&block_hash].author;
+        let chain = self.chain_hashes(block_hash);
+        if let Some(queue) = self.uncertified_blocks.get_mut(&author) {
+            queue.retain(|hash| !chain.contains(hash));
+        }
+    }
+
+    /// Same as `round_chain`, without copying the blocks.
+    fn round_chain_refs(&self, block_hash: BlockHash) -> Vec<RecordRef> {
+        let mut chain = Vec::new();
+        let mut current = Some(block_hash);
+        while let Some(hash) = current {
+            let block = self.shared_block(hash).unwrap();
+            chain.push(RecordRef::Block(hash, block));
+            current = block.previous_block_hash;
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Hashes of the blocks of the same round chained up to `block_hash`.
+    fn chain_hashes(&self, block_hash: BlockHash) -> HashSet<BlockHash> {
+        let mut hashes = HashSet::new();
+        let mut current = Some(block_hash);
+        while let Some(hash) = current {
+            hashes.insert(hash);
+            current = self
+                .blocks
+                .get(&hash)
+                .and_then(|block| block.previous_block_hash);
+        }
+        hashes
+    }
+
+    /// Insert a record that passed `verify_network_record`. In the case of QC, this is where
+    /// we check execution states.
+    fn insert_verified_record(
+        &mut self,
+        record: Record,
+        hash: u64,
         smr_context: &mut SMRContext,
     ) -> Result<()> {
-        // First, check that the record is "relevant" and that invariants of "verified records",
-        // such as chaining, are respected.
-        let hash = self.verify_network_record(&record)?;
-        // Second, insert the record. In the case of QC, this is where check execution states.
         match record {
             Record::Block(block) => {
                 let block_hash = BlockHash(hash);
                 if block.round == self.current_round
                     && PacemakerState::leader(&*self, block.round) == block.author
+                    && (block.previous_block_hash.is_none()
+                        || block.previous_block_hash == self.current_proposed_block)
                 {
                     // If we use a VRF, this assumes that we have inserted the highest commit rule
                     // beforehand.
                     self.current_proposed_block = Some(block_hash);
                 }
+                let author = block.author;
+                self.block_signatures.insert(block.signature, block_hash);
                 self.blocks.insert(block_hash, block);
+                if self.max_uncertified_blocks.is_some() {
+                    self.uncertified_blocks
+                        .entry(author)
+                        .or_insert_with(VecDeque::new)
+                        .push_back(block_hash);
+                    self.evict_uncertified_blocks(author);
+                }
             }
             Record::Vote(vote) => {
-                self.current_votes.insert(vote.author, vote.clone());
-                let has_newly_won_election = match &mut self.current_election {
-                    ElectionState::Ongoing { ballot } => {
-                        let entry = ballot
-                            .entry((vote.certified_block_hash, vote.state.clone()))
-                            .or_insert(0);
-                        *entry += self.configuration.weight(&vote.author);
-                        if *entry >= self.configuration.quorum_threshold() {
-                            Some(ElectionState::Won {
-                                block_hash: vote.certified_block_hash,
-                                state: vote.state,
-                            })
-                        } else {
-                            None
-                        }
+                let weight = self.configuration.weight(&vote.author);
+                let index = match self
+                    .current_votes
+                    .iter()
+                    .position(|votes| votes.matches(&vote))
+                {
+                    Some(index) => {
+                        self.current_votes[index].insert(&vote, weight);
+                        index
+                    }
+                    None => {
+                        self.current_votes.push(VoteSet::new(&vote, weight));
+                        self.current_votes.len() - 1
                     }
-                    _ => None,
                 };
-                if let Some(won_election) = has_newly_won_election {
-                    self.current_election = won_election;
+                let votes = &self.current_votes[index];
+                if let ElectionState::Ongoing = self.current_election {
+                    if votes.weight >= self.configuration.quorum_threshold() {
+                        self.current_election = ElectionState::Won {
+                            block_hash: votes.certified_block_hash,
+                            state: votes.state.clone(),
+                        };
+                    }
                 }
             }
             Record::QuorumCertificate(qc) => {
@@ -458,7 +1265,10 @@ impl RecordStoreState {
                 let qc_hash = QuorumCertificateHash(hash);
                 let qc_round = qc.round;
                 let qc_state = qc.state.clone();
+                self.quorum_certificate_signatures
+                    .insert(qc.signature, qc_hash);
                 self.quorum_certificates.insert(qc_hash, qc);
+                self.forget_uncertified_blocks(block_hash);
                 // Make sure that the state in the QC is known to execution.
                 match self.compute_state(block_hash, smr_context) {
                     Some(state) => {
@@ -478,7 +1288,7 @@ impl RecordStoreState {
                     self.highest_quorum_certificate_hash = qc_hash;
                 }
                 self.update_current_round(qc_round + 1);
-                self.update_commit_3chain_round(qc_hash);
+                self.update_commit_round(qc_hash);
             }
             Record::Timeout(timeout) => {
                 self.current_timeouts
@@ -489,12 +1299,132 @@ impl RecordStoreState {
                         self.current_timeouts.iter().map(|x| x.1.clone()).collect();
                     self.highest_timeout_certificate = Some(timeout_certificate);
                     self.highest_timeout_certificate_round = self.current_round;
+                    Arc::make_mut(&mut self.timeout_certificate_rounds).push(self.current_round);
                     self.update_current_round(self.current_round + 1);
                 }
             }
         }
         Ok(())
     }
+
+    /// Graphviz statements drawing the verified blocks and QCs, with node names prefixed by
+    /// `prefix`. Blocks point to the QC that they extend and QCs to the block that they certify.
+    /// Committed blocks are filled and blocks without a QC are dashed.
+    pub(crate) fn dot_statements(&self, prefix: &str) -> Vec<String> {
+        let committed: HashSet<_> = self
+            .committed_states_after(Round(0))
+            .into_iter()
+            .map(|(_, _, _, _, block_hash)| block_hash)
+            .collect();
+        let certified: HashSet<_> = self
+            .quorum_certificates
+            .values()
+            .map(|qc| qc.certified_block_hash)
+            .collect();
+        let mut statements = vec![format!(
+            "\"{}q{}\" [label = \"initial\", shape = box, style = filled];",
+            prefix, self.initial_hash.0
+        )];
+        let mut blocks: Vec<_> = self.blocks.iter().collect();
+        blocks.sort_by_key(|(hash, block)| (block.round, **hash));
+        for (hash, block) in blocks {
+            let style = if committed.contains(hash) {
+                "filled"
+            } else if certified.contains(hash) {
+                "solid"
+            } else {
+                "dashed"
+            };
+            statements.push(format!(
+                "\"{}b{}\" [label = \"round {}\\nauthor {}\", shape = box, style = {}];",
+                prefix, hash.0, block.round.0, block.author.0, style
+            ));
+            statements.push(format!(
+                "\"{}b{}\" -> \"{}q{}\";",
+                prefix, hash.0, prefix, block.previous_quorum_certificate_hash.0
+            ));
+        }
+        let mut qcs: Vec<_> = self.quorum_certificates.iter().collect();
+        qcs.sort_by_key(|(hash, qc)| (qc.round, **hash));
+        for (hash, qc) in qcs {
+            statements.push(format!(
+                "\"{}q{}\" [label = \"QC {}\", shape = ellipse];",
+                prefix, hash.0, qc.round.0
+            ));
+            statements.push(format!(
+                "\"{}q{}\" -> \"{}b{}\";",
+                prefix, hash.0, prefix, qc.certified_block_hash.0
+            ));
+        }
+        statements
+    }
+
+    pub(crate) fn epoch_id(&self) -> EpochId {
+        self.epoch_id
+    }
+
+    pub(crate) fn configuration(&self) -> &EpochConfiguration {
+        &self.configuration
+    }
+
+    /// Hash and state of the initial QC of the epoch, which certifies no block.
+    pub(crate) fn initial_quorum_certificate(&self) -> (QuorumCertificateHash, &State) {
+        (self.initial_hash, &self.initial_state)
+    }
+
+    /// Whether the block certified by the highest QC, or one of its ancestors after the highest
+    /// commit, carries transactions.
+    pub(crate) fn has_uncommitted_transactions(&self) -> bool {
+        let committed_round = self.highest_committed_round();
+        BackwardQuorumCertificateIterator::new(self, self.highest_quorum_certificate_hash())
+            .take_while(|qc| qc.round > committed_round)
+            .any(|qc| {
+                self.round_chain(qc.certified_block_hash)
+                    .iter()
+                    .any(|block| !block.command.transactions.is_empty())
+            })
+    }
+
+    /// Our chain of blocks for the current round, if we proposed one.
+    pub(crate) fn own_proposed_blocks(&self, local_author: Author) -> Vec<&Block> {
+        match self.current_proposed_block {
+            Some(hash) if self.block(hash).map_or(false, |x| x.author == local_author) => {
+                self.round_chain(hash)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The verified blocks, by round and hash.
+    pub(crate) fn verified_blocks(&self) -> Vec<(BlockHash, &Block)> {
+        let mut blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|(hash, block)| (*hash, &**block))
+            .collect();
+        blocks.sort_by_key(|(hash, block)| (block.round, *hash));
+        blocks
+    }
+
+    /// The verified QCs, by round and hash.
+    pub(crate) fn verified_quorum_certificates(
+        &self,
+    ) -> Vec<(QuorumCertificateHash, &QuorumCertificate)> {
+        let mut qcs: Vec<_> = self
+            .quorum_certificates
+            .iter()
+            .map(|(hash, qc)| (*hash, &**qc))
+            .collect();
+        qcs.sort_by_key(|(hash, qc)| (qc.round, *hash));
+        qcs
+    }
+
+    /// The timeouts of the highest TC, if any.
+    pub(crate) fn highest_timeout_certificate(&self) -> Option<&[Timeout]> {
+        self.highest_timeout_certificate
+            .as_ref()
+            .map(|timeouts| timeouts.as_slice())
+    }
 }
 
 impl RecordStore for RecordStoreState {
@@ -502,28 +1432,81 @@ impl RecordStore for RecordStoreState {
         self.current_round
     }
 
+    fn commit_rule(&self) -> CommitRule {
+        self.commit_rule
+    }
+
+    fn vote_delivery(&self) -> VoteDelivery {
+        self.vote_delivery
+    }
+
+    fn fast_path(&self) -> bool {
+        self.fast_path
+    }
+
+    fn is_fast_path_commit(&self, round: Round) -> bool {
+        self.fast_path_rounds.contains(&round)
+    }
+
+    fn aggregate_signatures(&self) -> bool {
+        self.aggregate_signatures
+    }
+
+    fn threshold_signatures(&self) -> bool {
+        self.threshold_keys.is_some()
+    }
+
     fn pick_author(&self, seed: u64) -> Author {
         self.configuration.pick_author(seed)
     }
 
+    fn blocks_per_round(&self) -> usize {
+        self.blocks_per_round
+    }
+
+    fn max_uncertified_blocks(&self) -> Option<usize> {
+        self.max_uncertified_blocks
+    }
+
+    fn evicted_blocks(&self) -> usize {
+        self.evicted_blocks
+    }
+
+    fn validators(&self) -> Vec<Author> {
+        self.configuration.validators()
+    }
+
     fn highest_quorum_certificate_hash(&self) -> QuorumCertificateHash {
         self.highest_quorum_certificate_hash
     }
 
-    fn committed_states_after(&self, after_round: Round) -> Vec<(Round, State)> {
-        let cc_hash = self
-            .highest_commit_certificate_hash
-            .unwrap_or(self.initial_hash);
-        let mut iter = BackwardQuorumCertificateIterator::new(self, cc_hash);
-        iter.next();
-        iter.next();
+    fn committed_states_after(
+        &self,
+        after_round: Round,
+    ) -> Vec<(Round, State, Author, NodeTime, BlockHash)> {
+        let (cc_hash, distance) = match self.highest_fast_path_commit_hash {
+            Some(hash) => (hash, 0),
+            None => (
+                self.highest_commit_certificate_hash
+                    .unwrap_or(self.initial_hash),
+                self.commit_rule.commit_certificate_distance(),
+            ),
+        };
+        let mut iter = BackwardQuorumCertificateIterator::new(self, cc_hash).skip(distance);
         let mut commits = Vec::new();
         while let Some(qc) = iter.next() {
             if qc.round <= after_round {
                 break;
             }
-            info!("Delivering committed state for round {:?}", qc.round);
-            commits.push((qc.round, qc.state.clone()));
+            tracing::info!(round = qc.round.0, "Delivering committed state");
+            let block = self.block(qc.certified_block_hash).unwrap();
+            commits.push((
+                qc.round,
+                qc.state.clone(),
+                block.author,
+                block.time,
+                qc.certified_block_hash,
+            ));
         }
         commits.reverse();
         commits
@@ -533,6 +1516,25 @@ impl RecordStore for RecordStoreState {
         self.highest_quorum_certificate_round
     }
 
+    fn num_quorum_certificates(&self) -> usize {
+        self.quorum_certificates.len()
+    }
+
+    fn num_timeout_certificates(&self) -> usize {
+        self.timeout_certificate_rounds.len()
+    }
+
+    fn timeout_certificate_rounds(&self) -> &[Round] {
+        &self.timeout_certificate_rounds
+    }
+
+    fn quorum_certificate_rounds(&self) -> Vec<Round> {
+        self.quorum_certificates
+            .values()
+            .map(|qc| qc.round)
+            .collect()
+    }
+
     fn highest_timeout_certificate_round(&self) -> Round {
         self.highest_timeout_certificate_round
     }
@@ -541,6 +1543,16 @@ impl RecordStore for RecordStoreState {
         self.highest_committed_round
     }
 
+    fn highest_commit_certificate_round(&self) -> Round {
+        match self
+            .highest_fast_path_commit_hash
+            .or(self.highest_commit_certificate_hash)
+        {
+            Some(hash) => self.quorum_certificate(hash).unwrap().round,
+            None => Round(0),
+        }
+    }
+
     fn previous_round(&self, block_hash: BlockHash) -> Round {
         let block = self.block(block_hash).unwrap();
         let hash = block.previous_quorum_certificate_hash;
@@ -574,6 +1586,7 @@ impl RecordStore for RecordStoreState {
         if let Some(leader) = pacemaker.active_leader() {
             match &self.current_proposed_block {
                 None => None,
+                Some(hash) if self.round_chain(*hash).len() < self.blocks_per_round => None,
                 Some(hash) => {
                     let block = self.block(*hash).unwrap();
                     assert_eq!(block.round, self.current_round);
@@ -587,7 +1600,8 @@ impl RecordStore for RecordStoreState {
     }
 
     fn create_timeout(&mut self, author: Author, round: Round, smr_context: &mut SMRContext) {
-        self.insert_network_record(
+        smr_context.charge_crypto(CryptoOperation::Sign, 1);
+        self.insert_own_record(
             Record::make_timeout(
                 self.epoch_id,
                 round,
@@ -609,15 +1623,33 @@ impl RecordStore for RecordStoreState {
         clock: NodeTime,
         smr_context: &mut SMRContext,
     ) {
-        if let Some(command) = smr_context.fetch() {
-            let block = Record::make_block(
+        // Resume our chain of blocks for this round, if we have started one.
+        let mut previous_block_hash = self.current_proposed_block.filter(|hash| {
+            self.block(*hash).unwrap().author == local_author
+                && self.block(*hash).unwrap().previous_quorum_certificate_hash == previous_qc_hash
+        });
+        let mut length = previous_block_hash.map_or(0, |hash| self.round_chain(hash).len());
+        while length < self.blocks_per_round {
+            let command = match smr_context.fetch(clock) {
+                Some(command) => command,
+                None => return,
+            };
+            let block = Record::make_chained_block(
                 command,
                 clock,
                 previous_qc_hash,
+                previous_block_hash,
                 self.current_round,
                 local_author,
             );
-            self.insert_network_record(block, smr_context)
+            smr_context.charge_crypto(CryptoOperation::Sign, 1);
+            self.insert_own_record(block, smr_context);
+            if self.current_proposed_block == previous_block_hash {
+                // The block was rejected.
+                return;
+            }
+            previous_block_hash = self.current_proposed_block;
+            length += 1;
         }
     }
 
@@ -630,7 +1662,7 @@ impl RecordStore for RecordStoreState {
         let committed_state = self.vote_committed_state(block_hash);
         match self.compute_state(block_hash, smr_context) {
             Some(state) => {
-                let vote = Record::make_vote(
+                let mut vote = Record::make_vote(
                     self.epoch_id,
                     self.block(block_hash).unwrap().round,
                     block_hash,
@@ -638,7 +1670,11 @@ impl RecordStore for RecordStoreState {
                     local_author,
                     committed_state,
                 );
-                self.insert_network_record(vote, smr_context);
+                smr_context.charge_crypto(CryptoOperation::Sign, 1);
+                if let (Record::Vote(vote), Some(keys)) = (&mut vote, &self.threshold_keys) {
+                    vote.share = keys.sign_share(vote.share_digest(), local_author);
+                }
+                self.insert_own_record(vote, smr_context);
                 true
             }
             None => false,
@@ -652,27 +1688,48 @@ impl RecordStore for RecordStoreState {
     ) -> bool {
         let quorum_certificate = match &self.current_election {
             ElectionState::Won { block_hash, state } => {
-                if self.block(*block_hash).unwrap().author != local_author {
+                if self.quorum_certificate_author(*block_hash) != local_author {
                     return false;
                 }
                 let committed_state = self.vote_committed_state(*block_hash);
-                let authors_and_signatures = self
-                    .current_votes
-                    .iter()
-                    .filter_map(|(_, vote)| {
-                        if vote.state == *state {
-                            Some((vote.author, vote.signature))
-                        } else {
-                            None
+                let voters = match self.current_votes.iter().find(|votes| {
+                    votes.certified_block_hash == *block_hash && votes.state == *state
+                }) {
+                    Some(voters) => voters,
+                    None => return false,
+                };
+                let votes = match &self.threshold_keys {
+                    Some(keys) => {
+                        let shares = voters.shares();
+                        smr_context.charge_crypto(CryptoOperation::Aggregate, shares.len());
+                        let digest = share_digest(
+                            self.epoch_id,
+                            self.current_round,
+                            *block_hash,
+                            state,
+                            &committed_state,
+                        );
+                        match keys.combine(digest, &shares) {
+                            Ok(signature) => MultiSignature::Threshold(signature),
+                            Err(_) => return false,
                         }
-                    })
-                    .collect();
+                    }
+                    None => {
+                        let votes =
+                            MultiSignature::new(voters.signatures(), self.aggregate_signatures);
+                        if self.aggregate_signatures {
+                            smr_context.charge_crypto(CryptoOperation::Aggregate, votes.len());
+                        }
+                        votes
+                    }
+                };
+                smr_context.charge_crypto(CryptoOperation::Sign, 1);
                 let quorum_certificate = Record::make_quorum_certificate(
                     self.epoch_id,
                     self.current_round,
                     *block_hash,
                     state.clone(),
-                    authors_and_signatures,
+                    votes,
                     committed_state,
                     local_author,
                 );
@@ -683,10 +1740,27 @@ impl RecordStore for RecordStoreState {
             }
         };
         self.current_election = ElectionState::Closed;
-        self.insert_network_record(quorum_certificate, smr_context);
+        self.insert_own_record(quorum_certificate, smr_context);
         true
     }
 
+    fn is_missing_votes(&self, local_author: Author) -> bool {
+        match &self.current_election {
+            ElectionState::Won { block_hash, state } if self.fast_path => {
+                let weight = self
+                    .current_votes
+                    .iter()
+                    .find(|votes| {
+                        votes.certified_block_hash == *block_hash && votes.state == *state
+                    })
+                    .map_or(0, |votes| votes.weight);
+                self.quorum_certificate_author(*block_hash) == local_author
+                    && weight < self.configuration.total_votes()
+            }
+            _ => false,
+        }
+    }
+
     fn highest_commit_certificate(&self) -> Option<&QuorumCertificate> {
         match self.highest_commit_certificate_hash {
             Some(hash) => Some(self.quorum_certificate(hash).unwrap()),
@@ -698,6 +1772,44 @@ impl RecordStore for RecordStoreState {
         self.quorum_certificate(self.highest_quorum_certificate_hash)
     }
 
+    fn commit_proof(&self, block_hash: BlockHash) -> Option<CommitProof> {
+        let commit_hash = self
+            .highest_fast_path_commit_hash
+            .or(self.highest_commit_certificate_hash)?;
+        // Walk back from the highest commit to the block, then keep the shortest chain of QCs
+        // that commits it.
+        let mut qcs = Vec::new();
+        for qc in BackwardQuorumCertificateIterator::new(self, commit_hash) {
+            qcs.push(qc);
+            if qc.certified_block_hash == block_hash {
+                break;
+            }
+        }
+        if qcs.last()?.certified_block_hash != block_hash {
+            return None;
+        }
+        qcs.reverse();
+        let length = (1..=qcs.len()).find(|length| {
+            completes_commit_rule(
+                &qcs[..*length],
+                self.commit_rule,
+                self.fast_path,
+                &self.configuration,
+            )
+        })?;
+        let links = qcs[..length]
+            .iter()
+            .map(|qc| {
+                let block = self.block(qc.certified_block_hash).unwrap();
+                (block.clone(), (*qc).clone())
+            })
+            .collect();
+        Some(CommitProof {
+            epoch_id: self.epoch_id,
+            links,
+        })
+    }
+
     fn timeouts(&self) -> Vec<Timeout> {
         let mut timeouts = Vec::new();
         if let Some(highest_tc) = &self.highest_timeout_certificate {
@@ -708,11 +1820,26 @@ impl RecordStore for RecordStoreState {
     }
 
     fn block(&self, block_hash: BlockHash) -> Option<&Block> {
-        self.blocks.get(&block_hash)
+        self.blocks.get(&block_hash).map(|block| &**block)
     }
 
-    fn current_vote(&self, local_author: Author) -> Option<&Vote> {
-        self.current_votes.get(&local_author)
+    fn round_chain(&self, block_hash: BlockHash) -> Vec<&Block> {
+        let mut chain = Vec::new();
+        let mut current = Some(block_hash);
+        while let Some(hash) = current {
+            let block = self.block(hash).unwrap();
+            chain.push(block);
+            current = block.previous_block_hash;
+        }
+        chain.reverse();
+        chain
+    }
+
+    fn current_vote(&self, local_author: Author) -> Option<Vote> {
+        self.current_votes
+            .iter()
+            .filter_map(|votes| votes.vote(local_author))
+            .next()
     }
 
     fn known_quorum_certificate_rounds(&self) -> BTreeSet<Round> {
@@ -739,45 +1866,120 @@ impl RecordStore for RecordStoreState {
     }
 
     fn unknown_records(&self, known_qc_rounds: BTreeSet<Round>) -> Vec<Record> {
+        self.unknown_record_refs(known_qc_rounds)
+            .iter()
+            .map(RecordRef::to_record)
+            .collect()
+    }
+
+    fn unknown_record_refs(&self, known_qc_rounds: BTreeSet<Round>) -> Vec<RecordRef> {
         let highest_qc_hash = self.highest_quorum_certificate_hash;
         let highest_cc_hash = self
             .highest_commit_certificate_hash
             .unwrap_or(self.initial_hash);
-        let chain1: Vec<_> = BackwardQuorumCertificateIterator::new(self, highest_qc_hash)
-            .take_while(|qc| !known_qc_rounds.contains(&qc.round))
-            .collect();
-        let chain2: Vec<_> = BackwardQuorumCertificateIterator::new(self, highest_cc_hash)
-            .take_while(|qc| !known_qc_rounds.contains(&qc.round))
-            .collect();
-        let qcs = merge_sort(chain1.into_iter(), chain2.into_iter(), |qc1, qc2| {
-            qc2.round.cmp(&qc1.round)
-        });
+        let unknown_chain = |qc_hash| {
+            let mut iterator = BackwardQuorumCertificateIterator::new(self, qc_hash);
+            std::iter::from_fn(move || iterator.next_with_hash())
+                .take_while(|(_, qc)| !known_qc_rounds.contains(&qc.round))
+                .collect::<Vec<_>>()
+        };
+        let chain1 = unknown_chain(highest_qc_hash);
+        let chain2 = unknown_chain(highest_cc_hash);
+        let qcs = merge_sort(
+            chain1.into_iter(),
+            chain2.into_iter(),
+            |(_, qc1), (_, qc2)| qc2.round.cmp(&qc1.round),
+        );
         let mut result = Vec::new();
         for n in (0..qcs.len()).rev() {
-            let qc = qcs[n];
-            let block = self.block(qc.certified_block_hash).unwrap();
-            result.push(Record::Block(block.clone()));
-            result.push(Record::QuorumCertificate(qc.clone()));
+            let (qc_hash, qc) = qcs[n];
+            result.extend(self.round_chain_refs(qc.certified_block_hash));
+            result.push(RecordRef::QuorumCertificate(
+                qc_hash,
+                self.shared_quorum_certificate(qc_hash).unwrap(),
+            ));
         }
-        // Copying timeouts again.
-        for timeout in self.timeouts() {
-            result.push(Record::Timeout(timeout.clone()));
+        // Sending timeouts again.
+        if let Some(highest_tc) = &self.highest_timeout_certificate {
+            result.extend(highest_tc.iter().map(RecordRef::Timeout));
         }
+        result.extend(self.current_timeouts.values().map(RecordRef::Timeout));
         // Skipping votes intentionally.
-        if let Some(block_hash) = &self.current_proposed_block {
-            result.push(Record::Block(self.block(*block_hash).unwrap().clone()));
+        if let Some(block_hash) = self.current_proposed_block {
+            result.extend(self.round_chain_refs(block_hash));
         }
         result
     }
 
-    fn insert_network_record(&mut self, record: Record, smr_context: &mut SMRContext) {
-        debug!("Inserting {:?}", record);
-        match self.try_insert_network_record(record, smr_context) {
-            Err(err) => {
-                debug!("=> Skipped: {}", err);
+    fn insert_network_record(
+        &mut self,
+        record: Record,
+        smr_context: &mut SMRContext,
+    ) -> Result<()> {
+        tracing::debug!("Inserting {:?}", record);
+        if self.is_redundant_record(&record) {
+            tracing::debug!("=> Skipped: redundant record");
+            return Ok(());
+        }
+        // As in batches, only the records that pass the cheap checks are hashed and checked.
+        if let Err(err) = self.check_record_fields(&record) {
+            tracing::debug!("=> Skipped: {}", err);
+            return Ok(());
+        }
+        Self::charge_verification(&record, smr_context);
+        self.insert_unchecked_record(record, None, smr_context)
+    }
+
+    fn insert_network_records(
+        &mut self,
+        records: Vec<Record>,
+        smr_context: &mut SMRContext,
+    ) -> Result<()> {
+        // Records are independent as far as their own fields and signatures are concerned, so
+        // we check them all first, cheapest checks first, then apply the records in order.
+        // Records that are redundant with the store or fail the cheap checks are not hashed,
+        // whereas records made redundant by earlier ones are skipped on insertion.
+        let selected: Vec<_> = records
+            .iter()
+            .map(|record| {
+                if self.is_redundant_record(record) {
+                    return false;
+                }
+                match self.check_record_fields(record) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        tracing::debug!("Skipping {:?}: {}", record, err);
+                        false
+                    }
+                }
+            })
+            .collect();
+        for (record, selected) in records.iter().zip(&selected) {
+            if *selected {
+                Self::charge_verification(record, smr_context);
             }
-            Ok(()) => (),
-        };
-        // TODO: discard unneeded records from self.blocks and self.quorum_certificates
+        }
+        let checks = Self::hash_and_check_records(&records, &selected);
+        // Keep inserting records after an error, as with separate insertions.
+        let mut result = Ok(());
+        for (record, check) in records.into_iter().zip(checks) {
+            tracing::debug!("Inserting {:?}", record);
+            let hash = match check {
+                None => continue,
+                Some((_, Err(err))) => {
+                    tracing::debug!("=> Skipped: {}", err);
+                    continue;
+                }
+                Some((hash, Ok(()))) => hash,
+            };
+            if self.is_redundant_record(&record) {
+                tracing::debug!("=> Skipped: redundant record");
+                continue;
+            }
+            if let Err(err) = self.insert_unchecked_record(record, Some(hash), smr_context) {
+                result = Err(err);
+            }
+        }
+        result
     }
 }
@@ -0,0 +1,215 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Differences between the record stores of two nodes, e.g. to find out why one node is stuck
+//! while the others make progress.
+//!
+//! Records are compared by hash, so two stores of the same epoch agree on a record exactly when
+//! they both hold it. Stores of different epochs share no records.
+
+use super::*;
+use std::{collections::BTreeSet, fmt};
+
+#[cfg(test)]
+#[path = "unit_tests/record_store_diff_tests.rs"]
+mod record_store_diff_tests;
+
+/// Records of one store that the other store is missing, in increasing order of round.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct MissingRecords {
+    pub blocks: Vec<(Round, BlockHash)>,
+    pub quorum_certificates: Vec<(Round, QuorumCertificateHash)>,
+    /// Rounds of the TCs.
+    pub timeout_certificates: Vec<Round>,
+}
+
+/// Comparison of a left and a right record store.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct RecordStoreDiff {
+    pub epochs: (EpochId, EpochId),
+    /// Records of the right store that the left store is missing.
+    pub missing_left: MissingRecords,
+    /// Records of the left store that the right store is missing.
+    pub missing_right: MissingRecords,
+    /// Round of the highest QC on the chains of both highest QCs, or 0 if there is none.
+    pub common_round: Round,
+    /// Rounds of the QCs of each chain above `common_round`, in increasing order. One side is
+    /// empty when its chain is a prefix of the other.
+    pub branches: (Vec<Round>, Vec<Round>),
+    pub committed_rounds: (Round, Round),
+    /// Whether the committed blocks of one store are a prefix of the committed blocks of the
+    /// other, or `None` for stores of different epochs.
+    pub committed_prefixes_agree: Option<bool>,
+}
+
+impl MissingRecords {
+    /// Records of `store` that `other` does not hold.
+    fn new(store: &RecordStoreState, other: &RecordStoreState) -> Self {
+        let other_blocks: BTreeSet<_> = other
+            .verified_blocks()
+            .into_iter()
+            .map(|(hash, _)| hash)
+            .collect();
+        let other_qcs: BTreeSet<_> = other
+            .verified_quorum_certificates()
+            .into_iter()
+            .map(|(hash, _)| hash)
+            .collect();
+        let other_tcs: BTreeSet<_> = other.timeout_certificate_rounds().iter().collect();
+        MissingRecords {
+            blocks: store
+                .verified_blocks()
+                .into_iter()
+                .filter(|(hash, _)| !other_blocks.contains(hash))
+                .map(|(hash, block)| (block.round, hash))
+                .collect(),
+            quorum_certificates: store
+                .verified_quorum_certificates()
+                .into_iter()
+                .filter(|(hash, _)| !other_qcs.contains(hash))
+                .map(|(hash, qc)| (qc.round, hash))
+                .collect(),
+            timeout_certificates: store
+                .timeout_certificate_rounds()
+                .iter()
+                .filter(|round| !other_tcs.contains(round))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+            && self.quorum_certificates.is_empty()
+            && self.timeout_certificates.is_empty()
+    }
+}
+
+impl RecordStoreDiff {
+    pub fn new(left: &RecordStoreState, right: &RecordStoreState) -> Self {
+        let epochs = (left.epoch_id(), right.epoch_id());
+        let (common_round, branches) = if epochs.0 == epochs.1 {
+            Self::branches(left, right)
+        } else {
+            (Round(0), (Vec::new(), Vec::new()))
+        };
+        let committed = |store: &RecordStoreState| -> Vec<(Round, BlockHash)> {
+            store
+                .committed_states_after(Round(0))
+                .into_iter()
+                .map(|(round, _, _, _, hash)| (round, hash))
+                .collect()
+        };
+        let committed_prefixes_agree = if epochs.0 == epochs.1 {
+            let (left_commits, right_commits) = (committed(left), committed(right));
+            let length = std::cmp::min(left_commits.len(), right_commits.len());
+            Some(left_commits[..length] == right_commits[..length])
+        } else {
+            None
+        };
+        RecordStoreDiff {
+            epochs,
+            missing_left: MissingRecords::new(right, left),
+            missing_right: MissingRecords::new(left, right),
+            common_round,
+            branches,
+            committed_rounds: (
+                left.highest_committed_round(),
+                right.highest_committed_round(),
+            ),
+            committed_prefixes_agree,
+        }
+    }
+
+    /// Compare the record stores of the current epochs of two nodes.
+    pub fn between_nodes(left: &NodeState, right: &NodeState) -> Self {
+        Self::new(left.record_store_state(), right.record_store_state())
+    }
+
+    /// The highest common QC round of the chains of the highest QCs of two stores of the same
+    /// epoch, and the rounds of each chain above it.
+    fn branches(
+        left: &RecordStoreState,
+        right: &RecordStoreState,
+    ) -> (Round, (Vec<Round>, Vec<Round>)) {
+        // QCs of the same chain certify the same blocks.
+        let chain = |store: &RecordStoreState| -> Vec<(Round, BlockHash)> {
+            store
+                .quorum_certificate_chain(store.highest_quorum_certificate_hash())
+                .into_iter()
+                .map(|qc| (qc.round, qc.certified_block_hash))
+                .collect()
+        };
+        let (left_chain, right_chain) = (chain(left), chain(right));
+        let right_blocks: BTreeSet<_> = right_chain.iter().collect();
+        let common_round = left_chain
+            .iter()
+            .find(|x| right_blocks.contains(x))
+            .map_or(Round(0), |(round, _)| *round);
+        let above = |chain: Vec<(Round, BlockHash)>| -> Vec<Round> {
+            let mut rounds: Vec<_> = chain
+                .into_iter()
+                .map(|(round, _)| round)
+                .take_while(|round| *round > common_round)
+                .collect();
+            rounds.reverse();
+            rounds
+        };
+        (common_round, (above(left_chain), above(right_chain)))
+    }
+
+    /// Whether the stores hold the same records.
+    pub fn is_identical(&self) -> bool {
+        self.epochs.0 == self.epochs.1
+            && self.missing_left.is_empty()
+            && self.missing_right.is_empty()
+    }
+
+    /// Whether both highest QCs extend QCs that the other chain does not contain.
+    pub fn branches_diverge(&self) -> bool {
+        !self.branches.0.is_empty() && !self.branches.1.is_empty()
+    }
+}
+
+fn rounds<'a, I: Iterator<Item = &'a Round>>(rounds: I) -> Vec<usize> {
+    rounds.map(|round| round.0).collect()
+}
+
+impl fmt::Display for MissingRecords {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "blocks at rounds {:?}, QCs at rounds {:?}, TCs at rounds {:?}",
+            rounds(self.blocks.iter().map(|(round, _)| round)),
+            rounds(self.quorum_certificates.iter().map(|(round, _)| round)),
+            rounds(self.timeout_certificates.iter())
+        )
+    }
+}
+
+impl fmt::Display for RecordStoreDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Epochs: {} and {}", (self.epochs.0).0, (self.epochs.1).0)?;
+        writeln!(f, "Missing on the left: {}", self.missing_left)?;
+        writeln!(f, "Missing on the right: {}", self.missing_right)?;
+        writeln!(
+            f,
+            "Chains after the QC of round {}: {:?} and {:?}",
+            self.common_round.0,
+            rounds(self.branches.0.iter()),
+            rounds(self.branches.1.iter())
+        )?;
+        let agreement = match self.committed_prefixes_agree {
+            Some(true) => "agree",
+            Some(false) => "disagree",
+            None => "cannot be compared",
+        };
+        write!(
+            f,
+            "Committed rounds: {} and {}, whose prefixes {}",
+            (self.committed_rounds.0).0,
+            (self.committed_rounds.1).0,
+            agreement
+        )
+    }
+}
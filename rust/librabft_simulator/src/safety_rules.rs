@@ -0,0 +1,92 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Voting constraints of a node, consulted before signing votes and timeouts.
+//!
+//! A node never votes twice at the same round, nor at a round for which it created a timeout,
+//! and only votes for blocks that extend a QC at least as recent as its locked round. The
+//! constraints are persisted before any vote or timeout that depends on them leaves the node,
+//! so that they survive restarts.
+
+use super::*;
+use smr_context::SafetyStorage;
+
+#[cfg(test)]
+#[path = "unit_tests/safety_rules_tests.rs"]
+mod safety_rules_tests;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct SafetyRules {
+    data: SafetyData,
+}
+
+impl SafetyRules {
+    /// Rules of a node that has not voted yet in the epoch `epoch_id`.
+    pub fn new(epoch_id: EpochId) -> Self {
+        SafetyRules {
+            data: SafetyData {
+                epoch_id,
+                latest_voted_round: Round(0),
+                locked_round: Round(0),
+            },
+        }
+    }
+
+    /// Rules persisted in `storage` for the epoch `epoch_id`, if any, or the rules of a node
+    /// that has not voted yet.
+    pub fn load<S: SafetyStorage + ?Sized>(epoch_id: EpochId, storage: &S) -> Self {
+        match storage.load_safety_data() {
+            Some(data) if data.epoch_id == epoch_id => SafetyRules { data },
+            _ => SafetyRules::new(epoch_id),
+        }
+    }
+
+    /// Persist the rules in `storage`.
+    pub fn save<S: SafetyStorage + ?Sized>(&self, storage: &mut S) {
+        storage.save_safety_data(self.data);
+    }
+
+    pub fn data(&self) -> SafetyData {
+        self.data
+    }
+
+    /// Highest round voted so far, or for which we created a timeout.
+    pub fn latest_voted_round(&self) -> Round {
+        self.data.latest_voted_round
+    }
+
+    pub fn locked_round(&self) -> Round {
+        self.data.locked_round
+    }
+
+    /// Whether we may vote for a block at `round` that extends a QC at `previous_round`.
+    pub fn can_vote(&self, round: Round, previous_round: Round) -> bool {
+        round > self.data.latest_voted_round && previous_round >= self.data.locked_round
+    }
+
+    /// Record and persist a vote for a block at `round` that extends a QC at `previous_round`,
+    /// and raise the locked round to `lock`, before the vote is signed. Return false, and
+    /// change nothing, if the rules forbid the vote.
+    pub fn record_vote<S: SafetyStorage + ?Sized>(
+        &mut self,
+        round: Round,
+        previous_round: Round,
+        lock: Round,
+        storage: &mut S,
+    ) -> bool {
+        if !self.can_vote(round, previous_round) {
+            return false;
+        }
+        self.data.latest_voted_round = round;
+        self.data.locked_round.max_update(lock);
+        self.save(storage);
+        true
+    }
+
+    /// Record and persist a timeout at `round`, before it is signed, so that we never vote at
+    /// this round afterwards.
+    pub fn record_timeout<S: SafetyStorage + ?Sized>(&mut self, round: Round, storage: &mut S) {
+        self.data.latest_voted_round.max_update(round);
+        self.save(storage);
+    }
+}
@@ -0,0 +1,1345 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Declarative description of an experiment, loaded from a TOML file.
+//!
+//! All sections and fields are optional and default to the default values of the command line.
+//! Example:
+//! ```toml
+//! seed = 42
+//! nodes = 4
+//! # Node 1 is counted as Byzantine in the chain quality.
+//! byzantine = [1]
+//! # Commit blocks with two consecutive certified rounds instead of three.
+//! commit_rule = "two-chain"
+//! # Send votes to the leader of the next round, who assembles the QC.
+//! vote_delivery = "next-leader"
+//! # Wait up to 5 units of time for the votes of all validators, which commit a block at once.
+//! fast_path_wait = 5
+//! # Aggregate the votes of each QC into a single signature.
+//! aggregate_signatures = true
+//! # Alternatively, combine the votes into a threshold signature, with keys dealt at each epoch.
+//! # threshold_signatures = true
+//! # Keep at most 8 blocks without a QC per author, evicting the oldest ones.
+//! max_uncertified_blocks = 8
+//! # Check the lemmas of the LibraBFT paper after every commit, not only at the end of the run.
+//! check_lemmas = true
+//! # Do not send records to the nodes whose notifications show that they hold them.
+//! suppress_redundant_records = true
+//! # Check all the records of a message before processing any, and reject damaged messages.
+//! strict_verification = true
+//! # Let lagging nodes resume from a checkpoint of the executed state of a peer.
+//! state_sync = true
+//! # Authenticate each copy of a message with a MAC, as on point-to-point channels.
+//! message_authentication = "mac"
+//!
+//! [network]
+//! mean = 10.0
+//! loss_rate = 0.01
+//!
+//! # Flip a random byte of 1% of the messages in transit, and cut 1% of them short.
+//! [corruption]
+//! flip_rate = 0.01
+//! truncate_rate = 0.01
+//!
+//! # Nodes accept at most 50 messages from each sender every 100 units of time.
+//! [quota]
+//! period = 100
+//! max_messages = 50
+//!
+//! # Replay an incident: delays grow from time 500 to 600, losses rise in 4 steps from time
+//! # 700 to 800, then the network recovers at time 900.
+//! [[network.change]]
+//! time = 500
+//! mean = 50.0
+//! ramp = 100
+//!
+//! [[network.change]]
+//! time = 700
+//! loss_rate = 0.3
+//! ramp = 100
+//! steps = 4
+//!
+//! [[network.change]]
+//! time = 900
+//! mean = 10.0
+//! loss_rate = 0.01
+//!
+//! [pacemaker]
+//! delta = 20
+//! # Lengthen rounds by up to 10% at random, so that nodes do not time out together.
+//! jitter = 0.1
+//! # Broadcast proposals and timeouts again after 30 units of time, then every 60, 120, ...
+//! retransmission_period = 30
+//! retransmission_backoff = 2.0
+//! # When a round starts, nodes send their records to 2 random validators instead of the leader.
+//! peer_selection = { kind = "random", count = 2 }
+//! # Skip the validators not heard from for 200 units of time when synchronizing.
+//! failure_detector = { kind = "heartbeat", timeout = 200 }
+//!
+//! # Switch to rounds of 100 units of time while half of the latest 10 rounds time out, and back
+//! # once fewer than 10% of them do.
+//! [pacemaker.hybrid]
+//! conservative_delta = 100
+//! window = 10
+//! max_timeout_rate = 0.5
+//! min_timeout_rate = 0.1
+//!
+//! # Push new records to 2 random peers instead of broadcasting them, and pull the missing ones
+//! # from a random peer every 50 units of time. The fanout adapts to the rate of duplicates.
+//! [push_pull]
+//! initial_fanout = 2
+//! pull_period = 50
+//!
+//! # Signing takes 1 unit of time and verifying a signature 2 units.
+//! [crypto]
+//! sign = 1
+//! verify = 2
+//!
+//! # Node 3 uses longer rounds and has two votes. It only supports the first version of the
+//! # data sync protocol, which the others use when they synchronize with it, and it executes
+//! # the blocks of the epochs that it missed.
+//! [[node]]
+//! author = 3
+//! delta = 40
+//! stake = 2
+//! data_sync_version = 1
+//! state_sync = false
+//!
+//! # Node 1 proposes payloads that fail validation, which the other nodes refuse to vote for.
+//! [[node]]
+//! author = 1
+//! invalid_payloads = true
+//!
+//! # Node 2 runs on a processor half as fast as the others, so it takes twice as long to
+//! # execute blocks and check signatures. Its records are lost when it restarts, but not its
+//! # voting constraints.
+//! [[node]]
+//! author = 2
+//! cpu_speed = 0.5
+//! durable_records = false
+//!
+//! [workload]
+//! commands_per_epoch = 100
+//! max_batch_size = 50
+//! # Blocks carry at most 4000 bytes of transactions of 100 bytes.
+//! transaction_size = 100
+//! max_block_payload = 4000
+//! # Leaders wait up to 15 units of time to fill a block before proposing.
+//! max_batch_wait = 15
+//! # Without transactions, leaders wait up to 20 more units of time before proposing an empty
+//! # block.
+//! empty_blocks = { kind = "delay", max_delay = 20 }
+//! # Leaders propose chains of 2 blocks per round, voted on through their last block.
+//! blocks_per_round = 2
+//! # Nodes take 5 units of time to execute a block, and vote on it before executing it.
+//! execution_latency = 5
+//! pipelined_execution = true
+//!
+//! # Each node receives 0.2 transactions per unit of time.
+//! [workload.arrivals]
+//! kind = "poisson"
+//! rate = 0.2
+//!
+//! # Clients send 0.01 targeted transactions per unit of time to all the nodes.
+//! [workload.targeted_arrivals]
+//! kind = "poisson"
+//! rate = 0.01
+//!
+//! # Executing a block takes 2 units of time, plus 1 per transaction, in the key-value
+//! # backend. Set `backend = "null"` in `[workload]` instead to only measure consensus.
+//! [workload.execution]
+//! num_keys = 500
+//! block_cost = 2
+//! transaction_cost = 1
+//!
+//! # From time 700 on, blocks propose to lengthen rounds and to halve their payload. The change
+//! # takes effect at the start of the epoch after its commit.
+//! [[parameter_change]]
+//! time = 700
+//! delta = 30
+//! max_block_payload = 2000
+//!
+//! # From the second epoch on, node 2 is no longer a validator.
+//! [[epoch]]
+//! validators = [0, 1, 2, 3]
+//!
+//! [[epoch]]
+//! validators = [0, 1, 3]
+//!
+//! # Node 2 crashes at time 300.
+//! [[fault]]
+//! author = 2
+//! crash_time = 300
+//!
+//! # Node 1 crashes at time 100 and restarts at time 200, keeping its voting constraints.
+//! [[fault]]
+//! author = 1
+//! crash_time = 100
+//! recovery_time = 200
+//!
+//! # Nodes 0 and 1 share a rack, which loses power at time 600 and is back at time 800.
+//! [[domain]]
+//! name = "rack-a"
+//! nodes = [0, 1]
+//!
+//! [[domain_fault]]
+//! domain = "rack-a"
+//! kind = "crash"
+//! time = 600
+//! end_time = 800
+//!
+//! # The same nodes are cut off from the others between times 1200 and 1400.
+//! [[domain_fault]]
+//! domain = "rack-a"
+//! kind = "partition"
+//! time = 1200
+//! end_time = 1400
+//!
+//! # An adversary controls the links of node 3 between times 1600 and 1800. It delays the
+//! # messages with the other nodes by 50 units of time and drops half of them, except for the
+//! # messages with node 1.
+//! [[eclipse]]
+//! victim = 3
+//! time = 1600
+//! end_time = 1800
+//! delay = 50
+//! drop_rate = 0.5
+//! relayed = [1]
+//!
+//! # Node 1 sends 5 copies of its notification to every other node every 10 units of time,
+//! # between times 1900 and 2000.
+//! [[flood]]
+//! attacker = 1
+//! time = 1900
+//! end_time = 2000
+//! period = 10
+//! notifications = 5
+//!
+//! [termination]
+//! max_clock = 2000
+//! max_commits = 50
+//! # Dump the state of the nodes and the dropped messages after 500 units without commits.
+//! stall_threshold = 500
+//!
+//! # Leave the first 200 units of time out of the statistics. A bounded window would also end
+//! # the run in place of `max_clock`.
+//! [measurement]
+//! warm_up = 200
+//! ```
+//!
+//! Instead of `[[epoch]]` sections, validators may change at given times:
+//! ```toml
+//! nodes = 5
+//!
+//! # Node 4 joins at time 400 and replaces node 3 as a validator.
+//! [[reconfiguration]]
+//! time = 400
+//! add = [4]
+//! remove = [3]
+//! ```
+//!
+//! Observers follow the committed chain like full nodes, without being validators:
+//! ```toml
+//! nodes = 5
+//! # Node 4 receives records and commits blocks, but never votes or proposes.
+//! observers = [4]
+//! ```
+//!
+//! Simulated clients measure the latency of their requests end to end:
+//! ```toml
+//! nodes = 4
+//! # 20 clients send each request to 2 nodes and wait for the acknowledgment of its commit.
+//! # They wait 10 units of time before their next request, and send a request again to the
+//! # next 2 nodes after 500 units of time without acknowledgment. Nodes forward each new
+//! # request to 2 other nodes after 5 units of time.
+//! [clients]
+//! clients = 20
+//! replicas = 2
+//! think_time = 10
+//! timeout = 500
+//! gossip_fanout = 2
+//! gossip_delay = 5
+//! ```
+
+use super::*;
+use bft_simulator_runtime::{
+    clients::ClientModel,
+    corruption::CorruptionModel,
+    nemesis,
+    network_trace::{NetworkChange, NetworkTrace},
+    quota::MessageQuota,
+    simulator::GlobalTime,
+    statistics::MeasurementPhases,
+    workload::ArrivalProcess,
+};
+use key_value_store::ExecutionModel;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+#[cfg(test)]
+#[path = "unit_tests/scenario_tests.rs"]
+mod scenario_tests;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    /// Seed of the random network delays and losses, at most `MAX_SEED`. Chosen randomly if
+    /// absent.
+    pub seed: Option<u64>,
+    pub nodes: usize,
+    /// Nodes counted as Byzantine in the chain quality. Values must precede tables in TOML.
+    pub byzantine: Vec<usize>,
+    /// Nodes that receive records and track the committed chain, but never vote, propose, or
+    /// count toward quorums. Values must precede tables in TOML.
+    pub observers: Vec<usize>,
+    /// Rule used to detect commits. Values must precede tables in TOML.
+    pub commit_rule: CommitRule,
+    /// Recipients of the votes. Values must precede tables in TOML.
+    pub vote_delivery: VoteDelivery,
+    /// How long QC assemblers wait for the votes of all validators, if at all. Values must
+    /// precede tables in TOML.
+    pub fast_path_wait: Option<Duration>,
+    /// Whether the votes of QCs are aggregated. Values must precede tables in TOML.
+    pub aggregate_signatures: bool,
+    /// Whether the votes of QCs are combined into threshold signatures. Values must precede
+    /// tables in TOML.
+    pub threshold_signatures: bool,
+    /// Maximal number of blocks without a QC that nodes keep per author, if bounded. Values
+    /// must precede tables in TOML.
+    pub max_uncertified_blocks: Option<usize>,
+    /// Whether the lemmas of the LibraBFT paper are checked after every commit. Values must
+    /// precede tables in TOML.
+    pub check_lemmas: bool,
+    /// Latest version of the data sync protocol supported by the nodes without overrides.
+    /// Values must precede tables in TOML.
+    pub data_sync_version: u32,
+    /// Whether nodes avoid sending records to the nodes known to hold them. Values must
+    /// precede tables in TOML.
+    pub suppress_redundant_records: bool,
+    /// Whether nodes check all the records of a message before processing any of them. Values
+    /// must precede tables in TOML.
+    pub strict_verification: bool,
+    /// Whether lagging nodes resume from checkpoints of the executed state instead of executing
+    /// the blocks of each missed epoch. Values must precede tables in TOML.
+    pub state_sync: bool,
+    /// Authentication of the network messages: "none", "mac", or "signature". Values must
+    /// precede tables in TOML.
+    pub message_authentication: MessageAuthentication,
+    pub network: NetworkModel,
+    /// Damage of the messages in transit.
+    pub corruption: CorruptionModel,
+    /// Messages that nodes accept from each sender per period, e.g.
+    /// `{ period = 100, max_messages = 50, max_bytes = 100000 }`. No limits by default.
+    pub quota: MessageQuota,
+    pub pacemaker: PacemakerParameters,
+    /// Push/pull dissemination, e.g. `{ initial_fanout = 2, pull_period = 50 }`. Nodes
+    /// broadcast their records if absent.
+    pub push_pull: Option<PushPullPolicy>,
+    /// Time charged to nodes per cryptographic operation, e.g. `{ sign = 1, verify = 2 }`.
+    pub crypto: CryptoCosts,
+    /// Parameters of specific nodes.
+    pub node: Vec<NodeOverrides>,
+    pub workload: Workload,
+    /// Simulated clients sending requests to the nodes, e.g. `{ clients = 20, think_time = 10 }`.
+    pub clients: Option<ClientModel>,
+    /// Validators of successive epochs. The last entry applies to later epochs.
+    pub epoch: Vec<EpochValidators>,
+    pub fault: Vec<Fault>,
+    /// Groups of nodes that fail together, e.g. racks or regions.
+    pub domain: Vec<FailureDomain>,
+    /// Faults of whole failure domains.
+    pub domain_fault: Vec<DomainFault>,
+    /// Nodes whose links are controlled by an adversary for some time.
+    pub eclipse: Vec<EclipseAttack>,
+    /// Nodes sending extra messages to all the others for some time.
+    pub flood: Vec<FloodAttack>,
+    /// Changes of the validators at given times. Cannot be combined with `epoch`.
+    pub reconfiguration: Vec<Reconfiguration>,
+    /// Governance changes of the protocol parameters, proposed on chain at given times.
+    pub parameter_change: Vec<ScheduledParameterChange>,
+    pub termination: Termination,
+    /// Warm-up left out of the statistics and length of the measurement window, e.g.
+    /// `{ warm_up = 500, window = 2000 }`.
+    pub measurement: MeasurementPhases,
+}
+
+/// Random network delays (log-normal distribution) and losses.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkModel {
+    pub mean: f64,
+    pub variance: f64,
+    pub loss_rate: f64,
+    /// Changes of the delays and losses at given times, e.g. to replay a network incident.
+    pub change: Vec<NetworkChange>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PacemakerParameters {
+    pub delta: Duration,
+    pub gamma: f64,
+    pub lambda: f64,
+    /// Largest fraction of a round duration added at random to the rounds of each node.
+    pub jitter: f64,
+    /// Time before nodes broadcast their proposal or timeout of a round again, if at all.
+    pub retransmission_period: Option<Duration>,
+    /// Factor applied to the time between retransmissions after each of them.
+    pub retransmission_backoff: f64,
+    /// When to switch to longer rounds, e.g. `{ conservative_delta = 100 }`. Round durations
+    /// do not change if absent.
+    pub hybrid: Option<HybridPolicy>,
+    /// Nodes that nodes send their records to when a round starts, e.g. `{ kind = "leader" }`,
+    /// `{ kind = "random", count = 2 }`, `{ kind = "leader-region", regions = [0, 0, 1, 1] }`,
+    /// or `{ kind = "relays", relays = [0, 1] }`.
+    pub peer_selection: PeerSelection,
+    /// How nodes suspect the peers that they skip when synchronizing, e.g.
+    /// `{ kind = "heartbeat", timeout = 200 }` or
+    /// `{ kind = "phi-accrual", threshold = 3.0, window = 10, min_interval = 10 }`.
+    pub failure_detector: Option<FailureDetectorPolicy>,
+}
+
+/// Pacemaker parameters, stake, protocol version, processor speed, and verification and
+/// synchronization settings of a single node. Missing values are taken from `[pacemaker]` and
+/// the top-level values.
+#[derive(PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeOverrides {
+    pub author: usize,
+    pub delta: Option<Duration>,
+    pub gamma: Option<f64>,
+    pub lambda: Option<f64>,
+    /// Voting rights of the node when it is a validator. 1 by default.
+    pub stake: Option<usize>,
+    /// Latest version of the data sync protocol supported by the node.
+    pub data_sync_version: Option<u32>,
+    /// Whether the node checks all the records of a message before processing any of them.
+    pub strict_verification: Option<bool>,
+    /// Whether the node resumes from checkpoints of the executed state when it lags behind.
+    pub state_sync: Option<bool>,
+    /// Whether the voting constraints of the node survive its restarts. True by default.
+    pub durable_safety_data: Option<bool>,
+    /// Whether the records of the current epoch survive the restarts of the node, which
+    /// otherwise fetches them again from its peers. True by default.
+    pub durable_records: Option<bool>,
+    /// Whether the node proposes payloads that fail validation, as a Byzantine leader. Such
+    /// nodes must be listed in `byzantine`.
+    #[serde(default)]
+    pub invalid_payloads: bool,
+    /// Speed of the processor of the node, which divides the time that it spends executing
+    /// blocks and computing signatures. 1 by default.
+    pub cpu_speed: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Workload {
+    pub commands_per_epoch: usize,
+    pub target_commit_interval: Duration,
+    /// Maximal number of transactions per block.
+    pub max_batch_size: usize,
+    /// Size of each transaction, in bytes.
+    pub transaction_size: usize,
+    /// Maximal size of the transactions of a block, in bytes.
+    pub max_block_payload: Option<usize>,
+    /// How long leaders may wait for a full block before proposing. Leaders propose
+    /// immediately if absent.
+    pub max_batch_wait: Option<Duration>,
+    /// What leaders propose without transactions: `{ kind = "propose" }` by default,
+    /// `{ kind = "delay", max_delay = 20 }`, or `{ kind = "no-op" }`.
+    pub empty_blocks: EmptyBlockPolicy,
+    /// Number of chained blocks that leaders propose in each round.
+    pub blocks_per_round: usize,
+    /// Time for a node to execute a block before voting on it.
+    pub execution_latency: Duration,
+    /// Vote before executing blocks, and wait for execution before delivering commits.
+    pub pipelined_execution: bool,
+    /// Arrivals of transactions at each node, e.g. `{ kind = "constant-rate", rate = 0.5 }`,
+    /// `{ kind = "poisson", rate = 0.5 }`, or `{ kind = "bursty", size = 20, interval = 100 }`.
+    /// Blocks are empty if absent.
+    pub arrivals: Option<ArrivalProcess>,
+    /// Arrivals of targeted transactions, submitted to all the nodes at once. Byzantine nodes
+    /// leave them out of their proposals.
+    pub targeted_arrivals: Option<ArrivalProcess>,
+    /// Execution of transactions in a key-value store, e.g.
+    /// `{ num_keys = 1000, block_cost = 1, transaction_cost = 1 }`. Free if absent.
+    pub execution: Option<ExecutionModel>,
+    /// How nodes execute the committed commands: "ledger", "key-value", or "null". The
+    /// key-value backend is the default with `execution`, and the ledger otherwise.
+    pub backend: Option<ExecutionBackend>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EpochValidators {
+    pub validators: Vec<usize>,
+}
+
+/// A node that stops at a given time, and restarts later if `recovery_time` is given.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fault {
+    pub author: usize,
+    pub crash_time: GlobalTime,
+    #[serde(default)]
+    pub recovery_time: Option<GlobalTime>,
+}
+
+/// Nodes that fail together, e.g. the nodes of a rack or of a region.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FailureDomain {
+    pub name: String,
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DomainFaultKind {
+    /// All the nodes of the domain crash, and restart at `end_time` if given.
+    Crash,
+    /// The nodes of the domain cannot exchange messages with the other nodes, until `end_time`
+    /// if given. Replaces the previous partition, if any.
+    Partition,
+}
+
+/// A fault of all the nodes of a failure domain at once.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainFault {
+    pub domain: String,
+    pub kind: DomainFaultKind,
+    pub time: GlobalTime,
+    #[serde(default)]
+    pub end_time: Option<GlobalTime>,
+}
+
+/// An adversary controlling all the links of `victim` from `time` on, until `end_time` if given.
+/// See `nemesis::Eclipse`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EclipseAttack {
+    pub victim: usize,
+    pub time: GlobalTime,
+    pub end_time: Option<GlobalTime>,
+    pub delay: Duration,
+    /// Probability that a message between the victim and another node is dropped. 1 by default.
+    pub drop_rate: f64,
+    /// Nodes whose messages with the victim go through untouched.
+    pub relayed: Vec<usize>,
+}
+
+/// `attacker` flooding the other nodes from `time` on, until `end_time` if given. See
+/// `nemesis::Flood`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FloodAttack {
+    pub attacker: usize,
+    pub time: GlobalTime,
+    pub end_time: Option<GlobalTime>,
+    /// Time between bursts. 1 by default.
+    pub period: Duration,
+    /// Copies of its notification that the attacker sends to each node per burst. 1 by default.
+    pub notifications: usize,
+    /// Data sync requests that the attacker sends to each node per burst.
+    pub requests: usize,
+}
+
+/// A change of validators, starting a new epoch from the first block proposed at or after
+/// `time`. Nodes listed in `add` for the first time are brand-new: they join the network at
+/// `time` and catch up with the others through synchronization.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Reconfiguration {
+    pub time: GlobalTime,
+    pub add: Vec<usize>,
+    pub remove: Vec<usize>,
+}
+
+/// A change of protocol parameters, carried by the first block proposed at or after `time` and
+/// the following ones until committed. It takes effect at the start of the next epoch.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduledParameterChange {
+    pub time: GlobalTime,
+    pub delta: Option<Duration>,
+    pub gamma: Option<f64>,
+    pub lambda: Option<f64>,
+    /// Maximal size of the transactions of a block, in bytes.
+    pub max_block_payload: Option<usize>,
+}
+
+impl ScheduledParameterChange {
+    pub fn change(&self) -> ParameterChange {
+        ParameterChange {
+            delta: self.delta,
+            gamma: self.gamma,
+            lambda: self.lambda,
+            max_block_payload: self.max_block_payload,
+        }
+    }
+
+    pub fn check(&self, pacemaker: &PacemakerParameters) -> Result<()> {
+        ensure!(
+            self.time >= GlobalTime(0),
+            "Parameter change times cannot be negative"
+        );
+        ensure!(
+            !self.change().is_empty(),
+            "Parameter changes need at least one parameter"
+        );
+        check_pacemaker(
+            self.delta.unwrap_or(pacemaker.delta),
+            self.gamma.unwrap_or(pacemaker.gamma),
+            self.lambda.unwrap_or(pacemaker.lambda),
+        )?;
+        ensure!(
+            self.max_block_payload != Some(0),
+            "Block payload limits must be positive"
+        );
+        Ok(())
+    }
+}
+
+/// Validators over time, as described by the reconfigurations of a scenario.
+struct ValidatorSchedule {
+    initial: BTreeSet<Author>,
+    changes: Vec<(GlobalTime, BTreeSet<Author>)>,
+    join_times: BTreeMap<Author, GlobalTime>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Termination {
+    pub max_clock: GlobalTime,
+    pub max_commits: Option<usize>,
+    pub max_rounds_without_progress: Option<usize>,
+    /// Fail the run if no block is committed for this long while a quorum of honest nodes is
+    /// running.
+    pub max_time_without_commit: Option<Duration>,
+    /// Diagnose the stalls that last longer than this, without failing the run.
+    pub stall_threshold: Option<Duration>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            seed: None,
+            nodes: 3,
+            byzantine: Vec::new(),
+            observers: Vec::new(),
+            commit_rule: CommitRule::ThreeChain,
+            vote_delivery: VoteDelivery::Proposer,
+            fast_path_wait: None,
+            aggregate_signatures: false,
+            threshold_signatures: false,
+            max_uncertified_blocks: None,
+            check_lemmas: false,
+            data_sync_version: DATA_SYNC_VERSION,
+            suppress_redundant_records: false,
+            strict_verification: false,
+            state_sync: false,
+            message_authentication: MessageAuthentication::None,
+            network: NetworkModel::default(),
+            corruption: CorruptionModel::default(),
+            quota: MessageQuota::default(),
+            pacemaker: PacemakerParameters::default(),
+            push_pull: None,
+            crypto: CryptoCosts::default(),
+            node: Vec::new(),
+            workload: Workload::default(),
+            clients: None,
+            epoch: Vec::new(),
+            fault: Vec::new(),
+            domain: Vec::new(),
+            domain_fault: Vec::new(),
+            eclipse: Vec::new(),
+            flood: Vec::new(),
+            reconfiguration: Vec::new(),
+            parameter_change: Vec::new(),
+            termination: Termination::default(),
+            measurement: MeasurementPhases::default(),
+        }
+    }
+}
+
+impl Default for NetworkModel {
+    fn default() -> Self {
+        NetworkModel {
+            mean: 10.0,
+            variance: 4.0,
+            loss_rate: 0.0,
+            change: Vec::new(),
+        }
+    }
+}
+
+impl Default for PacemakerParameters {
+    fn default() -> Self {
+        PacemakerParameters {
+            delta: Duration::from_millis(20),
+            gamma: 2.0,
+            lambda: 0.5,
+            jitter: 0.0,
+            retransmission_period: None,
+            retransmission_backoff: 1.0,
+            hybrid: None,
+            peer_selection: PeerSelection::Leader,
+            failure_detector: None,
+        }
+    }
+}
+
+impl NetworkModel {
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.mean > 0.0
+                && self.mean.is_finite()
+                && self.variance >= 0.0
+                && self.variance.is_finite(),
+            "Network delays need a positive and finite mean, and a non-negative and finite variance"
+        );
+        ensure!(
+            self.loss_rate >= 0.0 && self.loss_rate <= 1.0,
+            "Loss rates must be between 0 and 1"
+        );
+        self.trace()?;
+        Ok(())
+    }
+
+    pub fn trace(&self) -> Result<NetworkTrace> {
+        NetworkTrace::new(self.change.clone())
+    }
+}
+
+fn check_seed(seed: Option<u64>) -> Result<()> {
+    ensure!(
+        seed.map_or(true, |seed| seed <= MAX_SEED),
+        "Seeds must be at most {}",
+        MAX_SEED
+    );
+    Ok(())
+}
+
+/// Check pacemaker parameters: rounds must last, and grow at least linearly after failed rounds.
+fn check_pacemaker(delta: Duration, gamma: f64, lambda: f64) -> Result<()> {
+    ensure!(
+        delta.is_positive(),
+        "Round durations (delta) must be positive"
+    );
+    ensure!(
+        gamma >= 1.0,
+        "Round durations must grow at least linearly (gamma >= 1)"
+    );
+    ensure!(lambda > 0.0, "Query-all periods (lambda) must be positive");
+    Ok(())
+}
+
+impl PacemakerParameters {
+    pub fn check(&self) -> Result<()> {
+        check_pacemaker(self.delta, self.gamma, self.lambda)?;
+        ensure!(
+            self.jitter >= 0.0 && self.jitter <= 1.0,
+            "Round jitters must be between 0 and 1"
+        );
+        ensure!(
+            self.retransmission_period.map_or(true, |x| x.is_positive()),
+            "Retransmission periods must be positive"
+        );
+        ensure!(
+            self.retransmission_backoff >= 1.0,
+            "Retransmissions cannot get more frequent (retransmission_backoff >= 1)"
+        );
+        if let Some(hybrid) = &self.hybrid {
+            hybrid.check(self.delta)?;
+        }
+        if let Some(failure_detector) = &self.failure_detector {
+            failure_detector.check()?;
+        }
+        Ok(())
+    }
+
+    pub fn retransmission(&self) -> Option<RetransmissionPolicy> {
+        self.retransmission_period
+            .map(|period| RetransmissionPolicy {
+                period,
+                backoff: self.retransmission_backoff,
+            })
+    }
+}
+
+/// Largest seed that TOML files can hold, as TOML integers are signed.
+pub const MAX_SEED: u64 = std::i64::MAX as u64;
+
+/// A random seed, small enough to be saved in scenario files.
+pub fn random_seed() -> u64 {
+    rand::random::<u64>() & MAX_SEED
+}
+
+/// Check that nodes support a known version of the data sync protocol.
+pub fn check_data_sync_version(version: u32) -> Result<()> {
+    ensure!(
+        LEGACY_DATA_SYNC_VERSION <= version && version <= DATA_SYNC_VERSION,
+        "Unknown data sync version {} (expected {} to {})",
+        version,
+        LEGACY_DATA_SYNC_VERSION,
+        DATA_SYNC_VERSION
+    );
+    Ok(())
+}
+
+impl NodeOverrides {
+    /// Check the overrides, with missing values taken from `pacemaker`.
+    pub fn check(&self, pacemaker: &PacemakerParameters) -> Result<()> {
+        check_pacemaker(
+            self.delta.unwrap_or(pacemaker.delta),
+            self.gamma.unwrap_or(pacemaker.gamma),
+            self.lambda.unwrap_or(pacemaker.lambda),
+        )?;
+        ensure!(self.stake != Some(0), "Stakes must be positive");
+        ensure!(
+            self.cpu_speed
+                .map_or(true, |speed| speed > 0.0 && speed.is_finite()),
+            "CPU speeds must be positive"
+        );
+        if let Some(version) = self.data_sync_version {
+            check_data_sync_version(version)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Workload {
+    fn default() -> Self {
+        Workload {
+            commands_per_epoch: 30000,
+            target_commit_interval: Duration::from_millis(100_000),
+            max_batch_size: 100,
+            transaction_size: 100,
+            max_block_payload: None,
+            max_batch_wait: None,
+            empty_blocks: EmptyBlockPolicy::Propose,
+            blocks_per_round: 1,
+            execution_latency: Duration::ZERO,
+            pipelined_execution: false,
+            arrivals: None,
+            targeted_arrivals: None,
+            execution: None,
+            backend: None,
+        }
+    }
+}
+
+impl Default for EclipseAttack {
+    fn default() -> Self {
+        let eclipse = nemesis::Eclipse::default();
+        EclipseAttack {
+            victim: 0,
+            time: GlobalTime(0),
+            end_time: None,
+            delay: eclipse.delay,
+            drop_rate: eclipse.drop_rate,
+            relayed: Vec::new(),
+        }
+    }
+}
+
+impl EclipseAttack {
+    fn eclipse(&self) -> nemesis::Eclipse {
+        nemesis::Eclipse {
+            delay: self.delay,
+            drop_rate: self.drop_rate,
+            relayed: self.relayed.iter().map(|x| Author(*x)).collect(),
+        }
+    }
+}
+
+impl Default for FloodAttack {
+    fn default() -> Self {
+        let flood = nemesis::Flood::default();
+        FloodAttack {
+            attacker: 0,
+            time: GlobalTime(0),
+            end_time: None,
+            period: flood.period,
+            notifications: flood.notifications,
+            requests: flood.requests,
+        }
+    }
+}
+
+impl FloodAttack {
+    fn flood(&self) -> nemesis::Flood {
+        nemesis::Flood {
+            period: self.period,
+            notifications: self.notifications,
+            requests: self.requests,
+        }
+    }
+}
+
+impl Default for Reconfiguration {
+    fn default() -> Self {
+        Reconfiguration {
+            time: GlobalTime(0),
+            add: Vec::new(),
+            remove: Vec::new(),
+        }
+    }
+}
+
+impl Default for Termination {
+    fn default() -> Self {
+        Termination {
+            max_clock: GlobalTime(1000),
+            max_commits: None,
+            max_rounds_without_progress: None,
+            max_time_without_commit: None,
+            stall_threshold: None,
+        }
+    }
+}
+
+impl Scenario {
+    pub fn parse(contents: &str) -> Result<Self> {
+        let scenario: Scenario = toml::from_str(contents)?;
+        scenario.check()?;
+        Ok(scenario)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        check_seed(self.seed)?;
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Execution backend of the nodes, by default the key-value store if transactions are
+    /// executed.
+    pub fn backend(&self) -> ExecutionBackend {
+        match (self.workload.backend, &self.workload.execution) {
+            (Some(backend), _) => backend,
+            (None, Some(_)) => ExecutionBackend::KeyValue,
+            (None, None) => ExecutionBackend::Ledger,
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<()> {
+        ensure!(self.nodes > 0, "A scenario needs at least one node");
+        check_seed(self.seed)?;
+        for author in self
+            .node
+            .iter()
+            .map(|x| x.author)
+            .chain(self.fault.iter().map(|x| x.author))
+            .chain(self.domain.iter().flat_map(|x| x.nodes.iter().cloned()))
+            .chain(
+                self.eclipse
+                    .iter()
+                    .flat_map(|x| x.relayed.iter().chain(std::iter::once(&x.victim)).cloned()),
+            )
+            .chain(self.flood.iter().map(|x| x.attacker))
+            .chain(self.byzantine.iter().cloned())
+            .chain(self.observers.iter().cloned())
+            .chain(self.epoch.iter().flat_map(|x| x.validators.iter().cloned()))
+            .chain(
+                self.reconfiguration
+                    .iter()
+                    .flat_map(|x| x.add.iter().chain(x.remove.iter()).cloned()),
+            )
+        {
+            ensure!(
+                author < self.nodes,
+                "Unknown node {} in a scenario with {} nodes",
+                author,
+                self.nodes
+            );
+        }
+        ensure!(
+            self.epoch.iter().all(|x| !x.validators.is_empty()),
+            "Every epoch needs at least one validator"
+        );
+        for observer in &self.observers {
+            ensure!(
+                self.epoch.iter().all(|x| !x.validators.contains(observer))
+                    && self
+                        .reconfiguration
+                        .iter()
+                        .all(|x| !x.add.contains(observer)),
+                "Observer {} cannot be a validator",
+                observer
+            );
+        }
+        ensure!(
+            self.observers.len() < self.nodes,
+            "A scenario needs at least one node that is not an observer"
+        );
+        self.network.check()?;
+        self.corruption.check()?;
+        self.quota.check()?;
+        self.measurement.check()?;
+        self.pacemaker.check()?;
+        self.pacemaker.peer_selection.check(self.nodes)?;
+        for node in &self.node {
+            node.check(&self.pacemaker)?;
+            ensure!(
+                !node.invalid_payloads || self.byzantine.contains(&node.author),
+                "Nodes proposing invalid payloads must be Byzantine"
+            );
+        }
+        ensure!(
+            self.fault
+                .iter()
+                .all(|x| x.recovery_time.map_or(true, |time| time > x.crash_time)),
+            "Nodes must recover after they crash"
+        );
+        self.check_failure_domains()?;
+        for eclipse in &self.eclipse {
+            ensure!(
+                eclipse.time >= GlobalTime(0),
+                "Eclipse times cannot be negative"
+            );
+            ensure!(
+                eclipse.end_time.map_or(true, |time| time > eclipse.time),
+                "Eclipses must end after they start"
+            );
+            eclipse.eclipse().check()?;
+        }
+        for flood in &self.flood {
+            ensure!(
+                flood.time >= GlobalTime(0),
+                "Flood times cannot be negative"
+            );
+            ensure!(
+                flood.end_time.map_or(true, |time| time > flood.time),
+                "Floods must end after they start"
+            );
+            flood.flood().check()?;
+        }
+        ensure!(
+            self.epoch.is_empty() || self.reconfiguration.is_empty(),
+            "Epochs and reconfigurations cannot be combined"
+        );
+        self.validator_schedule()?;
+        for change in &self.parameter_change {
+            change.check(&self.pacemaker)?;
+        }
+        for arrivals in self
+            .workload
+            .arrivals
+            .iter()
+            .chain(&self.workload.targeted_arrivals)
+        {
+            if let Err(message) = arrivals.check() {
+                bail!("{}", message);
+            }
+        }
+        if let Some(clients) = &self.clients {
+            clients.check(self.nodes)?;
+        }
+        ensure!(
+            self.workload
+                .max_batch_wait
+                .map_or(true, |x| !x.is_negative()),
+            "Batch waits cannot be negative"
+        );
+        if let EmptyBlockPolicy::Delay { max_delay } = self.workload.empty_blocks {
+            ensure!(
+                !max_delay.is_negative(),
+                "Delays of empty blocks cannot be negative"
+            );
+        }
+        ensure!(
+            self.workload.blocks_per_round > 0,
+            "Leaders must propose at least one block per round"
+        );
+        ensure!(
+            self.max_uncertified_blocks != Some(0),
+            "Nodes must keep at least one block without a QC per author"
+        );
+        if let Some(push_pull) = &self.push_pull {
+            push_pull.check()?;
+        }
+        check_data_sync_version(self.data_sync_version)?;
+        ensure!(
+            self.fast_path_wait.map_or(true, |x| !x.is_negative()),
+            "Fast path waits cannot be negative"
+        );
+        ensure!(
+            !self.workload.execution_latency.is_negative(),
+            "Execution latencies cannot be negative"
+        );
+        if let Some(execution) = &self.workload.execution {
+            execution.check()?;
+            ensure!(
+                self.backend() == ExecutionBackend::KeyValue,
+                "Only the key-value backend executes transactions, not the {} backend",
+                self.backend()
+            );
+        }
+        self.crypto.check()?;
+        ensure!(
+            !(self.aggregate_signatures && self.threshold_signatures),
+            "Aggregate and threshold signatures cannot be combined"
+        );
+        if self.fast_path_wait.is_some() {
+            self.parameters().check_fast_path()?;
+        }
+        Ok(())
+    }
+
+    fn check_failure_domains(&self) -> Result<()> {
+        let mut names = BTreeSet::new();
+        for domain in &self.domain {
+            ensure!(
+                names.insert(&domain.name),
+                "Failure domain {} is declared more than once",
+                domain.name
+            );
+            ensure!(
+                !domain.nodes.is_empty(),
+                "Failure domain {} needs at least one node",
+                domain.name
+            );
+        }
+        for fault in &self.domain_fault {
+            ensure!(
+                names.contains(&fault.domain),
+                "Unknown failure domain {}",
+                fault.domain
+            );
+            ensure!(
+                fault.time >= GlobalTime(0),
+                "Fault times cannot be negative"
+            );
+            ensure!(
+                fault.end_time.map_or(true, |time| time > fault.time),
+                "Faults of failure domains must end after they start"
+            );
+        }
+        Ok(())
+    }
+
+    /// Faults of the failure domains, as injected in the simulator.
+    fn domain_faults(&self) -> Vec<(GlobalTime, nemesis::Fault)> {
+        let mut faults = Vec::new();
+        for fault in &self.domain_fault {
+            let nodes: Vec<_> = self
+                .domain
+                .iter()
+                .find(|x| x.name == fault.domain)
+                .expect("Scenarios are checked when parsed")
+                .nodes
+                .iter()
+                .map(|x| Author(*x))
+                .collect();
+            match fault.kind {
+                DomainFaultKind::Crash => {
+                    faults.extend(
+                        nodes
+                            .iter()
+                            .map(|x| (fault.time, nemesis::Fault::Crash(*x))),
+                    );
+                    if let Some(end_time) = fault.end_time {
+                        faults.extend(
+                            nodes
+                                .iter()
+                                .map(|x| (end_time, nemesis::Fault::Recover(*x))),
+                        );
+                    }
+                }
+                DomainFaultKind::Partition => {
+                    faults.push((fault.time, nemesis::Fault::Partition(vec![nodes])));
+                    if let Some(end_time) = fault.end_time {
+                        faults.push((end_time, nemesis::Fault::Heal));
+                    }
+                }
+            }
+        }
+        faults
+    }
+
+    /// Faults of the failure domains, eclipses, and floods, as injected in the simulator.
+    fn scheduled_faults(&self) -> Vec<(GlobalTime, nemesis::Fault)> {
+        let mut faults = self.domain_faults();
+        for eclipse in &self.eclipse {
+            let victim = Author(eclipse.victim);
+            faults.push((
+                eclipse.time,
+                nemesis::Fault::Eclipse(victim, eclipse.eclipse()),
+            ));
+            if let Some(end_time) = eclipse.end_time {
+                faults.push((end_time, nemesis::Fault::EndEclipse(victim)));
+            }
+        }
+        for flood in &self.flood {
+            let attacker = Author(flood.attacker);
+            faults.push((flood.time, nemesis::Fault::Flood(attacker, flood.flood())));
+            if let Some(end_time) = flood.end_time {
+                faults.push((end_time, nemesis::Fault::EndFlood(attacker)));
+            }
+        }
+        faults
+    }
+
+    /// Apply the reconfigurations in order of time. Initial validators are the nodes that are
+    /// never added, except for the observers.
+    fn validator_schedule(&self) -> Result<ValidatorSchedule> {
+        let mut reconfigurations: Vec<_> = self.reconfiguration.iter().collect();
+        reconfigurations.sort_by_key(|x| x.time);
+        let mut join_times = BTreeMap::new();
+        for reconfiguration in &reconfigurations {
+            ensure!(
+                reconfiguration.time >= GlobalTime(0),
+                "Reconfiguration times cannot be negative"
+            );
+            for author in &reconfiguration.add {
+                ensure!(
+                    join_times
+                        .insert(Author(*author), reconfiguration.time)
+                        .is_none(),
+                    "Node {} is added more than once",
+                    author
+                );
+            }
+        }
+        let initial: BTreeSet<_> = (0..self.nodes)
+            .map(Author)
+            .filter(|x| !join_times.contains_key(x) && !self.observers.contains(&x.0))
+            .collect();
+        let mut validators = initial.clone();
+        let mut changes = Vec::new();
+        for reconfiguration in reconfigurations {
+            for author in &reconfiguration.remove {
+                ensure!(
+                    validators.remove(&Author(*author)),
+                    "Node {} is not a validator at time {}",
+                    author,
+                    reconfiguration.time.0
+                );
+            }
+            validators.extend(reconfiguration.add.iter().map(|x| Author(*x)));
+            ensure!(
+                !validators.is_empty(),
+                "Every epoch needs at least one validator"
+            );
+            changes.push((reconfiguration.time, validators.clone()));
+        }
+        Ok(ValidatorSchedule {
+            initial,
+            changes,
+            join_times,
+        })
+    }
+
+    /// Parameters of the run described by the scenario.
+    pub fn parameters(&self) -> SimulationParameters {
+        let schedule = self
+            .validator_schedule()
+            .expect("Scenarios are checked when parsed");
+        // Without explicit validators, observers are left out of the initial ones.
+        let validator_sets = if !self.reconfiguration.is_empty()
+            || (self.epoch.is_empty() && !self.observers.is_empty())
+        {
+            vec![schedule.initial]
+        } else {
+            self.epoch
+                .iter()
+                .map(|x| x.validators.iter().map(|y| Author(*y)).collect())
+                .collect()
+        };
+        let mut parameter_changes: Vec<_> = self
+            .parameter_change
+            .iter()
+            .map(|x| (x.time, x.change()))
+            .collect();
+        parameter_changes.sort_by_key(|(time, _)| *time);
+        SimulationParameters {
+            max_clock: self.termination.max_clock,
+            max_commits: self.termination.max_commits,
+            max_rounds_without_progress: self.termination.max_rounds_without_progress,
+            max_time_without_commit: self.termination.max_time_without_commit,
+            stall_threshold: self.termination.stall_threshold,
+            measurement: self.measurement,
+            mean: self.network.mean,
+            variance: self.network.variance,
+            loss_rate: self.network.loss_rate,
+            network_trace: self
+                .network
+                .trace()
+                .expect("Scenarios are checked when parsed"),
+            nodes: self.nodes,
+            commands_per_epoch: self.workload.commands_per_epoch,
+            target_commit_interval: self.workload.target_commit_interval,
+            arrivals: self.workload.arrivals.clone(),
+            targeted_arrivals: self.workload.targeted_arrivals.clone(),
+            clients: self.clients.clone(),
+            max_batch_size: self.workload.max_batch_size,
+            transaction_size: self.workload.transaction_size,
+            max_block_payload: self.workload.max_block_payload,
+            max_batch_wait: self.workload.max_batch_wait,
+            empty_block_policy: self.workload.empty_blocks,
+            blocks_per_round: self.workload.blocks_per_round,
+            max_uncertified_blocks: self.max_uncertified_blocks,
+            backend: self.backend(),
+            execution: self.workload.execution.clone(),
+            execution_latency: self.workload.execution_latency,
+            pipelined_execution: self.workload.pipelined_execution,
+            delta: self.pacemaker.delta,
+            gamma: self.pacemaker.gamma,
+            lambda: self.pacemaker.lambda,
+            round_jitter: self.pacemaker.jitter,
+            retransmission: self.pacemaker.retransmission(),
+            hybrid_pacemaker: self.pacemaker.hybrid,
+            peer_selection: self.pacemaker.peer_selection.clone(),
+            failure_detector: self.pacemaker.failure_detector,
+            push_pull: self.push_pull,
+            data_sync_version: self.data_sync_version,
+            suppress_redundant_records: self.suppress_redundant_records,
+            strict_verification: self.strict_verification,
+            state_sync: self.state_sync,
+            message_corruption: self.corruption,
+            message_quota: self.quota,
+            commit_rule: self.commit_rule,
+            vote_delivery: self.vote_delivery,
+            fast_path_wait: self.fast_path_wait,
+            aggregate_signatures: self.aggregate_signatures,
+            threshold_signatures: self.threshold_signatures,
+            crypto_costs: self.crypto.clone(),
+            message_authentication: self.message_authentication,
+            seed: self.seed.unwrap_or_else(random_seed),
+            node_overrides: self
+                .node
+                .iter()
+                .map(|x| (Author(x.author), x.clone()))
+                .collect(),
+            crashes: self
+                .fault
+                .iter()
+                .map(|x| (Author(x.author), x.crash_time))
+                .collect(),
+            recoveries: self
+                .fault
+                .iter()
+                .filter_map(|x| x.recovery_time.map(|time| (Author(x.author), time)))
+                .collect(),
+            scheduled_faults: self.scheduled_faults(),
+            byzantine: self.byzantine.iter().map(|x| Author(*x)).collect(),
+            observers: self.observers.iter().map(|x| Author(*x)).collect(),
+            stakes: self
+                .node
+                .iter()
+                .filter_map(|x| x.stake.map(|stake| (Author(x.author), stake)))
+                .collect(),
+            validator_sets,
+            reconfigurations: schedule.changes,
+            parameter_changes,
+            join_times: schedule.join_times,
+            record_decisions: false,
+            check_lemmas: self.check_lemmas,
+        }
+    }
+}
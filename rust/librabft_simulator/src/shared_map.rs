@@ -0,0 +1,120 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hash maps that are cloned in constant time and copied piecewise on write.
+//!
+//! A `SharedMap` splits its entries into a fixed number of shards behind `Arc` pointers. Clones
+//! share all the shards. A write to a shared map copies the array of shard pointers and the shard
+//! of the entry, but not the others, so that a snapshot of a large map costs each later write
+//! about `1 / SHARDS` of the map, once per shard. Since pointers are atomic, maps can be sent to
+//! other threads when their entries can.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    ops::Index,
+    sync::Arc,
+};
+
+#[cfg(test)]
+#[path = "unit_tests/shared_map_tests.rs"]
+mod shared_map_tests;
+
+/// Number of shards of each map.
+pub const SHARDS: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct SharedMap<K, V> {
+    shards: Arc<Vec<Arc<HashMap<K, V>>>>,
+    len: usize,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Default for SharedMap<K, V> {
+    fn default() -> Self {
+        SharedMap::new()
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> SharedMap<K, V> {
+    pub fn new() -> Self {
+        SharedMap {
+            shards: Arc::new((0..SHARDS).map(|_| Arc::new(HashMap::new())).collect()),
+            len: 0,
+        }
+    }
+
+    fn shard_index(key: &K) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % SHARDS as u64) as usize
+    }
+
+    /// The shard of `key`, copied first if another map shares it.
+    fn shard_mut(&mut self, key: &K) -> &mut HashMap<K, V> {
+        let index = Self::shard_index(key);
+        Arc::make_mut(&mut Arc::make_mut(&mut self.shards)[index])
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.shards[Self::shard_index(key)].get(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let previous = self.shard_mut(&key).insert(key, value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if !self.contains_key(key) {
+            // Do not copy a shared shard for nothing.
+            return None;
+        }
+        self.len -= 1;
+        self.shard_mut(key).remove(key)
+    }
+
+    /// Iterate on the entries, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.shards.iter().flat_map(|shard| shard.iter())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Number of shards that the two maps still share, for monitoring purposes.
+    pub fn shared_shards(&self, other: &Self) -> usize {
+        self.shards
+            .iter()
+            .zip(other.shards.iter())
+            .filter(|(left, right)| Arc::ptr_eq(left, right))
+            .count()
+    }
+}
+
+impl<'a, K: Eq + Hash + Clone, V: Clone> Index<&'a K> for SharedMap<K, V> {
+    type Output = V;
+
+    fn index(&self, key: &K) -> &V {
+        self.get(key).expect("Unknown key in shared map")
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Minimization of failing scenarios.
+//!
+//! A scenario fails when its run stops with a safety, invariant, or liveness violation. Since
+//! runs are deterministic for a given seed, a failing scenario can be reduced while the same
+//! kind of violation persists: faults are removed by delta debugging, then the run is cut
+//! right after the violation.
+
+use super::*;
+use bft_simulator_runtime::termination::StopReason;
+use scenario::Scenario;
+use std::mem;
+
+#[cfg(test)]
+#[path = "unit_tests/shrink_tests.rs"]
+mod shrink_tests;
+
+#[derive(Clone, Debug)]
+pub struct ShrinkResult {
+    /// Smallest failing scenario found, with a fixed seed.
+    pub scenario: Scenario,
+    /// Violation observed when running `scenario`.
+    pub stop_reason: StopReason,
+    /// Number of runs used to minimize the scenario.
+    pub runs: usize,
+}
+
+pub fn is_failure(stop_reason: &StopReason) -> bool {
+    match stop_reason {
+        StopReason::SafetyViolation(_)
+        | StopReason::InvariantViolation(_)
+        | StopReason::LivenessViolation(_) => true,
+        _ => false,
+    }
+}
+
+/// Run the scenario and return why it stopped, together with the time of the stop.
+fn run_scenario(scenario: &Scenario) -> (StopReason, simulator::GlobalTime) {
+    let parameters = scenario.parameters();
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    (
+        stop_reason,
+        simulator::GlobalTime(0) + sim.summary().duration,
+    )
+}
+
+/// Minimize a failing scenario. Returns `None` if the scenario does not fail.
+pub fn shrink(scenario: &Scenario) -> Option<ShrinkResult> {
+    let mut scenario = Scenario {
+        seed: Some(scenario.seed.unwrap_or_else(scenario::random_seed)),
+        ..scenario.clone()
+    };
+    let (stop_reason, _) = run_scenario(&scenario);
+    if !is_failure(&stop_reason) {
+        return None;
+    }
+    let failure = mem::discriminant(&stop_reason);
+    let mut runs = 1;
+    let mut fails = |scenario: &Scenario| {
+        runs += 1;
+        let (stop_reason, time) = run_scenario(scenario);
+        if mem::discriminant(&stop_reason) == failure {
+            Some((stop_reason, time))
+        } else {
+            None
+        }
+    };
+
+    let faults = minimize(scenario.fault.clone(), |faults| {
+        let candidate = Scenario {
+            fault: faults.to_vec(),
+            ..scenario.clone()
+        };
+        fails(&candidate).is_some()
+    });
+    scenario.fault = faults;
+    // Removing faults may change the time of the violation.
+    let (_, time) = fails(&scenario).expect("Minimized scenarios keep failing");
+    scenario.termination.max_clock = time;
+    let (stop_reason, _) = fails(&scenario).expect("Cutting the run keeps the violation");
+    Some(ShrinkResult {
+        scenario,
+        stop_reason,
+        runs,
+    })
+}
+
+/// Delta debugging: find a small subset of `items` for which `fails` holds, assuming that it
+/// holds for `items`. The result is 1-minimal: removing any single item makes `fails` false.
+pub fn minimize<T, F>(items: Vec<T>, mut fails: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&[T]) -> bool,
+{
+    if items.is_empty() || fails(&[]) {
+        return Vec::new();
+    }
+    let mut items = items;
+    let mut granularity = 2;
+    while items.len() > 1 {
+        let granularity_bound = std::cmp::min(granularity, items.len());
+        let chunk_size = (items.len() + granularity_bound - 1) / granularity_bound;
+        // Try to remove each chunk in turn.
+        let reduced = (0..items.len()).step_by(chunk_size).find_map(|start| {
+            let end = std::cmp::min(start + chunk_size, items.len());
+            let complement: Vec<_> = items[..start]
+                .iter()
+                .chain(items[end..].iter())
+                .cloned()
+                .collect();
+            if fails(&complement) {
+                Some(complement)
+            } else {
+                None
+            }
+        });
+        match reduced {
+            Some(complement) => {
+                items = complement;
+                granularity = std::cmp::max(granularity - 1, 2);
+            }
+            None if chunk_size == 1 => break,
+            None => granularity *= 2,
+        }
+    }
+    items
+}
@@ -3,18 +3,105 @@
 
 use super::*;
 use base_types::*;
+use bft_simulator_runtime::{
+    mempool::{Mempool, Transaction},
+    workload::WorkloadGenerator,
+};
+use key_value_store::{ExecutionModel, KeyValueStore};
 use record::*;
+use serde::{Deserialize, Serialize};
 use smr_context::*;
 use std::{
-    collections::{hash_map::DefaultHasher, HashMap},
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap},
+    fmt,
     hash::{Hash, Hasher},
+    str::FromStr,
 };
 
 #[cfg(test)]
 #[path = "unit_tests/simulated_context_tests.rs"]
 mod simulated_context_tests;
 
-#[derive(Eq, PartialEq, Clone, Debug, Hash)]
+/// Index of the transactions that fail validation. Mempools number their transactions from 0,
+/// so honest proposers never carry one, whatever their clocks.
+pub const INVALID_TRANSACTION_INDEX: usize = std::usize::MAX;
+
+/// How simulated contexts execute the committed commands, so that the same scenario can run on
+/// different execution backends.
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExecutionBackend {
+    /// States record the executed commands, without executing their transactions.
+    Ledger,
+    /// Transactions are also executed in a key-value store, which takes simulated time.
+    KeyValue,
+    /// Execution takes no time, even with an execution latency, and states cannot be
+    /// checkpointed, so that runs only measure consensus.
+    Null,
+}
+
+/// States of the ledger of a simulated node, one type per execution backend.
+pub trait LedgerState: Clone + Default + fmt::Debug {
+    const BACKEND: ExecutionBackend;
+
+    /// Identifier of the state, as seen by consensus.
+    fn key(&self) -> State;
+
+    /// Execute a command at the given consensus time. Returns the simulated execution time.
+    fn execute(&mut self, command: Command, time: NodeTime) -> Duration;
+
+    /// All the executed commands and their consensus times, if the backend keeps them.
+    fn history(&self) -> &[(Command, NodeTime)];
+
+    fn num_commands(&self) -> usize {
+        self.history().len()
+    }
+
+    /// Latest consensus time of the executed commands, if any.
+    fn latest_time(&self) -> Option<NodeTime> {
+        self.history().iter().map(|x| x.1).max()
+    }
+
+    /// All the changes of parameters executed so far, in order.
+    fn parameter_changes(&self) -> ParameterChange {
+        self.history()
+            .iter()
+            .filter_map(|(command, _)| command.parameter_change.as_ref())
+            .map(|(_, change)| change)
+            .fold(ParameterChange::default(), |changes, change| {
+                changes.then(change)
+            })
+    }
+
+    fn last_command(&self) -> Option<&Command> {
+        self.history().last().map(|(command, _)| command)
+    }
+
+    /// Whether `other` is this state followed by a single command.
+    fn happened_just_before(&self, other: &Self) -> bool {
+        let history = self.history();
+        other.history().len() == history.len() + 1
+            && other.history()[..history.len()] == history[..]
+    }
+
+    /// Contents of the key-value store, if transactions are executed.
+    fn store(&self) -> Option<&KeyValueStore> {
+        None
+    }
+
+    /// Serialize the state for another node, if supported.
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Parse a snapshot taken by another node, keeping the settings of this state.
+    fn parse_snapshot(&self, _snapshot: &[u8]) -> Option<Self> {
+        None
+    }
+}
+
+/// States that record the executed commands, without executing their transactions.
+#[derive(Eq, PartialEq, Clone, Debug, Default, Hash, Deserialize, Serialize)]
 pub struct SimulatedLedgerState {
     /// All the executed commands and theirs consensus times of execution.
     /// TODO: use linked lists with sharing
@@ -22,11 +109,16 @@ pub struct SimulatedLedgerState {
 }
 
 impl SimulatedLedgerState {
-    fn new() -> SimulatedLedgerState {
+    pub fn new() -> SimulatedLedgerState {
         SimulatedLedgerState {
             execution_history: Vec::new(),
         }
     }
+}
+
+/// Snapshots are the executed commands, in JSON.
+impl LedgerState for SimulatedLedgerState {
+    const BACKEND: ExecutionBackend = ExecutionBackend::Ledger;
 
     fn key(&self) -> State {
         let mut hasher = DefaultHasher::new();
@@ -34,54 +126,517 @@ impl SimulatedLedgerState {
         State(hasher.finish())
     }
 
-    fn execute(&mut self, command: Command, time: NodeTime) {
+    fn execute(&mut self, command: Command, time: NodeTime) -> Duration {
         self.execution_history.push((command, time));
+        Duration::ZERO
     }
 
-    fn happened_just_before(&self, other: &SimulatedLedgerState) -> bool {
-        if self.execution_history.len() + 1 != other.execution_history.len() {
-            return false;
+    fn history(&self) -> &[(Command, NodeTime)] {
+        &self.execution_history
+    }
+
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        serde_json::to_vec(self).ok()
+    }
+
+    fn parse_snapshot(&self, snapshot: &[u8]) -> Option<Self> {
+        serde_json::from_slice(snapshot).ok()
+    }
+}
+
+/// States that also execute the transactions in a key-value store, which takes simulated time.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct KeyValueLedgerState {
+    ledger: SimulatedLedgerState,
+    store: KeyValueStore,
+    /// Costs of execution, which are settings of the node rather than part of the state.
+    #[serde(skip)]
+    model: ExecutionModel,
+}
+
+impl KeyValueLedgerState {
+    pub fn new(model: ExecutionModel) -> KeyValueLedgerState {
+        KeyValueLedgerState {
+            ledger: SimulatedLedgerState::new(),
+            store: KeyValueStore::new(),
+            model,
         }
-        for i in 0..self.execution_history.len() {
-            if self.execution_history[i] != other.execution_history[i] {
-                return false;
-            }
+    }
+}
+
+/// Snapshots are the executed commands and the key-value store, in JSON.
+impl LedgerState for KeyValueLedgerState {
+    const BACKEND: ExecutionBackend = ExecutionBackend::KeyValue;
+
+    fn key(&self) -> State {
+        let mut hasher = DefaultHasher::new();
+        self.ledger.key().hash(&mut hasher);
+        self.store.state_hash().hash(&mut hasher);
+        State(hasher.finish())
+    }
+
+    fn execute(&mut self, command: Command, time: NodeTime) -> Duration {
+        self.store.execute(&command, self.model.num_keys);
+        let duration = self.model.execution_time(&command);
+        self.ledger.execute(command, time);
+        duration
+    }
+
+    fn history(&self) -> &[(Command, NodeTime)] {
+        self.ledger.history()
+    }
+
+    fn store(&self) -> Option<&KeyValueStore> {
+        Some(&self.store)
+    }
+
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        serde_json::to_vec(self).ok()
+    }
+
+    fn parse_snapshot(&self, snapshot: &[u8]) -> Option<Self> {
+        let ledger_state: KeyValueLedgerState = serde_json::from_slice(snapshot).ok()?;
+        Some(KeyValueLedgerState {
+            model: self.model.clone(),
+            ..ledger_state
+        })
+    }
+}
+
+/// States that only keep what consensus reads, so that runs only measure consensus:
+/// execution takes no time, even with an execution latency, and states cannot be
+/// checkpointed.
+#[derive(Clone, Debug)]
+pub struct NullLedgerState {
+    /// Hash of the previous key and of the last command.
+    key: State,
+    previous_key: State,
+    num_commands: usize,
+    latest_time: Option<NodeTime>,
+    parameter_changes: ParameterChange,
+    last_command: Option<Command>,
+}
+
+impl Default for NullLedgerState {
+    fn default() -> Self {
+        NullLedgerState {
+            key: State(0),
+            previous_key: State(0),
+            num_commands: 0,
+            latest_time: None,
+            parameter_changes: ParameterChange::default(),
+            last_command: None,
+        }
+    }
+}
+
+impl LedgerState for NullLedgerState {
+    const BACKEND: ExecutionBackend = ExecutionBackend::Null;
+
+    fn key(&self) -> State {
+        self.key.clone()
+    }
+
+    fn execute(&mut self, command: Command, time: NodeTime) -> Duration {
+        let mut hasher = DefaultHasher::new();
+        self.key.hash(&mut hasher);
+        command.hash(&mut hasher);
+        time.hash(&mut hasher);
+        self.previous_key = std::mem::replace(&mut self.key, State(hasher.finish()));
+        self.num_commands += 1;
+        self.latest_time = std::cmp::max(self.latest_time, Some(time));
+        if let Some((_, change)) = &command.parameter_change {
+            self.parameter_changes = self.parameter_changes.then(change);
+        }
+        self.last_command = Some(command);
+        Duration::ZERO
+    }
+
+    fn history(&self) -> &[(Command, NodeTime)] {
+        &[]
+    }
+
+    fn num_commands(&self) -> usize {
+        self.num_commands
+    }
+
+    fn latest_time(&self) -> Option<NodeTime> {
+        self.latest_time
+    }
+
+    fn parameter_changes(&self) -> ParameterChange {
+        self.parameter_changes
+    }
+
+    fn last_command(&self) -> Option<&Command> {
+        self.last_command.as_ref()
+    }
+
+    fn happened_just_before(&self, other: &Self) -> bool {
+        other.num_commands == self.num_commands + 1 && other.previous_key == self.key
+    }
+}
+
+impl Default for ExecutionBackend {
+    fn default() -> Self {
+        ExecutionBackend::Ledger
+    }
+}
+
+impl fmt::Display for ExecutionBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecutionBackend::Ledger => write!(f, "ledger"),
+            ExecutionBackend::KeyValue => write!(f, "key-value"),
+            ExecutionBackend::Null => write!(f, "null"),
         }
-        return true;
     }
 }
 
-#[derive(Eq, PartialEq, Clone, Debug)]
-pub struct SimulatedContext {
+impl FromStr for ExecutionBackend {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ledger" => Ok(ExecutionBackend::Ledger),
+            "key-value" => Ok(ExecutionBackend::KeyValue),
+            "null" => Ok(ExecutionBackend::Null),
+            _ => bail!("Unknown execution backend: {}", s),
+        }
+    }
+}
+
+/// Contexts that execute commands with the ledger backend.
+#[derive(Clone, Debug)]
+pub struct SimulatedContext<L = SimulatedLedgerState> {
     author: Author,
     num_nodes: usize,
     max_command_per_epoch: usize,
     next_fetched_command_index: usize,
-    last_committed_ledger_state: SimulatedLedgerState,
-    pending_ledger_states: HashMap<State, SimulatedLedgerState>,
+    last_committed_ledger_state: L,
+    pending_ledger_states: HashMap<State, L>,
+    /// Validators of each epoch. The last set applies to later epochs.
+    validator_sets: Vec<BTreeSet<Author>>,
+    /// Validators from the first block proposed at or after a given time, in increasing order
+    /// of time. Each of these changes starts a new epoch.
+    reconfigurations: Vec<(NodeTime, BTreeSet<Author>)>,
+    /// Voting rights of the validators that do not have a single vote.
+    stakes: BTreeMap<Author, usize>,
+    /// Governance changes of the protocol parameters, proposed from a given time on until they
+    /// are committed, in increasing order of time.
+    parameter_changes: Vec<(NodeTime, ParameterChange)>,
+    /// Indices in `parameter_changes` of the changes committed so far.
+    committed_parameter_changes: BTreeSet<usize>,
+    /// Transactions submitted to this node.
+    mempool: Mempool,
+    /// Arrivals of transactions, if any. Blocks are empty otherwise.
+    workload: Option<WorkloadGenerator>,
+    /// Arrivals of targeted transactions, the same at all nodes, if any.
+    targeted_workload: Option<WorkloadGenerator>,
+    /// Identifier of the next targeted transaction.
+    next_targeted_id: usize,
+    max_batch_size: usize,
+    /// Size of each submitted transaction, in bytes.
+    transaction_size: usize,
+    /// Maximal size of the transactions of a block, in bytes, if any.
+    max_block_payload: Option<usize>,
+    /// Execution time not reported to the simulator yet.
+    execution_time: Duration,
+    /// Fraction of a millisecond of execution time, divided by the CPU speed, that was not
+    /// reported yet, so that small costs add up across handlers.
+    execution_remainder: f64,
+    /// Time to execute a block before voting on it.
+    execution_latency: Duration,
+    /// Voting constraints persisted by the node. Like the ledger, they survive restarts.
+    safety_data: Option<SafetyData>,
+    /// Time charged per cryptographic operation.
+    crypto_costs: CryptoCosts,
+    /// Authentication of the messages sent and received by the node.
+    message_authentication: MessageAuthentication,
+    signatures_created: usize,
+    signatures_verified: usize,
+    macs_computed: usize,
+    /// Whether proposals carry a transaction that fails validation, as a Byzantine leader.
+    invalid_payloads: bool,
+    /// Speed of the processor of the node, relative to the costs of execution and
+    /// cryptography. Charged times are divided by it.
+    cpu_speed: f64,
 }
 
+/// Contexts that also execute the transactions in a key-value store.
+pub type KeyValueContext = SimulatedContext<KeyValueLedgerState>;
+
+/// Contexts that execute nothing, so that runs only measure consensus.
+pub type NullContext = SimulatedContext<NullLedgerState>;
+
 impl SimulatedContext {
     pub fn new(author: Author, num_nodes: usize, max_command_per_epoch: usize) -> Self {
+        SimulatedContext::from_ledger_state(
+            author,
+            num_nodes,
+            max_command_per_epoch,
+            SimulatedLedgerState::new(),
+        )
+    }
+}
+
+impl KeyValueContext {
+    /// A context that executes transactions with the given costs.
+    pub fn with_execution_model(
+        author: Author,
+        num_nodes: usize,
+        max_command_per_epoch: usize,
+        model: ExecutionModel,
+    ) -> Self {
+        SimulatedContext::from_ledger_state(
+            author,
+            num_nodes,
+            max_command_per_epoch,
+            KeyValueLedgerState::new(model),
+        )
+    }
+}
+
+impl<L: LedgerState> SimulatedContext<L> {
+    /// A context whose initial committed state is `ledger_state`.
+    pub fn from_ledger_state(
+        author: Author,
+        num_nodes: usize,
+        max_command_per_epoch: usize,
+        ledger_state: L,
+    ) -> Self {
         SimulatedContext {
             author,
             num_nodes,
             max_command_per_epoch,
             next_fetched_command_index: 0,
-            last_committed_ledger_state: SimulatedLedgerState::new(),
+            last_committed_ledger_state: ledger_state,
             pending_ledger_states: HashMap::new(),
+            validator_sets: Vec::new(),
+            reconfigurations: Vec::new(),
+            stakes: BTreeMap::new(),
+            parameter_changes: Vec::new(),
+            committed_parameter_changes: BTreeSet::new(),
+            mempool: Mempool::new(),
+            workload: None,
+            targeted_workload: None,
+            next_targeted_id: 0,
+            max_batch_size: 0,
+            transaction_size: 0,
+            max_block_payload: None,
+            execution_time: Duration::ZERO,
+            execution_remainder: 0.0,
+            execution_latency: Duration::ZERO,
+            safety_data: None,
+            crypto_costs: CryptoCosts::default(),
+            message_authentication: MessageAuthentication::None,
+            signatures_created: 0,
+            signatures_verified: 0,
+            macs_computed: 0,
+            invalid_payloads: false,
+            cpu_speed: 1.0,
         }
     }
 
+    /// Change the validators from one epoch to the next. By default, all nodes are validators
+    /// with the same voting rights.
+    pub fn with_validator_sets(mut self, validator_sets: Vec<BTreeSet<Author>>) -> Self {
+        self.validator_sets = validator_sets;
+        self
+    }
+
+    /// Change the validators once a block proposed at or after a given time is executed, in
+    /// addition to the changes between epochs of `max_command_per_epoch` commands. Block times
+    /// are local times of the proposers.
+    pub fn with_reconfigurations(
+        mut self,
+        reconfigurations: Vec<(NodeTime, BTreeSet<Author>)>,
+    ) -> Self {
+        self.reconfigurations = reconfigurations;
+        self
+    }
+
+    /// Give validators the given voting rights instead of a single vote.
+    pub fn with_stakes(mut self, stakes: BTreeMap<Author, usize>) -> Self {
+        self.stakes = stakes;
+        self
+    }
+
+    /// Propose the given changes of the protocol parameters in the blocks proposed at or after
+    /// their times. Each change takes effect at the start of the epoch after its commit.
+    pub fn with_parameter_changes(
+        mut self,
+        parameter_changes: Vec<(NodeTime, ParameterChange)>,
+    ) -> Self {
+        self.parameter_changes = parameter_changes;
+        self
+    }
+
+    fn stake(&self, author: Author) -> usize {
+        *self.stakes.get(&author).unwrap_or(&1)
+    }
+
+    /// Submit transactions to this node as they arrive. Proposals carry at most
+    /// `max_batch_size` transactions.
+    pub fn with_workload(mut self, workload: WorkloadGenerator, max_batch_size: usize) -> Self {
+        self.workload = Some(workload);
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Submit targeted transactions as they arrive. Arrivals must be the same at all nodes, so
+    /// that each targeted transaction gets the same identifier everywhere.
+    pub fn with_targeted_workload(
+        mut self,
+        workload: WorkloadGenerator,
+        max_batch_size: usize,
+    ) -> Self {
+        self.targeted_workload = Some(workload);
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Accept the requests of the simulated clients. Proposals carry at most `max_batch_size`
+    /// transactions.
+    pub fn with_client_requests(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Leave targeted transactions out of the proposals of this node, as a censoring leader.
+    pub fn with_censorship(mut self, censoring: bool) -> Self {
+        self.mempool.set_censoring(censoring);
+        self
+    }
+
+    /// Add a transaction that fails validation to each proposal of this node, which other
+    /// nodes reject, as a Byzantine leader. Such nodes accept any payload themselves.
+    pub fn with_invalid_payloads(mut self, invalid_payloads: bool) -> Self {
+        self.invalid_payloads = invalid_payloads;
+        self
+    }
+
+    /// Submit transactions of `transaction_size` bytes, and limit proposals to
+    /// `max_block_payload` bytes of transactions, if any.
+    pub fn with_block_payload(
+        mut self,
+        transaction_size: usize,
+        max_block_payload: Option<usize>,
+    ) -> Self {
+        self.transaction_size = transaction_size;
+        self.max_block_payload = max_block_payload;
+        self
+    }
+
+    /// Charge the time of cryptographic operations to the node, as for execution.
+    pub fn with_crypto_costs(mut self, costs: CryptoCosts) -> Self {
+        self.crypto_costs = costs;
+        self
+    }
+
+    /// Charge the authentication of the network messages to the node, and count its tags in
+    /// the size of the messages.
+    pub fn with_message_authentication(mut self, authentication: MessageAuthentication) -> Self {
+        self.message_authentication = authentication;
+        self
+    }
+
+    /// Run on a processor `speed` times as fast as the reference of the cost models, e.g. 0.5
+    /// for a node that takes twice as long to execute blocks and check signatures.
+    pub fn with_cpu_speed(mut self, speed: f64) -> Self {
+        self.cpu_speed = speed;
+        self
+    }
+
+    /// Make nodes wait for `latency` after receiving a block before voting on it.
+    pub fn with_execution_latency(mut self, latency: Duration) -> Self {
+        self.execution_latency = latency;
+        self
+    }
+
+    /// Contents of the key-value store after the last commit, if transactions are executed.
+    pub fn committed_store(&self) -> Option<&KeyValueStore> {
+        self.last_committed_ledger_state.store()
+    }
+
+    pub fn mempool(&self) -> &Mempool {
+        &self.mempool
+    }
+
+    pub fn backend(&self) -> ExecutionBackend {
+        L::BACKEND
+    }
+
     pub fn last_committed_state(&self) -> State {
         self.last_committed_ledger_state.key()
     }
 
-    pub fn committed_history(&self) -> &Vec<(Command, NodeTime)> {
-        &self.last_committed_ledger_state.execution_history
+    /// The committed commands, empty with the null backend, which does not keep them.
+    pub fn committed_history(&self) -> &[(Command, NodeTime)] {
+        self.last_committed_ledger_state.history()
+    }
+
+    pub fn num_committed_commands(&self) -> usize {
+        self.last_committed_ledger_state.num_commands()
+    }
+
+    /// Submit the transactions that arrived until `time`.
+    fn receive_transactions(&mut self, time: NodeTime) {
+        if let Some(workload) = &mut self.workload {
+            for submission_time in workload.arrivals_until(time) {
+                self.mempool.submit(submission_time, self.transaction_size);
+            }
+        }
+        if let Some(workload) = &mut self.targeted_workload {
+            for submission_time in workload.arrivals_until(time) {
+                let id = self.next_targeted_id;
+                self.mempool
+                    .submit_targeted(submission_time, self.transaction_size, id);
+                self.next_targeted_id += 1;
+            }
+        }
+    }
+
+    /// Number of reconfigurations that took effect in a ledger state.
+    fn num_reconfigurations(&self, ledger_state: &L) -> usize {
+        let latest_time = match ledger_state.latest_time() {
+            Some(time) => time,
+            None => return 0,
+        };
+        self.reconfigurations
+            .iter()
+            .take_while(|(time, _)| *time <= latest_time)
+            .count()
     }
 
-    fn get_ledger_state(&self, state: &State) -> Option<&SimulatedLedgerState> {
+    fn epoch_id(&self, ledger_state: &L) -> EpochId {
+        let num_commands = ledger_state.num_commands();
+        EpochId(num_commands / self.max_command_per_epoch + self.num_reconfigurations(ledger_state))
+    }
+
+    /// The first change due at `time` that is not committed yet, if any, with its index.
+    fn pending_parameter_change(&self, time: NodeTime) -> Option<(usize, ParameterChange)> {
+        self.parameter_changes
+            .iter()
+            .enumerate()
+            .take_while(|(_, (change_time, _))| *change_time <= time)
+            .find(|(index, _)| !self.committed_parameter_changes.contains(index))
+            .map(|(index, (_, change))| (index, *change))
+    }
+
+    /// Account for a newly committed command.
+    fn commit_command(&mut self, command: &Command) {
+        if let Some((index, _)) = command.parameter_change {
+            self.committed_parameter_changes.insert(index);
+        }
+        if command.proposer == self.author {
+            self.mempool.commit_batch(command.index);
+        } else {
+            self.mempool.commit_targeted(&command.transactions);
+        }
+    }
+
+    fn get_ledger_state(&self, state: &State) -> Option<&L> {
         if state == &self.last_committed_ledger_state.key() {
             Some(&self.last_committed_ledger_state)
         } else {
@@ -90,18 +645,64 @@ impl SimulatedContext {
     }
 }
 
-impl CommandFetcher for SimulatedContext {
-    fn fetch(&mut self) -> Option<Command> {
+impl<L: LedgerState> CommandFetcher for SimulatedContext<L> {
+    fn fetch(&mut self, time: NodeTime) -> Option<Command> {
+        self.receive_transactions(time);
+        let index = self.next_fetched_command_index;
+        let mut transactions =
+            self.mempool
+                .pull_batch(index, self.max_batch_size, self.max_block_payload);
+        if self.invalid_payloads {
+            transactions.push(Transaction {
+                index: INVALID_TRANSACTION_INDEX,
+                submission_time: time,
+                size: self.transaction_size,
+                targeted: None,
+                request: None,
+                proposals: 0,
+            });
+        }
         let command = Command {
             proposer: self.author,
-            index: self.next_fetched_command_index,
+            index,
+            transactions,
+            parameter_change: self.pending_parameter_change(time),
         };
         self.next_fetched_command_index += 1;
         Some(command)
     }
+
+    fn is_batch_full(&mut self, time: NodeTime) -> bool {
+        self.receive_transactions(time);
+        self.mempool
+            .is_batch_full(self.max_batch_size, self.max_block_payload)
+    }
+
+    /// Changes of the protocol parameters are worth a block of their own.
+    fn is_batch_empty(&mut self, time: NodeTime) -> bool {
+        self.receive_transactions(time);
+        self.mempool
+            .is_batch_empty(self.max_batch_size, self.max_block_payload)
+            && self.pending_parameter_change(time).is_none()
+    }
+
+    fn release(&mut self, command: &Command) {
+        if command.proposer == self.author {
+            self.mempool.release_batch(command.index);
+        }
+    }
+
+    fn next_arrival(&mut self, time: NodeTime) -> Option<NodeTime> {
+        self.receive_transactions(time);
+        self.workload
+            .iter()
+            .chain(&self.targeted_workload)
+            .map(WorkloadGenerator::next_arrival)
+            .min()
+    }
 }
 
-impl StateComputer for SimulatedContext {
+impl<L: LedgerState> StateComputer for SimulatedContext<L> {
     fn compute(
         &mut self,
         base_state: &State,
@@ -113,7 +714,7 @@ impl StateComputer for SimulatedContext {
         match self.get_ledger_state(base_state) {
             Some(ledger_state) => {
                 let mut new_ledger_state = ledger_state.clone();
-                new_ledger_state.execute(command.clone(), time);
+                self.execution_time += new_ledger_state.execute(command.clone(), time);
                 let new_state = new_ledger_state.key();
                 self.pending_ledger_states
                     .insert(new_state.clone(), new_ledger_state);
@@ -132,9 +733,28 @@ impl StateComputer for SimulatedContext {
             }
         }
     }
+
+    /// Only Byzantine leaders propose invalid transactions. Submission times are not checked,
+    /// since the clock of an honest proposer may jump backward after a submission.
+    fn is_valid(&self, command: &Command, _time: NodeTime) -> bool {
+        self.invalid_payloads
+            || command
+                .transactions
+                .iter()
+                .all(|transaction| transaction.index != INVALID_TRANSACTION_INDEX)
+    }
+
+    fn execution_duration(&self, _command: &Command) -> Duration {
+        match L::BACKEND {
+            ExecutionBackend::Null => Duration::ZERO,
+            ExecutionBackend::Ledger | ExecutionBackend::KeyValue => {
+                self.execution_latency.mul_f64(1.0 / self.cpu_speed)
+            }
+        }
+    }
 }
 
-impl StateFinalizer for SimulatedContext {
+impl<L: LedgerState> StateFinalizer for SimulatedContext<L> {
     fn commit(&mut self, state: &State, certificate: Option<&QuorumCertificate>) {
         info!("{:?} Delivering commit for state: {:?}", self.author, state);
         let ledger_state = self
@@ -149,6 +769,14 @@ impl StateFinalizer for SimulatedContext {
         assert!(self
             .last_committed_ledger_state
             .happened_just_before(&ledger_state));
+        // Limit the payload of the next proposals once a new epoch starts.
+        if self.epoch_id(&ledger_state) > self.epoch_id(&self.last_committed_ledger_state) {
+            if let Some(payload) = ledger_state.parameter_changes().max_block_payload {
+                self.max_block_payload = Some(payload);
+            }
+        }
+        let command = ledger_state.last_command().unwrap();
+        self.commit_command(command);
         if let Some(qc) = certificate {
             if let Some(state2) = &qc.committed_state {
                 assert_eq!(state, state2);
@@ -169,24 +797,366 @@ impl StateFinalizer for SimulatedContext {
     }
 }
 
-impl EpochReader for SimulatedContext {
+impl<L: LedgerState> EpochReader for SimulatedContext<L> {
     fn read_epoch_id(&self, state: &State) -> EpochId {
-        let num_commands = self
+        let ledger_state = self
             .get_ledger_state(state)
-            .expect("Read states should be known")
-            .execution_history
-            .len();
-        EpochId(num_commands / self.max_command_per_epoch)
+            .expect("Read states should be known");
+        self.epoch_id(ledger_state)
     }
 
-    fn configuration(&self, _state: &State) -> EpochConfiguration {
-        // We do not simulate changes in the voting rights yet.
-        let mut voting_rights = BTreeMap::new();
-        for index in 0..self.num_nodes {
-            voting_rights.insert(Author(index), 1);
-        }
+    fn configuration(&self, state: &State) -> EpochConfiguration {
+        let ledger_state = self
+            .get_ledger_state(state)
+            .expect("Read states should be known");
+        let validators = match self.num_reconfigurations(ledger_state) {
+            0 => {
+                let epoch_id = self.read_epoch_id(state);
+                self.validator_sets
+                    .get(epoch_id.0)
+                    .or_else(|| self.validator_sets.last())
+            }
+            n => Some(&self.reconfigurations[n - 1].1),
+        };
+        let voting_rights = match validators {
+            Some(validators) => validators
+                .iter()
+                .map(|author| (*author, self.stake(*author)))
+                .collect(),
+            None => (0..self.num_nodes)
+                .map(|index| (Author(index), self.stake(Author(index))))
+                .collect(),
+        };
         EpochConfiguration::new(voting_rights)
     }
+
+    fn parameter_changes(&self, state: &State) -> ParameterChange {
+        let ledger_state = self
+            .get_ledger_state(state)
+            .expect("Read states should be known");
+        ledger_state.parameter_changes()
+    }
+}
+
+impl<L: LedgerState> ExecutionTime for SimulatedContext<L> {
+    fn take_execution_time(&mut self) -> Duration {
+        let execution_time = std::mem::replace(&mut self.execution_time, Duration::ZERO);
+        let millis = execution_time.as_millis_f64() / self.cpu_speed + self.execution_remainder;
+        let reported = Duration::from_millis_f64(millis);
+        self.execution_remainder = millis - reported.as_millis_f64();
+        reported
+    }
+}
+
+impl<L: LedgerState> ClientRequests for SimulatedContext<L> {
+    fn submit_request(&mut self, request: ClientRequest, clock: NodeTime) -> bool {
+        self.mempool
+            .submit_request(clock, self.transaction_size, request)
+    }
+}
+
+impl<L: LedgerState> SafetyStorage for SimulatedContext<L> {
+    fn save_safety_data(&mut self, data: SafetyData) {
+        self.safety_data = Some(data);
+    }
+
+    fn load_safety_data(&self) -> Option<SafetyData> {
+        self.safety_data
+    }
+}
+
+impl<L: LedgerState> CryptoCost for SimulatedContext<L> {
+    fn charge_crypto(&mut self, operation: CryptoOperation, count: usize) {
+        self.execution_time += self.crypto_costs.cost(operation) * count as i64;
+        match operation {
+            CryptoOperation::Sign => self.signatures_created += count,
+            CryptoOperation::Verify => self.signatures_verified += count,
+            CryptoOperation::Aggregate => (),
+            CryptoOperation::Mac => self.macs_computed += count,
+        }
+    }
+}
+
+impl<L: LedgerState> AuthenticatedChannels for SimulatedContext<L> {
+    fn authenticate_message(&mut self, recipients: usize) -> usize {
+        match self.message_authentication {
+            MessageAuthentication::None => (),
+            MessageAuthentication::Mac => self.charge_crypto(CryptoOperation::Mac, recipients),
+            MessageAuthentication::Signature => self.charge_crypto(CryptoOperation::Sign, 1),
+        }
+        self.message_authentication.tag_bytes()
+    }
+
+    fn check_message_authentication(&mut self) {
+        match self.message_authentication {
+            MessageAuthentication::None => (),
+            MessageAuthentication::Mac => self.charge_crypto(CryptoOperation::Mac, 1),
+            MessageAuthentication::Signature => self.charge_crypto(CryptoOperation::Verify, 1),
+        }
+    }
+}
+
+impl<L: LedgerState> CryptoStatistics for SimulatedContext<L> {
+    fn signatures_created(&self) -> usize {
+        self.signatures_created
+    }
+
+    fn signatures_verified(&self) -> usize {
+        self.signatures_verified
+    }
+
+    fn macs_computed(&self) -> usize {
+        self.macs_computed
+    }
+}
+
+/// Snapshots are serialized ledger states, when the backend supports them.
+impl<L: LedgerState> StateSync for SimulatedContext<L> {
+    fn snapshot(&self, state: &State) -> Option<Vec<u8>> {
+        self.get_ledger_state(state)?.snapshot()
+    }
+
+    fn install_snapshot(&mut self, state: &State, snapshot: &[u8]) -> bool {
+        let ledger_state = match self.last_committed_ledger_state.parse_snapshot(snapshot) {
+            Some(ledger_state) => ledger_state,
+            None => return false,
+        };
+        let known_commands = self.last_committed_ledger_state.num_commands();
+        if ledger_state.key() != *state
+            || ledger_state.num_commands() <= known_commands
+            || ledger_state.history()[..known_commands]
+                != self.last_committed_ledger_state.history()[..]
+        {
+            return false;
+        }
+        info!(
+            "{:?} Installing snapshot of state: {:?}",
+            self.author, state
+        );
+        for (command, _) in &ledger_state.history()[known_commands..] {
+            self.commit_command(command);
+        }
+        if let Some(payload) = ledger_state.parameter_changes().max_block_payload {
+            self.max_block_payload = Some(payload);
+        }
+        // Uncommitted states descend from the previous committed state.
+        self.pending_ledger_states.clear();
+        self.last_committed_ledger_state = ledger_state;
+        true
+    }
+}
+
+impl<L: LedgerState> SMRContext for SimulatedContext<L> {}
+
+/// Contexts of simulated nodes, whatever their execution backend, so that simulations choose
+/// the backend at run time.
+pub trait SimulationContext:
+    SMRContext + ExecutionTime + ClientRequests + AuthenticatedChannels + CryptoStatistics + fmt::Debug
+{
+    fn backend(&self) -> ExecutionBackend;
+    fn last_committed_state(&self) -> State;
+    fn committed_history(&self) -> &[(Command, NodeTime)];
+    fn num_committed_commands(&self) -> usize;
+    fn committed_store(&self) -> Option<&KeyValueStore>;
+    fn mempool(&self) -> &Mempool;
+    fn clone_box(&self) -> Box<SimulationContext>;
+}
+
+impl<L: LedgerState + 'static> SimulationContext for SimulatedContext<L> {
+    fn backend(&self) -> ExecutionBackend {
+        SimulatedContext::backend(self)
+    }
+
+    fn last_committed_state(&self) -> State {
+        SimulatedContext::last_committed_state(self)
+    }
+
+    fn committed_history(&self) -> &[(Command, NodeTime)] {
+        SimulatedContext::committed_history(self)
+    }
+
+    fn num_committed_commands(&self) -> usize {
+        SimulatedContext::num_committed_commands(self)
+    }
+
+    fn committed_store(&self) -> Option<&KeyValueStore> {
+        SimulatedContext::committed_store(self)
+    }
+
+    fn mempool(&self) -> &Mempool {
+        SimulatedContext::mempool(self)
+    }
+
+    fn clone_box(&self) -> Box<SimulationContext> {
+        Box::new(self.clone())
+    }
+}
+
+// Boxed contexts forward everything to their contents.
+
+impl Clone for Box<SimulationContext> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl SimulationContext for Box<SimulationContext> {
+    fn backend(&self) -> ExecutionBackend {
+        (**self).backend()
+    }
+
+    fn last_committed_state(&self) -> State {
+        (**self).last_committed_state()
+    }
+
+    fn committed_history(&self) -> &[(Command, NodeTime)] {
+        (**self).committed_history()
+    }
+
+    fn num_committed_commands(&self) -> usize {
+        (**self).num_committed_commands()
+    }
+
+    fn committed_store(&self) -> Option<&KeyValueStore> {
+        (**self).committed_store()
+    }
+
+    fn mempool(&self) -> &Mempool {
+        (**self).mempool()
+    }
+
+    fn clone_box(&self) -> Box<SimulationContext> {
+        (**self).clone_box()
+    }
+}
+
+impl CommandFetcher for Box<SimulationContext> {
+    fn fetch(&mut self, time: NodeTime) -> Option<Command> {
+        (**self).fetch(time)
+    }
+
+    fn is_batch_full(&mut self, time: NodeTime) -> bool {
+        (**self).is_batch_full(time)
+    }
+
+    fn is_batch_empty(&mut self, time: NodeTime) -> bool {
+        (**self).is_batch_empty(time)
+    }
+
+    fn release(&mut self, command: &Command) {
+        (**self).release(command)
+    }
+
+    fn next_arrival(&mut self, time: NodeTime) -> Option<NodeTime> {
+        (**self).next_arrival(time)
+    }
+}
+
+impl StateComputer for Box<SimulationContext> {
+    fn compute(
+        &mut self,
+        base_state: &State,
+        command: Command,
+        time: NodeTime,
+        previous_author: Option<Author>,
+        previous_voters: Vec<Author>,
+    ) -> Option<State> {
+        (**self).compute(base_state, command, time, previous_author, previous_voters)
+    }
+
+    fn execution_duration(&self, command: &Command) -> Duration {
+        (**self).execution_duration(command)
+    }
+
+    fn is_valid(&self, command: &Command, time: NodeTime) -> bool {
+        (**self).is_valid(command, time)
+    }
+}
+
+impl StateFinalizer for Box<SimulationContext> {
+    fn commit(&mut self, state: &State, certificate: Option<&QuorumCertificate>) {
+        (**self).commit(state, certificate)
+    }
+
+    fn discard(&mut self, state: &State) {
+        (**self).discard(state)
+    }
+}
+
+impl EpochReader for Box<SimulationContext> {
+    fn read_epoch_id(&self, state: &State) -> EpochId {
+        (**self).read_epoch_id(state)
+    }
+
+    fn configuration(&self, state: &State) -> EpochConfiguration {
+        (**self).configuration(state)
+    }
+
+    fn parameter_changes(&self, state: &State) -> ParameterChange {
+        EpochReader::parameter_changes(&**self, state)
+    }
+}
+
+impl ExecutionTime for Box<SimulationContext> {
+    fn take_execution_time(&mut self) -> Duration {
+        (**self).take_execution_time()
+    }
+}
+
+impl ClientRequests for Box<SimulationContext> {
+    fn submit_request(&mut self, request: ClientRequest, clock: NodeTime) -> bool {
+        (**self).submit_request(request, clock)
+    }
+}
+
+impl SafetyStorage for Box<SimulationContext> {
+    fn save_safety_data(&mut self, data: SafetyData) {
+        (**self).save_safety_data(data)
+    }
+
+    fn load_safety_data(&self) -> Option<SafetyData> {
+        (**self).load_safety_data()
+    }
+}
+
+impl CryptoCost for Box<SimulationContext> {
+    fn charge_crypto(&mut self, operation: CryptoOperation, count: usize) {
+        (**self).charge_crypto(operation, count)
+    }
+}
+
+impl AuthenticatedChannels for Box<SimulationContext> {
+    fn authenticate_message(&mut self, recipients: usize) -> usize {
+        (**self).authenticate_message(recipients)
+    }
+
+    fn check_message_authentication(&mut self) {
+        (**self).check_message_authentication()
+    }
+}
+
+impl CryptoStatistics for Box<SimulationContext> {
+    fn signatures_created(&self) -> usize {
+        (**self).signatures_created()
+    }
+
+    fn signatures_verified(&self) -> usize {
+        (**self).signatures_verified()
+    }
+
+    fn macs_computed(&self) -> usize {
+        (**self).macs_computed()
+    }
+}
+
+impl StateSync for Box<SimulationContext> {
+    fn snapshot(&self, state: &State) -> Option<Vec<u8>> {
+        (**self).snapshot(state)
+    }
+
+    fn install_snapshot(&mut self, state: &State, snapshot: &[u8]) -> bool {
+        (**self).install_snapshot(state, snapshot)
+    }
 }
 
-impl SMRContext for SimulatedContext {}
+impl SMRContext for Box<SimulationContext> {}
@@ -0,0 +1,423 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Simulated networks of LibraBFT nodes, as configured by the parameters of a run.
+//!
+//! Experiment drivers, such as scenarios, sweeps, or the model checker, build their simulators
+//! with `make_simulator`. Deployments on real sockets reuse `make_context` and `make_node`.
+
+use super::*;
+use bft_simulator_runtime::{
+    clients::{ClientModel, Clients},
+    corruption::CorruptionModel,
+    nemesis::{Fault, FaultSchedule},
+    network_trace::NetworkTrace,
+    quota::MessageQuota,
+    simulator::GlobalTime,
+    statistics::MeasurementPhases,
+    termination::TerminationCondition,
+    workload::{ArrivalProcess, WorkloadGenerator},
+};
+use data_sync::*;
+use simulated_context::NullLedgerState;
+
+pub type Simulator = simulator::Simulator<
+    NodeState,
+    Box<SimulationContext>,
+    DataSyncNotification,
+    DataSyncRequest,
+    DataSyncResponse,
+>;
+
+/// Parameters of a single simulation run.
+#[derive(Clone, Debug)]
+pub struct SimulationParameters {
+    pub max_clock: GlobalTime,
+    /// Stop once a node has committed this many blocks.
+    pub max_commits: Option<usize>,
+    /// Stop once a node has gone this many rounds past the latest commit.
+    pub max_rounds_without_progress: Option<usize>,
+    /// Fail once no block has been committed for this long while a quorum of honest nodes
+    /// is running.
+    pub max_time_without_commit: Option<Duration>,
+    /// Report the state of the nodes and the dropped messages once no block has been
+    /// committed for this long, if set.
+    pub stall_threshold: Option<Duration>,
+    /// Part of the run measured by the statistics. A bounded window ends the run in place of
+    /// `max_clock`.
+    pub measurement: MeasurementPhases,
+    pub mean: f64,
+    pub variance: f64,
+    pub loss_rate: f64,
+    /// Changes of the network delays and losses over time.
+    pub network_trace: NetworkTrace,
+    pub nodes: usize,
+    pub commands_per_epoch: usize,
+    pub target_commit_interval: Duration,
+    /// Arrivals of transactions at each node. Blocks are empty if `None`.
+    pub arrivals: Option<ArrivalProcess>,
+    /// Arrivals of targeted transactions, submitted to all the nodes at once. Byzantine nodes
+    /// leave them out of their proposals.
+    pub targeted_arrivals: Option<ArrivalProcess>,
+    /// Simulated clients sending requests to the nodes and waiting for their commit, if any.
+    pub clients: Option<ClientModel>,
+    /// Maximal number of transactions per block.
+    pub max_batch_size: usize,
+    /// Size of each transaction, in bytes.
+    pub transaction_size: usize,
+    /// Maximal size of the transactions of a block, in bytes, if any.
+    pub max_block_payload: Option<usize>,
+    /// How long leaders may wait for a full block before proposing. Leaders propose
+    /// immediately if `None`.
+    pub max_batch_wait: Option<Duration>,
+    /// What leaders propose when they have no transactions.
+    pub empty_block_policy: EmptyBlockPolicy,
+    /// Number of chained blocks that leaders propose in each round.
+    pub blocks_per_round: usize,
+    /// Maximal number of blocks without a QC that nodes keep per author, if bounded.
+    pub max_uncertified_blocks: Option<usize>,
+    /// How nodes execute the committed commands.
+    pub backend: ExecutionBackend,
+    /// Execution of transactions with the key-value backend. Defaults apply if absent.
+    pub execution: Option<key_value_store::ExecutionModel>,
+    /// Time for a node to execute a block before voting on it.
+    pub execution_latency: Duration,
+    /// Whether nodes vote before executing blocks, then wait for execution to deliver commits.
+    pub pipelined_execution: bool,
+    pub delta: Duration,
+    pub gamma: f64,
+    pub lambda: f64,
+    /// Largest fraction of a round duration added at random to the rounds of each node.
+    pub round_jitter: f64,
+    /// When nodes broadcast their proposal or timeout of a round again, if at all.
+    pub retransmission: Option<RetransmissionPolicy>,
+    /// When nodes switch to longer rounds, if at all.
+    pub hybrid_pacemaker: Option<HybridPolicy>,
+    /// Nodes that nodes send their records to when a round starts.
+    pub peer_selection: PeerSelection,
+    /// How nodes suspect the peers that they skip when synchronizing, if at all.
+    pub failure_detector: Option<FailureDetectorPolicy>,
+    /// How nodes push records to random peers and pull from them, instead of broadcasting, if
+    /// at all.
+    pub push_pull: Option<PushPullPolicy>,
+    /// Latest version of the data sync protocol supported by the nodes, unless overridden.
+    pub data_sync_version: u32,
+    /// Whether nodes avoid sending records to the nodes known to hold them.
+    pub suppress_redundant_records: bool,
+    /// Whether nodes check all the records of a message before processing any of them.
+    pub strict_verification: bool,
+    /// Whether lagging nodes resume from checkpoints of the executed state.
+    pub state_sync: bool,
+    /// Damage of the messages in transit.
+    pub message_corruption: CorruptionModel,
+    /// Messages that nodes accept from each sender per period.
+    pub message_quota: MessageQuota,
+    pub commit_rule: CommitRule,
+    /// Recipients of the votes.
+    pub vote_delivery: VoteDelivery,
+    /// How long QC assemblers wait for the votes of all validators, which commit a block
+    /// immediately, if at all.
+    pub fast_path_wait: Option<Duration>,
+    /// Whether QCs carry one aggregate signature instead of one signature per vote.
+    pub aggregate_signatures: bool,
+    /// Whether QCs carry a threshold signature combined from the shares of the votes.
+    pub threshold_signatures: bool,
+    /// Time charged to nodes per cryptographic operation.
+    pub crypto_costs: CryptoCosts,
+    /// Authentication of the network messages.
+    pub message_authentication: MessageAuthentication,
+    /// Seed of the random network delays and losses.
+    pub seed: u64,
+    /// Pacemaker parameters of specific nodes, replacing `delta`, `gamma`, and `lambda`.
+    pub node_overrides: BTreeMap<Author, scenario::NodeOverrides>,
+    /// Time at which some nodes crash.
+    pub crashes: BTreeMap<Author, GlobalTime>,
+    /// Time at which some crashed nodes restart with their durable state.
+    pub recoveries: BTreeMap<Author, GlobalTime>,
+    /// Faults injected at given times, e.g. the faults of whole failure domains or eclipses.
+    pub scheduled_faults: Vec<(GlobalTime, Fault)>,
+    /// Nodes counted as Byzantine in the chain quality.
+    pub byzantine: BTreeSet<Author>,
+    /// Nodes that follow the committed chain but never vote or propose, and are never validators.
+    pub observers: BTreeSet<Author>,
+    /// Voting rights of the nodes that do not have a single vote.
+    pub stakes: BTreeMap<Author, usize>,
+    /// Validators of each epoch, the last set applying to later epochs. All nodes if empty.
+    pub validator_sets: Vec<BTreeSet<Author>>,
+    /// Validators from the first block proposed at or after each time, in increasing order of
+    /// time. Each change starts a new epoch.
+    pub reconfigurations: Vec<(GlobalTime, BTreeSet<Author>)>,
+    /// Governance changes of the protocol parameters, proposed from each time on, in increasing
+    /// order of time. Each change takes effect at the start of the epoch after its commit.
+    pub parameter_changes: Vec<(GlobalTime, ParameterChange)>,
+    /// Time at which some nodes join the network, instead of the beginning of the run.
+    pub join_times: BTreeMap<Author, GlobalTime>,
+    /// Whether nodes keep a log of their decisions.
+    pub record_decisions: bool,
+    /// Whether the lemmas of the LibraBFT paper are checked after every commit. Each check
+    /// scans all the QCs of the nodes.
+    pub check_lemmas: bool,
+}
+
+impl SimulationParameters {
+    pub fn termination(&self) -> TerminationCondition {
+        TerminationCondition {
+            max_committed_blocks: self.max_commits,
+            max_rounds_without_progress: self.max_rounds_without_progress,
+            max_time_without_commit: self.max_time_without_commit,
+            stop_on_invariant_violation: true,
+            ..TerminationCondition::at_time(self.end_clock())
+        }
+    }
+
+    /// Time at which the run stops: the end of the measurement window, if bounded.
+    pub fn end_clock(&self) -> GlobalTime {
+        self.measurement.end().unwrap_or(self.max_clock)
+    }
+
+    /// Execution models only apply to the key-value backend, which executes transactions.
+    pub fn check_execution(&self) -> Result<()> {
+        ensure!(
+            self.execution.is_none() || self.backend == ExecutionBackend::KeyValue,
+            "Only the key-value backend executes transactions, not the {} backend",
+            self.backend
+        );
+        Ok(())
+    }
+
+    /// The fast path is only safe under synchrony: a node that times out before receiving a QC
+    /// with all the votes may lock on a conflicting branch. Runs with the fast path must thus
+    /// deliver every message in time, without losses, partitions, eclipses, or clock jumps.
+    pub fn check_fast_path(&self) -> Result<()> {
+        if self.fast_path_wait.is_none() {
+            return Ok(());
+        }
+        ensure!(
+            self.loss_rate == 0.0
+                && self
+                    .network_trace
+                    .changes()
+                    .iter()
+                    .all(|change| change.loss_rate.map_or(true, |x| x == 0.0)),
+            "The fast path requires a network without losses"
+        );
+        for (_, fault) in &self.scheduled_faults {
+            match fault {
+                Fault::Partition(_) | Fault::Eclipse(..) | Fault::ClockJump(..) => {
+                    bail!("The fast path cannot be combined with {}", fault)
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The context of a node, as configured by the simulation parameters, with the execution
+/// backend of the parameters.
+pub fn make_context(parameters: &SimulationParameters, author: Author) -> Box<SimulationContext> {
+    let (nodes, commands_per_epoch) = (parameters.nodes, parameters.commands_per_epoch);
+    match parameters.backend {
+        ExecutionBackend::Ledger => Box::new(configure_context(
+            parameters,
+            author,
+            SimulatedContext::new(author, nodes, commands_per_epoch),
+        )),
+        ExecutionBackend::KeyValue => Box::new(configure_context(
+            parameters,
+            author,
+            KeyValueContext::with_execution_model(
+                author,
+                nodes,
+                commands_per_epoch,
+                parameters.execution.clone().unwrap_or_default(),
+            ),
+        )),
+        ExecutionBackend::Null => Box::new(configure_context(
+            parameters,
+            author,
+            SimulatedContext::from_ledger_state(
+                author,
+                nodes,
+                commands_per_epoch,
+                NullLedgerState::default(),
+            ),
+        )),
+    }
+}
+
+fn configure_context<L: LedgerState>(
+    parameters: &SimulationParameters,
+    author: Author,
+    context: SimulatedContext<L>,
+) -> SimulatedContext<L> {
+    let mut context = context
+        .with_validator_sets(parameters.validator_sets.clone())
+        .with_stakes(parameters.stakes.clone())
+        .with_reconfigurations(
+            parameters
+                .reconfigurations
+                .iter()
+                .map(|(time, validators)| (NodeTime(time.0), validators.clone()))
+                .collect(),
+        )
+        .with_parameter_changes(
+            parameters
+                .parameter_changes
+                .iter()
+                .map(|(time, change)| (NodeTime(time.0), *change))
+                .collect(),
+        )
+        .with_execution_latency(parameters.execution_latency)
+        .with_block_payload(parameters.transaction_size, parameters.max_block_payload)
+        .with_crypto_costs(parameters.crypto_costs.clone())
+        .with_message_authentication(parameters.message_authentication)
+        .with_invalid_payloads(
+            parameters
+                .node_overrides
+                .get(&author)
+                .map_or(false, |x| x.invalid_payloads),
+        )
+        .with_cpu_speed(
+            parameters
+                .node_overrides
+                .get(&author)
+                .and_then(|x| x.cpu_speed)
+                .unwrap_or(1.0),
+        );
+    if parameters.clients.is_some() {
+        context = context.with_client_requests(parameters.max_batch_size);
+    }
+    if let Some(process) = &parameters.targeted_arrivals {
+        // All nodes receive the same arrivals.
+        let workload = WorkloadGenerator::new(process.clone(), parameters.seed ^ (1 << 31));
+        context = context
+            .with_targeted_workload(workload, parameters.max_batch_size)
+            .with_censorship(parameters.byzantine.contains(&author));
+    }
+    match &parameters.arrivals {
+        Some(process) => {
+            // Nodes receive independent arrivals, also independent from the network.
+            let seed = parameters.seed ^ ((author.0 as u64 + 1) << 32);
+            let workload = WorkloadGenerator::new(process.clone(), seed);
+            context.with_workload(workload, parameters.max_batch_size)
+        }
+        None => context,
+    }
+}
+
+/// A node started at `clock`, as configured by the simulation parameters.
+pub fn make_node<Context: SimulationContext>(
+    parameters: &SimulationParameters,
+    author: Author,
+    context: &Context,
+    clock: NodeTime,
+) -> NodeState {
+    let overrides = parameters.node_overrides.get(&author);
+    NodeState::new(
+        author,
+        context.last_committed_state(),
+        clock,
+        parameters.target_commit_interval,
+        overrides.and_then(|x| x.delta).unwrap_or(parameters.delta),
+        overrides.and_then(|x| x.gamma).unwrap_or(parameters.gamma),
+        overrides
+            .and_then(|x| x.lambda)
+            .unwrap_or(parameters.lambda),
+        parameters.commit_rule,
+        context,
+    )
+    .with_max_batch_wait(parameters.max_batch_wait)
+    .with_empty_block_policy(parameters.empty_block_policy)
+    .with_round_jitter(parameters.round_jitter, parameters.seed)
+    .with_retransmission(parameters.retransmission)
+    .with_hybrid_pacemaker(parameters.hybrid_pacemaker)
+    .with_peer_selection(parameters.peer_selection.clone())
+    .with_push_pull(parameters.push_pull)
+    .with_failure_detector(parameters.failure_detector)
+    .with_data_sync_version(
+        overrides
+            .and_then(|x| x.data_sync_version)
+            .unwrap_or(parameters.data_sync_version),
+    )
+    .with_redundancy_suppression(parameters.suppress_redundant_records)
+    .with_strict_verification(
+        overrides
+            .and_then(|x| x.strict_verification)
+            .unwrap_or(parameters.strict_verification),
+    )
+    .with_state_sync(
+        overrides
+            .and_then(|x| x.state_sync)
+            .unwrap_or(parameters.state_sync),
+    )
+    .with_observer(parameters.observers.contains(&author))
+    .with_durability(
+        overrides
+            .and_then(|x| x.durable_safety_data)
+            .unwrap_or(true),
+        overrides.and_then(|x| x.durable_records).unwrap_or(true),
+    )
+    .with_blocks_per_round(parameters.blocks_per_round)
+    .with_max_uncertified_blocks(parameters.max_uncertified_blocks)
+    .with_pipelined_execution(parameters.pipelined_execution)
+    .with_vote_delivery(parameters.vote_delivery)
+    .with_fast_path(parameters.fast_path_wait)
+    .with_aggregate_signatures(parameters.aggregate_signatures)
+    .with_threshold_signatures(parameters.threshold_signatures)
+    .with_decision_log(parameters.record_decisions)
+}
+
+pub fn make_simulator(parameters: &SimulationParameters) -> Simulator {
+    #[cfg(feature = "ed25519")]
+    bft_simulator_runtime::ed25519::generate_keys(parameters.nodes, parameters.seed);
+    let context_factory = |author: Author, _num_nodes| make_context(parameters, author);
+    let node_factory = |author: Author, context: &Box<SimulationContext>, clock: NodeTime| {
+        make_node(parameters, author, context, clock)
+    };
+    let delay_distribution = simulator::RandomDelay::new(parameters.mean, parameters.variance);
+    let mut sim = Simulator::new(
+        parameters.nodes,
+        delay_distribution,
+        parameters.loss_rate,
+        parameters.seed,
+        context_factory,
+        node_factory,
+    );
+    sim.set_network_trace(parameters.network_trace.clone());
+    sim.set_message_corruption(parameters.message_corruption);
+    sim.set_message_quota(parameters.message_quota);
+    if let Some(threshold) = parameters.stall_threshold {
+        sim.diagnose_stalls(threshold);
+    }
+    for (author, crash_time) in &parameters.crashes {
+        sim.crash_node(*author, *crash_time);
+    }
+    for (author, recovery_time) in &parameters.recoveries {
+        sim.recover_node_at(*author, *recovery_time);
+    }
+    if !parameters.scheduled_faults.is_empty() {
+        let faults = parameters.scheduled_faults.clone();
+        sim.add_nemesis(Box::new(FaultSchedule::new(faults)));
+    }
+    for (author, join_time) in &parameters.join_times {
+        sim.join_node_at(*author, *join_time);
+    }
+    for (author, stake) in &parameters.stakes {
+        sim.set_stake(*author, *stake);
+    }
+    for author in &parameters.byzantine {
+        sim.set_byzantine(*author);
+    }
+    if let Some(model) = &parameters.clients {
+        sim.set_clients(Clients::new(model.clone(), parameters.nodes));
+    }
+    sim.set_measurement_phases(parameters.measurement);
+    for invariant in invariants::builtin_invariants() {
+        sim.add_invariant(invariant);
+    }
+    if parameters.check_lemmas {
+        sim.add_invariant(Box::new(invariants::PaperLemmas));
+    }
+    sim
+}
@@ -2,25 +2,55 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
-use base_types::{Command, State};
+use base_types::{Command, CryptoOperation, ParameterChange, SafetyData, State};
 use record::QuorumCertificate;
 
+#[cfg(test)]
+#[path = "unit_tests/smr_context_tests.rs"]
+mod smr_context_tests;
+
+// The traits take the states `S` and commands `C` of the application as parameters, but only
+// their defaults `State` and `Command` are supported by the protocol: `NodeState`,
+// `RecordStore`, the records, and the data sync messages are written for them, and commit
+// certificates are quorum certificates of such records. Implementations for other types cannot
+// run LibraBFT nodes; the consensus core would have to be made generic first.
+
 // -- BEGIN FILE smr_apis --
-pub trait CommandFetcher {
-    /// How to fetch valid commands to submit to the consensus protocol.
-    fn fetch(&mut self) -> Option<Command>;
+pub trait CommandFetcher<C = Command> {
+    /// How to fetch valid commands to submit to the consensus protocol at the given time.
+    fn fetch(&mut self, time: NodeTime) -> Option<C>;
+
+    /// Whether a command fetched at the given time would be as large as allowed.
+    fn is_batch_full(&mut self, _time: NodeTime) -> bool {
+        true
+    }
+
+    /// Whether a command fetched at the given time would carry no transactions.
+    fn is_batch_empty(&mut self, _time: NodeTime) -> bool {
+        false
+    }
+
+    /// Report that a fetched command can no longer be committed, so that its transactions may
+    /// be fetched again.
+    fn release(&mut self, _command: &C) {}
+
+    /// Time after the given time when new transactions are expected, if known, so that a
+    /// leader waiting for transactions wakes up then.
+    fn next_arrival(&mut self, _time: NodeTime) -> Option<NodeTime> {
+        None
+    }
 }
 
-pub trait StateComputer {
+pub trait StateComputer<S = State, C = Command> {
     /// How to execute a command and obtain the next state.
     /// If execution fails, the value `None` is returned, meaning that the
     /// command should be rejected.
     fn compute(
         &mut self,
         // The state before executing the command.
-        base_state: &State,
+        base_state: &S,
         // Command to execute.
-        command: Command,
+        command: C,
         // Time associated to this execution step, in agreement with
         // other consensus nodes.
         time: NodeTime,
@@ -28,26 +58,81 @@ pub trait StateComputer {
         previous_author: Option<Author>,
         // Suggest to reward the voters of the previous block, if any.
         previous_voters: Vec<Author>,
-    ) -> Option<State>;
+    ) -> Option<S>;
+
+    /// How long it takes to execute a command before voting on it.
+    fn execution_duration(&self, _command: &C) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Whether a command proposed at `time` is valid. Nodes refuse to vote for blocks with
+    /// invalid commands, before executing them.
+    fn is_valid(&self, _command: &C, _time: NodeTime) -> bool {
+        true
+    }
 }
 
 /// How to communicate that a state was committed or discarded.
-pub trait StateFinalizer {
+pub trait StateFinalizer<S = State> {
     /// Report that a state was committed, together with a commit certificate.
-    fn commit(&mut self, state: &State, commit_certificate: Option<&QuorumCertificate>);
+    fn commit(&mut self, state: &S, commit_certificate: Option<&QuorumCertificate>);
 
     /// Report that a state was discarded.
-    fn discard(&mut self, state: &State);
+    fn discard(&mut self, state: &S);
 }
 
 /// How to communicate that a state was committed or discarded.
-pub trait EpochReader {
+pub trait EpochReader<S = State> {
     /// Read the id of the epoch in a state.
-    fn read_epoch_id(&self, state: &State) -> EpochId;
+    fn read_epoch_id(&self, state: &S) -> EpochId;
 
     /// Return the configuration (i.e. voting rights) for the epoch starting at a given state.
-    fn configuration(&self, state: &State) -> EpochConfiguration;
+    fn configuration(&self, state: &S) -> EpochConfiguration;
+
+    /// Return the changes of protocol parameters for the epoch starting at a given state,
+    /// relative to the initial parameters of the nodes.
+    fn parameter_changes(&self, _state: &S) -> ParameterChange {
+        ParameterChange::default()
+    }
 }
 
-pub trait SMRContext: CommandFetcher + StateComputer + StateFinalizer + EpochReader {}
+/// Durable storage of the voting constraints, separate from the records of the node.
+pub trait SafetyStorage {
+    /// Persist the voting constraints before acting on them.
+    fn save_safety_data(&mut self, data: SafetyData);
+
+    /// Read the latest persisted voting constraints, if any.
+    fn load_safety_data(&self) -> Option<SafetyData>;
+}
+
+/// Time spent by the node on cryptography.
+pub trait CryptoCost {
+    /// Charge the time of `count` operations of the given kind.
+    fn charge_crypto(&mut self, _operation: CryptoOperation, _count: usize) {}
+}
+
+/// Checkpoints of executed states, so that lagging nodes do not execute old blocks again.
+pub trait StateSync<S = State> {
+    /// Serialize the executed state `state`, if supported.
+    fn snapshot(&self, _state: &S) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Replace the latest committed state by a snapshot of `state`, taken by another node.
+    /// Return false if the snapshot is not supported or does not match `state`.
+    fn install_snapshot(&mut self, _state: &S, _snapshot: &[u8]) -> bool {
+        false
+    }
+}
+
+pub trait SMRContext<S = State, C = Command>:
+    CommandFetcher<C>
+    + StateComputer<S, C>
+    + StateFinalizer<S>
+    + EpochReader<S>
+    + SafetyStorage
+    + CryptoCost
+    + StateSync<S>
+{
+}
 // -- END FILE --
@@ -0,0 +1,284 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Live streaming of the events of a run over WebSocket, with the `websocket` feature, or as an
+//! asynchronous stream, with the `stream` feature.
+//!
+//! The run proceeds in steps of one unit of simulated time. After each step, the round changes,
+//! commits, and nodes going down or up during the step are sent to the client as JSON, in the
+//! order of simulated time, and paced to a playback speed in units of simulated time per second.
+//! Clients may change the speed at any time by sending `{"speed": SPEED}`.
+//!
+//! As a `futures::Stream`, the run only advances when the consumer polls for the next event, so
+//! that slow consumers hold back the simulation instead of buffering its events.
+
+use super::*;
+use bft_simulator_runtime::termination::StopReason;
+#[cfg(feature = "stream")]
+use futures::{task, Async, Poll, Stream};
+#[cfg(feature = "websocket")]
+use serde::Deserialize;
+use serde::Serialize;
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+#[cfg(feature = "websocket")]
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration as StdDuration, Instant},
+};
+#[cfg(feature = "websocket")]
+use tungstenite::{Message, WebSocket};
+
+#[cfg(test)]
+#[path = "unit_tests/streaming_tests.rs"]
+mod streaming_tests;
+
+/// An event of a run, as streamed to clients.
+#[derive(PartialEq, Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum StreamEvent {
+    /// A node entered a new round.
+    Round {
+        time: i64,
+        node: usize,
+        round: usize,
+    },
+    /// A node committed a block.
+    Commit {
+        time: i64,
+        node: usize,
+        round: usize,
+        proposer: usize,
+    },
+    /// A node crashed.
+    NodeDown { time: i64, node: usize },
+    /// A node started late or recovered.
+    NodeUp { time: i64, node: usize },
+    /// The run is over.
+    End { time: i64, stop_reason: String },
+}
+
+impl StreamEvent {
+    pub fn time(&self) -> i64 {
+        match self {
+            StreamEvent::Round { time, .. }
+            | StreamEvent::Commit { time, .. }
+            | StreamEvent::NodeDown { time, .. }
+            | StreamEvent::NodeUp { time, .. }
+            | StreamEvent::End { time, .. } => *time,
+        }
+    }
+}
+
+/// Message sent by clients to change the playback speed.
+#[cfg(feature = "websocket")]
+#[derive(Deserialize)]
+struct SpeedChange {
+    speed: f64,
+}
+
+/// A run producing the events of each step.
+pub struct EventStream {
+    parameters: SimulationParameters,
+    simulator: Simulator,
+    clock: i64,
+    /// Active round and whether each node was running, at the end of the previous step.
+    rounds: Vec<Round>,
+    running: Vec<bool>,
+    /// Commits reported by the simulator during the current step.
+    commits: Rc<RefCell<Vec<StreamEvent>>>,
+    /// Events of the latest step not polled yet.
+    pending: VecDeque<StreamEvent>,
+    is_over: bool,
+}
+
+impl EventStream {
+    pub fn new(parameters: SimulationParameters) -> Self {
+        let mut simulator = make_simulator(&parameters);
+        let commits = Rc::new(RefCell::new(Vec::new()));
+        let reported_commits = commits.clone();
+        simulator.add_commit_listener(Box::new(
+            move |author: Author, block: &CommittedBlock, time: simulator::GlobalTime| {
+                reported_commits.borrow_mut().push(StreamEvent::Commit {
+                    time: time.0,
+                    node: author.0,
+                    round: block.round.0,
+                    proposer: block.proposer.0,
+                });
+            },
+        ));
+        let nodes = simulator.simulated_nodes();
+        let rounds = nodes.iter().map(|node| node.active_round()).collect();
+        let running = nodes
+            .iter()
+            .map(|node| node.is_running(simulator::GlobalTime(0)))
+            .collect();
+        EventStream {
+            parameters,
+            simulator,
+            clock: 0,
+            rounds,
+            running,
+            commits,
+            pending: VecDeque::new(),
+            is_over: false,
+        }
+    }
+
+    /// Process the next step and return its events, or `None` once the run is over.
+    pub fn next_events(&mut self) -> Option<Vec<StreamEvent>> {
+        if self.is_over {
+            return None;
+        }
+        let until = std::cmp::min(self.clock + 1, self.parameters.end_clock().0);
+        let mut termination = self.parameters.termination();
+        termination.max_clock = Some(simulator::GlobalTime(until));
+        let stop_reason = self.simulator.run(&termination, None);
+        self.clock = until;
+        let mut events = std::mem::replace(&mut *self.commits.borrow_mut(), Vec::new());
+        for (index, node) in self.simulator.simulated_nodes().iter().enumerate() {
+            let running = node.is_running(simulator::GlobalTime(until));
+            if running != self.running[index] {
+                self.running[index] = running;
+                events.push(if running {
+                    StreamEvent::NodeUp {
+                        time: until,
+                        node: index,
+                    }
+                } else {
+                    StreamEvent::NodeDown {
+                        time: until,
+                        node: index,
+                    }
+                });
+            }
+            let round = node.active_round();
+            if round != self.rounds[index] {
+                self.rounds[index] = round;
+                events.push(StreamEvent::Round {
+                    time: until,
+                    node: index,
+                    round: round.0,
+                });
+            }
+        }
+        match stop_reason {
+            StopReason::MaxClock if until < self.parameters.end_clock().0 => (),
+            stop_reason => {
+                self.is_over = true;
+                events.push(StreamEvent::End {
+                    time: until,
+                    stop_reason: format!("{:?}", stop_reason),
+                });
+            }
+        }
+        // Commits were reported at the time of their events, before the end of the step.
+        events.sort_by_key(StreamEvent::time);
+        Some(events)
+    }
+}
+
+/// Events one at a time, in the order of `next_events`.
+#[cfg(feature = "stream")]
+impl Stream for EventStream {
+    type Item = StreamEvent;
+    type Error = failure::Error;
+
+    fn poll(&mut self) -> Poll<Option<StreamEvent>, failure::Error> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Async::Ready(Some(event)));
+        }
+        match self.next_events() {
+            Some(events) => {
+                self.pending.extend(events);
+                match self.pending.pop_front() {
+                    Some(event) => Ok(Async::Ready(Some(event))),
+                    None => {
+                        // Yield after a quiet step, so that long runs do not starve the
+                        // other tasks of the executor.
+                        task::current().notify();
+                        Ok(Async::NotReady)
+                    }
+                }
+            }
+            None => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+/// Wall-clock time between two checks for messages of the client.
+#[cfg(feature = "websocket")]
+const POLL_INTERVAL: StdDuration = StdDuration::from_millis(50);
+
+/// The speed requested by the client since the last call, if any.
+#[cfg(feature = "websocket")]
+fn poll_speed(socket: &mut WebSocket<TcpStream>) -> Result<Option<f64>> {
+    let mut speed = None;
+    loop {
+        match socket.read_message() {
+            Ok(Message::Text(text)) => match serde_json::from_str::<SpeedChange>(&text) {
+                Ok(change) if change.speed >= 0.0 => speed = Some(change.speed),
+                _ => warn!("Ignoring an invalid message from the client: {}", text),
+            },
+            Ok(_) => (),
+            // Nothing left to read.
+            Err(tungstenite::Error::Io(ref error))
+                if error.kind() == io::ErrorKind::WouldBlock
+                    || error.kind() == io::ErrorKind::TimedOut =>
+            {
+                return Ok(speed)
+            }
+            Err(error) => bail!("Lost the client: {}", error),
+        }
+    }
+}
+
+/// Wait for a client on `address`, then stream a run to it at `speed` units of simulated time
+/// per second. A speed of 0 streams events as fast as possible.
+#[cfg(feature = "websocket")]
+pub fn serve(parameters: SimulationParameters, address: &SocketAddr, speed: f64) -> Result<()> {
+    ensure!(speed >= 0.0, "Playback speeds cannot be negative");
+    let listener = TcpListener::bind(address)?;
+    let (stream, client) = listener.accept()?;
+    info!("Streaming a run to {}", client);
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(error) => bail!("WebSocket handshake failed: {}", error),
+    };
+    // Poll the client for speed changes without blocking the stream.
+    socket
+        .get_mut()
+        .set_read_timeout(Some(StdDuration::from_millis(1)))?;
+    let mut speed = speed;
+    // Simulated time and wall-clock time at the last change of speed.
+    let mut origin = (0, Instant::now());
+    let mut last_poll = Instant::now();
+    let mut events = EventStream::new(parameters);
+    while let Some(step) = events.next_events() {
+        if last_poll.elapsed() >= POLL_INTERVAL {
+            last_poll = Instant::now();
+            if let Some(new_speed) = poll_speed(&mut socket)? {
+                speed = new_speed;
+                origin = (events.clock - 1, Instant::now());
+            }
+        }
+        for event in step {
+            if speed > 0.0 {
+                let delay = (event.time() - origin.0) as f64 / speed;
+                let deadline = origin.1 + StdDuration::from_micros((delay * 1e6) as u64);
+                let now = Instant::now();
+                if deadline > now {
+                    thread::sleep(deadline - now);
+                }
+            }
+            if let Err(error) = socket.write_message(Message::Text(serde_json::to_string(&event)?))
+            {
+                bail!("Lost the client: {}", error);
+            }
+        }
+    }
+    // The client may already be gone.
+    let _ = socket.close(None);
+    Ok(())
+}
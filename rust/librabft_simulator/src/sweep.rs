@@ -0,0 +1,206 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bft_simulator_runtime::{html_report::HtmlReport, statistics::RunSummary};
+use std::{fmt::Display, io, str::FromStr};
+
+#[cfg(test)]
+#[path = "unit_tests/sweep_tests.rs"]
+mod sweep_tests;
+
+/// Values to explore for each swept parameter.
+#[derive(Clone, Debug)]
+pub struct SweepGrid {
+    pub delta: Vec<Duration>,
+    pub gamma: Vec<f64>,
+    pub lambda: Vec<f64>,
+    pub nodes: Vec<usize>,
+    pub loss_rate: Vec<f64>,
+    pub commit_rule: Vec<CommitRule>,
+}
+
+/// Outcome of one configuration of a sweep.
+#[derive(Clone, Debug)]
+pub struct SweepResult {
+    pub parameters: SimulationParameters,
+    pub summary: RunSummary,
+}
+
+impl SweepGrid {
+    /// A grid made of a single configuration.
+    pub fn single(parameters: &SimulationParameters) -> Self {
+        SweepGrid {
+            delta: vec![parameters.delta],
+            gamma: vec![parameters.gamma],
+            lambda: vec![parameters.lambda],
+            nodes: vec![parameters.nodes],
+            loss_rate: vec![parameters.loss_rate],
+            commit_rule: vec![parameters.commit_rule],
+        }
+    }
+
+    /// Enumerate all the combinations of swept values, using `base` for the other parameters.
+    pub fn configurations(&self, base: &SimulationParameters) -> Vec<SimulationParameters> {
+        let mut configurations = Vec::new();
+        for nodes in &self.nodes {
+            for delta in &self.delta {
+                for gamma in &self.gamma {
+                    for lambda in &self.lambda {
+                        for loss_rate in &self.loss_rate {
+                            for commit_rule in &self.commit_rule {
+                                configurations.push(SimulationParameters {
+                                    nodes: *nodes,
+                                    delta: *delta,
+                                    gamma: *gamma,
+                                    lambda: *lambda,
+                                    loss_rate: *loss_rate,
+                                    commit_rule: *commit_rule,
+                                    ..base.clone()
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        configurations
+    }
+}
+
+/// Parse a comma-separated list of values.
+pub fn parse_list<T>(value: &str) -> Vec<T>
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    value
+        .split(',')
+        .map(|x| x.trim().parse::<T>().unwrap())
+        .collect()
+}
+
+/// Run one simulation per configuration of the grid.
+pub fn run_sweep(base: &SimulationParameters, grid: &SweepGrid) -> Vec<SweepResult> {
+    grid.configurations(base)
+        .into_iter()
+        .map(|parameters| {
+            info!("Sweeping configuration {:?}", parameters);
+            SweepResult {
+                summary: run_summary(&parameters),
+                parameters,
+            }
+        })
+        .collect()
+}
+
+/// Run a simulation and summarize it.
+pub fn run_summary(parameters: &SimulationParameters) -> RunSummary {
+    let mut sim = make_simulator(parameters);
+    sim.run(&parameters.termination(), None);
+    sim.summary()
+}
+
+/// Write one CSV row per configuration, to the given file or to stdout.
+pub fn write_results(results: &[SweepResult], path: Option<&str>) -> Result<()> {
+    match path {
+        Some(path) => write_csv(results, csv::Writer::from_path(path)?),
+        None => write_csv(results, csv::Writer::from_writer(io::stdout())),
+    }
+}
+
+/// Write a self-contained HTML report of the sweep: a table of the results and charts of
+/// throughput, latency, and timeout rate per configuration.
+pub fn write_html_report(results: &[SweepResult], path: &str) -> Result<()> {
+    let mut report = HtmlReport::new("Sweep report");
+    let mut headers = vec!["configuration"];
+    headers.extend_from_slice(&RESULT_HEADERS);
+    let rows: Vec<_> = results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| {
+            let mut row = vec![index.to_string()];
+            row.extend(result_record(result));
+            row
+        })
+        .collect();
+    report.heading("Results");
+    report.table(&headers, &rows);
+    let labels: Vec<_> = (0..results.len()).map(|index| index.to_string()).collect();
+    let charts: [(&str, fn(&RunSummary) -> f64); 3] = [
+        ("Commit throughput per configuration", |summary| {
+            summary.commit_throughput
+        }),
+        ("Mean commit latency per configuration", |summary| {
+            summary.mean_commit_latency.unwrap_or(0.0)
+        }),
+        ("Timeout rate per configuration", |summary| {
+            summary.timeout_rate
+        }),
+    ];
+    for (caption, metric) in charts.iter() {
+        let values: Vec<_> = results
+            .iter()
+            .map(|result| metric(&result.summary))
+            .collect();
+        report.heading(caption);
+        report.bar_chart(caption, &labels, &values);
+    }
+    report.write(path)
+}
+
+const RESULT_HEADERS: [&str; 12] = [
+    "nodes",
+    "delta",
+    "gamma",
+    "lambda",
+    "loss_rate",
+    "commit_rule",
+    "commit_throughput",
+    "mean_commit_latency",
+    "timeout_rate",
+    "fast_path_rate",
+    "transaction_throughput",
+    "mean_transaction_latency",
+];
+
+/// The fields of a result, in the order of `RESULT_HEADERS`.
+fn result_record(result: &SweepResult) -> Vec<String> {
+    let parameters = &result.parameters;
+    let summary = &result.summary;
+    let fields: Vec<&Display> = vec![
+        &parameters.nodes,
+        &parameters.delta,
+        &parameters.gamma,
+        &parameters.lambda,
+        &parameters.loss_rate,
+        &parameters.commit_rule,
+        &summary.commit_throughput,
+    ];
+    let mut record: Vec<String> = fields.iter().map(|x| x.to_string()).collect();
+    record.push(
+        summary
+            .mean_commit_latency
+            .map(|x| x.to_string())
+            .unwrap_or_default(),
+    );
+    record.push(summary.timeout_rate.to_string());
+    record.push(summary.fast_path_rate.to_string());
+    record.push(summary.transaction_throughput.to_string());
+    record.push(
+        summary
+            .mean_transaction_latency
+            .map(|x| x.to_string())
+            .unwrap_or_default(),
+    );
+    record
+}
+
+fn write_csv<W: io::Write>(results: &[SweepResult], mut writer: csv::Writer<W>) -> Result<()> {
+    writer.write_record(&RESULT_HEADERS)?;
+    for result in results {
+        writer.write_record(&result_record(result))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
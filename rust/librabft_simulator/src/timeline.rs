@@ -0,0 +1,154 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Round timelines of nodes, for Gantt-style charts of a run.
+//!
+//! The timeline of a node is the sequence of rounds that it went through, as intervals of global
+//! time, with the times at which it proposed, voted, and timed out in each round. Timelines are
+//! built from decision logs, so nodes must record their decisions. Drawing the timelines of all
+//! nodes on the same time axis shows how far apart their rounds are.
+
+use super::*;
+use bft_simulator_runtime::simulator::GlobalTime;
+use decisions::{Decision, DecisionRecord};
+use serde::Serialize;
+use std::{fs, io::Write, path::Path};
+
+#[cfg(test)]
+#[path = "unit_tests/timeline_tests.rs"]
+mod timeline_tests;
+
+/// A round of a node. Times are global.
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+pub struct RoundInterval {
+    pub author: Author,
+    pub epoch_id: EpochId,
+    pub round: Round,
+    /// Leader of the round, as seen by the node.
+    pub leader: Option<Author>,
+    /// Time at which the node entered the round.
+    pub start: i64,
+    /// Time at which the node entered another round or recovered from a crash, or the end of
+    /// the run.
+    pub end: i64,
+    /// First proposal of the node in the round, if any.
+    pub propose: Option<i64>,
+    /// First vote of the node in the round, if any.
+    pub vote: Option<i64>,
+    /// First timeout of the node in the round, if any.
+    pub timeout: Option<i64>,
+}
+
+/// Rounds of the node `author` from its decision records. `global_time` converts the local
+/// times of the node, and the last round ends at `end` unless the node left it before.
+pub fn node_timeline(
+    author: Author,
+    records: &[DecisionRecord],
+    global_time: &Fn(NodeTime) -> GlobalTime,
+    end: GlobalTime,
+) -> Vec<RoundInterval> {
+    let mut intervals: Vec<RoundInterval> = Vec::new();
+    // Whether the last interval is still open.
+    let mut open = false;
+    for record in records {
+        let time = global_time(record.time).0;
+        let round = match &record.decision {
+            Decision::EnterRound { round, leader } => {
+                if open {
+                    intervals.last_mut().unwrap().end = time;
+                }
+                intervals.push(RoundInterval {
+                    author,
+                    epoch_id: record.epoch_id,
+                    round: *round,
+                    leader: *leader,
+                    start: time,
+                    end: end.0,
+                    propose: None,
+                    vote: None,
+                    timeout: None,
+                });
+                open = true;
+                continue;
+            }
+            Decision::Recover { .. } => {
+                if open {
+                    intervals.last_mut().unwrap().end = time;
+                }
+                open = false;
+                continue;
+            }
+            Decision::Propose { round, .. }
+            | Decision::Vote { round, .. }
+            | Decision::Timeout { round, .. } => *round,
+            _ => continue,
+        };
+        let interval = match intervals.last_mut() {
+            Some(interval) => interval,
+            None => continue,
+        };
+        if !open || interval.epoch_id != record.epoch_id || interval.round != round {
+            continue;
+        }
+        let mark = match &record.decision {
+            Decision::Propose { .. } => &mut interval.propose,
+            Decision::Vote { .. } => &mut interval.vote,
+            _ => &mut interval.timeout,
+        };
+        if mark.is_none() {
+            *mark = Some(time);
+        }
+    }
+    intervals
+}
+
+/// Rounds of all the nodes that recorded their decisions, by author then time, until the
+/// current time of the simulation. Local times are converted with the current clock offset of
+/// each node.
+pub fn round_timeline(sim: &Simulator) -> Vec<RoundInterval> {
+    let mut intervals = Vec::new();
+    for (index, node) in sim.simulated_nodes().iter().enumerate() {
+        if let Some(log) = node.node().decision_log() {
+            let clock = node.clock();
+            intervals.extend(node_timeline(
+                Author(index),
+                log.records(),
+                &|time| clock.global_time_at(time),
+                sim.clock(),
+            ));
+        }
+    }
+    intervals
+}
+
+/// Write one CSV row per round, with empty cells for missing times.
+pub fn write_csv<W: Write>(intervals: &[RoundInterval], output: W) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(output);
+    for interval in intervals {
+        writer.serialize(interval)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the rounds as a JSON array, with `null` for missing times.
+pub fn write_json<W: Write>(intervals: &[RoundInterval], output: W) -> Result<()> {
+    serde_json::to_writer_pretty(output, intervals)?;
+    Ok(())
+}
+
+/// Write the round timeline of a run to `path`, as JSON if the file name ends with `.json`,
+/// and as CSV otherwise.
+pub fn write_round_timeline<P: AsRef<Path>>(sim: &Simulator, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let intervals = round_timeline(sim);
+    let file = fs::File::create(path)?;
+    if path
+        .extension()
+        .map_or(false, |extension| extension == "json")
+    {
+        write_json(&intervals, file)
+    } else {
+        write_csv(&intervals, file)
+    }
+}
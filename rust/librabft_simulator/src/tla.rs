@@ -0,0 +1,144 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! State traces of runs, for trace validation with TLC against a TLA+ specification.
+//!
+//! The state of a run is made of the epoch, the active round, the round of the highest QC, and
+//! the highest committed round of each node. It is sampled before the first event and after
+//! every event, and consecutive identical states are recorded once. In TLA+, each variable is a
+//! sequence where node `i` is at index `i + 1`.
+
+use super::*;
+use bft_simulator_runtime::{invariants::Invariant, simulator::SimulatedNode};
+use serde::Serialize;
+use std::{cell::RefCell, fs, path::Path, rc::Rc};
+
+#[cfg(test)]
+#[path = "unit_tests/tla_tests.rs"]
+mod tla_tests;
+
+/// Variables of the nodes at some point of a run, indexed by author.
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlaState {
+    pub epoch: Vec<usize>,
+    pub round: Vec<usize>,
+    pub highest_qc_round: Vec<usize>,
+    pub highest_committed_round: Vec<usize>,
+}
+
+impl TlaState {
+    pub fn of<Context>(nodes: &[SimulatedNode<NodeState, Context>]) -> Self {
+        let store = |node: &SimulatedNode<NodeState, Context>| node.node().record_store();
+        TlaState {
+            epoch: nodes.iter().map(|node| node.node().epoch_id().0).collect(),
+            round: nodes.iter().map(|node| node.active_round().0).collect(),
+            highest_qc_round: nodes
+                .iter()
+                .map(|node| store(node).highest_quorum_certificate_round().0)
+                .collect(),
+            highest_committed_round: nodes
+                .iter()
+                .map(|node| store(node).highest_committed_round().0)
+                .collect(),
+        }
+    }
+
+    /// The state as a TLA+ record.
+    fn to_tla(&self) -> String {
+        let sequence = |values: &[usize]| {
+            let values: Vec<_> = values.iter().map(|value| value.to_string()).collect();
+            format!("<<{}>>", values.join(", "))
+        };
+        format!(
+            "[epoch |-> {}, round |-> {}, highestQcRound |-> {}, highestCommittedRound |-> {}]",
+            sequence(&self.epoch),
+            sequence(&self.round),
+            sequence(&self.highest_qc_round),
+            sequence(&self.highest_committed_round)
+        )
+    }
+}
+
+/// Records the states of a run. Registered as an invariant that always holds, so that it is
+/// called after every event.
+#[derive(Debug, Default)]
+pub struct TlaTraceRecorder {
+    states: Rc<RefCell<Vec<TlaState>>>,
+}
+
+impl TlaTraceRecorder {
+    /// Record the states of `sim` from now on. Returns the recorded states.
+    pub fn attach(sim: &mut Simulator) -> Rc<RefCell<Vec<TlaState>>> {
+        let recorder = TlaTraceRecorder::default();
+        let states = recorder.states.clone();
+        states
+            .borrow_mut()
+            .push(TlaState::of(sim.simulated_nodes()));
+        sim.add_invariant(Box::new(recorder));
+        states
+    }
+}
+
+impl<Context> Invariant<NodeState, Context> for TlaTraceRecorder {
+    fn name(&self) -> &str {
+        "tla trace"
+    }
+
+    fn check(
+        &mut self,
+        nodes: &[SimulatedNode<NodeState, Context>],
+    ) -> std::result::Result<(), String> {
+        let state = TlaState::of(nodes);
+        let mut states = self.states.borrow_mut();
+        if states.last() != Some(&state) {
+            states.push(state);
+        }
+        Ok(())
+    }
+}
+
+/// A TLA+ module defining `Trace` as the sequence of the given states. The name of the module
+/// must match the name of its file.
+pub fn tla_module(name: &str, states: &[TlaState]) -> String {
+    let states: Vec<_> = states
+        .iter()
+        .map(|state| format!("    {}", state.to_tla()))
+        .collect();
+    format!(
+        "---- MODULE {} ----\n\\* States of a LibraBFT simulation. Node i is at index i + 1.\nTrace == <<\n{}\n>>\n====\n",
+        name,
+        states.join(",\n")
+    )
+}
+
+/// One JSON object per state and per line, as read by `ndJsonDeserialize` in the `Json`
+/// module of the TLA+ community modules.
+pub fn nd_json(states: &[TlaState]) -> Result<String> {
+    let mut contents = String::new();
+    for state in states {
+        contents += &serde_json::to_string(state)?;
+        contents.push('\n');
+    }
+    Ok(contents)
+}
+
+/// Write the states to `path`: a TLA+ module named after the file if the extension of the path
+/// is `tla`, and newline-delimited JSON otherwise.
+pub fn write_trace<P: AsRef<Path>>(path: P, states: &[TlaState]) -> Result<()> {
+    let path = path.as_ref();
+    let contents = if path
+        .extension()
+        .map_or(false, |extension| extension == "tla")
+    {
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name,
+            None => bail!("Invalid module name in {:?}", path),
+        };
+        tla_module(name, states)
+    } else {
+        nd_json(states)?
+    };
+    fs::write(path, contents)?;
+    Ok(())
+}
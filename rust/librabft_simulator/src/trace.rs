@@ -0,0 +1,101 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! Traces of simulation runs.
+//!
+//! A trace is a TOML file made of the scenario of a run, with its seed, and of the sequence of
+//! events processed during the run. Since runs are deterministic, replaying a trace re-runs the
+//! scenario and checks that the same events are processed in the same order.
+
+use super::*;
+use bft_simulator_runtime::{simulator::EventRecord, statistics::RunSummary};
+use scenario::Scenario;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+#[cfg(test)]
+#[path = "unit_tests/trace_tests.rs"]
+mod trace_tests;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Trace {
+    pub scenario: Scenario,
+    pub events: Vec<TraceEvent>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct TraceEvent {
+    pub time: i64,
+    /// The node whose handlers processed the event.
+    pub node: usize,
+    pub kind: String,
+}
+
+impl<'a> From<&'a EventRecord> for TraceEvent {
+    fn from(record: &'a EventRecord) -> Self {
+        TraceEvent {
+            time: record.time.0,
+            node: record.node.0,
+            kind: record.kind.to_string(),
+        }
+    }
+}
+
+impl Trace {
+    /// Run a scenario and record its events. A random seed is chosen if the scenario has none.
+    pub fn record(scenario: &Scenario) -> (Trace, RunSummary) {
+        let parameters = scenario.parameters();
+        let (events, summary) = run_with_events(&parameters);
+        (Trace::new(scenario, parameters.seed, events), summary)
+    }
+
+    /// The trace of a run of `scenario` with the given seed.
+    pub fn new(scenario: &Scenario, seed: u64, events: Vec<TraceEvent>) -> Self {
+        Trace {
+            scenario: Scenario {
+                seed: Some(seed),
+                ..scenario.clone()
+            },
+            events,
+        }
+    }
+
+    /// Run the scenario of the trace again and check that the same events are processed.
+    pub fn replay(&self) -> Result<RunSummary> {
+        let (events, summary) = run_with_events(&self.scenario.parameters());
+        for (index, (expected, actual)) in self.events.iter().zip(events.iter()).enumerate() {
+            ensure!(
+                expected == actual,
+                "Replay diverged at event {}: expected {:?}, got {:?}",
+                index,
+                expected,
+                actual
+            );
+        }
+        ensure!(
+            self.events.len() == events.len(),
+            "Replay processed {} events instead of {}",
+            events.len(),
+            self.events.len()
+        );
+        Ok(summary)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.scenario.to_toml()?;
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+fn run_with_events(parameters: &SimulationParameters) -> (Vec<TraceEvent>, RunSummary) {
+    let mut sim = make_simulator(parameters);
+    sim.record_events();
+    sim.run(&parameters.termination(), None);
+    let events = sim.event_log().iter().map(TraceEvent::from).collect();
+    (events, sim.summary())
+}
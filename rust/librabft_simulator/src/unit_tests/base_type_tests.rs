@@ -12,6 +12,12 @@ fn test_is_power2_minus1() {
     assert!(!is_power2_minus1(2));
 }
 
+#[test]
+fn test_previous_epoch() {
+    assert_eq!(EpochId(0).previous(), None);
+    assert_eq!(EpochId(2).previous(), Some(EpochId(1)));
+}
+
 #[test]
 fn test_merge_sort() {
     assert_eq!(
@@ -19,3 +25,46 @@ fn test_merge_sort() {
         merge_sort(vec![0, 2, 6, 9], vec![2, 5, 6], u64::cmp),
     );
 }
+
+#[test]
+fn test_commit_rule() {
+    assert_eq!(CommitRule::default(), CommitRule::ThreeChain);
+    assert_eq!(CommitRule::TwoChain.commit_certificate_distance(), 1);
+    for rule in &[CommitRule::ThreeChain, CommitRule::TwoChain] {
+        assert_eq!(rule.to_string().parse::<CommitRule>().unwrap(), *rule);
+    }
+    assert!("one-chain".parse::<CommitRule>().is_err());
+}
+
+#[test]
+fn test_vote_delivery() {
+    assert_eq!(VoteDelivery::default(), VoteDelivery::Proposer);
+    for delivery in &[
+        VoteDelivery::Proposer,
+        VoteDelivery::NextLeader,
+        VoteDelivery::Broadcast,
+    ] {
+        assert_eq!(
+            delivery.to_string().parse::<VoteDelivery>().unwrap(),
+            *delivery
+        );
+    }
+    assert!("everyone".parse::<VoteDelivery>().is_err());
+}
+
+#[test]
+fn test_empty_block_policy() {
+    assert_eq!(EmptyBlockPolicy::default(), EmptyBlockPolicy::Propose);
+    assert_eq!(
+        "delay:20".parse::<EmptyBlockPolicy>().unwrap(),
+        EmptyBlockPolicy::Delay {
+            max_delay: Duration::from_millis(20)
+        }
+    );
+    assert_eq!(
+        "no-op".parse::<EmptyBlockPolicy>().unwrap(),
+        EmptyBlockPolicy::NoOp
+    );
+    assert!("delay".parse::<EmptyBlockPolicy>().is_err());
+    assert!("delay:soon".parse::<EmptyBlockPolicy>().is_err());
+}
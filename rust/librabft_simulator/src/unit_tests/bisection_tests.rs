@@ -0,0 +1,38 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+#[test]
+fn test_bisection() {
+    let contents = "seed = 6\nnodes = 4\n\
+                    [termination]\nmax_clock = 3000\nmax_time_without_commit = 300";
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let settings = BisectionSettings {
+        parameter: "loss_rate".parse().unwrap(),
+        low: 0.0,
+        high: 1.0,
+        seeds: 2,
+        tolerance: 0.25,
+        max_probes: 10,
+    };
+    let bisection = run_bisection(&parameters, &settings).unwrap();
+    // Both ends, then halves of widths 1 and 0.5.
+    assert_eq!(bisection.probes.len(), 4);
+    assert!(bisection.probes[0].is_live());
+    assert!(!bisection.probes[1].is_live());
+    assert!(bisection.live_value < bisection.lost_value);
+    assert!((bisection.lost_value - bisection.live_value - 0.25).abs() < 1e-9);
+    assert!(0.0 < bisection.threshold() && bisection.threshold() < 1.0);
+
+    // Liveness must change between the ends.
+    let settings = BisectionSettings {
+        low: 0.99,
+        ..settings
+    };
+    assert!(run_bisection(&parameters, &settings).is_err());
+    let unbounded = Scenario::parse("nodes = 4").unwrap().parameters();
+    assert!(run_bisection(&unbounded, &settings).is_err());
+    assert!("seed".parse::<BisectedParameter>().is_err());
+}
@@ -0,0 +1,30 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+#[test]
+fn test_block_tree_dot() {
+    let parameters = Scenario::parse("seed = 5\nnodes = 4").unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+
+    let dot = block_tree_dot(sim.simulated_node(Author(0)).node());
+    assert!(dot.starts_with("digraph block_tree {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("subgraph cluster_0 {"));
+    assert!(dot.contains("style = filled"));
+    // Edges only point to declared records.
+    let declared: Vec<_> = dot
+        .lines()
+        .filter(|line| line.contains(" [label = "))
+        .map(|line| line.trim().split(' ').next().unwrap())
+        .collect();
+    let edges: Vec<_> = dot.lines().filter(|line| line.contains(" -> ")).collect();
+    assert!(!edges.is_empty());
+    for edge in edges {
+        let target = edge.split(" -> ").nth(1).unwrap().trim_end_matches(';');
+        assert!(declared.contains(&target), "{}", edge);
+    }
+}
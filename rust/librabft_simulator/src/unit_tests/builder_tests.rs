@@ -0,0 +1,63 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_simulation_builder() {
+    let parameters = SimulationBuilder::new()
+        .seed(3)
+        .nodes(4)
+        .pacemaker(Duration::from_millis(30), 1.5, 0.5)
+        .node(
+            NodeConfigBuilder::new(Author(3))
+                .delta(Duration::from_millis(40))
+                .stake(2),
+        )
+        .crash(Author(2), GlobalTime(300), Some(GlobalTime(400)))
+        .max_clock(GlobalTime(500))
+        .build()
+        .unwrap();
+    assert_eq!(parameters.seed, 3);
+    assert_eq!(parameters.nodes, 4);
+    assert_eq!(
+        (parameters.delta, parameters.gamma),
+        (Duration::from_millis(30), 1.5)
+    );
+    assert_eq!(
+        parameters.node_overrides[&Author(3)].delta,
+        Some(Duration::from_millis(40))
+    );
+    assert_eq!(parameters.stakes[&Author(3)], 2);
+    assert_eq!(parameters.crashes[&Author(2)], GlobalTime(300));
+    assert_eq!(parameters.recoveries[&Author(2)], GlobalTime(400));
+    assert_eq!(parameters.max_clock, GlobalTime(500));
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.summary().committed_blocks > 0.0);
+
+    // Defaults are those of scenario files.
+    let parameters = SimulationBuilder::new().seed(3).build().unwrap();
+    let defaults = scenario::Scenario::default();
+    assert_eq!(parameters.nodes, defaults.nodes);
+    assert_eq!(parameters.delta, defaults.pacemaker.delta);
+
+    assert!(SimulationBuilder::new()
+        .delta(Duration::ZERO)
+        .build()
+        .is_err());
+    assert!(SimulationBuilder::new().gamma(0.5).build().is_err());
+    assert!(SimulationBuilder::new().lambda(0.0).build().is_err());
+    assert!(SimulationBuilder::new().loss_rate(1.5).build().is_err());
+    assert!(SimulationBuilder::new().nodes(0).build().is_err());
+    assert!(SimulationBuilder::new()
+        .nodes(4)
+        .node(NodeConfigBuilder::new(Author(1)).gamma(0.9))
+        .build()
+        .is_err());
+    assert!(SimulationBuilder::new()
+        .nodes(4)
+        .node(NodeConfigBuilder::new(Author(7)))
+        .build()
+        .is_err());
+}
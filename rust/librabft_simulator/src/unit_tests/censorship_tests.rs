@@ -0,0 +1,58 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+const SCENARIO: &str = "\
+seed = 3
+nodes = 4
+byzantine = [3]
+
+[workload.arrivals]
+kind = \"poisson\"
+rate = 0.05
+
+[workload.targeted_arrivals]
+kind = \"poisson\"
+rate = 0.02
+";
+
+#[test]
+fn test_censoring_leaders() {
+    let parameters = Scenario::parse(SCENARIO).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+
+    let blocks = sim.simulated_node(Author(0)).node().committed_blocks();
+    let is_targeted = |block: &CommittedBlock| {
+        block
+            .transactions
+            .iter()
+            .any(|transaction| transaction.targeted.is_some())
+    };
+    assert!(blocks.iter().any(|block| block.proposer == Author(3)));
+    assert!(!blocks
+        .iter()
+        .filter(|block| block.proposer == Author(3))
+        .any(is_targeted));
+    // Honest leaders commit the targeted transactions.
+    assert!(blocks.iter().any(is_targeted));
+    assert!(sim.summary().mean_targeted_latency.is_some());
+}
+
+#[test]
+fn test_run_censorship() {
+    let parameters = Scenario::parse(SCENARIO).unwrap().parameters();
+    let results = run_censorship(&parameters).unwrap();
+    let byzantine: Vec<_> = results
+        .iter()
+        .map(|result| result.parameters.byzantine.len())
+        .collect();
+    assert_eq!(byzantine, vec![0, 1]);
+    assert!(results[1].parameters.byzantine.contains(&Author(3)));
+    assert!(results.iter().all(|result| result.slowdown().is_some()));
+
+    let untargeted = Scenario::parse("nodes = 4").unwrap().parameters();
+    assert!(run_censorship(&untargeted).is_err());
+}
@@ -0,0 +1,34 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+#[test]
+fn test_compare() {
+    let a = Scenario::parse("seed = 4\nnodes = 4").unwrap().parameters();
+    let same = compare(&a, &a, 3);
+    assert_eq!(same.seeds, vec![4, 5, 6]);
+    // Identical configurations give identical runs.
+    for metric in &same.metrics {
+        if let Some(comparison) = &metric.comparison {
+            assert!(comparison.mean_difference.abs() < 1e-9, "{}", metric.metric);
+            assert!(!comparison.is_significant());
+        }
+    }
+
+    // Runs of `b` use the seeds of `a`.
+    let b = Scenario::parse("seed = 9\nnodes = 4\n[network]\nloss_rate = 0.3")
+        .unwrap()
+        .parameters();
+    let report = compare(&a, &b, 3);
+    assert_eq!(report.seeds, vec![4, 5, 6]);
+    let timeout_rate = report.metric("timeout_rate").unwrap();
+    assert_eq!(timeout_rate.comparison.as_ref().unwrap().count, 3);
+
+    let mut output = Vec::new();
+    write_csv(&report, csv::Writer::from_writer(&mut output)).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("metric,pairs,mean_a,mean_b,difference,"));
+    assert_eq!(output.lines().count(), 1 + report.metrics.len());
+}
@@ -0,0 +1,72 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use std::time::Duration as StdDuration;
+
+fn parameters() -> SimulationParameters {
+    scenario::Scenario::parse("seed = 1\nnodes = 4\n[termination]\nmax_clock = 1000")
+        .unwrap()
+        .parameters()
+}
+
+#[test]
+fn test_controlled_run_with_faults() {
+    let mut run = ControlledRun::new(parameters());
+    run.step();
+    assert_eq!(run.status().clock, GlobalTime(0) + STEP);
+    assert!(run.status().stop_reason.is_none());
+    // Without a quorum, nodes stop committing until one of them recovers.
+    let time = GlobalTime(0) + STEP + Duration::from_millis(1);
+    run.inject(Fault::Crash(Author(0)), time);
+    run.inject(Fault::Crash(Author(1)), time);
+    run.inject(Fault::Recover(Author(2)), time);
+    while run.clock < GlobalTime(300) {
+        run.step();
+    }
+    let stalled_blocks = run.status().summary.unwrap().committed_blocks;
+    while run.clock < GlobalTime(500) {
+        run.step();
+    }
+    assert_eq!(
+        run.status().summary.unwrap().committed_blocks,
+        stalled_blocks
+    );
+    run.inject(Fault::Recover(Author(1)), GlobalTime(501));
+    while run.stop_reason.is_none() {
+        run.step();
+    }
+    let status = run.status();
+    assert_eq!(status.clock, GlobalTime(1000));
+    assert_eq!(status.stop_reason, Some("MaxClock".to_string()));
+    assert!(status.summary.unwrap().committed_blocks > stalled_blocks);
+}
+
+#[test]
+fn test_experiments() {
+    let experiments = Experiments::default();
+    assert!(experiments.start_run("nodes = -1").is_err());
+    let scenario = "seed = 3\nnodes = 4\n[termination]\nmax_clock = 1000";
+    let (run_id, seed) = experiments.start_run(scenario).unwrap();
+    assert_eq!(seed, 3);
+    assert!(experiments.status(run_id + 1).is_err());
+    assert!(experiments
+        .inject_fault(run_id, Fault::Crash(Author(4)), GlobalTime(0))
+        .is_err());
+    loop {
+        let status = experiments.status(run_id).unwrap();
+        if status.stop_reason.is_some() {
+            assert_eq!(status.clock, GlobalTime(1000));
+            assert!(status.summary.unwrap().committed_blocks > 0.0);
+            break;
+        }
+        let time = experiments
+            .inject_fault(run_id, Fault::Crash(Author(3)), GlobalTime(0))
+            .unwrap();
+        assert!(time > status.clock);
+        thread::sleep(StdDuration::from_millis(1));
+    }
+    assert!(experiments
+        .inject_fault(run_id, Fault::Crash(Author(3)), GlobalTime(0))
+        .is_err());
+}
@@ -1,4 +1,276 @@
 // Copyright (c) Calibra Research
 // SPDX-License-Identifier: Apache-2.0
 
+use super::*;
+use record_store::RecordStore;
+use simulated_context::SimulatedContext;
 
+fn handle_request(node: &NodeState, request: DataSyncRequest) -> DataSyncResponse {
+    DataSyncNode::<SimulatedContext>::handle_request(node, &request)
+}
+
+#[test]
+fn test_push_pull() {
+    let policy = PushPullPolicy {
+        initial_fanout: 2,
+        min_fanout: 1,
+        max_fanout: 3,
+        pull_period: Duration::from_millis(10),
+        ..PushPullPolicy::default()
+    };
+    assert!(policy.check().is_ok());
+    let validators: Vec<_> = (0..5).map(Author).collect();
+    let mut state = PushPullState::new(policy);
+    let peers = state.push_targets(Author(0), validators.clone());
+    assert_eq!(peers.len(), 2);
+    assert!(peers[0] != peers[1] && !peers.contains(&Author(0)));
+    let pull = |state: &mut PushPullState, clock| {
+        state.pull_target(Author(0), validators.clone(), NodeTime(clock))
+    };
+    // Pulls are due at the first update, then once per period.
+    assert!(pull(&mut state, 5).is_some());
+    assert_eq!(state.next_pull_time(), NodeTime(15));
+    assert!(pull(&mut state, 10).is_none());
+    // Few duplicates: the fanout grows, up to `max_fanout`.
+    state.observe(10, 1);
+    assert!(pull(&mut state, 15).is_some());
+    assert_eq!(state.fanout(), 3);
+    state.observe(10, 0);
+    pull(&mut state, 25);
+    assert_eq!(state.fanout(), 3);
+    // Mostly duplicates: the fanout shrinks, down to `min_fanout`.
+    for i in 0..3 {
+        state.observe(10, 9);
+        pull(&mut state, 35 + 10 * i);
+    }
+    assert_eq!(state.fanout(), 1);
+    assert_eq!(state.restart().fanout(), 2);
+
+    let toml = "seed = 5\nnodes = 7\n[push_pull]\ninitial_fanout = 2\npull_period = 30\n\
+                [termination]\nmax_clock = 2000\n";
+    let parameters = scenario::Scenario::parse(toml).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.metrics_report().aggregate.committed_blocks > 0.0);
+
+    let policy = PushPullPolicy {
+        initial_fanout: 0,
+        ..PushPullPolicy::default()
+    };
+    assert!(policy.check().is_err());
+    let policy = PushPullPolicy {
+        min_fanout: 0,
+        initial_fanout: 0,
+        ..PushPullPolicy::default()
+    };
+    assert!(policy.check().is_err());
+    let policy = PushPullPolicy {
+        pull_period: Duration::from_millis(0),
+        ..PushPullPolicy::default()
+    };
+    assert!(policy.check().is_err());
+    let policy = PushPullPolicy {
+        min_duplicate_rate: 0.9,
+        ..PushPullPolicy::default()
+    };
+    assert!(policy.check().is_err());
+}
+
+#[test]
+fn test_data_sync_version() {
+    let toml = "seed = 5\nnodes = 4\n[[node]]\nauthor = 3\ndata_sync_version = 1\n\
+                [termination]\nmax_clock = 2000\n";
+    let parameters = scenario::Scenario::parse(toml).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.metrics_report().aggregate.committed_blocks > 0.0);
+    let node = sim.simulated_node(Author(0)).node();
+    let legacy_node = sim.simulated_node(Author(3)).node();
+    assert_eq!(legacy_node.data_sync_version(), LEGACY_DATA_SYNC_VERSION);
+
+    // Requests of legacy nodes do not summarize the known QCs, and exchanges involving them
+    // use the first version.
+    let request = node.create_request_internal();
+    assert_eq!(request.version, DATA_SYNC_VERSION);
+    assert!(!request.known_quorum_certificates.is_empty());
+    let legacy_request = legacy_node.create_request_internal();
+    assert!(legacy_request.known_quorum_certificates.is_empty());
+    let response = handle_request(node, legacy_request.clone());
+    assert_eq!(response.version, LEGACY_DATA_SYNC_VERSION);
+    let legacy_response = handle_request(legacy_node, request.clone());
+    assert_eq!(legacy_response.version, LEGACY_DATA_SYNC_VERSION);
+    // Without the summary, responses carry more records.
+    let summarized = handle_request(node, request).record_counts();
+    assert!(response.record_counts().quorum_certificates > summarized.quorum_certificates);
+
+    // Responses of unsupported versions are reported and skipped.
+    let mut response = handle_request(node, legacy_request);
+    response.version = DATA_SYNC_VERSION + 1;
+    let mut context = make_context(&parameters, Author(3));
+    let mut legacy_node = make_node(&parameters, Author(3), &context, NodeTime(0));
+    legacy_node.handle_response(Author(0), response, &mut context, NodeTime(0));
+    let errors = legacy_node.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("version 3"));
+
+    let toml = "nodes = 4\ndata_sync_version = 3\n";
+    assert!(scenario::Scenario::parse(toml).is_err());
+}
+
+#[test]
+fn test_redundancy_suppression() {
+    // Short rounds and frequent retransmissions send the same records many times.
+    let toml = "seed = 5\nnodes = 4\n{}\n[pacemaker]\ndelta = 5\nretransmission_period = 2\n\
+                [termination]\nmax_clock = 2000\n";
+    let run = |suppress: &str| {
+        let contents = toml.replace("{}", suppress);
+        let parameters = scenario::Scenario::parse(&contents).unwrap().parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        sim
+    };
+    let report = run("").metrics_report();
+    assert!(report.nodes.iter().all(|x| x.suppressed_notifications == 0));
+    let sim = run("suppress_redundant_records = true");
+    let report = sim.metrics_report();
+    assert!(report.nodes.iter().any(|x| x.suppressed_notifications > 0));
+    assert!(report.aggregate.committed_blocks > 0.0);
+
+    let node = sim.simulated_node(Author(1)).node();
+    let records = node.create_notification_internal().records();
+    let digests: Vec<_> = records.iter().map(Record::digest).collect();
+    let epoch_id = node.epoch_id();
+    let mut knowledge = PeerKnowledge::new(epoch_id);
+    assert!(!knowledge.holds_all(epoch_id, Author(1), &digests));
+    knowledge.learn(epoch_id, Author(1), &records);
+    assert!(knowledge.holds_all(epoch_id, Author(1), &digests));
+    assert!(!knowledge.holds_all(epoch_id, Author(2), &digests));
+    assert!(!knowledge.holds_all(epoch_id, Author(1), &[]));
+    assert_eq!(knowledge.peers(), vec![Author(1)]);
+    // Records of the peer's vote include the voted block.
+    if let Some(vote) = node.record_store().current_vote(Author(1)) {
+        assert!(knowledge.holds(epoch_id, Author(1), vote.certified_block_hash.0));
+    }
+    // Knowledge is dropped at the next epoch.
+    knowledge.learn(EpochId(epoch_id.0 + 1), Author(2), &[]);
+    assert_eq!(knowledge.peers(), vec![Author(2)]);
+    assert!(!knowledge.holds_all(epoch_id, Author(1), &digests));
+    // Peers that ask for records are forgotten, since they may have restarted.
+    let new_epoch_id = EpochId(epoch_id.0 + 1);
+    knowledge.learn(new_epoch_id, Author(2), &records);
+    assert!(knowledge.holds_all(new_epoch_id, Author(2), &digests));
+    knowledge.forget(Author(2));
+    assert!(!knowledge.holds_all(new_epoch_id, Author(2), &digests));
+    assert!(knowledge.peers().is_empty());
+}
+
+#[test]
+fn test_state_sync() {
+    // Node 3 misses many short epochs while it is down.
+    let toml = "seed = 5\nnodes = 4\nstate_sync = true\n[workload]\ncommands_per_epoch = 3\n\
+                [[fault]]\nauthor = 3\ncrash_time = 100\nrecovery_time = 1500\n\
+                [termination]\nmax_clock = 3000\n";
+    let parameters = scenario::Scenario::parse(toml).unwrap().parameters();
+    assert!(parameters.state_sync);
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.safety_violation().is_none());
+    let node = sim.simulated_node(Author(0)).node();
+    let lagging_node = sim.simulated_node(Author(3)).node();
+    assert!(node.state_checkpoint().is_some());
+    assert_eq!(node.installed_checkpoints(), 0);
+    // The lagging node jumped to a later epoch and kept up from there.
+    assert!(lagging_node.installed_checkpoints() > 0);
+    assert!(lagging_node.epoch_id().0 + 1 >= node.epoch_id().0);
+    assert!(lagging_node.committed_blocks().len() > node.committed_blocks().len() / 2);
+
+    // Checkpoints are installed once peers with more than `f` votes send the same one.
+    let mut context = make_context(&parameters, Author(3));
+    let mut fresh_node = make_node(&parameters, Author(3), &context, NodeTime(0));
+    let request = fresh_node.create_request_internal();
+    assert_eq!(request.committed_blocks, Some(0));
+    // Peers that committed the same checkpoint send the same one.
+    let peer = (1..3)
+        .map(Author)
+        .find(|author| {
+            sim.simulated_node(*author).node().state_checkpoint() == node.state_checkpoint()
+        })
+        .unwrap();
+    let respond = |author: Author, snapshot: Option<&[u8]>| {
+        let mut response = handle_request(sim.simulated_node(author).node(), request.clone());
+        let checkpoint = response.checkpoint.take().unwrap();
+        assert_eq!(
+            response.checkpoint_blocks.len(),
+            checkpoint.committed_blocks
+        );
+        response.checkpoint = Some(StateCheckpoint {
+            snapshot: snapshot.map_or(checkpoint.snapshot.clone(), <[u8]>::to_vec),
+            ..checkpoint
+        });
+        response
+    };
+    let forged = || respond(Author(0), Some(b"{}"));
+    let honest = |author| respond(author, None);
+    // A single peer cannot plant a checkpoint, even by sending it several times.
+    fresh_node.handle_response(Author(0), forged(), &mut context, NodeTime(0));
+    fresh_node.handle_response(Author(0), forged(), &mut context, NodeTime(0));
+    assert!(fresh_node.take_errors().is_empty());
+    assert_eq!(fresh_node.epoch_id(), EpochId(0));
+    // Checkpoints that do not match their state are reported and skipped.
+    fresh_node.handle_response(peer, respond(peer, Some(b"{}")), &mut context, NodeTime(0));
+    let errors = fresh_node.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("checkpoint"));
+    assert_eq!(fresh_node.epoch_id(), EpochId(0));
+    // So are checkpoints whose epoch disagrees with their state.
+    let mut response = honest(Author(0));
+    if let Some(checkpoint) = &mut response.checkpoint {
+        checkpoint.epoch_id = EpochId(checkpoint.epoch_id.0 + 1);
+    }
+    fresh_node.handle_response(Author(0), response, &mut context, NodeTime(0));
+    let errors = fresh_node.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("epoch"));
+    // Senders only endorse the latest checkpoint that they sent.
+    fresh_node.handle_response(Author(0), honest(Author(0)), &mut context, NodeTime(0));
+    fresh_node.handle_response(Author(0), forged(), &mut context, NodeTime(0));
+    fresh_node.handle_response(peer, honest(peer), &mut context, NodeTime(0));
+    assert!(fresh_node.take_errors().is_empty());
+    assert_eq!(fresh_node.epoch_id(), EpochId(0));
+    fresh_node.handle_response(Author(0), honest(Author(0)), &mut context, NodeTime(0));
+    assert!(fresh_node.take_errors().is_empty());
+    assert_eq!(fresh_node.installed_checkpoints(), 1);
+    assert_eq!(
+        fresh_node.epoch_id(),
+        node.state_checkpoint().unwrap().epoch_id
+    );
+}
+
+#[test]
+fn test_shared_records() {
+    let toml = "seed = 4\nnodes = 4\n[termination]\nmax_clock = 500";
+    let parameters = scenario::Scenario::parse(toml).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    let stores: Vec<_> = (0..4)
+        .map(|index| {
+            sim.simulated_node(Author(index))
+                .node()
+                .record_store_state()
+        })
+        .collect();
+    let (hash, block) = stores[0].verified_blocks()[0];
+    for store in &stores[1..] {
+        // All the nodes hold the copy of the block sent by its proposer.
+        assert!(std::ptr::eq(store.block(hash).unwrap(), block));
+    }
+    // Notifications carry the copies of the store instead of new ones.
+    let notification = sim
+        .simulated_node(Author(0))
+        .node()
+        .create_notification_internal();
+    assert!(Arc::ptr_eq(
+        notification.highest_quorum_certificate.as_ref().unwrap(),
+        stores[0].shared_highest_quorum_certificate().unwrap()
+    ));
+}
@@ -0,0 +1,74 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+#[test]
+fn test_repeated_decisions() {
+    let mut log = DecisionLog::new();
+    let decision = Decision::WaitForBatch {
+        round: Round(1),
+        until: NodeTime(10),
+    };
+    log.record(NodeTime(1), EpochId(0), decision.clone());
+    log.record(NodeTime(2), EpochId(0), decision.clone());
+    assert_eq!(log.records().len(), 1);
+    log.record(NodeTime(3), EpochId(1), decision);
+    assert_eq!(log.records().len(), 2);
+
+    let mut output = Vec::new();
+    log.write(&mut output).unwrap();
+    let lines: Vec<_> = std::str::from_utf8(&output).unwrap().lines().collect();
+    assert_eq!(
+        lines[0],
+        r#"{"time":1,"epoch_id":0,"kind":"wait-for-batch","round":1,"until":10}"#
+    );
+}
+
+#[test]
+fn test_decision_logs() {
+    let scenario =
+        Scenario::parse("seed = 2\nnodes = 4\n[[fault]]\nauthor = 3\ncrash_time = 0").unwrap();
+    let mut parameters = scenario.parameters();
+    parameters.record_decisions = true;
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+
+    let log = sim.simulated_node(Author(0)).node().decision_log().unwrap();
+    let has = |predicate: &Fn(&Decision) -> bool| {
+        log.records()
+            .iter()
+            .any(|record| predicate(&record.decision))
+    };
+    assert!(has(&|decision| match decision {
+        Decision::Vote { .. } => true,
+        _ => false,
+    }));
+    assert!(has(&|decision| match decision {
+        Decision::Commit { .. } => true,
+        _ => false,
+    }));
+    // Rounds led by the crashed node time out.
+    assert!(has(&|decision| match decision {
+        Decision::Timeout { .. } => true,
+        _ => false,
+    }));
+}
+
+#[test]
+fn test_decision_kinds() {
+    let scenario = Scenario::parse("seed = 2\nnodes = 4").unwrap();
+    let mut parameters = scenario.parameters();
+    parameters.record_decisions = true;
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+
+    // Structured events use the same kinds as decision logs.
+    let log = sim.simulated_node(Author(0)).node().decision_log().unwrap();
+    assert!(!log.records().is_empty());
+    for record in log.records() {
+        let json = serde_json::to_value(&record.decision).unwrap();
+        assert_eq!(json["kind"], record.decision.kind());
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+use std::net::TcpListener;
+
+fn local_addresses(count: usize) -> Vec<SocketAddr> {
+    // Reserve free ports, then release them for the nodes.
+    let listeners: Vec<_> = (0..count)
+        .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+        .collect();
+    listeners
+        .iter()
+        .map(|listener| listener.local_addr().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_local_deployment() {
+    let parameters = Scenario::parse("nodes = 4").unwrap().parameters();
+    let blocks = run_local_deployment(
+        &parameters,
+        local_addresses(4),
+        StdDuration::from_millis(2000),
+    )
+    .unwrap();
+    assert_eq!(blocks.len(), 4);
+    for chain in &blocks {
+        assert!(!chain.is_empty());
+    }
+    // Committed chains are prefixes of one another.
+    let hashes: Vec<Vec<_>> = blocks
+        .iter()
+        .map(|chain| chain.iter().map(|block| block.hash).collect())
+        .collect();
+    for chain in &hashes {
+        let length = std::cmp::min(chain.len(), hashes[0].len());
+        assert_eq!(chain[..length], hashes[0][..length]);
+    }
+}
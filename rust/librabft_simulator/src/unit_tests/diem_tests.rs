@@ -0,0 +1,92 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+fn check_round_trip(contents: &str) -> DiemTrace {
+    let parameters = scenario::Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    let node = sim.simulated_node(Author(0)).node();
+    let trace = export_node(node).unwrap();
+    let json = serde_json::to_string(&trace).unwrap();
+    assert_eq!(serde_json::from_str::<DiemTrace>(&json).unwrap(), trace);
+
+    let (_, store) = node.record_stores().pop().unwrap();
+    assert_eq!(trace.blocks.len(), store.verified_blocks().len());
+    assert_eq!(
+        trace.quorum_certs.len(),
+        store.verified_quorum_certificates().len()
+    );
+    assert!(trace
+        .quorum_certs
+        .iter()
+        .any(|qc| !ledger_info_with_signatures(qc)
+            .ledger_info
+            .commit_info
+            .is_empty()));
+
+    // Votes of QCs may be reordered, so compare the fields that do not depend on hashes.
+    let mut blocks = Vec::new();
+    let mut qcs = Vec::new();
+    for record in import_records(&trace).unwrap() {
+        match record {
+            Record::Block(block) => blocks.push((
+                block.round,
+                block.author,
+                block.command.clone(),
+                block.previous_block_hash,
+            )),
+            Record::QuorumCertificate(qc) => {
+                let mut voters = qc.votes.authors();
+                voters.sort();
+                qcs.push((qc.round, qc.certified_block_hash, qc.state.clone(), voters))
+            }
+            _ => unreachable!(),
+        }
+    }
+    let expected_blocks: Vec<_> = store
+        .verified_blocks()
+        .into_iter()
+        .map(|(_, block)| {
+            (
+                block.round,
+                block.author,
+                block.command.clone(),
+                block.previous_block_hash,
+            )
+        })
+        .collect();
+    assert_eq!(blocks, expected_blocks);
+    let expected_qcs: Vec<_> = store
+        .verified_quorum_certificates()
+        .into_iter()
+        .map(|(_, qc)| {
+            let mut voters = qc.votes.authors();
+            voters.sort();
+            (qc.round, qc.certified_block_hash, qc.state.clone(), voters)
+        })
+        .collect();
+    assert_eq!(qcs, expected_qcs);
+    trace
+}
+
+#[test]
+fn test_diem_round_trip() {
+    let trace = check_round_trip("seed = 5\nnodes = 4\n[termination]\nmax_clock = 300");
+    assert!(trace
+        .blocks
+        .iter()
+        .all(|block| block.block_data.previous_block_hash.is_none()));
+}
+
+#[test]
+fn test_diem_chained_blocks() {
+    let trace = check_round_trip(
+        "seed = 5\nnodes = 4\n[workload]\nblocks_per_round = 2\n[termination]\nmax_clock = 300",
+    );
+    assert!(trace
+        .blocks
+        .iter()
+        .any(|block| block.block_data.previous_block_hash.is_some()));
+}
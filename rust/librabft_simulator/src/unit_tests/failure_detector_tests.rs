@@ -0,0 +1,74 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_heartbeat_detector() {
+    let peers = [Author(1), Author(2)];
+    let mut detector = FailureDetector::new(FailureDetectorPolicy::Heartbeat {
+        timeout: Duration::from_millis(100),
+    });
+    assert_eq!(detector.suspicion(Author(1), NodeTime(0)), 0.0);
+    detector.update(&peers, NodeTime(0));
+    for time in (0..500).step_by(50) {
+        detector.hear_from(Author(1));
+        detector.update(&peers, NodeTime(time));
+    }
+    // The silent peer is suspected once the timeout has passed since the first update.
+    assert_eq!(
+        detector.suspected().iter().collect::<Vec<_>>(),
+        [&Author(2)]
+    );
+    assert_eq!(detector.suspicions(), 1);
+    let levels = detector.suspicion_levels(&peers, NodeTime(500));
+    assert_eq!(levels[&Author(1)], 0.5);
+    assert_eq!(levels[&Author(2)], 5.0);
+    // Hearing from a peer clears the suspicion, and silence brings it back.
+    detector.hear_from(Author(2));
+    detector.update(&peers, NodeTime(500));
+    assert!(!detector.is_suspected(Author(2)));
+    detector.update(&peers, NodeTime(700));
+    assert!(detector.is_suspected(Author(1)) && detector.is_suspected(Author(2)));
+    assert_eq!(detector.suspicions(), 3);
+    assert_eq!(detector.restart().suspicions(), 3);
+}
+
+#[test]
+fn test_phi_accrual_detector() {
+    let peers = [Author(1), Author(2)];
+    let policy: FailureDetectorPolicy = "phi-accrual:2,4,10".parse().unwrap();
+    assert!(policy.check().is_ok());
+    let mut detector = FailureDetector::new(policy);
+    // Peer 1 speaks every 10ms and peer 2 every 100ms.
+    for time in (0..=1000).step_by(10) {
+        detector.hear_from(Author(1));
+        if time % 100 == 0 {
+            detector.hear_from(Author(2));
+        }
+        detector.update(&peers, NodeTime(time));
+    }
+    assert!(detector.suspected().is_empty());
+    // The same silence is more suspicious for the peer that usually speaks more often.
+    let levels = detector.suspicion_levels(&peers, NodeTime(1100));
+    assert!(levels[&Author(1)] > 5.0 * levels[&Author(2)]);
+    detector.update(&peers, NodeTime(1100));
+    assert_eq!(
+        detector.suspected().iter().collect::<Vec<_>>(),
+        [&Author(1)]
+    );
+
+    assert!("heartbeat:0"
+        .parse::<FailureDetectorPolicy>()
+        .unwrap()
+        .check()
+        .is_err());
+    assert!("phi-accrual:2".parse::<FailureDetectorPolicy>().is_err());
+    // Windows are numbers of intervals.
+    assert!("phi-accrual:2,-1,10"
+        .parse::<FailureDetectorPolicy>()
+        .is_err());
+    assert!("phi-accrual:2,2.5,10"
+        .parse::<FailureDetectorPolicy>()
+        .is_err());
+}
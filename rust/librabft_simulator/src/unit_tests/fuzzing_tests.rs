@@ -0,0 +1,47 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_decode_honest_history() {
+    // Five rounds where nodes 0, 1, and 2 vote, then no arbitrary records.
+    let data = [5, 1, 7, 1, 7, 1, 7, 1, 7, 1, 7];
+    let input = decode_input(&data, 4, None);
+    assert_eq!(input.history.block_hashes().len(), 5);
+    assert!(input.records.is_empty());
+}
+
+#[test]
+fn test_decode_references() {
+    let data = [1, 1, 7, 0, 1, 0, 0, 0, 0, 0];
+    let input = decode_input(&data, 4, None);
+    assert_eq!(input.records.len(), 1);
+    // A vote for the block of the history, in the name of node 0.
+    match &input.records[0].1 {
+        Record::Vote(vote) => {
+            assert_eq!(vote.author, Author(0));
+            assert!(input
+                .history
+                .block_hashes()
+                .contains(&vote.certified_block_hash));
+        }
+        record => panic!("Unexpected record {:?}", record),
+    }
+}
+
+#[test]
+fn test_fuzz_targets() {
+    let inputs: Vec<Vec<u8>> = vec![
+        vec![],
+        vec![0],
+        vec![3, 5, 1, 7, 1, 7, 1, 7, 1, 7, 1, 7],
+        vec![3, 4, 1, 15, 0, 15, 1, 15, 1, 15, 2, 2, 3, 0, 0],
+        vec![0xff; 64],
+        (0..=255).collect(),
+    ];
+    for input in &inputs {
+        fuzz_record_insertion(input);
+        fuzz_byzantine_records(input);
+    }
+}
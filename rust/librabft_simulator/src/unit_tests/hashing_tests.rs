@@ -0,0 +1,52 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+/// A weak hash function that only looks at the length of the input.
+#[derive(Debug)]
+struct LengthRecordHasher;
+
+#[derive(Default)]
+struct LengthHasher(u64);
+
+impl Hasher for LengthHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 += bytes.len() as u64;
+    }
+}
+
+impl RecordHasher for LengthRecordHasher {
+    fn hasher(&self) -> Box<Hasher> {
+        Box::new(LengthHasher::default())
+    }
+}
+
+#[test]
+fn test_record_hasher() {
+    let default_digest = digest(&(1u64, 2u64));
+    let previous = set_record_hasher(Arc::new(LengthRecordHasher));
+    assert_eq!(digest(&(1u64, 2u64)), 16);
+    assert_ne!(digest(&(1u64, 2u64)), default_digest);
+    set_record_hasher(previous);
+    assert_eq!(digest(&(1u64, 2u64)), default_digest);
+}
+
+#[test]
+fn test_counting_record_hasher() {
+    let hasher = CountingRecordHasher::new(SipRecordHasher);
+    let counter = hasher.counter();
+    set_record_hasher(Arc::new(hasher));
+    let parameters = scenario::Scenario::parse("seed = 3\nnodes = 4")
+        .unwrap()
+        .parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.summary().committed_blocks > 0.0);
+    // Records are hashed when they are created and each time they are verified.
+    assert!(counter.load(Ordering::Relaxed) > 0);
+}
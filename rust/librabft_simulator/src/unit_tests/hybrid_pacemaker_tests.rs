@@ -0,0 +1,42 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_hybrid_pacemaker() {
+    // Rounds are too short for the network, so that most of them time out at first.
+    let toml = "seed = 3\nnodes = 4\n[pacemaker]\ndelta = 2\n[termination]\nmax_clock = 2000\n";
+    let hybrid = "[pacemaker.hybrid]\nconservative_delta = 100\nwindow = 4\n";
+    let run = |contents: &str| {
+        let parameters = scenario::Scenario::parse(contents).unwrap().parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        sim.metrics_report()
+    };
+    let report = run(toml);
+    assert!(report.nodes.iter().all(|node| node.pacemaker_switches == 0));
+    let report = run(&format!("{}{}", toml, hybrid));
+    assert!(report.nodes.iter().any(|node| node.pacemaker_switches > 0));
+    for node in &report.nodes {
+        let times = &node.pacemaker_switch_times;
+        assert_eq!(times.len(), node.pacemaker_switches);
+        assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(times.iter().all(|time| time.0 <= report.aggregate.duration));
+    }
+    assert!(report.aggregate.committed_blocks > 0.0);
+
+    let policy = HybridPolicy::default();
+    assert!(policy.check(Duration::from_millis(20)).is_ok());
+    assert!(policy.check(Duration::from_millis(200)).is_err());
+    let policy = HybridPolicy {
+        window: 0,
+        ..HybridPolicy::default()
+    };
+    assert!(policy.check(Duration::from_millis(20)).is_err());
+    let policy = HybridPolicy {
+        min_timeout_rate: 0.8,
+        ..HybridPolicy::default()
+    };
+    assert!(policy.check(Duration::from_millis(20)).is_err());
+}
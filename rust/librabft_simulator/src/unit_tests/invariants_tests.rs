@@ -0,0 +1,76 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bft_simulator_runtime::termination::StopReason;
+use scenario::Scenario;
+
+#[test]
+fn test_builtin_invariants_hold() {
+    let parameters = Scenario::parse("seed = 7\nnodes = 4\ncheck_lemmas = true")
+        .unwrap()
+        .parameters();
+    assert!(parameters.check_lemmas);
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+}
+
+#[test]
+fn test_builtin_invariants_hold_with_two_chains() {
+    let contents = "seed = 7\nnodes = 4\ncommit_rule = \"two-chain\"";
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    assert!(sim
+        .simulated_nodes()
+        .iter()
+        .any(|node| !node.node().committed_blocks().is_empty()));
+}
+
+#[test]
+fn test_monotonic_epoch() {
+    let parameters = Scenario::parse("seed = 7").unwrap().parameters();
+    let sim = make_simulator(&parameters);
+    let mut invariant = MonotonicEpoch::default();
+    let nodes = sim.simulated_nodes();
+    assert_eq!(invariant.check(nodes), Ok(()));
+    // Pretend that node 0 was seen at a later epoch.
+    invariant.observed.insert(Author(0), EpochId(1));
+    let message = invariant.check(nodes).unwrap_err();
+    assert!(message.contains("back to EpochId(0)"));
+}
+
+#[test]
+fn test_paper_lemmas() {
+    let parameters = Scenario::parse("seed = 7\nnodes = 4").unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert_eq!(audit(sim.simulated_nodes()), Ok(()));
+
+    // The stores of an unrelated run certify other blocks at the same rounds.
+    let contents = r#"
+        seed = 7
+        nodes = 4
+
+        [workload]
+        max_batch_size = 10
+
+        [workload.arrivals]
+        kind = "constant-rate"
+        rate = 1.0
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut other_sim = make_simulator(&parameters);
+    other_sim.run(&parameters.termination(), None);
+    let stores = vec![
+        sim.simulated_node(Author(0)).node().record_store_state(),
+        other_sim
+            .simulated_node(Author(0))
+            .node()
+            .record_store_state(),
+    ];
+    let message = check_lemmas(&stores).unwrap_err();
+    assert!(message.contains("are both certified"));
+}
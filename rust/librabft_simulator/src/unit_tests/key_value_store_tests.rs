@@ -0,0 +1,78 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bft_simulator_runtime::mempool::Transaction;
+
+fn command(proposer: usize, indices: &[usize]) -> Command {
+    Command {
+        proposer: Author(proposer),
+        index: 0,
+        transactions: indices
+            .iter()
+            .map(|index| Transaction {
+                index: *index,
+                submission_time: NodeTime(0),
+                size: 0,
+                targeted: None,
+                request: None,
+                proposals: 0,
+            })
+            .collect(),
+        parameter_change: None,
+    }
+}
+
+#[test]
+fn test_key_value_store() {
+    let c1 = command(0, &[0, 1, 2]);
+    let c2 = command(1, &[0, 1]);
+    let mut s1 = KeyValueStore::new();
+    let mut s2 = KeyValueStore::new();
+    assert_eq!(s1.state_hash(), s2.state_hash());
+    s1.execute(&c1, 2);
+    assert!(!s1.is_empty());
+    assert!(s1.len() <= 2);
+    assert!((0..2).any(|key| s1.get(key).is_some()));
+    assert_eq!(s1.get(2), None);
+    s1.execute(&c2, 2);
+    // Execution is deterministic but depends on the order of commands.
+    s2.execute(&c1, 2);
+    s2.execute(&c2, 2);
+    assert_eq!(s1, s2);
+    assert_eq!(s1.state_hash(), s2.state_hash());
+    let mut s3 = KeyValueStore::new();
+    s3.execute(&c2, 2);
+    s3.execute(&c1, 2);
+    assert_ne!(s1.state_hash(), s3.state_hash());
+    // Empty commands leave the store unchanged.
+    s3 = s1.clone();
+    s3.execute(&command(2, &[]), 2);
+    assert_eq!(s1, s3);
+}
+
+#[test]
+fn test_execution_model() {
+    let model = "100:5:2".parse::<ExecutionModel>().unwrap();
+    assert_eq!(
+        model,
+        ExecutionModel {
+            num_keys: 100,
+            block_cost: Duration::from_millis(5),
+            transaction_cost: Duration::from_millis(2),
+        }
+    );
+    assert_eq!(
+        model.execution_time(&command(0, &[])),
+        Duration::from_millis(5)
+    );
+    assert_eq!(
+        model.execution_time(&command(0, &[0, 1, 2])),
+        Duration::from_millis(11)
+    );
+    assert!(ExecutionModel::default().check().is_ok());
+    assert!("0:5:2".parse::<ExecutionModel>().is_err());
+    assert!("100:-1:2".parse::<ExecutionModel>().is_err());
+    assert!("100:5".parse::<ExecutionModel>().is_err());
+    assert!("100:x:2".parse::<ExecutionModel>().is_err());
+}
@@ -0,0 +1,40 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+#[test]
+fn test_load_curve() {
+    assert!(LoadLevels {
+        step: 0.0,
+        ..LoadLevels::default()
+    }
+    .check()
+    .is_err());
+
+    // Blocks of one transaction saturate quickly.
+    let parameters = Scenario::parse("seed = 2\nnodes = 4\n[workload]\nmax_batch_size = 1")
+        .unwrap()
+        .parameters();
+    let levels = LoadLevels {
+        start_rate: 0.1,
+        step: 0.1,
+        max_levels: 10,
+        saturation_ratio: 0.9,
+    };
+    let curve = run_load_curve(&parameters, &levels);
+    assert!(curve.saturation_rate().is_some());
+    // Levels stop at the first saturated one.
+    let (last, others) = curve.points.split_last().unwrap();
+    assert!(last.saturated);
+    assert!(others.iter().all(|point| !point.saturated));
+    for (level, point) in curve.points.iter().enumerate() {
+        assert!((point.rate - levels.rate(level)).abs() < 1e-9);
+    }
+
+    let mut output = Vec::new();
+    write_csv(&[curve.clone()], csv::Writer::from_writer(&mut output)).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 1 + curve.points.len());
+}
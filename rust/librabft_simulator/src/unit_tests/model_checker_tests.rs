@@ -0,0 +1,52 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+fn options(max_runs: usize) -> ModelCheckingOptions {
+    ModelCheckingOptions {
+        width: 2,
+        max_depth: 30,
+        max_runs,
+        state_hashing: true,
+    }
+}
+
+#[test]
+fn test_exploration() {
+    let parameters = Scenario::parse("seed = 1\nnodes = 4").unwrap().parameters();
+    let report = check(&parameters, &options(50)).unwrap();
+    assert!(report.violation.is_none());
+    assert!(report.runs > 1);
+    assert!(report.runs <= 50);
+}
+
+#[test]
+fn test_complete_exploration() {
+    let parameters = Scenario::parse("seed = 1\nnodes = 4").unwrap().parameters();
+    let options = ModelCheckingOptions {
+        max_depth: 6,
+        ..options(10_000)
+    };
+    let report = check(&parameters, &options).unwrap();
+    assert!(report.complete);
+    // At most two choices per step.
+    assert!(report.runs <= 1 << 6);
+}
+
+#[test]
+fn test_too_many_nodes() {
+    let parameters = Scenario::parse("nodes = 8").unwrap().parameters();
+    assert!(check(&parameters, &options(1)).is_err());
+}
+
+#[test]
+fn test_coverage_exploration() {
+    let parameters = Scenario::parse("seed = 1\nnodes = 4").unwrap().parameters();
+    let report = explore(&parameters, &options(20), 3).unwrap();
+    assert!(report.violation.is_none());
+    assert_eq!(report.runs, 20);
+    assert!(!report.complete);
+    assert!(report.states > 1);
+}
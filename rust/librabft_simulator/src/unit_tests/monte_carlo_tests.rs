@@ -0,0 +1,111 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bft_simulator_runtime::{
+    corruption::CorruptionModel, network_trace::NetworkTrace, quota::MessageQuota,
+    statistics::MeasurementPhases,
+};
+
+fn parameters(seed: u64) -> SimulationParameters {
+    SimulationParameters {
+        max_clock: simulator::GlobalTime(500),
+        max_commits: None,
+        max_rounds_without_progress: None,
+        max_time_without_commit: None,
+        stall_threshold: None,
+        measurement: MeasurementPhases::default(),
+        mean: 10.0,
+        variance: 4.0,
+        loss_rate: 0.0,
+        network_trace: NetworkTrace::default(),
+        nodes: 4,
+        commands_per_epoch: 30_000,
+        target_commit_interval: Duration::from_millis(100_000),
+        arrivals: None,
+        targeted_arrivals: None,
+        clients: None,
+        max_batch_size: 100,
+        transaction_size: 100,
+        max_block_payload: None,
+        max_batch_wait: None,
+        empty_block_policy: EmptyBlockPolicy::Propose,
+        backend: ExecutionBackend::Ledger,
+        execution: None,
+        execution_latency: Duration::ZERO,
+        pipelined_execution: false,
+        delta: Duration::from_millis(20),
+        gamma: 2.0,
+        lambda: 0.5,
+        round_jitter: 0.0,
+        retransmission: None,
+        hybrid_pacemaker: None,
+        peer_selection: PeerSelection::Leader,
+        failure_detector: None,
+        push_pull: None,
+        data_sync_version: DATA_SYNC_VERSION,
+        suppress_redundant_records: false,
+        strict_verification: false,
+        state_sync: false,
+        message_corruption: CorruptionModel::default(),
+        message_quota: MessageQuota::default(),
+        blocks_per_round: 1,
+        max_uncertified_blocks: None,
+        commit_rule: CommitRule::ThreeChain,
+        vote_delivery: VoteDelivery::Proposer,
+        fast_path_wait: None,
+        aggregate_signatures: false,
+        threshold_signatures: false,
+        crypto_costs: CryptoCosts::default(),
+        message_authentication: MessageAuthentication::None,
+        seed,
+        node_overrides: BTreeMap::new(),
+        crashes: BTreeMap::new(),
+        recoveries: BTreeMap::new(),
+        scheduled_faults: Vec::new(),
+        byzantine: BTreeSet::new(),
+        observers: BTreeSet::new(),
+        stakes: BTreeMap::new(),
+        validator_sets: Vec::new(),
+        reconfigurations: Vec::new(),
+        parameter_changes: Vec::new(),
+        join_times: BTreeMap::new(),
+        record_decisions: false,
+        check_lemmas: false,
+    }
+}
+
+#[test]
+fn test_same_seed_same_run() {
+    assert_eq!(
+        sweep::run_summary(&parameters(7)),
+        sweep::run_summary(&parameters(7))
+    );
+}
+
+#[test]
+fn test_run_monte_carlo() {
+    let base = parameters(3);
+    let mut grid = SweepGrid::single(&base);
+    grid.nodes = vec![3, 4];
+    let results = run_monte_carlo(&base, &grid, 5);
+    assert_eq!(results.len(), 2);
+    for result in &results {
+        assert_eq!(result.summary.runs, 5);
+        assert_eq!(result.summary.live_runs, 5);
+        assert!(!result.summary.is_liveness_seed_dependent());
+        let latency = result.summary.mean_commit_latency.as_ref().unwrap();
+        assert_eq!(latency.count, 5);
+        assert!(latency.confidence_interval.0 <= latency.mean);
+        assert!(latency.mean <= latency.confidence_interval.1);
+        assert!(latency.min <= latency.mean && latency.mean <= latency.max);
+        assert!(latency.std_dev > 0.0);
+        assert_eq!(
+            result.summary.metrics().len(),
+            MonteCarloSummary::METRICS.len()
+        );
+        for name in result.summary.seed_sensitive_metrics() {
+            assert!(MonteCarloSummary::METRICS.contains(&name));
+        }
+    }
+}
@@ -2,12 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use bft_simulator_runtime::{
+    handler_profile::Handler,
+    workload::{ArrivalProcess, WorkloadGenerator},
+};
+use data_sync::*;
 use simulated_context::*;
 use smr_context::*;
-use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
-};
+use std::time::Duration as StdDuration;
 
 #[test]
 fn test_node() {
@@ -23,20 +25,18 @@ fn test_node() {
         Author(0),
         initial_state.clone(),
         NodeTime(0),
-        1000,
-        30,
+        Duration::from_millis(1000),
+        Duration::from_millis(30),
         2.0,
         0.5,
+        CommitRule::ThreeChain,
         &context,
     );
 
     // Make a sequence of blocks / QCs
-    let cmd = context.fetch().unwrap();
+    let cmd = context.fetch(NodeTime(0)).unwrap();
     let b0 = Record::make_block(cmd.clone(), NodeTime(1), initial_hash, Round(1), Author(0));
-
-    let mut hasher = DefaultHasher::new();
-    b0.hash(&mut hasher);
-    let block_hash = BlockHash(hasher.finish());
+    let block_hash = BlockHash(b0.digest());
 
     let state = context
         .compute(&initial_state, cmd.clone(), NodeTime(1), None, Vec::new())
@@ -58,7 +58,7 @@ fn test_node() {
         Round(1),
         block_hash,
         state,
-        /* votes */ vec![(Author(0), v0.signature)],
+        /* votes */ MultiSignature::Individual(vec![(Author(0), v0.signature)]),
         /* commitment */ None,
         Author(0),
     );
@@ -71,3 +71,356 @@ fn test_node() {
         qc_hash
     );
 }
+
+#[test]
+fn test_execution_latency() {
+    let mut context =
+        SimulatedContext::new(Author(0), 1, 100).with_execution_latency(Duration::from_millis(10));
+    let mut node = NodeState::new(
+        Author(0),
+        context.last_committed_state(),
+        NodeTime(0),
+        Duration::from_millis(1000),
+        Duration::from_millis(30),
+        2.0,
+        0.5,
+        CommitRule::ThreeChain,
+        &context,
+    );
+    // The node proposes a block, then waits for its execution before voting.
+    let actions = node.update_node(NodeTime(0), &mut context);
+    assert_eq!(node.safety_rules.latest_voted_round(), Round(0));
+    assert!(actions.next_scheduled_update <= NodeTime(10));
+    node.update_node(NodeTime(5), &mut context);
+    assert_eq!(node.safety_rules.latest_voted_round(), Round(0));
+    node.update_node(NodeTime(10), &mut context);
+    assert_eq!(node.safety_rules.latest_voted_round(), Round(1));
+}
+
+#[test]
+fn test_pipelined_execution() {
+    let mut context =
+        SimulatedContext::new(Author(0), 1, 100).with_execution_latency(Duration::from_millis(10));
+    let mut node = NodeState::new(
+        Author(0),
+        context.last_committed_state(),
+        NodeTime(0),
+        Duration::from_millis(1000),
+        Duration::from_millis(30),
+        2.0,
+        0.5,
+        CommitRule::ThreeChain,
+        &context,
+    )
+    .with_pipelined_execution(true);
+    // The node votes on its proposals right away.
+    for _ in 0..100 {
+        if node.record_store.highest_committed_round() > Round(0) {
+            break;
+        }
+        node.update_node(NodeTime(0), &mut context);
+    }
+    assert_eq!(node.record_store.highest_committed_round(), Round(1));
+    assert!(node.safety_rules.latest_voted_round() >= Round(3));
+    // Blocks are executed one at a time, starting with the first block.
+    assert!(node.committed_blocks.is_empty());
+    assert_eq!(
+        node.commit_wait_end(NodeTime(5), &context),
+        Some(NodeTime(10))
+    );
+    assert_eq!(node.commit_wait_end(NodeTime(10), &context), None);
+    // Only the executions of blocks that may still be committed are kept.
+    assert!(!node.background_executions.is_empty());
+    let record_store = &node.record_store;
+    assert!(node
+        .background_executions
+        .keys()
+        .all(|block_hash| record_store.block(*block_hash).unwrap().round > Round(1)));
+    node.process_commits(NodeTime(10), &mut context);
+    assert_eq!(node.committed_blocks.len(), 1);
+}
+
+#[test]
+fn test_retransmission() {
+    let mut context = SimulatedContext::new(Author(0), 2, 100);
+    let mut node = NodeState::new(
+        Author(0),
+        context.last_committed_state(),
+        NodeTime(0),
+        Duration::from_millis(1000),
+        Duration::from_millis(30),
+        2.0,
+        0.5,
+        CommitRule::ThreeChain,
+        &context,
+    )
+    .with_retransmission(Some(RetransmissionPolicy {
+        period: Duration::from_millis(20),
+        backoff: 2.0,
+    }));
+    let mut broadcasts = |time| {
+        node.update_node(NodeTime(time), &mut context)
+            .should_broadcast
+    };
+    broadcasts(0);
+    // The other node is down, so the round times out and our timeout is never certified.
+    assert!(broadcasts(30));
+    assert!(!broadcasts(49));
+    assert!(broadcasts(50));
+    // The time between retransmissions doubles.
+    assert!(!broadcasts(89));
+    assert!(broadcasts(90));
+    assert!(!broadcasts(120));
+    assert_eq!(node.pacemaker.active_round(), Round(1));
+}
+
+#[test]
+fn test_round_jitter() {
+    let deadline = |author, jitter, seed| {
+        let mut context = SimulatedContext::new(author, 4, 100);
+        let mut node = NodeState::new(
+            author,
+            context.last_committed_state(),
+            NodeTime(0),
+            Duration::from_millis(1000),
+            Duration::from_millis(1000),
+            0.0,
+            0.5,
+            CommitRule::ThreeChain,
+            &context,
+        )
+        .with_round_jitter(jitter, seed);
+        node.update_node(NodeTime(0), &mut context);
+        node.pacemaker.active_round_deadline()
+    };
+    let deadlines: Vec<_> = (0..4).map(|i| deadline(Author(i), 0.5, 0)).collect();
+    for &deadline in &deadlines {
+        assert!(deadline >= NodeTime(1000) && deadline <= NodeTime(1500));
+    }
+    assert!(deadlines.iter().any(|deadline| *deadline != deadlines[0]));
+    // Samples are reproducible for a given seed, and disabled by default.
+    assert_eq!(deadline(Author(1), 0.5, 0), deadlines[1]);
+    assert_eq!(deadline(Author(1), 0.0, 0), NodeTime(1000));
+    let reseeded: Vec<_> = (0..4).map(|i| deadline(Author(i), 0.5, 1)).collect();
+    assert_ne!(reseeded, deadlines);
+}
+
+#[test]
+fn test_max_batch_wait() {
+    let process = ArrivalProcess::ConstantRate { rate: 1.0 };
+    let mut context = SimulatedContext::new(Author(0), 1, 100)
+        .with_workload(WorkloadGenerator::new(process, 0), 5);
+    let mut node = NodeState::new(
+        Author(0),
+        context.last_committed_state(),
+        NodeTime(0),
+        Duration::from_millis(1000),
+        Duration::from_millis(30),
+        2.0,
+        0.5,
+        CommitRule::ThreeChain,
+        &context,
+    )
+    .with_max_batch_wait(Some(Duration::from_millis(20)));
+    // The leader waits for a full block.
+    let actions = node.update_node(NodeTime(0), &mut context);
+    assert_eq!(node.record_store.proposed_block(&node.pacemaker), None);
+    assert!(actions.next_scheduled_update <= NodeTime(20));
+    node.update_node(NodeTime(3), &mut context);
+    assert_eq!(node.record_store.proposed_block(&node.pacemaker), None);
+    node.update_node(NodeTime(5), &mut context);
+    let (block_hash, _, _) = node.record_store.proposed_block(&node.pacemaker).unwrap();
+    let block = node.record_store.block(block_hash).unwrap();
+    assert_eq!(block.command.transactions.len(), 5);
+}
+
+#[test]
+fn test_empty_block_delay() {
+    let make_node = |context: &SimulatedContext| {
+        NodeState::new(
+            Author(0),
+            context.last_committed_state(),
+            NodeTime(0),
+            Duration::from_millis(1000),
+            Duration::from_millis(30),
+            2.0,
+            0.5,
+            CommitRule::ThreeChain,
+            context,
+        )
+        .with_empty_block_policy(EmptyBlockPolicy::Delay {
+            max_delay: Duration::from_millis(20),
+        })
+    };
+    // The leader defers its proposal, but wakes up when the first transaction arrives.
+    let process = ArrivalProcess::ConstantRate { rate: 0.1 };
+    let mut context = SimulatedContext::new(Author(0), 1, 100)
+        .with_workload(WorkloadGenerator::new(process, 0), 5);
+    let mut node = make_node(&context);
+    node.update_node(NodeTime(0), &mut context);
+    let actions = node.update_node(NodeTime(5), &mut context);
+    assert_eq!(node.record_store.proposed_block(&node.pacemaker), None);
+    assert!(actions.next_scheduled_update > NodeTime(5));
+    assert!(actions.next_scheduled_update <= NodeTime(10));
+    node.update_node(NodeTime(10), &mut context);
+    let (block_hash, _, _) = node.record_store.proposed_block(&node.pacemaker).unwrap();
+    let block = node.record_store.block(block_hash).unwrap();
+    assert_eq!(block.command.transactions.len(), 1);
+
+    // Without transactions, the leader proposes an empty block at the end of the delay.
+    let mut context = SimulatedContext::new(Author(0), 1, 100);
+    let mut node = make_node(&context);
+    node.update_node(NodeTime(0), &mut context);
+    node.update_node(NodeTime(5), &mut context);
+    assert_eq!(node.record_store.proposed_block(&node.pacemaker), None);
+    node.update_node(NodeTime(20), &mut context);
+    let (block_hash, _, _) = node.record_store.proposed_block(&node.pacemaker).unwrap();
+    let block = node.record_store.block(block_hash).unwrap();
+    assert!(block.command.transactions.is_empty());
+}
+
+#[test]
+fn test_recover() {
+    let mut context = SimulatedContext::new(Author(0), 1, 100);
+    let mut node = NodeState::new(
+        Author(0),
+        context.last_committed_state(),
+        NodeTime(0),
+        Duration::from_millis(1000),
+        Duration::from_millis(30),
+        2.0,
+        0.5,
+        CommitRule::ThreeChain,
+        &context,
+    );
+    for _ in 0..100 {
+        if node.safety_rules.locked_round() > Round(0) {
+            break;
+        }
+        node.update_node(NodeTime(0), &mut context);
+    }
+    let (latest_voted_round, locked_round) = (
+        node.safety_rules.latest_voted_round(),
+        node.safety_rules.locked_round(),
+    );
+    assert!(locked_round > Round(0));
+    // The voting constraints survive a restart, unlike the vote of the current round.
+    node.recover(NodeTime(1), &mut context);
+    assert_eq!(node.safety_rules.latest_voted_round(), latest_voted_round);
+    assert_eq!(node.safety_rules.locked_round(), locked_round);
+    assert!(node.record_store.current_vote(Author(0)).is_none());
+    // Without durable storage, they would be lost.
+    let mut empty_context = SimulatedContext::new(Author(0), 1, 100);
+    node.recover(NodeTime(2), &mut empty_context);
+    assert_eq!(node.safety_rules.latest_voted_round(), Round(0));
+}
+
+#[test]
+fn test_unexpected_epoch_is_reported() {
+    let mut context = SimulatedContext::new(Author(0), 1, 100);
+    let mut node = NodeState::new(
+        Author(0),
+        context.last_committed_state(),
+        NodeTime(0),
+        Duration::from_millis(1000),
+        Duration::from_millis(30),
+        2.0,
+        0.5,
+        CommitRule::ThreeChain,
+        &context,
+    );
+    // Records of a future epoch are skipped instead of aborting the run.
+    let response = DataSyncResponse {
+        version: DATA_SYNC_VERSION,
+        current_epoch: EpochId(3),
+        records: vec![(EpochId(3), Vec::new())],
+        checkpoint: None,
+        checkpoint_blocks: Vec::new(),
+    };
+    node.handle_response(Author(1), response, &mut context, NodeTime(0));
+    let errors = node.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("EpochId(3)"));
+    assert!(node.take_errors().is_empty());
+}
+
+#[test]
+fn test_inspect_nodes() {
+    let parameters =
+        scenario::Scenario::parse("seed = 3\nnodes = 4\n[termination]\nmax_clock = 300")
+            .unwrap()
+            .parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.crash_node(Author(3), simulator::GlobalTime(0));
+    sim.run(&parameters.termination(), None);
+
+    let views = sim.inspect_nodes();
+    assert_eq!(views.len(), 4);
+    let view = &views[0];
+    let node = sim.simulated_node(Author(0)).node();
+    assert!(view.running);
+    assert_eq!(view.active_round, node.active_round());
+    assert_eq!(view.active_leader, node.active_leader());
+    assert_eq!(
+        view.highest_quorum_certificate_round,
+        node.record_store.highest_quorum_certificate_round()
+    );
+    assert!(view.highest_quorum_certificate_round > Round(0));
+    assert_eq!(view.committed_blocks, node.committed_blocks().len());
+    assert!(!views[3].running);
+}
+
+#[test]
+fn test_blocks_per_round() {
+    let parameters = scenario::Scenario::parse(
+        "seed = 3\nnodes = 4\n[workload]\nblocks_per_round = 3\n[termination]\nmax_clock = 300",
+    )
+    .unwrap()
+    .parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+
+    let node = sim.simulated_node(Author(0)).node();
+    assert!(!node.committed_blocks().is_empty());
+    for committed_block in node.committed_blocks() {
+        // Each commit covers a full chain of blocks proposed in the same round.
+        let chain = node
+            .record_store
+            .round_chain(BlockHash(committed_block.hash));
+        assert_eq!(chain.len(), 3);
+        assert!(chain
+            .iter()
+            .all(|block| block.round == committed_block.round));
+        assert_eq!(chain[0].previous_block_hash, None);
+    }
+}
+
+#[test]
+fn test_profile_handlers() {
+    let parameters =
+        scenario::Scenario::parse("seed = 3\nnodes = 4\n[termination]\nmax_clock = 300")
+            .unwrap()
+            .parameters();
+    let mut sim = make_simulator(&parameters);
+    assert!(sim.handler_profile().is_none());
+    sim.profile_handlers();
+    sim.run(&parameters.termination(), None);
+
+    let profile = sim.handler_profile().unwrap();
+    let aggregate = profile.aggregate();
+    for handler in &[
+        Handler::Update,
+        Handler::Notification,
+        Handler::RecordInsertion,
+    ] {
+        assert!(aggregate[handler].calls > 0);
+    }
+    // Insertions are timed within the handlers that receive records.
+    let receiving: StdDuration = [Handler::Notification, Handler::Response]
+        .iter()
+        .filter_map(|handler| aggregate.get(handler))
+        .map(|time| time.total)
+        .sum();
+    assert!(aggregate[&Handler::RecordInsertion].total <= receiving);
+    assert!(!profile.node(Author(0)).is_empty());
+}
@@ -1,4 +1,95 @@
 // Copyright (c) Calibra Research
 // SPDX-License-Identifier: Apache-2.0
 
+use super::*;
+use simulated_context::SimulatedContext;
+use smr_context::*;
 
+#[test]
+fn test_peer_selection() {
+    assert_eq!(
+        "leader".parse::<PeerSelection>().unwrap(),
+        PeerSelection::Leader
+    );
+    assert_eq!(
+        "random:2".parse::<PeerSelection>().unwrap(),
+        PeerSelection::Random { count: 2 }
+    );
+    assert_eq!(
+        "relays:1,3".parse::<PeerSelection>().unwrap(),
+        PeerSelection::Relays {
+            relays: vec![Author(1), Author(3)]
+        }
+    );
+    assert!("random".parse::<PeerSelection>().is_err());
+    assert!("relays:x".parse::<PeerSelection>().is_err());
+
+    let context = SimulatedContext::new(Author(0), 4, 100);
+    let state = context.last_committed_state();
+    let configuration = context.configuration(&state);
+    let store = RecordStoreState::new(
+        EpochId(0).initial_hash(),
+        state,
+        EpochId(0),
+        configuration,
+        CommitRule::ThreeChain,
+    );
+    let leader = PacemakerState::leader(&store, Round(1));
+    let local_author = Author((leader.0 + 1) % 4);
+    let other = Author((leader.0 + 2) % 4);
+    let peers = |peer_selection| {
+        let mut pacemaker =
+            PacemakerState::new(EpochId(0), NodeTime(0), Duration::from_millis(10), 2.0, 0.5)
+                .with_peer_selection(peer_selection);
+        pacemaker
+            .update_pacemaker(local_author, EpochId(0), &store, NodeTime(0), NodeTime(0))
+            .should_send
+    };
+    assert_eq!(peers(PeerSelection::Leader), vec![leader]);
+    let random = peers(PeerSelection::Random { count: 2 });
+    assert_eq!(random.len(), 2);
+    assert!(random[0] != random[1] && !random.contains(&local_author));
+    assert_eq!(peers(PeerSelection::Random { count: 2 }), random);
+    assert_eq!(peers(PeerSelection::Random { count: 10 }).len(), 3);
+
+    let mut regions = vec![1; 4];
+    regions[leader.0] = 0;
+    regions[other.0] = 0;
+    let mut expected = vec![leader, other];
+    expected.sort();
+    assert_eq!(peers(PeerSelection::LeaderRegion { regions }), expected);
+    assert_eq!(
+        peers(PeerSelection::Relays {
+            relays: vec![local_author, other]
+        }),
+        vec![other]
+    );
+
+    // Suspected peers are skipped, and replaced by others when picked at random.
+    let mut pacemaker =
+        PacemakerState::new(EpochId(0), NodeTime(0), Duration::from_millis(10), 2.0, 0.5)
+            .with_peer_selection(PeerSelection::Random { count: 10 });
+    pacemaker.set_suspected_peers(vec![other].into_iter().collect());
+    let random = pacemaker
+        .update_pacemaker(local_author, EpochId(0), &store, NodeTime(0), NodeTime(0))
+        .should_send;
+    assert_eq!(random.len(), 2);
+    assert!(!random.contains(&other));
+    // So are suspected relays, but the leader is kept.
+    let suspected = |peer_selection| {
+        let mut pacemaker =
+            PacemakerState::new(EpochId(0), NodeTime(0), Duration::from_millis(10), 2.0, 0.5)
+                .with_peer_selection(peer_selection);
+        pacemaker.set_suspected_peers(vec![leader, other].into_iter().collect());
+        pacemaker
+            .update_pacemaker(local_author, EpochId(0), &store, NodeTime(0), NodeTime(0))
+            .should_send
+    };
+    assert_eq!(suspected(PeerSelection::Leader), vec![leader]);
+    assert_eq!(
+        suspected(PeerSelection::Relays {
+            relays: vec![leader, other]
+        }),
+        Vec::<Author>::new()
+    );
+}
@@ -0,0 +1,160 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+fn propose(voters: &[usize]) -> RoundStep {
+    RoundStep::Propose {
+        voters: voters.iter().cloned().collect(),
+    }
+}
+
+#[test]
+fn test_history_commits() {
+    let steps: Vec<_> = (0..5).map(|_| propose(&[0, 1, 2])).collect();
+    let history = NetworkHistory::new(4, &steps);
+    // Each round has a block, 3 votes, and a QC.
+    assert_eq!(history.records.len(), 5 * 5);
+    assert_eq!(history.block_hashes().len(), 5);
+    let (mut store, mut context) = initial_record_store(4);
+    assert_eq!(
+        check_insertions(&mut store, &mut context, history.records),
+        Ok(())
+    );
+    assert_eq!(store.highest_quorum_certificate_round(), Round(5));
+    assert_eq!(store.highest_committed_round(), Round(3));
+}
+
+#[test]
+fn test_history_timeouts() {
+    let steps = vec![
+        propose(&[0]),
+        RoundStep::Timeout {
+            authors: (0..3).collect(),
+        },
+        propose(&[0, 1, 2, 3]),
+    ];
+    let history = NetworkHistory::new(4, &steps);
+    let (mut store, mut context) = initial_record_store(4);
+    assert_eq!(
+        check_insertions(&mut store, &mut context, history.records),
+        Ok(())
+    );
+    assert_eq!(store.highest_timeout_certificate_round(), Round(1));
+    assert_eq!(store.highest_quorum_certificate_round(), Round(2));
+}
+
+#[test]
+fn test_byzantine_records_must_be_signed_by_byzantine_nodes() {
+    let history = NetworkHistory::new(4, &[propose(&[0, 1, 2])]);
+    let byzantine = vec![Author(3)].into_iter().collect();
+    let timeout = Record::make_timeout(EpochId(0), Round(2), Round(1), Author(0));
+    assert!(check_byzantine_records(&history, &byzantine, vec![timeout]).is_err());
+    let too_many = (2..4).map(Author).collect();
+    assert!(check_byzantine_records(&history, &too_many, Vec::new()).is_err());
+}
+
+#[test]
+fn test_differential_commit_rules() {
+    let steps: Vec<_> = (0..5).map(|_| propose(&[0, 1, 2])).collect();
+    let history = NetworkHistory::new(4, &steps);
+    let (mut left, mut left_context) = initial_record_store(4);
+    let (mut right, mut right_context) = initial_record_store(4);
+    assert_eq!(
+        check_differential(
+            &mut left,
+            &mut left_context,
+            &mut right,
+            &mut right_context,
+            history.records.clone(),
+        ),
+        Ok(())
+    );
+    // A store with another commit rule diverges.
+    let (mut left, mut left_context) = initial_record_store(4);
+    let mut right_context = SimulatedContext::new(Author(0), 4, std::usize::MAX);
+    let initial_state = right_context.last_committed_state();
+    let mut right = RecordStoreState::new(
+        EpochId(0).initial_hash(),
+        initial_state.clone(),
+        EpochId(0),
+        right_context.configuration(&initial_state),
+        CommitRule::TwoChain,
+    );
+    assert!(check_differential(
+        &mut left,
+        &mut left_context,
+        &mut right,
+        &mut right_context,
+        history.records,
+    )
+    .is_err());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(50))]
+
+    #[test]
+    fn test_arbitrary_record_stores((store, _) in arb_record_store(4, 30)) {
+        prop_assert_eq!(check_record_store(&store), Ok(()));
+    }
+
+    #[test]
+    fn test_arbitrary_deliveries(records in arb_deliveries(4, 30)) {
+        let (mut store, mut context) = initial_record_store(4);
+        prop_assert_eq!(check_insertions(&mut store, &mut context, records), Ok(()));
+    }
+
+    #[test]
+    fn test_bounded_record_store(history in arb_network_history(4, 30)) {
+        let (mut left, mut left_context) = initial_record_store(4);
+        let (right, mut right_context) = initial_record_store(4);
+        // Evicted blocks never get a QC in honest histories delivered in order.
+        let mut right = right.with_max_uncertified_blocks(Some(1));
+        let result = check_differential(
+            &mut left,
+            &mut left_context,
+            &mut right,
+            &mut right_context,
+            history.records,
+        );
+        prop_assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_byzantine_node(
+        history in arb_network_history(4, 30),
+        records in vec(arb_signed_record(vec![Author(3)]), 0..30),
+    ) {
+        let byzantine = vec![Author(3)].into_iter().collect();
+        prop_assert_eq!(check_byzantine_records(&history, &byzantine, records), Ok(()));
+    }
+
+    #[test]
+    fn test_pacemaker_state(
+        (store, _) in arb_record_store(4, 30),
+        author in 0..4usize,
+        clocks in vec(0..1000i64, 1..5),
+    ) {
+        let mut pacemaker = PacemakerState::new(
+            EpochId(0),
+            NodeTime(0),
+            Duration::from_millis(10),
+            2.0,
+            0.5,
+        );
+        let mut clocks = clocks;
+        clocks.sort();
+        for clock in clocks {
+            let result = check_pacemaker_update(
+                &mut pacemaker,
+                Author(author),
+                EpochId(0),
+                &store,
+                NodeTime(0),
+                NodeTime(clock),
+            );
+            prop_assert!(result.is_ok(), "{:?}", result.err());
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use simulated_context::SimulatedContext;
+
+#[test]
+fn test_protobuf_round_trip() {
+    for signatures in &[
+        "",
+        "aggregate_signatures = true",
+        "threshold_signatures = true",
+        "[[parameter_change]]\ntime = 0\ngamma = 1.5\nmax_block_payload = 500",
+    ] {
+        let contents = format!(
+            "seed = 11\nnodes = 4\n{}\n[termination]\nmax_clock = 300",
+            signatures
+        );
+        let parameters = scenario::Scenario::parse(&contents).unwrap().parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        let node = sim.simulated_node(Author(0)).node();
+        let notification = DataSyncNode::<SimulatedContext>::create_notification(node);
+        assert!(notification.highest_quorum_certificate.is_some());
+        assert_eq!(
+            DataSyncNotification::from_bytes(&notification.to_bytes()).unwrap(),
+            notification
+        );
+        let request =
+            DataSyncNode::<SimulatedContext>::create_request(sim.simulated_node(Author(1)).node());
+        assert_eq!(
+            DataSyncRequest::from_bytes(&request.to_bytes()).unwrap(),
+            request
+        );
+        let response = DataSyncNode::<SimulatedContext>::handle_request(node, &request);
+        assert_eq!(
+            DataSyncResponse::from_bytes(&response.to_bytes()).unwrap(),
+            response
+        );
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(
+            serde_json::from_str::<DataSyncResponse>(&json).unwrap(),
+            response
+        );
+    }
+    // Records need a kind.
+    assert!(Record::from_bytes(&[]).is_err());
+}
+
+#[test]
+fn test_individual_signatures_round_trip() {
+    let signature = |i| (Author(i), Signature::sign(i as u64, Author(i)));
+    // Votes are gathered in increasing order of authors and sent as a bitmap of the signers.
+    let ordered = MultiSignature::new(vec![signature(0), signature(2), signature(70)], false);
+    let json = serde_json::to_string(&ordered).unwrap();
+    assert!(json.contains("\"signers\":[5,64]"));
+    assert_eq!(
+        serde_json::from_str::<MultiSignature>(&json).unwrap(),
+        ordered
+    );
+    let message = ordered.to_message();
+    match &message.kind {
+        Some(messages::MultiSignatureKind::Individual(individual)) => {
+            assert_eq!(individual.signers, vec![5, 64]);
+            assert!(individual.signatures.is_empty());
+        }
+        _ => panic!("Unexpected kind of signatures"),
+    }
+    assert_eq!(
+        MultiSignature::from_bytes(&ordered.to_bytes()).unwrap(),
+        ordered
+    );
+    // Other orders are kept as they are.
+    let unordered = MultiSignature::new(vec![signature(2), signature(0), signature(2)], false);
+    let json = serde_json::to_string(&unordered).unwrap();
+    assert!(!json.contains("signers"));
+    assert_eq!(
+        serde_json::from_str::<MultiSignature>(&json).unwrap(),
+        unordered
+    );
+    assert_eq!(
+        MultiSignature::from_bytes(&unordered.to_bytes()).unwrap(),
+        unordered
+    );
+    // The bitmap must match the signatures.
+    let mut message = ordered.to_message();
+    if let Some(messages::MultiSignatureKind::Individual(individual)) = &mut message.kind {
+        individual.signers = vec![1];
+    }
+    assert!(MultiSignature::from_message(message).is_err());
+}
@@ -0,0 +1,40 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use pyo3::types::PyDict;
+
+#[test]
+fn test_python_scenario() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let mut scenario = PyScenario {
+        scenario: scenario::Scenario::parse("seed = 7\nnodes = 4").unwrap(),
+    };
+    scenario.set_max_clock(500).unwrap();
+    scenario.add_fault(3, 100, None).unwrap();
+    let value = scenario.to_dict(py).unwrap();
+    let copy = PyScenario::from_dict(py, value.as_ref(py)).unwrap();
+    assert_eq!(
+        copy.as_ref(py).to_toml().unwrap(),
+        scenario.to_toml().unwrap()
+    );
+
+    let metrics = scenario.run(py).unwrap();
+    let metrics = metrics.as_ref(py);
+    assert_eq!(metrics.seed().unwrap(), 7);
+    assert_eq!(metrics.stop_reason().unwrap(), "MaxClock");
+    assert!(metrics.committed_blocks().unwrap() > 0.0);
+    let summary = metrics.summary(py).unwrap();
+    let summary: &PyDict = summary.cast_as(py).unwrap();
+    let committed_blocks: f64 = summary
+        .get_item("committed_blocks")
+        .unwrap()
+        .extract()
+        .unwrap();
+    assert_eq!(committed_blocks, metrics.committed_blocks().unwrap());
+
+    // Changes are checked before running.
+    scenario.set_nodes(0).unwrap();
+    assert!(scenario.run(py).is_err());
+}
@@ -0,0 +1,82 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bft_simulator_runtime::termination::StopReason;
+use scenario::Scenario;
+
+#[test]
+fn test_record_store_diff() {
+    // Node 3 stops early, and lags behind the others.
+    let contents = r#"
+        seed = 8
+        nodes = 4
+
+        [[fault]]
+        author = 3
+        crash_time = 200
+
+        [termination]
+        max_clock = 1000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    let node = sim.simulated_node(Author(0)).node();
+    let stuck_node = sim.simulated_node(Author(3)).node();
+
+    let diff = RecordStoreDiff::between_nodes(node, node);
+    assert!(diff.is_identical());
+    assert_eq!(diff.branches, (Vec::new(), Vec::new()));
+    assert_eq!(diff.committed_prefixes_agree, Some(true));
+
+    let diff = RecordStoreDiff::between_nodes(stuck_node, node);
+    assert_eq!(diff.epochs, (EpochId(0), EpochId(0)));
+    assert!(!diff.is_identical());
+    assert!(!diff.missing_left.quorum_certificates.is_empty());
+    // Its chain is a prefix of the others.
+    assert!(diff.branches.0.is_empty());
+    assert!(!diff.branches.1.is_empty());
+    assert!(!diff.branches_diverge());
+    assert!(diff.committed_rounds.0 < diff.committed_rounds.1);
+    assert_eq!(diff.committed_prefixes_agree, Some(true));
+    assert!(diff
+        .to_string()
+        .contains("Missing on the left: blocks at rounds"));
+}
+
+#[test]
+fn test_diverging_prefixes() {
+    // Two separate runs commit different blocks from the same genesis, as two nodes would after
+    // a safety violation.
+    let contents = "seed = {}\nnodes = 4\n[termination]\nmax_clock = 1000\n";
+    let run = |seed: &str| {
+        let parameters = Scenario::parse(&contents.replace("{}", seed))
+            .unwrap()
+            .parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        sim
+    };
+    let (left_sim, right_sim) = (run("8"), run("9"));
+    let left = left_sim.simulated_node(Author(0)).node();
+    let right = right_sim.simulated_node(Author(0)).node();
+
+    let diff = RecordStoreDiff::between_nodes(left, right);
+    assert_eq!(diff.epochs, (EpochId(0), EpochId(0)));
+    assert!(!diff.is_identical());
+    assert!(!diff.missing_left.blocks.is_empty());
+    assert!(!diff.missing_right.blocks.is_empty());
+    // Both chains extend the latest QC that they share.
+    assert!(diff.branches_diverge());
+    assert!(diff.branches.0[0] > diff.common_round);
+    assert!(diff.branches.1[0] > diff.common_round);
+    assert!(diff.committed_rounds.0 > Round(0) && diff.committed_rounds.1 > Round(0));
+    assert_eq!(diff.committed_prefixes_agree, Some(false));
+    assert!(diff.to_string().contains("whose prefixes disagree"));
+    // The comparison is symmetric.
+    let reversed = RecordStoreDiff::between_nodes(right, left);
+    assert_eq!(reversed.branches, (diff.branches.1, diff.branches.0));
+    assert_eq!(reversed.committed_prefixes_agree, Some(false));
+}
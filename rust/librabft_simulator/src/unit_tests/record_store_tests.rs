@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use shared_map::SHARDS;
 use simulated_context::SimulatedContext;
 use smr_context::*;
+use std::sync::Arc;
 
 struct SharedRecordStore {
     store: RecordStoreState,
@@ -12,6 +14,10 @@ struct SharedRecordStore {
 
 impl SharedRecordStore {
     fn new(num_nodes: usize, epoch_ttl: usize) -> Self {
+        Self::with_commit_rule(num_nodes, epoch_ttl, CommitRule::ThreeChain)
+    }
+
+    fn with_commit_rule(num_nodes: usize, epoch_ttl: usize, commit_rule: CommitRule) -> Self {
         let epoch_id = EpochId(0);
         let initial_hash = QuorumCertificateHash(0);
         let mut contexts = HashMap::new();
@@ -32,6 +38,7 @@ impl SharedRecordStore {
                 state.clone(),
                 epoch_id,
                 contexts.get(&Author(0)).unwrap().configuration(&state),
+                commit_rule,
             ),
             contexts,
         }
@@ -291,3 +298,420 @@ fn test_commit() {
             .as_ref()
     );
 }
+
+#[test]
+fn test_two_chain_commit() {
+    let mut shared_store = SharedRecordStore::with_commit_rule(2, 20, CommitRule::TwoChain);
+    shared_store.make_round(NodeTime(10));
+    shared_store.make_tc();
+    shared_store.make_round(NodeTime(30));
+    assert_eq!(shared_store.store.highest_committed_round(), Round(0));
+    shared_store.make_round(NodeTime(40));
+    let store = &shared_store.store;
+    assert_eq!(store.highest_committed_round(), Round(3));
+    assert_eq!(store.highest_commit_certificate().unwrap().round, Round(4));
+
+    let commits = store.committed_states_after(Round(0));
+    assert_eq!(commits.len(), 2);
+    assert_eq!(commits[0].0, Round(1));
+    assert_eq!(commits[1].0, Round(3));
+    assert_eq!(
+        Some(&commits[1].1),
+        store
+            .highest_commit_certificate()
+            .unwrap()
+            .committed_state
+            .as_ref()
+    );
+}
+
+#[test]
+fn test_reinsert_quorum_certificate() {
+    let mut shared_store = SharedRecordStore::new(2, 20);
+    shared_store.make_round(NodeTime(10));
+    let qc = shared_store
+        .store
+        .highest_quorum_certificate()
+        .unwrap()
+        .clone();
+    // Copies of a QC share their votes.
+    assert!(Arc::ptr_eq(
+        &qc.votes,
+        &shared_store
+            .store
+            .highest_quorum_certificate()
+            .unwrap()
+            .votes
+    ));
+    assert!(shared_store
+        .store
+        .is_redundant_record(&Record::QuorumCertificate(Arc::new(qc.clone()))));
+    let mut context = shared_store.contexts.remove(&Author(1)).unwrap();
+    shared_store
+        .store
+        .insert_network_record(Record::QuorumCertificate(Arc::new(qc)), &mut context)
+        .unwrap();
+    let store = &shared_store.store;
+    assert_eq!(store.quorum_certificates.len(), 1);
+    assert_eq!(store.highest_quorum_certificate_round(), Round(1));
+    assert_eq!(store.current_round(), Round(2));
+}
+
+#[test]
+fn test_known_blocks() {
+    let mut shared_store = SharedRecordStore::new(2, 20);
+    shared_store.make_round(NodeTime(10));
+    let block = Block::clone(shared_store.store.blocks.values().next().unwrap());
+    assert!(shared_store.store.is_known_block(&block));
+    assert!(shared_store
+        .store
+        .is_redundant_record(&Record::Block(Arc::new(block.clone()))));
+    // A copy of the block with the same signature but different contents is unknown.
+    let mut forged = block.clone();
+    forged.time = NodeTime(15);
+    assert!(!shared_store.store.is_known_block(&forged));
+    assert!(!SharedRecordStore::new(2, 20).store.is_known_block(&block));
+}
+
+#[test]
+fn test_fast_path_commit() {
+    let mut shared_store = SharedRecordStore::new(4, 20);
+    shared_store.store = shared_store.store.with_fast_path(true);
+    let leader = shared_store.leader(Round(1));
+    shared_store.propose_block(leader.0, QuorumCertificateHash(0), NodeTime(1));
+    let proposed_hash = shared_store.store.current_proposed_block.unwrap();
+    for i in 0..3 {
+        assert!(shared_store.create_vote(i, proposed_hash));
+    }
+    assert!(shared_store.store.is_missing_votes(leader));
+    assert!(shared_store.create_vote(3, proposed_hash));
+    assert!(!shared_store.store.is_missing_votes(leader));
+    assert!(shared_store.check_for_new_quorum_certificate());
+
+    // The block is committed by its own QC, without a commit certificate.
+    let store = &shared_store.store;
+    assert_eq!(store.highest_committed_round(), Round(1));
+    assert_eq!(store.highest_commit_certificate_round(), Round(1));
+    assert!(store.highest_commit_certificate().is_none());
+    assert!(store.is_fast_path_commit(Round(1)));
+    let commits = store.committed_states_after(Round(0));
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].0, Round(1));
+}
+
+#[test]
+fn test_snapshot() {
+    let mut shared_store = SharedRecordStore::new(2, 20);
+    shared_store.make_round(NodeTime(1));
+    let snapshot = shared_store.store.snapshot();
+    let store = &shared_store.store;
+    assert_eq!(store.blocks.shared_shards(&snapshot.blocks), SHARDS);
+    assert_eq!(
+        store
+            .quorum_certificates
+            .shared_shards(&snapshot.quorum_certificates),
+        SHARDS
+    );
+
+    // Writes to the store leave the snapshot untouched.
+    shared_store.make_round(NodeTime(2));
+    shared_store.make_tc();
+    assert_eq!(snapshot.blocks.len(), 1);
+    assert_eq!(snapshot.quorum_certificates.len(), 1);
+    assert_eq!(snapshot.highest_quorum_certificate_round(), Round(1));
+    assert_eq!(snapshot.num_timeout_certificates(), 0);
+    assert_eq!(shared_store.store.blocks.len(), 2);
+    assert_eq!(
+        shared_store.store.highest_quorum_certificate_round(),
+        Round(2)
+    );
+    assert_eq!(shared_store.store.num_timeout_certificates(), 1);
+    // Only the shard of the new block was copied.
+    assert_eq!(
+        shared_store.store.blocks.shared_shards(&snapshot.blocks),
+        SHARDS - 1
+    );
+
+    // Snapshots can be read on other threads.
+    let round = std::thread::spawn(move || snapshot.highest_quorum_certificate_round())
+        .join()
+        .unwrap();
+    assert_eq!(round, Round(1));
+}
+
+#[test]
+fn test_insert_network_records() {
+    let mut shared_store = SharedRecordStore::new(2, 20);
+    shared_store.make_round(NodeTime(10));
+    shared_store.make_round(NodeTime(20));
+    let mut records = shared_store.store.unknown_records(BTreeSet::new());
+    // A forged block and duplicates of valid records are skipped.
+    let mut forged = Block::clone(shared_store.store.blocks.values().next().unwrap());
+    forged.time = NodeTime(15);
+    records.insert(0, Record::Block(Arc::new(forged)));
+    let duplicates = records.clone();
+    records.extend(duplicates);
+
+    let mut other_store = SharedRecordStore::new(2, 20);
+    let context = other_store.contexts.get_mut(&Author(1)).unwrap();
+    assert!(other_store
+        .store
+        .insert_network_records(records, context)
+        .is_ok());
+    let store = &other_store.store;
+    assert_eq!(store.blocks.len(), 2);
+    assert_eq!(store.quorum_certificates.len(), 2);
+    assert_eq!(store.highest_quorum_certificate_round(), Round(2));
+    assert_eq!(store.current_round(), Round(3));
+}
+
+#[test]
+fn test_insert_network_records_checks_fields_first() {
+    let mut shared_store = SharedRecordStore::new(2, 20);
+    shared_store.make_round(NodeTime(10));
+    let mut qc = shared_store
+        .store
+        .highest_quorum_certificate()
+        .unwrap()
+        .clone();
+    qc.epoch_id = EpochId(1);
+
+    // Records of another epoch fail the cheap checks, so they are neither hashed nor checked.
+    let mut other_store = SharedRecordStore::new(2, 20);
+    let context = other_store.contexts.get_mut(&Author(1)).unwrap();
+    let hasher = hashing::CountingRecordHasher::new(hashing::SipRecordHasher);
+    let counter = hasher.counter();
+    let previous = hashing::set_record_hasher(Arc::new(hasher));
+    assert!(other_store
+        .store
+        .insert_network_records(vec![Record::QuorumCertificate(Arc::new(qc))], context)
+        .is_ok());
+    hashing::set_record_hasher(previous);
+    assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 0);
+    assert_eq!(other_store.store.quorum_certificates.len(), 0);
+}
+
+#[test]
+fn test_verification_costs() {
+    let mut shared_store = SharedRecordStore::new(2, 20);
+    shared_store.make_round(NodeTime(10));
+    // Nodes do not pay to verify the records they create.
+    assert!(shared_store
+        .contexts
+        .values()
+        .all(|context| context.signatures_verified() == 0));
+    let block = Block::clone(shared_store.store.blocks.values().next().unwrap());
+    let mut forged = block.clone();
+    forged.time = NodeTime(15);
+    let qc = shared_store
+        .store
+        .highest_quorum_certificate()
+        .unwrap()
+        .clone();
+
+    // Records from the network are charged before their checks, even if they fail them.
+    let mut other_store = SharedRecordStore::new(2, 20);
+    let context = other_store.contexts.get_mut(&Author(1)).unwrap();
+    let store = &mut other_store.store;
+    assert!(store
+        .insert_network_record(Record::Block(Arc::new(forged)), context)
+        .is_ok());
+    assert!(store.blocks.is_empty());
+    assert_eq!(context.signatures_verified(), 1);
+    let block = Record::Block(Arc::new(block));
+    assert!(store.insert_network_record(block.clone(), context).is_ok());
+    assert_eq!(context.signatures_verified(), 2);
+    // Redundant records are skipped before any check.
+    assert!(store.insert_network_record(block, context).is_ok());
+    assert_eq!(context.signatures_verified(), 2);
+    // A QC costs its own signature and the signatures of its votes.
+    assert!(store
+        .insert_network_records(vec![Record::QuorumCertificate(Arc::new(qc))], context)
+        .is_ok());
+    assert_eq!(store.quorum_certificates.len(), 1);
+    assert_eq!(context.signatures_verified(), 5);
+}
+
+#[test]
+fn test_unknown_record_refs() {
+    let mut shared_store = SharedRecordStore::new(2, 20);
+    shared_store.make_round(NodeTime(10));
+    shared_store.make_round(NodeTime(20));
+    shared_store.make_tc();
+    let store = &shared_store.store;
+    let refs = store.unknown_record_refs(BTreeSet::new());
+    assert_eq!(refs.len(), 6);
+    // Stored records keep the digests of their copies.
+    for record in &refs {
+        assert_eq!(record.digest(), record.to_record().digest());
+    }
+    // The chain of both rounds, oldest first, then the timeouts of the TC.
+    let qc2_hash = store.highest_quorum_certificate_hash();
+    let qc2 = store.quorum_certificates[&qc2_hash].clone();
+    let block2 = store.blocks[&qc2.certified_block_hash].clone();
+    let qc1 = store.quorum_certificates[&block2.previous_quorum_certificate_hash].clone();
+    let block1 = store.blocks[&qc1.certified_block_hash].clone();
+    assert_eq!((qc1.round, qc2.round), (Round(1), Round(2)));
+    let records: Vec<_> = refs.iter().map(RecordRef::to_record).collect();
+    assert_eq!(
+        records[..4].to_vec(),
+        vec![
+            Record::Block(block1),
+            Record::QuorumCertificate(qc1),
+            Record::Block(block2),
+            Record::QuorumCertificate(qc2),
+        ]
+    );
+    let mut timeouts: Vec<_> = records[4..]
+        .iter()
+        .map(|record| match record {
+            Record::Timeout(timeout) => (timeout.author, timeout.round),
+            _ => panic!("Unexpected record {:?}", record),
+        })
+        .collect();
+    timeouts.sort();
+    assert_eq!(timeouts, vec![(Author(0), Round(3)), (Author(1), Round(3))]);
+}
+
+#[test]
+fn test_max_uncertified_blocks() {
+    let mut shared_store = SharedRecordStore::new(4, 20);
+    shared_store.store = shared_store.store.with_max_uncertified_blocks(Some(2));
+    shared_store.make_round(NodeTime(1));
+    let certified_hash = shared_store
+        .store
+        .highest_quorum_certificate()
+        .unwrap()
+        .certified_block_hash;
+    // The author of the certified block floods the store with blocks of a later round.
+    let author = shared_store.leader(Round(1));
+    let previous_qc_hash = shared_store.store.highest_quorum_certificate_hash();
+    let mut flood = Vec::new();
+    for i in 0..4 {
+        let command = Command {
+            proposer: author,
+            index: i,
+            transactions: Vec::new(),
+            parameter_change: None,
+        };
+        let block = Record::make_block(command, NodeTime(10), previous_qc_hash, Round(5), author);
+        flood.push(BlockHash(block.digest()));
+        let context = shared_store.contexts.get_mut(&Author(0)).unwrap();
+        shared_store
+            .store
+            .insert_network_record(block, context)
+            .unwrap();
+    }
+    let store = &shared_store.store;
+    assert_eq!(store.evicted_blocks(), 2);
+    assert_eq!(store.blocks.len(), 3);
+    assert!(store.blocks.contains_key(&certified_hash));
+    assert!(store.blocks.contains_key(&flood[2]) && store.blocks.contains_key(&flood[3]));
+}
+
+#[test]
+fn test_vote_set() {
+    let vote = |author| Vote {
+        epoch_id: EpochId(0),
+        round: Round(1),
+        certified_block_hash: BlockHash(3),
+        state: State(4),
+        committed_state: None,
+        author: Author(author),
+        signature: Signature::forged(author as u64),
+        share: None,
+    };
+    let mut votes = VoteSet::new(&vote(70), 1);
+    votes.insert(&vote(2), 1);
+    votes.insert(&vote(65), 2);
+    assert_eq!(votes.signers.len(), 2);
+    assert_eq!(votes.weight, 4);
+    assert_eq!(votes.authors(), vec![Author(2), Author(65), Author(70)]);
+    assert_eq!(votes.signatures()[1], (Author(65), Signature::forged(65)));
+    assert_eq!(votes.vote(Author(65)), Some(vote(65)));
+    assert_eq!(votes.vote(Author(3)), None);
+    assert!(votes.matches(&vote(5)));
+    assert!(!votes.matches(&Vote {
+        state: State(5),
+        ..vote(5)
+    }));
+}
+
+#[test]
+fn test_vote_tallies() {
+    let mut shared_store = SharedRecordStore::new(100, 20);
+    shared_store.store = shared_store.store.with_fast_path(true);
+    let leader = shared_store.leader(Round(1));
+    shared_store.propose_block(leader.0, QuorumCertificateHash(0), NodeTime(1));
+    let proposed_hash = shared_store.store.current_proposed_block.unwrap();
+    let threshold = shared_store.store.configuration.quorum_threshold();
+    for i in 0..100 {
+        let is_won = match shared_store.store.current_election {
+            ElectionState::Won { .. } => true,
+            _ => false,
+        };
+        assert_eq!(is_won, i >= threshold);
+        assert_eq!(shared_store.store.is_missing_votes(leader), is_won);
+        assert!(shared_store.create_vote(i, proposed_hash));
+        // Tallies match a recount of the votes.
+        let votes = &shared_store.store.current_votes[0];
+        assert_eq!(
+            votes.weight,
+            shared_store
+                .store
+                .configuration
+                .count_votes(&votes.authors())
+        );
+    }
+    assert!(!shared_store.store.is_missing_votes(leader));
+}
+
+#[test]
+fn test_commit_proof() {
+    let mut shared_store = SharedRecordStore::new(2, 20);
+    for i in 1..5 {
+        shared_store.make_round(NodeTime(10 * i));
+    }
+    let store = &shared_store.store;
+    assert_eq!(store.highest_committed_round(), Round(2));
+    let configuration = shared_store
+        .contexts
+        .get(&Author(0))
+        .unwrap()
+        .configuration(&store.initial_state);
+    let commits = store.committed_states_after(Round(0));
+    let proof = store.commit_proof(commits[0].4).unwrap();
+    assert_eq!(proof.links.len(), 3);
+    assert_eq!(proof.committed_block().unwrap().round, Round(1));
+    assert_eq!(proof.committed_state(), Some(&commits[0].1));
+    assert!(proof
+        .verify(&configuration, CommitRule::ThreeChain, false)
+        .is_ok());
+    let json = serde_json::to_string(&proof).unwrap();
+    assert_eq!(serde_json::from_str::<CommitProof>(&json).unwrap(), proof);
+
+    // Blocks that are certified but not committed have no proof.
+    let round_three = store
+        .quorum_certificates
+        .values()
+        .find(|qc| qc.round == Round(3))
+        .unwrap();
+    assert!(store
+        .commit_proof(round_three.certified_block_hash)
+        .is_none());
+
+    // Incomplete or tampered proofs are rejected.
+    let mut incomplete = proof.clone();
+    incomplete.links.pop();
+    assert!(incomplete
+        .verify(&configuration, CommitRule::ThreeChain, false)
+        .is_err());
+    assert!(incomplete
+        .verify(&configuration, CommitRule::TwoChain, false)
+        .is_ok());
+    let mut tampered = proof.clone();
+    tampered.links[0].1.state = State(0);
+    assert!(tampered
+        .verify(&configuration, CommitRule::ThreeChain, false)
+        .is_err());
+}
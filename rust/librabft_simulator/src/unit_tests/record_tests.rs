@@ -9,6 +9,8 @@ fn test_block_signing() {
         Command {
             proposer: Author(1),
             index: 2,
+            transactions: Vec::new(),
+            parameter_change: None,
         },
         NodeTime(2),
         QuorumCertificateHash(47),
@@ -21,6 +23,8 @@ fn test_block_signing() {
         Command {
             proposer: Author(3),
             index: 2,
+            transactions: Vec::new(),
+            parameter_change: None,
         },
         NodeTime(2),
         QuorumCertificateHash(47),
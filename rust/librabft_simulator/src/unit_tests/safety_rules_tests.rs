@@ -0,0 +1,44 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_safety_rules() {
+    let mut storage = SimulatedContext::new(Author(0), 1, 100);
+    let mut rules = SafetyRules::new(EpochId(0));
+    assert!(rules.can_vote(Round(1), Round(0)));
+    assert!(rules.record_vote(Round(3), Round(2), Round(1), &mut storage));
+    // Never vote twice at a round, nor for an older one.
+    assert!(!rules.record_vote(Round(3), Round(2), Round(1), &mut storage));
+    assert!(!rules.can_vote(Round(2), Round(1)));
+    // Only extend QCs at the locked round or above.
+    assert!(!rules.record_vote(Round(5), Round(0), Round(2), &mut storage));
+    assert_eq!(rules.latest_voted_round(), Round(3));
+    assert_eq!(rules.locked_round(), Round(1));
+    // Locks never go down.
+    assert!(rules.record_vote(Round(4), Round(3), Round(0), &mut storage));
+    assert_eq!(rules.locked_round(), Round(1));
+    // No votes at a round after a timeout at this round.
+    rules.record_timeout(Round(6), &mut storage);
+    assert!(!rules.can_vote(Round(6), Round(5)));
+    assert!(rules.can_vote(Round(7), Round(5)));
+}
+
+#[test]
+fn test_safety_rules_persistence() {
+    let mut storage = SimulatedContext::new(Author(0), 1, 100);
+    assert_eq!(
+        SafetyRules::load(EpochId(0), &storage),
+        SafetyRules::new(EpochId(0))
+    );
+    let mut rules = SafetyRules::new(EpochId(0));
+    rules.record_vote(Round(2), Round(1), Round(1), &mut storage);
+    assert_eq!(storage.load_safety_data(), Some(rules.data()));
+    assert_eq!(SafetyRules::load(EpochId(0), &storage), rules);
+    // Constraints of a past epoch do not apply.
+    assert_eq!(
+        SafetyRules::load(EpochId(1), &storage),
+        SafetyRules::new(EpochId(1))
+    );
+}
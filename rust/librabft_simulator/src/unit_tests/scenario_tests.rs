@@ -0,0 +1,1309 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bft_simulator_runtime::{message_counters, stall::DropCause, termination::StopReason};
+use decisions::Decision;
+
+#[test]
+fn test_defaults() {
+    let parameters = Scenario::parse("seed = 3").unwrap().parameters();
+    assert_eq!(parameters.seed, 3);
+    assert_eq!(parameters.nodes, 3);
+    assert_eq!(parameters.max_clock, simulator::GlobalTime(1000));
+    assert_eq!(parameters.delta, Duration::from_millis(20));
+    assert!(parameters.node_overrides.is_empty());
+    assert!(parameters.crashes.is_empty());
+    assert!(parameters.byzantine.is_empty());
+    assert_eq!(parameters.commit_rule, CommitRule::ThreeChain);
+}
+
+#[test]
+fn test_example_scenario() {
+    let scenario = Scenario::parse(include_str!("../../scenarios/crash_fault.toml")).unwrap();
+    let parameters = scenario.parameters();
+    assert_eq!(parameters.seed, 42);
+    assert_eq!(parameters.nodes, 4);
+    assert!((parameters.loss_rate - 0.01).abs() < 1e-9);
+    assert_eq!(parameters.max_commits, Some(50));
+    assert_eq!(
+        parameters.node_overrides[&Author(3)].delta,
+        Some(Duration::from_millis(40))
+    );
+    assert_eq!(parameters.node_overrides[&Author(3)].gamma, None);
+    assert_eq!(parameters.crashes[&Author(2)], simulator::GlobalTime(300));
+
+    let summary = sweep::run_summary(&parameters);
+    assert!(summary.committed_blocks > 0.0);
+}
+
+#[test]
+fn test_chain_quality() {
+    let scenario = Scenario::parse("seed = 5\nnodes = 4\nbyzantine = [1]").unwrap();
+    let parameters = scenario.parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    let quality = sim.chain_quality();
+    assert!(quality.honest_blocks > 0);
+    assert!(quality.longest_byzantine_span <= quality.byzantine_blocks);
+    assert_eq!(sim.byzantine_nodes().len(), 1);
+}
+
+#[test]
+fn test_serialization() {
+    let contents = include_str!("../../scenarios/crash_fault.toml");
+    let scenario = Scenario {
+        byzantine: vec![1],
+        commit_rule: CommitRule::TwoChain,
+        ..Scenario::parse(contents).unwrap()
+    };
+    let parsed = Scenario::parse(&scenario.to_toml().unwrap()).unwrap();
+    assert_eq!(parsed.byzantine, vec![1]);
+    assert_eq!(parsed.commit_rule, CommitRule::TwoChain);
+    assert_eq!(parsed.fault.len(), 1);
+    assert_eq!(parsed.node, scenario.node);
+
+    // Random seeds fit in TOML integers, unlike the largest seeds.
+    let seed = Scenario {
+        seed: None,
+        ..scenario.clone()
+    }
+    .parameters()
+    .seed;
+    let scenario = Scenario {
+        seed: Some(seed),
+        ..scenario
+    };
+    let parsed = Scenario::parse(&scenario.to_toml().unwrap()).unwrap();
+    assert_eq!(parsed.seed, Some(seed));
+    assert!(random_seed() <= MAX_SEED);
+    let scenario = Scenario {
+        seed: Some(MAX_SEED + 1),
+        ..scenario
+    };
+    assert!(scenario.to_toml().is_err());
+}
+
+#[test]
+fn test_invalid_scenarios() {
+    assert!(Scenario::parse("nodes = 0").is_err());
+    assert!(Scenario::parse("nodez = 4").is_err());
+    assert!(Scenario::parse("commit_rule = \"one-chain\"").is_err());
+    assert!(Scenario::parse("nodes = 2\n[[fault]]\nauthor = 2\ncrash_time = 10").is_err());
+    assert!(Scenario::parse("nodes = 2\nbyzantine = [2]").is_err());
+    assert!(Scenario::parse("nodes = 2\n[[epoch]]\nvalidators = [2]").is_err());
+    assert!(Scenario::parse("nodes = 2\n[[epoch]]\nvalidators = []").is_err());
+}
+
+#[test]
+fn test_epoch_changes() {
+    let contents = r#"
+        seed = 11
+        nodes = 5
+
+        [workload]
+        commands_per_epoch = 5
+
+        [[epoch]]
+        validators = [0, 1, 2, 3]
+
+        [[epoch]]
+        validators = [1, 2, 3, 4]
+
+        [termination]
+        max_clock = 5000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    assert_eq!(parameters.validator_sets.len(), 2);
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    for node in sim.simulated_nodes() {
+        // Consensus resumes after each epoch change.
+        assert!(node.node().epoch_id() >= EpochId(2));
+        assert!(node.node().committed_blocks().len() > 10);
+    }
+}
+
+#[test]
+fn test_transaction_workload() {
+    let contents = r#"
+        seed = 13
+        nodes = 4
+
+        [workload]
+        max_batch_size = 10
+
+        [workload.arrivals]
+        kind = "poisson"
+        rate = 0.05
+
+        [termination]
+        max_clock = 2000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    let summary = sim.summary();
+    assert!(summary.transaction_throughput > 0.0);
+    assert!(summary.mean_transaction_latency.unwrap() > 0.0);
+    // Clients wait for a quorum of nodes to commit their transactions.
+    let statistics = sim.statistics();
+    let client_latency = statistics.client_latency_distribution().unwrap();
+    let transaction_latency = statistics.transaction_latency_distribution().unwrap();
+    assert!(client_latency.min >= transaction_latency.min);
+    assert!(client_latency.count * 3 <= transaction_latency.count);
+    for context in sim.contexts() {
+        let mut transactions = BTreeSet::new();
+        for (command, _) in context.committed_history() {
+            assert!(command.transactions.len() <= 10);
+            for transaction in &command.transactions {
+                // No transaction is committed twice.
+                assert!(transactions.insert((command.proposer, transaction.index)));
+            }
+        }
+    }
+    assert!(Scenario::parse("[workload.arrivals]\nkind = \"poisson\"\nrate = 0.0").is_err());
+}
+
+#[test]
+fn test_execution_cost() {
+    let contents = r#"
+        seed = 13
+        nodes = 4
+
+        [workload]
+        max_batch_size = 10
+
+        [workload.arrivals]
+        kind = "constant-rate"
+        rate = 0.1
+
+        [termination]
+        max_clock = 2000
+    "#;
+    let mut scenario = Scenario::parse(contents).unwrap();
+    let mut sim = make_simulator(&scenario.parameters());
+    sim.run(&scenario.parameters().termination(), None);
+    let free_commits = sim.statistics().commit_latencies().len();
+    scenario.workload.execution = Some(ExecutionModel {
+        num_keys: 100,
+        block_cost: Duration::from_millis(10),
+        transaction_cost: Duration::from_millis(1),
+    });
+    let parameters = scenario.parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    // Executing blocks slows down the protocol.
+    let commits = sim.statistics().commit_latencies().len();
+    assert!(commits > 0);
+    assert!(commits < free_commits);
+    // Nodes agree on the contents of the store after the same number of commits.
+    let mut stores = BTreeMap::new();
+    for context in sim.contexts() {
+        let store = context.committed_store().unwrap().clone();
+        let num_commits = context.committed_history().len();
+        assert_eq!(*stores.entry(num_commits).or_insert(store.clone()), store);
+    }
+    assert!(stores.values().any(|store| !store.is_empty()));
+    assert!(Scenario::parse("[workload.execution]\nnum_keys = 0").is_err());
+}
+
+#[test]
+fn test_execution_latency() {
+    let mut scenario = Scenario::parse("seed = 5\nnodes = 4").unwrap();
+    let parameters = scenario.parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    let fast_summary = sim.summary();
+    // Executing blocks for longer than the first rounds last causes timeouts.
+    scenario.workload.execution_latency = Duration::from_millis(50);
+    let parameters = scenario.parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    let slow_summary = sim.summary();
+    assert!(slow_summary.committed_blocks > 0.0);
+    assert!(slow_summary.committed_blocks < fast_summary.committed_blocks);
+    // Voting before executing blocks avoids the timeouts.
+    scenario.workload.pipelined_execution = true;
+    let parameters = scenario.parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    let pipelined_summary = sim.summary();
+    assert!(pipelined_summary.committed_blocks > slow_summary.committed_blocks);
+    assert!(pipelined_summary.timeout_rate < slow_summary.timeout_rate);
+    assert!(Scenario::parse("[workload]\nexecution_latency = -1").is_err());
+}
+
+#[test]
+fn test_block_payload() {
+    let contents = r#"
+        seed = 13
+        nodes = 4
+
+        [workload]
+        transaction_size = 100
+        max_block_payload = 300
+        max_batch_wait = 10
+
+        [workload.arrivals]
+        kind = "poisson"
+        rate = 0.2
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.summary().transaction_throughput > 0.0);
+    for context in sim.contexts() {
+        for (command, _) in context.committed_history() {
+            assert!(command.payload_bytes() <= 300);
+        }
+    }
+    let total = message_counters::total_counters(sim.message_counters());
+    assert!(total.sent.records.payload_bytes > 0);
+    assert!(Scenario::parse("[workload]\nmax_batch_wait = -1").is_err());
+}
+
+#[test]
+fn test_reconfigurations() {
+    let contents = r#"
+        seed = 17
+        nodes = 5
+
+        [[reconfiguration]]
+        time = 300
+        add = [4]
+        remove = [0]
+
+        [termination]
+        max_clock = 3000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    assert_eq!(parameters.validator_sets.len(), 1);
+    assert_eq!(
+        parameters.join_times.get(&Author(4)),
+        Some(&simulator::GlobalTime(300))
+    );
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    for node in sim.simulated_nodes() {
+        assert!(node.node().epoch_id() >= EpochId(1));
+    }
+    // The new node catches up and takes part in consensus.
+    let history = sim.contexts()[4].committed_history();
+    assert!(!history.is_empty());
+    assert!(history
+        .iter()
+        .any(|(command, _)| command.proposer == Author(4)));
+    let invalid = "nodes = 2\n[[reconfiguration]]\ntime = 10\nremove = [0, 1]";
+    assert!(Scenario::parse(invalid).is_err());
+    let invalid = "nodes = 2\n[[epoch]]\nvalidators = [0]\n[[reconfiguration]]\nadd = [1]";
+    assert!(Scenario::parse(invalid).is_err());
+}
+
+#[test]
+fn test_stakes() {
+    // Node 3 holds 4 of the 7 votes, so that it forms a quorum with any other node.
+    let contents = r#"
+        seed = 19
+        nodes = 4
+
+        [[node]]
+        author = 3
+        stake = 4
+
+        [[fault]]
+        author = 0
+        crash_time = 0
+
+        [[fault]]
+        author = 1
+        crash_time = 0
+
+        [termination]
+        max_clock = 3000
+        max_time_without_commit = 1000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    assert_eq!(parameters.stakes.get(&Author(3)), Some(&4));
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    // Half of the nodes crashed, but not half of the stake.
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    let history = sim.contexts()[2].committed_history();
+    assert!(!history.is_empty());
+    let summary = sim.summary();
+    assert!(summary.stake_weighted_committed_blocks > summary.committed_blocks);
+    assert_eq!(sim.metrics_report().nodes[3].stake, 4);
+    assert!(Scenario::parse("[[node]]\nauthor = 0\nstake = 0").is_err());
+}
+
+#[test]
+fn test_execution_backends() {
+    let mut scenario = Scenario::parse("seed = 31\nnodes = 4").unwrap();
+    assert_eq!(scenario.backend(), ExecutionBackend::Ledger);
+    for backend in &[
+        ExecutionBackend::Ledger,
+        ExecutionBackend::KeyValue,
+        ExecutionBackend::Null,
+    ] {
+        scenario.workload.backend = Some(*backend);
+        let parameters = scenario.parameters();
+        let mut sim = make_simulator(&parameters);
+        let stop_reason = sim.run(&parameters.termination(), None);
+        assert_eq!(stop_reason, StopReason::MaxClock);
+        assert!(sim.summary().committed_blocks > 0.0);
+        let context = sim.contexts()[0];
+        assert_eq!(context.backend(), *backend);
+        assert_eq!(
+            context.committed_store().is_some(),
+            *backend == ExecutionBackend::KeyValue
+        );
+        // The null backend counts the commands without keeping them.
+        assert!(context.num_committed_commands() > 0);
+        assert_eq!(
+            context.committed_history().is_empty(),
+            *backend == ExecutionBackend::Null
+        );
+    }
+    scenario.workload.backend = Some(ExecutionBackend::Null);
+    scenario.workload.execution = Some(ExecutionModel::default());
+    assert!(scenario.parameters().check_execution().is_err());
+    let contents = "[workload]\nbackend = \"null\"\n[workload.execution]\nnum_keys = 10";
+    assert!(Scenario::parse(contents).is_err());
+    let contents = "[workload.execution]\nnum_keys = 10";
+    assert_eq!(
+        Scenario::parse(contents).unwrap().backend(),
+        ExecutionBackend::KeyValue
+    );
+}
+
+#[test]
+fn test_heterogeneous_nodes() {
+    // Half of the nodes use short rounds, and one of them checks messages strictly. The other
+    // half restarts without some of their durable state.
+    let contents = r#"
+        seed = 29
+        nodes = 4
+        state_sync = true
+
+        [pacemaker]
+        delta = 500
+
+        [[node]]
+        author = 0
+        delta = 100
+        strict_verification = true
+
+        [[node]]
+        author = 1
+        delta = 100
+        state_sync = false
+
+        [[node]]
+        author = 2
+        durable_records = false
+
+        [[node]]
+        author = 3
+        durable_safety_data = false
+
+        [[fault]]
+        author = 2
+        crash_time = 1500
+        recovery_time = 2000
+
+        [[fault]]
+        author = 3
+        crash_time = 3000
+        recovery_time = 3500
+
+        [termination]
+        max_clock = 5000
+    "#;
+    let mut parameters = Scenario::parse(contents).unwrap().parameters();
+    parameters.record_decisions = true;
+    let nodes: Vec<_> = (0..4)
+        .map(|index| {
+            let context = make_context(&parameters, Author(index));
+            make_node(&parameters, Author(index), &context, NodeTime(0))
+        })
+        .collect();
+    let flags: Vec<_> = nodes
+        .iter()
+        .map(|node| (node.strict_verification(), node.state_sync()))
+        .collect();
+    assert_eq!(
+        flags,
+        vec![(true, true), (false, false), (false, true), (false, true)]
+    );
+    let durability: Vec<_> = nodes
+        .iter()
+        .map(|node| (node.durable_safety_data(), node.durable_records()))
+        .collect();
+    assert_eq!(
+        durability,
+        vec![(true, true), (true, true), (true, false), (false, true)]
+    );
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    assert!(sim.summary().committed_blocks > 0.0);
+    assert_eq!(sim.safety_violation(), None);
+    // Voting constraints reloaded by each restarted node.
+    let recovered_round = |author| {
+        let log = sim
+            .simulated_node(Author(author))
+            .node()
+            .decision_log()
+            .unwrap();
+        log.records()
+            .iter()
+            .find_map(|record| match record.decision {
+                Decision::Recover {
+                    latest_voted_round, ..
+                } => Some(latest_voted_round),
+                _ => None,
+            })
+            .unwrap()
+    };
+    // Node 2 kept its voting constraints, and fetched its lost records again from its peers.
+    let node = sim.simulated_node(Author(2)).node();
+    assert_eq!(node.record_losses(), 1);
+    assert!(node.record_store().highest_committed_round() > Round(0));
+    assert!(recovered_round(2) > Round(0));
+    // Node 3 kept its records, but not its voting constraints.
+    assert_eq!(sim.simulated_node(Author(3)).node().record_losses(), 0);
+    assert_eq!(recovered_round(3), Round(0));
+}
+
+#[test]
+fn test_vote_delivery() {
+    let mut scenario =
+        Scenario::parse("seed = 23\nnodes = 7\nvote_delivery = \"next-leader\"").unwrap();
+    let mut votes = Vec::new();
+    for delivery in &[VoteDelivery::NextLeader, VoteDelivery::Broadcast] {
+        scenario.vote_delivery = *delivery;
+        let parameters = scenario.parameters();
+        let mut sim = make_simulator(&parameters);
+        let stop_reason = sim.run(&parameters.termination(), None);
+        assert_eq!(stop_reason, StopReason::MaxClock);
+        let summary = sim.summary();
+        assert!(summary.committed_blocks > 0.0);
+        let total = message_counters::total_counters(sim.message_counters());
+        votes.push(total.sent.records.votes as f64 / summary.committed_blocks);
+    }
+    // Each vote goes to a single node instead of all 7 of them.
+    assert!(votes[1] > 3.0 * votes[0]);
+    assert!(Scenario::parse("vote_delivery = \"everyone\"").is_err());
+}
+
+#[test]
+fn test_fast_path() {
+    let contents = "seed = 29\nnodes = 4\nfast_path_wait = 10";
+    let crash = "\n[[fault]]\nauthor = 3\ncrash_time = 500";
+    let mut rates = Vec::new();
+    for contents in &[contents.to_string(), format!("{}{}", contents, crash)] {
+        let parameters = Scenario::parse(contents).unwrap().parameters();
+        let mut sim = make_simulator(&parameters);
+        let stop_reason = sim.run(&parameters.termination(), None);
+        assert_eq!(stop_reason, StopReason::MaxClock);
+        assert_eq!(sim.safety_violation(), None);
+        rates.push(sim.summary().fast_path_rate);
+    }
+    // Missing votes make the fast path fall back to the commit rule.
+    assert!(rates[0] > 0.5);
+    assert!(rates[1] < rates[0]);
+    assert!(Scenario::parse("fast_path_wait = -1").is_err());
+    // The fast path is only safe if all messages are delivered in time.
+    assert!(Scenario::parse("fast_path_wait = 10\n[network]\nloss_rate = 0.1").is_err());
+    let eclipse = "fast_path_wait = 10\n[[eclipse]]\nvictim = 0\ntime = 100";
+    assert!(Scenario::parse(eclipse).is_err());
+}
+
+#[test]
+fn test_crash_recovery() {
+    let contents = r#"
+        seed = 31
+        nodes = 4
+
+        [[fault]]
+        author = 1
+        crash_time = 200
+        recovery_time = 400
+
+        [[fault]]
+        author = 2
+        crash_time = 500
+        recovery_time = 700
+
+        [termination]
+        max_clock = 1500
+        max_time_without_commit = 1000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    assert_eq!(
+        parameters.recoveries.get(&Author(1)),
+        Some(&simulator::GlobalTime(400))
+    );
+    let mut sim = make_simulator(&parameters);
+    sim.record_events();
+    let stop_reason = sim.run(&parameters.termination(), None);
+    // Recovered nodes keep their voting constraints, so that commits stay consistent.
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    let recoveries = sim
+        .event_log()
+        .iter()
+        .filter(|event| event.kind == "recovery")
+        .count();
+    assert_eq!(recoveries, 2);
+    let report = sim.metrics_report();
+    assert!(report.nodes.iter().all(|node| !node.crashed));
+    assert!(report.nodes[1].committed_blocks > 0);
+    assert!(Scenario::parse("[[fault]]\nauthor = 0\ncrash_time = 10\nrecovery_time = 5").is_err());
+}
+
+#[test]
+fn test_abandoned_transactions() {
+    let contents = r#"
+        seed = 83
+        nodes = 4
+
+        [network]
+        loss_rate = 0.2
+
+        [workload.arrivals]
+        kind = "poisson"
+        rate = 0.05
+
+        [termination]
+        max_clock = 3000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert_eq!(sim.safety_violation(), None);
+    // Rounds that time out abandon their blocks, whose transactions are proposed again and
+    // committed later.
+    let proposals = sim.statistics().proposals_per_transaction().to_vec();
+    assert!(proposals.iter().any(|count| *count > 1));
+    assert!(proposals.iter().all(|count| *count >= 1));
+    for context in sim.contexts() {
+        let mut transactions = BTreeSet::new();
+        for (command, _) in context.committed_history() {
+            for transaction in &command.transactions {
+                assert!(transactions.insert((command.proposer, transaction.index)));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_aggregate_signatures() {
+    let mut scenario = Scenario::parse("seed = 37\nnodes = 16").unwrap();
+    let mut bytes = Vec::new();
+    for aggregate_signatures in &[false, true] {
+        scenario.aggregate_signatures = *aggregate_signatures;
+        let parameters = scenario.parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        assert!(sim.summary().committed_blocks > 0.0);
+        let total = message_counters::total_counters(sim.message_counters());
+        bytes.push(total.sent.records.signature_bytes as f64 / total.sent.records.total() as f64);
+    }
+    // Aggregates replace the signatures of the votes in each QC.
+    assert!(bytes[1] < bytes[0]);
+}
+
+#[test]
+fn test_threshold_signatures() {
+    let mut scenario = Scenario::parse("seed = 37\nnodes = 16\n[crypto]\nverify = 1").unwrap();
+    let mut bytes = Vec::new();
+    let mut latencies = Vec::new();
+    for threshold_signatures in &[false, true] {
+        scenario.threshold_signatures = *threshold_signatures;
+        let parameters = scenario.parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        assert!(sim.summary().committed_blocks > 0.0);
+        let total = message_counters::total_counters(sim.message_counters());
+        bytes.push(total.sent.records.signature_bytes);
+        latencies.push(sim.statistics().mean_commit_latency().unwrap());
+    }
+    // A single signature replaces the votes of each QC, and verifies at once.
+    assert!(bytes[1] < bytes[0]);
+    assert!(latencies[1] < latencies[0]);
+    assert!(Scenario::parse("aggregate_signatures = true\nthreshold_signatures = true").is_err());
+}
+
+#[test]
+fn test_resource_costs() {
+    let mut scenario = Scenario::parse("seed = 37\nnodes = 16").unwrap();
+    let mut verifications = Vec::new();
+    for threshold_signatures in &[false, true] {
+        scenario.threshold_signatures = *threshold_signatures;
+        let parameters = scenario.parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        let costs = sim.resource_costs();
+        assert!(costs.committed_blocks > 0);
+        assert!(costs.bytes > costs.messages);
+        assert!(costs.signatures > 0);
+        verifications.push(costs.verifications_per_commit().unwrap());
+    }
+    // Each QC is verified at once instead of vote by vote.
+    assert!(verifications[1] < verifications[0]);
+}
+
+#[test]
+fn test_message_authentication() {
+    let run = |authentication: &str| {
+        let contents = format!(
+            "seed = 9\nnodes = 4\nmessage_authentication = \"{}\"",
+            authentication
+        );
+        let parameters = Scenario::parse(&contents).unwrap().parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        sim.resource_costs()
+    };
+    let none = run("none");
+    let mac = run("mac");
+    let signature = run("signature");
+    // Authentication takes no time by default, hence the runs send the same messages.
+    assert_eq!(mac.messages, none.messages);
+    assert_eq!(signature.messages, none.messages);
+    assert_eq!(mac.bytes, none.bytes + 32 * none.messages);
+    assert_eq!(signature.bytes, none.bytes + 64 * none.messages);
+    // MACs are computed per copy, while a broadcast is signed once.
+    assert_eq!(none.macs, 0);
+    assert!(mac.macs > none.messages);
+    assert_eq!(mac.signatures, none.signatures);
+    assert!(signature.signatures < none.signatures + none.messages);
+    assert!(signature.verifications > none.verifications);
+    assert!(Scenario::parse("message_authentication = \"tls\"").is_err());
+}
+
+#[test]
+fn test_parameter_change() {
+    let contents = r#"
+        seed = 14
+        nodes = 4
+
+        [workload]
+        commands_per_epoch = 20
+        max_batch_size = 10
+
+        [workload.arrivals]
+        kind = "poisson"
+        rate = 0.5
+
+        [[parameter_change]]
+        time = 100
+        delta = 30
+        max_block_payload = 300
+
+        [[parameter_change]]
+        time = 200
+        gamma = 1.5
+        lambda = 0.4
+
+        # The same as the first change, which must not hide it.
+        [[parameter_change]]
+        time = 300
+        delta = 30
+        max_block_payload = 300
+
+        [termination]
+        max_clock = 3000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    for context in sim.contexts() {
+        let history = context.committed_history();
+        let index = history
+            .iter()
+            .position(|(command, _)| command.parameter_change.is_some())
+            .unwrap();
+        assert!(history[..index]
+            .iter()
+            .any(|(command, _)| command.payload_bytes() > 300));
+        // The limit applies from the next epoch on.
+        let next_epoch = (index / 20 + 1) * 20;
+        assert!(history.len() > next_epoch);
+        assert!(history[next_epoch..]
+            .iter()
+            .all(|(command, _)| command.payload_bytes() <= 300));
+        // Each change is committed, including the repeated one.
+        let indices: BTreeSet<_> = history
+            .iter()
+            .filter_map(|(command, _)| command.parameter_change.map(|(index, _)| index))
+            .collect();
+        assert_eq!(indices, (0..3).collect());
+        let changes = context.parameter_changes(&context.last_committed_state());
+        assert_eq!(changes.delta, Some(Duration::from_millis(30)));
+        assert_eq!(changes.gamma, Some(1.5));
+        assert_eq!(changes.lambda, Some(0.4));
+        assert_eq!(changes.max_block_payload, Some(300));
+    }
+    let invalid = "[[parameter_change]]\ntime = 10";
+    assert!(Scenario::parse(invalid).is_err());
+    let invalid = "[[parameter_change]]\ntime = 10\ngamma = 0.5";
+    assert!(Scenario::parse(invalid).is_err());
+}
+
+#[test]
+fn test_measurement_phases() {
+    let run = |measurement: &str| {
+        let contents = format!("seed = 12\nnodes = 4\n[measurement]\n{}", measurement);
+        let parameters = Scenario::parse(&contents).unwrap().parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        (parameters, sim.summary())
+    };
+    let (_, full) = run("");
+    let (_, warmed_up) = run("warm_up = 500");
+    assert!(0.0 < warmed_up.committed_blocks);
+    assert!(warmed_up.committed_blocks < full.committed_blocks);
+    // The window ends the run, whatever `max_clock`.
+    let (parameters, windowed) = run("warm_up = 500\nwindow = 2000");
+    assert_eq!(
+        parameters.termination().max_clock,
+        Some(simulator::GlobalTime(2500))
+    );
+    assert!(windowed.committed_blocks > warmed_up.committed_blocks);
+    assert!(Scenario::parse("[measurement]\nwindow = 0").is_err());
+}
+
+#[test]
+fn test_crypto_costs() {
+    let mut scenario = Scenario::parse("seed = 41\nnodes = 16").unwrap();
+    let mut latencies = Vec::new();
+    for verify in &[0, 2] {
+        scenario.crypto.verify = Duration::from_millis(*verify);
+        let parameters = scenario.parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        latencies.push(sim.statistics().mean_commit_latency().unwrap());
+    }
+    // Verifying the votes of every QC delays commits.
+    assert!(latencies[1] > latencies[0]);
+    assert!(Scenario::parse("[crypto]\nsign = -1").is_err());
+}
+
+#[test]
+fn test_network_trace() {
+    let contents = r#"
+        seed = 43
+        nodes = 4
+
+        [[network.change]]
+        time = 600
+        loss_rate = 0.0
+
+        [[network.change]]
+        time = 200
+        mean = 20.0
+        loss_rate = 1.0
+    "#;
+    let scenario = Scenario::parse(contents).unwrap();
+    let mut commits = Vec::new();
+    for changes in &[Vec::new(), scenario.network.change.clone()] {
+        let mut scenario = scenario.clone();
+        scenario.network.change = changes.clone();
+        let parameters = scenario.parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        assert!(sim.network_loss_rate().abs() < 1e-9);
+        commits.push(sim.summary().committed_blocks);
+    }
+    // Nothing is committed while all messages are lost, then commits resume with longer delays.
+    assert!(commits[1] > 0.0);
+    assert!(commits[1] < commits[0]);
+    assert!(Scenario::parse("[[network.change]]\ntime = 10\nloss_rate = 2.0").is_err());
+    assert!(Scenario::parse("[[network.change]]\nmean = 10.0").is_err());
+}
+
+#[test]
+fn test_failure_domains() {
+    let contents = r#"
+        seed = 47
+        nodes = 4
+
+        [[domain]]
+        name = "rack-a"
+        nodes = [0, 1]
+
+        [[domain_fault]]
+        domain = "rack-a"
+        kind = "crash"
+        time = 200
+        end_time = 400
+
+        [[domain_fault]]
+        domain = "rack-a"
+        kind = "partition"
+        time = 600
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    assert_eq!(parameters.scheduled_faults.len(), 5);
+    let mut sim = make_simulator(&parameters);
+    sim.record_events();
+    sim.run(&parameters.termination(), None);
+    // Both nodes of the rack crash and recover together, then lose the other nodes.
+    let recoveries = sim
+        .event_log()
+        .iter()
+        .filter(|event| event.kind == "recovery")
+        .count();
+    assert_eq!(recoveries, 2);
+    assert!(!sim.is_partitioned(Author(0), Author(1)));
+    assert!(sim.is_partitioned(Author(1), Author(2)));
+
+    let domain = "[[domain]]\nname = \"a\"\nnodes = [0]\n";
+    assert!(Scenario::parse(&format!("{}{}", domain, domain)).is_err());
+    assert!(Scenario::parse("[[domain]]\nname = \"a\"\nnodes = [5]").is_err());
+    assert!(Scenario::parse(&format!(
+        "{}[[domain_fault]]\ndomain = \"b\"\nkind = \"crash\"\ntime = 10",
+        domain
+    ))
+    .is_err());
+    assert!(Scenario::parse(&format!(
+        "{}[[domain_fault]]\ndomain = \"a\"\nkind = \"crash\"\ntime = 10\nend_time = 5",
+        domain
+    ))
+    .is_err());
+}
+
+#[test]
+fn test_message_corruption() {
+    let contents = r#"
+        seed = 53
+        nodes = 4
+        strict_verification = true
+
+        [corruption]
+        flip_rate = 0.3
+        truncate_rate = 0.1
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    // Damaged messages are dropped or rejected cleanly, and commits stay consistent.
+    assert_eq!(sim.safety_violation(), None);
+    let report = sim.metrics_report();
+    let corrupted: usize = report.nodes.iter().map(|x| x.corrupted_messages).sum();
+    let rejected: usize = report.nodes.iter().map(|x| x.rejected_messages).sum();
+    assert!(corrupted > 0);
+    assert!(rejected > 0 && rejected <= corrupted);
+    assert!(sim.summary().committed_blocks > 0.0);
+    assert!(Scenario::parse("[corruption]\nflip_rate = 2.0").is_err());
+}
+
+#[test]
+fn test_message_quota() {
+    let contents = r#"
+        seed = 61
+        nodes = 4
+        byzantine = [3]
+
+        [quota]
+        period = 100
+        max_messages = 50
+
+        [[flood]]
+        attacker = 3
+        time = 100
+        end_time = 400
+        period = 1
+        notifications = 10
+        requests = 2
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    // The receivers drop most of the flood, but little of the honest traffic.
+    assert_eq!(sim.safety_violation(), None);
+    let honest: Vec<_> = (0..3).map(Author).collect();
+    let dropped = |sender: Author| -> usize {
+        honest
+            .iter()
+            .map(|receiver| sim.quota_ledger(*receiver).dropped(sender))
+            .sum()
+    };
+    let flood_drops = dropped(Author(3));
+    let honest_drops: usize = honest.iter().map(|sender| dropped(*sender)).sum();
+    assert!(flood_drops > 0);
+    assert!(honest_drops * 100 <= flood_drops);
+    // Blocks are committed at about the pace of a run without the flood.
+    let without_flood = contents.split("[[flood]]").next().unwrap();
+    let parameters = Scenario::parse(without_flood).unwrap().parameters();
+    let mut baseline = make_simulator(&parameters);
+    baseline.run(&parameters.termination(), None);
+    let expected = baseline.summary().committed_blocks;
+    assert!(expected > 0.0);
+    assert!(sim.summary().committed_blocks >= 0.75 * expected);
+
+    assert!(Scenario::parse("[quota]\nmax_messages = 0").is_err());
+    assert!(Scenario::parse("[[flood]]\nattacker = 4").is_err());
+    assert!(Scenario::parse("[[flood]]\nperiod = 0").is_err());
+}
+
+#[test]
+fn test_invalid_payloads() {
+    let contents = r#"
+        seed = 71
+        nodes = 4
+        byzantine = [3]
+
+        [[node]]
+        author = 3
+        invalid_payloads = true
+
+        [termination]
+        max_clock = 3000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    // Honest nodes refuse to vote for the proposals of node 3, whose rounds time out.
+    assert_eq!(sim.safety_violation(), None);
+    let report = sim.metrics_report();
+    assert!(report.nodes[..3].iter().all(|x| x.rejected_proposals > 0));
+    assert_eq!(report.nodes[3].rejected_proposals, 0);
+    assert!(report.nodes[3].timeouts_as_leader > 0);
+    let quality = sim.chain_quality();
+    assert!(quality.honest_blocks > 0);
+    assert_eq!(quality.byzantine_blocks, 0);
+
+    assert!(Scenario::parse("nodes = 4\n[[node]]\nauthor = 3\ninvalid_payloads = true").is_err());
+}
+
+#[test]
+fn test_backward_clock_jump() {
+    let contents = r#"
+        seed = 71
+        nodes = 4
+
+        [workload.arrivals]
+        kind = "poisson"
+        rate = 0.05
+
+        [termination]
+        max_clock = 3000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    let mut injected = false;
+    sim.add_nemesis(Box::new(move |time: simulator::GlobalTime| {
+        if injected || time < simulator::GlobalTime(1000) {
+            return Vec::new();
+        }
+        injected = true;
+        vec![nemesis::Fault::ClockJump(
+            Author(0),
+            Duration::from_millis(-500),
+        )]
+    }));
+    sim.run(&parameters.termination(), None);
+    assert_eq!(sim.fault_log().len(), 1);
+    // The transactions in the mempool of node 0 now seem to come from the future, yet the other
+    // nodes still vote for its proposals.
+    assert_eq!(sim.safety_violation(), None);
+    let report = sim.metrics_report();
+    assert!(report.nodes.iter().all(|x| x.rejected_proposals == 0));
+    let proposers: BTreeSet<_> = sim.contexts()[1]
+        .committed_history()
+        .iter()
+        .filter(|(command, time)| *time > NodeTime(1000) && !command.transactions.is_empty())
+        .map(|(command, _)| command.proposer)
+        .collect();
+    assert!(proposers.contains(&Author(0)));
+}
+
+#[test]
+fn test_empty_blocks() {
+    // Count the committed empty blocks and transactions when all nodes receive a transaction
+    // every 200 units of time on average.
+    let run = |policy: &str| {
+        let contents = format!(
+            r#"
+            seed = 79
+            nodes = 4
+
+            [workload]
+            empty_blocks = {}
+
+            [workload.targeted_arrivals]
+            kind = "poisson"
+            rate = 0.005
+
+            [termination]
+            max_clock = 3000
+        "#,
+            policy
+        );
+        let parameters = Scenario::parse(&contents).unwrap().parameters();
+        let mut sim = make_simulator(&parameters);
+        sim.run(&parameters.termination(), None);
+        assert_eq!(sim.safety_violation(), None);
+        let history = sim.contexts()[0].committed_history();
+        let empty_blocks = history
+            .iter()
+            .filter(|(command, _)| command.transactions.is_empty())
+            .count();
+        let transactions: usize = history
+            .iter()
+            .map(|(command, _)| command.transactions.len())
+            .sum();
+        (empty_blocks, transactions)
+    };
+    let (empty_blocks, transactions) = run(r#"{ kind = "propose" }"#);
+    assert!(transactions > 0);
+    // No-op blocks only follow blocks with transactions, which still commit.
+    let (no_op_blocks, no_op_transactions) = run(r#"{ kind = "no-op" }"#);
+    assert!(no_op_blocks * 2 < empty_blocks);
+    assert!(no_op_transactions * 2 > transactions);
+}
+
+#[test]
+fn test_cpu_speed() {
+    let contents = r#"
+        seed = 73
+        nodes = 4
+
+        [crypto]
+        sign = 2
+        verify = 2
+
+        [[node]]
+        author = 3
+        cpu_speed = 0.2
+
+        [termination]
+        max_clock = 3000
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert_eq!(sim.safety_violation(), None);
+    // The slow node spends 10 units of time per verification, hence commits later.
+    let statistics = sim.statistics();
+    let slow_latency = statistics.node_mean_commit_latency(Author(3)).unwrap();
+    for index in 0..3 {
+        assert!(statistics.node_mean_commit_latency(Author(index)).unwrap() < slow_latency);
+    }
+
+    assert!(Scenario::parse("[[node]]\nauthor = 1\ncpu_speed = 0").is_err());
+}
+
+#[test]
+fn test_stall_diagnostics() {
+    let contents = r#"
+        seed = 67
+        nodes = 4
+
+        [[domain]]
+        name = "rack-a"
+        nodes = [0, 1]
+
+        [[domain_fault]]
+        domain = "rack-a"
+        kind = "partition"
+        time = 300
+
+        [termination]
+        max_clock = 2000
+        stall_threshold = 500
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    // Neither side of the partition holds a quorum, so the stall is reported once, with the
+    // messages dropped across the partition.
+    let reports = sim.stall_reports();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert!(report.time - report.since > Duration::from_millis(500));
+    assert_eq!(report.nodes.len(), 4);
+    assert!(report.nodes.iter().all(|x| x.view.running));
+    assert!(report.dropped_by_cause()[&DropCause::Partition] > 0);
+    assert!(report.dropped.keys().all(|(x, y)| (x.0 < 2) != (y.0 < 2)));
+}
+
+#[test]
+fn test_eclipse() {
+    let contents = r#"
+        seed = 59
+        nodes = 4
+
+        [[eclipse]]
+        victim = 3
+        time = 200
+        end_time = 600
+        delay = 30
+        drop_rate = 0.8
+    "#;
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    // The others proceed without the victim, which never commits a conflicting block.
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    assert_eq!(sim.safety_violation(), None);
+    let recoveries = sim.eclipse_recoveries();
+    assert_eq!(recoveries.len(), 1);
+    assert_eq!(recoveries[0].victim, Author(3));
+    assert!(recoveries[0].target_round > Round(1));
+    // The victim catches up once the adversary lets go of its links.
+    assert!(recoveries[0].duration().is_some());
+
+    assert!(Scenario::parse("[[eclipse]]\nvictim = 4").is_err());
+    assert!(Scenario::parse("[[eclipse]]\ntime = 10\nend_time = 10").is_err());
+    assert!(Scenario::parse("[[eclipse]]\ndrop_rate = 1.5").is_err());
+}
+
+#[test]
+fn test_observers() {
+    let contents = "seed = 13\nnodes = 5\nobservers = [4]\n[termination]\nmax_clock = 2000";
+    let parameters = Scenario::parse(contents).unwrap().parameters();
+    let validators: BTreeSet<_> = (0..4).map(Author).collect();
+    assert_eq!(parameters.validator_sets, vec![validators]);
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.safety_violation().is_none());
+    // The observer commits the chain of the validators without taking part in it.
+    let observer = sim.simulated_node(Author(4)).node();
+    assert!(observer.is_observer());
+    assert!(!observer.committed_blocks().is_empty());
+    assert!(observer
+        .committed_blocks()
+        .iter()
+        .all(|block| block.proposer != Author(4)));
+    let qc = sim
+        .simulated_node(Author(0))
+        .node()
+        .record_store()
+        .highest_quorum_certificate()
+        .unwrap();
+    assert!(!qc.votes.authors().contains(&Author(4)));
+    // Light clients check its commits against the validators alone.
+    let context = &sim.contexts()[4];
+    let configuration = context.configuration(&context.last_committed_state());
+    let block = &observer.committed_blocks()[0];
+    let proof = observer.commit_proof(BlockHash(block.hash)).unwrap();
+    assert!(proof
+        .verify(&configuration, parameters.commit_rule, false)
+        .is_ok());
+
+    assert!(Scenario::parse("nodes = 2\nobservers = [0, 1]").is_err());
+    let invalid = "nodes = 3\nobservers = [2]\n[[epoch]]\nvalidators = [0, 2]";
+    assert!(Scenario::parse(invalid).is_err());
+}
+
+#[test]
+fn test_clients() {
+    let toml = "seed = 2\nnodes = 4\n[clients]\nclients = 4\nreplicas = 2\nthink_time = 5\n\
+                [termination]\nmax_clock = 2000\n";
+    let parameters = Scenario::parse(toml).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.safety_violation().is_none());
+    // Clients keep sending requests, which are acknowledged without retries.
+    let clients = sim.clients().unwrap();
+    assert_eq!(clients.retries(), 0);
+    let latencies = sim.statistics().request_latencies();
+    assert!(latencies.len() >= 4 * 5);
+    // A request travels to the nodes, is committed, and its acknowledgment travels back before
+    // the client times out.
+    assert!(latencies
+        .iter()
+        .all(|latency| latency.is_positive() && *latency < Duration::from_millis(2000)));
+}
+
+#[test]
+fn test_request_gossip() {
+    let toml = "seed = 2\nnodes = 4\n[clients]\nclients = 4\nreplicas = 1\n\
+                gossip_fanout = 2\ngossip_delay = 5\n[termination]\nmax_clock = 2000\n";
+    let parameters = Scenario::parse(toml).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.safety_violation().is_none());
+    assert!(!sim.statistics().request_latencies().is_empty());
+    // Forwarded requests reach the mempools of nodes that their clients never contacted.
+    assert!(sim.clients().unwrap().forwarded() > 0);
+    let without_gossip = toml.replace("gossip_fanout = 2", "gossip_fanout = 0");
+    let parameters = Scenario::parse(&without_gossip).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert_eq!(sim.clients().unwrap().forwarded(), 0);
+    // Requests are forwarded to other nodes only.
+    let invalid = toml.replace("gossip_fanout = 2", "gossip_fanout = 4");
+    assert!(Scenario::parse(&invalid).is_err());
+}
+
+#[test]
+fn test_failure_detector() {
+    let toml = "seed = 5\nnodes = 4\n[[fault]]\nauthor = 3\ncrash_time = 500\n[pacemaker]\n\
+                peer_selection = { kind = \"random\", count = 3 }\n\
+                failure_detector = { kind = \"heartbeat\", timeout = 200 }\n\
+                [termination]\nmax_clock = 2000\n";
+    let parameters = Scenario::parse(toml).unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.safety_violation().is_none());
+    let report = sim.metrics_report();
+    for index in 0..3 {
+        // The honest nodes suspect the crashed node, and only it.
+        let node = sim.simulated_node(Author(index)).node();
+        let detector = node.failure_detector().unwrap();
+        assert_eq!(
+            detector.suspected().iter().collect::<Vec<_>>(),
+            [&Author(3)]
+        );
+        assert!(report.nodes[index].suspicions >= 1);
+        // Their peer selection then skips it.
+        let mut pacemaker = PacemakerState::new(
+            node.epoch_id(),
+            NodeTime(0),
+            Duration::from_millis(10),
+            2.0,
+            0.5,
+        )
+        .with_peer_selection(PeerSelection::Random { count: 3 });
+        pacemaker.set_suspected_peers(detector.suspected().clone());
+        let peers = pacemaker
+            .update_pacemaker(
+                Author(index),
+                node.epoch_id(),
+                node.record_store(),
+                NodeTime(0),
+                NodeTime(0),
+            )
+            .should_send;
+        assert_eq!(peers.len(), 2);
+        assert!(!peers.contains(&Author(3)) && !peers.contains(&Author(index)));
+    }
+    assert_eq!(report.nodes[3].suspicions, 0);
+
+    // With the default peer selection, nodes keep sending to the leader, even when they
+    // suspect it, and the crashed node leads some of the rounds.
+    let toml = toml.replace("peer_selection = { kind = \"random\", count = 3 }\n", "");
+    let parameters = Scenario::parse(&toml).unwrap().parameters();
+    assert_eq!(parameters.peer_selection, PeerSelection::Leader);
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert!(sim.safety_violation().is_none());
+    let report = sim.metrics_report();
+    assert!(report.aggregate.committed_blocks > 0.0);
+    for index in 0..3 {
+        let node = sim.simulated_node(Author(index)).node();
+        assert!(node
+            .failure_detector()
+            .unwrap()
+            .suspected()
+            .contains(&Author(3)));
+    }
+}
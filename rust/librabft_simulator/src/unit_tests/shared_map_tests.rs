@@ -0,0 +1,27 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_shared_map() {
+    let mut map = SharedMap::new();
+    for key in 0..1000 {
+        assert_eq!(map.insert(key, key * 2), None);
+    }
+    assert_eq!(map.insert(3, 7), Some(6));
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map[&3], 7);
+    assert_eq!(map.iter().count(), 1000);
+
+    // Clones share all the shards, and writes only copy the shard that they change.
+    let snapshot = map.clone();
+    assert_eq!(map.shared_shards(&snapshot), SHARDS);
+    assert_eq!(map.remove(&3), Some(7));
+    assert_eq!(map.remove(&3), None);
+    assert_eq!(map.shared_shards(&snapshot), SHARDS - 1);
+    assert_eq!(map.len(), 999);
+    assert!(!map.contains_key(&3));
+    assert_eq!(snapshot.len(), 1000);
+    assert_eq!(snapshot.get(&3), Some(&7));
+}
@@ -0,0 +1,45 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[test]
+fn test_minimize() {
+    // Failures need both 3 and 7.
+    let items: Vec<_> = (0..10).collect();
+    let mut runs = 0;
+    let result = minimize(items, |items| {
+        runs += 1;
+        items.contains(&3) && items.contains(&7)
+    });
+    assert_eq!(result, vec![3, 7]);
+    assert!(runs < 30);
+    assert_eq!(minimize(vec![1, 2], |_| true), Vec::<i32>::new());
+    assert_eq!(minimize(vec![1, 2], |items| items.len() == 2), vec![1, 2]);
+}
+
+#[test]
+fn test_shrink_liveness_violation() {
+    // All messages are lost, hence the crashes play no role in the violation.
+    let scenario = Scenario::parse(
+        "seed = 1\nnodes = 4\n[network]\nloss_rate = 1.0\n\
+         [[fault]]\nauthor = 1\ncrash_time = 500\n\
+         [[fault]]\nauthor = 3\ncrash_time = 700\n\
+         [termination]\nmax_clock = 5000\nmax_time_without_commit = 200",
+    )
+    .unwrap();
+    let result = shrink(&scenario).unwrap();
+    assert!(result.scenario.fault.is_empty());
+    assert!(result.scenario.termination.max_clock < simulator::GlobalTime(500));
+    assert_eq!(result.scenario.seed, Some(1));
+    match result.stop_reason {
+        StopReason::LivenessViolation(_) => (),
+        reason => panic!("Unexpected stop reason {:?}", reason),
+    }
+}
+
+#[test]
+fn test_shrink_passing_scenario() {
+    let scenario = Scenario::parse("seed = 1").unwrap();
+    assert!(shrink(&scenario).is_none());
+}
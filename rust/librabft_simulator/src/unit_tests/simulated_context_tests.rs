@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use bft_simulator_runtime::workload::ArrivalProcess;
 
 #[test]
 fn test_happened_before() {
@@ -12,6 +13,8 @@ fn test_happened_before() {
         Command {
             proposer: Author(0),
             index: 0,
+            transactions: Vec::new(),
+            parameter_change: None,
         },
         NodeTime(1),
     );
@@ -21,6 +24,8 @@ fn test_happened_before() {
         Command {
             proposer: Author(1),
             index: 0,
+            transactions: Vec::new(),
+            parameter_change: None,
         },
         NodeTime(1),
     );
@@ -28,12 +33,221 @@ fn test_happened_before() {
         Command {
             proposer: Author(1),
             index: 0,
+            transactions: Vec::new(),
+            parameter_change: None,
         },
         NodeTime(1),
     );
     assert!(!s2.happened_just_before(&s1));
 }
 
+#[test]
+fn test_validator_sets() {
+    let validator_sets = vec![
+        vec![Author(0), Author(1)].into_iter().collect(),
+        vec![Author(1)].into_iter().collect(),
+    ];
+    let mut context = SimulatedContext::new(
+        Author(0),
+        /* num_nodes */ 2,
+        /* max commands per epoch */ 1,
+    )
+    .with_validator_sets(validator_sets);
+    let s0 = context.last_committed_state();
+    assert_eq!(context.configuration(&s0).weight(&Author(0)), 1);
+    let c1 = context.fetch(NodeTime(0)).unwrap();
+    let s1 = context
+        .compute(&s0, c1, NodeTime(1), None, Vec::new())
+        .unwrap();
+    let c2 = context.fetch(NodeTime(0)).unwrap();
+    let s2 = context
+        .compute(&s1, c2, NodeTime(2), None, Vec::new())
+        .unwrap();
+    assert_eq!(context.read_epoch_id(&s1), EpochId(1));
+    assert_eq!(context.configuration(&s1).weight(&Author(0)), 0);
+    assert_eq!(context.configuration(&s1).quorum_threshold(), 1);
+    // The last set applies to later epochs.
+    assert_eq!(context.configuration(&s2).weight(&Author(1)), 1);
+    assert_eq!(context.configuration(&s2).weight(&Author(0)), 0);
+}
+
+#[test]
+fn test_reconfigurations() {
+    let reconfigurations = vec![(
+        NodeTime(5),
+        vec![Author(1), Author(2)].into_iter().collect(),
+    )];
+    let mut context = SimulatedContext::new(
+        Author(0),
+        /* num_nodes */ 3,
+        /* max commands per epoch */ 100,
+    )
+    .with_validator_sets(vec![vec![Author(0), Author(1)].into_iter().collect()])
+    .with_reconfigurations(reconfigurations);
+    let s0 = context.last_committed_state();
+    assert_eq!(context.configuration(&s0).weight(&Author(2)), 0);
+    let c1 = context.fetch(NodeTime(0)).unwrap();
+    let s1 = context
+        .compute(&s0, c1, NodeTime(4), None, Vec::new())
+        .unwrap();
+    assert_eq!(context.read_epoch_id(&s1), EpochId(0));
+    // The first block proposed at or after time 5 changes the validators.
+    let c2 = context.fetch(NodeTime(0)).unwrap();
+    let s2 = context
+        .compute(&s1, c2, NodeTime(6), None, Vec::new())
+        .unwrap();
+    assert_eq!(context.read_epoch_id(&s2), EpochId(1));
+    assert_eq!(context.configuration(&s2).weight(&Author(0)), 0);
+    assert_eq!(context.configuration(&s2).weight(&Author(2)), 1);
+}
+
+#[test]
+fn test_stakes() {
+    let stakes = vec![(Author(2), 3)].into_iter().collect();
+    let context = SimulatedContext::new(
+        Author(0),
+        /* num_nodes */ 3,
+        /* max commands per epoch */ 100,
+    )
+    .with_stakes(stakes);
+    let configuration = context.configuration(&context.last_committed_state());
+    assert_eq!(configuration.weight(&Author(0)), 1);
+    assert_eq!(configuration.weight(&Author(2)), 3);
+    // Node 2 and one of the other nodes hold more than two thirds of the 5 votes.
+    assert_eq!(configuration.quorum_threshold(), 4);
+}
+
+#[test]
+fn test_workload() {
+    let process = ArrivalProcess::ConstantRate { rate: 1.0 };
+    let mut context = SimulatedContext::new(
+        Author(0),
+        /* num_nodes */ 1,
+        /* max commands per epoch */ 10,
+    )
+    .with_workload(WorkloadGenerator::new(process, 0), 3);
+    let s0 = context.last_committed_state();
+    let c1 = context.fetch(NodeTime(5)).unwrap();
+    assert_eq!(c1.transactions.len(), 3);
+    assert_eq!(c1.transactions[0].submission_time, NodeTime(1));
+    assert_eq!(context.mempool().num_pending(), 2);
+    let s1 = context
+        .compute(&s0, c1, NodeTime(5), None, Vec::new())
+        .unwrap();
+    context.commit(&s1, None);
+    assert_eq!(context.mempool().num_committed(), 3);
+    // Without a workload, blocks are empty.
+    let mut context = SimulatedContext::new(Author(0), 1, 10);
+    assert!(context.fetch(NodeTime(5)).unwrap().transactions.is_empty());
+}
+
+#[test]
+fn test_release() {
+    let process = ArrivalProcess::ConstantRate { rate: 1.0 };
+    let mut context = SimulatedContext::new(Author(0), 2, 10)
+        .with_workload(WorkloadGenerator::new(process, 0), 3);
+    let c1 = context.fetch(NodeTime(5)).unwrap();
+    // Commands of other proposers are not ours to release.
+    context.release(&Command {
+        proposer: Author(1),
+        ..c1.clone()
+    });
+    assert_eq!(context.mempool().num_pending(), 2);
+    // The transactions of an abandoned block are proposed again.
+    context.release(&c1);
+    assert_eq!(context.mempool().num_pending(), 5);
+    let c2 = context.fetch(NodeTime(5)).unwrap();
+    assert_eq!(c2.transactions.len(), 3);
+    assert!(c2.transactions.iter().all(|x| x.proposals == 1));
+}
+
+#[test]
+fn test_invalid_payloads() {
+    let process = ArrivalProcess::ConstantRate { rate: 1.0 };
+    let mut honest = SimulatedContext::new(Author(0), 2, 10)
+        .with_workload(WorkloadGenerator::new(process, 0), 3);
+    let mut byzantine = SimulatedContext::new(Author(1), 2, 10).with_invalid_payloads(true);
+    let command = honest.fetch(NodeTime(5)).unwrap();
+    assert!(honest.is_valid(&command, NodeTime(5)));
+    // Proposers whose clock jumped backward still propose valid transactions.
+    assert!(honest.is_valid(&command, NodeTime(0)));
+    // Even an empty batch carries an invalid transaction.
+    let command = byzantine.fetch(NodeTime(5)).unwrap();
+    assert_eq!(command.transactions.len(), 1);
+    assert!(!honest.is_valid(&command, NodeTime(5)));
+    assert!(byzantine.is_valid(&command, NodeTime(5)));
+}
+
+#[test]
+fn test_execution() {
+    let process = ArrivalProcess::ConstantRate { rate: 1.0 };
+    let model = ExecutionModel {
+        num_keys: 10,
+        block_cost: Duration::from_millis(3),
+        transaction_cost: Duration::from_millis(2),
+    };
+    let mut context = KeyValueContext::with_execution_model(Author(0), 1, 10, model)
+        .with_workload(WorkloadGenerator::new(process, 0), 4);
+    assert!(context.committed_store().unwrap().is_empty());
+    let s0 = context.last_committed_state();
+    let c1 = context.fetch(NodeTime(5)).unwrap();
+    let s1 = context
+        .compute(&s0, c1.clone(), NodeTime(5), None, Vec::new())
+        .unwrap();
+    assert_eq!(
+        context.take_execution_time(),
+        Duration::from_millis(3 + 2 * 4)
+    );
+    assert_eq!(context.take_execution_time(), Duration::ZERO);
+    context.commit(&s1, None);
+    assert!(!context.committed_store().unwrap().is_empty());
+    // The state depends on the contents of the store.
+    let mut other = SimulatedContext::new(Author(0), 1, 10);
+    let s0 = other.last_committed_state();
+    let s2 = other
+        .compute(&s0, c1, NodeTime(5), None, Vec::new())
+        .unwrap();
+    assert_ne!(s1, s2);
+    assert_eq!(other.take_execution_time(), Duration::ZERO);
+    assert!(other.committed_store().is_none());
+}
+
+#[test]
+fn test_backends() {
+    let context =
+        KeyValueContext::with_execution_model(Author(0), 1, 10, ExecutionModel::default());
+    assert_eq!(context.backend(), ExecutionBackend::KeyValue);
+    assert!(context.committed_store().is_some());
+    let mut context = NullContext::from_ledger_state(Author(0), 1, 10, NullLedgerState::default())
+        .with_execution_latency(Duration::from_millis(5));
+    assert_eq!(context.backend(), ExecutionBackend::Null);
+    assert!(context.committed_store().is_none());
+    let s0 = context.last_committed_state();
+    let c1 = context.fetch(NodeTime(5)).unwrap();
+    assert_eq!(context.execution_duration(&c1), Duration::ZERO);
+    let s1 = context
+        .compute(&s0, c1.clone(), NodeTime(5), None, Vec::new())
+        .unwrap();
+    assert_ne!(s0, s1);
+    assert_eq!(context.take_execution_time(), Duration::ZERO);
+    context.commit(&s1, None);
+    // Null states count the commands without keeping them.
+    assert_eq!(context.num_committed_commands(), 1);
+    assert!(context.committed_history().is_empty());
+    assert!(context.snapshot(&s1).is_none());
+    let s2 = context
+        .compute(&s1, c1, NodeTime(6), None, Vec::new())
+        .unwrap();
+    assert_eq!(context.read_epoch_id(&s2), EpochId(0));
+    context.commit(&s2, None);
+    assert_eq!(context.num_committed_commands(), 2);
+    assert_eq!(
+        "key-value".parse::<ExecutionBackend>().unwrap(),
+        ExecutionBackend::KeyValue
+    );
+    assert!("none".parse::<ExecutionBackend>().is_err());
+}
+
 #[test]
 fn test_simulated_context() {
     let mut context = SimulatedContext::new(
@@ -42,9 +256,9 @@ fn test_simulated_context() {
         /* max commands per epoch */ 2,
     );
     let s0 = context.last_committed_state();
-    let c1 = context.fetch().unwrap();
-    let c2 = context.fetch().unwrap();
-    let c3 = context.fetch().unwrap();
+    let c1 = context.fetch(NodeTime(0)).unwrap();
+    let c2 = context.fetch(NodeTime(0)).unwrap();
+    let c3 = context.fetch(NodeTime(0)).unwrap();
 
     let s1 = context
         .compute(&s0, c1, NodeTime(1), None, Vec::new())
@@ -72,6 +286,8 @@ fn test_simulated_context() {
                 Command {
                     proposer: Author(0),
                     index: 0,
+                    transactions: Vec::new(),
+                    parameter_change: None,
                 },
                 NodeTime(1)
             ),
@@ -79,9 +295,44 @@ fn test_simulated_context() {
                 Command {
                     proposer: Author(0),
                     index: 1,
+                    transactions: Vec::new(),
+                    parameter_change: None,
                 },
                 NodeTime(4)
             ),
         ]
     )
 }
+
+#[test]
+fn test_cpu_speed() {
+    let costs = CryptoCosts {
+        verify: Duration::from_millis(2),
+        ..CryptoCosts::default()
+    };
+    let mut context = SimulatedContext::new(Author(0), 1, 10)
+        .with_crypto_costs(costs)
+        .with_execution_latency(Duration::from_millis(5))
+        .with_cpu_speed(0.5);
+    context.charge_crypto(CryptoOperation::Verify, 3);
+    assert_eq!(context.take_execution_time(), Duration::from_millis(12));
+    // On a faster processor, the fractions of small costs add up instead of being lost.
+    let costs = CryptoCosts {
+        verify: Duration::from_millis(1),
+        ..CryptoCosts::default()
+    };
+    let mut fast = SimulatedContext::new(Author(0), 1, 10)
+        .with_crypto_costs(costs)
+        .with_cpu_speed(2.0);
+    let mut total = Duration::ZERO;
+    for _ in 0..4 {
+        fast.charge_crypto(CryptoOperation::Verify, 1);
+        total += fast.take_execution_time();
+    }
+    assert_eq!(total, Duration::from_millis(2));
+    let command = context.fetch(NodeTime(5)).unwrap();
+    assert_eq!(
+        context.execution_duration(&command),
+        Duration::from_millis(10)
+    );
+}
@@ -0,0 +1,46 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+/// An application whose states are counters, and whose commands are increments.
+struct Counter {
+    next_increment: u64,
+}
+
+impl CommandFetcher<u64> for Counter {
+    fn fetch(&mut self, _time: NodeTime) -> Option<u64> {
+        self.next_increment += 1;
+        Some(self.next_increment)
+    }
+}
+
+impl StateComputer<u64, u64> for Counter {
+    fn compute(
+        &mut self,
+        base_state: &u64,
+        command: u64,
+        _time: NodeTime,
+        _previous_author: Option<Author>,
+        _previous_voters: Vec<Author>,
+    ) -> Option<u64> {
+        base_state.checked_add(command)
+    }
+}
+
+#[test]
+fn test_application_types() {
+    let mut counter = Counter { next_increment: 0 };
+    let command = counter.fetch(NodeTime(0)).unwrap();
+    assert_eq!(command, 1);
+    assert!(counter.is_batch_full(NodeTime(0)));
+    assert_eq!(
+        counter.compute(&5, command, NodeTime(1), None, Vec::new()),
+        Some(6)
+    );
+    assert_eq!(
+        counter.compute(&std::u64::MAX, command, NodeTime(1), None, Vec::new()),
+        None
+    );
+    assert_eq!(counter.execution_duration(&command), Duration::ZERO);
+}
@@ -0,0 +1,82 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+#[test]
+fn test_event_stream() {
+    let scenario = Scenario::parse(
+        r#"
+        seed = 6
+        nodes = 4
+
+        [[fault]]
+        author = 3
+        crash_time = 200
+        recovery_time = 400
+
+        [termination]
+        max_clock = 600
+        "#,
+    )
+    .unwrap();
+    let mut stream = EventStream::new(scenario.parameters());
+    let mut events = Vec::new();
+    while let Some(step) = stream.next_events() {
+        events.extend(step);
+    }
+    assert!(stream.next_events().is_none());
+
+    for pair in events.windows(2) {
+        assert!(pair[0].time() <= pair[1].time());
+    }
+    let has = |predicate: &Fn(&StreamEvent) -> bool| events.iter().any(predicate);
+    assert!(has(&|event| match event {
+        StreamEvent::Round { .. } => true,
+        _ => false,
+    }));
+    assert!(has(&|event| match event {
+        StreamEvent::Commit { .. } => true,
+        _ => false,
+    }));
+    assert!(has(
+        &|event| *event == StreamEvent::NodeDown { time: 200, node: 3 }
+    ));
+    assert!(has(
+        &|event| *event == StreamEvent::NodeUp { time: 400, node: 3 }
+    ));
+    assert_eq!(
+        events.last(),
+        Some(&StreamEvent::End {
+            time: 600,
+            stop_reason: "MaxClock".to_string()
+        })
+    );
+}
+
+#[cfg(feature = "stream")]
+#[test]
+fn test_async_event_stream() {
+    use futures::Stream;
+
+    let scenario = Scenario::parse("seed = 6\nnodes = 4\n[termination]\nmax_clock = 300").unwrap();
+    let mut steps = EventStream::new(scenario.parameters());
+    let mut expected = Vec::new();
+    while let Some(step) = steps.next_events() {
+        expected.extend(step);
+    }
+    // Streams replay the same run, one event at a time.
+    let events = EventStream::new(scenario.parameters())
+        .wait()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(events, expected);
+    // Consumers may stop early.
+    let first = EventStream::new(scenario.parameters())
+        .take(3)
+        .wait()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(&first[..], &expected[..3]);
+}
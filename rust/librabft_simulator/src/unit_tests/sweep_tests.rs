@@ -0,0 +1,403 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bft_simulator_runtime::{
+    corruption::CorruptionModel,
+    invariants::FnInvariant,
+    message_counters::{self, MessageKind},
+    nemesis::{Fault, Flood},
+    network_trace::NetworkTrace,
+    observers::SimulationObserver,
+    quota::MessageQuota,
+    statistics::MeasurementPhases,
+    termination::StopReason,
+};
+use std::{cell::RefCell, rc::Rc};
+
+fn base_parameters() -> SimulationParameters {
+    SimulationParameters {
+        max_clock: simulator::GlobalTime(500),
+        max_commits: None,
+        max_rounds_without_progress: None,
+        max_time_without_commit: None,
+        stall_threshold: None,
+        measurement: MeasurementPhases::default(),
+        mean: 10.0,
+        variance: 4.0,
+        loss_rate: 0.0,
+        network_trace: NetworkTrace::default(),
+        nodes: 3,
+        commands_per_epoch: 30_000,
+        target_commit_interval: Duration::from_millis(100_000),
+        arrivals: None,
+        targeted_arrivals: None,
+        clients: None,
+        max_batch_size: 100,
+        transaction_size: 100,
+        max_block_payload: None,
+        max_batch_wait: None,
+        empty_block_policy: EmptyBlockPolicy::Propose,
+        backend: ExecutionBackend::Ledger,
+        execution: None,
+        execution_latency: Duration::ZERO,
+        pipelined_execution: false,
+        delta: Duration::from_millis(20),
+        gamma: 2.0,
+        lambda: 0.5,
+        round_jitter: 0.0,
+        retransmission: None,
+        hybrid_pacemaker: None,
+        peer_selection: PeerSelection::Leader,
+        failure_detector: None,
+        push_pull: None,
+        data_sync_version: DATA_SYNC_VERSION,
+        suppress_redundant_records: false,
+        strict_verification: false,
+        state_sync: false,
+        message_corruption: CorruptionModel::default(),
+        message_quota: MessageQuota::default(),
+        blocks_per_round: 1,
+        max_uncertified_blocks: None,
+        commit_rule: CommitRule::ThreeChain,
+        vote_delivery: VoteDelivery::Proposer,
+        fast_path_wait: None,
+        aggregate_signatures: false,
+        threshold_signatures: false,
+        crypto_costs: CryptoCosts::default(),
+        message_authentication: MessageAuthentication::None,
+        seed: 0,
+        node_overrides: BTreeMap::new(),
+        crashes: BTreeMap::new(),
+        recoveries: BTreeMap::new(),
+        scheduled_faults: Vec::new(),
+        byzantine: BTreeSet::new(),
+        observers: BTreeSet::new(),
+        stakes: BTreeMap::new(),
+        validator_sets: Vec::new(),
+        reconfigurations: Vec::new(),
+        parameter_changes: Vec::new(),
+        join_times: BTreeMap::new(),
+        record_decisions: false,
+        check_lemmas: false,
+    }
+}
+
+#[test]
+fn test_parse_list() {
+    assert_eq!(parse_list::<usize>("4"), vec![4]);
+    assert_eq!(parse_list::<f64>("0.5, 1,2.5"), vec![0.5, 1.0, 2.5]);
+}
+
+#[test]
+fn test_configurations() {
+    let grid = SweepGrid {
+        delta: vec![Duration::from_millis(10), Duration::from_millis(20)],
+        gamma: vec![2.0],
+        lambda: vec![0.5],
+        nodes: vec![3, 4, 7],
+        loss_rate: vec![0.0, 0.1],
+        commit_rule: vec![CommitRule::ThreeChain],
+    };
+    let configurations = grid.configurations(&base_parameters());
+    assert_eq!(configurations.len(), 12);
+    assert_eq!(configurations[0].nodes, 3);
+    assert_eq!(configurations[0].delta, Duration::from_millis(10));
+    assert!((configurations[1].loss_rate - 0.1).abs() < 1e-9);
+    assert_eq!(configurations[11].nodes, 7);
+    assert_eq!(configurations[11].max_clock, simulator::GlobalTime(500));
+}
+
+#[test]
+fn test_run_sweep() {
+    let grid = SweepGrid {
+        delta: vec![Duration::from_millis(20)],
+        gamma: vec![2.0],
+        lambda: vec![0.5],
+        nodes: vec![3, 4],
+        loss_rate: vec![0.0],
+        commit_rule: vec![CommitRule::ThreeChain],
+    };
+    let results = run_sweep(&base_parameters(), &grid);
+    assert_eq!(results.len(), 2);
+    for result in &results {
+        assert!(result.summary.committed_blocks > 0.0);
+        assert!(result.summary.commit_throughput > 0.0);
+        assert!(result.summary.mean_commit_latency.unwrap() > 0.0);
+        assert!(result.summary.timeout_rate >= 0.0 && result.summary.timeout_rate <= 1.0);
+    }
+}
+
+#[test]
+fn test_commit_rules() {
+    let mut grid = SweepGrid::single(&base_parameters());
+    grid.nodes = vec![4];
+    grid.commit_rule = vec![CommitRule::ThreeChain, CommitRule::TwoChain];
+    let results = run_sweep(&base_parameters(), &grid);
+    assert_eq!(results.len(), 2);
+    let latencies: Vec<_> = results
+        .iter()
+        .map(|result| result.summary.mean_commit_latency.unwrap())
+        .collect();
+    // With the same network delays, blocks are committed one round earlier.
+    assert!(latencies[1] < latencies[0]);
+    let mut parameters = base_parameters();
+    parameters.commit_rule = CommitRule::TwoChain;
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+    assert_eq!(sim.safety_violation(), None);
+}
+
+#[test]
+fn test_termination() {
+    let parameters = SimulationParameters {
+        max_commits: Some(3),
+        ..base_parameters()
+    };
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::CommittedBlocks);
+    assert!(sim.statistics().commit_latencies().len() >= 3);
+
+    let mut sim = make_simulator(&base_parameters());
+    sim.add_invariant(Box::new(FnInvariant::new(
+        "no commit",
+        |nodes: &[simulator::SimulatedNode<NodeState, Box<SimulationContext>>]| {
+            if nodes
+                .iter()
+                .any(|node| !node.node().committed_blocks().is_empty())
+            {
+                Err("a block was committed".to_string())
+            } else {
+                Ok(())
+            }
+        },
+    )));
+    match sim.run(&base_parameters().termination(), None) {
+        StopReason::InvariantViolation(message) => {
+            assert!(message.starts_with("no commit at "));
+            assert!(message.contains("a block was committed"));
+        }
+        reason => panic!("Unexpected stop reason {:?}", reason),
+    }
+}
+
+#[test]
+fn test_commit_listeners() {
+    let commits = Rc::new(RefCell::new(Vec::new()));
+    let mut sim = make_simulator(&base_parameters());
+    let listener_commits = commits.clone();
+    sim.add_commit_listener(Box::new(
+        move |author: Author, block: &CommittedBlock, time: simulator::GlobalTime| {
+            listener_commits
+                .borrow_mut()
+                .push((author, block.round, time));
+        },
+    ));
+    sim.run(&base_parameters().termination(), None);
+    let commits = commits.borrow();
+    assert_eq!(commits.len(), sim.statistics().commit_latencies().len());
+    for index in 0..base_parameters().nodes {
+        // Each commit is reported once, in the order of the commits of the node.
+        let rounds: Vec<_> = commits
+            .iter()
+            .filter(|(author, _, _)| author.0 == index)
+            .map(|(_, round, _)| *round)
+            .collect();
+        let expected_rounds: Vec<_> = sim
+            .simulated_node(Author(index))
+            .node()
+            .committed_blocks()
+            .iter()
+            .map(|block| block.round)
+            .collect();
+        assert_eq!(rounds, expected_rounds);
+    }
+    assert!(commits.windows(2).all(|x| x[0].2 <= x[1].2));
+}
+
+#[derive(Default)]
+struct ObservedEvents {
+    messages: usize,
+    updates: usize,
+    rounds: Vec<(Author, Round)>,
+    commits: usize,
+}
+
+struct Observer(Rc<RefCell<ObservedEvents>>);
+
+impl SimulationObserver for Observer {
+    fn on_message_delivered(
+        &mut self,
+        _sender: Author,
+        _receiver: Author,
+        _kind: MessageKind,
+        _time: simulator::GlobalTime,
+    ) {
+        self.0.borrow_mut().messages += 1;
+    }
+
+    fn on_node_updated(&mut self, _author: Author, _time: simulator::GlobalTime) {
+        self.0.borrow_mut().updates += 1;
+    }
+
+    fn on_round_change(&mut self, author: Author, round: Round, _time: simulator::GlobalTime) {
+        self.0.borrow_mut().rounds.push((author, round));
+    }
+
+    fn on_commit(
+        &mut self,
+        _author: Author,
+        _block: &CommittedBlock,
+        _time: simulator::GlobalTime,
+    ) {
+        self.0.borrow_mut().commits += 1;
+    }
+}
+
+#[test]
+fn test_observers() {
+    let events = Rc::new(RefCell::new(ObservedEvents::default()));
+    let mut sim = make_simulator(&base_parameters());
+    sim.add_observer(Box::new(Observer(events.clone())));
+    sim.run(&base_parameters().termination(), None);
+    let events = events.borrow();
+    let total = message_counters::total_counters(sim.message_counters());
+    assert_eq!(events.messages, total.received.messages());
+    assert!(events.updates >= events.rounds.len());
+    assert_eq!(events.commits, sim.statistics().commit_latencies().len());
+    for index in 0..base_parameters().nodes {
+        let rounds: Vec<_> = events
+            .rounds
+            .iter()
+            .filter(|(author, _)| author.0 == index)
+            .map(|(_, round)| *round)
+            .collect();
+        // Only changes are reported, and the last one is the current round of the node.
+        assert!(rounds.windows(2).all(|x| x[0] != x[1]));
+        assert_eq!(
+            rounds.last(),
+            Some(&sim.simulated_node(Author(index)).active_round())
+        );
+    }
+}
+
+#[test]
+fn test_message_counters() {
+    let mut sim = make_simulator(&base_parameters());
+    sim.run(&base_parameters().termination(), None);
+    let total = message_counters::total_counters(sim.message_counters());
+    assert!(total.sent.notifications > 0);
+    assert!(total.sent.records.blocks > 0);
+    assert!(total.sent.records.votes > 0);
+    // Messages still in flight at the end of the run were not received.
+    assert!(total.received.messages() <= total.sent.messages());
+    assert!(total.received.records.total() <= total.sent.records.total());
+}
+
+#[test]
+fn test_view_change_statistics() {
+    let mut parameters = SimulationParameters {
+        nodes: 4,
+        max_clock: simulator::GlobalTime(2000),
+        ..base_parameters()
+    };
+    parameters
+        .crashes
+        .insert(Author(3), simulator::GlobalTime(0));
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    let view_changes = sim.view_change_statistics();
+    assert!(view_changes.timeout_certificate_rounds > 0.0);
+    // Rounds led by the crashed node can only end with a timeout certificate.
+    assert!(view_changes.timeouts_by_leader[&Author(3)] > 0);
+    assert!(view_changes.wasted_rounds.unwrap().max >= 1.0);
+    assert_eq!(sim.safety_violation(), None);
+}
+
+#[test]
+fn test_html_report() {
+    let mut parameters = SimulationParameters {
+        nodes: 4,
+        max_clock: simulator::GlobalTime(1000),
+        ..base_parameters()
+    };
+    parameters
+        .crashes
+        .insert(Author(3), simulator::GlobalTime(100));
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+    assert_eq!(
+        sim.fault_log(),
+        &[(simulator::GlobalTime(100), Fault::Crash(Author(3)))]
+    );
+    let html = sim.html_report("Crash of node 3").to_html();
+    for heading in &["Throughput", "Latency", "Timeouts", "Leaders", "Faults"] {
+        assert!(html.contains(&format!("<h2>{}</h2>", heading)));
+    }
+    assert!(html.contains("<td>100</td><td>crash of node 3</td>"));
+    assert!(html.contains("<td>commit latency (ms)</td>"));
+    assert!(!html.contains("<script"));
+}
+
+#[test]
+fn test_nemesis() {
+    let parameters = SimulationParameters {
+        nodes: 4,
+        max_clock: simulator::GlobalTime(1000),
+        ..base_parameters()
+    };
+    let mut sim = make_simulator(&parameters);
+    let mut injected = false;
+    sim.add_nemesis(Box::new(move |time: simulator::GlobalTime| {
+        if injected || time < simulator::GlobalTime(200) {
+            return Vec::new();
+        }
+        injected = true;
+        vec![
+            // Node 9 does not exist, so these faults are ignored.
+            Fault::Partition(vec![vec![Author(9)]]),
+            Fault::Crash(Author(9)),
+            Fault::ClockJump(Author(9), Duration::from_millis(10)),
+            Fault::Flood(Author(9), Flood::default()),
+            Fault::Partition(vec![vec![Author(0), Author(1)], vec![Author(2)]]),
+        ]
+    }));
+    sim.run(&parameters.termination(), None);
+    let fault_log = sim.fault_log();
+    assert_eq!(fault_log.len(), 1);
+    assert!(fault_log[0].0 >= simulator::GlobalTime(200));
+    assert_eq!(
+        fault_log[0].1,
+        Fault::Partition(vec![vec![Author(0), Author(1)], vec![Author(2)]])
+    );
+    // Node 3 is missing from the groups, so it forms a group of its own.
+    assert!(!sim.is_partitioned(Author(0), Author(1)));
+    assert!(sim.is_partitioned(Author(1), Author(2)));
+    assert!(sim.is_partitioned(Author(2), Author(3)));
+    assert_eq!(sim.safety_violation(), None);
+}
+
+#[test]
+fn test_liveness_bound() {
+    // All messages are lost, hence nothing is committed although all nodes are running.
+    let mut parameters = SimulationParameters {
+        loss_rate: 1.0,
+        max_time_without_commit: Some(Duration::from_millis(200)),
+        ..base_parameters()
+    };
+    let mut sim = make_simulator(&parameters);
+    match sim.run(&parameters.termination(), None) {
+        StopReason::LivenessViolation(message) => assert!(message.contains("node 2: round")),
+        reason => panic!("Unexpected stop reason {:?}", reason),
+    }
+
+    // The bound does not apply without a quorum of running nodes.
+    parameters
+        .crashes
+        .insert(Author(1), simulator::GlobalTime(0));
+    let mut sim = make_simulator(&parameters);
+    let stop_reason = sim.run(&parameters.termination(), None);
+    assert_eq!(stop_reason, StopReason::MaxClock);
+}
@@ -0,0 +1,89 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use decisions::DecisionLog;
+use scenario::Scenario;
+
+#[test]
+fn test_node_timeline() {
+    let mut log = DecisionLog::new();
+    let enter = |round| Decision::EnterRound {
+        round: Round(round),
+        leader: Some(Author(1)),
+    };
+    log.record(NodeTime(0), EpochId(0), enter(1));
+    let vote = Decision::Vote {
+        round: Round(1),
+        proposer: Author(1),
+        previous_round: Round(0),
+    };
+    log.record(NodeTime(2), EpochId(0), vote);
+    log.record(NodeTime(5), EpochId(0), enter(2));
+    let timeout = Decision::Timeout {
+        round: Round(2),
+        deadline: NodeTime(8),
+        highest_quorum_certificate_round: Round(1),
+    };
+    log.record(NodeTime(8), EpochId(0), timeout);
+    // Decisions of other rounds are ignored.
+    let propose = Decision::Propose {
+        round: Round(1),
+        previous_round: Round(0),
+    };
+    log.record(NodeTime(9), EpochId(0), propose);
+    log.record(NodeTime(10), EpochId(0), enter(3));
+
+    let intervals = node_timeline(
+        Author(0),
+        log.records(),
+        &|time| GlobalTime(time.0 + 100),
+        GlobalTime(120),
+    );
+    let spans: Vec<_> = intervals
+        .iter()
+        .map(|interval| (interval.round, interval.start, interval.end))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (Round(1), 100, 105),
+            (Round(2), 105, 110),
+            (Round(3), 110, 120)
+        ]
+    );
+    assert_eq!(intervals[0].vote, Some(102));
+    assert_eq!(intervals[0].propose, None);
+    assert_eq!(intervals[1].timeout, Some(108));
+
+    let mut output = Vec::new();
+    write_csv(&intervals[..1], &mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "author,epoch_id,round,leader,start,end,propose,vote,timeout\n0,0,1,1,100,105,,102,\n"
+    );
+}
+
+#[test]
+fn test_round_timeline() {
+    let scenario = Scenario::parse("seed = 3\nnodes = 4").unwrap();
+    let mut parameters = scenario.parameters();
+    parameters.record_decisions = true;
+    let mut sim = make_simulator(&parameters);
+    sim.run(&parameters.termination(), None);
+
+    let intervals = round_timeline(&sim);
+    for index in 0..4 {
+        let node: Vec<_> = intervals
+            .iter()
+            .filter(|interval| interval.author == Author(index))
+            .collect();
+        assert!(node.len() > 1);
+        // Rounds of a node follow each other.
+        for pair in node.windows(2) {
+            assert!(pair[0].start <= pair[0].end);
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert!(node.iter().any(|interval| interval.vote.is_some()));
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use scenario::Scenario;
+
+#[test]
+fn test_tla_trace() {
+    let parameters = Scenario::parse("seed = 4\nnodes = 3").unwrap().parameters();
+    let mut sim = make_simulator(&parameters);
+    let states = TlaTraceRecorder::attach(&mut sim);
+    sim.run(&parameters.termination(), None);
+
+    let states = states.borrow();
+    assert!(states.len() > 2);
+    assert_eq!(states[0].highest_committed_round, vec![0, 0, 0]);
+    for pair in states.windows(2) {
+        assert_ne!(pair[0], pair[1]);
+        for author in 0..3 {
+            assert!(pair[0].highest_qc_round[author] <= pair[1].highest_qc_round[author]);
+        }
+    }
+    assert!(states.last().unwrap().highest_committed_round[0] > 0);
+
+    let module = tla_module("Run", &states[..1]);
+    assert_eq!(
+        module,
+        "---- MODULE Run ----\n\\* States of a LibraBFT simulation. Node i is at index i + 1.\nTrace == <<\n    [epoch |-> <<0, 0, 0>>, round |-> <<0, 0, 0>>, highestQcRound |-> <<0, 0, 0>>, highestCommittedRound |-> <<0, 0, 0>>]\n>>\n====\n"
+    );
+    let lines = nd_json(&states).unwrap();
+    assert_eq!(lines.lines().count(), states.len());
+    assert!(lines.starts_with(r#"{"epoch":[0,0,0],"round":"#));
+}
@@ -0,0 +1,43 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+fn scenario() -> Scenario {
+    Scenario::parse("seed = 5\nnodes = 4\n[termination]\nmax_clock = 300").unwrap()
+}
+
+#[test]
+fn test_record_and_replay() {
+    let (trace, summary) = Trace::record(&scenario());
+    assert!(!trace.events.is_empty());
+    assert!(trace.events.iter().all(|event| event.time <= 300));
+    assert_eq!(trace.replay().unwrap(), summary);
+
+    // Traces survive a round trip through TOML.
+    let trace: Trace = toml::from_str(&toml::to_string(&trace).unwrap()).unwrap();
+    assert_eq!(trace.replay().unwrap(), summary);
+
+    // Scenarios without a seed get one that can be saved.
+    let scenario = Scenario {
+        seed: None,
+        ..scenario()
+    };
+    let (trace, _) = Trace::record(&scenario);
+    assert!(trace.scenario.seed.unwrap() <= scenario::MAX_SEED);
+    let trace = Trace::new(&scenario, std::u64::MAX, Vec::new());
+    assert!(trace
+        .save(std::env::temp_dir().join("trace_tests.toml"))
+        .is_err());
+}
+
+#[test]
+fn test_divergence() {
+    let (mut trace, _) = Trace::record(&scenario());
+    trace.events[3].node += 1;
+    assert!(trace.replay().is_err());
+
+    let (mut trace, _) = Trace::record(&scenario());
+    trace.events.pop();
+    assert!(trace.replay().is_err());
+}
@@ -0,0 +1,77 @@
+// Copyright (c) Calibra Research
+// SPDX-License-Identifier: Apache-2.0
+
+//! JavaScript API of the simulator, with the `wasm` feature.
+//!
+//! Built for `wasm32-unknown-unknown`, e.g. with `wasm-pack build -- --features wasm`, this lets
+//! a browser run a scenario step by step and display the state of the nodes. Values are passed
+//! to JavaScript as JSON strings.
+
+use super::*;
+use bft_simulator_runtime::termination::StopReason;
+use wasm_bindgen::prelude::*;
+
+/// A simulation run driven from JavaScript.
+#[wasm_bindgen]
+pub struct WasmSimulation {
+    parameters: SimulationParameters,
+    simulator: Simulator,
+    /// Why the run ended, if it did.
+    stop_reason: Option<StopReason>,
+}
+
+#[wasm_bindgen]
+impl WasmSimulation {
+    /// Set up the run described by a TOML scenario.
+    #[wasm_bindgen(constructor)]
+    pub fn new(scenario: &str) -> std::result::Result<WasmSimulation, JsValue> {
+        let scenario = scenario::Scenario::parse(scenario).map_err(|error| error.to_string())?;
+        let parameters = scenario.parameters();
+        let simulator = make_simulator(&parameters);
+        Ok(WasmSimulation {
+            parameters,
+            simulator,
+            stop_reason: None,
+        })
+    }
+
+    /// Process the events up to the simulated time `until`, without going past the end of the
+    /// scenario. Returns whether the run may continue.
+    pub fn step(&mut self, until: i64) -> bool {
+        if self.stop_reason.is_some() {
+            return false;
+        }
+        let mut termination = self.parameters.termination();
+        let until = std::cmp::min(simulator::GlobalTime(until), self.parameters.end_clock());
+        termination.max_clock = Some(until);
+        match self.simulator.run(&termination, None) {
+            StopReason::MaxClock if until < self.parameters.end_clock() => true,
+            stop_reason => {
+                self.stop_reason = Some(stop_reason);
+                false
+            }
+        }
+    }
+
+    /// Simulated time of the latest event.
+    pub fn time(&self) -> i64 {
+        self.simulator.clock().0
+    }
+
+    /// Why the run ended, if it did.
+    pub fn stop_reason(&self) -> Option<String> {
+        self.stop_reason
+            .as_ref()
+            .map(|stop_reason| format!("{:?}", stop_reason))
+    }
+
+    /// State of each node, as a JSON array of dashboard rows.
+    pub fn node_states(&self) -> String {
+        serde_json::to_string(&self.simulator.dashboard_rows()).expect("Rows are serializable")
+    }
+
+    /// Summary of the run so far, as a JSON object.
+    pub fn summary(&self) -> String {
+        serde_json::to_string(&self.simulator.summary()).expect("Summaries are serializable")
+    }
+}